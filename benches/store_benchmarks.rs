@@ -0,0 +1,151 @@
+//! Benchmarks for loading and saving the store, generating shell commands, and export and import
+//!
+//! Run with `cargo bench`, or a single group with e.g. `cargo bench -- load_store`.
+//! Criterion compares every run with the previous one and reports changes.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tempfile::TempDir;
+
+use envswitch::bench_support::{manager_with_store, synthetic_store, synthetic_variables};
+use envswitch::config::{ConfigManager, ExportFormat, ExportOptions, ImportFormat, ImportOptions};
+use envswitch::env::{EnvironmentManager, ShellEnvironmentManager};
+use envswitch::shell::ShellType;
+
+/// Store sizes as (configurations, variables per configuration)
+const STORE_SIZES: [(usize, usize); 4] = [(10, 5), (1_000, 5), (1_000, 20), (10_000, 5)];
+
+fn size_id(configs: usize, variables: usize) -> String {
+    format!("{}x{}", configs, variables)
+}
+
+fn export_options(format: ExportFormat) -> ExportOptions {
+    ExportOptions {
+        format,
+        include_metadata: true,
+        pretty_print: true,
+        configs: None,
+        resolve_files: false,
+        checksum: false,
+        baseline: None,
+        include_settings: false,
+        exclude_keys: Vec::new(),
+        template: false,
+    }
+}
+
+fn bench_load_store(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_store");
+    for (configs, variables) in STORE_SIZES {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_with_store(dir.path(), &synthetic_store(configs, variables));
+        group.bench_function(BenchmarkId::from_parameter(size_id(configs, variables)), |b| {
+            b.iter(|| black_box(manager.load_configs().unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_save_store(c: &mut Criterion) {
+    let mut group = c.benchmark_group("save_store");
+    group.sample_size(20);
+    for (configs, variables) in STORE_SIZES {
+        let dir = TempDir::new().unwrap();
+        let store = synthetic_store(configs, variables);
+        let manager = manager_with_store(dir.path(), &store);
+        group.bench_function(BenchmarkId::from_parameter(size_id(configs, variables)), |b| {
+            b.iter(|| manager.save_configs(black_box(&store)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_generate_shell_commands(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate_shell_commands");
+    for count in [5, 50, 500] {
+        let variables = synthetic_variables(1, count);
+        for shell in [ShellType::Bash, ShellType::Zsh, ShellType::Fish] {
+            let manager = ShellEnvironmentManager::with_shell_type(shell.clone());
+            group.bench_function(BenchmarkId::new(format!("{:?}", shell), count), |b| {
+                b.iter(|| black_box(manager.generate_shell_commands(&variables).unwrap()))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_export(c: &mut Criterion) {
+    let mut group = c.benchmark_group("export");
+    group.sample_size(20);
+    for (configs, variables) in [(10, 5), (1_000, 5)] {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_with_store(dir.path(), &synthetic_store(configs, variables));
+        for (format, name) in [(ExportFormat::Json, "json"), (ExportFormat::Env, "env"), (ExportFormat::Yaml, "yaml")] {
+            let path = dir.path().join(format!("export.{}", name));
+            let options = export_options(format);
+            group.bench_function(BenchmarkId::new(name, size_id(configs, variables)), |b| {
+                b.iter(|| manager.export_to_file_with_options(&path, &options).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Import with merge into a store that already holds half of the imported configurations
+fn bench_import_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("import_merge");
+    group.sample_size(20);
+    for (configs, variables) in [(10, 5), (1_000, 5)] {
+        let source_dir = TempDir::new().unwrap();
+        let source = manager_with_store(source_dir.path(), &synthetic_store(configs, variables));
+        let existing = synthetic_store(configs / 2, variables);
+
+        for (export_format, import_format, name) in [
+            (ExportFormat::Json, ImportFormat::Json, "json"),
+            (ExportFormat::Env, ImportFormat::Env, "env"),
+        ] {
+            let path = source_dir.path().join(format!("export.{}", name));
+            source.export_to_file_with_options(&path, &export_options(export_format)).unwrap();
+            let options = ImportOptions {
+                format: import_format,
+                force_overwrite: true,
+                merge_existing: true,
+                replace_store: false,
+                skip_validation: false,
+                dry_run: false,
+                keep_whitespace: false,
+                strict: false,
+                key_map: HashMap::new(),
+                exclude_keys: Vec::new(),
+                rewrite_home: false,
+                configs: None,
+                flatten: None,
+            };
+
+            group.bench_function(BenchmarkId::new(name, size_id(configs, variables)), |b| {
+                b.iter_batched(
+                    || {
+                        let dir = TempDir::new().unwrap();
+                        let manager = manager_with_store(dir.path(), &existing);
+                        (dir, manager)
+                    },
+                    |(_dir, manager)| black_box(manager.import_from_file_with_options(&path, &options).unwrap()),
+                    criterion::BatchSize::PerIteration,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_load_store,
+    bench_save_store,
+    bench_generate_shell_commands,
+    bench_export,
+    bench_import_merge
+);
+criterion_main!(benches);
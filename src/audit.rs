@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use crate::config::ConfigStore;
+use crate::output::OutputContext;
+use crate::env::{is_file_reference, is_lazy_reference};
+use crate::types::validation::{clean_value, host_specific_warnings, token_value_warnings};
+use crate::utils::{is_sensitive_key, mask_sensitive_value};
+
+/// Values that are obviously not real secrets, compared case-insensitively
+const PLACEHOLDER_VALUES: &[&str] = &[
+    "changeme", "change-me", "change_me", "replaceme", "replace-me", "replace_me",
+    "todo", "tbd", "placeholder", "dummy", "secret", "password", "token", "none", "null",
+];
+
+/// A variable of a configuration, identified by names only
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct VariableLocation {
+    pub config: String,
+    pub key: String,
+}
+
+/// Sensitive variables in different places holding the same value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateSecret {
+    /// The shared value, masked
+    pub value: String,
+    pub locations: Vec<VariableLocation>,
+}
+
+/// A sensitive variable whose value looks like a placeholder
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaceholderSecret {
+    pub config: String,
+    pub key: String,
+    /// The value, masked
+    pub value: String,
+}
+
+/// A credential whose value looks mis-pasted, see [`token_value_warnings`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SuspiciousSecret {
+    pub config: String,
+    pub key: String,
+    pub problems: Vec<String>,
+}
+
+/// A value that looks tied to another machine, see [`host_specific_warnings`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostSpecificValue {
+    pub config: String,
+    pub key: String,
+    pub problems: Vec<String>,
+}
+
+/// Findings of [`audit_store`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AuditReport {
+    pub duplicates: Vec<DuplicateSecret>,
+    pub placeholders: Vec<PlaceholderSecret>,
+    pub suspicious: Vec<SuspiciousSecret>,
+    /// Values of any variable with surrounding whitespace or control characters
+    pub untrimmed: Vec<VariableLocation>,
+    /// Sensitive variables without a value
+    pub empty: Vec<VariableLocation>,
+    /// Values that may only work on another machine, e.g. paths into another user's home
+    /// directory; these are hints and don't make the report unclean
+    pub host_specific: Vec<HostSpecificValue>,
+    /// The duplicate check was turned off in the settings
+    pub duplicate_check_skipped: bool,
+}
+
+impl AuditReport {
+    /// Check if nothing was found
+    pub fn is_clean(&self) -> bool {
+        self.duplicates.is_empty() && self.placeholders.is_empty() && self.suspicious.is_empty()
+            && self.untrimmed.is_empty() && self.empty.is_empty()
+    }
+}
+
+/// Check if a secret value is empty or an obvious stand-in such as "changeme" or "xxxx"
+pub fn is_placeholder_value(value: &str) -> bool {
+    let value = value.trim().to_lowercase();
+    if value.is_empty() || PLACEHOLDER_VALUES.contains(&value.as_str()) {
+        return true;
+    }
+    // "xxx", "****", "<your-api-key>", "your_token_here"
+    (value.len() >= 3 && value.chars().all(|c| c == 'x' || c == '*'))
+        || (value.starts_with('<') && value.ends_with('>'))
+        || value.starts_with("your-") || value.starts_with("your_")
+}
+
+/// Look for secrets shared between variables, empty and placeholder secrets, mis-pasted
+/// credentials and values with surrounding whitespace
+///
+/// Apart from the whitespace check, only literal values of sensitive keys are
+/// checked; `@file:`, `@op:` and `@cmd:` references are skipped. Values are compared by hash and reported masked.
+/// Every value is also checked for paths into home directories other than `local_home` and private hosts.
+pub fn audit_store(output: &OutputContext, store: &ConfigStore, check_duplicates: bool, local_home: Option<&str>) -> AuditReport {
+    let mut report = AuditReport { duplicate_check_skipped: !check_duplicates, ..Default::default() };
+    let mut by_hash: HashMap<[u8; 32], (String, Vec<VariableLocation>)> = HashMap::new();
+
+    for (name, config) in &store.configs {
+        for (key, value) in &config.variables {
+            if clean_value(value).is_some() {
+                report.untrimmed.push(VariableLocation { config: name.clone(), key: key.clone() });
+            }
+            let problems = host_specific_warnings(value, local_home);
+            if !problems.is_empty() {
+                report.host_specific.push(HostSpecificValue { config: name.clone(), key: key.clone(), problems });
+            }
+            if !is_sensitive_key(key) || is_file_reference(value) || is_lazy_reference(value) {
+                continue;
+            }
+
+            if value.is_empty() {
+                report.empty.push(VariableLocation { config: name.clone(), key: key.clone() });
+                continue;
+            }
+            if is_placeholder_value(value) {
+                report.placeholders.push(PlaceholderSecret {
+                    config: name.clone(),
+                    key: key.clone(),
+                    value: mask_sensitive_value(output, value),
+                });
+                continue;
+            }
+            
+            let problems = token_value_warnings(key, value);
+            if !problems.is_empty() {
+                report.suspicious.push(SuspiciousSecret { config: name.clone(), key: key.clone(), problems });
+            }
+            if check_duplicates {
+                let hash: [u8; 32] = Sha256::digest(value.as_bytes()).into();
+                by_hash.entry(hash)
+                    .or_insert_with(|| (mask_sensitive_value(output, value), Vec::new()))
+                    .1
+                    .push(VariableLocation { config: name.clone(), key: key.clone() });
+            }
+        }
+    }
+
+    report.duplicates = by_hash.into_values()
+        .filter(|(_, locations)| locations.len() > 1)
+        .map(|(value, mut locations)| {
+            locations.sort();
+            DuplicateSecret { value, locations }
+        })
+        .collect();
+    report.duplicates.sort_by(|a, b| a.locations.cmp(&b.locations));
+    report.placeholders.sort_by(|a, b| (&a.config, &a.key).cmp(&(&b.config, &b.key)));
+    report.suspicious.sort_by(|a, b| (&a.config, &a.key).cmp(&(&b.config, &b.key)));
+    report.host_specific.sort_by(|a, b| (&a.config, &a.key).cmp(&(&b.config, &b.key)));
+    report.untrimmed.sort();
+    report.empty.sort();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnvConfig;
+
+    fn store_with(configs: &[(&str, &[(&str, &str)])]) -> ConfigStore {
+        let mut store = ConfigStore::default();
+        for (name, variables) in configs {
+            let variables = variables.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            store.add_config(EnvConfig::new(name.to_string(), variables, None).unwrap()).unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn test_is_placeholder_value() {
+        for value in ["", "  ", "changeme", "CHANGEME", "xxx", "****", "<your-api-key>", "your_token_here", "TODO"] {
+            assert!(is_placeholder_value(value), "'{}' should be a placeholder", value);
+        }
+        for value in ["sk-1234567890abcdef", "x", "hunter2-but-longer"] {
+            assert!(!is_placeholder_value(value), "'{}' should not be a placeholder", value);
+        }
+    }
+
+    #[test]
+    fn test_audit_store_finds_shared_and_placeholder_secrets() {
+        let store = store_with(&[
+            ("prod", &[("API_KEY", "sk-prod-1234567890"), ("API_URL", "https://api.example.com")]),
+            ("staging", &[("API_KEY", "changeme"), ("API_URL", "https://api.example.com")]),
+            ("scripts", &[("DEPLOY_TOKEN", "sk-prod-1234567890"), ("OTHER_TOKEN", "@file:~/token")]),
+            ("local", &[("OTHER_TOKEN", "@file:~/token"), ("GITHUB_TOKEN", "ghp_Xy7Qp2Lm9Rt4Vw8Zb1Nc\n")]),
+            ("shared", &[("MODEL_DIR", "/Users/alice/models"), ("CACHE_DIR", "/home/me/.cache")]),
+        ]);
+
+        let report = audit_store(&OutputContext::default(), &store, true, Some("/home/me"));
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].value, "sk-p***7890");
+        assert_eq!(report.duplicates[0].locations, vec![
+            VariableLocation { config: "prod".to_string(), key: "API_KEY".to_string() },
+            VariableLocation { config: "scripts".to_string(), key: "DEPLOY_TOKEN".to_string() },
+        ]);
+        assert_eq!(report.placeholders.len(), 1);
+        assert_eq!((report.placeholders[0].config.as_str(), report.placeholders[0].key.as_str()), ("staging", "API_KEY"));
+        assert_eq!(report.suspicious.len(), 1);
+        assert_eq!(report.suspicious[0].key, "GITHUB_TOKEN");
+        assert_eq!(report.untrimmed, vec![
+            VariableLocation { config: "local".to_string(), key: "GITHUB_TOKEN".to_string() },
+        ]);
+
+        assert!(report.empty.is_empty());
+        assert_eq!(report.host_specific.len(), 1);
+        assert_eq!((report.host_specific[0].key.as_str(), report.host_specific[0].problems.len()), ("MODEL_DIR", 1));
+
+        let report = audit_store(&OutputContext::default(), &store, false, Some("/home/me"));
+        assert!(report.duplicates.is_empty());
+        assert!(report.duplicate_check_skipped);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_audit_store_counts_empty_secrets() {
+        let store = store_with(&[
+            ("work", &[("API_KEY", ""), ("API_URL", ""), ("OTHER_TOKEN", "changeme")]),
+        ]);
+
+        let report = audit_store(&OutputContext::default(), &store, true, Some("/home/me"));
+        assert_eq!(report.empty, vec![VariableLocation { config: "work".to_string(), key: "API_KEY".to_string() }]);
+        assert_eq!(report.placeholders.len(), 1);
+        assert_eq!(report.placeholders[0].key, "OTHER_TOKEN");
+        assert!(!report.is_clean());
+    }
+}
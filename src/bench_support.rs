@@ -0,0 +1,60 @@
+//! Deterministic synthetic stores for the benchmarks in benches/ and the performance tests
+//!
+//! The same arguments always produce the same store, so timings of different
+//! runs and branches compare like with like.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::config::{ConfigManager, ConfigStore, EnvConfig, FileConfigManager};
+use crate::types::ConfigPaths;
+
+/// Fixed timestamp for every generated configuration
+fn timestamp() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+}
+
+/// Variables of the configuration with the given index, a mix of Claude-style names and URLs
+pub fn synthetic_variables(index: usize, count: usize) -> HashMap<String, String> {
+    (0..count)
+        .map(|i| match i {
+            0 => ("ANTHROPIC_BASE_URL".to_string(), format!("https://api{}.example.com/v1", index)),
+            1 => ("ANTHROPIC_MODEL".to_string(), format!("model-{}", index % 7)),
+            2 => ("ANTHROPIC_AUTH_TOKEN".to_string(), format!("sk-bench-{:016x}", index * 2654435761)),
+            _ => (format!("BENCH_VAR_{}", i), format!("value {} of config {}", i, index)),
+        })
+        .collect()
+}
+
+/// A store with `configs` configurations named `config-0000`, `config-0001`, ... of `variables` variables each
+///
+/// Every tenth configuration has a description; the first one is active.
+pub fn synthetic_store(configs: usize, variables: usize) -> ConfigStore {
+    let mut store = ConfigStore::new();
+    for index in 0..configs {
+        let alias = format!("config-{:04}", index);
+        let description = (index % 10 == 0).then(|| format!("Synthetic configuration {}", index));
+        let mut config = EnvConfig::new(alias, synthetic_variables(index, variables), description)
+            .expect("synthetic configurations are valid");
+        config.created_at = timestamp();
+        config.updated_at = timestamp();
+        store.add_config(config).expect("synthetic names are unique");
+    }
+    store.active_config = (configs > 0).then(|| "config-0000".to_string());
+    store.last_modified = timestamp();
+    store
+}
+
+/// Configuration paths inside `dir`, laid out like the default directory
+pub fn config_paths(dir: &Path) -> ConfigPaths {
+    ConfigPaths::in_dir(dir)
+}
+
+/// A manager for `dir` with `store` already saved in it
+pub fn manager_with_store(dir: &Path, store: &ConfigStore) -> FileConfigManager {
+    let manager = FileConfigManager::with_paths(config_paths(dir));
+    manager.save_configs(store).expect("Failed to save the synthetic store");
+    manager
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "envswitch")]
@@ -6,49 +6,263 @@ use clap::{Parser, Subcommand};
 #[command(long_about = "EnvSwitch helps you manage different sets of environment variables and quickly switch between them. Perfect for switching between different AI model configurations, development environments, or any other environment-specific settings.")]
 #[command(version = "0.1.0")]
 #[command(author = "EnvSwitch Team")]
+// Unambiguous prefixes work too, e.g. `envswitch stat` for status
+#[command(infer_subcommands = true)]
 pub struct Cli {
     /// Enable verbose output
     #[arg(short, long, global = true)]
     pub verbose: bool,
     
+    /// Restore the newest valid backup without asking if config.json is corrupt
+    #[arg(long, global = true)]
+    pub auto_recover: bool,
+    
+    /// Only print results, warnings and errors; leave out hints and progress messages
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    
+    /// Print ASCII tags such as [OK] and [WARN] instead of emoji
+    #[arg(long, global = true)]
+    pub no_emoji: bool,
+    
+    /// Print only ASCII, e.g. * and x instead of ✓ and ✗ in tables (automatic for TERM=dumb and non-UTF-8 locales)
+    #[arg(long, global = true)]
+    pub ascii: bool,
+    
+    /// Show timestamps in UTC instead of the local time zone (machine-readable output is always UTC)
+    #[arg(long, global = true)]
+    pub utc: bool,
+    
+    /// Print how long loading, saving, validation, backups and shell detection took when the command finishes
+    #[arg(long, global = true)]
+    pub timings: bool,
+    
+    /// The output is eval'd: print only shell code on stdout and fail instead of prompting (also ENVSWITCH_EVAL=1)
+    #[arg(long, global = true)]
+    pub eval: bool,
+    
+    /// Print the summary of set, delete, import, export, backup create and prune as JSON on stdout; other messages go to stderr
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text", value_parser = ["text", "json"])]
+    pub report: String,
+    
+    /// Treat warnings of set, import, export, convert and audit as errors (exit status 5; also the
+    /// strict setting). Goes before the command: `use --strict` and `import --strict` are their own flags
+    #[arg(long)]
+    pub strict: bool,
+    
+    /// Use DIR as the configuration directory, like ENVSWITCH_CONFIG_DIR
+    #[arg(long, global = true, value_name = "DIR")]
+    pub config_dir: Option<std::path::PathBuf>,
+    
     #[command(subcommand)]
     pub command: Commands,
 }
 
+impl Cli {
+    /// Parse the command line like [`Parser::parse`], then run the checks clap can't express,
+    /// exiting with a usage error when one fails
+    pub fn parse_checked() -> Self {
+        Self::try_parse_checked_from(std::env::args_os()).unwrap_or_else(|e| e.exit())
+    }
+    
+    /// [`Cli::parse_checked`] for the given arguments, returning the error instead of exiting
+    pub fn try_parse_checked_from<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let matches = Self::command().try_get_matches_from(args)?;
+        if let Some(("set", set)) = matches.subcommand() {
+            check_set_arguments(set)?;
+        }
+        Self::from_arg_matches(&matches)
+    }
+}
+
+/// Reject variables of the set command that are almost certainly typos, naming the
+/// offending argument and its position on the command line
+///
+/// Catches empty names (`-e =value`) and, unless `--allow-empty-value` is given, empty
+/// values (`KEY= value` with a stray space). Positions count from the `set` subcommand.
+fn check_set_arguments(matches: &ArgMatches) -> Result<(), clap::Error> {
+    let allow_empty_value = matches.get_flag("allow_empty_value");
+    let mut arguments: Vec<(usize, String, &str)> = Vec::new();
+    for (id, name) in [("env", "-e"), ("pairs", "KEY=VALUE")] {
+        if let (Some(raw), Some(indices)) = (matches.get_raw(id), matches.indices_of(id)) {
+            arguments.extend(indices.zip(raw).map(|(index, token)| (index, token.to_string_lossy().into_owned(), name)));
+        }
+    }
+    arguments.sort();
+    
+    for (index, token, name) in &arguments {
+        let (key, value) = match token.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (token.as_str(), None),
+        };
+        let problem = if key.trim().is_empty() {
+            "the variable name is empty".to_string()
+        } else if value.is_some_and(|value| value.trim().is_empty()) && !allow_empty_value {
+            format!(
+                "{} has an empty value; quote values with spaces ('{}=a value') or pass --allow-empty-value",
+                key.trim(), key.trim()
+            )
+        } else {
+            continue;
+        };
+        return Err(Cli::command().error(
+            clap::error::ErrorKind::ValueValidation,
+            format!("invalid {} argument '{}' at position {} after 'set': {}", name, token, index, problem),
+        ));
+    }
+    Ok(())
+}
+
+// Parsed once per run, so the size of the Set variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Create or update a configuration
-    #[command(alias = "add")]
+    #[command(visible_aliases = ["add", "s"], after_help = crate::examples::after_help("set"))]
     Set {
         /// Configuration alias name
         alias: String,
-        /// Environment variables in KEY=VALUE format
-        #[arg(short, long, value_parser = parse_env_var)]
-        env: Vec<(String, String)>,
+        /// Environment variables as trailing KEY=VALUE arguments (same as -e)
+        #[arg(value_name = "KEY=VALUE", value_parser = parse_positional_env_var)]
+        pairs: Vec<(String, String)>,
+        /// Environment variables in KEY=VALUE format; a bare KEY captures its current value
+        // Hyphen values reach parse_env_var_or_key, which explains `-e -e KEY=value`
+        #[arg(short, long, value_parser = parse_env_var_or_key, allow_hyphen_values = true)]
+        env: Vec<(String, Option<String>)>,
         /// Description for the configuration
         #[arg(short, long)]
         description: Option<String>,
         /// Read environment variables from a file
-        #[arg(short, long, conflicts_with = "env")]
+        #[arg(short, long, conflicts_with_all = ["env", "pairs"])]
         file: Option<String>,
-        /// Replace all variables instead of merging (only for updates)
+        /// Flatten nested objects of a JSON --file, e.g. {"anthropic": {"model": ...}} becomes ANTHROPIC_MODEL
+        #[arg(long, requires = "file")]
+        flatten: bool,
+        /// Separator between the keys of nested objects when flattening
+        #[arg(long, value_name = "SEP", default_value = "_", requires = "flatten")]
+        flatten_separator: String,
+        /// Arrays when flattening: rejected, or flattened with index suffixes (KEY_0, KEY_1, ...)
+        #[arg(long, default_value = "reject", value_parser = ["reject", "index"], requires = "flatten")]
+        flatten_arrays: String,
+        /// Replace all variables instead of merging (only for updates); asks before removing
+        /// many of them, see the replace-guard setting
         #[arg(short, long)]
         replace: bool,
-        /// Interactive mode to add variables one by one
-        #[arg(short, long, conflicts_with_all = ["env", "file"])]
+        /// Remove the variables --replace drops without asking; without it, declining exits with status 8
+        #[arg(short, long, requires = "replace")]
+        yes: bool,
+        /// Add variables and a description in the same editor as `edit`
+        #[arg(short, long, conflicts_with_all = ["env", "pairs", "file"])]
         interactive: bool,
+        /// Read KEY=VALUE lines from stdin until EOF, e.g. `pbpaste | envswitch set work --stdin`; -e values win on conflicts
+        #[arg(long, conflicts_with = "interactive")]
+        stdin: bool,
+        /// Prepend an entry to a PATH-style variable, e.g. PATH=/opt/tool/bin (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+        prepend: Vec<(String, String)>,
+        /// Append an entry to a PATH-style variable (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+        append: Vec<(String, String)>,
+        /// Why a variable is set, e.g. --comment API_URL='pinned until v2' (repeatable; empty removes it)
+        #[arg(long = "comment", value_name = "KEY=REASON", value_parser = parse_env_var)]
+        comments: Vec<(String, String)>,
+        /// Skip bare -e KEY arguments that are not set in the current environment
+        #[arg(long)]
+        allow_missing: bool,
+        /// Free-form notes for the configuration (replaces existing notes)
+        #[arg(long)]
+        note: Option<String>,
+        /// Related URL, e.g. a provider console (repeatable)
+        #[arg(long = "link")]
+        links: Vec<String>,
+        /// Expiration date for temporary credentials (YYYY-MM-DD or RFC 3339)
+        #[arg(long, conflicts_with = "expires_in")]
+        expires: Option<String>,
+        /// Expire after a duration from now, e.g. 12h, 7d, 2w
+        #[arg(long)]
+        expires_in: Option<String>,
+        /// Read a variable from 1Password with `op read`, e.g. 'op://vault/item/field=API_KEY' (repeatable)
+        #[arg(long, value_name = "REFERENCE=KEY", value_parser = parse_op_reference)]
+        from_op: Vec<(String, String)>,
+        /// Read a variable from the output of a shell command, e.g. 'API_KEY=pass show api' (repeatable)
+        #[arg(long, value_name = "KEY=COMMAND", value_parser = parse_env_var)]
+        from_cmd: Vec<(String, String)>,
+        /// Store --from-op/--from-cmd as references resolved on every 'use' instead of their values
+        #[arg(long)]
+        lazy: bool,
+        /// Show what would change without saving anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Output format: human-readable text or a JSON summary of the changes
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        output: String,
+        /// Exit with status 6 when nothing changed
+        #[arg(long)]
+        fail_on_noop: bool,
+        /// Create the configuration even if its name differs only by case from an existing one
+        #[arg(long)]
+        allow_case_collision: bool,
+        /// Store values exactly as given instead of trimming surrounding whitespace and control characters
+        #[arg(long)]
+        keep_whitespace: bool,
+        /// Accept variables without a value, e.g. -e KEY=, which are rejected as likely typos
+        #[arg(long)]
+        allow_empty_value: bool,
     },
     /// Switch to a configuration
-    #[command(alias = "switch")]
+    #[command(visible_aliases = ["switch", "sw", "u"], after_help = crate::examples::after_help("use"))]
     Use {
         /// Configuration alias to activate
         alias: String,
         /// Show commands without executing (dry run)
         #[arg(short, long)]
         dry_run: bool,
+        /// Also copy the generated commands to the system clipboard
+        #[arg(long)]
+        copy: bool,
+        /// Refuse to activate an expired configuration
+        #[arg(long)]
+        strict: bool,
+        /// Output format: shell commands or a JSON description of the switch
+        #[arg(long, default_value = "shell", value_parser = ["shell", "json"])]
+        format: String,
+        /// Activate an archived configuration, or let --format json print secrets to a terminal
+        #[arg(long)]
+        force: bool,
+        /// Check tokens and BASE_URL reachability and report on stderr; "strict" aborts on failure
+        #[arg(long, num_args = 0..=1, default_missing_value = "report", value_parser = ["report", "strict"])]
+        verify: Option<String>,
+        /// Ask before overwriting each variable that was set outside envswitch
+        #[arg(long)]
+        interactive_conflicts: bool,
+        /// Variable scope for fish: session (set -gx) or universal (set -Ux, persists across sessions)
+        #[arg(long, default_value = "session", value_parser = ["session", "universal"])]
+        scope: String,
+        /// Only print the commands; don't record the configuration as active (nothing is written)
+        #[arg(long, conflicts_with = "dry_run")]
+        no_persist: bool,
+    },
+    /// Unset the variables of the active configuration in this shell and forget it
+    ///
+    /// Prints shell commands like `use`; explanations go to stderr.
+    #[command(after_help = crate::examples::after_help("clear"))]
+    Clear {
+        /// Unset every variable that appears in any configuration, not only the active one
+        #[arg(long)]
+        all_known: bool,
+        /// Show the commands without forgetting the active configuration
+        #[arg(short, long)]
+        dry_run: bool,
+        /// Variable scope for fish: session (set -gx) or universal (set -Ux), as used with `use`
+        #[arg(long, default_value = "session", value_parser = ["session", "universal"])]
+        scope: String,
     },
     /// List all configurations
-    #[command(alias = "ls")]
+    #[command(visible_alias = "ls", after_help = crate::examples::after_help("list"))]
     List {
         /// Show detailed information
         #[arg(short, long)]
@@ -59,9 +273,42 @@ pub enum Commands {
         /// Show only active configuration
         #[arg(short, long)]
         active: bool,
+        /// Sort order within the pinned and unpinned groups
+        #[arg(long, default_value = "name", value_parser = ["name", "updated", "created", "variables"])]
+        sort: String,
+        /// Columns of the table, e.g. name,vars,active,updated (implies --table)
+        ///
+        /// Available: name, description, vars, active, updated, created, expires, source.
+        /// The list-columns setting changes the default.
+        #[arg(long, value_name = "COLUMNS", conflicts_with = "active")]
+        columns: Option<String>,
+        /// Print only the configuration names, one per line and sorted, for completion scripts
+        #[arg(long, conflicts_with_all = ["verbose", "table", "active", "sort", "columns"])]
+        names: bool,
+        /// With --names, print `name<TAB>description` lines, e.g. for an fzf preview
+        #[arg(long, requires = "names")]
+        with_description: bool,
+        /// Show only archived configurations
+        #[arg(long, conflicts_with = "active")]
+        archived: bool,
+        /// Show archived configurations along with the others
+        #[arg(long, conflicts_with_all = ["active", "archived"])]
+        all: bool,
+        /// With --verbose, list variables alphabetically instead of grouped by prefix (ANTHROPIC_*, AWS_*)
+        #[arg(long)]
+        flat: bool,
+        /// Print the configurations as JSON, without variable values
+        #[arg(long, conflicts_with_all = ["verbose", "table", "active", "columns", "names"])]
+        json: bool,
+        /// With --json, only these fields of each configuration, e.g. name,active
+        ///
+        /// Available: name, description, variables, active, pinned, archived, created_at,
+        /// updated_at, expires_at, source.
+        #[arg(long, value_name = "FIELDS", requires = "json")]
+        fields: Option<String>,
     },
     /// Show current active configuration and environment status
-    #[command(alias = "info")]
+    #[command(visible_aliases = ["info", "st"], after_help = crate::examples::after_help("status"))]
     Status {
         /// Show only Claude-specific variables
         #[arg(short, long)]
@@ -72,28 +319,47 @@ pub enum Commands {
         /// Show only mismatched variables
         #[arg(short, long)]
         mismatched: bool,
+        /// Print the status as JSON, including whether the active configuration is stale
+        #[arg(long, conflicts_with_all = ["claude", "table"])]
+        json: bool,
+        /// With --json, only these fields of each variable, e.g. key,matches
+        ///
+        /// Available: key, current, matches, source, current_matches.
+        #[arg(long, value_name = "FIELDS", requires = "json")]
+        fields: Option<String>,
+        /// List variables alphabetically instead of grouped by prefix (ANTHROPIC_*, AWS_*)
+        #[arg(long)]
+        flat: bool,
+    },
+    /// Show variables that changed in this shell since the active configuration was activated
+    ///
+    /// Compares the live environment with what `use` left in it, plus the variables
+    /// of the drift-watch setting. Unlike `status`, editing the configuration after
+    /// activating it is not reported as drift.
+    #[command(after_help = crate::examples::after_help("drift"))]
+    Drift {
+        /// Print commands restoring the drifted variables; the report goes to stderr
+        #[arg(long)]
+        fix: bool,
     },
     /// Edit a configuration interactively
     /// 
     /// Opens an interactive editor to modify environment variables.
     /// Allows adding, editing, and removing variables with real-time validation.
-    /// 
-    /// Example:
-    ///   envswitch edit my-config
+    #[command(after_help = crate::examples::after_help("edit"))]
     Edit {
         /// Configuration alias to edit
         /// Creates a new configuration if it doesn't exist
         alias: String,
+        /// Store values exactly as entered instead of trimming surrounding whitespace and control characters
+        #[arg(long)]
+        keep_whitespace: bool,
     },
     /// Delete a configuration
     /// 
     /// Removes a configuration permanently. Shows interactive confirmation
     /// unless --force is used. Cannot delete the currently active configuration.
-    /// 
-    /// Examples:
-    ///   envswitch delete old-config
-    ///   envswitch delete temp-config --force
-    #[command(alias = "rm")]
+    #[command(visible_alias = "rm", after_help = crate::examples::after_help("delete"))]
     Delete {
         /// Configuration alias to delete
         alias: String,
@@ -101,16 +367,15 @@ pub enum Commands {
         /// Use with caution as this action cannot be undone
         #[arg(short, long)]
         force: bool,
+        /// Show what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
         /// Show verbose output during deletion
         #[arg(short, long)]
         verbose: bool,
     },
     /// Export configurations to a file
-    /// 
-    /// Examples:
-    ///   envswitch export --output my-configs.json
-    ///   envswitch export --configs dev,prod --format env --output configs.env
-    ///   envswitch export --metadata --pretty --output detailed-configs.json
+    #[command(visible_alias = "ex", after_help = crate::examples::after_help("export"))]
     Export {
         /// Output file path (default: envswitch_export.json)
         /// Supports .json, .env, and .yaml extensions for format detection
@@ -120,8 +385,8 @@ pub enum Commands {
         /// Example: --configs dev,staging,prod
         #[arg(short, long, value_delimiter = ',')]
         configs: Vec<String>,
-        /// Export format: json (default), env, or yaml
-        /// Format is auto-detected from file extension if not specified
+        /// Export format: json (default), env, yaml, or shell (a script of `envswitch set`
+        /// commands that recreates the configurations where envswitch is installed)
         #[arg(short, long, default_value = "json")]
         format: String,
         /// Include metadata such as creation timestamps and descriptions
@@ -130,28 +395,69 @@ pub enum Commands {
         /// Pretty print JSON output for better readability
         #[arg(short, long)]
         pretty: bool,
+        /// Write the contents of @file: references instead of the references
+        #[arg(long)]
+        resolve_files: bool,
+        /// Embed a SHA-256 checksum to detect corruption on import (default for JSON)
+        #[arg(long)]
+        checksum: bool,
+        /// Don't embed a checksum in JSON exports
+        #[arg(long, conflicts_with = "checksum")]
+        no_checksum: bool,
+        /// Export only configurations updated after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+        /// Export only configurations added or changed since this backup (file name, path or note)
+        #[arg(long, value_name = "BACKUP", conflicts_with = "since")]
+        since_backup: Option<String>,
+        /// Also export the settings (default configuration, git-track, language, ...)
+        #[arg(long)]
+        include_settings: bool,
+        /// Leave out variables matching a glob pattern, e.g. --exclude-keys 'AWS_*' (repeatable)
+        /// Added to the patterns of the exclude-keys setting
+        #[arg(long = "exclude-keys", value_name = "PATTERN")]
+        exclude_keys: Vec<String>,
+        /// Share configurations without secrets: sensitive values become {{KEY}} placeholders
+        /// that `use` asks for after importing
+        #[arg(long)]
+        template: bool,
+        /// With --format shell, leave secrets out of the script; it reads them from
+        /// same-named environment variables when it runs
+        #[arg(long, conflicts_with = "template")]
+        redact_secrets: bool,
+        /// Leave archived configurations out of the export
+        #[arg(long)]
+        exclude_archived: bool,
+        /// Bundle the whole configuration directory (configs, settings, backups) into a .tar.gz
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["output", "configs", "format", "metadata", "pretty", "resolve_files", "checksum", "no_checksum", "since", "since_backup", "include_settings", "exclude_keys", "template", "redact_secrets", "exclude_archived"])]
+        archive: Option<String>,
     },
     /// Import configurations from a file
     /// 
     /// Supports JSON, ENV, and YAML formats with automatic format detection.
+    /// Existing configurations are kept; new ones are added and configurations that
+    /// already exist are skipped unless --force or --merge is given.
     /// Creates automatic backups when --backup is used.
-    /// 
-    /// Examples:
-    ///   envswitch import configs.json
-    ///   envswitch import --backup --merge team-configs.json
-    ///   envswitch import --dry-run --verbose new-configs.yaml
+    #[command(visible_alias = "im", after_help = crate::examples::after_help("import"))]
     Import {
         /// Input file path (supports .json, .env, .yaml formats)
         /// Format is automatically detected from file content and extension
         file: String,
         /// Overwrite existing configurations without confirmation
         /// Use with caution as this will replace existing configs
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "archive")]
         force: bool,
-        /// Merge with existing configurations instead of replacing
+        /// Merge configurations that already exist instead of skipping them
         /// Combines variables from imported and existing configs
         #[arg(short, long)]
         merge: bool,
+        /// Replace the whole store with the file (or archive), deleting configurations that are not in it
+        /// Asks to type 'replace' first unless --yes is given
+        #[arg(long)]
+        replace_store: bool,
+        /// Do not ask for confirmation before replacing the store
+        #[arg(short, long, requires = "replace_store")]
+        yes: bool,
         /// Preview import changes without actually importing (dry run)
         /// Shows what configurations would be created or modified
         #[arg(short, long)]
@@ -164,6 +470,343 @@ pub enum Commands {
         /// Backup is saved to ~/.config/envswitch/backups/
         #[arg(short, long)]
         backup: bool,
+        /// Treat FILE as an archive created by 'export --archive'; existing configurations are kept
+        /// unless --replace-store is given
+        #[arg(long, conflicts_with_all = ["dry_run", "skip_validation"])]
+        archive: bool,
+        /// Store values exactly as in the file instead of trimming surrounding whitespace and control characters
+        #[arg(long)]
+        keep_whitespace: bool,
+        /// Also apply the settings of an export made with --include-settings, after confirmation
+        #[arg(long, conflicts_with = "archive")]
+        include_settings: bool,
+        /// Import nothing if any configuration is invalid, instead of skipping the invalid ones
+        #[arg(long, conflicts_with = "archive")]
+        strict: bool,
+        /// Rename a variable while importing, e.g. --map DEEPSEEK_API_KEY=ANTHROPIC_AUTH_TOKEN (repeatable)
+        #[arg(long = "map", value_name = "OLD=NEW", value_parser = parse_env_var, conflicts_with = "archive")]
+        map: Vec<(String, String)>,
+        /// JSON file of renames like {"DEEPSEEK_API_KEY": "ANTHROPIC_AUTH_TOKEN"}; --map takes precedence
+        #[arg(long, value_name = "PATH", conflicts_with = "archive")]
+        map_file: Option<String>,
+        /// Leave out variables matching a glob pattern, e.g. --exclude-keys '*_TOKEN' (repeatable)
+        /// Added to the patterns of the exclude-keys setting; matched after --map renames
+        #[arg(long = "exclude-keys", value_name = "PATTERN", conflicts_with = "archive")]
+        exclude_keys: Vec<String>,
+        /// Replace paths into another user's home directory, e.g. /Users/alice/..., with your home directory
+        #[arg(long, conflicts_with = "archive")]
+        rewrite_home: bool,
+        /// Read a JSON file of nested variables instead of an export, flattening it into one
+        /// configuration named 'imported', e.g. {"anthropic": {"model": ...}} becomes ANTHROPIC_MODEL
+        #[arg(long, conflicts_with_all = ["archive", "replace_store", "include_settings"])]
+        flatten: bool,
+        /// Separator between the keys of nested objects when flattening
+        #[arg(long, value_name = "SEP", default_value = "_", requires = "flatten")]
+        flatten_separator: String,
+        /// Arrays when flattening: rejected, or flattened with index suffixes (KEY_0, KEY_1, ...)
+        #[arg(long, default_value = "reject", value_parser = ["reject", "index"], requires = "flatten")]
+        flatten_arrays: String,
+    },
+    /// Compare your configurations with a shared export and apply selected changes
+    ///
+    /// Lists configurations only in the file, only in your store, and the ones whose
+    /// variables differ, then asks which to import or update. Updates merge the file's
+    /// variables into yours. Nothing is written without confirmation or --apply-all-missing.
+    #[command(after_help = crate::examples::after_help("sync"))]
+    Sync {
+        /// Shared export to compare with (.json or .env)
+        file: String,
+        /// Import every configuration you don't have without asking, e.g. to bootstrap CI;
+        /// differing configurations are only listed
+        #[arg(long)]
+        apply_all_missing: bool,
+    },
+    /// Watch config.json and re-emit the active configuration when it changes
+    ///
+    /// Useful when the configuration directory is synced between machines.
+    #[command(after_help = crate::examples::after_help("watch"))]
+    Watch {
+        /// Rewrite this dotenv file instead of printing export commands
+        #[arg(long, value_name = "PATH")]
+        dotenv: Option<String>,
+        /// Milliseconds to wait for further writes before refreshing
+        #[arg(long, default_value_t = 500)]
+        debounce: u64,
+        /// Exit after the first refresh
+        #[arg(long)]
+        once: bool,
+    },
+    /// Convert a variables file between env, json, yaml and toml
+    ///
+    /// Formats are detected from the file extensions unless given explicitly.
+    #[command(after_help = crate::examples::after_help("convert"))]
+    Convert {
+        /// Input file with a flat map of variables
+        input: String,
+        /// Output file to write
+        output: String,
+        /// Format of the input file
+        #[arg(long, value_parser = ["env", "json", "yaml", "toml"])]
+        from: Option<String>,
+        /// Format of the output file
+        #[arg(long, value_parser = ["env", "json", "yaml", "toml"])]
+        to: Option<String>,
+    },
+    /// Change one variable of a configuration
+    ///
+    /// Without VALUE, shows the current value and prompts for a new one,
+    /// hiding the input for sensitive keys.
+    #[command(after_help = crate::examples::after_help("set-var"))]
+    SetVar {
+        /// Configuration name or alias
+        alias: String,
+        /// Variable name
+        key: String,
+        /// New value (prompted for if omitted)
+        value: Option<String>,
+        /// Change a protected configuration
+        #[arg(short, long)]
+        force: bool,
+        /// Store the value exactly as given instead of trimming surrounding whitespace and control characters
+        #[arg(long)]
+        keep_whitespace: bool,
+    },
+    /// Remove one variable from a configuration
+    #[command(after_help = crate::examples::after_help("unset-var"))]
+    UnsetVar {
+        /// Configuration name or alias
+        alias: String,
+        /// Variable name
+        key: String,
+        /// Change a protected configuration
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Protect a configuration from set-var, unset-var and delete without --force
+    #[command(after_help = crate::examples::after_help("protect"))]
+    Protect {
+        /// Configuration to protect
+        alias: String,
+    },
+    /// Remove the protection of a configuration
+    #[command(after_help = crate::examples::after_help("unprotect"))]
+    Unprotect {
+        /// Configuration to unprotect
+        alias: String,
+    },
+    /// Hide a retired configuration from listings without deleting it
+    ///
+    /// Archived configurations stay in the store and in exports; `list --archived`
+    /// shows them and `use` needs --force to activate one.
+    #[command(after_help = crate::examples::after_help("archive"))]
+    Archive {
+        /// Configuration to archive
+        alias: String,
+    },
+    /// Bring an archived configuration back into listings
+    #[command(after_help = crate::examples::after_help("unarchive"))]
+    Unarchive {
+        /// Configuration to unarchive
+        alias: String,
+    },
+    /// Pin a configuration to the top of list output
+    #[command(after_help = crate::examples::after_help("pin"))]
+    Pin {
+        /// Configuration to pin
+        alias: String,
+    },
+    /// Unpin a configuration
+    #[command(after_help = crate::examples::after_help("unpin"))]
+    Unpin {
+        /// Configuration to unpin
+        alias: String,
+    },
+    /// Remove stale configurations
+    #[command(after_help = crate::examples::after_help("prune"))]
+    Prune {
+        /// Delete configurations whose expiration date has passed
+        #[arg(long)]
+        expired: bool,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Manage alternative names for configurations
+    #[command(after_help = crate::examples::after_help("alias"))]
+    Alias {
+        #[command(subcommand)]
+        action: AliasAction,
+    },
+    /// Show the history of configuration changes (requires the git-track setting)
+    #[command(after_help = crate::examples::after_help("log"))]
+    Log {
+        /// Maximum number of entries to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show when a variable changed across the configuration history (requires the git-track setting)
+    #[command(after_help = crate::examples::after_help("history-var"))]
+    HistoryVar {
+        /// Configuration alias name
+        alias: String,
+        /// Variable name
+        key: String,
+        /// Show secret values in plain text, after confirmation
+        #[arg(long)]
+        reveal: bool,
+        /// Print the changes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Restore all configurations as they were at a commit shown by 'envswitch log'
+    #[command(after_help = crate::examples::after_help("revert"))]
+    Revert {
+        /// Commit hash from 'envswitch log'
+        commit: String,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// View and change envswitch settings
+    #[command(after_help = crate::examples::after_help("settings"))]
+    Settings {
+        #[command(subcommand)]
+        action: SettingsAction,
+    },
+    /// Print a configuration's variables as sorted KEY=VALUE lines for scripts
+    ///
+    /// Nothing else is ever written to stdout. Exits with status 3 when the
+    /// configuration doesn't exist.
+    #[command(after_help = crate::examples::after_help("env"))]
+    Env {
+        /// Configuration name or alias
+        #[arg(required_unless_present = "active", conflicts_with = "active")]
+        alias: Option<String>,
+        /// Use the active configuration
+        #[arg(long)]
+        active: bool,
+        /// Separate entries with NUL bytes and print values unquoted
+        #[arg(short = '0', long, conflicts_with = "json")]
+        null: bool,
+        /// Print a flat JSON object
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a configuration's export commands to a script file
+    ///
+    /// The script has a shebang and a generated-by header and is made executable by
+    /// its owner only, for containers, systemd units and other places without envswitch.
+    /// Files that weren't written by render are only overwritten with --force.
+    #[command(after_help = crate::examples::after_help("render"))]
+    Render {
+        /// Script file to write
+        #[arg(short, long)]
+        output: String,
+        /// Target shell (auto-detected if not specified)
+        #[arg(long, value_parser = ["bash", "zsh", "fish"])]
+        shell: Option<String>,
+        /// Configuration name or alias (defaults to the active configuration)
+        #[arg(long)]
+        alias: Option<String>,
+        /// Also write a script that unsets the variables again
+        #[arg(long)]
+        unset_script: Option<String>,
+        /// Overwrite files that weren't generated by render
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Show where configurations, state, backups and history are stored
+    ///
+    /// Set ENVSWITCH_CONFIG_DIR to use another configuration directory.
+    #[command(after_help = crate::examples::after_help("paths"))]
+    Paths {
+        /// Print the paths as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the name of the active configuration for scripts
+    ///
+    /// Prints nothing and exits with status 7 when no configuration is active (status 4
+    /// already means permission denied). With --verbose, the activation time and whether
+    /// the configuration changed since go to stderr.
+    #[command(after_help = crate::examples::after_help("current"))]
+    Current {
+        /// Print the name, activation time, staleness and detected shell as JSON
+        #[arg(long, conflicts_with = "clear")]
+        json: bool,
+        /// Forget the active configuration without printing unset commands; its variables stay set in this shell
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Print the active configuration name for a shell prompt
+    ///
+    /// Prints nothing when no configuration is active, and appends '*' when the
+    /// configuration was modified after activation.
+    #[command(after_help = crate::examples::after_help("prompt"))]
+    Prompt,
+    /// Look for secrets shared between configurations and placeholder secrets
+    ///
+    /// Only masked values and configuration/variable names are shown. Exits with
+    /// status 1 when something is found.
+    #[command(after_help = crate::examples::after_help("audit"))]
+    Audit {
+        /// Print the findings as JSON
+        #[arg(long)]
+        json: bool,
+        /// Trim surrounding whitespace and control characters from stored values
+        #[arg(long, conflicts_with = "json")]
+        repair: bool,
+    },
+    /// Check the configuration store for problems
+    ///
+    /// Reports names that differ only by case, with a suggested rename, and
+    /// configurations stored under another name than their own (after hand edits).
+    #[command(after_help = crate::examples::after_help("doctor"))]
+    Doctor {
+        /// Repair configurations stored under another name, asking which name to keep
+        #[arg(long)]
+        repair: bool,
+        /// Name to keep without asking: the key the configuration is stored under, or its alias field
+        #[arg(long, value_parser = ["key", "alias"], requires = "repair")]
+        keep: Option<String>,
+    },
+    /// Move the configuration store to another storage layout
+    ///
+    /// The split layout keeps each configuration in its own file under configs/
+    /// next to a small index, so commands that read one configuration stay fast
+    /// with very large stores. A backup is made before migrating.
+    #[command(after_help = crate::examples::after_help("migrate-storage"))]
+    MigrateStorage {
+        /// Layout to move to
+        #[arg(value_parser = ["single", "split"])]
+        layout: String,
+    },
+    /// Choose the configuration applied to every new shell
+    ///
+    /// The default is applied by 'envswitch shellenv --activate' in your shell rc file.
+    #[command(after_help = crate::examples::after_help("default"))]
+    Default {
+        #[command(subcommand)]
+        action: DefaultAction,
+    },
+    /// Print shell code for an rc file
+    ///
+    /// With --activate, also exports the default configuration unless the shell
+    /// already has it.
+    #[command(after_help = crate::examples::after_help("shellenv"))]
+    Shellenv {
+        /// Target shell (auto-detected if not specified)
+        #[arg(short, long)]
+        shell: Option<String>,
+        /// Apply the default configuration
+        #[arg(long)]
+        activate: bool,
+    },
+    /// Create, list and restore backups of the configuration store
+    #[command(after_help = crate::examples::after_help("backup"))]
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
     },
     /// Show shell integration instructions and generate setup scripts
     Setup {
@@ -183,17 +826,46 @@ pub enum Commands {
         #[arg(short, long)]
         wrapper: bool,
     },
-    /// Generate shell initialization code for eval
+    /// Set up envswitch step by step: shell integration, a first configuration and the default
+    ///
+    /// Every step can be skipped and steps that are already done are left alone, so
+    /// running it again is safe. With --non-interactive nothing is asked and only the
+    /// steps requested by flags run, e.g. from a dotfiles install script.
+    #[command(after_help = crate::examples::after_help("init"))]
     Init {
         /// Target shell (auto-detected if not specified)
         #[arg(short, long)]
         shell: Option<String>,
-        /// Generate completion scripts
-        #[arg(short, long)]
-        completions: bool,
+        /// Don't ask anything; only run the steps given by the other flags
+        #[arg(long)]
+        non_interactive: bool,
+        /// Add the shell integration to the startup file of the shell
+        #[arg(long)]
+        install: bool,
+        /// Create the first configuration under this name (kept as it is if it exists)
+        #[arg(long, value_name = "NAME")]
+        create: Option<String>,
+        /// Fill the first configuration from a preset, e.g. claude; values come from the environment or the preset defaults
+        #[arg(long, requires = "create", conflicts_with = "from_env")]
+        preset: Option<String>,
+        /// Fill the first configuration from the variables in the environment starting with PREFIX
+        #[arg(long, value_name = "PREFIX", requires = "create")]
+        from_env: Option<String>,
+        /// Make the first configuration the default for new shells
+        #[arg(long, requires = "create")]
+        set_default: bool,
+    },
+    /// Print task-oriented recipes with ready-to-run commands
+    ///
+    /// Topics: switching, sharing, ci, direnv. The eval snippets are written for the
+    /// detected shell.
+    #[command(after_help = crate::examples::after_help("examples"))]
+    Examples {
+        /// Recipe to show, or a prefix of its name; lists the topics when omitted
+        topic: Option<String>,
     },
     /// Show getting started guide and examples
-    #[command(alias = "guide")]
+    #[command(visible_alias = "guide")]
     Tutorial {
         /// Show advanced usage examples
         #[arg(short, long)]
@@ -204,6 +876,149 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum AliasAction {
+    /// Add an alias for a configuration
+    Add {
+        /// Configuration name the alias points to
+        config: String,
+        /// Alias to add
+        alias: String,
+    },
+    /// Remove an alias
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Alias to remove
+        alias: String,
+    },
+    /// List all aliases
+    #[command(visible_alias = "ls")]
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupAction {
+    /// Back up the current configuration store
+    Create {
+        /// Note describing why the backup was made
+        #[arg(short, long)]
+        note: Option<String>,
+    },
+    /// List backups with their notes, newest first
+    List,
+    /// Restore a backup by file name, path or part of its note
+    Restore {
+        /// Backup file name, path, or note substring
+        target: String,
+        /// Restore without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Compare a backup with the current configurations
+    ///
+    /// Exits with status 1 when they differ.
+    Diff {
+        /// Backup file name, path, or note substring
+        #[arg(required_unless_present = "latest")]
+        target: Option<String>,
+        /// Compare the newest backup
+        #[arg(long, conflicts_with = "target")]
+        latest: bool,
+        /// Show each changed variable
+        #[arg(short, long)]
+        detailed: bool,
+    },
+    /// Gzip backups written uncompressed by older versions
+    Compress,
+    /// Check backups against their checksums
+    ///
+    /// Exits with an error when a backup is damaged.
+    Verify {
+        /// Backup file name, path, or note substring; all backups when omitted
+        target: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DefaultAction {
+    /// Make a configuration the default for new shells
+    Set {
+        /// Configuration name or alias
+        alias: String,
+    },
+    /// Stop applying a configuration to new shells
+    Clear,
+    /// Show the default configuration
+    Show,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SettingsAction {
+    /// Show current settings
+    Show,
+    /// Treat an extra variable as a Claude variable in status and detection
+    WatchClaude {
+        /// Variable name, e.g. OPENROUTER_API_KEY
+        name: String,
+    },
+    /// Stop treating a user-added variable as a Claude variable
+    UnwatchClaude {
+        /// Variable name to remove from the watchlist
+        name: String,
+    },
+    /// Change a setting
+    ///
+    /// Available settings:
+    ///   skip-verification    true to ignore 'use --verify' (for offline use)
+    ///   git-track            true to commit every change to a git repository in the state directory
+    ///   skip-duplicate-check true to stop 'audit' from looking for secrets shared between configurations
+    ///   no-emoji             true to print [OK], [WARN] and similar tags instead of emoji
+    ///   language             en or zh-CN (ENVSWITCH_LANG takes precedence)
+    ///   follow-symlinks      false to replace a symlinked config.json with a file instead of writing to its target
+    ///   strict               true to always treat warnings as errors, as with envswitch --strict
+    ///   replace-guard        variables 'set --replace' may remove without asking, unless over half (default 10, off)
+    Set {
+        /// Setting name
+        key: String,
+        /// New value
+        value: String,
+    },
+}
+
+impl Commands {
+    /// Commands called from shell prompts and completions on every keystroke or line, which
+    /// skip the store integrity check and everything else they don't need for their output
+    pub fn is_prompt_path(&self) -> bool {
+        matches!(self, Commands::Prompt | Commands::List { active: true, .. } | Commands::List { names: true, .. } | Commands::Current { clear: false, .. })
+    }
+    
+    /// Whether the store integrity check runs before this command
+    ///
+    /// The check parses every configuration, so only commands that change the store and
+    /// `doctor` pay for it; read-only commands report a corrupt store when they load it.
+    pub fn checks_integrity(&self) -> bool {
+        !matches!(self,
+            Commands::List { .. } | Commands::Status { .. } | Commands::Drift { .. } | Commands::Export { .. }
+            | Commands::Watch { .. } | Commands::Convert { .. } | Commands::Log { .. } | Commands::HistoryVar { .. }
+            | Commands::Env { .. } | Commands::Render { .. } | Commands::Paths { .. } | Commands::Current { clear: false, .. }
+            | Commands::Prompt | Commands::Audit { repair: false, .. } | Commands::Shellenv { .. }
+            | Commands::Examples { .. } | Commands::Tutorial { .. }
+            | Commands::Alias { action: AliasAction::List }
+            | Commands::Backup { action: BackupAction::List | BackupAction::Diff { .. } | BackupAction::Verify { .. } }
+            | Commands::Default { action: DefaultAction::Show }
+            | Commands::Settings { action: SettingsAction::Show })
+    }
+    
+    /// Whether the first-run welcome message may be shown before this command
+    ///
+    /// `use`, `clear`, `drift --fix`, `env`, `shellenv`, `prompt`, `current`, `paths` and `watch` must stay machine-readable and side-effect free;
+    /// `init` covers what the welcome message says.
+    pub fn shows_welcome(&self) -> bool {
+        !self.is_prompt_path()
+            && !matches!(self, Commands::Use { .. } | Commands::Clear { .. } | Commands::Drift { fix: true } | Commands::Env { .. } | Commands::Shellenv { .. } | Commands::Paths { .. } | Commands::Current { .. } | Commands::Watch { .. } | Commands::Init { .. })
+    }
+}
+
 /// Parse environment variable in KEY=VALUE format
 fn parse_env_var(s: &str) -> Result<(String, String), String> {
     let parts: Vec<&str> = s.splitn(2, '=').collect();
@@ -211,8 +1026,43 @@ fn parse_env_var(s: &str) -> Result<(String, String), String> {
         return Err(format!("Invalid format '{}'. Expected KEY=VALUE", s));
     }
     Ok((parts[0].to_string(), parts[1].to_string()))
-}#[cfg(test
-)]
+}
+
+/// Parse a --from-op argument as REFERENCE=KEY into (KEY, REFERENCE)
+///
+/// The key comes after the last '=' since 1Password references may contain query strings.
+fn parse_op_reference(s: &str) -> Result<(String, String), String> {
+    match s.rsplit_once('=') {
+        Some((reference, key)) if reference.starts_with("op://") && !key.is_empty() => {
+            Ok((key.to_string(), reference.to_string()))
+        }
+        _ => Err(format!("Invalid format '{}'. Expected op://vault/item/field=KEY", s)),
+    }
+}
+
+/// Parse an -e argument as KEY=VALUE, or a bare KEY whose value is taken from the environment
+fn parse_env_var_or_key(s: &str) -> Result<(String, Option<String>), String> {
+    if s.starts_with('-') {
+        return Err(format!("'{}' looks like a flag, not a variable name; every -e needs a KEY=VALUE after it", s));
+    }
+    if s.contains('=') {
+        return parse_env_var(s).map(|(key, value)| (key, Some(value)));
+    }
+    if s.trim().is_empty() {
+        return Err("Invalid format ''. Expected KEY=VALUE or KEY".to_string());
+    }
+    Ok((s.to_string(), None))
+}
+
+/// Parse a trailing positional argument of the set command as KEY=VALUE
+fn parse_positional_env_var(s: &str) -> Result<(String, String), String> {
+    parse_env_var(s).map_err(|_| format!(
+        "Unexpected argument '{}'. Extra arguments must be KEY=VALUE pairs (e.g. API_KEY=value); \
+         quote values with spaces, e.g. 'API_KEY=a value'", s
+    ))
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -265,4 +1115,149 @@ mod tests {
         assert_eq!(key, "");
         assert_eq!(value, "");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_only_writing_commands_check_integrity() {
+        let checks = |args: &[&str]| {
+            let cli = Cli::try_parse_from([&["envswitch"][..], args].concat()).expect("arguments should parse");
+            cli.command.checks_integrity()
+        };
+        for args in [&["set", "dev", "KEY=value"][..], &["delete", "dev"], &["use", "dev"], &["doctor"], &["backup", "restore", "latest"]] {
+            assert!(checks(args), "{:?} should check integrity", args);
+        }
+        for args in [&["list"][..], &["status"], &["export"], &["backup", "list"], &["alias", "list"], &["audit"]] {
+            assert!(!checks(args), "{:?} should skip the integrity check", args);
+        }
+    }
+
+    #[test]
+    fn test_set_positional_pairs() {
+        let Ok(cli) = Cli::try_parse_from([
+            "envswitch", "set", "deepseek",
+            "ANTHROPIC_MODEL=deepseek-chat", "TOKEN=abc=def",
+            "-e", "EXTRA=1",
+        ]) else {
+            panic!("positional pairs should parse");
+        };
+        
+        match cli.command {
+            Commands::Set { alias, pairs, env, .. } => {
+                assert_eq!(alias, "deepseek");
+                assert_eq!(pairs, vec![
+                    ("ANTHROPIC_MODEL".to_string(), "deepseek-chat".to_string()),
+                    ("TOKEN".to_string(), "abc=def".to_string()),
+                ]);
+                assert_eq!(env, vec![("EXTRA".to_string(), Some("1".to_string()))]);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_set_positional_without_equals() {
+        let error = Cli::try_parse_from(["envswitch", "set", "deepseek", "NOT_A_PAIR"])
+            .err()
+            .expect("argument without '=' should be rejected");
+        assert!(error.to_string().contains("NOT_A_PAIR"));
+    }
+
+    #[test]
+    fn test_parse_env_var_or_key() {
+        assert_eq!(parse_env_var_or_key("KEY=value").unwrap(), ("KEY".to_string(), Some("value".to_string())));
+        assert_eq!(parse_env_var_or_key("AWS_PROFILE").unwrap(), ("AWS_PROFILE".to_string(), None));
+        assert!(parse_env_var_or_key("").is_err());
+    }
+
+    fn parse(args: &[&str]) -> Commands {
+        let args = std::iter::once("envswitch").chain(args.iter().copied());
+        Cli::try_parse_from(args).unwrap_or_else(|e| panic!("{}", e)).command
+    }
+
+    #[test]
+    fn test_command_aliases() {
+        for args in [&["add", "work"][..], &["s", "work"]] {
+            assert!(matches!(parse(args), Commands::Set { alias, .. } if alias == "work"), "{:?}", args);
+        }
+        for args in [&["switch", "work"][..], &["sw", "work"], &["u", "work"]] {
+            assert!(matches!(parse(args), Commands::Use { alias, .. } if alias == "work"), "{:?}", args);
+        }
+        assert!(matches!(parse(&["ls"]), Commands::List { .. }));
+        assert!(matches!(parse(&["info"]), Commands::Status { .. }));
+        assert!(matches!(parse(&["st"]), Commands::Status { .. }));
+        assert!(matches!(parse(&["rm", "work"]), Commands::Delete { alias, .. } if alias == "work"));
+        assert!(matches!(parse(&["ex"]), Commands::Export { .. }));
+        assert!(matches!(parse(&["im", "configs.json"]), Commands::Import { file, .. } if file == "configs.json"));
+        assert!(matches!(parse(&["guide"]), Commands::Tutorial { .. }));
+        assert!(matches!(parse(&["alias", "rm", "ds"]), Commands::Alias { action: AliasAction::Remove { .. } }));
+    }
+
+    #[test]
+    fn test_every_command_has_examples_that_parse() {
+        let examples = crate::examples::COMMAND_EXAMPLES.iter().flat_map(|(_, examples)| examples.iter())
+            .chain(crate::examples::RECIPES.iter().flat_map(|recipe| recipe.steps.iter()));
+        for example in examples {
+            let command = example.command.strip_prefix("eval \"$(")
+                .and_then(|command| command.strip_suffix(")\""))
+                .unwrap_or(example.command);
+            // Quoting, pipes and redirections would need a shell to split
+            if !command.starts_with("envswitch ") || command.contains(['\'', '"', '|', '<', '>']) {
+                continue;
+            }
+            let args: Vec<&str> = command.split_whitespace().collect();
+            if let Err(error) = Cli::try_parse_from(&args) {
+                panic!("{}: {}", example.command, error);
+            }
+        }
+
+        // Setup, init and the tutorial still have to be written
+        for command in Cli::command().get_subcommands() {
+            let name = command.get_name();
+            assert!(
+                ["setup", "init", "tutorial"].contains(&name) || !crate::examples::command_examples(name).is_empty(),
+                "'{}' has no examples", name
+            );
+        }
+    }
+
+    #[test]
+    fn test_unambiguous_prefixes() {
+        assert!(matches!(parse(&["stat"]), Commands::Status { .. }));
+        assert!(matches!(parse(&["del", "work"]), Commands::Delete { .. }));
+        assert!(matches!(parse(&["hist", "work", "KEY"]), Commands::HistoryVar { .. }));
+
+        // Ambiguous prefixes are rejected, with the candidates as suggestions
+        let error = Cli::try_parse_from(["envswitch", "un"]).err().expect("'un' is ambiguous");
+        assert!(error.to_string().contains("unset-var"), "{}", error);
+        let error = Cli::try_parse_from(["envswitch", "lsit"]).err().expect("'lsit' is no command");
+        assert!(error.to_string().contains("'list'"), "{}", error);
+    }
+
+    fn set_error(args: &[&str]) -> String {
+        let args = ["envswitch", "set", "foo"].into_iter().chain(args.iter().copied());
+        Cli::try_parse_checked_from(args).err().expect("arguments should be rejected").to_string()
+    }
+
+    #[test]
+    fn test_set_rejects_flag_like_and_empty_arguments() {
+        // A repeated flag used to end up as a confusing "value is required" error
+        let error = set_error(&["-e", "-e", "KEY=value"]);
+        assert!(error.contains("'-e' looks like a flag"), "{}", error);
+        let error = set_error(&["-e", "--description", "text"]);
+        assert!(error.contains("'--description' looks like a flag"), "{}", error);
+
+        let error = set_error(&["-e", "=value"]);
+        assert!(error.contains("'=value' at position 3") && error.contains("name is empty"), "{}", error);
+        let error = set_error(&["-e", "KEY=  "]);
+        assert!(error.contains("KEY has an empty value"), "{}", error);
+        let error = set_error(&["OTHER=1", "KEY="]);
+        assert!(error.contains("'KEY=' at position 3"), "{}", error);
+
+        // A stray space after '=' leaves the value as an extra argument
+        let error = set_error(&["KEY=", "value"]);
+        assert!(error.contains("'value'") && error.contains("quote values with spaces"), "{}", error);
+
+        let cli = Cli::try_parse_checked_from(["envswitch", "set", "foo", "-e", "KEY=", "--allow-empty-value"]).unwrap();
+        assert!(matches!(cli.command, Commands::Set { env, .. } if env == vec![("KEY".to_string(), Some(String::new()))]));
+        assert!(Cli::try_parse_checked_from(["envswitch", "set", "foo", "-e", "KEY=value", "-e", "BARE"]).is_ok());
+    }
+}
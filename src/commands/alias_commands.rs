@@ -0,0 +1,52 @@
+use crate::cli::AliasAction;
+use crate::config::{FileConfigManager, ConfigManager};
+use crate::utils::config_not_found;
+use std::error::Error;
+use crate::output::OutputContext;
+
+/// Handle the alias command to manage alternative configuration names
+pub fn handle_alias_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    action: AliasAction,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    match action {
+        AliasAction::Add { config, alias } => {
+            // Allow pointing a new alias at an existing alias of the config
+            let config_name = match config_manager.resolve_config_name(&config)? {
+                Some(name) => name,
+                None => return Err(config_not_found(&config, &config_manager.list_names_and_aliases()?).into()),
+            };
+
+            config_manager.add_config_alias(&config_name, &alias)?;
+            outln!(output, "✅ Alias '{}' now points to configuration '{}'", alias, config_name);
+
+            if verbose {
+                outln!(output, "   envswitch use {}    # Activate '{}' using the alias", alias, config_name);
+            }
+        }
+        AliasAction::Remove { alias } => {
+            let config_name = config_manager.remove_config_alias(&alias)?;
+            outln!(output, "✅ Alias '{}' removed from configuration '{}'", alias, config_name);
+        }
+        AliasAction::List => {
+            let store = config_manager.load_configs()?;
+            let aliases = store.list_config_aliases();
+
+            if aliases.is_empty() {
+                outln!(output, "No aliases defined");
+                outln!(output, "💡 Add one with: envswitch alias add <config> <alias>");
+                return Ok(());
+            }
+
+            let width = aliases.iter().map(|(alias, _)| alias.len()).max().unwrap_or(0);
+            outln!(output, "Configuration aliases:");
+            for (alias, config_name) in aliases {
+                outln!(output, "  {:<width$} -> {}", alias, config_name, width = width);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use crate::audit::audit_store;
+use crate::config::{FileConfigManager, ConfigManager};
+use crate::utils::WarningAggregator;
+use std::error::Error;
+use crate::output::OutputContext;
+
+/// Handle the audit command to look for shared and placeholder secrets
+///
+/// With `repair`, untrimmed values are fixed first. Returns false when something was found.
+pub fn handle_audit_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    json: bool,
+    repair: bool,
+    verbose: bool,
+) -> Result<bool, Box<dyn Error>> {
+    if repair {
+        let cleaned = config_manager.clean_values()?;
+        for (config, key) in &cleaned {
+            outln!(output, "✂️  Trimmed {}:{}", config, key);
+        }
+        if cleaned.is_empty() && verbose {
+            outln!(output, "ℹ️  No values needed trimming");
+        }
+    }
+
+    let store = config_manager.load_configs()?;
+    let local_home = dirs::home_dir().map(|home| home.to_string_lossy().into_owned());
+    let report = audit_store(output, &store, !store.settings.skip_duplicate_check, local_home.as_deref());
+
+    if json {
+        outln!(output, "{}", serde_json::to_string_pretty(&report)?);
+        return Ok(report.is_clean());
+    }
+
+    for duplicate in &report.duplicates {
+        let locations: Vec<String> = duplicate.locations.iter()
+            .map(|location| format!("{}:{}", location.config, location.key))
+            .collect();
+        outln!(output, "⚠️  Same secret ({}) in {}", duplicate.value, locations.join(", "));
+    }
+    if !report.duplicates.is_empty() {
+        outln!(output, "   💡 Rotating one of these means updating all of them");
+    }
+
+    // The same problem across many configurations is summarised, --verbose lists every occurrence
+    let mut warnings = WarningAggregator::new();
+    for location in &report.empty {
+        warnings.add("{count} empty secret(s)", format!("{}:{}", location.config, location.key));
+    }
+    for placeholder in &report.placeholders {
+        warnings.add("{count} placeholder secret(s)", format!("{}:{} ({})", placeholder.config, placeholder.key, placeholder.value));
+    }
+    for suspicious in &report.suspicious {
+        for problem in &suspicious.problems {
+            warnings.add(&format!("{{count}} secret(s) whose value {}", problem), format!("{}:{}", suspicious.config, suspicious.key));
+        }
+    }
+    for location in &report.untrimmed {
+        warnings.add("{count} value(s) with surrounding whitespace or control characters", format!("{}:{}", location.config, location.key));
+    }
+    for value in &report.host_specific {
+        for problem in &value.problems {
+            warnings.add_with_symbol("ℹ️", &format!("{{count}} variable(s) whose value {}", problem), format!("{}:{}", value.config, value.key));
+        }
+    }
+    warnings.display(output, verbose);
+    if !report.untrimmed.is_empty() {
+        outln!(output, "   💡 Trim them with: envswitch audit --repair");
+    }
+
+    if report.duplicate_check_skipped && verbose {
+        outln!(output, "ℹ️  Shared secrets were not checked (skip-duplicate-check is enabled)");
+    }
+
+    let findings = report.duplicates.len() + report.empty.len() + report.placeholders.len() + report.suspicious.len()
+        + report.untrimmed.len();
+    if findings == 0 {
+        outln!(output, "✅ No problems with secrets in {} configurations", store.configs.len());
+    } else {
+        outln!(output, "\n📊 {} finding(s)", findings);
+    }
+
+    Ok(report.is_clean())
+}
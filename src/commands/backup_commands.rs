@@ -0,0 +1,212 @@
+use crate::cli::BackupAction;
+use crate::config::{FileConfigManager, ConfigManager};
+use crate::diff::VariableChange;
+use crate::error::ConfigError;
+use crate::utils::{display_operation_report, ChecksumStatus, format_file_size, format_recency, is_sensitive_key, mask_sensitive_value, prompt_confirmation, OperationReport};
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Instant;
+use crate::output::OutputContext;
+
+/// Handle the backup command to create, list, verify and restore backups
+pub fn handle_backup_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    action: BackupAction,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    match action {
+        BackupAction::Create { note } => {
+            let start_time = Instant::now();
+            let backup_path = config_manager.backup_config_with_note(note.as_deref())?;
+            let duration = start_time.elapsed();
+            outln!(output, "✅ Backup created: {}", backup_path.display());
+            
+            let store = config_manager.load_configs()?;
+            let mut report = OperationReport::new("Backup", 1, duration)
+                .with_detail("Configurations", store.configs.len())
+                .with_detail("Variables", store.configs.values().map(|config| config.variables.len()).sum::<usize>());
+            if let Some(note) = &note {
+                report = report.with_detail("Note", note);
+            }
+            display_operation_report(output, &report.with_artifact(&backup_path));
+        }
+        BackupAction::List => {
+            let backups = config_manager.list_backups_with_info()?;
+            if backups.is_empty() {
+                outln!(output, "📭 No backups found in {}", config_manager.backup_dir().display());
+                outln!(output, "💡 Create one with: envswitch backup create --note \"why\"");
+                return Ok(());
+            }
+
+            outln!(output, "📦 Backups (newest first):");
+            for (path, info) in backups {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                match info {
+                    Some(info) => {
+                        let note = info.note.map(|note| format!("  \"{}\"", note)).unwrap_or_default();
+                        outln!(output, "  {}  {}  {} configs, {} vars{}", name, format_recency(output, info.created_at, verbose), info.configs, info.variables, note);
+                    }
+                    None => outln!(output, "  {}", name),
+                }
+                if verbose {
+                    outln!(output, "     {}", path.display());
+                }
+            }
+        }
+        BackupAction::Compress => {
+            let summary = config_manager.compress_backups()?;
+            if summary.compressed == 0 {
+                outln!(output, "✅ All backups are already compressed");
+                return Ok(());
+            }
+            outln!(
+                output,
+                "✅ Compressed {} backups: {} → {} ({} saved)",
+                summary.compressed,
+                format_file_size(summary.original_bytes),
+                format_file_size(summary.compressed_bytes),
+                format_file_size(summary.saved_bytes()),
+            );
+        }
+        BackupAction::Diff { target, latest, detailed } => {
+            handle_backup_diff_command(output, config_manager, target, latest, detailed, verbose)?;
+        }
+        BackupAction::Verify { target } => {
+            let backups = match target {
+                Some(target) => vec![config_manager.find_backup(&target)?],
+                None => config_manager.list_backups()?,
+            };
+            if backups.is_empty() {
+                outln!(output, "📭 No backups found in {}", config_manager.backup_dir().display());
+                return Ok(());
+            }
+
+            let mut damaged = 0;
+            for path in &backups {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                match config_manager.verify_backup(path) {
+                    Ok(ChecksumStatus::Verified) => outln!(output, "✅ {}: checksum verified", name),
+                    Ok(ChecksumStatus::Missing) => outln!(output, "⚠️  {}: readable, but made before backups had checksums", name),
+                    Ok(status) => {
+                        damaged += 1;
+                        outln!(output, "❌ {}: {}", name, status.error_message().unwrap_or_default());
+                    }
+                    Err(e) => {
+                        damaged += 1;
+                        outln!(output, "❌ {}: {}", name, e);
+                    }
+                }
+            }
+
+            if damaged > 0 {
+                return Err(Box::new(ConfigError::ValidationError(format!(
+                    "{} of {} backups failed verification", damaged, backups.len()
+                ))));
+            }
+        }
+        BackupAction::Restore { target, force } => {
+            let backup_path = config_manager.find_backup(&target)?;
+
+            if !force {
+                let message = format!("Replace all configurations with {}?", backup_path.display());
+                if !prompt_confirmation(output, &message, false)? {
+                    outln!(output, "❌ Restore cancelled");
+                    return Ok(());
+                }
+            }
+
+            // Keep the current state so the restore itself can be undone
+            if config_manager.config_file_exists() {
+                let name = backup_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                config_manager.backup_config_with_note(Some(&format!("before restore of {}", name)))?;
+            }
+
+            config_manager.restore_from_backup(&backup_path)?;
+            outln!(output, "✅ Restored {}", backup_path.display());
+
+            if let Some(active) = config_manager.get_active_config()? {
+                outln!(output, "💡 Re-run 'envswitch use {}' to apply the restored values to your shell", active);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle the backup diff command, returning whether the backup differs from the current store
+pub fn handle_backup_diff_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    target: Option<String>,
+    latest: bool,
+    detailed: bool,
+    verbose: bool,
+) -> Result<bool, Box<dyn Error>> {
+    let backup_path: PathBuf = match target {
+        Some(target) if !latest => config_manager.find_backup(&target)?,
+        _ => config_manager.list_backups()?
+            .into_iter()
+            .next()
+            .ok_or("No backups found")?,
+    };
+    let name = backup_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let diff = config_manager.diff_with_backup(&backup_path)?;
+    if verbose {
+        outln!(output, "📁 Backup: {}", backup_path.display());
+    }
+
+    if diff.is_empty() {
+        outln!(output, "✅ {} matches the current configurations", name);
+        return Ok(false);
+    }
+
+    outln!(output, "🔍 Changes restoring {} would make:", name);
+    for config in &diff.added {
+        outln!(output, "  + {} (only in backup)", config);
+    }
+    for config in &diff.removed {
+        outln!(output, "  - {} (only in current store)", config);
+    }
+    for config in &diff.changed {
+        let (added, changed, removed) = config.counts();
+        outln!(output, "  ~ {} ({} added, {} changed, {} removed)", config.name, added, changed, removed);
+
+        if detailed {
+            for change in &config.changes {
+                match change {
+                    VariableChange::Added { key, value } => {
+                        outln!(output, "      + {}={}", key, display_value(output, key, value));
+                    }
+                    VariableChange::Removed { key, value } => {
+                        outln!(output, "      - {}={}", key, display_value(output, key, value));
+                    }
+                    VariableChange::Changed { key, old, new } => {
+                        outln!(output, "      ~ {}: {} → {}", key, display_value(output, key, old), display_value(output, key, new));
+                    }
+                }
+            }
+        }
+    }
+
+    outln!(
+        output,
+        "\n📊 {} added, {} removed, {} changed",
+        diff.added.len(), diff.removed.len(), diff.changed.len()
+    );
+    if !detailed && !diff.changed.is_empty() {
+        outln!(output, "💡 Use --detailed to see each changed variable");
+    }
+
+    Ok(true)
+}
+
+/// Mask values of sensitive variables
+fn display_value(output: &OutputContext, key: &str, value: &str) -> String {
+    if is_sensitive_key(key) {
+        mask_sensitive_value(output, value)
+    } else {
+        value.to_string()
+    }
+}
@@ -1,21 +1,75 @@
 use std::collections::HashMap;
-use std::io::{self, Write};
-use crate::config::{FileConfigManager, ConfigManager};
-use crate::env::{ShellEnvironmentManager, EnvironmentManager};
-use crate::handlers::interactive_env_input;
-use crate::utils::{read_env_file, is_sensitive_key, mask_sensitive_value, is_claude_configuration, find_similar_configs};
+use std::io::{self, IsTerminal};
+use std::time::{Duration, Instant};
+use crate::config::{ArchiveFilter, FileConfigManager, ConfigManager, EnvConfig};
+use crate::error::ConfigError;
+use crate::diff::{diff_variables, VariableChange};
+use serde::Serialize;
+use crate::env::{ShellEnvironmentManager, EnvironmentManager, SwitchPayload, COMMAND_REFERENCE_PREFIX, OP_REFERENCE_PREFIX, is_lazy_reference, resolve_lazy_reference, resolve_lazy_references, resolve_variables, value_matches_expected};
+use crate::shell::{ShellDetector, ShellType, VariableScope, ListOp};
+use crate::output::OutputContext;
+use crate::handlers::{prompt_template_values, set_wizard, EditableConfig, Prompter, TerminalPrompter, VariableEditor, check_strict, trim_values_with_notice, warn_suspicious_tokens};
+use crate::utils::{prompt_confirmation, confirm_on_stderr, display_operation_report, OperationReport};
+use crate::types::constants::DEFAULT_MARKER_VAR;
+use crate::utils::{read_env_file_with, FlattenOptions, parse_env_content, is_sensitive_key, mask_sensitive_value, is_claude_configuration, find_similar_configs, config_not_found, copy_to_clipboard, parse_date, parse_duration, format_recency, format_timestamp};
+
+/// Options of the set command, as given on the command line
+#[derive(Debug, Default)]
+pub struct SetOptions {
+    pub alias: String,
+    /// `-e` assignments; a bare KEY captures its value from the current environment
+    pub env_vars: Vec<(String, Option<String>)>,
+    pub description: Option<String>,
+    /// A .env or JSON file to read variables from
+    pub file: Option<String>,
+    pub flatten: Option<FlattenOptions>,
+    /// Drop the variables that are not given instead of merging into them
+    pub replace: bool,
+    /// Confirm a `--replace` that removes many variables without asking
+    pub yes: bool,
+    pub interactive: bool,
+    pub stdin: bool,
+    pub prepend: Vec<(String, String)>,
+    pub append: Vec<(String, String)>,
+    /// Per-variable comments; an empty reason removes the comment
+    pub comments: Vec<(String, String)>,
+    /// Skip bare keys that are not set in the current environment
+    pub allow_missing: bool,
+    pub note: Option<String>,
+    pub links: Vec<String>,
+    pub expires: Option<String>,
+    pub expires_in: Option<String>,
+    pub from_op: Vec<(String, String)>,
+    pub from_cmd: Vec<(String, String)>,
+    /// Store `--from-op`/`--from-cmd` as references resolved on use
+    pub lazy: bool,
+    pub dry_run: bool,
+    /// `--output`, "text" or "json"
+    pub output: String,
+    pub allow_case_collision: bool,
+    pub keep_whitespace: bool,
+}
 
 /// Handle the set command to create or update configurations
+///
+/// Returns whether the configuration changed (or would change in a dry run).
 pub fn handle_set_command(
+    output: &OutputContext,
     config_manager: &FileConfigManager,
-    alias: String,
-    env_vars: Vec<(String, String)>,
-    description: Option<String>,
-    file: Option<String>,
-    replace: bool,
-    interactive: bool,
+    env_manager: &ShellEnvironmentManager,
+    options: SetOptions,
     verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let SetOptions {
+        alias, env_vars, mut description, file, flatten, replace, yes, interactive, stdin, prepend, append,
+        comments, allow_missing, note, links, expires, expires_in, from_op, from_cmd, lazy, dry_run, output: output_format,
+        allow_case_collision, keep_whitespace,
+    } = options;
+    let start_time = Instant::now();
+    // JSON output replaces every other message on stdout
+    let json = output_format == "json";
+    let verbose = verbose && !json;
+    
     // Validate alias name
     if alias.trim().is_empty() {
         return Err("Configuration name cannot be empty. Please provide a name for your configuration.".into());
@@ -34,312 +88,1486 @@ pub fn handle_set_command(
         return Err("Configuration name is too long. Please use a name with 50 characters or less.".into());
     }
     
+    // Resolve aliases so that `set <alias>` updates the configuration it points to
+    let alias = config_manager.resolve_config_name(&alias)?.unwrap_or(alias);
+    
+    // Parse the expiration up front so a typo doesn't leave a half-written config
+    let expires_at = match (expires, expires_in) {
+        (Some(date), _) => Some(parse_date(&date)?),
+        (None, Some(duration)) => Some(chrono::Utc::now() + parse_duration(&duration)?),
+        (None, None) => None,
+    };
+    
     if verbose {
-        println!("Creating/updating configuration '{}'...", alias);
+        outln!(output, "Creating/updating configuration '{}'...", alias);
     }
     
+    // Nothing to set from any source
+    let bare = env_vars.is_empty() && file.is_none() && !stdin && !interactive
+        && from_op.is_empty() && from_cmd.is_empty() && prepend.is_empty() && append.is_empty();
+    
     // Collect variables from different sources
     let mut variables: HashMap<String, String> = HashMap::new();
     
-    // Add variables from command line
-    variables.extend(env_vars.into_iter());
+    // Add variables from command line; bare keys capture their current value
+    let flag_keys: Vec<String> = env_vars.iter().map(|(key, _)| key.clone()).collect();
+    let mut captured_keys = Vec::new();
+    let mut missing_keys = Vec::new();
+    for (key, value) in env_vars {
+        match value {
+            Some(value) => {
+                variables.insert(key, value);
+            }
+            None => match env_manager.get_variable(&key) {
+                Some(value) => {
+                    captured_keys.push(key.clone());
+                    variables.insert(key, value);
+                }
+                None => missing_keys.push(key),
+            },
+        }
+    }
+    
+    if !missing_keys.is_empty() {
+        if !allow_missing {
+            return Err(format!(
+                "Not set in the current environment: {}\n💡 Export them first, pass KEY=VALUE explicitly, or use --allow-missing to skip them",
+                missing_keys.join(", ")
+            ).into());
+        }
+        output.execution.record_warnings(missing_keys.len());
+        outln!(output, "⚠️  Skipping variables not set in the current environment: {}", missing_keys.join(", "));
+    }
     
     // Add variables from file if specified
     if let Some(file_path) = file {
-        let file_vars = read_env_file(&file_path)?;
+        let file_vars = read_env_file_with(&file_path, flatten.as_ref())?;
         if verbose {
-            println!("Read {} variables from file: {}", file_vars.len(), file_path);
+            outln!(output, "Read {} variables from file: {}", file_vars.len(), file_path);
         }
         variables.extend(file_vars);
     }
     
-    // Interactive mode
+    // Lines piped in, parsed like a .env file; invalid lines are reported and skipped
+    if stdin {
+        let parsed = parse_env_content(&std::io::read_to_string(std::io::stdin())?);
+        for (line_number, reason) in &parsed.skipped {
+            eoutln!(output, "⚠️  Skipped line {} of stdin: {}", line_number, reason);
+        }
+        if !json {
+            outln!(output, "📥 Parsed {} variables from stdin", parsed.variables.len());
+        }
+        for (key, value) in parsed.variables {
+            if !flag_keys.contains(&key) {
+                variables.insert(key, value);
+            }
+        }
+    }
+    
+    // Per-variable comments; an empty reason removes the comment
+    let mut comments: HashMap<String, String> = comments.into_iter().collect();
+    
+    // Interactive mode, starting from the variables and description given so far
     if interactive {
-        variables.extend(interactive_env_input(verbose)?);
+        let editor = VariableEditor::new(EditableConfig {
+            variables: variables.clone(),
+            description: description.clone(),
+            comments: comments.clone(),
+            ..Default::default()
+        });
+        match editor.run(output, &mut TerminalPrompter::new(output))? {
+            Some(edited) => {
+                variables = edited.variables;
+                description = edited.description;
+                comments = edited.comments;
+            }
+            None => return Ok(false),
+        }
+    }
+    
+    // Secrets from 1Password or other commands; their values are never printed
+    let secret_references: Vec<(String, String)> = from_op.into_iter()
+        .map(|(key, reference)| (key, format!("{}{}", OP_REFERENCE_PREFIX, reference)))
+        .chain(from_cmd.into_iter().map(|(key, command)| (key, format!("{}{}", COMMAND_REFERENCE_PREFIX, command))))
+        .collect();
+    let secret_keys: Vec<String> = secret_references.iter().map(|(key, _)| key.clone()).collect();
+    
+    if lazy {
+        if secret_references.is_empty() {
+            return Err("--lazy only applies to --from-op and --from-cmd".into());
+        }
+        if !config_manager.get_settings()?.lazy_secrets {
+            return Err("Lazy secret references are disabled.\n💡 Enable them with: envswitch settings set lazy-secrets true".into());
+        }
+        variables.extend(secret_references);
+    } else if dry_run {
+        // Secret commands only run when the configuration is really saved
+        variables.extend(secret_references);
+    } else {
+        let mut failures = Vec::new();
+        for (key, reference) in secret_references {
+            match resolve_lazy_reference(&key, &reference) {
+                Ok(value) => {
+                    variables.insert(key, value);
+                }
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+        
+        if !failures.is_empty() {
+            for failure in &failures {
+                eoutln!(output, "❌ {}", failure);
+            }
+            return Err(format!("{} secret(s) could not be read; configuration was not changed", failures.len()).into());
+        }
+    }
+    
+    // PATH-style entries are stored like other variables plus their list mode
+    let plain_keys: Vec<String> = variables.keys().cloned().collect();
+    let mut new_list_ops = HashMap::new();
+    for (op, entries) in [(ListOp::Prepend, prepend), (ListOp::Append, append)] {
+        for (key, value) in entries {
+            new_list_ops.insert(key.clone(), op);
+            variables.insert(key, value);
+        }
+    }
+    
+    if !keep_whitespace {
+        trim_values_with_notice(output, &alias, &mut variables);
+    }
+    
+    let has_details = note.is_some() || !links.is_empty();
+    
+    // Notes, links, comments and expiration alone may be attached to an existing configuration
+    let existing_config = config_manager.get_config(&alias)?;
+    if let (true, true, Some(existing)) = (variables.is_empty(), has_details || !comments.is_empty() || expires_at.is_some(), &existing_config) {
+        check_comment_keys(&comments, &existing.variables)?;
+        // Variables stay the same, but the details count as a change
+        let summary = SetSummary::new(&alias, Some(existing), &existing.variables);
+        if json {
+            outln!(output, "{}", serde_json::to_string_pretty(&summary)?);
+        }
+        if dry_run {
+            if !json {
+                outln!(output, "🔍 {}", msg!(output, "set.dry_run_details", alias = alias));
+                if has_details {
+                    outln!(output, "   Notes and links");
+                }
+                if !comments.is_empty() {
+                    outln!(output, "   Variable comments");
+                }
+                if let Some(expires_at) = expires_at {
+                    outln!(output, "   Expiration: {}", format_timestamp(output, expires_at));
+                }
+                outln!(output, "   {}", msg!(output, "common.nothing_saved"));
+            }
+            return Ok(true);
+        }
+        let mut config = existing.clone();
+        let updated_comments = !comments.is_empty();
+        apply_variable_comments(&mut config, comments);
+        if has_details {
+            apply_config_details(&mut config, note, links)?;
+        }
+        if expires_at.is_some() {
+            config.expires_at = expires_at;
+        }
+        config_manager.save_config(config, false)?;
+        if !json {
+            if has_details {
+                outln!(output, "✅ {}", msg!(output, "set.details_updated", alias = alias));
+            }
+            if updated_comments {
+                outln!(output, "✅ Variable comments of '{}' updated", alias);
+            }
+            if let Some(expires_at) = expires_at {
+                outln!(output, "⏳ Configuration '{}' expires {}", alias, format_timestamp(output, expires_at));
+            }
+        }
+        display_set_report(output, &summary, json, start_time);
+        return Ok(true);
     }
     
-    if variables.is_empty() {
-        println!("No environment variables provided.");
-        println!("Examples:");
-        println!("  envswitch set {} -e ANTHROPIC_BASE_URL=https://api.deepseek.com -e ANTHROPIC_MODEL=deepseek-chat", alias);
-        println!("  envswitch set {} -e API_KEY=your-key -d \"My API configuration\"", alias);
+    // In a terminal, a bare `set <name>` walks through creating the configuration
+    let wizard = !json && !output.execution.eval && io::stdin().is_terminal() && io::stdout().is_terminal();
+    if bare && wizard {
+        let current = existing_config.as_ref().map(|config| config.variables.clone()).unwrap_or_default();
+        match set_wizard(output, &mut TerminalPrompter::new(output), &alias, &current)? {
+            Some(answers) => {
+                variables = answers.variables;
+                description = description.or(answers.description);
+            }
+            None => return Ok(false),
+        }
+    }
+    
+    if variables.is_empty() {
+        outln!(output, "{}", msg!(output, "set.no_variables"));
+        outln!(output, "{}", msg!(output, "set.examples"));
+        outln!(output, "  envswitch set {} -e ANTHROPIC_BASE_URL=https://api.deepseek.com -e ANTHROPIC_MODEL=deepseek-chat", alias);
+        outln!(output, "  envswitch set {} -e API_KEY=your-key -d \"My API configuration\"", alias);
+        return Ok(false);
+    }
+    
+    if verbose {
+        outln!(output, "Variables to set ({}):", variables.len());
+        let mut sorted_vars: Vec<_> = variables.iter().collect();
+        sorted_vars.sort_by_key(|(k, _)| *k);
+        for (key, value) in sorted_vars {
+            // Mask sensitive values in verbose output
+            let display_value = if secret_keys.contains(key) && !lazy {
+                "********".to_string()
+            } else if is_sensitive_key(key) {
+                mask_sensitive_value(output, value)
+            } else {
+                value.clone()
+            };
+            let origin = if captured_keys.contains(key) {
+                " (from environment)"
+            } else if secret_keys.contains(key) {
+                if lazy { " (resolved on use)" } else { " (from secret command)" }
+            } else {
+                ""
+            };
+            outln!(output, "  {} = {}{}", key, display_value, origin);
+        }
+    }
+    
+    // Without --replace the new variables are merged into the existing ones
+    let final_variables = match &existing_config {
+        Some(existing) if !replace => {
+            let mut merged_variables = existing.variables.clone();
+            merged_variables.extend(variables.clone());
+            merged_variables
+        }
+        _ => variables.clone(),
+    };
+    let summary = SetSummary::new(&alias, existing_config.as_ref(), &final_variables);
+    check_comment_keys(&comments, &final_variables)?;
+    warn_suspicious_tokens(output, &alias, &variables);
+    // A strict run fails before anything is saved
+    check_strict(output)?;
+    
+    if dry_run {
+        if json {
+            outln!(output, "{}", serde_json::to_string_pretty(&summary)?);
+            return Ok(summary.has_changes());
+        }
+        print_set_dry_run(output, &summary);
+        if replace && summary.removes_too_many(config_manager.get_settings()?.replace_guard_keys()) {
+            outln!(output, "   Removing {} variables would ask for confirmation (or --yes)", summary.removed.len());
+        }
+        if has_details {
+            outln!(output, "   Notes and links would be updated");
+        }
+        if !comments.is_empty() {
+            outln!(output, "   Variable comments would be updated");
+        }
+        if let Some(expires_at) = expires_at {
+            outln!(output, "   Expiration: {}", format_timestamp(output, expires_at));
+        }
+        outln!(output, "   {}", msg!(output, "common.nothing_saved"));
+        return Ok(summary.has_changes());
+    }
+    
+    // The whole configuration is saved in one store write, so a failed set changes nothing
+    let mut config = match &existing_config {
+        Some(existing) => {
+            let mut config = existing.clone();
+            config.update(final_variables, description.clone())?;
+            config
+        }
+        None => EnvConfig::new(alias.clone(), variables.clone(), description.clone())?,
+    };
+    // Plain assignments reset a variable's list mode
+    for key in &plain_keys {
+        config.list_ops.remove(key);
+    }
+    config.list_ops.extend(new_list_ops);
+    apply_variable_comments(&mut config, comments);
+    if has_details {
+        apply_config_details(&mut config, note, links)?;
+    }
+    if expires_at.is_some() {
+        config.expires_at = expires_at;
+    }
+    
+    if let Some(existing) = &existing_config {
+        if verbose {
+            outln!(output, "Updating existing configuration:");
+            outln!(output, "  Created: {}", format_timestamp(output, existing.created_at));
+            outln!(output, "  Previous variables: {}", existing.variables.len());
+            if !summary.added.is_empty() {
+                outln!(output, "  Adding: {}", summary.added.join(", "));
+            }
+            if !summary.updated.is_empty() {
+                outln!(output, "  Updating: {}", summary.updated.join(", "));
+            }
+            if !summary.removed.is_empty() {
+                outln!(output, "  Removing: {}", summary.removed.join(", "));
+            }
+            if replace {
+                outln!(output, "Replacing all variables (--replace mode)");
+            }
+        }
+        
+        // A habitual --replace can wipe out most of a configuration; ask first and keep a backup to undo it
+        if replace && summary.removes_too_many(config_manager.get_settings()?.replace_guard_keys()) {
+            let existing_count = existing.variables.len();
+            eoutln!(output, "⚠️  --replace would remove {} of {} variables from '{}': {}", summary.removed.len(), existing_count, alias, summary.removed.join(", "));
+            let question = "Remove them?";
+            let confirmed = yes || if json { confirm_on_stderr(output, question)? } else { prompt_confirmation(output, question, false)? };
+            if !confirmed {
+                return Err(ConfigError::ReplaceGuard { alias, removed: summary.removed.len() }.into());
+            }
+            let backup = config_manager.backup_config_with_note(Some(&format!("before set --replace of {}", alias)))?;
+            if !json {
+                let name = backup.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                outln!(output, "💾 Undo with: envswitch backup restore {}", name);
+            }
+        }
+        
+        config_manager.save_config(config, false)?;
+    } else {
+        let collision = config_manager.load_configs()?.case_collision(&alias).map(str::to_string);
+        if let (Some(existing), true) = (&collision, allow_case_collision) {
+            eoutln!(output, "⚠️  '{}' differs only by case from existing '{}'", alias, existing);
+        }
+        config_manager.save_config(config, allow_case_collision).map_err(|e| -> Box<dyn std::error::Error> {
+            if collision.is_some() {
+                format!("{}\n💡 Pick another name, or pass --allow-case-collision to create it anyway", e).into()
+            } else {
+                e.into()
+            }
+        })?;
+    }
+    
+    if has_details && verbose {
+        outln!(output, "Notes and links updated");
+    }
+    if let (Some(expires_at), false) = (expires_at, json) {
+        outln!(output, "⏳ Expires: {}", format_timestamp(output, expires_at));
+    }
+    
+    if json {
+        outln!(output, "{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        // Detect if this looks like a Claude configuration
+        let settings = config_manager.get_settings()?;
+        if is_claude_configuration(&variables, &settings.claude_variables) {
+            outln!(output, "💡 This appears to be a Claude configuration. Use 'envswitch status --claude' to check Claude variables.");
+        }
+        
+        if verbose {
+            outln!(output, "{}", msg!(output, "set.saved_to", path = config_manager.config_file_path().display()));
+        }
+    }
+    
+    display_set_report(output, &summary, json, start_time);
+    Ok(summary.has_changes())
+}
+
+/// Print the operation report of `set`, unless `--output json` printed the summary instead
+fn display_set_report(output: &OutputContext, summary: &SetSummary, json: bool, started: Instant) {
+    if !json || output.execution.json_report {
+        display_operation_report(output, &summary.report(started.elapsed()));
+    }
+}
+
+/// Changes made by `set`, printed with `--output json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SetSummary {
+    pub alias: String,
+    pub created: bool,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub total_variables: usize,
+}
+
+impl SetSummary {
+    /// Compare the existing configuration, if any, with the variables it will hold
+    pub fn new(alias: &str, existing: Option<&EnvConfig>, final_variables: &HashMap<String, String>) -> Self {
+        let empty = HashMap::new();
+        let mut summary = SetSummary {
+            alias: alias.to_string(),
+            created: existing.is_none(),
+            added: Vec::new(),
+            updated: Vec::new(),
+            removed: Vec::new(),
+            total_variables: final_variables.len(),
+        };
+        
+        for change in diff_variables(existing.map(|config| &config.variables).unwrap_or(&empty), final_variables) {
+            match change {
+                VariableChange::Added { key, .. } => summary.added.push(key),
+                VariableChange::Changed { key, .. } => summary.updated.push(key),
+                VariableChange::Removed { key, .. } => summary.removed.push(key),
+            }
+        }
+        summary
+    }
+    
+    /// Whether the update removes more than `max_keys` variables or over half of the existing ones;
+    /// `None` never counts as too many
+    pub fn removes_too_many(&self, max_keys: Option<usize>) -> bool {
+        let Some(max_keys) = max_keys else {
+            return false;
+        };
+        let existing = self.total_variables + self.removed.len() - self.added.len();
+        self.removed.len() > max_keys || self.removed.len() * 2 > existing
+    }
+    
+    /// Check if the configuration is new or any variable changed
+    pub fn has_changes(&self) -> bool {
+        self.created || !self.added.is_empty() || !self.updated.is_empty() || !self.removed.is_empty()
+    }
+    
+    /// The operation report once the changes are saved
+    pub fn report(&self, duration: Duration) -> OperationReport {
+        OperationReport::new("Set", 1, duration)
+            .with_detail("Configuration", &self.alias)
+            .with_detail("Action", if self.created { "created" } else { "updated" })
+            .with_detail("Added", self.added.len())
+            .with_detail("Updated", self.updated.len())
+            .with_detail("Removed", self.removed.len())
+            .with_detail("Total variables", self.total_variables)
+    }
+}
+
+/// Print the changes `set` would make to a configuration
+fn print_set_dry_run(output: &OutputContext, summary: &SetSummary) {
+    if summary.created {
+        outln!(output, "🔍 {}", msg!(output, "set.dry_run_create", alias = summary.alias));
+    } else {
+        outln!(output, "🔍 {}", msg!(output, "set.dry_run_update", alias = summary.alias));
+    }
+    if !summary.added.is_empty() {
+        outln!(output, "   Add: {}", summary.added.join(", "));
+    }
+    if !summary.updated.is_empty() {
+        outln!(output, "   Update: {}", summary.updated.join(", "));
+    }
+    if !summary.removed.is_empty() {
+        outln!(output, "   Remove: {}", summary.removed.join(", "));
+    }
+    if !summary.has_changes() {
+        outln!(output, "   No variable changes");
+    }
+    outln!(output, "   Variables after save: {}", summary.total_variables);
+}
+
+/// Replace a configuration's notes (when given) and append new links
+fn apply_config_details(config: &mut EnvConfig, note: Option<String>, new_links: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    if new_links.iter().any(|link| link.trim().is_empty()) {
+        return Err("Links cannot be empty".into());
+    }
+    
+    if let Some(note) = note {
+        config.notes = Some(note).filter(|n| !n.trim().is_empty());
+    }
+    for link in new_links {
+        if !config.links.contains(&link) {
+            config.links.push(link);
+        }
+    }
+    config.updated_at = chrono::Utc::now();
+    Ok(())
+}
+
+/// Refuse comments for variables the configuration won't have; removals are always fine
+fn check_comment_keys(comments: &HashMap<String, String>, variables: &HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut unknown: Vec<&str> = comments.iter()
+        .filter(|(key, reason)| !reason.trim().is_empty() && !variables.contains_key(*key))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    unknown.sort();
+    Err(format!("--comment names variables that are not set: {}\n💡 Set them with -e in the same command", unknown.join(", ")).into())
+}
+
+/// Merge comments into a configuration; an empty reason removes the variable's comment
+fn apply_variable_comments(config: &mut EnvConfig, comments: HashMap<String, String>) {
+    for (key, reason) in comments {
+        if reason.trim().is_empty() {
+            config.variable_comments.remove(&key);
+        } else {
+            config.variable_comments.insert(key, reason);
+        }
+    }
+}
+
+/// Parse `--scope`, falling back to the session scope for shells other than fish
+fn variable_scope(output: &OutputContext, shell_type: &ShellType, scope: &str) -> Result<VariableScope, Box<dyn std::error::Error>> {
+    let scope: VariableScope = scope.parse()?;
+    if scope == VariableScope::Universal && *shell_type != ShellType::Fish {
+        eoutln!(output, "⚠️  --scope universal only applies to fish (detected {}); using session scope", shell_type);
+        return Ok(VariableScope::Session);
+    }
+    Ok(scope)
+}
+
+/// Options of the use command, as given on the command line
+#[derive(Debug, Default)]
+pub struct UseOptions {
+    pub alias: String,
+    pub dry_run: bool,
+    /// Also copy the generated shell code to the clipboard
+    pub copy: bool,
+    /// Refuse to activate an expired configuration
+    pub strict: bool,
+    /// `--format`, "shell" or "json"
+    pub format: String,
+    pub force: bool,
+    /// Check the values before activating: "warn" or "strict"
+    pub verify: Option<String>,
+    /// Ask before replacing values set by something other than envswitch
+    pub interactive_conflicts: bool,
+    /// `--scope`, "session" or fish's "universal"
+    pub scope: String,
+    /// Don't record the configuration as active
+    pub no_persist: bool,
+}
+
+/// Handle the use command to switch configurations
+pub fn handle_use_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    env_manager: &ShellEnvironmentManager,
+    options: UseOptions,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let UseOptions { alias, dry_run, copy, strict, format, force, verify, interactive_conflicts, scope, no_persist } = options;
+    
+    // Validate alias
+    if alias.trim().is_empty() {
+        return Err("Configuration name cannot be empty. Please specify which configuration to use.".into());
+    }
+    
+    // The JSON payload contains every secret in clear text
+    let json = format == "json";
+    if json && io::stdout().is_terminal() && !force {
+        return Err("Refusing to print the JSON switch payload to a terminal because it contains secret values.\n💡 Pipe the output to another program, or pass --force to print it anyway".into());
+    }
+    
+    // Resolve aliases to the canonical configuration name
+    let alias = config_manager.resolve_config_name(&alias)?.unwrap_or(alias);
+    
+    let config = config_manager.get_config(&alias)?
+        .ok_or_else(|| config_not_found(&alias, &config_manager.list_names_and_aliases().unwrap_or_default()))?;
+    
+    if config.archived && !force {
+        return Err(format!(
+            "Configuration '{}' is archived. Use --force to activate it anyway, or 'envswitch unarchive {}'",
+            alias, alias
+        ).into());
+    }
+    
+    // Warnings go to stderr so they never end up in an eval'd script
+    if config.is_expired() {
+        let expires_at = format_timestamp(output, config.expires_at.unwrap());
+        if strict {
+            return Err(format!(
+                "Configuration '{}' expired on {}. Refusing to activate in --strict mode.\n💡 Renew it with: envswitch set {} --expires-in 7d",
+                alias, expires_at, alias
+            ).into());
+        }
+        eoutln!(output, "⚠️  {}", msg!(output, "use.expired", alias = alias, date = expires_at));
+    }
+    
+    // `@op:` and `@cmd:` references run their commands once, right here
+    let settings = config_manager.get_settings()?;
+    let mut unsaved_keys: Vec<String> = config.variables.iter()
+        .filter(|(_, value)| is_lazy_reference(value))
+        .map(|(key, _)| key.clone())
+        .collect();
+    let mut config = config;
+    config.variables = resolve_lazy_references(&config.variables, settings.lazy_secrets)?;
+    
+    // Templates ask for their placeholders each time; a dry run shows the placeholders
+    let placeholders = config.placeholder_keys();
+    if config.template && !placeholders.is_empty() {
+        if dry_run {
+            eoutln!(output, "🧩 '{}' is a template; without --dry-run you are asked for {}", alias, placeholders.join(", "));
+        } else {
+            config.variables.extend(prompt_template_values(output, &alias, &placeholders)?);
+            unsaved_keys.extend(placeholders);
+        }
+    }
+    
+    // Details go to stderr, stdout is eval'd
+    if verbose {
+        eoutln!(output, "{}", msg!(output, "use.switching", alias = alias));
+        eoutln!(output, "Description: {}", config.description.as_deref().unwrap_or("No description"));
+        eoutln!(output, "Variables: {}", config.variables.len());
+        eoutln!(output, "Created: {}", format_timestamp(output, config.created_at));
+        eoutln!(output, "Updated: {}", format_timestamp(output, config.updated_at));
+    }
+    
+    let shell_type = env_manager.get_shell_type().clone();
+    let scope = variable_scope(output, &shell_type, &scope)?;
+    
+    // Compare against the current environment so values set by other tools aren't silently replaced
+    let previous = config_manager.get_active_config()?
+        .and_then(|name| config_manager.get_config(&name).ok().flatten());
+    let (previous_variables, previous_list_ops) = previous.as_ref()
+        .map(|previous| {
+            // Lazy secrets of the previous configuration are assumed to be the exported values
+            let variables: HashMap<String, String> = previous.variables.iter()
+                .map(|(key, value)| {
+                    let value = if is_lazy_reference(value) {
+                        env_manager.get_variable(key).unwrap_or_else(|| value.clone())
+                    } else {
+                        value.clone()
+                    };
+                    (key.clone(), value)
+                })
+                .collect();
+            (
+                resolve_variables(&variables).unwrap_or(variables),
+                previous.list_ops.clone(),
+            )
+        })
+        .unwrap_or_default();
+    let breakdown = env_manager.plan_switch(
+        &resolve_variables(&config.variables)?,
+        &config.list_ops,
+        &previous_variables,
+        &previous_list_ops,
+    );
+    
+    let mut variables = config.variables.clone();
+    if !breakdown.foreign.is_empty() {
+        eoutln!(output, "⚠️  {}", msg!(output, "use.overwrites_external", alias = alias));
+        for key in &breakdown.foreign {
+            let current = env_manager.get_variable(key).unwrap_or_default();
+            let display_current = if is_sensitive_key(key) { mask_sensitive_value(output, &current) } else { current };
+            eoutln!(output, "   {} (currently {})", key, display_current);
+        }
+        
+        if interactive_conflicts {
+            for key in &breakdown.foreign {
+                if !confirm_on_stderr(output, &format!("Overwrite {}?", key))? {
+                    variables.remove(key);
+                }
+            }
+        }
+    }
+    
+    if verbose {
+        eoutln!(
+            output,
+            "Changes: {} new, {} unchanged, {} replaced, {} foreign, {} unset",
+            breakdown.new.len(), breakdown.matching.len(), breakdown.replacing.len(),
+            breakdown.foreign.len(), breakdown.unset.len()
+        );
+    }
+    
+    let payload = SwitchPayload {
+        alias: alias.clone(),
+        shell: shell_type.to_string(),
+        set: resolve_variables(&variables)?.into_iter().collect(),
+        unset: breakdown.unset.clone(),
+        list_ops: config.list_ops.iter()
+            .filter(|(key, _)| variables.contains_key(*key))
+            .map(|(key, op)| (key.clone(), *op))
+            .collect(),
+        changes: breakdown.clone(),
+    };
+    
+    let scoped_env_manager = ShellEnvironmentManager::with_shell_type(shell_type).with_scope(scope);
+    let mut command_groups = Vec::new();
+    
+    // Take back list entries added by the previous activation so they don't pile up
+    if let Some(previous) = &previous {
+        if !previous.list_ops.is_empty() {
+            command_groups.push(scoped_env_manager.generate_list_removal_commands(&previous.variables, &previous.list_ops)?);
+        }
+    }
+    // Variables of the previous configuration that this one doesn't set
+    if !breakdown.unset.is_empty() {
+        command_groups.push(scoped_env_manager.generate_unset_commands(&breakdown.unset)?);
+    }
+    command_groups.push(scoped_env_manager.generate_config_commands(&variables, &config.list_ops)?);
+    let commands = if json {
+        serde_json::to_string_pretty(&payload)?
+    } else {
+        command_groups.join("\n")
+    };
+    
+    // Clipboard output goes to stderr so it never ends up in an eval'd script; a dry run
+    // leaves the clipboard alone and only says what would be copied
+    if copy && dry_run {
+        eoutln!(output, "📋 {}", msg!(output, "use.copy_dry_run"));
+    } else if copy {
+        match copy_to_clipboard(&commands) {
+            Ok(()) => eoutln!(output, "📋 {}", msg!(output, "use.copied", alias = alias)),
+            Err(e) => eoutln!(output, "⚠️  {}", msg!(output, "use.copy_failed", error = e)),
+        }
+    }
+    
+    if dry_run {
+        if !json {
+            outln!(output, "# Commands that would be executed:");
+        }
+        println!("{}", commands);
+        return Ok(());
+    }
+    
+    // Verification runs before activation so strict mode can abort cleanly
+    let report = match verify.as_deref() {
+        Some(_) if settings.skip_verification => {
+            eoutln!(output, "ℹ️  {}", msg!(output, "use.verification_skipped"));
+            None
+        }
+        Some(mode) => {
+            let report = verify_variables(&payload.set.clone().into_iter().collect(), VerifyOptions::default());
+            if mode == "strict" && report.has_failures() {
+                display_verification_report(output, &alias, &report);
+                return Err(format!(
+                    "Verification of '{}' failed with {} problem(s); configuration was not activated",
+                    alias, report.failure_count()
+                ).into());
+            }
+            Some(report)
+        }
+        None => None,
+    };
+    
+    // Set as active configuration; the shell still switches when that can't be saved
+    let persisted = !no_persist && match config_manager.set_active_config(alias.clone()) {
+        Ok(()) => true,
+        Err(ConfigError::ReadOnly(dir)) => {
+            eoutln!(output, "⚠️  '{}' was not recorded as the active configuration: {} is read-only", alias, dir);
+            eoutln!(output, "💡 Pass --no-persist to skip recording it");
+            false
+        }
+        Err(e) => return Err(e.into()),
+    };
+    
+    // Snapshot for `drift`, without values that are never saved (lazy secrets, answers
+    // for template placeholders) or list variables; the switch itself already
+    // succeeded, so a failure here is only a warning
+    let mut applied: HashMap<String, Option<String>> = settings.drift_watch.iter()
+        .map(|key| (key.clone(), env_manager.get_variable(key)))
+        .collect();
+    applied.extend(payload.unset.iter()
+        .filter(|key| !previous_list_ops.contains_key(*key))
+        .map(|key| (key.clone(), None)));
+    applied.extend(payload.set.iter()
+        .filter(|(key, _)| !unsaved_keys.contains(key) && !config.list_ops.contains_key(*key))
+        .map(|(key, value)| (key.clone(), Some(value.clone()))));
+    if persisted {
+        if let Err(e) = config_manager.record_applied_values(applied) {
+            eoutln!(output, "⚠️  Could not record the activation for 'envswitch drift': {}", e);
+        }
+    }
+    
+    // Output the commands for shell evaluation, verbatim since values may contain anything
+    println!("{}", commands);
+    
+    if let Some(report) = report {
+        display_verification_report(output, &alias, &report);
+    }
+    
+    if verbose && !json {
+        outln!(output, "# {}", msg!(output, "use.activated", alias = alias));
+        outln!(output, "# {}", msg!(output, "use.variables_set", count = config.variables.len()));
+    }
+    
+    Ok(())
+}
+
+/// Handle the clear command unsetting what envswitch set in this shell
+///
+/// Unsets the variables of the active configuration, or with `all_known` of every
+/// configuration, and forgets the active configuration. List variables such as PATH
+/// only lose the entries envswitch added. Commands go to stdout, everything else to stderr.
+pub fn handle_clear_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    env_manager: &ShellEnvironmentManager,
+    all_known: bool,
+    dry_run: bool,
+    scope: &str,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = config_manager.load_configs()?;
+    let active = store.active_config.clone();
+    let configs: Vec<&EnvConfig> = if all_known {
+        store.configs.values().collect()
+    } else {
+        active.iter().filter_map(|name| store.configs.get(name)).collect()
+    };
+    
+    if configs.is_empty() {
+        match &active {
+            // The active configuration was deleted or renamed outside envswitch
+            Some(name) => eoutln!(output, "⚠️  Configuration '{}' no longer exists; nothing to unset", name),
+            None => {
+                eoutln!(output, "ℹ️  No configuration is active; nothing to unset");
+                if !all_known {
+                    eoutln!(output, "💡 Unset the variables of every configuration with: eval \"$(envswitch clear --all-known)\"");
+                }
+            }
+        }
+        if active.is_some() && !dry_run {
+            config_manager.clear_active_config()?;
+        }
+        return Ok(());
+    }
+    
+    let shell_type = env_manager.get_shell_type().clone();
+    let scope = variable_scope(output, &shell_type, scope)?;
+    let scoped_env_manager = ShellEnvironmentManager::with_shell_type(shell_type).with_scope(scope);
+    
+    // Unsetting a list variable would lose the entries that were there before envswitch
+    let list_keys: std::collections::HashSet<&String> = configs.iter()
+        .flat_map(|config| config.list_ops.keys())
+        .collect();
+    let mut keys: Vec<String> = configs.iter()
+        .flat_map(|config| config.variables.keys())
+        .filter(|key| !list_keys.contains(key))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys.dedup();
+    
+    let mut command_groups = Vec::new();
+    let mut list_variables = std::collections::BTreeSet::new();
+    for config in &configs {
+        // Lazy secrets can't be resolved without running their commands; those entries stay
+        let variables: HashMap<String, String> = config.variables.iter()
+            .filter(|(key, value)| config.list_ops.contains_key(*key) && !is_lazy_reference(value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        if !variables.is_empty() {
+            command_groups.push(scoped_env_manager.generate_list_removal_commands(&variables, &config.list_ops)?);
+            list_variables.extend(variables.into_keys());
+        }
+    }
+    if !keys.is_empty() {
+        command_groups.push(scoped_env_manager.generate_unset_commands(&keys)?);
+    }
+    let commands = command_groups.join("\n");
+    
+    let source = if all_known {
+        format!("{} configuration(s)", configs.len())
+    } else {
+        format!("'{}'", active.as_deref().unwrap_or_default())
+    };
+    // List variables lose only their entries, but they are changed all the same
+    eoutln!(output, "🔄 Clearing {} variable(s) of {}", keys.len() + list_variables.len(), source);
+    if !list_variables.is_empty() {
+        let list_variables: Vec<&str> = list_variables.iter().map(String::as_str).collect();
+        eoutln!(output, "   Removing added entries from {}", list_variables.join(", "));
+    }
+    if verbose {
+        for key in &keys {
+            eoutln!(output, "   • {}", key);
+        }
+    }
+    
+    if dry_run {
+        eoutln!(output, "🔍 Dry run: commands that would be executed; the active configuration is kept");
+        println!("{}", commands);
+        return Ok(());
+    }
+    
+    if active.is_some() {
+        config_manager.clear_active_config()?;
+    }
+    // Output the commands for shell evaluation, verbatim like `use`
+    println!("{}", commands);
+    Ok(())
+}
+
+/// Options of the list command, as given on the command line
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    pub table: bool,
+    /// Only print the active configuration
+    pub active: bool,
+    /// Sort order within the pinned and unpinned groups: "name", "updated", "created" or "variables"
+    pub sort_by: String,
+    /// `--columns` of the table, overriding the list-columns setting
+    pub columns: Option<String>,
+    pub filter: ArchiveFilter,
+    /// With verbose output, list variables alphabetically instead of grouped by prefix
+    pub flat: bool,
+    pub json: bool,
+    /// `--fields` to keep in the JSON output
+    pub fields: Option<String>,
+}
+
+/// Handle the list command to show all configurations
+pub fn handle_list_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    options: ListOptions,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ListOptions { table, active, sort_by, columns, filter, flat, json, fields } = options;
+    let fields = fields.as_deref().map(|list| parse_fields(list, LIST_FIELDS)).transpose()?;
+    if active {
+        // Show only active configuration; called from prompts, so nothing else is loaded
+        if let Some(active_config) = config_manager.get_active_config()? {
+            outln!(output, "{}", msg!(output, "list.active", alias = active_config));
+        } else {
+            outln!(output, "{}", msg!(output, "list.no_active"));
+        }
+        return Ok(());
+    }
+    
+    let store = config_manager.load_configs()?;
+    let configs = store.list_for_display(&sort_by, filter);
+    
+    if json {
+        let summaries: Vec<ConfigSummary> = configs.iter()
+            .filter_map(|name| store.configs.get(name))
+            .map(|config| ConfigSummary::new(output, config, &store))
+            .collect();
+        let mut value = serde_json::to_value(&summaries)?;
+        if let Some(fields) = &fields {
+            select_fields(&mut value, fields);
+        }
+        outln!(output, "{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+    
+    // Everything is archived, or nothing is when only archived ones were asked for
+    if configs.is_empty() && !store.configs.is_empty() {
+        match filter {
+            ArchiveFilter::Archived => outln!(output, "📭 No archived configurations"),
+            _ => {
+                outln!(output, "📭 All {} configurations are archived", store.configs.len());
+                outln!(output, "💡 Show them with: envswitch list --archived");
+            }
+        }
+        return Ok(());
+    }
+    
+    if configs.is_empty() {
+        outln!(output, "📭 {}", msg!(output, "list.empty"));
+        outln!(output);
+        outln!(output, "🚀 {}", msg!(output, "list.get_started"));
+        outln!(output, "   envswitch set my-config -e API_KEY=your-key -e ENV=development");
+        outln!(output);
+        outln!(output, "💡 {}", msg!(output, "list.tutorial"));
+        outln!(output, "   envswitch tutorial");
+        return Ok(());
+    }
+    
+    if table || columns.is_some() {
+        let columns = match columns.or(store.settings.list_columns) {
+            Some(columns) => ListColumn::parse_list(&columns)?,
+            None => ListColumn::DEFAULT.to_vec(),
+        };
+        display_configs_table(output, &configs, config_manager, &columns, verbose)?;
+    } else {
+        display_configs_list(output, &configs, config_manager, verbose, flat)?;
+    }
+    
+    Ok(())
+}
+
+/// Handle `list --names`: one configuration name per line, sorted, for completion scripts and fzf
+///
+/// Nothing else is printed, not even with `--no-emoji` or in another language; with
+/// `with_description` each line is `name<TAB>description`.
+pub fn handle_list_names_command(
+    config_manager: &FileConfigManager,
+    with_description: bool,
+    filter: ArchiveFilter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut lines: Vec<String> = if with_description {
+        let store = config_manager.load_configs()?;
+        store.configs.iter()
+            .filter(|(_, config)| filter.includes(config))
+            .map(|(name, config)| {
+                // Tabs and line breaks would split the line for the reader
+                let description: String = config.description.as_deref().unwrap_or_default()
+                    .chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .collect();
+                format!("{}\t{}", name, description)
+            })
+            .collect()
+    } else {
+        // Only the names are needed, which the split layout reads from its index
+        config_manager.list_configs_matching(filter)?
+    };
+    lines.sort();
+    
+    let mut output = lines.join("\n");
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    // A reader such as `head` may stop early; that is not an error
+    match std::io::Write::write_all(&mut std::io::stdout().lock(), output.as_bytes()) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Handle the current command printing the active configuration for scripts
+///
+/// Returns false when no configuration is active; plain output is empty then. With
+/// `clear`, the active configuration is forgotten without touching the shell.
+pub fn handle_current_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    json: bool,
+    clear: bool,
+    verbose: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if clear {
+        match config_manager.get_active_config()? {
+            Some(active) => {
+                config_manager.clear_active_config()?;
+                outln!(output, "✅ '{}' is no longer the active configuration", active);
+                outln!(output, "💡 Its variables stay set in this shell until you unset them or open a new one");
+            }
+            None => outln!(output, "ℹ️  No configuration is active"),
+        }
+        return Ok(true);
+    }
+    
+    // Prompts call this constantly: the name comes from state.json alone, the store is only
+    // read when there is no state file yet
+    if !json && !verbose {
+        let active = match config_manager.activation_state() {
+            Some(state) => state.active_config,
+            None => config_manager.get_active_config()?,
+        };
+        if let Some(active) = &active {
+            // Written directly: scripts read this even with --quiet
+            println!("{}", active);
+        }
+        return Ok(active.is_some());
+    }
+    
+    let store = config_manager.load_configs()?;
+    if json {
+        let report = CurrentReport {
+            active_config: store.active_config.clone(),
+            activated_at: store.activated_at,
+            stale: store.is_active_stale(),
+            shell: ShellDetector::detect_shell().to_string(),
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if let Some(active) = &store.active_config {
+        println!("{}", active);
+        // Details go to stderr so scripts still read just the name
+        if let Some(activated_at) = store.activated_at {
+            eoutln!(output, "Activated: {}", format_timestamp(output, activated_at));
+        }
+        if store.is_active_stale() {
+            eoutln!(output, "⚠️  '{}' changed after activation; apply it again with: eval \"$(envswitch use {})\"", active, active);
+        }
+    }
+    Ok(store.active_config.is_some())
+}
+
+/// Handle the drift command comparing the shell with what `use` left in it
+///
+/// With `fix`, commands restoring the recorded values go to stdout and the report to stderr.
+pub fn handle_drift_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    env_manager: &ShellEnvironmentManager,
+    fix: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report = |line: String| if fix { eoutln!(output, "{}", line) } else { outln!(output, "{}", line) };
+    
+    let Some(active) = config_manager.get_active_config()? else {
+        report("ℹ️  No configuration is active; nothing to compare".to_string());
+        return Ok(());
+    };
+    let state = config_manager.activation_state().unwrap_or_default();
+    if state.applied.is_empty() {
+        report(format!("ℹ️  Nothing was recorded when '{}' was activated", active));
+        report(format!("💡 Activate it again to compare later: eval \"$(envswitch use {})\"", active));
         return Ok(());
     }
     
-    if verbose {
-        println!("Variables to set ({}):", variables.len());
-        let mut sorted_vars: Vec<_> = variables.iter().collect();
-        sorted_vars.sort_by_key(|(k, _)| *k);
-        for (key, value) in sorted_vars {
-            // Mask sensitive values in verbose output
-            let display_value = if is_sensitive_key(key) {
-                mask_sensitive_value(value)
-            } else {
-                value.clone()
-            };
-            println!("  {} = {}", key, display_value);
+    let shown = |key: &str, value: &str| if is_sensitive_key(key) { mask_sensitive_value(output, value) } else { value.to_string() };
+    let mut keys: Vec<&String> = state.applied.keys().collect();
+    keys.sort();
+    let mut restore = HashMap::new();
+    let mut unset = Vec::new();
+    let mut lines = Vec::new();
+    for key in keys {
+        let expected = &state.applied[key];
+        let live = env_manager.get_variable(key);
+        if live == *expected {
+            continue;
+        }
+        lines.push(match (expected, &live) {
+            (Some(_), None) => format!("   • {}: unset", key),
+            (Some(expected), Some(live)) => format!("   • {}: {} (was {})", key, shown(key, live), shown(key, expected)),
+            (None, Some(live)) => format!("   • {}: {} (was unset)", key, shown(key, live)),
+            (None, None) => unreachable!("equal values were skipped"),
+        });
+        match expected {
+            Some(value) => { restore.insert(key.clone(), value.clone()); }
+            None => unset.push(key.clone()),
         }
     }
     
-    // Check if config already exists
-    let existing_config = config_manager.get_config(&alias)?;
-    let exists = existing_config.is_some();
+    let since = state.activated_at
+        .map(|activated_at| format!(" ({})", format_recency(output, activated_at, verbose)))
+        .unwrap_or_default();
+    if lines.is_empty() {
+        report(format!("✅ No drift: {} variable(s) unchanged since '{}' was activated{}", state.applied.len(), active, since));
+        return Ok(());
+    }
+    report(format!("⚠️  {} variable(s) changed since '{}' was activated{}:", lines.len(), active, since));
+    for line in lines {
+        report(line);
+    }
     
-    if exists {
-        let existing = existing_config.unwrap();
-        
-        if verbose {
-            println!("Updating existing configuration:");
-            println!("  Created: {}", existing.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
-            println!("  Previous variables: {}", existing.variables.len());
-            
-            // Show what's changing
-            let mut added = Vec::new();
-            let mut updated = Vec::new();
-            let mut removed = Vec::new();
-            
-            for (key, new_value) in &variables {
-                match existing.variables.get(key) {
-                    Some(old_value) if old_value != new_value => updated.push(key.clone()),
-                    None => added.push(key.clone()),
-                    _ => {} // No change
-                }
-            }
-            
-            for key in existing.variables.keys() {
-                if !variables.contains_key(key as &str) {
-                    removed.push(key.clone());
-                }
-            }
-            
-            if !added.is_empty() {
-                println!("  Adding: {}", added.join(", "));
-            }
-            if !updated.is_empty() {
-                println!("  Updating: {}", updated.join(", "));
-            }
-            if !removed.is_empty() {
-                println!("  Removing: {}", removed.join(", "));
-            }
+    if fix {
+        let mut command_groups = Vec::new();
+        if !restore.is_empty() {
+            command_groups.push(env_manager.generate_shell_commands(&restore)?);
         }
-        
-        // Handle variable merging based on replace flag
-        let final_variables = if replace {
-            if verbose {
-                println!("Replacing all variables (--replace mode)");
-            }
-            variables.clone()
-        } else {
-            // Merge with existing variables (update mode)
-            let mut merged_variables = existing.variables.clone();
-            merged_variables.extend(variables.clone());
-            merged_variables
-        };
-        
-        let var_count = final_variables.len();
-        config_manager.update_config(alias.clone(), final_variables, description.clone())?;
-        println!("✅ Configuration '{}' updated successfully!", alias);
-        
-        if verbose {
-            println!("  Total variables: {}", var_count);
+        if !unset.is_empty() {
+            command_groups.push(env_manager.generate_unset_commands(&unset)?);
         }
+        println!("{}", command_groups.join("\n"));
     } else {
-        config_manager.create_config(alias.clone(), variables.clone(), description.clone())?;
-        println!("✅ Configuration '{}' created successfully!", alias);
-        println!("📝 {} environment variables configured", variables.len());
-        if let Some(desc) = description {
-            println!("📄 Description: {}", desc);
-        }
-        println!();
-        println!("🚀 Next steps:");
-        println!("   envswitch use {}           # Activate this configuration", alias);
-        println!("   envswitch show {}          # View configuration details", alias);
-        println!("   envswitch list             # See all configurations");
+        report("💡 Restore them with: eval \"$(envswitch drift --fix)\"".to_string());
     }
+    Ok(())
+}
+
+/// Handle the prompt command printing the active configuration for a shell prompt
+///
+/// Errors print nothing rather than breaking the prompt.
+pub fn handle_prompt_command(config_manager: &FileConfigManager) -> Result<(), Box<dyn std::error::Error>> {
+    let Ok(store) = config_manager.load_configs() else {
+        return Ok(());
+    };
     
-    // Detect if this looks like a Claude configuration
-    if is_claude_configuration(&variables) {
-        println!("💡 This appears to be a Claude configuration. Use 'envswitch status --claude' to check Claude variables.");
+    if let Some(active) = &store.active_config {
+        let marker = if store.is_active_stale() { "*" } else { "" };
+        println!("{}{}", active, marker);
+    } else if let Ok(default) = std::env::var(DEFAULT_MARKER_VAR) {
+        println!("{}", default);
     }
     
-    if verbose {
-        println!("Configuration saved to: {}", config_manager.config_file_path().display());
+    Ok(())
+}
+
+/// Handle the protect and unprotect commands
+pub fn handle_protect_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    alias: String,
+    protected: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let alias = config_manager.resolve_config_name(&alias)?.unwrap_or(alias);
+    
+    config_manager.set_config_protected(&alias, protected)?;
+    
+    if protected {
+        outln!(output, "🔒 Configuration '{}' protected", alias);
+    } else {
+        outln!(output, "✅ Configuration '{}' unprotected", alias);
+    }
+    
+    if verbose && protected {
+        outln!(output, "💡 set-var, unset-var and delete now need --force for this configuration");
     }
     
     Ok(())
 }
 
-/// Handle the use command to switch configurations
-pub fn handle_use_command(
+/// Handle the archive and unarchive commands
+pub fn handle_archive_command(
+    output: &OutputContext,
     config_manager: &FileConfigManager,
-    env_manager: &ShellEnvironmentManager,
     alias: String,
-    dry_run: bool,
+    archived: bool,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Validate alias
-    if alias.trim().is_empty() {
-        return Err("Configuration name cannot be empty. Please specify which configuration to use.".into());
-    }
+    let alias = config_manager.resolve_config_name(&alias)?.unwrap_or(alias);
     
-    let config = config_manager.get_config(&alias)?
-        .ok_or_else(|| {
-            let available_configs = config_manager.list_configs().unwrap_or_default();
-            if available_configs.is_empty() {
-                format!("Configuration '{}' not found. No configurations exist yet.\n💡 Create your first configuration with: envswitch set {} -e KEY=value", alias, alias)
-            } else {
-                let suggestions = find_similar_configs(&alias, &available_configs);
-                if suggestions.is_empty() {
-                    format!("Configuration '{}' not found.\nAvailable configurations: {}\n💡 Use 'envswitch list' to see all configurations", 
-                        alias, available_configs.join(", "))
-                } else {
-                    format!("Configuration '{}' not found.\nDid you mean: {}?\nAvailable configurations: {}", 
-                        alias, suggestions.join(", "), available_configs.join(", "))
-                }
-            }
-        })?;
+    config_manager.set_config_archived(&alias, archived)?;
     
-    if verbose {
-        println!("Switching to configuration: {}", alias);
-        println!("Description: {}", config.description.as_deref().unwrap_or("No description"));
-        println!("Variables: {}", config.variables.len());
-        println!("Created: {}", config.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
-        println!("Updated: {}", config.updated_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    if archived {
+        outln!(output, "📦 Configuration '{}' archived", alias);
+    } else {
+        outln!(output, "✅ Configuration '{}' unarchived", alias);
     }
     
-    let commands = env_manager.generate_shell_commands(&config.variables)?;
-    
-    if dry_run {
-        println!("# Commands that would be executed:");
-        println!("{}", commands);
-        return Ok(());
+    if verbose && archived {
+        outln!(output, "💡 It is hidden from list; see it with 'envswitch list --archived'. use now needs --force");
     }
     
-    // Set as active configuration
-    config_manager.set_active_config(alias.clone())?;
+    Ok(())
+}
+
+/// Handle the pin and unpin commands
+pub fn handle_pin_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    alias: String,
+    pinned: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let alias = config_manager.resolve_config_name(&alias)?.unwrap_or(alias);
     
-    // Output the commands for shell evaluation
-    println!("{}", commands);
+    config_manager.set_config_pinned(&alias, pinned)?;
+    
+    if pinned {
+        outln!(output, "★ Configuration '{}' pinned", alias);
+    } else {
+        outln!(output, "✅ Configuration '{}' unpinned", alias);
+    }
     
     if verbose {
-        println!("# Configuration '{}' activated", alias);
-        println!("# {} environment variables set", config.variables.len());
+        outln!(output, "💡 Pinned configurations are listed first in 'envswitch list'");
     }
     
     Ok(())
 }
 
-/// Handle the list command to show all configurations
-pub fn handle_list_command(
-    config_manager: &FileConfigManager, 
-    verbose: bool, 
-    table: bool, 
-    active: bool
+/// Handle the prune command to remove stale configurations
+pub fn handle_prune_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    expired: bool,
+    force: bool,
+    verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let configs = config_manager.list_configs()?;
+    if !expired {
+        return Err("Nothing to prune. Specify what to remove, e.g. 'envswitch prune --expired'".into());
+    }
     
-    if configs.is_empty() {
-        println!("📭 No configurations found");
-        println!();
-        println!("🚀 Get started by creating your first configuration:");
-        println!("   envswitch set my-config -e API_KEY=your-key -e ENV=development");
-        println!();
-        println!("💡 Or try the tutorial:");
-        println!("   envswitch tutorial");
+    let start_time = Instant::now();
+    let store = config_manager.load_configs()?;
+    let expired_configs = store.expired_configs();
+    
+    if expired_configs.is_empty() {
+        outln!(output, "✅ No expired configurations");
+        display_operation_report(output, &OperationReport::new("Prune", 0, start_time.elapsed()));
         return Ok(());
     }
     
-    if active {
-        // Show only active configuration
-        if let Some(active_config) = config_manager.get_active_config()? {
-            println!("Active configuration: {}", active_config);
-        } else {
-            println!("No active configuration");
+    outln!(output, "⏳ Expired configurations ({}):", expired_configs.len());
+    for name in &expired_configs {
+        if let Some(expires_at) = store.get_config(name).and_then(|config| config.expires_at) {
+            outln!(output, "   {} (expired {})", name, format_timestamp(output, expires_at));
         }
+    }
+    
+    if !force && !prompt_confirmation(output, "Delete these configurations?", false)? {
+        outln!(output, "❌ Prune cancelled.");
         return Ok(());
     }
     
-    if table {
-        display_configs_table(&configs, config_manager, verbose)?;
-    } else {
-        display_configs_list(&configs, config_manager, verbose)?;
+    let active_config = config_manager.get_active_config()?;
+    for name in &expired_configs {
+        config_manager.delete_config(name.clone())?;
+        if verbose {
+            outln!(output, "🗑️  Deleted '{}'", name);
+        }
     }
     
+    let active_cleared = active_config.is_some_and(|active| expired_configs.contains(&active));
+    if active_cleared {
+        config_manager.clear_active_config()?;
+        outln!(output, "🔄 Active configuration cleared.");
+    }
+    
+    outln!(output, "✅ Pruned {} expired configuration(s)", expired_configs.len());
+    let report = OperationReport::new("Prune", expired_configs.len(), start_time.elapsed())
+        .with_detail("Deleted", expired_configs.join(", "))
+        .with_detail("Active cleared", if active_cleared { "yes" } else { "no" });
+    display_operation_report(output, &report);
     Ok(())
 }
 
+/// Print the detected shell and whether the eval wrapper is installed
+fn display_shell_context(output: &OutputContext) {
+    let detection = ShellDetector::detect();
+    outln!(output, "Shell: {} (detected via {})", detection.shell, detection.method);
+    
+    let config_file = ShellDetector::shell_config_file(&detection.shell);
+    match (ShellDetector::is_integration_installed(&detection.shell), config_file) {
+        (Some(true), Some(path)) => outln!(output, "Integration: ✅ installed in {}", path.display()),
+        (Some(false), Some(path)) => {
+            outln!(output, "Integration: ⚠️  not found in {}", path.display());
+            outln!(output, "💡 Run ./install-shell-integration.sh to use 'envswitch use' without eval");
+        }
+        _ => outln!(output, "Integration: unknown"),
+    }
+    outln!(output);
+}
+
+/// Options of the status command, as given on the command line
+#[derive(Debug, Default)]
+pub struct StatusOptions {
+    /// Only show Claude variables
+    pub claude: bool,
+    pub table: bool,
+    /// Only show variables whose value differs from the active configuration
+    pub mismatched: bool,
+    pub json: bool,
+    /// `--fields` of each variable to keep in the JSON output
+    pub fields: Option<String>,
+    /// List variables alphabetically instead of grouped by prefix
+    pub flat: bool,
+}
+
 /// Handle the status command to show current environment status
 pub fn handle_status_command(
+    output: &OutputContext,
     config_manager: &FileConfigManager,
     env_manager: &ShellEnvironmentManager,
-    claude: bool,
-    table: bool,
-    mismatched: bool,
+    options: StatusOptions,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let StatusOptions { claude, table, mismatched, json, fields, flat } = options;
+    let fields = fields.as_deref().map(|list| parse_fields(list, STATUS_FIELDS)).transpose()?;
     if claude {
-        display_claude_status(env_manager, table, verbose)?;
+        let store = config_manager.load_configs()?;
+        let active = store.active_config.as_ref()
+            .and_then(|name| store.configs.get(name).map(|config| (name.as_str(), config)));
+        display_claude_status(output, env_manager, &store.settings.claude_variables, active, &store, table, verbose)?;
         return Ok(());
     }
     
     // Get active configuration
-    let active_config_name = config_manager.get_active_config()?;
+    let store = config_manager.load_configs()?;
+    // A shell started with `shellenv --activate` carries the default it applied
+    let applied_default = std::env::var(DEFAULT_MARKER_VAR).ok()
+        .filter(|name| store.configs.contains_key(name));
+    let via_default = store.active_config.is_none() && applied_default.is_some();
+    let active_config_name = store.active_config.clone().or(applied_default);
+    
+    if json {
+        let config = active_config_name.as_ref().and_then(|name| store.configs.get(name));
+        let mut variables = match config {
+            Some(config) => {
+                let keys: Vec<String> = config.variables.keys().cloned().collect();
+                let provenance = VariableProvenance::new(&config.alias, &store);
+                variable_status_reports(output, &env_manager.get_variable_status(&keys), &config.variables, &config.list_ops, &provenance)
+            }
+            None => Vec::new(),
+        };
+        if mismatched {
+            variables.retain(|variable| !variable.matches);
+        }
+        let report = StatusReport {
+            active_config: active_config_name,
+            via_default,
+            activated_at: store.activated_at.filter(|_| !via_default),
+            stale: !via_default && store.is_active_stale(),
+            variables,
+        };
+        let mut value = serde_json::to_value(&report)?;
+        if let Some(fields) = &fields {
+            select_fields(&mut value["variables"], fields);
+        }
+        outln!(output, "{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+    
+    display_shell_context(output);
     
     if let Some(config_name) = active_config_name {
         let config = config_manager.get_config(&config_name)?
             .ok_or_else(|| format!("Active configuration '{}' not found", config_name))?;
         
-        println!("Active configuration: {}", config_name);
+        if via_default {
+            outln!(output, "Active configuration: {} (via default)", config_name);
+        } else {
+            outln!(output, "Active configuration: {} (explicitly activated)", config_name);
+        }
+        if let Some(activated_at) = store.activated_at.filter(|_| !via_default) {
+            outln!(output, "Activated: {}", format_recency(output, activated_at, verbose));
+        }
+        if !via_default && store.is_active_stale() {
+            print_stale_hint(output, Some(&config_name));
+        }
         if let Some(description) = &config.description {
-            println!("Description: {}", description);
+            outln!(output, "Description: {}", description);
         }
-        println!("Variables: {}", config.variables.len());
-        println!("Created: {}", config.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
-        println!("Updated: {}", config.updated_at.format("%Y-%m-%d %H:%M:%S UTC"));
-        println!();
+        outln!(output, "Variables: {}", config.variables.len());
+        outln!(output, "Created: {}", format_recency(output, config.created_at, verbose));
+        outln!(output, "Updated: {}", format_recency(output, config.updated_at, verbose));
+        if let Some(expires_at) = config.expires_at {
+            if config.is_expired() {
+                outln!(output, "⚠️  EXPIRED: {} (credentials may no longer work)", format_timestamp(output, expires_at));
+            } else {
+                outln!(output, "Expires: {}", format_timestamp(output, expires_at));
+            }
+        }
+        outln!(output);
         
         // Check environment variable status
         let keys: Vec<String> = config.variables.keys().cloned().collect();
         let statuses = env_manager.get_variable_status(&keys);
+        let provenance = VariableProvenance::new(&config_name, &store);
+        
+        // File references are compared by content; report any that can't be read
+        let resolved_variables = match resolve_variables(&config.variables) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                outln!(output, "⚠️  {}", e.user_message());
+                outln!(output);
+                config.variables.clone()
+            }
+        };
         
         if mismatched {
             let mismatched_vars: Vec<_> = statuses.iter()
                 .filter(|status| {
-                    let expected_value = config.variables.get(&status.key);
-                    !expected_value.map_or(false, |expected| {
-                        status.value.as_deref() == Some(expected)
+                    let expected_value = resolved_variables.get(&status.key);
+                    !expected_value.is_some_and(|expected| {
+                        value_matches_expected(status.value.as_deref(), expected, config.list_ops.get(&status.key).copied())
                     })
                 })
                 .cloned()
                 .collect();
             
             if mismatched_vars.is_empty() {
-                println!("✅ All variables match expected values");
+                outln!(output, "✅ All variables match expected values");
             } else {
-                println!("⚠️  {} variables don't match expected values:", mismatched_vars.len());
+                outln!(output, "⚠️  {} variables don't match expected values:", mismatched_vars.len());
                 if table {
-                    display_status_table(&mismatched_vars, &config.variables, verbose)?;
+                    display_status_table(output, &mismatched_vars, &config.variables, &config.list_ops, &provenance, verbose)?;
                 } else {
-                    display_status_list(&mismatched_vars, &config.variables, verbose)?;
+                    display_status_list(output, &mismatched_vars, &config.variables, &config.list_ops, &provenance, verbose, flat)?;
                 }
             }
         } else {
             if table {
-                display_status_table(&statuses, &config.variables, verbose)?;
+                display_status_table(output, &statuses, &config.variables, &config.list_ops, &provenance, verbose)?;
             } else {
-                display_status_list(&statuses, &config.variables, verbose)?;
+                display_status_list(output, &statuses, &config.variables, &config.list_ops, &provenance, verbose, flat)?;
             }
         }
     } else {
-        println!("No active configuration");
-        println!("Use 'envswitch use <config-name>' to activate a configuration");
+        outln!(output, "No active configuration");
+        outln!(output, "Use 'envswitch use <config-name>' to activate a configuration");
+        if let Some(default) = &store.settings.default_config {
+            outln!(output, "Default configuration: {} (applied by 'envswitch shellenv --activate' in new shells)", default);
+        }
         
         let configs = config_manager.list_configs()?;
         if !configs.is_empty() {
-            println!();
-            println!("Available configurations:");
+            outln!(output);
+            outln!(output, "Available configurations:");
             for config in configs {
-                println!("  {}", config);
+                outln!(output, "  {}", config);
             }
         }
     }
@@ -350,15 +1578,18 @@ pub fn handle_status_command(
 
 
 // Import display functions that will be moved to handlers module
-use crate::handlers::{display_configs_table, display_configs_list, display_claude_status, display_status_table, display_status_list};
+use crate::handlers::{ConfigSummary, CurrentReport, LIST_FIELDS, STATUS_FIELDS, parse_fields, select_fields, print_stale_hint, variable_status_reports, StatusReport, VariableProvenance, ListColumn, display_configs_table, display_configs_list, display_claude_status, display_status_table, display_status_list, display_verification_report};
+use crate::verify::{verify_variables, VerifyOptions};
 // Handle the edit command to interactively edit a configuration
 pub fn handle_edit_command(
+    output: &OutputContext,
     config_manager: &FileConfigManager,
     alias: String,
+    keep_whitespace: bool,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if verbose {
-        println!("📝 Starting interactive edit for configuration '{}'...", alias);
+        outln!(output, "📝 Starting interactive edit for configuration '{}'...", alias);
     }
     
     // Validate alias
@@ -366,30 +1597,30 @@ pub fn handle_edit_command(
         return Err("Configuration name cannot be empty. Please specify which configuration to edit.".into());
     }
     
+    // Resolve aliases to the canonical configuration name
+    let alias = config_manager.resolve_config_name(&alias)?.unwrap_or(alias);
+    
     // Load existing configuration or offer to create new one
     let mut config = match config_manager.get_config(&alias)? {
         Some(config) => {
             if verbose {
-                println!("📋 Loaded existing configuration '{}'", alias);
+                outln!(output, "📋 Loaded existing configuration '{}'", alias);
             }
             config
         }
         None => {
-            println!("Configuration '{}' not found.", alias);
-            print!("Would you like to create a new configuration with this name? [y/N]: ");
-            io::Write::flush(&mut io::stdout())?;
-            
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            let input = input.trim().to_lowercase();
-            
-            if input != "y" && input != "yes" {
-                println!("❌ Edit cancelled.");
+            outln!(output, "Configuration '{}' not found.", alias);
+            let suggestions = find_similar_configs(&alias, &config_manager.list_names_and_aliases()?);
+            if !suggestions.is_empty() {
+                outln!(output, "Did you mean: {}?", suggestions.join(", "));
+            }
+            if !TerminalPrompter::new(output).confirm(output, "Would you like to create a new configuration with this name?", false)? {
+                outln!(output, "❌ Edit cancelled.");
                 return Ok(());
             }
             
             // Create new configuration
-            use crate::config::EnvConfig;
+            use crate::config::{EnvConfig, ConfigSource};
             use std::collections::HashMap;
             use chrono::Utc;
             
@@ -399,267 +1630,133 @@ pub fn handle_edit_command(
                 description: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
+                aliases: Vec::new(),
+                pinned: false,
+                protected: false,
+                notes: None,
+                links: Vec::new(),
+                source: Some(ConfigSource::Manual),
+                expires_at: None,
+                list_ops: HashMap::new(),
+                template: false,
+                archived: false,
+                variable_comments: HashMap::new(),
             }
         }
     };
     
     let original_variables = config.variables.clone();
     let original_description = config.description.clone();
+    let original_notes = config.notes.clone();
+    let original_links = config.links.clone();
+    let original_comments = config.variable_comments.clone();
     
-    println!();
-    println!("📝 Editing configuration: {}", config.alias);
+    outln!(output);
+    outln!(output, "📝 Editing configuration: {}", config.alias);
     if let Some(desc) = &config.description {
-        println!("   Description: {}", desc);
+        outln!(output, "   Description: {}", desc);
+    }
+    if let Some(notes) = &config.notes {
+        outln!(output, "   Notes: {}", notes);
     }
-    println!();
+    for link in &config.links {
+        outln!(output, "   Link: {}", link);
+    }
+    outln!(output);
     
-    loop {
-        // Display current variables
-        if config.variables.is_empty() {
-            println!("📋 Current variables: (none)");
-        } else {
-            println!("📋 Current variables:");
-            let mut sorted_vars: Vec<_> = config.variables.iter().collect();
-            sorted_vars.sort_by_key(|(k, _)| *k);
-            
-            for (i, (key, value)) in sorted_vars.iter().enumerate() {
-                let display_value = if is_sensitive_key(key) {
-                    mask_sensitive_value(value)
-                } else {
-                    value.to_string()
-                };
-                println!("   {}. {} = {}", i + 1, key, display_value);
-            }
-        }
-        
-        println!();
-        println!("Actions:");
-        println!("   [a]dd     - Add a new variable");
-        println!("   [e]dit    - Edit an existing variable");
-        println!("   [d]elete  - Delete a variable");
-        println!("   [desc]    - Edit description");
-        println!("   [s]ave    - Save changes and exit");
-        println!("   [q]uit    - Quit without saving");
-        println!();
-        print!("> ");
-        io::Write::flush(&mut io::stdout())?;
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
-        
-        match input.as_str() {
-            "a" | "add" => {
-                println!();
-                print!("Enter variable name: ");
-                io::Write::flush(&mut io::stdout())?;
-                
-                let mut key = String::new();
-                io::stdin().read_line(&mut key)?;
-                let key = key.trim().to_string();
-                
-                if key.is_empty() {
-                    println!("❌ Variable name cannot be empty.");
-                    continue;
-                }
-                
-                if config.variables.contains_key(&key) {
-                    println!("⚠️  Variable '{}' already exists. Use 'edit' to modify it.", key);
-                    continue;
-                }
-                
-                print!("Enter variable value: ");
-                io::Write::flush(&mut io::stdout())?;
-                
-                let mut value = String::new();
-                io::stdin().read_line(&mut value)?;
-                let value = value.trim().to_string();
-                
-                config.variables.insert(key.clone(), value);
-                println!("✅ Added variable '{}'", key);
-            }
-            
-            "e" | "edit" => {
-                if config.variables.is_empty() {
-                    println!("❌ No variables to edit. Use 'add' to create variables first.");
-                    continue;
-                }
-                
-                println!();
-                print!("Enter variable name to edit: ");
-                io::Write::flush(&mut io::stdout())?;
-                
-                let mut key = String::new();
-                io::stdin().read_line(&mut key)?;
-                let key = key.trim().to_string();
-                
-                if let Some(current_value) = config.variables.get(&key) {
-                    let display_value = if is_sensitive_key(&key) {
-                        mask_sensitive_value(current_value)
-                    } else {
-                        current_value.clone()
-                    };
-                    
-                    println!("Current value: {}", display_value);
-                    print!("Enter new value (or press Enter to keep current): ");
-                    io::Write::flush(&mut io::stdout())?;
-                    
-                    let mut value = String::new();
-                    io::stdin().read_line(&mut value)?;
-                    let value = value.trim();
-                    
-                    if !value.is_empty() {
-                        config.variables.insert(key.clone(), value.to_string());
-                        println!("✅ Updated variable '{}'", key);
-                    } else {
-                        println!("⏭️  Variable '{}' unchanged", key);
-                    }
-                } else {
-                    println!("❌ Variable '{}' not found.", key);
-                }
-            }
-            
-            "d" | "delete" => {
-                if config.variables.is_empty() {
-                    println!("❌ No variables to delete.");
-                    continue;
-                }
-                
-                println!();
-                print!("Enter variable name to delete: ");
-                io::Write::flush(&mut io::stdout())?;
-                
-                let mut key = String::new();
-                io::stdin().read_line(&mut key)?;
-                let key = key.trim().to_string();
-                
-                if config.variables.remove(&key).is_some() {
-                    println!("✅ Deleted variable '{}'", key);
-                } else {
-                    println!("❌ Variable '{}' not found.", key);
-                }
-            }
-            
-            "desc" | "description" => {
-                println!();
-                if let Some(current_desc) = &config.description {
-                    println!("Current description: {}", current_desc);
-                }
-                print!("Enter new description (or press Enter to clear): ");
-                io::Write::flush(&mut io::stdout())?;
-                
-                let mut desc = String::new();
-                io::stdin().read_line(&mut desc)?;
-                let desc = desc.trim();
-                
-                if desc.is_empty() {
-                    config.description = None;
-                    println!("✅ Description cleared");
-                } else {
-                    config.description = Some(desc.to_string());
-                    println!("✅ Description updated");
-                }
-            }
-            
-            "s" | "save" => {
-                // Validate configuration before saving
-                if config.variables.is_empty() {
-                    println!("⚠️  Configuration has no variables. Save anyway? [y/N]: ");
-                    io::Write::flush(&mut io::stdout())?;
-                    
-                    let mut confirm = String::new();
-                    io::stdin().read_line(&mut confirm)?;
-                    let confirm = confirm.trim().to_lowercase();
-                    
-                    if confirm != "y" && confirm != "yes" {
-                        println!("❌ Save cancelled. Add some variables first.");
-                        continue;
-                    }
-                }
-                
-                if verbose {
-                    println!("💾 Saving configuration...");
-                }
-                
-                // Update timestamp
-                config.updated_at = chrono::Utc::now();
-                
-                // Save the configuration - check if it's a new config or existing one
-                let existing_config = config_manager.get_config(&config.alias)?;
-                if existing_config.is_some() {
-                    // Update existing configuration
-                    config_manager.update_config(
-                        config.alias.clone(),
-                        config.variables.clone(),
-                        config.description.clone(),
-                    )?;
-                } else {
-                    // Create new configuration
-                    config_manager.create_config(
-                        config.alias.clone(),
-                        config.variables.clone(),
-                        config.description.clone(),
-                    )?;
-                }
-                
-                println!("✅ Configuration '{}' saved successfully!", config.alias);
-                
-                // Show summary of changes
-                let mut changes = Vec::new();
-                
-                // Check for added variables
-                for key in config.variables.keys() {
-                    if !original_variables.contains_key(key) {
-                        changes.push(format!("+ {}", key));
-                    }
-                }
-                
-                // Check for modified variables
-                for (key, value) in &config.variables {
-                    if let Some(original_value) = original_variables.get(key) {
-                        if original_value != value {
-                            changes.push(format!("~ {}", key));
-                        }
-                    }
-                }
-                
-                // Check for removed variables
-                for key in original_variables.keys() {
-                    if !config.variables.contains_key(key) {
-                        changes.push(format!("- {}", key));
-                    }
-                }
-                
-                // Check for description changes
-                if original_description != config.description {
-                    changes.push("~ description".to_string());
-                }
-                
-                if !changes.is_empty() {
-                    println!("📝 Changes made: {}", changes.join(", "));
-                } else {
-                    println!("📝 No changes made");
-                }
-                
-                println!("📊 Total variables: {}", config.variables.len());
-                break;
-            }
-            
-            "q" | "quit" => {
-                println!("❌ Edit cancelled. No changes saved.");
-                break;
-            }
-            
-            _ => {
-                println!("❌ Invalid option. Please choose from the available actions.");
-            }
-        }
-        
-        println!();
+    let editor = VariableEditor::new(EditableConfig {
+        variables: config.variables.clone(),
+        description: config.description.clone(),
+        notes: config.notes.clone(),
+        links: config.links.clone(),
+        comments: config.variable_comments.clone(),
+    }).with_details();
+    let Some(edited) = editor.run(output, &mut TerminalPrompter::new(output))? else {
+        return Ok(());
+    };
+    config.variables = edited.variables;
+    config.description = edited.description;
+    config.notes = edited.notes;
+    config.links = edited.links;
+    config.variable_comments = edited.comments;
+    
+    if verbose {
+        outln!(output, "💾 Saving configuration...");
+    }
+    
+    if !keep_whitespace {
+        trim_values_with_notice(output, &config.alias, &mut config.variables);
+    }
+    
+    // Update timestamp
+    config.updated_at = chrono::Utc::now();
+    
+    // Save the configuration - check if it's a new config or existing one
+    let existing_config = config_manager.get_config(&config.alias)?;
+    if existing_config.is_some() {
+        // Update existing configuration
+        config_manager.update_config(
+            config.alias.clone(),
+            config.variables.clone(),
+            config.description.clone(),
+        )?;
+    } else {
+        // Create new configuration
+        config_manager.create_config(
+            config.alias.clone(),
+            config.variables.clone(),
+            config.description.clone(),
+        )?;
+    }
+    
+    if original_notes != config.notes || original_links != config.links {
+        config_manager.set_config_details(&config.alias, config.notes.clone(), config.links.clone())?;
+    }
+    if original_comments != config.variable_comments {
+        config_manager.set_config_variable_comments(&config.alias, config.variable_comments.clone())?;
+    }
+    
+    outln!(output, "✅ Configuration '{}' saved successfully!", config.alias);
+    let edited: HashMap<String, String> = config.variables.iter()
+        .filter(|(key, value)| original_variables.get(*key) != Some(*value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    warn_suspicious_tokens(output, &config.alias, &edited);
+    
+    // Show summary of changes
+    let mut changes: Vec<String> = diff_variables(&original_variables, &config.variables)
+        .iter()
+        .map(|change| match change {
+            VariableChange::Added { key, .. } => format!("+ {}", key),
+            VariableChange::Changed { key, .. } => format!("~ {}", key),
+            VariableChange::Removed { key, .. } => format!("- {}", key),
+        })
+        .collect();
+    
+    // Check for description changes
+    if original_description != config.description {
+        changes.push("~ description".to_string());
+    }
+    if original_notes != config.notes {
+        changes.push("~ notes".to_string());
+    }
+    if original_links != config.links {
+        changes.push("~ links".to_string());
+    }
+    if original_comments != config.variable_comments {
+        changes.push("~ comments".to_string());
+    }
+    
+    if !changes.is_empty() {
+        outln!(output, "📝 Changes made: {}", changes.join(", "));
+    } else {
+        outln!(output, "📝 No changes made");
     }
     
+    outln!(output, "📊 Total variables: {}", config.variables.len());
+    
     if verbose {
-        println!("✅ Edit operation completed.");
+        outln!(output, "✅ Edit operation completed.");
     }
     
     Ok(())
@@ -667,13 +1764,16 @@ pub fn handle_edit_command(
 
 /// Handle the delete command to remove a configuration
 pub fn handle_delete_command(
+    output: &OutputContext,
     config_manager: &FileConfigManager,
     alias: String,
     force: bool,
+    dry_run: bool,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let start_time = Instant::now();
     if verbose {
-        println!("🗑️  Starting delete operation for configuration '{}'...", alias);
+        outln!(output, "🗑️  Starting delete operation for configuration '{}'...", alias);
     }
     
     // Validate alias
@@ -681,81 +1781,95 @@ pub fn handle_delete_command(
         return Err("Configuration name cannot be empty. Please specify which configuration to delete.".into());
     }
     
+    // Resolve aliases to the canonical configuration name
+    let alias = config_manager.resolve_config_name(&alias)?.unwrap_or(alias);
+    
     // Check if configuration exists
     let config = config_manager.get_config(&alias)?;
     let config = match config {
         Some(config) => config,
-        None => {
-            let available_configs = config_manager.list_configs()?;
-            if available_configs.is_empty() {
-                return Err("No configurations exist to delete.".into());
-            }
-            
-            // Find similar configuration names
-            let suggestions = find_similar_configs(&alias, &available_configs);
-            if suggestions.is_empty() {
-                return Err(format!(
-                    "Configuration '{}' not found.\nAvailable configurations: {}\n💡 Use 'envswitch list' to see all configurations",
-                    alias, available_configs.join(", ")
-                ).into());
-            } else {
-                return Err(format!(
-                    "Configuration '{}' not found.\nDid you mean: {}?\nAvailable configurations: {}",
-                    alias, suggestions.join(", "), available_configs.join(", ")
-                ).into());
-            }
-        }
+        None => return Err(config_not_found(&alias, &config_manager.list_names_and_aliases()?).into()),
     };
     
+    if config.protected && !force && !dry_run {
+        return Err(format!(
+            "Configuration '{}' is protected. Use --force to delete it anyway, or 'envswitch unprotect {}'",
+            alias, alias
+        ).into());
+    }
+    
     // Check if this is the active configuration
     let active_config = config_manager.get_active_config()?;
     let is_active = active_config.as_deref() == Some(&alias);
+    let is_default = config_manager.get_settings()?.default_config.as_deref() == Some(&alias);
     
     if verbose {
-        println!("📋 Configuration details:");
-        println!("   Name: {}", config.alias);
-        println!("   Description: {}", config.description.as_deref().unwrap_or("No description"));
-        println!("   Variables: {}", config.variables.len());
-        println!("   Created: {}", config.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
-        println!("   Updated: {}", config.updated_at.format("%Y-%m-%d %H:%M:%S UTC"));
+        outln!(output, "📋 {}", msg!(output, "delete.details"));
+        outln!(output, "   Name: {}", config.alias);
+        outln!(output, "   Description: {}", config.description.as_deref().unwrap_or("No description"));
+        outln!(output, "   Variables: {}", config.variables.len());
+        if !config.aliases.is_empty() {
+            outln!(output, "   Aliases: {}", config.aliases.join(", "));
+        }
+        if let Some(source) = &config.source {
+            outln!(output, "   Source: {}", source.describe(output));
+        }
+        outln!(output, "   Created: {}", format_timestamp(output, config.created_at));
+        outln!(output, "   Updated: {}", format_timestamp(output, config.updated_at));
+        if is_active {
+            outln!(output, "   Status: ⭐ Currently active");
+        }
+    }
+    
+    if dry_run {
+        outln!(output, "🔍 {}", msg!(output, "delete.dry_run", alias = alias));
+        outln!(output, "   Variables: {}", config.variables.len());
+        if !config.aliases.is_empty() {
+            outln!(output, "   Aliases removed with it: {}", config.aliases.join(", "));
+        }
         if is_active {
-            println!("   Status: ⭐ Currently active");
+            outln!(output, "   The active configuration would be cleared");
+        }
+        if is_default {
+            outln!(output, "   The default configuration for new shells would be cleared");
         }
+        outln!(output, "   Backups: none (delete doesn't create one; run 'envswitch backup create' first to keep a copy)");
+        if config_manager.get_settings()?.git_track {
+            outln!(output, "   The deletion would be committed to the git history");
+        }
+        outln!(output, "   {}", msg!(output, "delete.nothing_changed"));
+        return Ok(());
     }
     
     // Confirmation prompt unless force flag is used
     if !force {
-        println!("⚠️  Delete configuration '{}'? This cannot be undone.", alias);
-        println!("   Variables: {} ({})", 
+        outln!(output, "⚠️  {}", msg!(output, "delete.confirm", alias = alias));
+        outln!(output, "   Variables: {} ({})", 
             config.variables.len(),
             config.variables.keys().take(3).cloned().collect::<Vec<_>>().join(", ")
         );
         if config.variables.len() > 3 {
-            println!("   ... and {} more", config.variables.len() - 3);
+            outln!(output, "   ... and {} more", config.variables.len() - 3);
         }
-        println!("   Created: {}", config.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+        outln!(output, "   Created: {}", format_timestamp(output, config.created_at));
         
         if is_active {
-            println!("   ⚠️  This is your currently active configuration!");
-            println!("   Deleting it will clear your active configuration.");
+            outln!(output, "   ⚠️  {}", msg!(output, "delete.is_active"));
+            outln!(output, "   {}", msg!(output, "delete.clears_active"));
+        }
+        if is_default {
+            outln!(output, "   ⚠️  {}", msg!(output, "delete.is_default"));
         }
         
-        println!();
-        print!("Continue? [y/N]: ");
-        std::io::Write::flush(&mut std::io::stdout())?;
-        
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
-        
-        if input != "y" && input != "yes" {
-            println!("❌ Deletion cancelled.");
+        outln!(output);
+        if !prompt_confirmation(output, "Continue?", false)? {
+            outln!(output, "❌ {}", msg!(output, "delete.cancelled"));
             return Ok(());
         }
     }
     
     if verbose {
-        println!("🗑️  Deleting configuration '{}'...", alias);
+        outln!(output, "🗑️  Deleting configuration '{}'...", alias);
     }
     
     // Perform the deletion
@@ -764,31 +1878,70 @@ pub fn handle_delete_command(
     // Clear active configuration if we deleted the active one
     if is_active {
         if verbose {
-            println!("🔄 Clearing active configuration...");
+            outln!(output, "🔄 Clearing active configuration...");
         }
         config_manager.clear_active_config()?;
     }
     
     // Success message
-    println!("✅ Configuration '{}' deleted successfully!", alias);
+    outln!(output, "✅ {}", msg!(output, "delete.deleted", alias = alias));
     
     if is_active {
-        println!("🔄 Active configuration cleared.");
-        println!("💡 Use 'envswitch use <config>' to activate another configuration.");
+        outln!(output, "🔄 {}", msg!(output, "delete.active_cleared"));
+        outln!(output, "💡 {}", msg!(output, "delete.use_another"));
+    }
+    if is_default {
+        eoutln!(output, "⚠️  '{}' was the default configuration; new shells no longer apply one.", alias);
+        eoutln!(output, "💡 Choose another with: envswitch default set <config>");
     }
     
     // Show remaining configurations
     let remaining_configs = config_manager.list_configs()?;
     if remaining_configs.is_empty() {
-        println!("📭 No configurations remaining.");
-        println!("💡 Create a new configuration with: envswitch set <name> -e KEY=value");
+        outln!(output, "📭 {}", msg!(output, "delete.none_remaining"));
+        outln!(output, "💡 {}", msg!(output, "delete.create_new"));
     } else {
-        println!("📋 Remaining configurations: {}", remaining_configs.join(", "));
+        outln!(output, "📋 {}", msg!(output, "delete.remaining", names = remaining_configs.join(", ")));
     }
     
     if verbose {
-        println!("✅ Delete operation completed successfully.");
+        outln!(output, "✅ {}", msg!(output, "delete.completed"));
     }
     
+    let report = OperationReport::new("Delete", 1, start_time.elapsed())
+        .with_detail("Configuration", &alias)
+        .with_detail("Variables", config.variables.len())
+        .with_detail("Active cleared", if is_active { "yes" } else { "no" })
+        .with_detail("Remaining configurations", remaining_configs.len());
+    display_operation_report(output, &report);
+    
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_guard_threshold() {
+        // Replacing `existing` variables with `kept` of them plus one new one
+        let summary = |existing: usize, kept: usize| {
+            let mut config = EnvConfig::new("big".to_string(), HashMap::new(), None).unwrap();
+            config.variables = (0..existing).map(|i| (format!("VAR_{}", i), "1".to_string())).collect();
+            let mut variables: HashMap<String, String> = (0..kept).map(|i| (format!("VAR_{}", i), "1".to_string())).collect();
+            variables.insert("NEW".to_string(), "1".to_string());
+            SetSummary::new("big", Some(&config), &variables)
+        };
+        
+        // Half of them is fine, one more is not
+        assert!(!summary(4, 2).removes_too_many(Some(10)));
+        assert!(summary(4, 1).removes_too_many(Some(10)));
+        // Ten keys are fine however large the configuration, eleven are not
+        assert!(!summary(30, 20).removes_too_many(Some(10)));
+        assert!(summary(30, 19).removes_too_many(Some(10)));
+        assert!(!summary(30, 25).removes_too_many(Some(5)));
+        assert!(summary(30, 24).removes_too_many(Some(5)));
+        // Turned off, nothing is too many
+        assert!(!summary(30, 0).removes_too_many(None));
+    }
+}
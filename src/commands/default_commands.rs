@@ -0,0 +1,92 @@
+use crate::cli::DefaultAction;
+use crate::config::{FileConfigManager, ConfigManager};
+use crate::env::{ShellEnvironmentManager, resolve_lazy_references};
+use crate::shell::ShellDetector;
+use crate::types::constants::DEFAULT_MARKER_VAR;
+use std::error::Error;
+use crate::output::OutputContext;
+
+/// Handle the default command to choose the configuration applied to new shells
+pub fn handle_default_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    action: DefaultAction,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    match action {
+        DefaultAction::Set { alias } => {
+            let name = config_manager.set_default_config(Some(&alias))?.unwrap_or(alias);
+            outln!(output, "✅ '{}' is now the default configuration for new shells", name);
+            outln!(output, "💡 Add this line to your shell rc file: eval \"$(envswitch shellenv --activate)\"");
+            if verbose {
+                outln!(output, "   Shells that already ran 'envswitch use' keep their configuration");
+            }
+        }
+        DefaultAction::Clear => {
+            match config_manager.get_settings()?.default_config {
+                Some(previous) => {
+                    config_manager.set_default_config(None)?;
+                    outln!(output, "✅ '{}' is no longer the default configuration", previous);
+                }
+                None => outln!(output, "ℹ️  No default configuration is set"),
+            }
+        }
+        DefaultAction::Show => {
+            match config_manager.get_settings()?.default_config {
+                Some(name) => outln!(output, "Default configuration: {}", name),
+                None => {
+                    outln!(output, "No default configuration");
+                    outln!(output, "💡 Set one with: envswitch default set <config>");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle the shellenv command printing shell code for an rc file
+///
+/// With `activate`, exports the default configuration plus a marker variable so
+/// nested shells, which inherit both, don't apply it again. Problems are reported
+/// on stderr and produce no output, so a broken default never stops a shell from starting.
+pub fn handle_shellenv_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    shell: Option<String>,
+    activate: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !activate || std::env::var_os(DEFAULT_MARKER_VAR).is_some() {
+        return Ok(());
+    }
+    let Some(name) = config_manager.get_settings()?.default_config else {
+        return Ok(());
+    };
+
+    let env_manager = match shell {
+        Some(shell) => ShellEnvironmentManager::with_shell_type(ShellDetector::shell_from_name(&shell)),
+        None => ShellEnvironmentManager::new(),
+    };
+
+    let commands = config_manager.get_config(&name)?
+        .ok_or_else(|| format!("Default configuration '{}' not found", name))
+        .and_then(|config| {
+            let lazy_allowed = config_manager.get_settings().map_err(|e| e.to_string())?.lazy_secrets;
+            let mut variables = resolve_lazy_references(&config.variables, lazy_allowed).map_err(|e| e.to_string())?;
+            variables.insert(DEFAULT_MARKER_VAR.to_string(), name.clone());
+            env_manager.generate_config_commands(&variables, &config.list_ops).map_err(|e| e.to_string())
+        });
+
+    match commands {
+        Ok(commands) => {
+            println!("{}", commands);
+            if verbose {
+                eoutln!(output, "🔄 Applied default configuration '{}'", name);
+            }
+        }
+        Err(e) => eoutln!(output, "⚠️  envswitch: default configuration not applied: {}", e),
+    }
+
+    Ok(())
+}
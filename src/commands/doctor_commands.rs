@@ -0,0 +1,85 @@
+use crate::config::{FileConfigManager, ConfigManager};
+use crate::utils::prompt_confirmation;
+use std::error::Error;
+use std::io::IsTerminal;
+use crate::output::OutputContext;
+
+/// Handle the doctor command to report problems in the configuration store
+pub fn handle_doctor_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    repair: bool,
+    keep: Option<String>,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    if verbose {
+        outln!(output, "📁 Store: {}", config_manager.config_file_path().display());
+    }
+
+    // A mismatch makes the store fail validation, so it is checked before loading
+    let mismatches = config_manager.alias_mismatches()?;
+    let mut problems = mismatches.len();
+    for (key, alias) in &mismatches {
+        outln!(output, "⚠️  Configuration '{}' is stored under the name '{}'", alias, key);
+    }
+    if !mismatches.is_empty() {
+        if !repair {
+            outln!(output, "   💡 Choose which name to keep with: envswitch doctor --repair");
+            outln!(output, "\n📊 {} problem(s) found", problems);
+            return Ok(());
+        }
+        let repairs = mismatch_repairs(output, &mismatches, keep.as_deref())?;
+        for name in config_manager.repair_alias_mismatches(&repairs)? {
+            outln!(output, "✅ Repaired '{}'", name);
+        }
+    }
+
+    let store = config_manager.load_configs()?;
+
+    for group in store.case_collisions() {
+        problems += 1;
+        outln!(output, "⚠️  Names differ only by case: {}", group.join(", "));
+
+        // Keep the first name and suggest new ones for the rest
+        for name in group.iter().skip(1) {
+            let suggestion = store.suggest_distinct_name(name);
+            if store.configs.contains_key(name) {
+                outln!(
+                    output,
+                    "   💡 Rename '{}' to '{}': create it with the same variables, then run 'envswitch delete {}'",
+                    name, suggestion, name
+                );
+            } else {
+                outln!(
+                    output,
+                    "   💡 '{}' is an alias; replace it with: envswitch alias remove {} && envswitch alias add <config> {}",
+                    name, name, suggestion
+                );
+            }
+        }
+    }
+
+    if problems == 0 {
+        outln!(output, "✅ No problems found in {} configurations", store.configs.len());
+    } else {
+        outln!(output, "\n📊 {} problem(s) found", problems);
+    }
+
+    Ok(())
+}
+
+/// Which name to keep for each mismatch, as (key, keep the key): from --keep, or asked for
+fn mismatch_repairs(output: &OutputContext, mismatches: &[(String, String)], keep: Option<&str>) -> Result<Vec<(String, bool)>, Box<dyn Error>> {
+    if let Some(keep) = keep {
+        return Ok(mismatches.iter().map(|(key, _)| (key.clone(), keep == "key")).collect());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err("Choose the name to keep with --keep key or --keep alias".into());
+    }
+    mismatches.iter()
+        .map(|(key, alias)| {
+            let keep_key = prompt_confirmation(output, &format!("Keep the name '{}' (no renames it to '{}')?", key, alias), true)?;
+            Ok((key.clone(), keep_key))
+        })
+        .collect()
+}
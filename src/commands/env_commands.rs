@@ -0,0 +1,191 @@
+use crate::config::{EnvConfig, FileConfigManager, ConfigManager};
+use crate::env::{resolve_lazy_references, resolve_variables, EnvironmentManager, ShellEnvironmentManager};
+use crate::shell::{ShellDetector, ShellType};
+use crate::utils::{config_not_found, config_not_found_message, format_env_line};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use crate::output::OutputContext;
+
+/// First comment line of scripts written by `render`, used to recognise them before overwriting
+const RENDER_HEADER: &str = "# Generated by envswitch render";
+
+/// Handle the env command to print a configuration's variables for scripts
+///
+/// Returns false, after explaining why on stderr, when there is no such configuration.
+pub fn handle_env_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    alias: Option<String>,
+    null: bool,
+    json: bool,
+) -> Result<bool, Box<dyn Error>> {
+    let name = match alias {
+        Some(alias) => config_manager.resolve_config_name(&alias)?.unwrap_or(alias),
+        None => match config_manager.get_active_config()? {
+            Some(active) => active,
+            None => {
+                eoutln!(output, "No active configuration.\n💡 Activate one with: {}", crate::examples::activation_command("<config>"));
+                return Ok(false);
+            }
+        },
+    };
+
+    let Some(config) = config_manager.get_config(&name)? else {
+        eoutln!(output, "{}", config_not_found_message(
+            &name,
+            &config_manager.list_configs()?,
+            &config_manager.list_names_and_aliases()?,
+        ));
+        return Ok(false);
+    };
+
+    let lazy_allowed = config_manager.get_settings()?.lazy_secrets;
+    let variables: BTreeMap<String, String> = resolve_variables(&resolve_lazy_references(&config.variables, lazy_allowed)?)?
+        .into_iter()
+        .collect();
+
+    // Build everything first so a failure never leaves partial output behind
+    let output = if json {
+        format!("{}\n", serde_json::to_string_pretty(&variables)?)
+    } else if null {
+        variables.iter().map(|(key, value)| format!("{}={}\0", key, value)).collect()
+    } else {
+        variables.iter().map(|(key, value)| format!("{}\n", format_env_line(key, value))).collect()
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(output.as_bytes())?;
+    stdout.flush()?;
+    Ok(true)
+}
+
+/// Handle the render command writing a configuration's export commands to a script file
+///
+/// Renders the given configuration, or the active one, for the given or detected shell.
+/// The script gets a shebang and a generated-by header and is made executable by its
+/// owner only, since it holds the values in clear text. Files without the header are
+/// only overwritten with `force`.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_render_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    output_file: String,
+    shell: Option<String>,
+    alias: Option<String>,
+    unset_script: Option<String>,
+    force: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let name = match alias {
+        Some(alias) => config_manager.resolve_config_name(&alias)?.unwrap_or(alias),
+        None => config_manager.get_active_config()?.ok_or_else(|| {
+            format!("No active configuration to render.\n💡 Choose one with: envswitch render --output {} --alias <config>", output_file)
+        })?,
+    };
+    let config = config_manager.get_config(&name)?
+        .ok_or_else(|| config_not_found(&name, &config_manager.list_names_and_aliases().unwrap_or_default()))?;
+
+    let env_manager = match &shell {
+        Some(shell) => ShellEnvironmentManager::with_shell_type(ShellDetector::shell_from_name(shell)),
+        None => ShellEnvironmentManager::new(),
+    };
+    let shell_type = env_manager.get_shell_type().clone();
+
+    // Check every target before writing anything
+    let targets: Vec<&str> = std::iter::once(output_file.as_str()).chain(unset_script.as_deref()).collect();
+    for target in &targets {
+        check_render_target(Path::new(target), force)?;
+    }
+
+    // `@op:` and `@cmd:` references run once here; the script holds their values
+    let lazy_allowed = config_manager.get_settings()?.lazy_secrets;
+    let variables = resolve_lazy_references(&config.variables, lazy_allowed)?;
+    let commands = env_manager.generate_config_commands(&variables, &config.list_ops)?;
+    let script = render_script(&shell_type, &config, "Sets", &commands);
+    write_render_script(Path::new(&output_file), &script)?;
+    outln!(output, "✅ Wrote the {} script for '{}' to {}", shell_type, name, output_file);
+
+    if let Some(unset_script) = &unset_script {
+        let commands = render_unset_commands(&env_manager, &config)?;
+        let script = render_script(&shell_type, &config, "Unsets", &commands);
+        write_render_script(Path::new(unset_script), &script)?;
+        outln!(output, "✅ Wrote the unset script for '{}' to {}", name, unset_script);
+    }
+
+    if verbose {
+        outln!(output, "📊 {} variable(s), run with: . {}", variables.len(), output_file);
+    }
+    outln!(output, "⚠️  The script contains the values in clear text; keep it out of version control");
+    Ok(())
+}
+
+/// Refuse to overwrite a file that `render` didn't write, unless forced
+fn check_render_target(path: &Path, force: bool) -> Result<(), Box<dyn Error>> {
+    if !path.exists() || force {
+        return Ok(());
+    }
+    let generated = std::fs::read_to_string(path)
+        .map(|content| content.lines().take(3).any(|line| line.starts_with(RENDER_HEADER)))
+        .unwrap_or(false);
+    if !generated {
+        return Err(format!(
+            "'{}' exists and wasn't generated by envswitch render.\n💡 Pass --force to overwrite it",
+            path.display()
+        ).into());
+    }
+    Ok(())
+}
+
+/// Commands unsetting what the rendered script set; list variables only lose the added entries
+fn render_unset_commands(env_manager: &ShellEnvironmentManager, config: &EnvConfig) -> Result<String, Box<dyn Error>> {
+    let mut command_groups = Vec::new();
+    let list_variables: HashMap<String, String> = config.variables.iter()
+        .filter(|(key, _)| config.list_ops.contains_key(*key))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if !list_variables.is_empty() {
+        command_groups.push(env_manager.generate_list_removal_commands(&list_variables, &config.list_ops)?);
+    }
+    let mut keys: Vec<String> = config.variables.keys()
+        .filter(|key| !config.list_ops.contains_key(*key))
+        .cloned()
+        .collect();
+    keys.sort();
+    if !keys.is_empty() {
+        command_groups.push(env_manager.generate_unset_commands(&keys)?);
+    }
+    Ok(command_groups.join("\n"))
+}
+
+/// Wrap commands in a script with a shebang and the generated-by header
+fn render_script(shell_type: &ShellType, config: &EnvConfig, action: &str, commands: &str) -> String {
+    let shebang = match shell_type {
+        ShellType::Bash => "#!/usr/bin/env bash",
+        ShellType::Zsh => "#!/usr/bin/env zsh",
+        ShellType::Fish => "#!/usr/bin/env fish",
+        ShellType::Unknown(_) => "#!/bin/sh",
+    };
+    let mut script = format!("{}\n{} from configuration '{}'\n", shebang, RENDER_HEADER, config.alias);
+    script.push_str(&format!("# Generated on {}\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
+    if let Some(description) = &config.description {
+        script.push_str(&format!("# {}\n", description));
+    }
+    script.push_str(&format!("# {} {} variable(s); source it (. ./script) so it affects the current shell.\n", action, config.variables.len()));
+    script.push_str("# Regenerate with envswitch render instead of editing by hand.\n\n");
+    script.push_str(commands);
+    script.push('\n');
+    script
+}
+
+/// Write a rendered script, readable and executable by its owner only
+fn write_render_script(path: &Path, script: &str) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use crate::config::{FileConfigManager, ConfigManager};
+use crate::utils::{confirm_on_stderr, format_timestamp, is_sensitive_key, mask_sensitive_value, prompt_confirmation};
+use std::error::Error;
+use crate::output::OutputContext;
+
+/// Handle the log command to show the configuration history
+pub fn handle_log_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    limit: usize,
+    _verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let entries = config_manager.history(limit)?;
+
+    if entries.is_empty() {
+        if config_manager.get_settings()?.git_track {
+            outln!(output, "No history recorded yet");
+        } else {
+            outln!(output, "Configuration history is disabled");
+            outln!(output, "💡 Enable it with: envswitch settings set git-track true");
+        }
+        return Ok(());
+    }
+
+    for entry in entries {
+        outln!(output, "{}", entry);
+    }
+
+    Ok(())
+}
+
+/// Handle the history-var command to show how one variable changed across the history
+pub fn handle_history_var_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    alias: String,
+    key: String,
+    reveal: bool,
+    json: bool,
+    _verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    // Configurations deleted since keep their history under their old name
+    let name = config_manager.load_configs()?.resolve_name(&alias).unwrap_or(alias);
+    let changes = config_manager.variable_history(&name, &key)?;
+
+    if changes.is_empty() && !json {
+        if config_manager.get_settings()?.git_track {
+            outln!(output, "No changes to {} recorded in the history of '{}'", key, name);
+        } else {
+            outln!(output, "No history available: configuration history is disabled");
+            outln!(output, "💡 Enable it with: envswitch settings set git-track true");
+        }
+        return Ok(());
+    }
+
+    let masked = is_sensitive_key(&key);
+    if reveal && masked && !confirm_on_stderr(output, &format!("Show the values of {} in plain text?", key))? {
+        outln!(output, "❌ Cancelled");
+        return Ok(());
+    }
+    let show = |value: &Option<String>| match value {
+        Some(value) if masked && !reveal => Some(mask_sensitive_value(output, value)),
+        value => value.clone(),
+    };
+
+    if json {
+        let changes: Vec<_> = changes.iter()
+            .map(|change| crate::diff::ValueChange { old: show(&change.old), new: show(&change.new), ..change.clone() })
+            .collect();
+        outln!(output, "{}", serde_json::to_string_pretty(&changes)?);
+        return Ok(());
+    }
+
+    outln!(output, "📋 {} in '{}' ({} changes, oldest first):", key, name, changes.len());
+    for change in &changes {
+        let old = show(&change.old).unwrap_or_else(|| "(unset)".to_string());
+        let new = show(&change.new).unwrap_or_else(|| "(unset)".to_string());
+        outln!(output, "  {}  {}  {} → {}", format_timestamp(output, change.timestamp), change.commit, old, new);
+    }
+
+    Ok(())
+}
+
+/// Handle the revert command to restore the store from a prior commit
+pub fn handle_revert_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    commit: String,
+    force: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !force {
+        let message = format!("Replace all configurations with their state at {}?", commit);
+        if !prompt_confirmation(output, &message, false)? {
+            outln!(output, "❌ Revert cancelled");
+            return Ok(());
+        }
+    }
+
+    let store = config_manager.revert_to_commit(&commit)?;
+    outln!(output, "✅ Configurations restored to {} ({} configurations)", commit, store.configs.len());
+
+    if verbose {
+        for name in store.list_aliases() {
+            outln!(output, "   {}", name);
+        }
+    }
+
+    if let Some(active) = config_manager.get_active_config()? {
+        outln!(output, "💡 Re-run 'envswitch use {}' to apply the restored values to your shell", active);
+    }
+
+    Ok(())
+}
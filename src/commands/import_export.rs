@@ -1,24 +1,52 @@
-use crate::config::{FileConfigManager, ConfigManager, ExportOptions, ExportFormat, ImportOptions, ImportFormat};
-use crate::utils::file_utils::{detect_file_format, validate_file_format, FileFormat};
+use crate::config::{FileConfigManager, ConfigManager, ExportBaseline, ExportOptions, ExportFormat, ImportOptions, ImportFormat, ImportResult, SettingChange, SettingsDocument, build_key_map, exported_settings, parse_key_map_file, partial_export_baseline};
+use crate::utils::file_utils::{convert_format, detect_file_format, validate_file_format, FileFormat, FlattenOptions};
+use crate::utils::{find_similar_configs, format_timestamp, parse_date, prompt_confirmation, prompt_typed_confirmation};
+use crate::handlers::{check_strict, collect_suspicious_tokens};
 use crate::utils::feedback::{
-    ProgressIndicator, display_error_with_suggestions, display_success_with_next_steps,
-    display_warning, display_operation_summary, display_file_operation_result,
-    display_verbose_info, format_file_size
+    ProgressIndicator, display_success_with_next_steps,
+    display_warning, display_operation_report, display_file_operation_result,
+    display_verbose_info, OperationReport, WarningAggregator
 };
 use std::error::Error;
 use std::path::Path;
 use std::time::Instant;
+use crate::output::OutputContext;
+
+/// Options of the export command, as given on the command line
+#[derive(Debug, Default)]
+pub struct ExportCommandOptions {
+    /// Output file; envswitch_export.json (or .sh for the shell format) when not given
+    pub output: Option<String>,
+    /// Configurations to export; all of them when empty
+    pub configs: Vec<String>,
+    /// "json", "env", "yaml" or "shell"
+    pub format: String,
+    pub metadata: bool,
+    pub pretty: bool,
+    pub resolve_files: bool,
+    pub checksum: bool,
+    pub no_checksum: bool,
+    pub since: Option<String>,
+    pub since_backup: Option<String>,
+    pub include_settings: bool,
+    /// Glob patterns of variables to leave out
+    pub exclude_keys: Vec<String>,
+    pub template: bool,
+    pub redact_secrets: bool,
+    pub exclude_archived: bool,
+}
 
 /// Handle the export command to export configurations to a file
 pub fn handle_export_command(
+    output: &OutputContext,
     config_manager: &FileConfigManager,
-    output: Option<String>,
-    configs: Vec<String>,
-    format: String,
-    metadata: bool,
-    pretty: bool,
+    options: ExportCommandOptions,
     verbose: bool,
 ) -> Result<(), Box<dyn Error>> {
+    let ExportCommandOptions {
+        output: output_file, configs, format, metadata, pretty, resolve_files, checksum, no_checksum, since, since_backup,
+        include_settings, exclude_keys, template, redact_secrets, exclude_archived,
+    } = options;
     let start_time = Instant::now();
     let mut progress = ProgressIndicator::new("🚀 Starting export operation");
     
@@ -27,13 +55,14 @@ pub fn handle_export_command(
     }
     
     // Determine output file path
-    let output_path = match output {
+    let output_path = match output_file {
         Some(path) => path,
+        None if format == "shell" => "envswitch_export.sh".to_string(),
         None => "envswitch_export.json".to_string(),
     };
     
     if verbose {
-        display_verbose_info("Export configuration", &[
+        display_verbose_info(output, "Export configuration", &[
             ("Output file", &output_path),
             ("Format", &format),
             ("Include metadata", &metadata.to_string()),
@@ -46,14 +75,28 @@ pub fn handle_export_command(
         "json" => ExportFormat::Json,
         "env" => ExportFormat::Env,
         "yaml" => ExportFormat::Yaml,
+        "shell" if template => {
+            return Err("--template doesn't apply to --format shell; leave secrets out with --redact-secrets".into());
+        }
+        "shell" => ExportFormat::Shell { redact_secrets },
         _ => {
-            let error = format!("Unsupported format '{}'. Supported formats: json, env, yaml", format);
+            let error = format!("Unsupported format '{}'. Supported formats: json, env, yaml, shell", format);
             if verbose {
-                progress.finish_error(&error);
+                progress.finish_error(output, &error);
             }
             return Err(error.into());
         }
     };
+    if redact_secrets && !matches!(export_format, ExportFormat::Shell { .. }) {
+        return Err("--redact-secrets only applies to --format shell; share other formats without secrets with --template".into());
+    }
+    
+    // Resolve aliases in the requested configuration names
+    let mut resolved_configs = Vec::with_capacity(configs.len());
+    for name in configs {
+        resolved_configs.push(config_manager.resolve_config_name(&name)?.unwrap_or(name));
+    }
+    let configs = resolved_configs;
     
     // Validate specific configurations if provided
     if !configs.is_empty() {
@@ -67,27 +110,32 @@ pub fn handle_export_command(
         }
         
         if !invalid_configs.is_empty() {
-            let error = format!(
-                "Configuration(s) not found: {}\nAvailable configurations: {}",
-                invalid_configs.join(", "),
-                available_configs.join(", ")
-            );
+            let candidates = config_manager.list_names_and_aliases()?;
+            let mut error = format!("Configuration(s) not found: {}", invalid_configs.join(", "));
+            for config in &invalid_configs {
+                let suggestions = find_similar_configs(config, &candidates);
+                if !suggestions.is_empty() {
+                    error.push_str(&format!("\n'{}': did you mean {}?", config, suggestions.join(", ")));
+                }
+            }
+            error.push_str(&format!("\nAvailable configurations: {}", available_configs.join(", ")));
             if verbose {
-                progress.finish_error(&error);
+                progress.finish_error(output, &error);
             }
             return Err(error.into());
         }
         
         if verbose {
-            println!("📋 Exporting {} specific configurations: {}", configs.len(), configs.join(", "));
+            outln!(output, "📋 {}", msg!(output, "export.specific", count = configs.len(), names = configs.join(", ")));
         }
     } else {
         let all_configs = config_manager.list_configs()?;
         if all_configs.is_empty() {
             if verbose {
-                progress.finish_warning("No configurations found to export");
+                progress.finish_warning(output, "No configurations found to export");
             }
             display_warning(
+                output,
                 "No configurations found to export",
                 Some(&["Create configurations first with: envswitch set <name> -e KEY=value"])
             );
@@ -95,19 +143,49 @@ pub fn handle_export_command(
         }
         
         if verbose {
-            display_verbose_info("Export scope", &[
+            display_verbose_info(output, "Export scope", &[
                 ("Total configurations", &all_configs.len().to_string()),
                 ("Configuration names", &all_configs.join(", ")),
             ]);
         }
     }
     
+    // Archived configurations are exported unless asked otherwise
+    let configs = if exclude_archived {
+        let store = config_manager.load_configs()?;
+        let (archived, kept): (Vec<String>, Vec<String>) = if configs.is_empty() { store.list_aliases() } else { configs }
+            .into_iter()
+            .partition(|name| store.get_config(name).is_some_and(|config| config.archived));
+        if kept.is_empty() {
+            display_warning(output, "Every configuration to export is archived", Some(&["Leave out --exclude-archived to export them anyway"]));
+            return Ok(());
+        }
+        if verbose && !archived.is_empty() {
+            outln!(output, "ℹ️  Leaving out {} archived configuration(s): {}", archived.len(), archived.join(", "));
+        }
+        kept
+    } else {
+        configs
+    };
+    
+    let baseline = match (since, since_backup) {
+        (Some(date), _) => Some(ExportBaseline::Since(parse_date(&date)?)),
+        (None, Some(backup)) => Some(ExportBaseline::Backup(config_manager.find_backup(&backup)?)),
+        (None, None) => None,
+    };
+    
     // Create export options
     let export_options = ExportOptions {
         format: export_format,
         include_metadata: metadata,
         pretty_print: pretty,
         configs: if configs.is_empty() { None } else { Some(configs.clone()) },
+        resolve_files,
+        checksum: checksum || (matches!(export_format, ExportFormat::Json) && !no_checksum),
+        baseline: baseline.clone(),
+        include_settings,
+        exclude_keys: excluded_key_patterns(config_manager, exclude_keys)?,
+        template,
     };
     
     // Create output directory if it doesn't exist
@@ -115,14 +193,14 @@ pub fn handle_export_command(
     if let Some(parent_dir) = output_path_obj.parent() {
         if !parent_dir.exists() {
             if verbose {
-                display_verbose_info("Creating directory", &[
+                display_verbose_info(output, "Creating directory", &[
                     ("Path", &parent_dir.display().to_string()),
                 ]);
                 progress.tick();
             }
             std::fs::create_dir_all(parent_dir).map_err(|e| {
                 if verbose {
-                    progress.finish_error("Failed to create directory");
+                    progress.finish_error(output, "Failed to create directory");
                 }
                 e
             })?;
@@ -134,9 +212,9 @@ pub fn handle_export_command(
         progress.tick();
     }
     
-    config_manager.export_to_file_with_options(output_path_obj, &export_options).map_err(|e| {
+    let export_result = config_manager.export_to_file_with_options(output_path_obj, &export_options).map_err(|e| {
         if verbose {
-            progress.finish_error("Export failed");
+            progress.finish_error(output, "Export failed");
         }
         e
     })?;
@@ -152,49 +230,61 @@ pub fn handle_export_command(
     };
     
     // Count exported configurations and variables
-    let store = config_manager.load_configs()?;
-    let exported_configs = if !configs.is_empty() {
-        configs.len()
-    } else {
-        store.configs.len()
-    };
-    
-    let total_variables: usize = if !configs.is_empty() {
-        configs.iter()
-            .filter_map(|name| store.configs.get(name))
-            .map(|config| config.variables.len())
-            .sum()
-    } else {
-        store.configs.values()
-            .map(|config| config.variables.len())
-            .sum()
-    };
+    let mut store = config_manager.load_configs()?;
+    if !configs.is_empty() {
+        store.configs.retain(|name, _| configs.contains(name));
+    }
+    if let Some(baseline) = &baseline {
+        let changed = config_manager.changed_since(&store, baseline)?;
+        store.configs.retain(|name, _| changed.contains(name));
+    }
+    let exported_configs = store.configs.len();
+    let total_variables: usize = store.configs.values()
+        .map(|config| config.variables.len())
+        .sum::<usize>() - export_result.excluded.len();
     
     let duration = start_time.elapsed();
     
     if verbose {
-        progress.finish_success("Export completed successfully");
+        progress.finish_success(output, "Export completed successfully");
     }
     
     // Display file operation result
-    display_file_operation_result("Export", &output_path, Some(file_size), true);
+    display_file_operation_result(output, "Export", &output_path, Some(file_size), true);
     
     // Display operation summary
-    display_operation_summary(
-        "Export",
-        exported_configs,
-        0,
-        0,
-        duration,
-        Some(&[
-            &format!("Total variables: {}", total_variables),
-            &format!("File size: {}", format_file_size(file_size)),
-            &format!("Format: {}", format),
-        ])
-    );
+    let mut report = OperationReport::new("Export", exported_configs, duration)
+        .with_detail("Total variables", total_variables)
+        .with_detail("Format", &format);
+    if !export_result.excluded.is_empty() {
+        report = report.with_detail("Excluded variables", export_result.excluded.len());
+    }
+    if template {
+        report = report.with_detail("Placeholders", export_result.placeholders.len());
+    }
+    display_operation_report(output, &report.with_artifact(output_path_obj));
+    print_excluded_keys(output, &export_result.excluded, verbose);
+    if template {
+        outln!(output, "🧩 Template: {} sensitive value(s) replaced by {{{{KEY}}}} placeholders; 'use' asks for them after importing", export_result.placeholders.len());
+        if verbose {
+            for key in &export_result.placeholders {
+                outln!(output, "   • {}", key);
+            }
+        }
+    }
+    
+    if let Some(baseline) = &baseline {
+        let mut names: Vec<&str> = store.configs.keys().map(String::as_str).collect();
+        names.sort();
+        if names.is_empty() {
+            outln!(output, "🧩 Partial export ({}): no configurations changed", baseline.describe());
+        } else {
+            outln!(output, "🧩 Partial export ({}): {}", baseline.describe(), names.join(", "));
+        }
+    }
     
     if !configs.is_empty() {
-        display_verbose_info("Exported configurations", &[
+        display_verbose_info(output, "Exported configurations", &[
             ("Names", &configs.join(", ")),
         ]);
     }
@@ -209,41 +299,84 @@ pub fn handle_export_command(
         if pretty {
             details.push(("Formatting", "pretty print enabled"));
         }
-        display_verbose_info("Export options", &details);
+        if include_settings {
+            details.push(("Settings", "included (apply with import --include-settings)"));
+        }
+        display_verbose_info(output, "Export options", &details);
     }
     
     // Show next steps
+    if let ExportFormat::Shell { redact_secrets } = export_format {
+        if redact_secrets {
+            outln!(output, "🔒 Sensitive values were left out; the script reads them from environment variables");
+        }
+        display_success_with_next_steps(
+            output,
+            &msg!(output, "export.done", count = exported_configs),
+            &[&format!("sh {}        # Recreate the configurations where envswitch is installed", output_path)],
+        );
+        return Ok(());
+    }
     display_success_with_next_steps(
-        &format!("Exported {} configurations", exported_configs),
+        output,
+        &msg!(output, "export.done", count = exported_configs),
         &[
-            &format!("envswitch import {}        # Import on another machine", output_path),
-            &format!("cat {}                     # View exported content", output_path),
+            &format!("envswitch import {}        # {}", output_path, msg!(output, "export.hint_import")),
+            &format!("cat {}                     # {}", output_path, msg!(output, "export.hint_view")),
         ]
     );
     
     Ok(())
 }
 
+/// Options of the import command, as given on the command line
+#[derive(Debug, Default)]
+pub struct ImportCommandOptions {
+    pub file: String,
+    /// Overwrite configurations that already exist
+    pub force: bool,
+    pub merge: bool,
+    /// Drop every configuration that is not in the file
+    pub replace_store: bool,
+    /// Confirm `--replace-store` without asking
+    pub yes: bool,
+    pub dry_run: bool,
+    pub skip_validation: bool,
+    pub backup: bool,
+    pub keep_whitespace: bool,
+    pub include_settings: bool,
+    /// Fail the whole import when any configuration is invalid
+    pub strict: bool,
+    /// Variables to rename, as (old, new)
+    pub map: Vec<(String, String)>,
+    pub map_file: Option<String>,
+    /// Glob patterns of variables to leave out
+    pub exclude_keys: Vec<String>,
+    pub rewrite_home: bool,
+    pub flatten: Option<FlattenOptions>,
+}
+
 /// Handle the import command to import configurations from a file
 pub fn handle_import_command(
+    output: &OutputContext,
     config_manager: &FileConfigManager,
-    file: String,
-    force: bool,
-    merge: bool,
-    dry_run: bool,
-    skip_validation: bool,
-    backup: bool,
+    options: ImportCommandOptions,
     verbose: bool,
 ) -> Result<(), Box<dyn Error>> {
+    let ImportCommandOptions {
+        file, force, merge, replace_store, yes, dry_run, skip_validation, backup, keep_whitespace,
+        include_settings, strict, map, map_file, exclude_keys, rewrite_home, flatten,
+    } = options;
     let start_time = Instant::now();
     let mut progress = ProgressIndicator::new("📥 Starting import operation");
     
     if verbose {
         progress.start();
-        display_verbose_info("Import configuration", &[
+        display_verbose_info(output, "Import configuration", &[
             ("Import file", &file),
             ("Force overwrite", &force.to_string()),
             ("Merge existing", &merge.to_string()),
+            ("Replace store", &replace_store.to_string()),
             ("Dry run", &dry_run.to_string()),
             ("Skip validation", &skip_validation.to_string()),
             ("Create backup", &backup.to_string()),
@@ -255,13 +388,9 @@ pub fn handle_import_command(
     if !import_path.exists() {
         let error = format!("Import file '{}' not found", file);
         if verbose {
-            progress.finish_error(&error);
+            progress.finish_error(output, &error);
         }
-        display_error_with_suggestions(
-            &std::io::Error::new(std::io::ErrorKind::NotFound, error.clone()),
-            verbose
-        );
-        return Err(error.into());
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, error).into());
     }
     
     // Detect and validate format based on file extension and content
@@ -269,19 +398,29 @@ pub fn handle_import_command(
         progress.tick();
     }
     
-    let detected_format = detect_file_format(import_path).map_err(|e| {
+    let detected_format = {
+        let _timer = output.execution.timings.step("detect_format");
+        detect_file_format(import_path)
+    }.map_err(|e| {
         if verbose {
-            progress.finish_error("Format detection failed");
+            progress.finish_error(output, "Format detection failed");
         }
         e
     })?;
     
     if verbose {
-        display_verbose_info("Format detection", &[
+        display_verbose_info(output, "Format detection", &[
             ("Detected format", &format!("{:?}", detected_format)),
         ]);
     }
     
+    // Formats that can't be imported are refused before anything else is checked
+    let format = import_format(&detected_format, &file).inspect_err(|_| {
+        if verbose {
+            progress.finish_error(output, "Unsupported import format");
+        }
+    })?;
+    
     // Validate the file format
     if verbose {
         progress.tick();
@@ -289,7 +428,7 @@ pub fn handle_import_command(
     
     let validation_result = validate_file_format(import_path, &detected_format).map_err(|e| {
         if verbose {
-            progress.finish_error("Format validation failed");
+            progress.finish_error(output, "Format validation failed");
         }
         e
     })?;
@@ -300,56 +439,113 @@ pub fn handle_import_command(
             error_msg.push_str(&format!("\n  • {}", error));
         }
         if verbose {
-            progress.finish_error("File validation failed");
+            progress.finish_error(output, "File validation failed");
         }
         return Err(error_msg.into());
     }
     
-    // Show warnings if any
-    if !validation_result.warnings.is_empty() {
+    // Repeated problems are summarised, --verbose lists every occurrence
+    validation_result.warnings.display(output, verbose);
+    check_strict(output)?;
+    
+    // Partial exports only carry changed configurations, so replacing the store would drop the rest
+    let content = std::fs::read_to_string(import_path)?;
+    let settings = exported_settings(&content);
+    let mut merge = merge;
+    let mut replace_store = replace_store;
+    if let Some(baseline) = partial_export_baseline(&content) {
+        let mut hints = vec!["Configurations not in the file are left as they are"];
+        if !merge {
+            merge = true;
+            hints.push("Importing in merge mode");
+        }
+        if replace_store {
+            replace_store = false;
+            hints.push("Ignoring --replace-store");
+        }
         display_warning(
-            "Format validation warnings",
-            Some(&validation_result.warnings.iter().map(|s| s.as_str()).collect::<Vec<_>>())
+            output,
+            &format!("{} is a partial export ({})", file, baseline),
+            Some(&hints)
         );
     }
     
+    // Renames from the mapping file, overridden by --map
+    let mut key_map = match &map_file {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| format!("Cannot read mapping file '{}': {}", path, e))?;
+            parse_key_map_file(&content)?
+        }
+        None => Vec::new(),
+    };
+    key_map.retain(|(old, _)| !map.iter().any(|(mapped, _)| mapped == old));
+    key_map.extend(map);
+    let key_map = build_key_map(key_map)?;
+    
+    // Create import options
+    let import_options = crate::config::ImportOptions {
+        format,
+        force_overwrite: force,
+        merge_existing: merge,
+        replace_store,
+        skip_validation,
+        dry_run,
+        keep_whitespace,
+        strict,
+        key_map,
+        exclude_keys: excluded_key_patterns(config_manager, exclude_keys)?,
+        rewrite_home,
+        configs: None,
+        flatten,
+    };
+    
+    // Replacing the store deletes every configuration that is not in the file
+    if replace_store && !dry_run && !yes {
+        let preview = config_manager.import_from_file_with_options(
+            import_path,
+            &crate::config::ImportOptions { dry_run: true, ..import_options.clone() },
+        )?;
+        if !preview.removed.is_empty() {
+            outln!(output, "⚠️  --replace-store deletes {} configuration(s) that are not in {}:", preview.removed.len(), file);
+            for config in &preview.removed {
+                outln!(output, "   • {}", config);
+            }
+            if !prompt_typed_confirmation(output, "Configurations that are also in the file are replaced by its version.", "replace")? {
+                outln!(output, "❌ Import cancelled; the store was not changed");
+                return Ok(());
+            }
+        }
+    }
+    
     // Create backup if requested
+    let mut backup_path = None;
     if backup && config_manager.config_file_exists() {
         if verbose {
             progress.tick();
-            display_verbose_info("Creating backup", &[
+            display_verbose_info(output, "Creating backup", &[
                 ("Reason", "Backup requested before import"),
             ]);
         }
-        let backup_path = config_manager.backup_config().map_err(|e| {
+        let path = config_manager.backup_config_with_note(Some(&format!("before import of {}", file))).map_err(|e| {
             if verbose {
-                progress.finish_error("Backup creation failed");
+                progress.finish_error(output, "Backup creation failed");
             }
             e
         })?;
         display_success_with_next_steps(
-            &format!("Backup created: {}", backup_path.display()),
+            output,
+            &format!("Backup created: {}", path.display()),
             &[]
         );
+        backup_path = Some(path);
     }
     
-    // Create import options
-    let import_options = crate::config::ImportOptions {
-        format: match detected_format {
-            FileFormat::Json => crate::config::ImportFormat::Json,
-            FileFormat::Env => crate::config::ImportFormat::Env,
-            FileFormat::Yaml => crate::config::ImportFormat::Yaml,
-        },
-        force_overwrite: force,
-        merge_existing: merge,
-        skip_validation,
-        dry_run,
-    };
-    
     if verbose {
-        display_verbose_info("Import options", &[
+        display_verbose_info(output, "Import options", &[
             ("Force overwrite", &force.to_string()),
             ("Merge existing", &merge.to_string()),
+            ("Replace store", &replace_store.to_string()),
             ("Dry run", &dry_run.to_string()),
             ("Skip validation", &skip_validation.to_string()),
         ]);
@@ -359,113 +555,429 @@ pub fn handle_import_command(
     // Perform the import
     let result = config_manager.import_from_file_with_options(import_path, &import_options).map_err(|e| {
         if verbose {
-            progress.finish_error("Import operation failed");
+            progress.finish_error(output, "Import operation failed");
         }
-        display_error_with_suggestions(&e, verbose);
         e
     })?;
     
     if dry_run {
-        println!("🔍 Dry run results:");
+        outln!(output, "🔍 {}", msg!(output, "import.dry_run_results"));
         if !result.imported.is_empty() {
-            println!("✅ Would import {} configurations:", result.imported.len());
+            outln!(output, "✅ {}", msg!(output, "import.would_import", count = result.imported.len()));
             for config in &result.imported {
-                println!("   • {}", config);
+                outln!(output, "   • {}", config);
             }
         }
         
+        print_renamed_keys(output, &result.renamed);
+        print_excluded_keys(output, &result.excluded, verbose);
+        print_host_warnings(output, &result, &file, rewrite_home, verbose);
+        print_templates(output, &result.templates);
+        
         if !result.conflicts.is_empty() {
-            println!("⚠️  {} conflicts found:", result.conflicts.len());
+            outln!(output, "⚠️  {}", msg!(output, "import.conflicts", count = result.conflicts.len()));
             for config in &result.conflicts {
-                println!("   • {} (already exists)", config);
+                outln!(output, "   • {}", msg!(output, "import.already_exists", name = config));
             }
             
             if !force && !merge {
-                println!();
-                println!("💡 Resolution options:");
-                println!("   --force    Overwrite existing configurations");
-                println!("   --merge    Merge with existing configurations");
+                outln!(output);
+                outln!(output, "💡 {}", msg!(output, "common.resolution"));
+                outln!(output, "   --force    {}", msg!(output, "common.force_option"));
+                outln!(output, "   --merge    {}", msg!(output, "common.merge_option"));
+            }
+        }
+        
+        if !result.removed.is_empty() {
+            outln!(output, "🗑️  Would remove {} configuration(s) not in the file:", result.removed.len());
+            for config in &result.removed {
+                outln!(output, "   • {}", config);
             }
         }
         
         if !result.errors.is_empty() {
-            println!("❌ {} errors found:", result.errors.len());
+            outln!(output, "❌ {}", msg!(output, "import.errors_found", count = result.errors.len()));
             for error in &result.errors {
-                println!("   • {}", error);
+                outln!(output, "   • {}", error);
             }
         }
         
-        println!();
-        println!("🚀 To perform the actual import, run the same command without --dry-run");
+        if let (Some(document), true) = (&settings, include_settings) {
+            match config_manager.apply_settings_document(document, true) {
+                Ok(changes) => print_setting_changes(output, &changes),
+                Err(e) => outln!(output, "⚠️  Settings would not be applied: {}", e),
+            }
+        }
+        
+        outln!(output);
+        outln!(output, "🚀 {}", msg!(output, "import.run_without_dry_run"));
         return Ok(());
     }
     
+    let mut trimmed = WarningAggregator::new();
+    for location in &result.trimmed {
+        trimmed.add_with_symbol("✂️", "{count} value(s) had surrounding whitespace or control characters; trimmed (keep them with --keep-whitespace)", location.as_str());
+    }
+    trimmed.display(output, verbose);
+    print_renamed_keys(output, &result.renamed);
+    print_excluded_keys(output, &result.excluded, verbose);
+    print_host_warnings(output, &result, &file, rewrite_home, verbose);
+    print_templates(output, &result.templates);
+    
+    // Apart from trimming, values are imported as they are; only point out credentials that look wrong
+    let mut suspicious = WarningAggregator::new();
+    let saved_names = result.imported.iter().chain(result.conflicts.iter().filter(|_| force || merge));
+    for name in saved_names {
+        if let Some(config) = config_manager.get_config(name)? {
+            collect_suspicious_tokens(name, &config.variables, &mut suspicious);
+        }
+    }
+    suspicious.display(output, verbose);
+    
     // Report results
     if !result.imported.is_empty() {
-        println!("✅ Successfully imported {} configurations:", result.imported.len());
+        outln!(output, "✅ {}", msg!(output, "import.imported", count = result.imported.len()));
         for config in &result.imported {
-            println!("   • {}", config);
+            outln!(output, "   • {}", config);
         }
     }
     
     if !result.conflicts.is_empty() {
         if force {
-            println!("🔄 Overwrote {} existing configurations:", result.conflicts.len());
+            outln!(output, "🔄 {}", msg!(output, "import.overwrote", count = result.conflicts.len()));
             for config in &result.conflicts {
-                println!("   • {}", config);
+                outln!(output, "   • {}", config);
             }
         } else if merge {
-            println!("🔗 Merged with {} existing configurations:", result.conflicts.len());
+            outln!(output, "🔗 {}", msg!(output, "import.merged", count = result.conflicts.len()));
             for config in &result.conflicts {
-                println!("   • {}", config);
+                outln!(output, "   • {}", config);
             }
         } else {
-            println!("⚠️  {} conflicts found (skipped):", result.conflicts.len());
+            outln!(output, "⚠️  {}", msg!(output, "import.conflicts_skipped", count = result.conflicts.len()));
             for config in &result.conflicts {
-                println!("   • {} (already exists)", config);
+                outln!(output, "   • {}", msg!(output, "import.already_exists", name = config));
             }
-            println!();
-            println!("💡 To resolve conflicts:");
-            println!("   --force    Overwrite existing configurations");
-            println!("   --merge    Merge with existing configurations");
+            outln!(output);
+            outln!(output, "💡 {}", msg!(output, "import.resolve"));
+            outln!(output, "   --force    {}", msg!(output, "common.force_option"));
+            outln!(output, "   --merge    {}", msg!(output, "common.merge_option"));
+        }
+    }
+    
+    if !result.removed.is_empty() {
+        outln!(output, "🗑️  Removed {} configuration(s) not in the file:", result.removed.len());
+        for config in &result.removed {
+            outln!(output, "   • {}", config);
         }
     }
     
     if !result.errors.is_empty() {
-        println!("❌ {} errors occurred:", result.errors.len());
+        outln!(output, "❌ {}", msg!(output, "import.errors_occurred", count = result.errors.len()));
         for error in &result.errors {
-            println!("   • {}", error);
+            outln!(output, "   • {}", error);
         }
+    }
+    
+    // Conflicts count as imported when they were overwritten or merged, otherwise as warnings
+    let saved_conflicts = if force || merge { result.conflicts.len() } else { 0 };
+    let store = config_manager.load_configs()?;
+    let total_variables: usize = result.imported.iter()
+        .filter_map(|name| store.configs.get(name))
+        .map(|config| config.variables.len())
+        .sum();
+    let conflicts_label = if force { "Overwritten" } else if merge { "Merged" } else { "Skipped" };
+    let mut report = OperationReport::new("Import", result.imported.len() + saved_conflicts, start_time.elapsed())
+        .with_warnings(result.conflicts.len() - saved_conflicts + result.host_warnings.len())
+        .with_errors(result.errors.len())
+        .with_detail("Format", format!("{:?}", detected_format).to_lowercase())
+        .with_detail("Imported", result.imported.len())
+        .with_detail(conflicts_label, result.conflicts.len())
+        .with_detail("Total variables", total_variables);
+    if !result.removed.is_empty() {
+        report = report.with_detail("Removed", result.removed.len());
+    }
+    if !result.host_warnings.is_empty() {
+        report = report.with_detail("Host-specific values", result.host_warnings.len());
+    }
+    if let Some(path) = &backup_path {
+        report = report.with_artifact(path);
+    }
+    display_operation_report(output, &report);
+    
+    if !result.errors.is_empty() {
         return Err("Import completed with errors".into());
     }
     
-    let total_imported = result.imported.len();
-    if total_imported > 0 {
-        println!();
-        println!("📊 Import summary:");
-        println!("   Total imported: {}", total_imported);
-        
-        if verbose {
-            // Count total variables imported
-            let store = config_manager.load_configs()?;
-            let total_variables: usize = result.imported.iter()
-                .filter_map(|name| store.configs.get(name))
-                .map(|config| config.variables.len())
-                .sum();
-            println!("   Total variables: {}", total_variables);
+    if let Some(first_config) = result.imported.first() {
+        outln!(output);
+        outln!(output, "🚀 Next steps:");
+        outln!(output, "   envswitch list             # View all configurations");
+        outln!(output, "   envswitch use {}           # Activate imported configuration", first_config);
+    } else {
+        outln!(output, "📭 {}", msg!(output, "import.none"));
+    }
+    
+    match settings {
+        Some(document) if include_settings => import_settings(output, config_manager, &document, force)?,
+        Some(_) => outln!(output, "💡 The file also contains settings; apply them with: envswitch import {} --include-settings", file),
+        None if include_settings => outln!(output, "ℹ️  {} contains no settings; export with --include-settings to carry them along", file),
+        None => {}
+    }
+    
+    Ok(())
+}
+
+/// List the variables renamed by --map and --map-file under their new names
+fn print_renamed_keys(output: &OutputContext, renamed: &[(String, String, String)]) {
+    if renamed.is_empty() {
+        return;
+    }
+    outln!(output, "🔄 Renamed {} variable(s):", renamed.len());
+    for (config, old, new) in renamed {
+        outln!(output, "   • {}:{} (from {})", config, new, old);
+    }
+}
+
+/// Point out values that may only work on the exporting machine, and the ones `--rewrite-home` fixed
+fn print_host_warnings(output: &OutputContext, result: &ImportResult, file: &str, rewrite_home: bool, verbose: bool) {
+    let mut warnings = WarningAggregator::new();
+    for location in &result.rewritten_home {
+        warnings.add_with_symbol("🔄", "Replaced another user's home directory with yours in {count} variable(s)", location.as_str());
+    }
+    // Host warnings read "config:KEY problem"
+    for warning in &result.host_warnings {
+        let (location, problem) = warning.split_once(' ').unwrap_or((warning.as_str(), ""));
+        warnings.add(&format!("{{count}} variable(s) whose value {}", problem), location);
+    }
+    warnings.display(output, verbose);
+    if !rewrite_home && result.host_warnings.iter().any(|warning| warning.contains("home directory")) {
+        outln!(output, "💡 Point those paths at your home directory with: envswitch import {} --rewrite-home", file);
+    }
+}
+
+/// Point out imported templates, whose placeholders `use` asks for
+fn print_templates(output: &OutputContext, templates: &[String]) {
+    if templates.is_empty() {
+        return;
+    }
+    // Placeholders instead of secrets are a warning for --strict
+    output.execution.record_warnings(templates.len());
+    outln!(output, "🧩 {} template configuration(s); 'use' asks for the {{{{KEY}}}} placeholders:", templates.len());
+    for name in templates {
+        outln!(output, "   • {}", name);
+    }
+    outln!(output, "💡 Or fill them in for good with: envswitch set {} -e KEY=value", templates[0]);
+}
+
+/// The import format of a detected file format; TOML has to be converted first
+pub(crate) fn import_format(format: &FileFormat, file: &str) -> Result<ImportFormat, Box<dyn Error>> {
+    match format {
+        FileFormat::Json => Ok(ImportFormat::Json),
+        FileFormat::Env => Ok(ImportFormat::Env),
+        FileFormat::Yaml => Ok(ImportFormat::Yaml),
+        FileFormat::Toml => Err(format!(
+            "TOML files cannot be imported directly. Convert it first:\n  envswitch convert {} vars.env",
+            file
+        ).into()),
+    }
+}
+
+/// Print the variables left out by `--exclude-keys` or the exclude-keys setting
+fn print_excluded_keys(output: &OutputContext, excluded: &[String], verbose: bool) {
+    let mut warnings = WarningAggregator::new();
+    for location in excluded {
+        warnings.add_with_symbol("⏭️", "Excluded {count} variable(s)", location.as_str());
+    }
+    warnings.display(output, verbose);
+}
+
+/// The exclude-keys setting followed by the patterns given on the command line
+fn excluded_key_patterns(config_manager: &FileConfigManager, patterns: Vec<String>) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut all = config_manager.get_settings()?.exclude_keys;
+    all.extend(patterns);
+    Ok(all)
+}
+
+/// Apply the settings document of an import, after confirming the changes unless forced
+fn import_settings(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    document: &SettingsDocument,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    let changes = config_manager.apply_settings_document(document, true)?;
+    print_setting_changes(output, &changes);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    if !force && !prompt_confirmation(output, "Apply these settings?", false)? {
+        outln!(output, "❌ Settings were not changed");
+        return Ok(());
+    }
+    
+    config_manager.apply_settings_document(document, false)?;
+    outln!(output, "✅ {} setting(s) applied", changes.len());
+    Ok(())
+}
+
+fn print_setting_changes(output: &OutputContext, changes: &[SettingChange]) {
+    if changes.is_empty() {
+        outln!(output, "ℹ️  Settings already match the imported ones");
+        return;
+    }
+    outln!(output, "🔄 Settings to change:");
+    for change in changes {
+        let old = if change.old.is_empty() { "(unset)" } else { change.old.as_str() };
+        let new = if change.new.is_empty() { "(unset)" } else { change.new.as_str() };
+        outln!(output, "   {}: {} → {}", change.key, old, new);
+    }
+}
+
+// Format detection is now handled by utils::file_utils module
+
+/// Handle `export --archive` to bundle the configuration directory
+pub fn handle_archive_export_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    archive: String,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let archive_path = Path::new(&archive);
+    let manifest = config_manager.export_archive(archive_path)?;
+    
+    let file_size = std::fs::metadata(archive_path).ok().map(|m| m.len());
+    display_file_operation_result(output, "Archive export", &archive, file_size, true);
+    outln!(output, "📦 {} file(s) archived with envswitch {}", manifest.files.len(), manifest.envswitch_version);
+    
+    if verbose {
+        for file in &manifest.files {
+            outln!(output, "   {}", file);
         }
-        
-        println!();
-        println!("🚀 Next steps:");
-        println!("   envswitch list             # View all configurations");
-        if let Some(first_config) = result.imported.first() {
-            println!("   envswitch use {}           # Activate imported configuration", first_config);
+    }
+    
+    outln!(output, "⚠️  The archive contains secrets in clear text; store it somewhere safe");
+    outln!(output, "💡 Restore on another machine with: envswitch import --archive {}", archive);
+    Ok(())
+}
+
+/// Handle `import --archive` to restore a configuration directory archive
+///
+/// Existing configurations are kept unless `replace_store` is set, which asks to type
+/// 'replace' first unless `yes` is set.
+pub fn handle_archive_import_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    file: String,
+    replace_store: bool,
+    yes: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let archive_path = Path::new(&file);
+    if !archive_path.exists() {
+        return Err(format!("Archive '{}' not found", file).into());
+    }
+    
+    // Replacing the store deletes every configuration that is not in the archive
+    if replace_store && !yes {
+        let archived = config_manager.archive_config_names(archive_path)?;
+        let removed: Vec<String> = config_manager.list_configs()?.into_iter()
+            .filter(|name| !archived.contains(name))
+            .collect();
+        if !removed.is_empty() {
+            outln!(output, "⚠️  --replace-store deletes {} configuration(s) that are not in {}:", removed.len(), file);
+            for config in &removed {
+                outln!(output, "   • {}", config);
+            }
+            if !prompt_typed_confirmation(output, "Configurations that are also in the archive are replaced by its version (a backup is made first).", "replace")? {
+                outln!(output, "❌ Import cancelled; the store was not changed");
+                outln!(output, "💡 Leave out --replace-store to add the archived configurations to the existing ones");
+                return Ok(());
+            }
         }
-    } else {
-        println!("📭 No configurations were imported");
+    }
+    
+    let result = config_manager.import_archive(archive_path, !replace_store)?;
+    
+    outln!(output, "✅ Archive imported from {} (created by envswitch {} on {})",
+        file,
+        result.manifest.envswitch_version,
+        format_timestamp(output, result.manifest.created_at)
+    );
+    outln!(output, "📥 {} configuration(s) imported, {} backup(s) restored", result.imported.len(), result.restored_backups);
+    
+    if verbose {
+        for name in &result.imported {
+            outln!(output, "   + {}", name);
+        }
+    }
+    
+    if !result.conflicts.is_empty() {
+        display_warning(
+            output,
+            &format!("{} configuration(s) already exist and were kept unchanged", result.conflicts.len()),
+            Some(&result.conflicts.iter().map(String::as_str).collect::<Vec<_>>()),
+        );
     }
     
     Ok(())
 }
 
-// Format detection is now handled by utils::file_utils module
\ No newline at end of file
+/// Handle the convert command to translate a variables file between formats
+pub fn handle_convert_command(
+    output: &OutputContext,
+    input: String,
+    output_file: String,
+    from: Option<String>,
+    to: Option<String>,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let input_path = Path::new(&input);
+    let output_path = Path::new(&output_file);
+    
+    if !input_path.exists() {
+        return Err(format!("Input file '{}' not found", input).into());
+    }
+    
+    let source_format = match from.as_deref() {
+        Some(name) => FileFormat::from_name(name).ok_or_else(|| format!("Unknown format '{}'", name))?,
+        None => {
+            let _timer = output.execution.timings.step("detect_format");
+            detect_file_format(input_path)?
+        }
+    };
+    let target_format = match to.as_deref() {
+        Some(name) => FileFormat::from_name(name).ok_or_else(|| format!("Unknown format '{}'", name))?,
+        None => FileFormat::from_extension(output_path).ok_or_else(|| {
+            format!("Cannot tell the output format from '{}'. Use --to env|json|yaml|toml", output_file)
+        })?,
+    };
+    
+    if verbose {
+        display_verbose_info(output, "Conversion", &[
+            ("Input", &input),
+            ("Input format", &format!("{:?}", source_format).to_lowercase()),
+            ("Output", &output_file),
+            ("Output format", &format!("{:?}", target_format).to_lowercase()),
+        ]);
+    }
+    
+    let summary = convert_format(input_path, output_path, &source_format, &target_format)?;
+    
+    outln!(output, "✅ Converted {} variable(s) from {} to {}",
+        summary.variables,
+        format!("{:?}", source_format).to_lowercase(),
+        format!("{:?}", target_format).to_lowercase()
+    );
+    outln!(output, "📄 Written to {}", output_file);
+    
+    if !summary.warnings.is_empty() {
+        display_warning(
+            output,
+            "Some information could not be converted",
+            Some(&summary.warnings.iter().map(String::as_str).collect::<Vec<_>>()),
+        );
+    }
+    
+    Ok(())
+}
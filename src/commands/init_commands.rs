@@ -0,0 +1,237 @@
+use crate::config::{ConfigManager, ConfigSource, FileConfigManager};
+use crate::handlers::startup::mark_welcome_shown;
+use crate::handlers::{set_wizard, Prompter, TerminalPrompter};
+use crate::shell::ShellDetector;
+use crate::types::presets::{self, Preset};
+use std::collections::HashMap;
+use std::error::Error;
+use crate::output::OutputContext;
+
+/// Options of the init command, as given on the command line
+#[derive(Debug, Default)]
+pub struct InitOptions {
+    /// Target shell, detected when not given
+    pub shell: Option<String>,
+    /// Ask nothing and only run the steps requested by the other options
+    pub non_interactive: bool,
+    /// Add the shell integration without asking
+    pub install: bool,
+    /// Name of the first configuration
+    pub create: Option<String>,
+    /// Preset filling the first configuration
+    pub preset: Option<String>,
+    /// Prefix of the environment variables filling the first configuration
+    pub from_env: Option<String>,
+    /// Make the first configuration the default without asking
+    pub set_default: bool,
+}
+
+/// Handle the init command walking a new user through the first-run setup
+///
+/// Detects the shell, offers to add the integration to its startup file, to create a first
+/// configuration from a preset or the current environment, and to make it the default.
+/// Steps that are already done are skipped, so running it again changes nothing. With
+/// `non_interactive` nothing is asked and only the steps requested by flags run.
+pub fn handle_init_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    options: InitOptions,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let InitOptions { shell, non_interactive, install, create, preset, from_env, set_default } = options;
+    let preset = match preset {
+        Some(name) => Some(presets::find(&name).ok_or_else(|| {
+            let names: Vec<&str> = presets::PRESETS.iter().map(|preset| preset.name).collect();
+            format!("Unknown preset '{}'. Available presets: {}", name, names.join(", "))
+        })?),
+        None => None,
+    };
+    if non_interactive && create.is_some() && preset.is_none() && from_env.is_none() {
+        return Err("--create needs --preset or --from-env with --non-interactive".into());
+    }
+    let mut prompter = TerminalPrompter::new(output);
+
+    // 1. Shell
+    let shell_type = match shell {
+        Some(name) => {
+            let shell_type = ShellDetector::shell_from_name(&name);
+            outln!(output, "🔍 Shell: {}", shell_type);
+            shell_type
+        }
+        None => {
+            let detection = ShellDetector::detect();
+            outln!(output, "🔍 Shell: {} (detected via {})", detection.shell, detection.method);
+            detection.shell
+        }
+    };
+
+    // 2. Shell integration in the startup file
+    match (ShellDetector::shell_config_file(&shell_type), ShellDetector::is_integration_installed(&shell_type)) {
+        (Some(config_file), Some(true)) => outln!(output, "✅ Shell integration already in {}", config_file.display()),
+        (Some(config_file), _) if ShellDetector::integration_block(&shell_type).is_some() => {
+            let question = format!("Add the shell integration to {}?", config_file.display());
+            if decide(output, &mut prompter, non_interactive, install, &question, true)? {
+                ShellDetector::install_integration(&shell_type)?;
+                outln!(output, "✅ Added the shell integration to {}", config_file.display());
+            } else {
+                outln!(output, "⏭️  Skipped the shell integration");
+            }
+        }
+        _ => {
+            outln!(output, "⚠️  No startup file is known for '{}'; add the integration by hand:", shell_type);
+            for line in ShellDetector::get_shell_integration_instructions(&shell_type).lines() {
+                outln!(output, "   {}", line);
+            }
+        }
+    }
+
+    // 3. First configuration
+    let existing = config_manager.list_configs()?;
+    let name = match create {
+        Some(name) => Some(name),
+        None if non_interactive => None,
+        None => {
+            if prompter.confirm(output, "Create a configuration now?", existing.is_empty())? {
+                let answer = prompter.read_line("Name [claude]: ")?.unwrap_or_default();
+                Some(Some(answer.trim()).filter(|name| !name.is_empty()).unwrap_or("claude").to_string())
+            } else {
+                None
+            }
+        }
+    };
+    let name = match name {
+        Some(name) if config_manager.get_config(&name)?.is_some() => {
+            outln!(output, "✅ '{}' already exists; left as it is", name);
+            Some(name)
+        }
+        Some(name) => {
+            let filled = match (preset, &from_env) {
+                (Some(preset), _) => Some(fill_from_preset(preset)?),
+                (None, Some(prefix)) => Some(fill_from_environment(prefix)?),
+                (None, None) => fill_interactively(output, &mut prompter, &name)?,
+            };
+            match filled {
+                Some((variables, description, source)) => {
+                    let count = variables.len();
+                    if verbose {
+                        let mut keys: Vec<&String> = variables.keys().collect();
+                        keys.sort();
+                        outln!(output, "📋 Variables: {}", keys.iter().map(|key| key.as_str()).collect::<Vec<_>>().join(", "));
+                    }
+                    config_manager.create_config(name.clone(), variables, description)?;
+                    config_manager.set_config_source(&name, source)?;
+                    outln!(output, "✅ Created '{}' with {} variables", name, count);
+                    Some(name)
+                }
+                None => {
+                    outln!(output, "⏭️  Skipped creating a configuration");
+                    None
+                }
+            }
+        }
+        None => {
+            outln!(output, "⏭️  Skipped creating a configuration");
+            None
+        }
+    };
+
+    // 4. Default for new shells
+    if let Some(name) = &name {
+        if config_manager.get_settings()?.default_config.as_deref() == Some(name.as_str()) {
+            outln!(output, "✅ '{}' is already the default configuration", name);
+        } else if decide(output, &mut prompter, non_interactive, set_default, &format!("Make '{}' the default for new shells?", name), true)? {
+            config_manager.set_default_config(Some(name))?;
+            outln!(output, "✅ '{}' is now the default configuration for new shells", name);
+        } else {
+            outln!(output, "⏭️  Skipped the default configuration");
+        }
+    }
+
+    mark_welcome_shown();
+
+    // 5. The current shell
+    outln!(output);
+    match &name {
+        Some(name) => {
+            outln!(output, "🚀 Start using '{}' in this shell with:", name);
+            outln!(output, "   {}", crate::examples::activation_command(name));
+        }
+        None => {
+            outln!(output, "🚀 Create a configuration when you're ready with:");
+            outln!(output, "   envswitch set <name>");
+        }
+    }
+    Ok(())
+}
+
+/// Answer a yes/no step: the flag decides without a terminal, otherwise a set flag skips the question
+fn decide(output: &OutputContext, prompter: &mut dyn Prompter, non_interactive: bool, flag: bool, question: &str, default_yes: bool) -> std::io::Result<bool> {
+    if non_interactive || flag {
+        Ok(flag)
+    } else {
+        prompter.confirm(output, question, default_yes)
+    }
+}
+
+/// Variables, description and source of a new configuration
+type Filled = (HashMap<String, String>, Option<String>, ConfigSource);
+
+/// The preset's variables, taken from the current environment or their defaults
+fn fill_from_preset(preset: &Preset) -> Result<Filled, Box<dyn Error>> {
+    let mut variables = HashMap::new();
+    let mut missing = Vec::new();
+    for variable in preset.variables {
+        match std::env::var(variable.key).ok().or(variable.default.map(str::to_string)) {
+            Some(value) => {
+                variables.insert(variable.key.to_string(), value);
+            }
+            None if variable.required => missing.push(variable.key),
+            None => {}
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "The {} preset needs {}\n💡 Export them first, or leave out --preset to type them in",
+            preset.name, missing.join(", ")
+        ).into());
+    }
+    Ok((variables, Some(preset.description.to_string()), ConfigSource::Preset { name: preset.name.to_string() }))
+}
+
+/// Variables of the current environment whose names start with `prefix`, leaving out envswitch's own
+fn fill_from_environment(prefix: &str) -> Result<Filled, Box<dyn Error>> {
+    let variables: HashMap<String, String> = std::env::vars()
+        .filter(|(key, _)| key.starts_with(prefix) && !key.starts_with("ENVSWITCH_"))
+        .collect();
+    if variables.is_empty() {
+        return Err(format!("No variables starting with '{}' in the current environment", prefix).into());
+    }
+    Ok((variables, None, ConfigSource::Snapshot))
+}
+
+/// Offer to capture the current environment, otherwise walk through the `set` wizard
+fn fill_interactively(output: &OutputContext, prompter: &mut dyn Prompter, name: &str) -> Result<Option<Filled>, Box<dyn Error>> {
+    if prompter.confirm(output, "Capture variables from the current environment?", false)? {
+        let prefix = prompter.read_line("Variable name prefix, e.g. ANTHROPIC_: ")?.unwrap_or_default();
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            return Ok(None);
+        }
+        return match fill_from_environment(prefix) {
+            Ok(filled) => {
+                let mut keys: Vec<&String> = filled.0.keys().collect();
+                keys.sort();
+                outln!(output, "📋 Found: {}", keys.iter().map(|key| key.as_str()).collect::<Vec<_>>().join(", "));
+                let save = prompter.confirm(output, &format!("Save them as '{}'?", name), true)?;
+                Ok(Some(filled).filter(|_| save))
+            }
+            Err(e) => {
+                outln!(output, "⚠️  {}", e);
+                Ok(None)
+            }
+        };
+    }
+
+    Ok(set_wizard(output, prompter, name, &HashMap::new())?
+        .map(|answers| (answers.variables, answers.description, ConfigSource::Manual)))
+}
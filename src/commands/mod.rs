@@ -2,10 +2,38 @@ pub mod config_commands;
 pub mod shell_commands;
 pub mod tutorial_commands;
 pub mod import_export;
+pub mod alias_commands;
+pub mod settings_commands;
+pub mod history_commands;
+pub mod watch_commands;
+pub mod backup_commands;
+pub mod doctor_commands;
+pub mod env_commands;
+pub mod default_commands;
+pub mod variable_commands;
+pub mod paths_commands;
+pub mod audit_commands;
+pub mod storage_commands;
+pub mod sync_commands;
+pub mod init_commands;
 pub mod router;
 
 pub use config_commands::*;
 pub use shell_commands::*;
 pub use tutorial_commands::*;
 pub use import_export::*;
+pub use alias_commands::*;
+pub use settings_commands::*;
+pub use history_commands::*;
+pub use watch_commands::*;
+pub use backup_commands::*;
+pub use doctor_commands::*;
+pub use env_commands::*;
+pub use default_commands::*;
+pub use variable_commands::*;
+pub use paths_commands::*;
+pub use audit_commands::*;
+pub use storage_commands::*;
+pub use sync_commands::*;
+pub use init_commands::*;
 pub use router::*;
\ No newline at end of file
@@ -0,0 +1,92 @@
+use crate::config::FileConfigManager;
+use crate::types::ConfigPaths;
+use crate::utils::format_file_size;
+use serde::Serialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use crate::output::OutputContext;
+
+/// One location reported by `envswitch paths`
+#[derive(Debug, Clone, Serialize)]
+pub struct PathInfo {
+    pub name: &'static str,
+    pub path: PathBuf,
+    pub exists: bool,
+    /// Size in bytes, for files only
+    pub size: Option<u64>,
+    /// Unix permission bits in octal, e.g. "0600"
+    pub permissions: Option<String>,
+}
+
+impl PathInfo {
+    fn new(name: &'static str, path: &Path) -> Self {
+        let metadata = std::fs::metadata(path).ok();
+        Self {
+            name,
+            path: path.to_path_buf(),
+            exists: metadata.is_some(),
+            size: metadata.as_ref().filter(|m| m.is_file()).map(|m| m.len()),
+            permissions: metadata.as_ref().and_then(permission_bits),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn permission_bits(metadata: &std::fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(format!("{:04o}", metadata.permissions().mode() & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn permission_bits(_metadata: &std::fs::Metadata) -> Option<String> {
+    None
+}
+
+/// Handle the paths command to show where envswitch keeps its files
+///
+/// Only reads metadata, so it never creates the files it reports.
+pub fn handle_paths_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    json: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (_, source) = ConfigPaths::resolve_config_dir()?;
+    let paths = config_manager.config_paths();
+    // Settings live in config.json; the history is a git repository in the state directory
+    let entries = vec![
+        PathInfo::new("config_dir", &paths.config_dir),
+        PathInfo::new("config_file", &paths.config_file),
+        PathInfo::new("settings_file", &paths.config_file),
+        PathInfo::new("backups_dir", &config_manager.backup_dir()),
+        PathInfo::new("state_dir", &paths.state_dir),
+        PathInfo::new("state_file", &paths.state_file),
+        PathInfo::new("history", &paths.history_dir()),
+        PathInfo::new("cache_dir", &paths.cache_dir),
+    ];
+
+    if json {
+        let value = serde_json::json!({
+            "config_dir_source": source.to_string(),
+            "paths": entries,
+        });
+        outln!(output, "{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    outln!(output, "Config directory source: {}", source);
+    for entry in &entries {
+        let mut details = vec![if entry.exists { "exists".to_string() } else { "missing".to_string() }];
+        details.extend(entry.size.map(format_file_size));
+        details.extend(entry.permissions.clone());
+        outln!(output, "  {:<14} {} ({})", entry.name, entry.path.display(), details.join(", "));
+    }
+
+    if verbose {
+        outln!(output, "💡 Set $ENVSWITCH_CONFIG_DIR to use another configuration directory; state and cache then live in it too");
+        outln!(output, "💡 Only the configuration directory is meant to be synced between machines");
+        outln!(output, "💡 Settings are stored in config.json; the history is kept only with 'settings set git-track true'");
+    }
+
+    Ok(())
+}
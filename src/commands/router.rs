@@ -1,49 +1,257 @@
-use crate::cli::Commands;
-use crate::config::FileConfigManager;
+use crate::cli::{BackupAction, Cli, Commands};
+use crate::config::{ArchiveFilter, FileConfigManager, Settings};
 use crate::env::ShellEnvironmentManager;
+use crate::messages::Locale;
+use crate::output::{ExecutionContext, OutputContext};
+use crate::timing::Timings;
 use crate::commands::*;
+use crate::utils::file_utils::FlattenOptions;
+use crate::types::constants::{EXIT_CODE_NOOP, EXIT_CODE_NOT_FOUND, EXIT_CODE_NO_ACTIVE};
+use std::cell::LazyCell;
 use std::error::Error;
 
+/// Create the output context for all handlers, with the message language for [`msg!`]
+///
+/// The execution context records whether stdout is eval'd, from `--eval` or `ENVSWITCH_EVAL`,
+/// or kept for a JSON report, whether warnings are errors, from `--strict` or the strict
+/// setting, and the step timings of `--timings`. Settings are not read for the prompt path,
+/// which has to stay fast and prints no messages.
+pub fn init_output(cli: &Cli) -> OutputContext {
+    let timings = Timings::new(cli.verbose, cli.timings);
+    let settings = if cli.command.is_prompt_path() {
+        Settings::default()
+    } else {
+        FileConfigManager::new()
+            .and_then(|config_manager| config_manager.with_timings(timings.clone()).get_settings())
+            .unwrap_or_default()
+    };
+    let execution = ExecutionContext::detect(cli.eval)
+        .with_json_report(cli.report == "json")
+        .with_strict(cli.strict || settings.strict)
+        .with_timings(timings);
+    OutputContext::new(cli.quiet, cli.no_emoji || settings.no_emoji)
+        .with_ascii(cli.ascii || OutputContext::terminal_lacks_unicode())
+        .with_mask_length_hints(settings.mask_length_hints)
+        .with_utc(cli.utc || settings.utc_timestamps)
+        .with_locale(Locale::detect(settings.language.as_deref()))
+        .with_execution(execution)
+}
+
 /// Route commands to their respective handlers
-pub fn run_command(command: Commands, verbose: bool) -> Result<(), Box<dyn Error>> {
-    let config_manager = FileConfigManager::new()?;
-    let env_manager = ShellEnvironmentManager::new();
+pub fn run_command(command: Commands, output: &OutputContext, verbose: bool, auto_recover: bool) -> Result<(), Box<dyn Error>> {
+    let timings = &output.execution.timings;
+    let config_manager = FileConfigManager::new()?.with_timings(timings.clone());
+    // Shell detection may run `ps`, so only commands generating shell code pay for it
+    let env_manager = LazyCell::new(|| {
+        let _timer = timings.step("detect_shell");
+        ShellEnvironmentManager::new()
+    });
+    
+    if !command.is_prompt_path() {
+        crate::handlers::startup::run_migrations(output, &config_manager);
+    }
+    if command.checks_integrity() {
+        crate::handlers::startup::run_integrity_check(output, &config_manager, auto_recover)?;
+    }
+    
+    let result = dispatch(command, output, &config_manager, &env_manager, verbose);
+    // Steps the managers do on a best-effort basis, like recording history, report here
+    for warning in config_manager.take_warnings() {
+        output.execution.record_warnings(1);
+        eoutln!(output, "⚠️  {}", warning);
+    }
+    result?;
     
+    // Warnings shown after the last check of the command still fail a strict run
+    crate::handlers::check_strict(output)?;
+    Ok(())
+}
+
+/// Run the handler of `command`; only handlers using `env_manager` detect the shell
+fn dispatch(
+    command: Commands,
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    env_manager: &LazyCell<ShellEnvironmentManager, impl FnOnce() -> ShellEnvironmentManager>,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
     match command {
-        Commands::Set { alias, env, description, file, replace, interactive } => {
-            handle_set_command(&config_manager, alias, env, description, file, replace, interactive, verbose)?;
+        Commands::Set { alias, pairs, mut env, description, file, replace, yes, interactive, stdin, prepend, append, comments, allow_missing, note, links, expires, expires_in, from_op, from_cmd, lazy, dry_run, output: output_file, fail_on_noop, allow_case_collision, keep_whitespace, flatten, flatten_separator, flatten_arrays, .. } => {
+            env.extend(pairs.into_iter().map(|(key, value)| (key, Some(value))));
+            let flatten = flatten_options(flatten, flatten_separator, &flatten_arrays);
+            let options = SetOptions {
+                alias, env_vars: env, description, file, flatten, replace, yes, interactive, stdin, prepend, append,
+                comments, allow_missing, note, links, expires, expires_in, from_op, from_cmd, lazy, dry_run, output: output_file,
+                allow_case_collision, keep_whitespace,
+            };
+            let changed = handle_set_command(output, config_manager, env_manager, options, verbose)?;
+            if fail_on_noop && !changed {
+                std::process::exit(EXIT_CODE_NOOP);
+            }
+        }
+        Commands::Use { alias, dry_run, copy, strict, format, force, verify, interactive_conflicts, scope, no_persist } => {
+            let options = UseOptions { alias, dry_run, copy, strict, format, force, verify, interactive_conflicts, scope, no_persist };
+            handle_use_command(output, config_manager, env_manager, options, verbose)?;
+        }
+        Commands::Clear { all_known, dry_run, scope } => {
+            handle_clear_command(output, config_manager, env_manager, all_known, dry_run, &scope, verbose)?;
+        }
+        Commands::List { names: true, with_description, archived, all, .. } => {
+            handle_list_names_command(config_manager, with_description, ArchiveFilter::from_flags(archived, all))?;
+        }
+        Commands::List { verbose: list_verbose, table, active, sort, columns, archived, all, flat, json, fields, .. } => {
+            let options = ListOptions { table, active, sort_by: sort, columns, filter: ArchiveFilter::from_flags(archived, all), flat, json, fields };
+            handle_list_command(output, config_manager, options, list_verbose || verbose)?;
+        }
+        Commands::Status { claude, table, mismatched, json, fields, flat } => {
+            let options = StatusOptions { claude, table, mismatched, json, fields, flat };
+            handle_status_command(output, config_manager, env_manager, options, verbose)?;
+        }
+        Commands::Drift { fix } => {
+            handle_drift_command(output, config_manager, env_manager, fix, verbose)?;
+        }
+        Commands::Edit { alias, keep_whitespace } => {
+            handle_edit_command(output, config_manager, alias, keep_whitespace, verbose)?;
+        }
+        Commands::Delete { alias, force, dry_run, verbose: cmd_verbose } => {
+            handle_delete_command(output, config_manager, alias, force, dry_run, verbose || cmd_verbose)?;
+        }
+        Commands::Export { archive: Some(archive), .. } => {
+            handle_archive_export_command(output, config_manager, archive, verbose)?;
+        }
+        Commands::Export { output: output_file, configs, format, metadata, pretty, resolve_files, checksum, no_checksum, since, since_backup, include_settings, exclude_keys, template, redact_secrets, exclude_archived, archive: None } => {
+            let options = ExportCommandOptions {
+                output: output_file, configs, format, metadata, pretty, resolve_files, checksum, no_checksum, since, since_backup,
+                include_settings, exclude_keys, template, redact_secrets, exclude_archived,
+            };
+            handle_export_command(output, config_manager, options, verbose)?;
+        }
+        Commands::Import { file, replace_store, yes, archive: true, .. } => {
+            handle_archive_import_command(output, config_manager, file, replace_store, yes, verbose)?;
+        }
+        Commands::Import { file, force, merge, replace_store, yes, dry_run, skip_validation, backup, archive: false, keep_whitespace, include_settings, strict, map, map_file, exclude_keys, rewrite_home, flatten, flatten_separator, flatten_arrays } => {
+            let flatten = flatten_options(flatten, flatten_separator, &flatten_arrays);
+            let options = ImportCommandOptions {
+                file, force, merge, replace_store, yes, dry_run, skip_validation, backup, keep_whitespace,
+                include_settings, strict, map, map_file, exclude_keys, rewrite_home, flatten,
+            };
+            handle_import_command(output, config_manager, options, verbose)?;
+        }
+        Commands::Sync { file, apply_all_missing } => {
+            handle_sync_command(output, config_manager, file, apply_all_missing, verbose)?;
+        }
+        Commands::Watch { dotenv, debounce, once } => {
+            handle_watch_command(output, config_manager, env_manager, dotenv, debounce, once, verbose)?;
         }
-        Commands::Use { alias, dry_run } => {
-            handle_use_command(&config_manager, &env_manager, alias, dry_run, verbose)?;
+        Commands::Convert { input, output: output_file, from, to } => {
+            handle_convert_command(output, input, output_file, from, to, verbose)?;
         }
-        Commands::List { verbose: list_verbose, table, active } => {
-            handle_list_command(&config_manager, list_verbose || verbose, table, active)?;
+        Commands::Pin { alias } => {
+            handle_pin_command(output, config_manager, alias, true, verbose)?;
         }
-        Commands::Status { claude, table, mismatched } => {
-            handle_status_command(&config_manager, &env_manager, claude, table, mismatched, verbose)?;
+        Commands::Unpin { alias } => {
+            handle_pin_command(output, config_manager, alias, false, verbose)?;
         }
-        Commands::Edit { alias } => {
-            handle_edit_command(&config_manager, alias, verbose)?;
+        Commands::SetVar { alias, key, value, force, keep_whitespace } => {
+            handle_set_var_command(output, config_manager, alias, key, value, force, keep_whitespace, verbose)?;
         }
-        Commands::Delete { alias, force, verbose: cmd_verbose } => {
-            handle_delete_command(&config_manager, alias, force, verbose || cmd_verbose)?;
+        Commands::UnsetVar { alias, key, force } => {
+            handle_unset_var_command(output, config_manager, alias, key, force, verbose)?;
         }
-        Commands::Export { output, configs, format, metadata, pretty } => {
-            handle_export_command(&config_manager, output, configs, format, metadata, pretty, verbose)?;
+        Commands::Protect { alias } => {
+            handle_protect_command(output, config_manager, alias, true, verbose)?;
         }
-        Commands::Import { file, force, merge, dry_run, skip_validation, backup } => {
-            handle_import_command(&config_manager, file, force, merge, dry_run, skip_validation, backup, verbose)?;
+        Commands::Unprotect { alias } => {
+            handle_protect_command(output, config_manager, alias, false, verbose)?;
         }
-        Commands::Setup { shell, generate, output, install, wrapper } => {
-            handle_setup_command(&env_manager, shell, generate, output, install, wrapper, verbose)?;
+        Commands::Archive { alias } => {
+            handle_archive_command(output, config_manager, alias, true, verbose)?;
         }
-        Commands::Init { shell, completions } => {
-            handle_init_command(&env_manager, shell, completions, verbose)?;
+        Commands::Unarchive { alias } => {
+            handle_archive_command(output, config_manager, alias, false, verbose)?;
+        }
+        Commands::Prune { expired, force } => {
+            handle_prune_command(output, config_manager, expired, force, verbose)?;
+        }
+        Commands::Alias { action } => {
+            handle_alias_command(output, config_manager, action, verbose)?;
+        }
+        Commands::Log { limit } => {
+            handle_log_command(output, config_manager, limit, verbose)?;
+        }
+        Commands::HistoryVar { alias, key, reveal, json } => {
+            handle_history_var_command(output, config_manager, alias, key, reveal, json, verbose)?;
+        }
+        Commands::Revert { commit, force } => {
+            handle_revert_command(output, config_manager, commit, force, verbose)?;
+        }
+        Commands::Env { alias, active: _, null, json } => {
+            if !handle_env_command(output, config_manager, alias, null, json)? {
+                std::process::exit(EXIT_CODE_NOT_FOUND);
+            }
+        }
+        Commands::Render { output: output_file, shell, alias, unset_script, force } => {
+            handle_render_command(output, config_manager, output_file, shell, alias, unset_script, force, verbose)?;
+        }
+        Commands::Paths { json } => {
+            handle_paths_command(output, config_manager, json, verbose)?;
+        }
+        Commands::Audit { json, repair } => {
+            if !handle_audit_command(output, config_manager, json, repair, verbose)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Current { json, clear } => {
+            if !handle_current_command(output, config_manager, json, clear, verbose)? {
+                std::process::exit(EXIT_CODE_NO_ACTIVE);
+            }
+        }
+        Commands::Prompt => {
+            handle_prompt_command(config_manager)?;
+        }
+        Commands::Doctor { repair, keep } => {
+            handle_doctor_command(output, config_manager, repair, keep, verbose)?;
+        }
+        Commands::MigrateStorage { layout } => {
+            handle_migrate_storage_command(output, config_manager, layout, verbose)?;
+        }
+        Commands::Default { action } => {
+            handle_default_command(output, config_manager, action, verbose)?;
+        }
+        Commands::Shellenv { shell, activate } => {
+            handle_shellenv_command(output, config_manager, shell, activate, verbose)?;
+        }
+        Commands::Backup { action: BackupAction::Diff { target, latest, detailed } } => {
+            let differs = handle_backup_diff_command(output, config_manager, target, latest, detailed, verbose)?;
+            if differs {
+                std::process::exit(1);
+            }
+        }
+        Commands::Backup { action } => {
+            handle_backup_command(output, config_manager, action, verbose)?;
+        }
+        Commands::Settings { action } => {
+            handle_settings_command(output, config_manager, action, verbose)?;
+        }
+        Commands::Setup { shell, generate, output: output_file, install, wrapper } => {
+            handle_setup_command(output, env_manager, shell, generate, output_file, install, wrapper, verbose)?;
+        }
+        Commands::Init { shell, non_interactive, install, create, preset, from_env, set_default } => {
+            let options = InitOptions { shell, non_interactive, install, create, preset, from_env, set_default };
+            handle_init_command(output, config_manager, options, verbose)?;
+        }
+        Commands::Examples { topic } => {
+            handle_examples_command(output, topic, verbose)?;
         }
         Commands::Tutorial { advanced, use_case } => {
-            handle_tutorial_command(advanced, use_case, verbose)?;
+            handle_tutorial_command(output, advanced, use_case, verbose)?;
         }
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Flattening options of `set --file` and `import`, when --flatten was given
+fn flatten_options(flatten: bool, separator: String, arrays: &str) -> Option<FlattenOptions> {
+    flatten.then(|| FlattenOptions { separator, index_arrays: arrays == "index" })
+}
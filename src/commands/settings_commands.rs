@@ -0,0 +1,77 @@
+use crate::cli::SettingsAction;
+use crate::config::FileConfigManager;
+use crate::types::constants::CLAUDE_ENV_VARS;
+use std::error::Error;
+use crate::output::OutputContext;
+
+/// Handle the settings command to view and change user preferences
+pub fn handle_settings_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    action: SettingsAction,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    match action {
+        SettingsAction::Show => {
+            let settings = config_manager.get_settings()?;
+
+            outln!(output, "Claude variables (built-in):");
+            for var in CLAUDE_ENV_VARS {
+                outln!(output, "  {}", var);
+            }
+
+            outln!(output, "skip-verification: {}", settings.skip_verification);
+            outln!(output, "git-track: {}", settings.git_track);
+            outln!(output, "lazy-secrets: {}", settings.lazy_secrets);
+            outln!(output, "skip-integrity-check: {}", settings.skip_integrity_check);
+            outln!(output, "skip-duplicate-check: {}", settings.skip_duplicate_check);
+            outln!(output, "no-emoji: {}", settings.no_emoji);
+            outln!(output, "mask-length-hints: {}", settings.mask_length_hints);
+            outln!(output, "utc-timestamps: {}", settings.utc_timestamps);
+            outln!(output, "exclude-keys: {}", if settings.exclude_keys.is_empty() { "none".to_string() } else { settings.exclude_keys.join(",") });
+            outln!(output, "drift-watch: {}", if settings.drift_watch.is_empty() { "none".to_string() } else { settings.drift_watch.join(",") });
+            outln!(output, "language: {}", settings.language.as_deref().unwrap_or("en"));
+            outln!(output, "storage-layout: {}", settings.storage_layout);
+            outln!(output, "list-columns: {}", settings.list_columns.as_deref().unwrap_or("name,description,vars,active,updated"));
+            outln!(output, "backup-dir: {}", config_manager.backup_dir().display());
+            outln!(output, "default: {}", settings.default_config.as_deref().unwrap_or("none (change with 'envswitch default set')"));
+
+            if settings.claude_variables.is_empty() {
+                outln!(output, "Claude variables (added): none");
+                outln!(output, "💡 Add one with: envswitch settings watch-claude <NAME>");
+            } else {
+                outln!(output, "Claude variables (added):");
+                for var in &settings.claude_variables {
+                    outln!(output, "  {}", var);
+                }
+            }
+        }
+        SettingsAction::WatchClaude { name } => {
+            if config_manager.watch_claude_variable(&name)? {
+                outln!(output, "✅ '{}' added to the Claude variable watchlist", name);
+                if verbose {
+                    outln!(output, "   It now appears in 'envswitch status --claude' and marks configurations as Claude configurations");
+                }
+            } else {
+                outln!(output, "ℹ️  '{}' is already on the Claude variable watchlist", name);
+            }
+        }
+        SettingsAction::UnwatchClaude { name } => {
+            config_manager.unwatch_claude_variable(&name)?;
+            outln!(output, "✅ '{}' removed from the Claude variable watchlist", name);
+        }
+        SettingsAction::Set { key, value } => {
+            config_manager.set_setting(&key, &value)?;
+            outln!(output, "✅ Setting '{}' set to '{}'", key, value);
+
+            if key == "git-track" && config_manager.get_settings()?.git_track {
+                outln!(output, "⚠️  config.json holds your secrets in clear text, and every version is now kept in git history.");
+                outln!(output, "   Only push the repository to remotes you fully trust, and never to a public one.");
+                outln!(output, "💡 Repository: {}", config_manager.config_paths().history_dir().display());
+                outln!(output, "💡 View history with 'envswitch log' and restore with 'envswitch revert <commit>'");
+            }
+        }
+    }
+
+    Ok(())
+}
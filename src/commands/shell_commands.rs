@@ -1,32 +1,22 @@
 use crate::env::ShellEnvironmentManager;
+use crate::output::OutputContext;
 use std::error::Error;
 use std::io::{self, Write};
 
 /// Handle the setup command to show shell integration instructions
+#[allow(clippy::too_many_arguments)]
 pub fn handle_setup_command(
+    output: &OutputContext,
     env_manager: &ShellEnvironmentManager,
     shell: Option<String>,
     generate: bool,
-    output: Option<String>,
+    output_file: Option<String>,
     install: bool,
     wrapper: bool,
     verbose: bool,
 ) -> Result<(), Box<dyn Error>> {
     // This function will be moved from main.rs
     // For now, return a placeholder
-    println!("Setup command - to be implemented");
+    outln!(output, "Setup command - to be implemented");
     Ok(())
 }
-
-/// Handle the init command to generate shell initialization code
-pub fn handle_init_command(
-    env_manager: &ShellEnvironmentManager,
-    shell: Option<String>,
-    completions: bool,
-    verbose: bool,
-) -> Result<(), Box<dyn Error>> {
-    // This function will be moved from main.rs
-    // For now, return a placeholder
-    println!("Init command - to be implemented");
-    Ok(())
-}
\ No newline at end of file
@@ -0,0 +1,41 @@
+use crate::config::{FileConfigManager, ConfigManager, StorageLayout};
+use crate::error::ConfigError;
+use std::error::Error;
+use crate::output::OutputContext;
+
+/// Handle the migrate-storage command to move the store to another storage layout
+pub fn handle_migrate_storage_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    layout: String,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let target = StorageLayout::parse(&layout)
+        .ok_or_else(|| format!("Unknown storage layout '{}'. Available layouts: {}", layout, StorageLayout::NAMES.join(", ")))?;
+    let current = config_manager.get_settings()?.storage_layout;
+    if current == target {
+        outln!(output, "ℹ️  The store already uses the {} storage layout", target);
+        return Ok(());
+    }
+
+    if config_manager.config_file_exists() {
+        let backup = config_manager.backup_config_with_note(Some(&format!("before migrating to the {} storage layout", target)))?;
+        if verbose {
+            outln!(output, "💾 Backup created: {}", backup.display());
+        }
+    }
+
+    config_manager.set_setting("storage-layout", &target.to_string()).map_err(|e| ConfigError::MigrationFailed {
+        layout: target.to_string(),
+        reason: e.to_string(),
+    })?;
+
+    let count = config_manager.list_configs()?.len();
+    outln!(output, "✅ Moved {} configurations to the {} storage layout", count, target);
+    match target {
+        StorageLayout::Split => outln!(output, "📁 Configurations: {}", config_manager.split_manager().configs_dir().display()),
+        StorageLayout::Single => outln!(output, "📄 Store: {}", config_manager.config_file_path().display()),
+    }
+
+    Ok(())
+}
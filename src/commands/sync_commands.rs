@@ -0,0 +1,141 @@
+use crate::config::{FileConfigManager, ImportOptions};
+use crate::commands::import_format;
+use crate::diff::VariableChange;
+use crate::utils::file_utils::detect_file_format;
+use crate::utils::feedback::{display_operation_report, OperationReport};
+use crate::utils::{is_sensitive_key, mask_sensitive_value, prompt_confirmation};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::Instant;
+use crate::output::OutputContext;
+
+/// Handle the sync command comparing the store with a shared export and applying selected changes
+pub fn handle_sync_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    file: String,
+    apply_all_missing: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let start_time = Instant::now();
+    let path = Path::new(&file);
+    if !path.exists() {
+        return Err(format!("File '{}' not found", file).into());
+    }
+    let detected = {
+        let _timer = output.execution.timings.step("detect_format");
+        detect_file_format(path)?
+    };
+    let format = import_format(&detected, &file)?;
+
+    let (diff, shared) = config_manager.diff_with_file(path, format)?;
+    if verbose {
+        outln!(output, "📁 Shared file: {} ({} configuration(s))", path.display(), shared.configs.len());
+    }
+    if diff.is_empty() {
+        outln!(output, "✅ Your configurations match {}", file);
+        return Ok(());
+    }
+
+    let shown = |key: &str, value: &str| if is_sensitive_key(key) { mask_sensitive_value(output, value) } else { value.to_string() };
+    outln!(output, "🔍 Comparing with {}:", file);
+    if !diff.added.is_empty() {
+        outln!(output, "\n📥 Only in {} ({}):", file, diff.added.len());
+        for name in &diff.added {
+            match shared.configs.get(name).and_then(|config| config.description.as_deref()) {
+                Some(description) => outln!(output, "  + {} - {}", name, description),
+                None => outln!(output, "  + {}", name),
+            }
+        }
+    }
+    if !diff.removed.is_empty() {
+        outln!(output, "\nℹ️  Only in your store ({}), left as they are:", diff.removed.len());
+        for name in &diff.removed {
+            outln!(output, "  • {}", name);
+        }
+    }
+    if !diff.changed.is_empty() {
+        outln!(output, "\n🔄 Different ({}):", diff.changed.len());
+        for config in &diff.changed {
+            let (added, changed, removed) = config.counts();
+            outln!(output, "  ~ {} ({} new, {} changed, {} only yours)", config.name, added, changed, removed);
+            for change in &config.changes {
+                match change {
+                    VariableChange::Added { key, value } => outln!(output, "      + {}={}", key, shown(key, value)),
+                    VariableChange::Removed { key, value } => outln!(output, "      - {}={} (kept on update)", key, shown(key, value)),
+                    VariableChange::Changed { key, old, new } => {
+                        outln!(output, "      ~ {}: {} → {}", key, shown(key, old), shown(key, new));
+                    }
+                }
+            }
+        }
+    }
+    outln!(output);
+
+    let mut selected: Vec<String> = Vec::new();
+    if apply_all_missing {
+        selected.extend(diff.added.iter().cloned());
+    } else if std::io::stdin().is_terminal() {
+        for name in &diff.added {
+            if prompt_confirmation(output, &format!("Import '{}'?", name), false)? {
+                selected.push(name.clone());
+            }
+        }
+        for config in &diff.changed {
+            if prompt_confirmation(output, &format!("Update '{}' with the values of {}?", config.name, file), false)? {
+                selected.push(config.name.clone());
+            }
+        }
+    } else {
+        outln!(output, "💡 Nothing changed. Run this in a terminal to choose what to apply,");
+        outln!(output, "   or import every missing configuration with: envswitch sync {} --apply-all-missing", file);
+    }
+
+    let mut imported = 0;
+    let mut updated = 0;
+    let mut errors = Vec::new();
+    if !selected.is_empty() {
+        let options = ImportOptions {
+            format,
+            force_overwrite: false,
+            merge_existing: true,
+            replace_store: false,
+            skip_validation: false,
+            dry_run: false,
+            keep_whitespace: false,
+            strict: false,
+            key_map: HashMap::new(),
+            exclude_keys: config_manager.get_settings()?.exclude_keys,
+            rewrite_home: false,
+            configs: Some(selected),
+            flatten: None,
+        };
+        let result = config_manager.import_from_file_with_options(path, &options)?;
+        for name in &result.imported {
+            if diff.added.contains(name) {
+                imported += 1;
+                outln!(output, "📥 Imported '{}'", name);
+            } else {
+                updated += 1;
+                outln!(output, "🔄 Updated '{}'", name);
+            }
+        }
+        for error in &result.errors {
+            eoutln!(output, "❌ Skipped {}", error);
+        }
+        errors = result.errors;
+    }
+
+    let report = OperationReport::new("Sync", imported + updated, start_time.elapsed())
+        .with_errors(errors.len())
+        .with_detail("Only in file", diff.added.len())
+        .with_detail("Only local", diff.removed.len())
+        .with_detail("Different", diff.changed.len())
+        .with_detail("Imported", imported)
+        .with_detail("Updated", updated);
+    display_operation_report(output, &report);
+
+    Ok(())
+}
@@ -1,13 +1,69 @@
+use crate::examples::{for_shell, recipe, Recipe, RECIPES};
+use crate::shell::{ShellDetector, ShellType};
 use std::error::Error;
+use crate::output::OutputContext;
 
 /// Handle tutorial command to show getting started guide and examples
+///
+/// Shows the switching recipe, every recipe with `advanced`, or the one of `use_case`.
 pub fn handle_tutorial_command(
+    output: &OutputContext,
     advanced: bool,
     use_case: Option<String>,
     verbose: bool,
 ) -> Result<(), Box<dyn Error>> {
-    // This function will be moved from main.rs
-    // For now, return a placeholder
-    println!("Tutorial command - to be implemented");
+    let shell = ShellDetector::detect_shell();
+    if verbose {
+        outln!(output, "🔍 Snippets written for {}", shell);
+    }
+
+    let recipes: Vec<&Recipe> = match use_case {
+        Some(topic) => vec![find_recipe(&topic)?],
+        None if advanced => RECIPES.iter().collect(),
+        None => RECIPES.iter().take(1).collect(),
+    };
+    outln!(output, "🚀 Getting started with envswitch");
+    for recipe in recipes {
+        outln!(output);
+        print_recipe(output, recipe, &shell);
+    }
+    if !advanced {
+        outln!(output, "\n💡 More recipes: envswitch tutorial --advanced, or envswitch examples <topic>");
+    }
+    Ok(())
+}
+
+/// Handle the examples command printing task-oriented recipes
+pub fn handle_examples_command(output: &OutputContext, topic: Option<String>, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let shell = ShellDetector::detect_shell();
+    if verbose {
+        outln!(output, "🔍 Snippets written for {}", shell);
+    }
+
+    match topic {
+        Some(topic) => print_recipe(output, find_recipe(&topic)?, &shell),
+        None => {
+            outln!(output, "📋 Recipes:");
+            for recipe in RECIPES {
+                outln!(output, "   • {} - {}", recipe.topic, recipe.title);
+            }
+            outln!(output, "\n💡 Show one with: envswitch examples <topic>");
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+fn find_recipe(topic: &str) -> Result<&'static Recipe, Box<dyn Error>> {
+    recipe(topic).ok_or_else(|| {
+        let topics: Vec<&str> = RECIPES.iter().map(|recipe| recipe.topic).collect();
+        format!("Unknown topic '{}'. Topics: {}", topic, topics.join(", ")).into()
+    })
+}
+
+fn print_recipe(output: &OutputContext, recipe: &Recipe, shell: &ShellType) {
+    outln!(output, "📝 {}", recipe.title);
+    for step in recipe.steps {
+        outln!(output, "   # {}", step.description);
+        outln!(output, "   {}", for_shell(step.command, shell));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::config::{FileConfigManager, ConfigManager, EnvConfig};
+use crate::handlers::{read_hidden_line, trim_values_with_notice, warn_suspicious_tokens};
+use crate::output::OutputContext;
+use crate::utils::{config_not_found, is_sensitive_key, mask_sensitive_value};
+use std::error::Error;
+use std::io;
+
+/// Handle the set-var command to change a single variable of a configuration
+///
+/// Prompts for the value when none is given; an empty answer changes nothing.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_set_var_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    alias: String,
+    key: String,
+    value: Option<String>,
+    force: bool,
+    keep_whitespace: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config = modifiable_config(config_manager, &alias, force)?;
+    let current = config.variables.get(&key);
+    let sensitive = is_sensitive_key(&key);
+
+    let value = match value {
+        Some(value) => value,
+        None => {
+            match current {
+                Some(current) if sensitive => outln!(output, "Current value of {}: {}", key, mask_sensitive_value(output, current)),
+                Some(current) => outln!(output, "Current value of {}: {}", key, current),
+                None => outln!(output, "{} is not set in '{}'", key, config.alias),
+            }
+            output.execution.check_prompt(&format!("New value for {}", key))?;
+            output.prompt(&format!("New value for {}{}: ", key, if sensitive { " (input hidden)" } else { "" }))?;
+
+            let value = if sensitive {
+                read_hidden_line(output)?
+            } else {
+                let mut value = String::new();
+                io::stdin().read_line(&mut value)?;
+                value.trim().to_string()
+            };
+            if value.is_empty() {
+                outln!(output, "❌ No value entered; '{}' was not changed.", config.alias);
+                return Ok(());
+            }
+            value
+        }
+    };
+
+    let existed = current.is_some();
+    let mut variables = [(key.clone(), value)].into_iter().collect();
+    if !keep_whitespace {
+        trim_values_with_notice(output, &config.alias, &mut variables);
+    }
+    warn_suspicious_tokens(output, &config.alias, &variables);
+    if !config_manager.set_config_variable(&config.alias, &key, &variables[&key])? {
+        outln!(output, "ℹ️  {} already has that value; '{}' was not changed.", key, config.alias);
+        return Ok(());
+    }
+
+    if existed {
+        outln!(output, "✅ Updated {} in '{}'", key, config.alias);
+    } else {
+        outln!(output, "✅ Added {} to '{}'", key, config.alias);
+    }
+    if verbose && config_manager.get_active_config()?.as_deref() == Some(config.alias.as_str()) {
+        outln!(output, "💡 Re-run: {} to apply it to your shell", crate::examples::activation_command(&config.alias));
+    }
+
+    Ok(())
+}
+
+/// Handle the unset-var command to remove a single variable from a configuration
+pub fn handle_unset_var_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    alias: String,
+    key: String,
+    force: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config = modifiable_config(config_manager, &alias, force)?;
+
+    if !config_manager.unset_config_variable(&config.alias, &key)? {
+        outln!(output, "ℹ️  {} is not set in '{}'; nothing changed.", key, config.alias);
+        return Ok(());
+    }
+
+    outln!(output, "✅ Removed {} from '{}'", key, config.alias);
+    if verbose && config_manager.get_active_config()?.as_deref() == Some(config.alias.as_str()) {
+        outln!(output, "💡 {} stays set in your shell until you run: unset {}", key, key);
+    }
+
+    Ok(())
+}
+
+/// Load a configuration by name or alias, refusing protected ones without `force`
+fn modifiable_config(config_manager: &FileConfigManager, alias: &str, force: bool) -> Result<EnvConfig, Box<dyn Error>> {
+    let name = config_manager.resolve_config_name(alias)?.unwrap_or_else(|| alias.to_string());
+    let Some(config) = config_manager.get_config(&name)? else {
+        return Err(config_not_found(&name, &config_manager.list_names_and_aliases()?).into());
+    };
+
+    if config.protected && !force {
+        return Err(format!(
+            "Configuration '{}' is protected. Use --force to change it anyway, or 'envswitch unprotect {}'",
+            config.alias, config.alias
+        ).into());
+    }
+
+    Ok(config)
+}
@@ -0,0 +1,241 @@
+use crate::config::{FileConfigManager, ConfigManager};
+use crate::env::{ShellEnvironmentManager, EnvironmentManager, resolve_lazy_references, resolve_variables};
+use crate::shell::ListOp;
+use crate::utils::file_utils::write_env_file;
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+use crate::output::OutputContext;
+
+/// Set by the SIGINT handler to stop the watch loop
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// How often the watch loop checks for a stop request while idle
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Active configuration as last emitted by the watcher
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ActiveSnapshot {
+    name: Option<String>,
+    variables: HashMap<String, String>,
+    list_ops: HashMap<String, ListOp>,
+}
+
+/// Handle the watch command to keep shells or a dotenv file in sync with config.json
+pub fn handle_watch_command(
+    output: &OutputContext,
+    config_manager: &FileConfigManager,
+    env_manager: &ShellEnvironmentManager,
+    dotenv: Option<String>,
+    debounce: u64,
+    once: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config_file = config_manager.config_file_path().to_path_buf();
+    install_sigint_handler();
+
+    // Bring the caller up to date before waiting for changes
+    let mut last = load_snapshot(config_manager)?;
+    emit_snapshot(env_manager, &ActiveSnapshot::default(), &last, dotenv.as_deref())?;
+
+    eoutln!(output, "👀 Watching {} (Ctrl-C to stop)", config_file.display());
+
+    watch_file(output, &config_file, Duration::from_millis(debounce), once, || {
+        let current = match load_snapshot(config_manager) {
+            Ok(current) => current,
+            Err(e) => {
+                // A half-written file from a sync tool is retried on the next change
+                eoutln!(output, "⚠️  Could not reload configuration: {}", e);
+                return Ok(false);
+            }
+        };
+
+        if current == last {
+            if verbose {
+                eoutln!(output, "ℹ️  config.json changed but the active configuration did not");
+            }
+            return Ok(false);
+        }
+
+        emit_snapshot(env_manager, &last, &current, dotenv.as_deref())?;
+        eoutln!(output, "🔄 Refreshed '{}'", current.name.as_deref().unwrap_or("(none)"));
+        last = current;
+        Ok(true)
+    })?;
+
+    eoutln!(output, "👋 Stopped watching");
+    Ok(())
+}
+
+/// Read the active configuration with file and lazy references resolved
+fn load_snapshot(config_manager: &FileConfigManager) -> Result<ActiveSnapshot, Box<dyn Error>> {
+    let Some(name) = config_manager.get_active_config()? else {
+        return Ok(ActiveSnapshot::default());
+    };
+    let Some(config) = config_manager.get_config(&name)? else {
+        return Ok(ActiveSnapshot::default());
+    };
+
+    let lazy_allowed = config_manager.get_settings()?.lazy_secrets;
+    let variables = resolve_variables(&resolve_lazy_references(&config.variables, lazy_allowed)?)?;
+
+    Ok(ActiveSnapshot {
+        name: Some(name),
+        variables,
+        list_ops: config.list_ops,
+    })
+}
+
+/// Print shell commands moving from `previous` to `current`, or rewrite the dotenv file
+fn emit_snapshot(
+    env_manager: &ShellEnvironmentManager,
+    previous: &ActiveSnapshot,
+    current: &ActiveSnapshot,
+    dotenv: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = dotenv {
+        write_env_file(Path::new(path), &current.variables)?;
+        return Ok(());
+    }
+
+    let mut command_groups = Vec::new();
+    if !previous.list_ops.is_empty() {
+        command_groups.push(env_manager.generate_list_removal_commands(&previous.variables, &previous.list_ops)?);
+    }
+
+    let mut removed: Vec<String> = previous.variables.keys()
+        .filter(|key| !current.variables.contains_key(*key) && !previous.list_ops.contains_key(*key))
+        .cloned()
+        .collect();
+    removed.sort();
+    command_groups.push(env_manager.generate_unset_commands(&removed)?);
+
+    if !current.variables.is_empty() {
+        command_groups.push(env_manager.generate_config_commands(&current.variables, &current.list_ops)?);
+    }
+
+    // One command per line so `while read` loops can eval them as they arrive
+    for line in command_groups.iter().flat_map(|group| group.lines()).filter(|line| !line.trim().is_empty()) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Call `on_change` after `path` changes, waiting `debounce` for the writes to settle
+///
+/// Returns when SIGINT is received, or after the first handled change with `once`.
+/// `on_change` returns whether it acted on the change.
+fn watch_file(
+    output: &OutputContext,
+    path: &Path,
+    debounce: Duration,
+    once: bool,
+    mut on_change: impl FnMut() -> Result<bool, Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })?;
+
+    // Watch the directory since saves replace config.json through a rename
+    let dir = path.parent().ok_or("Configuration file has no parent directory")?;
+    let file_name = path.file_name().ok_or("Configuration file has no file name")?;
+    // Nothing has been saved yet on first use
+    std::fs::create_dir_all(dir)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    while !STOP_REQUESTED.load(Ordering::SeqCst) {
+        let event: notify::Event = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => event,
+            Ok(Err(e)) => {
+                eoutln!(output, "⚠️  Watch error: {}", e);
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        if event.kind.is_access() || !event.paths.iter().any(|p| p.file_name() == Some(file_name)) {
+            continue;
+        }
+
+        // Collapse bursts of writes into a single refresh
+        while rx.recv_timeout(debounce).is_ok() {}
+
+        if on_change()? && once {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn install_sigint_handler() {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        STOP_REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_sigint_handler() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_watch_file_once_stops_after_change() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, "{}").unwrap();
+
+        let (done_tx, done_rx) = mpsc::channel();
+        let watched = path.clone();
+        std::thread::spawn(move || {
+            let mut calls = 0;
+            let result = watch_file(&OutputContext::default(), &watched, Duration::from_millis(50), true, || {
+                calls += 1;
+                Ok(true)
+            });
+            done_tx.send((result.is_ok(), calls)).unwrap();
+        });
+
+        // Unrelated files are ignored
+        std::thread::sleep(Duration::from_millis(300));
+        fs::write(dir.path().join("other.json"), "{}").unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(done_rx.try_recv().is_err());
+
+        fs::write(&path, "{\"changed\": true}").unwrap();
+        let (ok, calls) = done_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(ok);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_emit_snapshot_writes_dotenv() {
+        let dir = TempDir::new().unwrap();
+        let dotenv = dir.path().join("active.env");
+
+        let mut current = ActiveSnapshot { name: Some("dev".to_string()), ..Default::default() };
+        current.variables.insert("API_URL".to_string(), "https://dev.example.com".to_string());
+
+        let manager = ShellEnvironmentManager::new();
+        emit_snapshot(&manager, &ActiveSnapshot::default(), &current, dotenv.to_str()).unwrap();
+
+        let content = fs::read_to_string(&dotenv).unwrap();
+        assert!(content.contains("API_URL=https://dev.example.com"));
+    }
+}
@@ -5,12 +5,19 @@ use std::fs;
 use chrono::{DateTime, Utc};
 use crate::error::{ConfigError, ConfigResult};
 use crate::types::ConfigPaths;
+use crate::shell::ListOp;
+use crate::split_store::SplitFileConfigManager;
+use crate::output::OutputContext;
+use crate::timing::Timings;
 
 #[derive(Debug, Clone, Copy)]
 pub enum ExportFormat {
     Json,
     Env,
     Yaml,
+    /// A script of `envswitch set` commands that recreates the configurations;
+    /// redacted sensitive values are read from the environment when it runs
+    Shell { redact_secrets: bool },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -26,6 +33,103 @@ pub struct ExportOptions {
     pub include_metadata: bool,
     pub pretty_print: bool,
     pub configs: Option<Vec<String>>,
+    /// Replace `@file:` references with the contents of the referenced files
+    pub resolve_files: bool,
+    /// Embed a SHA-256 checksum so corrupted or truncated files are detected on import
+    pub checksum: bool,
+    /// Export only configurations changed since this baseline
+    pub baseline: Option<ExportBaseline>,
+    /// Embed the settings document so `import --include-settings` can apply it
+    pub include_settings: bool,
+    /// Glob patterns of variables to leave out, see [`crate::utils::glob_match`]
+    pub exclude_keys: Vec<String>,
+    /// Write sensitive values as `{{KEY}}` placeholders and mark every configuration as a template
+    pub template: bool,
+}
+
+/// What an export left out
+#[derive(Debug, Clone, Default)]
+pub struct ExportResult {
+    /// Variables matching an exclude pattern, as "config:KEY"
+    pub excluded: Vec<String>,
+    /// Sensitive variables written as placeholders by a template export, as "config:KEY"
+    pub placeholders: Vec<String>,
+}
+
+/// What a partial export is compared against
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportBaseline {
+    /// Configurations updated after this instant
+    Since(DateTime<Utc>),
+    /// Configurations missing from or different in this backup file
+    Backup(std::path::PathBuf),
+}
+
+impl ExportBaseline {
+    /// Human-readable description written into partial exports
+    pub fn describe(&self) -> String {
+        match self {
+            ExportBaseline::Since(since) => format!("changed since {}", since.to_rfc3339()),
+            ExportBaseline::Backup(path) => format!(
+                "changed since backup {}",
+                path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// Key holding the baseline of a partial JSON export
+pub const PARTIAL_EXPORT_KEY: &str = "partial_export";
+
+/// Comment line marking a partial ENV export
+pub const PARTIAL_EXPORT_COMMENT: &str = "# Partial export:";
+
+/// The document of a JSON or YAML export, which are the same serialized store
+fn export_document(content: &str) -> Option<serde_json::Value> {
+    serde_json::from_str::<serde_json::Value>(content).ok()
+        .or_else(|| serde_yaml::from_str(content).ok())
+        .filter(|value| value.get("configs").is_some())
+}
+
+/// Baseline description of a partial export, or None for a full export
+pub fn partial_export_baseline(content: &str) -> Option<String> {
+    if let Some(value) = export_document(content) {
+        return value.get(PARTIAL_EXPORT_KEY)?.get("baseline")?.as_str().map(str::to_string);
+    }
+    content.lines()
+        .find_map(|line| line.strip_prefix(PARTIAL_EXPORT_COMMENT))
+        .map(|baseline| baseline.trim().to_string())
+}
+
+/// Key of the metadata block of JSON and YAML exports, holding the settings document
+pub const EXPORT_METADATA_KEY: &str = "metadata";
+
+/// Comment line carrying one setting in ENV exports
+pub const SETTINGS_EXPORT_COMMENT: &str = "# Setting:";
+
+/// Settings by their command-line name, e.g. "git-track" → "true"
+pub type SettingsDocument = std::collections::BTreeMap<String, String>;
+
+/// Settings document embedded by `export --include-settings`, if any
+pub fn exported_settings(content: &str) -> Option<SettingsDocument> {
+    if let Some(value) = export_document(content) {
+        let settings = value.get(EXPORT_METADATA_KEY)?.get("settings")?;
+        return serde_json::from_value(settings.clone()).ok();
+    }
+    let document: SettingsDocument = content.lines()
+        .filter_map(|line| line.strip_prefix(SETTINGS_EXPORT_COMMENT))
+        .filter_map(|setting| setting.trim().split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .collect();
+    (!document.is_empty()).then_some(document)
+}
+
+/// A setting an imported settings document changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingChange {
+    pub key: String,
+    pub old: String,
+    pub new: String,
 }
 
 #[derive(Debug, Clone)]
@@ -33,15 +137,183 @@ pub struct ImportOptions {
     pub format: ImportFormat,
     pub force_overwrite: bool,
     pub merge_existing: bool,
+    /// Start from an empty store, dropping every configuration that is not in the file
+    pub replace_store: bool,
     pub skip_validation: bool,
     pub dry_run: bool,
+    /// Store values verbatim instead of trimming whitespace and control characters
+    pub keep_whitespace: bool,
+    /// Fail the whole import when any configuration is invalid instead of skipping it
+    pub strict: bool,
+    /// Variables to rename, old name to new name, see [`build_key_map`]
+    pub key_map: HashMap<String, String>,
+    /// Glob patterns of variables to leave out, matched after renaming
+    pub exclude_keys: Vec<String>,
+    /// Replace paths into another user's home directory with the local home directory
+    pub rewrite_home: bool,
+    /// Import only these configurations of the file
+    pub configs: Option<Vec<String>>,
+    /// Read a JSON file of nested variables instead of an export, as one configuration named "imported"
+    pub flatten: Option<crate::utils::file_utils::FlattenOptions>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ImportResult {
     pub imported: Vec<String>,
     pub conflicts: Vec<String>,
+    /// Skipped configurations, as "config: KEY: reason"
     pub errors: Vec<String>,
+    /// Variables whose values were trimmed, as "config:KEY"
+    pub trimmed: Vec<String>,
+    /// Existing configurations dropped because the import replaced the store
+    pub removed: Vec<String>,
+    /// Variables renamed by the key map, as (config, old name, new name)
+    pub renamed: Vec<(String, String, String)>,
+    /// Variables matching an exclude pattern, as "config:KEY"
+    pub excluded: Vec<String>,
+    /// Values that may only work on the exporting machine, as "config:KEY reason"
+    pub host_warnings: Vec<String>,
+    /// Variables whose foreign home directory was replaced by `rewrite_home`, as "config:KEY"
+    pub rewritten_home: Vec<String>,
+    /// Imported configurations holding `{{KEY}}` placeholders, which `use` asks for
+    pub templates: Vec<String>,
+}
+
+/// Check the renames of `import --map`: names must be valid and no two old names may share a new one
+pub fn build_key_map(pairs: Vec<(String, String)>) -> ConfigResult<HashMap<String, String>> {
+    let mut map: HashMap<String, String> = HashMap::new();
+    for (old, new) in pairs {
+        let (old, new) = (old.trim().to_string(), new.trim().to_string());
+        for name in [&old, &new] {
+            crate::error::validate_env_var_name(name).map_err(|e| ConfigError::ValidationError(format!("Invalid key mapping {}={}: {}", old, new, e)))?;
+        }
+        if let Some((other, _)) = map.iter().find(|(other, target)| **target == new && **other != old) {
+            return Err(ConfigError::ValidationError(format!(
+                "Key mapping collision: both {} and {} map to {}", other, old, new
+            )));
+        }
+        map.insert(old, new);
+    }
+    Ok(map)
+}
+
+/// Parse a mapping file for `import --map-file`: a JSON object of old name to new name
+pub fn parse_key_map_file(content: &str) -> ConfigResult<Vec<(String, String)>> {
+    let entries: serde_json::Map<String, serde_json::Value> = serde_json::from_str(content)
+        .map_err(ConfigError::JsonError)?;
+    entries.into_iter()
+        .map(|(old, new)| match new {
+            serde_json::Value::String(new) => Ok((old, new)),
+            other => Err(ConfigError::ValidationError(format!(
+                "Mapping for {} must be a variable name, not {}", old, other
+            ))),
+        })
+        .collect()
+}
+
+/// Rename the variables of `config` with `key_map`, returning (old, new) for every rename
+fn apply_key_map(config: &mut EnvConfig, key_map: &HashMap<String, String>) -> ConfigResult<Vec<(String, String)>> {
+    let mut renames: Vec<(String, String)> = key_map.iter()
+        .filter(|(old, new)| old != new && config.variables.contains_key(*old))
+        .map(|(old, new)| (old.clone(), new.clone()))
+        .collect();
+    renames.sort();
+    
+    // A kept variable must not be overwritten by a renamed one
+    for (old, new) in &renames {
+        if config.variables.contains_key(new) && !key_map.contains_key(new) {
+            return Err(ConfigError::ValidationError(format!(
+                "{}: {} would be renamed to {}, which the configuration already has", config.alias, old, new
+            )));
+        }
+    }
+    
+    // Take all renamed variables out first, so chains like A=B and B=C rename both
+    let moved: Vec<(String, String, Option<ListOp>, Option<String>)> = renames.iter()
+        .map(|(old, new)| (
+            new.clone(),
+            config.variables.remove(old).unwrap_or_default(),
+            config.list_ops.remove(old),
+            config.variable_comments.remove(old),
+        ))
+        .collect();
+    for (new, value, list_op, comment) in moved {
+        if let Some(list_op) = list_op {
+            config.list_ops.insert(new.clone(), list_op);
+        }
+        if let Some(comment) = comment {
+            config.variable_comments.insert(new.clone(), comment);
+        }
+        config.variables.insert(new, value);
+    }
+    Ok(renames)
+}
+
+/// Remove the variables matching any of `patterns` from every configuration, returning them sorted as "config:KEY"
+fn exclude_keys(store: &mut ConfigStore, patterns: &[String]) -> Vec<String> {
+    let mut excluded = Vec::new();
+    if patterns.is_empty() {
+        return excluded;
+    }
+    for (alias, config) in store.configs.iter_mut() {
+        config.variables.retain(|key, _| {
+            let matched = crate::utils::matches_any_glob(patterns, key);
+            if matched {
+                excluded.push(format!("{}:{}", alias, key));
+            }
+            !matched
+        });
+        config.list_ops.retain(|key, _| !crate::utils::matches_any_glob(patterns, key));
+        config.variable_comments.retain(|key, _| !crate::utils::matches_any_glob(patterns, key));
+    }
+    excluded.sort();
+    excluded
+}
+
+/// Turn every configuration into a template: sensitive values become `{{KEY}}` placeholders
+///
+/// Returns the replaced variables sorted as "config:KEY"; other values are kept as they are.
+fn make_templates(store: &mut ConfigStore) -> Vec<String> {
+    let mut replaced = Vec::new();
+    for (alias, config) in store.configs.iter_mut() {
+        for (key, value) in config.variables.iter_mut() {
+            if crate::utils::is_sensitive_key(key) && crate::types::validation::placeholder_name(value).is_none() {
+                *value = crate::types::validation::template_placeholder(key);
+                replaced.push(format!("{}:{}", alias, key));
+            }
+        }
+        config.template = true;
+    }
+    replaced.sort();
+    replaced
+}
+
+/// Name of the manifest at the root of a configuration archive
+pub const ARCHIVE_MANIFEST_NAME: &str = "manifest.json";
+
+/// Directory inside a configuration archive that holds backup files
+const ARCHIVE_BACKUP_DIR: &str = "backups";
+
+/// Describes the contents of a configuration archive
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArchiveManifest {
+    /// Version of envswitch that created the archive
+    pub envswitch_version: String,
+    pub created_at: DateTime<Utc>,
+    /// Paths of the files in the archive, relative to its root
+    pub files: Vec<String>,
+}
+
+/// Outcome of importing a configuration archive
+#[derive(Debug, Clone)]
+pub struct ArchiveImportResult {
+    pub manifest: ArchiveManifest,
+    /// Configurations written to the store
+    pub imported: Vec<String>,
+    /// Configurations skipped because they already exist (merge only)
+    pub conflicts: Vec<String>,
+    /// Backup files restored into the configuration directory
+    pub restored_backups: usize,
 }
 
 /// Configuration statistics
@@ -50,12 +322,102 @@ pub struct ConfigStats {
     pub total_configs: usize,
     pub total_variables: usize,
     pub claude_configs: usize,
+    /// Archived configurations, also counted in `total_configs`
+    pub archived_configs: usize,
     pub active_config: Option<String>,
     pub backup_count: usize,
     pub last_modified: DateTime<Utc>,
     pub config_file_size: Option<u64>,
 }
 
+/// Which configurations a listing shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArchiveFilter {
+    /// Everything but archived configurations
+    #[default]
+    Unarchived,
+    /// Only archived configurations
+    Archived,
+    All,
+}
+
+impl ArchiveFilter {
+    /// From the `--archived` and `--all` flags of the listing commands
+    pub fn from_flags(archived: bool, all: bool) -> Self {
+        match (archived, all) {
+            (_, true) => ArchiveFilter::All,
+            (true, false) => ArchiveFilter::Archived,
+            (false, false) => ArchiveFilter::Unarchived,
+        }
+    }
+    
+    pub fn includes(self, config: &EnvConfig) -> bool {
+        match self {
+            ArchiveFilter::Unarchived => !config.archived,
+            ArchiveFilter::Archived => config.archived,
+            ArchiveFilter::All => true,
+        }
+    }
+}
+
+/// Where a configuration originally came from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// Created locally with set or edit
+    Manual,
+    /// Imported from a file
+    Import {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        host: Option<String>,
+        imported_at: DateTime<Utc>,
+        /// Provenance recorded in the imported file, if any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        origin: Option<Box<ConfigSource>>,
+    },
+    /// Created from a named preset
+    Preset { name: String },
+    /// Captured from a snapshot of the environment
+    Snapshot,
+}
+
+impl ConfigSource {
+    /// Create an import source for the given file, chaining any previous provenance
+    pub fn import(path: &std::path::Path, origin: Option<ConfigSource>) -> Self {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        ConfigSource::Import {
+            path: path.display().to_string(),
+            host: crate::utils::current_hostname(),
+            imported_at: Utc::now(),
+            origin: origin.map(Box::new),
+        }
+    }
+}
+
+impl ConfigSource {
+    /// Where the configuration came from, e.g. `import:/path on host (time) <- manual`,
+    /// with the time shown as `output` asks for
+    pub fn describe(&self, output: &OutputContext) -> String {
+        match self {
+            ConfigSource::Manual => "manual".to_string(),
+            ConfigSource::Import { path, host, imported_at, origin } => {
+                let mut text = format!("import:{}", path);
+                if let Some(host) = host {
+                    text.push_str(&format!(" on {}", host));
+                }
+                text.push_str(&format!(" ({})", crate::utils::format_timestamp(output, *imported_at)));
+                if let Some(origin) = origin {
+                    text.push_str(&format!(" <- {}", origin.describe(output)));
+                }
+                text
+            }
+            ConfigSource::Preset { name } => format!("preset:{}", name),
+            ConfigSource::Snapshot => "snapshot".to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EnvConfig {
     pub alias: String,
@@ -63,6 +425,39 @@ pub struct EnvConfig {
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Alternative names that resolve to this configuration
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Pinned configurations are listed before all others
+    #[serde(default)]
+    pub pinned: bool,
+    /// Protected configurations are only changed by set-var, unset-var and delete with --force
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub protected: bool,
+    /// Free-form notes, e.g. where a token came from or when it expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Related URLs such as provider consoles or documentation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    /// Where this configuration came from; `None` for configs predating provenance tracking
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ConfigSource>,
+    /// When the credentials in this configuration stop being valid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// PATH-style variables whose value is prepended or appended instead of assigned
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub list_ops: HashMap<String, ListOp>,
+    /// Shared without secrets: `use` asks for the values of `{{KEY}}` placeholders
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub template: bool,
+    /// Retired: kept in the store but left out of listings, and `use` needs --force
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+    /// Why a variable is set, e.g. "pinned until the v2 API ships"; exported as comments, never sent to the shell
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variable_comments: HashMap<String, String>,
 }
 
 impl EnvConfig {
@@ -83,6 +478,17 @@ impl EnvConfig {
             description,
             created_at: now,
             updated_at: now,
+            aliases: Vec::new(),
+            pinned: false,
+            protected: false,
+            notes: None,
+            links: Vec::new(),
+            source: Some(ConfigSource::Manual),
+            expires_at: None,
+            list_ops: HashMap::new(),
+            template: false,
+            archived: false,
+            variable_comments: HashMap::new(),
         })
     }
     
@@ -97,6 +503,10 @@ impl EnvConfig {
         if description.is_some() {
             self.description = description;
         }
+        // Drop list modes and comments for variables that no longer exist
+        let variables = &self.variables;
+        self.list_ops.retain(|key, _| variables.contains_key(key));
+        self.variable_comments.retain(|key, _| variables.contains_key(key));
         self.updated_at = Utc::now();
         Ok(())
     }
@@ -108,29 +518,487 @@ impl EnvConfig {
         format!("{} ({} variables) - {}", self.alias, var_count, desc)
     }
     
+    /// Trim whitespace and control characters from values, returning the changed keys sorted
+    pub fn clean_values(&mut self) -> Vec<String> {
+        let mut cleaned = Vec::new();
+        for (key, value) in self.variables.iter_mut() {
+            if let Some(clean) = crate::types::validation::clean_value(value) {
+                *value = clean;
+                cleaned.push(key.clone());
+            }
+        }
+        cleaned.sort();
+        cleaned
+    }
+    
+    /// Check if this configuration has passed its expiration date
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+    
+    /// Variables still holding a `{{KEY}}` placeholder of a template, sorted
+    pub fn placeholder_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.variables.iter()
+            .filter(|(_, value)| crate::types::validation::placeholder_name(value).is_some())
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+    
     /// Check if this configuration contains Claude-specific variables
-    pub fn is_claude_config(&self) -> bool {
-        self.variables.keys().any(|key| crate::types::validation::is_claude_env_var(key))
+    ///
+    /// `extra` holds the user-added names from [`Settings::claude_variables`].
+    pub fn is_claude_config(&self, extra: &[String]) -> bool {
+        self.variables.keys().any(|key| crate::types::validation::is_watched_claude_var(key, extra))
     }
     
     /// Get only Claude-specific variables from this configuration
-    pub fn claude_variables(&self) -> HashMap<String, String> {
+    pub fn claude_variables(&self, extra: &[String]) -> HashMap<String, String> {
         self.variables
             .iter()
-            .filter(|(key, _)| crate::types::validation::is_claude_env_var(key))
+            .filter(|(key, _)| crate::types::validation::is_watched_claude_var(key, extra))
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect()
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// Machine-local activation state stored in state.json next to config.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ActivationState {
+    /// Name of the active configuration, mirrored from the store so `current` reads only this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_config: Option<String>,
+    /// When the active configuration was last activated with `use`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activated_at: Option<DateTime<Utc>>,
+    /// What the shell held right after that activation, for `drift`; None means unset
+    ///
+    /// Covers the variables `use` set or unset and the `drift-watch` setting. Lazy
+    /// secrets and list variables such as PATH are left out.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub applied: HashMap<String, Option<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConfigStore {
     pub configs: HashMap<String, EnvConfig>,
     pub active_config: Option<String>,
+    /// When the active configuration was last activated with `use`
+    ///
+    /// Kept in state.json; only read from config.json written by older versions.
+    #[serde(default, skip_serializing)]
+    pub activated_at: Option<DateTime<Utc>>,
     #[serde(default = "Utc::now")]
     pub last_modified: DateTime<Utc>,
     #[serde(default = "default_version")]
     pub version: String,
+    /// User preferences
+    #[serde(default, skip_serializing_if = "Settings::is_empty")]
+    pub settings: Settings,
+}
+
+/// User preferences stored alongside the configurations
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// Extra variable names treated as Claude variables, e.g. OPENROUTER_API_KEY
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub claude_variables: Vec<String>,
+    /// Ignore `use --verify`, e.g. for users who are usually offline
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip_verification: bool,
+    /// Commit every change of config.json to a git repository in the state directory
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub git_track: bool,
+    /// Allow `@op:`/`@cmd:` values that run a command each time a configuration is activated
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lazy_secrets: bool,
+    /// Skip the store integrity check that runs before every command
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip_integrity_check: bool,
+    /// Directory for backups instead of `<config_dir>/backups`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<String>,
+    /// Leave out the shared-secret check of `envswitch audit`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip_duplicate_check: bool,
+    /// Configuration applied by `envswitch shellenv --activate` in new shells
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_config: Option<String>,
+    /// Print ASCII tags such as [OK] and [WARN] instead of emoji, like `--no-emoji`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_emoji: bool,
+    /// Language of messages, e.g. "zh-CN"; `$ENVSWITCH_LANG` takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// How the store is laid out on disk; changing it migrates the store
+    #[serde(default, skip_serializing_if = "StorageLayout::is_single")]
+    pub storage_layout: StorageLayout,
+    /// Columns of `list --table`, e.g. "name,vars,active"; `--columns` takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_columns: Option<String>,
+    /// Show the length of masked secrets, e.g. `sk-a****(51 chars)`, and flag empty ones
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub mask_length_hints: bool,
+    /// Show timestamps in UTC instead of the local time zone
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub utc_timestamps: bool,
+    /// Glob patterns of variables left out of every import and export, e.g. "AWS_*"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_keys: Vec<String>,
+    /// Variables `drift` watches besides those set by `use`, e.g. "PATH,HTTPS_PROXY"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub drift_watch: Vec<String>,
+    /// Replace a symlinked config.json with a regular file instead of writing to the link's target
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub replace_symlinks: bool,
+    /// Treat warnings as errors, like the global `--strict` flag
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+    /// `set --replace` asks before removing more than this many variables, or over half of them;
+    /// `None` is the default of [`DEFAULT_REPLACE_GUARD`](crate::types::constants::DEFAULT_REPLACE_GUARD), 0 turns the check off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replace_guard: Option<usize>,
+}
+
+/// How the configuration store is kept on disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageLayout {
+    /// Everything in config.json
+    #[default]
+    Single,
+    /// One file per configuration in `configs/` plus a small index, see [`crate::split_store`]
+    Split,
+}
+
+impl StorageLayout {
+    /// Names accepted by [`StorageLayout::parse`]
+    pub const NAMES: &'static [&'static str] = &["single", "split"];
+    
+    /// Parse a layout name, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "single" => Some(StorageLayout::Single),
+            "split" => Some(StorageLayout::Split),
+            _ => None,
+        }
+    }
+    
+    pub fn is_single(&self) -> bool {
+        *self == StorageLayout::Single
+    }
+}
+
+impl std::fmt::Display for StorageLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageLayout::Single => write!(f, "single"),
+            StorageLayout::Split => write!(f, "split"),
+        }
+    }
+}
+
+impl Settings {
+    /// Names accepted by [`Settings::set`]
+    pub const KEYS: &'static [&'static str] = &["skip-verification", "git-track", "lazy-secrets", "skip-integrity-check", "backup-dir", "skip-duplicate-check", "no-emoji", "language", "storage-layout", "list-columns", "mask-length-hints", "utc-timestamps", "exclude-keys", "drift-watch", "follow-symlinks", "strict", "replace-guard"];
+    
+    /// Check if all settings have their default values
+    pub fn is_empty(&self) -> bool {
+        self.claude_variables.is_empty() && !self.skip_verification && !self.git_track && !self.lazy_secrets && !self.skip_integrity_check
+            && self.backup_dir.is_none() && self.default_config.is_none() && !self.skip_duplicate_check
+            && !self.no_emoji && self.language.is_none() && self.storage_layout.is_single()
+            && self.list_columns.is_none() && !self.mask_length_hints && !self.utc_timestamps
+            && self.exclude_keys.is_empty() && self.drift_watch.is_empty() && !self.replace_symlinks && !self.strict && self.replace_guard.is_none()
+    }
+    
+    /// How many variables `set --replace` may remove without asking, or None when it never asks
+    pub fn replace_guard_keys(&self) -> Option<usize> {
+        match self.replace_guard {
+            Some(0) => None,
+            keys => Some(keys.unwrap_or(crate::types::constants::DEFAULT_REPLACE_GUARD)),
+        }
+    }
+    
+    /// Current value of a setting by its command-line name, as [`Settings::set`] accepts it
+    pub fn get(&self, key: &str) -> Option<String> {
+        let value = match key {
+            "skip-verification" => self.skip_verification.to_string(),
+            "git-track" => self.git_track.to_string(),
+            "lazy-secrets" => self.lazy_secrets.to_string(),
+            "skip-integrity-check" => self.skip_integrity_check.to_string(),
+            "skip-duplicate-check" => self.skip_duplicate_check.to_string(),
+            "no-emoji" => self.no_emoji.to_string(),
+            "language" => self.language.clone().unwrap_or_default(),
+            "storage-layout" => self.storage_layout.to_string(),
+            "backup-dir" => self.backup_dir.clone().unwrap_or_default(),
+            "list-columns" => self.list_columns.clone().unwrap_or_default(),
+            "mask-length-hints" => self.mask_length_hints.to_string(),
+            "utc-timestamps" => self.utc_timestamps.to_string(),
+            "exclude-keys" => self.exclude_keys.join(","),
+            "drift-watch" => self.drift_watch.join(","),
+            "follow-symlinks" => (!self.replace_symlinks).to_string(),
+            "strict" => self.strict.to_string(),
+            "replace-guard" => match self.replace_guard_keys() {
+                Some(keys) => keys.to_string(),
+                None => "off".to_string(),
+            },
+            _ => return None,
+        };
+        Some(value)
+    }
+    
+    /// Every setting by command-line name, plus the default configuration and the Claude watchlist
+    pub fn to_document(&self) -> SettingsDocument {
+        let mut document: SettingsDocument = Self::KEYS.iter()
+            .filter_map(|key| Some((key.to_string(), self.get(key)?)))
+            .collect();
+        document.insert("default".to_string(), self.default_config.clone().unwrap_or_default());
+        document.insert("claude-variables".to_string(), self.claude_variables.join(","));
+        document
+    }
+    
+    /// Change a setting from its command-line name and string value
+    pub fn set(&mut self, key: &str, value: &str) -> ConfigResult<()> {
+        match key {
+            "skip-verification" => {
+                self.skip_verification = parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "git-track" => {
+                let git_track = parse_bool_setting(key, value)?;
+                if git_track && !self.storage_layout.is_single() {
+                    return Err(ConfigError::ValidationError(
+                        "git-track only works with the single storage layout; run 'envswitch migrate-storage single' first".to_string()
+                    ));
+                }
+                self.git_track = git_track;
+                Ok(())
+            }
+            "lazy-secrets" => {
+                self.lazy_secrets = parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "skip-integrity-check" => {
+                self.skip_integrity_check = parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "skip-duplicate-check" => {
+                self.skip_duplicate_check = parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "no-emoji" => {
+                self.no_emoji = parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "mask-length-hints" => {
+                self.mask_length_hints = parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "utc-timestamps" => {
+                self.utc_timestamps = parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "follow-symlinks" => {
+                self.replace_symlinks = !parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "strict" => {
+                self.strict = parse_bool_setting(key, value)?;
+                Ok(())
+            }
+            "replace-guard" => {
+                let keys = match value.trim() {
+                    "off" => 0,
+                    number => number.parse().map_err(|_| ConfigError::ValidationError(
+                        format!("Invalid value '{}' for 'replace-guard'. Expected a number of variables or off", value)
+                    ))?,
+                };
+                self.replace_guard = Some(keys).filter(|&keys| keys != crate::types::constants::DEFAULT_REPLACE_GUARD);
+                Ok(())
+            }
+            "exclude-keys" => {
+                // Comma-separated patterns; an empty value clears the list
+                self.exclude_keys = value.split(',')
+                    .map(str::trim)
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                Ok(())
+            }
+            "drift-watch" => {
+                // Comma-separated variable names; an empty value clears the list
+                let names: Vec<String> = value.split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                for name in &names {
+                    crate::error::validate_env_var_name(name)
+                        .map_err(|e| ConfigError::ValidationError(format!("Invalid value for 'drift-watch': {}", e)))?;
+                }
+                self.drift_watch = names;
+                Ok(())
+            }
+            "language" => {
+                // An empty value goes back to English
+                let value = value.trim();
+                if !value.is_empty() && crate::messages::Locale::parse(value).is_none() {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Unknown language '{}'. Available languages: {}", value, crate::messages::Locale::NAMES.join(", ")
+                    )));
+                }
+                self.language = Some(value).filter(|language| !language.is_empty()).map(str::to_string);
+                Ok(())
+            }
+            "storage-layout" => {
+                let layout = StorageLayout::parse(value).ok_or_else(|| ConfigError::ValidationError(format!(
+                    "Unknown storage layout '{}'. Available layouts: {}", value, StorageLayout::NAMES.join(", ")
+                )))?;
+                if !layout.is_single() && self.git_track {
+                    return Err(ConfigError::ValidationError(
+                        "The split storage layout can't be tracked in git; disable git-track first".to_string()
+                    ));
+                }
+                self.storage_layout = layout;
+                Ok(())
+            }
+            "backup-dir" => {
+                // An empty value goes back to the default location
+                self.backup_dir = Some(value.trim()).filter(|dir| !dir.is_empty()).map(str::to_string);
+                Ok(())
+            }
+            "list-columns" => {
+                // An empty value goes back to the default columns
+                let value = value.trim();
+                if !value.is_empty() {
+                    crate::handlers::ListColumn::parse_list(value).map_err(ConfigError::ValidationError)?;
+                }
+                self.list_columns = Some(value).filter(|columns| !columns.is_empty()).map(str::to_string);
+                Ok(())
+            }
+            _ => Err(ConfigError::ValidationError(
+                format!("Unknown setting '{}'. Available settings: {}", key, Self::KEYS.join(", "))
+            )),
+        }
+    }
+}
+
+/// Result of [`FileConfigManager::compress_backups`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackupCompression {
+    pub compressed: usize,
+    /// Size of the compressed backups before compression
+    pub original_bytes: u64,
+    pub compressed_bytes: u64,
+}
+
+impl BackupCompression {
+    /// Bytes freed by compressing
+    pub fn saved_bytes(&self) -> u64 {
+        self.original_bytes.saturating_sub(self.compressed_bytes)
+    }
+}
+
+/// What is known about a backup, kept in the backup index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// Why the backup was made, e.g. "before big refactor" or "before import of team.json"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Number of configurations in the backup
+    pub configs: usize,
+    /// Number of variables across all configurations in the backup
+    pub variables: usize,
+}
+
+/// Problems found and fixed by [`FileConfigManager::check_integrity`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntegrityReport {
+    /// Why config.json could not be loaded; nothing else is checked in that case
+    pub parse_error: Option<String>,
+    /// config.json permissions were not 0600 and have been reset
+    pub fixed_permissions: bool,
+    /// Active configuration that no longer exists and was cleared
+    pub cleared_active: Option<String>,
+    /// Default configuration that no longer exists and was cleared
+    pub cleared_default: Option<String>,
+    /// Why the backups could not be listed
+    pub backups_error: Option<String>,
+}
+
+impl IntegrityReport {
+    /// Check if nothing was found
+    pub fn is_clean(&self) -> bool {
+        *self == IntegrityReport::default()
+    }
+}
+
+/// Summarize the difference between two stores as a commit message,
+/// e.g. "set deepseek: +2 vars; delete kimi"
+pub fn describe_store_change(old: &ConfigStore, new: &ConfigStore) -> String {
+    let mut changes = Vec::new();
+    
+    let mut names: Vec<&String> = old.configs.keys().chain(new.configs.keys()).collect();
+    names.sort();
+    names.dedup();
+    
+    for name in names {
+        match (old.configs.get(name), new.configs.get(name)) {
+            (None, Some(config)) => {
+                changes.push(format!("set {}: +{} vars", name, config.variables.len()));
+            }
+            (Some(_), None) => changes.push(format!("delete {}", name)),
+            (Some(before), Some(after)) if before != after => {
+                let added = after.variables.keys().filter(|k| !before.variables.contains_key(*k)).count();
+                let removed = before.variables.keys().filter(|k| !after.variables.contains_key(*k)).count();
+                let changed = after.variables.iter()
+                    .filter(|(k, v)| before.variables.get(*k).is_some_and(|old| old != *v))
+                    .count();
+                
+                let mut parts = Vec::new();
+                if added > 0 { parts.push(format!("+{}", added)); }
+                if removed > 0 { parts.push(format!("-{}", removed)); }
+                if changed > 0 { parts.push(format!("~{}", changed)); }
+                
+                if parts.is_empty() {
+                    changes.push(format!("update {}", name));
+                } else {
+                    changes.push(format!("set {}: {} vars", name, parts.join(" ")));
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    if old.active_config != new.active_config {
+        match &new.active_config {
+            Some(name) => changes.push(format!("use {}", name)),
+            None => changes.push("clear active configuration".to_string()),
+        }
+    }
+    
+    if old.settings != new.settings {
+        changes.push("update settings".to_string());
+    }
+    
+    if changes.is_empty() {
+        "update configuration".to_string()
+    } else {
+        changes.join("; ")
+    }
+}
+
+/// Parse a boolean setting value
+fn parse_bool_setting(key: &str, value: &str) -> ConfigResult<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(ConfigError::ValidationError(
+            format!("Invalid value '{}' for '{}'. Expected true or false", value, key)
+        )),
+    }
 }
 
 fn default_version() -> String {
@@ -142,8 +1010,10 @@ impl Default for ConfigStore {
         Self {
             configs: HashMap::new(),
             active_config: None,
+            activated_at: None,
             last_modified: Utc::now(),
             version: default_version(),
+            settings: Settings::default(),
         }
     }
 }
@@ -155,22 +1025,97 @@ impl ConfigStore {
     }
     
     /// Add a configuration to the store
+    ///
+    /// Names that differ only by case from an existing name or alias are rejected.
     pub fn add_config(&mut self, config: EnvConfig) -> ConfigResult<()> {
+        self.insert_new_config(config, false)
+    }
+    
+    /// Add a configuration even if its name differs only by case from an existing one
+    pub fn add_config_allowing_case_collision(&mut self, config: EnvConfig) -> ConfigResult<()> {
+        self.insert_new_config(config, true)
+    }
+    
+    fn insert_new_config(&mut self, config: EnvConfig, allow_case_collision: bool) -> ConfigResult<()> {
         let alias = config.alias.clone();
         
         if self.configs.contains_key(&alias) {
             return Err(ConfigError::ConfigExists(alias));
         }
         
-        self.configs.insert(alias, config);
+        if let Some(owner) = self.alias_owner(&alias) {
+            return Err(ConfigError::ValidationError(
+                format!("'{}' is already an alias of configuration '{}'", alias, owner)
+            ));
+        }
+        
+        if !allow_case_collision {
+            if let Some(existing) = self.case_collision(&alias) {
+                return Err(ConfigError::ValidationError(case_collision_message(&alias, existing)));
+            }
+        }
+        
+        self.insert_config(config);
         self.last_modified = Utc::now();
         Ok(())
     }
     
+    /// Add or replace a configuration under its own alias, returning the one it replaced
+    ///
+    /// The map key is taken from `config.alias`, so the two cannot drift apart; use this
+    /// instead of inserting into `configs` directly.
+    pub fn insert_config(&mut self, config: EnvConfig) -> Option<EnvConfig> {
+        self.configs.insert(config.alias.clone(), config)
+    }
+    
+    /// Configurations stored under another key than their alias, as (key, alias), sorted
+    ///
+    /// Only a hand-edited file gets there; such a store fails validation until repaired.
+    pub fn alias_mismatches(&self) -> Vec<(String, String)> {
+        let mut mismatches: Vec<(String, String)> = self.configs.iter()
+            .filter(|(key, config)| **key != config.alias)
+            .map(|(key, config)| (key.clone(), config.alias.clone()))
+            .collect();
+        mismatches.sort();
+        mismatches
+    }
+    
+    /// Make the configuration stored under `key` consistent again, returning its name
+    ///
+    /// With `keep_key` the alias is rewritten to the key; otherwise the configuration
+    /// moves to its alias, taking the active and default configuration along.
+    pub fn repair_alias_mismatch(&mut self, key: &str, keep_key: bool) -> ConfigResult<String> {
+        let mut config = self.configs.remove(key)
+            .ok_or_else(|| ConfigError::not_found(key.to_string()))?;
+        if keep_key {
+            config.alias = key.to_string();
+        } else {
+            let problem = if self.configs.contains_key(&config.alias) || self.alias_owner(&config.alias).is_some() {
+                Some(format!("'{}' is already taken", config.alias))
+            } else {
+                crate::error::validate_config_name(&config.alias).err().map(|e| e.to_string())
+            };
+            if let Some(problem) = problem {
+                self.configs.insert(key.to_string(), config);
+                return Err(ConfigError::ValidationError(format!("Cannot move '{}' to its alias: {}", key, problem)));
+            }
+            if self.active_config.as_deref() == Some(key) {
+                self.active_config = Some(config.alias.clone());
+            }
+            if self.settings.default_config.as_deref() == Some(key) {
+                self.settings.default_config = Some(config.alias.clone());
+            }
+        }
+        let name = config.alias.clone();
+        self.insert_config(config);
+        self.last_modified = Utc::now();
+        Ok(name)
+    }
+    
     /// Update an existing configuration
     pub fn update_config(&mut self, alias: &str, variables: HashMap<String, String>, description: Option<String>) -> ConfigResult<()> {
         let config = self.configs.get_mut(alias)
-            .ok_or_else(|| ConfigError::ConfigNotFound(alias.to_string()))?;
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
         
         config.update(variables, description)?;
         self.last_modified = Utc::now();
@@ -180,11 +1125,17 @@ impl ConfigStore {
     /// Remove a configuration from the store
     pub fn remove_config(&mut self, alias: &str) -> ConfigResult<EnvConfig> {
         let config = self.configs.remove(alias)
-            .ok_or_else(|| ConfigError::ConfigNotFound(alias.to_string()))?;
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
         
         // If this was the active config, clear it
         if self.active_config.as_deref() == Some(alias) {
             self.active_config = None;
+            self.activated_at = None;
+        }
+        
+        // A default pointing nowhere would be skipped by every new shell
+        if self.settings.default_config.as_deref() == Some(alias) {
+            self.settings.default_config = None;
         }
         
         self.last_modified = Utc::now();
@@ -208,51 +1159,456 @@ impl ConfigStore {
         aliases
     }
     
-    /// Set the active configuration
-    pub fn set_active(&mut self, alias: String) -> ConfigResult<()> {
-        if !self.configs.contains_key(&alias) {
-            return Err(ConfigError::ConfigNotFound(alias));
-        }
-        
-        self.active_config = Some(alias);
+    /// List configuration names for display: pinned configurations first,
+    /// each group ordered by `sort_by` ("name", "updated", "created" or "variables")
+    pub fn list_for_display(&self, sort_by: &str, filter: ArchiveFilter) -> Vec<String> {
+        let mut configs: Vec<&EnvConfig> = self.configs.values().filter(|config| filter.includes(config)).collect();
+        configs.sort_by(|a, b| {
+            b.pinned.cmp(&a.pinned).then_with(|| match sort_by {
+                "updated" => b.updated_at.cmp(&a.updated_at),
+                "created" => b.created_at.cmp(&a.created_at),
+                "variables" => b.variables.len().cmp(&a.variables.len()),
+                _ => std::cmp::Ordering::Equal,
+            }).then_with(|| a.alias.cmp(&b.alias))
+        });
+        configs.into_iter().map(|config| config.alias.clone()).collect()
+    }
+    
+    /// Pin or unpin a configuration
+    pub fn set_pinned(&mut self, alias: &str, pinned: bool) -> ConfigResult<()> {
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        config.pinned = pinned;
         self.last_modified = Utc::now();
         Ok(())
     }
     
-    /// Clear the active configuration
-    pub fn clear_active(&mut self) {
-        self.active_config = None;
+    /// Trim whitespace and control characters from every stored value
+    ///
+    /// Returns the changed variables as (configuration, key) pairs, sorted.
+    pub fn clean_values(&mut self) -> Vec<(String, String)> {
+        let mut cleaned = Vec::new();
+        for (name, config) in self.configs.iter_mut() {
+            let keys = config.clean_values();
+            if !keys.is_empty() {
+                config.updated_at = Utc::now();
+                cleaned.extend(keys.into_iter().map(|key| (name.clone(), key)));
+            }
+        }
+        if !cleaned.is_empty() {
+            self.last_modified = Utc::now();
+        }
+        cleaned.sort();
+        cleaned
+    }
+    
+    /// Archive or unarchive a configuration
+    pub fn set_archived(&mut self, alias: &str, archived: bool) -> ConfigResult<()> {
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        config.archived = archived;
         self.last_modified = Utc::now();
+        Ok(())
     }
     
-    /// Get the active configuration
-    pub fn get_active_config(&self) -> Option<&EnvConfig> {
-        self.active_config.as_ref().and_then(|alias| self.configs.get(alias))
+    /// Record where a configuration came from
+    pub fn set_source(&mut self, alias: &str, source: ConfigSource) -> ConfigResult<()> {
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        config.source = Some(source);
+        self.last_modified = Utc::now();
+        Ok(())
     }
     
-    /// Check if the store is empty
-    pub fn is_empty(&self) -> bool {
-        self.configs.is_empty()
+    /// Mark or unmark a configuration as protected
+    pub fn set_protected(&mut self, alias: &str, protected: bool) -> ConfigResult<()> {
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        config.protected = protected;
+        self.last_modified = Utc::now();
+        Ok(())
     }
     
-    /// Get the number of configurations
-    pub fn len(&self) -> usize {
-        self.configs.len()
+    /// Set one variable of a configuration, returning false if it already had that value
+    pub fn set_variable(&mut self, alias: &str, key: &str, value: &str) -> ConfigResult<bool> {
+        crate::types::validation::validate_env_var(key, value)?;
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        
+        if config.variables.get(key).map(String::as_str) == Some(value) {
+            return Ok(false);
+        }
+        config.variables.insert(key.to_string(), value.to_string());
+        config.updated_at = Utc::now();
+        self.last_modified = Utc::now();
+        Ok(true)
+    }
+    
+    /// Remove one variable from a configuration, returning false if it wasn't set
+    pub fn unset_variable(&mut self, alias: &str, key: &str) -> ConfigResult<bool> {
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        
+        if config.variables.remove(key).is_none() {
+            return Ok(false);
+        }
+        config.list_ops.remove(key);
+        config.variable_comments.remove(key);
+        config.updated_at = Utc::now();
+        self.last_modified = Utc::now();
+        Ok(true)
+    }
+    
+    /// Replace the prepend/append modes of a configuration's list variables
+    pub fn set_list_ops(&mut self, alias: &str, list_ops: HashMap<String, ListOp>) -> ConfigResult<()> {
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        
+        if let Some(key) = list_ops.keys().find(|key| !config.variables.contains_key(*key)) {
+            return Err(ConfigError::ValidationError(format!("Variable '{}' is not part of configuration '{}'", key, alias)));
+        }
+        
+        config.list_ops = list_ops;
+        config.updated_at = Utc::now();
+        self.last_modified = Utc::now();
+        Ok(())
+    }
+    
+    /// Replace the per-variable comments of a configuration
+    pub fn set_variable_comments(&mut self, alias: &str, comments: HashMap<String, String>) -> ConfigResult<()> {
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        
+        if let Some(key) = comments.keys().find(|key| !config.variables.contains_key(*key)) {
+            return Err(ConfigError::ValidationError(format!("Variable '{}' is not part of configuration '{}'", key, alias)));
+        }
+        
+        config.variable_comments = comments;
+        config.updated_at = Utc::now();
+        self.last_modified = Utc::now();
+        Ok(())
+    }
+    
+    /// Set or clear the expiration date of a configuration
+    pub fn set_expiry(&mut self, alias: &str, expires_at: Option<DateTime<Utc>>) -> ConfigResult<()> {
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        config.expires_at = expires_at;
+        self.last_modified = Utc::now();
+        Ok(())
+    }
+    
+    /// List the names of all expired configurations
+    pub fn expired_configs(&self) -> Vec<String> {
+        let mut expired: Vec<String> = self.configs.values()
+            .filter(|config| config.is_expired())
+            .map(|config| config.alias.clone())
+            .collect();
+        expired.sort();
+        expired
+    }
+    
+    /// Replace the notes and links of a configuration
+    pub fn set_details(&mut self, alias: &str, notes: Option<String>, links: Vec<String>) -> ConfigResult<()> {
+        for link in &links {
+            if link.trim().is_empty() {
+                return Err(ConfigError::ValidationError("Links cannot be empty".to_string()));
+            }
+        }
+        
+        let config = self.configs.get_mut(alias)
+            .ok_or_else(|| ConfigError::not_found(alias.to_string()))?;
+        config.notes = notes.filter(|n| !n.trim().is_empty());
+        config.links = links;
+        config.updated_at = Utc::now();
+        self.last_modified = Utc::now();
+        Ok(())
+    }
+    
+    /// Resolve a configuration name or alias to the canonical configuration name
+    pub fn resolve_name(&self, name: &str) -> Option<String> {
+        if self.configs.contains_key(name) {
+            return Some(name.to_string());
+        }
+        self.alias_owner(name).map(|owner| owner.to_string())
+    }
+    
+    /// Find the configuration that owns the given alias
+    pub fn alias_owner(&self, alias: &str) -> Option<&str> {
+        self.configs
+            .values()
+            .find(|config| config.aliases.iter().any(|a| a == alias))
+            .map(|config| config.alias.as_str())
+    }
+    
+    /// Existing configuration name or alias that equals `name` ignoring case, but not exactly
+    pub fn case_collision(&self, name: &str) -> Option<&str> {
+        self.configs
+            .values()
+            .flat_map(|config| std::iter::once(&config.alias).chain(&config.aliases))
+            .find(|existing| *existing != name && existing.eq_ignore_ascii_case(name))
+            .map(String::as_str)
+    }
+    
+    /// Groups of configuration names and aliases that differ only by case, each sorted
+    pub fn case_collisions(&self) -> Vec<Vec<String>> {
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for config in self.configs.values() {
+            for name in std::iter::once(&config.alias).chain(&config.aliases) {
+                groups.entry(name.to_ascii_lowercase()).or_default().push(name.clone());
+            }
+        }
+        
+        let mut collisions: Vec<Vec<String>> = groups.into_values()
+            .filter(|names| names.len() > 1)
+            .map(|mut names| {
+                names.sort();
+                names
+            })
+            .collect();
+        collisions.sort();
+        collisions
+    }
+    
+    /// Lowercase name based on `name` that collides with nothing in the store
+    pub fn suggest_distinct_name(&self, name: &str) -> String {
+        let base = name.to_ascii_lowercase();
+        (2..)
+            .map(|n| format!("{}-{}", base, n))
+            .find(|candidate| self.case_collision(candidate).is_none() && !self.configs.contains_key(candidate) && self.alias_owner(candidate).is_none())
+            .unwrap_or(base)
+    }
+    
+    /// List all aliases as (alias, configuration name) pairs, sorted by alias
+    pub fn list_config_aliases(&self) -> Vec<(String, String)> {
+        let mut aliases: Vec<(String, String)> = self.configs
+            .values()
+            .flat_map(|config| config.aliases.iter().map(move |a| (a.clone(), config.alias.clone())))
+            .collect();
+        aliases.sort();
+        aliases
+    }
+    
+    /// Add an alias to a configuration
+    pub fn add_config_alias(&mut self, config_name: &str, alias: &str) -> ConfigResult<()> {
+        crate::error::validate_config_name(alias)?;
+        
+        if !self.configs.contains_key(config_name) {
+            return Err(ConfigError::not_found(config_name.to_string()));
+        }
+        
+        if self.configs.contains_key(alias) {
+            return Err(ConfigError::ValidationError(
+                format!("'{}' is already the name of a configuration", alias)
+            ));
+        }
+        
+        if let Some(owner) = self.alias_owner(alias) {
+            return Err(ConfigError::ValidationError(
+                format!("'{}' is already an alias of configuration '{}'", alias, owner)
+            ));
+        }
+        
+        if let Some(existing) = self.case_collision(alias) {
+            return Err(ConfigError::ValidationError(case_collision_message(alias, existing)));
+        }
+        
+        let config = self.configs.get_mut(config_name)
+            .ok_or_else(|| ConfigError::not_found(config_name.to_string()))?;
+        config.aliases.push(alias.to_string());
+        config.aliases.sort();
+        self.last_modified = Utc::now();
+        Ok(())
+    }
+    
+    /// Remove an alias, returning the name of the configuration it pointed to
+    pub fn remove_config_alias(&mut self, alias: &str) -> ConfigResult<String> {
+        let owner = self.alias_owner(alias)
+            .map(|owner| owner.to_string())
+            .ok_or_else(|| ConfigError::ValidationError(format!("Alias '{}' does not exist", alias)))?;
+        
+        if let Some(config) = self.configs.get_mut(&owner) {
+            config.aliases.retain(|a| a != alias);
+        }
+        self.last_modified = Utc::now();
+        Ok(owner)
+    }
+    
+    /// Add a variable name to the Claude watchlist, returning false if it was already watched
+    pub fn watch_claude_variable(&mut self, name: &str) -> ConfigResult<bool> {
+        crate::error::validate_env_var_name(name)
+            .map_err(|e| ConfigError::ValidationError(e.to_string()))?;
+        
+        if crate::types::validation::is_watched_claude_var(name, &self.settings.claude_variables) {
+            return Ok(false);
+        }
+        
+        self.settings.claude_variables.push(name.to_string());
+        self.last_modified = Utc::now();
+        Ok(true)
+    }
+    
+    /// Apply a settings document from [`Settings::to_document`], returning what changed
+    ///
+    /// Each setting is validated like `envswitch settings set`; the default must name an
+    /// existing configuration. Nothing changes when any setting is rejected.
+    pub fn apply_settings_document(&mut self, document: &SettingsDocument) -> ConfigResult<Vec<SettingChange>> {
+        let original = self.settings.clone();
+        if let Err(e) = self.apply_settings(document) {
+            self.settings = original;
+            return Err(e);
+        }
+        
+        let before = original.to_document();
+        let changes: Vec<SettingChange> = self.settings.to_document().into_iter()
+            .filter(|(key, new)| before.get(key) != Some(new))
+            .map(|(key, new)| SettingChange { old: before.get(&key).cloned().unwrap_or_default(), key, new })
+            .collect();
+        if !changes.is_empty() {
+            self.last_modified = Utc::now();
+        }
+        Ok(changes)
+    }
+    
+    fn apply_settings(&mut self, document: &SettingsDocument) -> ConfigResult<()> {
+        // git-track and storage-layout depend on each other, so a rejected setting gets a second try
+        let mut pending: Vec<(&String, &String)> = document.iter().collect();
+        for retry in [false, true] {
+            let mut rejected = Vec::new();
+            for (key, value) in pending {
+                let applied = match key.as_str() {
+                    "default" if value.is_empty() => {
+                        self.settings.default_config = None;
+                        Ok(())
+                    }
+                    "default" => self.resolve_name(value)
+                        .map(|name| self.settings.default_config = Some(name))
+                        .ok_or_else(|| ConfigError::not_found(value.clone())),
+                    "claude-variables" => {
+                        self.settings.claude_variables.clear();
+                        value.split(',')
+                            .map(str::trim)
+                            .filter(|name| !name.is_empty())
+                            .try_for_each(|name| self.watch_claude_variable(name).map(|_| ()))
+                    }
+                    _ => self.settings.set(key, value),
+                };
+                match applied {
+                    Err(e) if retry => return Err(e),
+                    Err(_) => rejected.push((key, value)),
+                    Ok(()) => {}
+                }
+            }
+            pending = rejected;
+        }
+        Ok(())
+    }
+    
+    /// Remove a user-added variable name from the Claude watchlist
+    pub fn unwatch_claude_variable(&mut self, name: &str) -> ConfigResult<()> {
+        if crate::types::validation::is_claude_env_var(name) {
+            return Err(ConfigError::ValidationError(
+                format!("'{}' is a built-in Claude variable and cannot be removed", name)
+            ));
+        }
+        
+        if !self.settings.claude_variables.iter().any(|var| var == name) {
+            return Err(ConfigError::ValidationError(
+                format!("'{}' is not on the Claude watchlist", name)
+            ));
+        }
+        
+        self.settings.claude_variables.retain(|var| var != name);
+        self.last_modified = Utc::now();
+        Ok(())
+    }
+    
+    /// Set the active configuration
+    pub fn set_active(&mut self, alias: String) -> ConfigResult<()> {
+        if !self.configs.contains_key(&alias) {
+            return Err(ConfigError::not_found(alias));
+        }
+        
+        self.active_config = Some(alias);
+        self.activated_at = Some(Utc::now());
+        self.last_modified = Utc::now();
+        Ok(())
+    }
+    
+    /// Clear the active configuration
+    pub fn clear_active(&mut self) {
+        self.active_config = None;
+        self.activated_at = None;
+        self.last_modified = Utc::now();
+    }
+    
+    /// Names of the configurations that set `key` to exactly `value`, sorted
+    pub fn configs_defining(&self, key: &str, value: &str) -> Vec<String> {
+        let mut names: Vec<String> = self.configs.iter()
+            .filter(|(_, config)| config.variables.get(key).is_some_and(|v| v == value))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+    
+    /// Check whether the active configuration was modified after it was activated
+    pub fn is_active_stale(&self) -> bool {
+        match (self.get_active_config(), self.activated_at) {
+            (Some(config), Some(activated_at)) => config.updated_at > activated_at,
+            _ => false,
+        }
+    }
+    
+    /// Get the active configuration
+    pub fn get_active_config(&self) -> Option<&EnvConfig> {
+        self.active_config.as_ref().and_then(|alias| self.configs.get(alias))
+    }
+    
+    /// Check if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty()
+    }
+    
+    /// Get the number of configurations
+    pub fn len(&self) -> usize {
+        self.configs.len()
+    }
+    
+    /// Validate one configuration: its name and every variable
+    pub fn validate_config(alias: &str, config: &EnvConfig) -> ConfigResult<()> {
+        if alias != config.alias {
+            return Err(ConfigError::ValidationError(
+                format!("Alias mismatch: key '{}' vs config alias '{}'", alias, config.alias)
+            ));
+        }
+        
+        crate::error::validate_config_name(alias)?;
+        
+        for (key, value) in &config.variables {
+            crate::types::validation::validate_env_var(key, value)?;
+        }
+        Ok(())
     }
     
     /// Validate the entire store
     pub fn validate(&self) -> ConfigResult<()> {
         for (alias, config) in &self.configs {
-            if alias != &config.alias {
+            Self::validate_config(alias, config)?;
+        }
+        
+        // Aliases must not shadow configuration names or each other
+        let mut seen_aliases = std::collections::HashSet::new();
+        for (alias, config_name) in self.list_config_aliases() {
+            if self.configs.contains_key(&alias) {
                 return Err(ConfigError::ValidationError(
-                    format!("Alias mismatch: key '{}' vs config alias '{}'", alias, config.alias)
+                    format!("Alias '{}' of '{}' collides with a configuration name", alias, config_name)
                 ));
             }
-            
-            crate::error::validate_config_name(alias)?;
-            
-            for (key, value) in &config.variables {
-                crate::types::validation::validate_env_var(key, value)?;
+            if !seen_aliases.insert(alias.clone()) {
+                return Err(ConfigError::ValidationError(
+                    format!("Alias '{}' is defined more than once", alias)
+                ));
             }
         }
         
@@ -283,57 +1639,302 @@ pub trait ConfigManager {
 }
 
 /// File-based configuration manager
+///
+/// Keeps the store in config.json, or in the split layout of
+/// [`SplitFileConfigManager`] when the storage-layout setting says so.
 pub struct FileConfigManager {
     config_paths: ConfigPaths,
+    split: SplitFileConfigManager,
+    timings: Timings,
+    /// Failures of best-effort steps, for the caller to report, see [`FileConfigManager::take_warnings`]
+    warnings: std::sync::Mutex<Vec<String>>,
 }
 
 impl FileConfigManager {
     /// Create a new file-based configuration manager
     pub fn new() -> ConfigResult<Self> {
         let config_paths = ConfigPaths::new()?;
-        Ok(Self { config_paths })
+        Ok(Self::with_paths(config_paths))
     }
     
     /// Create with custom paths (mainly for testing)
     pub fn with_paths(config_paths: ConfigPaths) -> Self {
-        Self { config_paths }
+        let split = SplitFileConfigManager::with_paths(config_paths.clone());
+        Self { config_paths, split, timings: Timings::default(), warnings: Default::default() }
+    }
+    
+    /// Record the time spent loading, saving and backing up the store in `timings`
+    pub fn with_timings(self, timings: Timings) -> Self {
+        Self { timings, ..self }
+    }
+    
+    /// Warnings since the last call about steps that failed without failing the operation,
+    /// such as recording configuration history
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+    
+    fn warn(&self, message: String) {
+        self.warnings.lock().unwrap_or_else(|e| e.into_inner()).push(message);
+    }
+    
+    /// Paths of the configuration directory and the files in it
+    pub fn config_paths(&self) -> &ConfigPaths {
+        &self.config_paths
     }
     
-    /// Get the configuration file path
+    /// Get the configuration file path; the index file in the split layout
     pub fn config_file_path(&self) -> &std::path::Path {
-        &self.config_paths.config_file
+        if self.uses_split_layout() {
+            self.split.index_path()
+        } else {
+            &self.config_paths.config_file
+        }
     }
     
     /// Check if configuration file exists
     pub fn config_file_exists(&self) -> bool {
-        self.config_paths.config_file.exists()
+        self.config_file_path().exists()
     }
     
     /// Get configuration file size in bytes
     pub fn config_file_size(&self) -> ConfigResult<u64> {
-        let metadata = fs::metadata(&self.config_paths.config_file)
+        let metadata = fs::metadata(self.config_file_path())
             .map_err(ConfigError::FileError)?;
         Ok(metadata.len())
     }
     
+    /// Check if the store is kept in the split layout, one file per configuration
+    pub fn uses_split_layout(&self) -> bool {
+        self.split.exists()
+    }
+    
+    /// Manager of the split layout, used for reading and writing the store while it is active
+    pub fn split_manager(&self) -> &SplitFileConfigManager {
+        &self.split
+    }
+    
+    /// Directory backups are written to
+    ///
+    /// `$ENVSWITCH_BACKUP_DIR` wins over the backup-dir setting, which wins over
+    /// `<config_dir>/backups`.
+    pub fn backup_dir(&self) -> std::path::PathBuf {
+        if let Some(dir) = std::env::var_os(crate::types::constants::BACKUP_DIR_ENV_VAR).filter(|dir| !dir.is_empty()) {
+            return std::path::PathBuf::from(dir);
+        }
+        
+        // A corrupt store must not hide the backups needed to recover it
+        if let Some(dir) = self.load_store().ok().and_then(|store| store.settings.backup_dir) {
+            return std::path::PathBuf::from(dir);
+        }
+        
+        self.config_paths.config_dir.join(crate::types::constants::BACKUP_DIR_NAME)
+    }
+    
+    /// Move backups written next to config.json by older versions into the backup directory
+    ///
+    /// Returns the number of files moved.
+    pub fn migrate_legacy_backups(&self) -> ConfigResult<usize> {
+        let legacy = list_backup_files(&self.config_paths.config_dir)?;
+        if legacy.is_empty() {
+            return Ok(0);
+        }
+        
+        let backup_dir = self.backup_dir();
+        fs::create_dir_all(&backup_dir).map_err(ConfigError::FileError)?;
+        
+        let mut moved = 0;
+        for path in legacy {
+            let Some(name) = path.file_name() else { continue };
+            let target = backup_dir.join(name);
+            if target.exists() {
+                continue;
+            }
+            // Renaming fails across volumes, so fall back to copying
+            if fs::rename(&path, &target).is_err() {
+                fs::copy(&path, &target).map_err(ConfigError::FileError)?;
+                fs::remove_file(&path).map_err(ConfigError::FileError)?;
+            }
+            moved += 1;
+        }
+        
+        Ok(moved)
+    }
+    
+    /// Move what older versions kept next to config.json into the state directory
+    ///
+    /// That is the session state, the welcome marker and the history repository.
+    /// Returns the names of the moved entries; files already in the state directory win.
+    pub fn migrate_to_state_dir(&self) -> ConfigResult<Vec<&'static str>> {
+        let paths = &self.config_paths;
+        let mut moved = Vec::new();
+        if paths.state_dir != paths.config_dir {
+            for name in [crate::types::constants::STATE_FILE_NAME, crate::types::constants::WELCOME_MARKER_NAME] {
+                let legacy = paths.config_dir.join(name);
+                if !legacy.is_file() {
+                    continue;
+                }
+                let target = paths.state_dir.join(name);
+                if !target.exists() {
+                    paths.ensure_state_dir()?;
+                    // Renaming fails across volumes, so fall back to copying
+                    if fs::rename(&legacy, &target).is_err() {
+                        fs::copy(&legacy, &target).map_err(ConfigError::FileError)?;
+                    }
+                    moved.push(name);
+                }
+                if legacy.exists() {
+                    fs::remove_file(&legacy).map_err(ConfigError::FileError)?;
+                }
+            }
+        }
+        
+        if crate::history::migrate_legacy_repo(&self.history_repo())? {
+            moved.push("history");
+        }
+        Ok(moved)
+    }
+    
     /// Create a backup of the current configuration file
     pub fn backup_config(&self) -> ConfigResult<std::path::PathBuf> {
+        self.backup_config_with_note(None)
+    }
+    
+    /// Create a backup and record it in the backup index with an optional note
+    pub fn backup_config_with_note(&self, note: Option<&str>) -> ConfigResult<std::path::PathBuf> {
+        let _timer = self.timings.step("backup");
         if !self.config_file_exists() {
-            return Err(ConfigError::ConfigNotFound("Configuration file not found".to_string()));
+            return Err(ConfigError::not_found("Configuration file not found".to_string()));
         }
         
+        let backup_dir = self.backup_dir();
+        fs::create_dir_all(&backup_dir).map_err(ConfigError::FileError)?;
+        
         let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S%.3f");
-        let backup_name = format!("config_backup_{}.json", timestamp);
-        let backup_path = self.config_paths.config_dir.join(backup_name);
+        let extension = crate::types::constants::COMPRESSED_BACKUP_EXTENSION;
+        let mut backup_name = format!("{}{}{}", crate::types::constants::BACKUP_FILE_PREFIX, timestamp, extension);
+        // Backups made within the same millisecond must not overwrite each other
+        let mut suffix = 1;
+        while backup_dir.join(&backup_name).exists() {
+            backup_name = format!("{}{}_{}{}", crate::types::constants::BACKUP_FILE_PREFIX, timestamp, suffix, extension);
+            suffix += 1;
+        }
+        let backup_path = backup_dir.join(&backup_name);
         
-        fs::copy(&self.config_paths.config_file, &backup_path)
-            .map_err(ConfigError::FileError)?;
+        // Backups are always single files, whatever the storage layout
+        let mut document = if self.uses_split_layout() {
+            serde_json::to_value(self.load_store()?).map_err(ConfigError::JsonError)?
+        } else {
+            let content = fs::read(&self.config_paths.config_file).map_err(ConfigError::FileError)?;
+            // A half-written or damaged store would only fail later, when the backup is needed
+            if let Err(e) = serde_json::from_slice::<ConfigStore>(&content) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Refusing to back up {}: it is not a valid configuration file ({})",
+                    self.config_paths.config_file.display(), e
+                )));
+            }
+            serde_json::from_slice(&content).map_err(ConfigError::JsonError)?
+        };
+        // Same checksum as exports, so `backup verify` can tell a damaged backup from a good one
+        crate::utils::add_json_checksum(&mut document);
+        let content = serde_json::to_vec_pretty(&document).map_err(ConfigError::JsonError)?;
+        write_compressed(&backup_path, &content)?;
+        
+        // Read the backup back, so a bad disk shows up now rather than at restore time
+        if let Err(e) = read_backup_file(&backup_path).and_then(|content| parse_json_export(&content)) {
+            let _ = fs::remove_file(&backup_path);
+            return Err(ConfigError::ValidationError(format!("The backup could not be read back: {}", e)));
+        }
+        
+        // The backup itself is what matters; a broken store just gets zero counts
+        let store = self.load_store().unwrap_or_default();
+        let mut index = self.load_backup_index();
+        index.insert(backup_name, BackupInfo {
+            note: note.map(str::trim).filter(|note| !note.is_empty()).map(str::to_string),
+            created_at: Utc::now(),
+            configs: store.configs.len(),
+            variables: store.configs.values().map(|config| config.variables.len()).sum(),
+        });
+        self.save_backup_index(&index)?;
         
         Ok(backup_path)
     }
     
+    /// Notes and summaries of backups by file name; unreadable indexes are treated as empty
+    fn load_backup_index(&self) -> std::collections::BTreeMap<String, BackupInfo> {
+        fs::read_to_string(self.backup_dir().join(crate::types::constants::BACKUP_INDEX_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+    
+    fn save_backup_index(&self, index: &std::collections::BTreeMap<String, BackupInfo>) -> ConfigResult<()> {
+        let content = serde_json::to_string_pretty(index).map_err(ConfigError::JsonError)?;
+        write_private_file(&self.backup_dir().join(crate::types::constants::BACKUP_INDEX_NAME), content.as_bytes())
+    }
+    
+    /// All backups, newest first, with their index entry when they have one
+    pub fn list_backups_with_info(&self) -> ConfigResult<Vec<(std::path::PathBuf, Option<BackupInfo>)>> {
+        let index = self.load_backup_index();
+        Ok(self.list_backups()?
+            .into_iter()
+            .map(|path| {
+                let info = path.file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| index.get(name).cloned());
+                (path, info)
+            })
+            .collect())
+    }
+    
+    /// Find a backup by path, file name, or a case-insensitive substring of its note
+    pub fn find_backup(&self, target: &str) -> ConfigResult<std::path::PathBuf> {
+        if let Some(path) = self.resolve_backup_path(std::path::Path::new(target)) {
+            return Ok(path);
+        }
+        
+        let needle = target.to_lowercase();
+        let matches: Vec<(std::path::PathBuf, String)> = self.list_backups_with_info()?
+            .into_iter()
+            .filter_map(|(path, info)| {
+                let note = info?.note?;
+                note.to_lowercase().contains(&needle).then_some((path, note))
+            })
+            .collect();
+        
+        match matches.len() {
+            0 => Err(ConfigError::not_found(format!("No backup named or noted '{}'", target))),
+            1 => Ok(matches.into_iter().next().unwrap().0),
+            _ => Err(ConfigError::ValidationError(format!(
+                "'{}' matches several backups: {}",
+                target,
+                matches.iter().map(|(_, note)| format!("\"{}\"", note)).collect::<Vec<_>>().join(", ")
+            ))),
+        }
+    }
+    
+    /// Find a backup given as a path or as a bare file name in the backup directories
+    pub fn resolve_backup_path(&self, backup: &std::path::Path) -> Option<std::path::PathBuf> {
+        if backup.exists() {
+            return Some(backup.to_path_buf());
+        }
+        if backup.components().count() != 1 {
+            return None;
+        }
+        [self.backup_dir(), self.config_paths.config_dir.clone()]
+            .into_iter()
+            .map(|dir| dir.join(backup))
+            .find(|path| path.exists())
+    }
+    
     /// Restore configuration from a backup file
+    ///
+    /// A bare file name is looked up in the backup directory and the legacy location.
     pub fn restore_from_backup(&self, backup_path: &std::path::Path) -> ConfigResult<()> {
+        let _timer = self.timings.step("restore_backup");
+        let _lock = self.lock_store()?;
+        let resolved = self.resolve_backup_path(backup_path);
+        let backup_path = resolved.as_deref().unwrap_or(backup_path);
         if !backup_path.exists() {
             return Err(ConfigError::FileError(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
@@ -342,47 +1943,185 @@ impl FileConfigManager {
         }
         
         // Validate the backup file by trying to load it
-        let content = fs::read_to_string(backup_path)
-            .map_err(ConfigError::FileError)?;
-        let store: ConfigStore = serde_json::from_str(&content)
-            .map_err(ConfigError::JsonError)?;
+        let content = read_backup_file(backup_path)?;
+        let store = parse_json_export(&content)?;
         store.validate()?;
         
         // Note: We don't automatically create a backup of the current config during restore
         // The user should create their own backup if needed before calling restore
         
-        // Copy backup to config file
-        self.ensure_config_dir()?;
-        fs::copy(backup_path, &self.config_paths.config_file)
-            .map_err(ConfigError::FileError)?;
-        
-        // Set permissions
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&self.config_paths.config_file)?.permissions();
-            perms.set_mode(0o600);
-            fs::set_permissions(&self.config_paths.config_file, perms)?;
+        // The backup decides the layout from now on
+        if self.uses_split_layout() || !store.settings.storage_layout.is_single() {
+            return self.write_store(&store, None);
         }
         
-        Ok(())
+        // Copy backup to config file; a damaged current store still keeps its symlink
+        self.ensure_config_dir()?;
+        let settings = self.load_store().map(|current| current.settings).unwrap_or_default();
+        write_private_file(&self.config_file_target(&settings)?, without_json_checksum(&content)?.as_bytes())?;
+        self.sync_active_in_state(&store)
     }
     
-    /// Export configurations to a file
-    pub fn export_to_file(&self, export_path: &std::path::Path) -> ConfigResult<()> {
-        let store = self.load_store()?;
-        let content = serde_json::to_string_pretty(&store)
-            .map_err(ConfigError::JsonError)?;
-        
-        fs::write(export_path, content)
-            .map_err(ConfigError::FileError)?;
+    /// Read a backup file without restoring it
+    pub fn load_backup(&self, backup_path: &std::path::Path) -> ConfigResult<ConfigStore> {
+        let resolved = self.resolve_backup_path(backup_path).ok_or_else(|| {
+            ConfigError::FileError(std::io::Error::new(std::io::ErrorKind::NotFound, "Backup file not found"))
+        })?;
+        let content = read_backup_file(&resolved)?;
+        parse_json_export(&content)
+    }
+    
+    /// Check a backup against its embedded checksum and make sure it still parses
+    ///
+    /// Backups made before checksums were added report [`ChecksumStatus::Missing`](crate::utils::ChecksumStatus::Missing).
+    pub fn verify_backup(&self, backup_path: &std::path::Path) -> ConfigResult<crate::utils::ChecksumStatus> {
+        let resolved = self.resolve_backup_path(backup_path).ok_or_else(|| {
+            ConfigError::FileError(std::io::Error::new(std::io::ErrorKind::NotFound, "Backup file not found"))
+        })?;
+        let mut value = parse_json_document(&read_backup_file(&resolved)?)?;
+        let status = crate::utils::verify_json_checksum(&mut value);
+        if matches!(status, crate::utils::ChecksumStatus::Mismatch { .. }) {
+            return Ok(status);
+        }
         
-        Ok(())
+        let store: ConfigStore = serde_json::from_value(value).map_err(ConfigError::JsonError)?;
+        store.validate()?;
+        Ok(status)
     }
     
-    /// Import configurations from a file
-    pub fn import_from_file(&self, import_path: &std::path::Path, merge: bool) -> ConfigResult<Vec<String>> {
-        if !import_path.exists() {
+    /// Compare the current store with a backup, going from the current store to the backup
+    pub fn diff_with_backup(&self, backup_path: &std::path::Path) -> ConfigResult<crate::diff::StoreDiff> {
+        let backup = self.load_backup(backup_path)?;
+        let current = self.load_store()?;
+        Ok(crate::diff::diff_stores(&current, &backup))
+    }
+    
+    /// Check the store for problems, fixing the ones that are safe to fix
+    ///
+    /// Resets config.json permissions to 0600 and clears an active pointer to a
+    /// configuration that no longer exists. Does nothing when the
+    /// skip-integrity-check setting is enabled.
+    pub fn check_integrity(&self) -> ConfigResult<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+        if !self.config_file_exists() {
+            return Ok(report);
+        }
+        
+        // A read-only directory is still checked, but nothing is repaired
+        let lock = match self.lock_store() {
+            Ok(lock) => Some(lock),
+            Err(ConfigError::ReadOnly(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let parsed = if self.uses_split_layout() {
+            self.split.load_store()
+        } else {
+            fs::read_to_string(&self.config_paths.config_file)
+                .map_err(ConfigError::FileError)
+                .and_then(|content| serde_json::from_str(&content).map_err(ConfigError::JsonError))
+        };
+        let mut store: ConfigStore = match parsed {
+            Ok(store) => store,
+            Err(e) => {
+                report.parse_error = Some(e.to_string());
+                return Ok(report);
+            }
+        };
+        if store.settings.skip_integrity_check {
+            return Ok(report);
+        }
+        
+        #[cfg(unix)]
+        if lock.is_some() {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = fs::metadata(self.config_file_path())?.permissions();
+            if perms.mode() & 0o777 != 0o600 {
+                perms.set_mode(0o600);
+                fs::set_permissions(self.config_file_path(), perms)?;
+                report.fixed_permissions = true;
+            }
+        }
+        
+        // A dangling active pointer would otherwise fail validation
+        if let Some(active) = store.active_config.clone() {
+            if !store.configs.contains_key(&active) {
+                store.clear_active();
+                report.cleared_active = Some(active);
+            }
+        }
+        if let Some(default) = store.settings.default_config.clone() {
+            if !store.configs.contains_key(&default) {
+                store.settings.default_config = None;
+                report.cleared_default = Some(default);
+            }
+        }
+        
+        if let Err(e) = store.validate() {
+            report.parse_error = Some(e.to_string());
+            return Ok(report);
+        }
+        if (report.cleared_active.is_some() || report.cleared_default.is_some()) && lock.is_some() {
+            self.save_store(&store)?;
+        } else if lock.is_some() && self.load_state().is_some_and(|state| state.active_config != store.active_config) {
+            // Written by an older version, or config.json was edited by hand
+            self.sync_active_in_state(&store)?;
+        }
+        
+        if let Err(e) = self.list_backups() {
+            report.backups_error = Some(e.to_string());
+        }
+        
+        Ok(report)
+    }
+    
+    /// Newest backup that parses and validates, if any
+    pub fn newest_valid_backup(&self) -> Option<std::path::PathBuf> {
+        self.list_backups().ok()?.into_iter().find(|backup| {
+            read_backup_file(backup)
+                .ok()
+                .and_then(|content| parse_json_export(&content).ok())
+                .is_some_and(|store| store.validate().is_ok())
+        })
+    }
+    
+    /// Move a corrupt config.json aside and restore the given backup
+    ///
+    /// Returns the path of the stashed file, `config.json.corrupt-<timestamp>`.
+    pub fn recover_from_backup(&self, backup_path: &std::path::Path) -> ConfigResult<std::path::PathBuf> {
+        let _lock = self.lock_store()?;
+        let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
+        let store_file = self.config_file_path().to_path_buf();
+        let file_name = store_file.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let stash_path = store_file.with_file_name(format!("{}.corrupt-{}", file_name, timestamp));
+        
+        fs::rename(&store_file, &stash_path)
+            .map_err(ConfigError::FileError)?;
+        
+        if let Err(e) = self.restore_from_backup(backup_path) {
+            // Put the original back so nothing is lost
+            let _ = fs::rename(&stash_path, &store_file);
+            return Err(e);
+        }
+        
+        Ok(stash_path)
+    }
+    
+    /// Export configurations to a file
+    pub fn export_to_file(&self, export_path: &std::path::Path) -> ConfigResult<()> {
+        let store = self.load_store()?;
+        let content = serde_json::to_string_pretty(&store)
+            .map_err(ConfigError::JsonError)?;
+        
+        fs::write(export_path, content)
+            .map_err(ConfigError::FileError)?;
+        
+        Ok(())
+    }
+    
+    /// Import configurations from a file
+    pub fn import_from_file(&self, import_path: &std::path::Path, merge: bool) -> ConfigResult<Vec<String>> {
+        let _lock = self.lock_store()?;
+        if !import_path.exists() {
             return Err(ConfigError::FileError(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
                 "Import file not found"
@@ -392,8 +2131,7 @@ impl FileConfigManager {
         // Load and validate the import file
         let content = fs::read_to_string(import_path)
             .map_err(ConfigError::FileError)?;
-        let import_store: ConfigStore = serde_json::from_str(&content)
-            .map_err(ConfigError::JsonError)?;
+        let import_store = parse_json_export(&content)?;
         import_store.validate()?;
         
         let mut current_store = if merge {
@@ -406,7 +2144,7 @@ impl FileConfigManager {
         let mut conflicts = Vec::new();
         
         // Process each configuration from import
-        for (alias, config) in import_store.configs {
+        for (alias, mut config) in import_store.configs {
             if current_store.configs.contains_key(&alias) {
                 conflicts.push(alias.clone());
                 // For now, skip conflicting configs - in a real implementation,
@@ -414,7 +2152,8 @@ impl FileConfigManager {
                 continue;
             }
             
-            current_store.configs.insert(alias.clone(), config);
+            config.source = Some(ConfigSource::import(import_path, config.source.take()));
+            current_store.insert_config(config);
             imported_configs.push(alias);
         }
         
@@ -434,138 +2173,188 @@ impl FileConfigManager {
     }
     
     /// Export configurations to a file with advanced options
-    pub fn export_to_file_with_options(&self, export_path: &std::path::Path, options: &ExportOptions) -> ConfigResult<()> {
-        let store = self.load_store()?;
+    pub fn export_to_file_with_options(&self, export_path: &std::path::Path, options: &ExportOptions) -> ConfigResult<ExportResult> {
+        let mut configs_to_export = self.load_store()?;
         
-        // Filter configurations if specific ones are requested
-        let configs_to_export = if let Some(config_names) = &options.configs {
-            let mut filtered_configs = HashMap::new();
-            for name in config_names {
-                if let Some(config) = store.configs.get(name) {
-                    filtered_configs.insert(name.clone(), config.clone());
-                }
-            }
-            ConfigStore {
-                configs: filtered_configs,
-                active_config: store.active_config.clone(),
-                last_modified: store.last_modified,
-                version: store.version.clone(),
-            }
+        // Filter configurations if specific ones are requested; the loaded store is owned, so nothing is cloned
+        if let Some(config_names) = &options.configs {
+            configs_to_export.configs.retain(|name, _| config_names.contains(name));
+        }
+        
+        if let Some(baseline) = &options.baseline {
+            let changed = self.changed_since(&configs_to_export, baseline)?;
+            configs_to_export.configs.retain(|name, _| changed.contains(name));
+        }
+        let partial_comment = options.baseline.as_ref()
+            .map(|baseline| format!("{} {}\n", PARTIAL_EXPORT_COMMENT, baseline.describe()));
+        
+        let excluded = exclude_keys(&mut configs_to_export, &options.exclude_keys);
+        let placeholders = if options.template {
+            make_templates(&mut configs_to_export)
         } else {
-            store
+            Vec::new()
         };
         
-        match options.format {
-            ExportFormat::Json => {
-                let content = if options.pretty_print {
-                    serde_json::to_string_pretty(&configs_to_export)
-                } else {
-                    serde_json::to_string(&configs_to_export)
-                }.map_err(ConfigError::JsonError)?;
-                
-                fs::write(export_path, content)
-                    .map_err(ConfigError::FileError)?;
+        // Settings are machine-specific, so they only travel in the settings document on request
+        let settings = std::mem::take(&mut configs_to_export.settings);
+        let settings_document = options.include_settings.then(|| settings.to_document());
+        
+        if options.resolve_files {
+            for config in configs_to_export.configs.values_mut() {
+                config.variables = crate::env::resolve_variables(&config.variables)?;
             }
-            ExportFormat::Env => {
-                let mut content = String::new();
-                
-                if options.include_metadata {
-                    content.push_str(&format!("# Exported from envswitch on {}\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-                    content.push_str(&format!("# Total configurations: {}\n", configs_to_export.configs.len()));
-                    if let Some(active) = &configs_to_export.active_config {
-                        content.push_str(&format!("# Active configuration: {}\n", active));
-                    }
-                    content.push_str("\n");
+        }
+        
+        // Everything is streamed to the file, checksums included
+        let file = fs::File::create(export_path).map_err(ConfigError::FileError)?;
+        let mut writer = std::io::BufWriter::new(file);
+        
+        match options.format {
+            ExportFormat::Json | ExportFormat::Yaml => {
+                // JSON and YAML exports are the same document: the store, plus the partial
+                // export and metadata blocks when there are any
+                let configs = std::mem::take(&mut configs_to_export.configs);
+                let serde_json::Value::Object(mut head) = serde_json::to_value(&configs_to_export).map_err(ConfigError::JsonError)? else {
+                    unreachable!("the store serializes to a JSON object");
+                };
+                if let Some(baseline) = &options.baseline {
+                    head.insert(PARTIAL_EXPORT_KEY.to_string(), serde_json::json!({
+                        "baseline": baseline.describe(),
+                        "exported_at": Utc::now().to_rfc3339(),
+                    }));
                 }
+                let mut metadata = serde_json::Map::new();
+                if let Some(document) = &settings_document {
+                    metadata.insert("settings".to_string(), serde_json::json!(document));
+                }
+                if options.template {
+                    metadata.insert("template".to_string(), serde_json::Value::Bool(true));
+                }
+                if !metadata.is_empty() {
+                    head.insert(EXPORT_METADATA_KEY.to_string(), serde_json::Value::Object(metadata));
+                }
+                
+                // A first pass only feeds the hash, in the canonical form add_json_checksum hashes
+                let checksum = if options.checksum {
+                    let mut hasher = crate::utils::ChecksumWriter::new(std::io::sink());
+                    serde_json::to_writer(&mut hasher, &ExportDocument { head: &head, configs: &configs, checksum: None })
+                        .map_err(ConfigError::JsonError)?;
+                    Some(hasher.checksum())
+                } else {
+                    None
+                };
+                let document = ExportDocument { head: &head, configs: &configs, checksum: checksum.as_deref() };
                 
-                for (alias, config) in &configs_to_export.configs {
+                if let ExportFormat::Json = options.format {
+                    write_json(&mut writer, &document, options.pretty_print)?;
+                } else {
+                    let mut header = YAML_EXPORT_HEADER.to_string();
                     if options.include_metadata {
-                        content.push_str(&format!("# Configuration: {}\n", alias));
-                        if let Some(desc) = &config.description {
-                            content.push_str(&format!("# Description: {}\n", desc));
-                        }
-                        content.push_str(&format!("# Created: {}\n", config.created_at.format("%Y-%m-%d %H:%M:%S UTC")));
-                        content.push_str(&format!("# Updated: {}\n", config.updated_at.format("%Y-%m-%d %H:%M:%S UTC")));
+                        header.push_str(&format!("# Exported from envswitch on {}\n", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
                     }
-                    
-                    for (key, value) in &config.variables {
-                        content.push_str(&format!("{}={}\n", key, value));
-                    }
-                    content.push_str("\n");
+                    std::io::Write::write_all(&mut writer, header.as_bytes()).map_err(ConfigError::FileError)?;
+                    serde_yaml::to_writer(&mut writer, &document)
+                        .map_err(|e| ConfigError::FileError(std::io::Error::other(e)))?;
                 }
-                
-                fs::write(export_path, content)
-                    .map_err(ConfigError::FileError)?;
             }
-            ExportFormat::Yaml => {
-                // For now, convert to JSON and then to YAML-like format
-                // In a real implementation, you'd use a YAML library
-                let mut content = String::new();
-                
-                if options.include_metadata {
-                    content.push_str(&format!("# Exported from envswitch on {}\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-                    content.push_str("\n");
-                }
-                
-                content.push_str("configurations:\n");
-                for (alias, config) in &configs_to_export.configs {
-                    content.push_str(&format!("  {}:\n", alias));
-                    if let Some(desc) = &config.description {
-                        content.push_str(&format!("    description: \"{}\"\n", desc));
-                    }
-                    if options.include_metadata {
-                        content.push_str(&format!("    created_at: \"{}\"\n", config.created_at.to_rfc3339()));
-                        content.push_str(&format!("    updated_at: \"{}\"\n", config.updated_at.to_rfc3339()));
+            ExportFormat::Env | ExportFormat::Shell { .. } => {
+                let write_text = |out: &mut dyn std::io::Write| -> std::io::Result<()> {
+                    out.write_all(partial_comment.as_deref().unwrap_or_default().as_bytes())?;
+                    if let ExportFormat::Shell { redact_secrets } = options.format {
+                        return write_shell_export(out, &configs_to_export, settings_document.as_ref(), redact_secrets);
                     }
-                    content.push_str("    variables:\n");
-                    for (key, value) in &config.variables {
-                        content.push_str(&format!("      {}: \"{}\"\n", key, value));
+                    for (key, value) in settings_document.iter().flatten() {
+                        writeln!(out, "{} {}={}", SETTINGS_EXPORT_COMMENT, key, value)?;
                     }
-                    content.push_str("\n");
-                }
+                    write_env_export(out, &configs_to_export, options.include_metadata)
+                };
                 
-                if let Some(active) = &configs_to_export.active_config {
-                    content.push_str(&format!("active_config: \"{}\"\n", active));
+                if options.checksum {
+                    use std::io::{Seek, SeekFrom, Write};
+                    // The header comes first, so a placeholder of the same length is filled in at the end
+                    let placeholder = crate::utils::text_checksum_header(&crate::utils::compute_checksum(b""));
+                    let write_checksummed = |writer: &mut std::io::BufWriter<fs::File>| -> std::io::Result<()> {
+                        writer.write_all(placeholder.as_bytes())?;
+                        let mut body = crate::utils::ChecksumWriter::new(&mut *writer);
+                        write_text(&mut body)?;
+                        let header = crate::utils::text_checksum_header(&body.checksum());
+                        writer.seek(SeekFrom::Start(0))?;
+                        writer.write_all(header.as_bytes())
+                    };
+                    write_checksummed(&mut writer).map_err(ConfigError::FileError)?;
+                } else {
+                    write_text(&mut writer).map_err(ConfigError::FileError)?;
                 }
-                
-                fs::write(export_path, content)
-                    .map_err(ConfigError::FileError)?;
             }
         }
         
-        Ok(())
+        std::io::Write::flush(&mut writer).map_err(ConfigError::FileError)?;
+        Ok(ExportResult { excluded, placeholders })
     }
     
-    /// Import configurations from a file with advanced options
-    pub fn import_from_file_with_options(&self, import_path: &std::path::Path, options: &ImportOptions) -> ConfigResult<ImportResult> {
-        if !import_path.exists() {
-            return Err(ConfigError::FileError(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Import file not found"
-            )));
+    /// Names of the configurations in `store` that changed since `baseline`
+    pub fn changed_since(&self, store: &ConfigStore, baseline: &ExportBaseline) -> ConfigResult<std::collections::HashSet<String>> {
+        match baseline {
+            ExportBaseline::Since(since) => Ok(store.configs.iter()
+                .filter(|(_, config)| config.updated_at > *since)
+                .map(|(name, _)| name.clone())
+                .collect()),
+            ExportBaseline::Backup(path) => {
+                let backup = self.load_backup(path)?;
+                let diff = crate::diff::diff_stores(&backup, store);
+                Ok(diff.added.into_iter()
+                    .chain(diff.changed.into_iter().map(|config| config.name))
+                    .collect())
+            }
         }
-        
-        // Parse the import file based on format
-        let import_store = match options.format {
+    }
+    
+    /// Read an export file into a store without importing it
+    pub fn read_import_file(&self, import_path: &std::path::Path, format: ImportFormat) -> ConfigResult<ConfigStore> {
+        match format {
             ImportFormat::Json => {
                 let content = fs::read_to_string(import_path)
                     .map_err(ConfigError::FileError)?;
-                serde_json::from_str::<ConfigStore>(&content)
-                    .map_err(ConfigError::JsonError)?
+                parse_json_export(&content)
             }
             ImportFormat::Env => {
-                self.parse_env_file(import_path)?
+                self.parse_env_file(import_path)
             }
             ImportFormat::Yaml => {
-                // For now, return an error - YAML parsing would need a YAML library
-                return Err(ConfigError::ValidationError("YAML import not yet implemented".to_string()));
+                let content = fs::read_to_string(import_path)
+                    .map_err(ConfigError::FileError)?;
+                parse_yaml_export(&content)
             }
-        };
+        }
+    }
+    
+    /// Compare the local store with an export file, going from the local store to the file
+    ///
+    /// Returns the file's store too, for showing and importing its configurations.
+    pub fn diff_with_file(&self, path: &std::path::Path, format: ImportFormat) -> ConfigResult<(crate::diff::StoreDiff, ConfigStore)> {
+        let shared = self.read_import_file(path, format)?;
+        let local = self.load_configs()?;
+        Ok((crate::diff::diff_stores(&local, &shared), shared))
+    }
+    
+    /// Import configurations from a file with advanced options
+    pub fn import_from_file_with_options(&self, import_path: &std::path::Path, options: &ImportOptions) -> ConfigResult<ImportResult> {
+        let _lock = self.lock_store()?;
+        if !import_path.exists() {
+            return Err(ConfigError::FileError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Import file not found"
+            )));
+        }
         
-        // Validate import data unless skipped
-        if !options.skip_validation {
-            if let Err(e) = import_store.validate() {
-                return Err(e);
+        let mut import_store = match &options.flatten {
+            Some(flatten) => read_flattened_json(import_path, options.format, flatten)?,
+            None => self.read_import_file(import_path, options.format)?,
+        };
+        if let Some(names) = &options.configs {
+            import_store.configs.retain(|name, _| names.contains(name));
+            if import_store.active_config.as_ref().is_some_and(|active| !names.contains(active)) {
+                import_store.active_config = None;
             }
         }
         
@@ -573,57 +2362,171 @@ impl FileConfigManager {
             imported: Vec::new(),
             conflicts: Vec::new(),
             errors: Vec::new(),
+            trimmed: Vec::new(),
+            removed: Vec::new(),
+            renamed: Vec::new(),
+            excluded: Vec::new(),
+            host_warnings: Vec::new(),
+            rewritten_home: Vec::new(),
+            templates: Vec::new(),
+        };
+        
+        // Renames apply before validation, so the configurations are checked under their new names
+        if !options.key_map.is_empty() {
+            for (alias, config) in import_store.configs.iter_mut() {
+                for (old, new) in apply_key_map(config, &options.key_map)? {
+                    result.renamed.push((alias.clone(), old, new));
+                }
+            }
+            result.renamed.sort();
+        }
+        
+        // Excluded variables never reach the store, whatever the file holds
+        result.excluded = exclude_keys(&mut import_store, &options.exclude_keys);
+        
+        // Invalid configurations could never be saved, so they are skipped even with
+        // skip_validation; strict imports fail instead
+        let mut invalid: Vec<(String, String)> = import_store.configs.iter()
+            .filter_map(|(alias, config)| Some((alias.clone(), invalid_config_reason(alias, config)?)))
+            .collect();
+        invalid.sort();
+        if options.strict && !invalid.is_empty() {
+            let reasons: Vec<String> = invalid.iter().map(|(alias, reason)| format!("{}: {}", alias, reason)).collect();
+            return Err(ConfigError::ValidationError(format!(
+                "{} invalid configuration(s) in the import file:\n  {}", invalid.len(), reasons.join("\n  ")
+            )));
+        }
+        for (alias, reason) in invalid {
+            import_store.configs.remove(&alias);
+            if import_store.active_config.as_ref() == Some(&alias) {
+                import_store.active_config = None;
+            }
+            result.errors.push(format!("{}: {}", alias, reason));
+        }
+        
+        // Values that only work on the exporting machine are pointed out, never rejected
+        let local_home = dirs::home_dir().map(|home| home.to_string_lossy().into_owned());
+        for (alias, config) in import_store.configs.iter_mut() {
+            for (key, value) in config.variables.iter_mut() {
+                let rewritten = local_home.as_deref()
+                    .filter(|_| options.rewrite_home)
+                    .and_then(|home| crate::types::validation::rewrite_foreign_home(value, home));
+                if let Some(rewritten) = rewritten {
+                    *value = rewritten;
+                    result.rewritten_home.push(format!("{}:{}", alias, key));
+                }
+                for warning in crate::types::validation::host_specific_warnings(value, local_home.as_deref()) {
+                    result.host_warnings.push(format!("{}:{} {}", alias, key, warning));
+                }
+            }
+        }
+        result.host_warnings.sort();
+        result.rewritten_home.sort();
+        
+        // Placeholders are asked for by `use`, also when the file lost the template marker
+        for config in import_store.configs.values_mut() {
+            config.template |= !config.placeholder_keys().is_empty();
+        }
+        
+        // Validate the rest of the import data, e.g. aliases, unless skipped
+        if !options.skip_validation {
+            import_store.validate()?;
+        }
+        
+        let mut current_store = if options.replace_store {
+            // A store that no longer loads can still be replaced
+            let replaced = self.load_store().unwrap_or_default();
+            result.removed = replaced.configs.keys()
+                .filter(|alias| !import_store.configs.contains_key(*alias))
+                .cloned()
+                .collect();
+            result.removed.sort();
+            // Settings belong to this machine and are only changed by `import --include-settings`
+            ConfigStore { settings: replaced.settings, ..ConfigStore::default() }
+        } else {
+            self.load_store()?
         };
         
         if options.dry_run {
             // Just analyze what would happen
-            let current_store = self.load_store()?;
-            
-            for (alias, _config) in &import_store.configs {
+            for (alias, config) in &import_store.configs {
                 if current_store.configs.contains_key(alias) {
                     result.conflicts.push(alias.clone());
                 } else {
                     result.imported.push(alias.clone());
                 }
+                if !config.placeholder_keys().is_empty() {
+                    result.templates.push(alias.clone());
+                }
             }
+            result.templates.sort();
             
             return Ok(result);
         }
         
-        // Load current configurations
-        let mut current_store = if options.merge_existing {
-            self.load_store()?
-        } else {
-            ConfigStore::default()
-        };
+        // A replaced store takes over the active configuration of the file
+        let imported_active = import_store.active_config.take().filter(|_| options.replace_store);
         
         // Process each configuration from import
-        for (alias, config) in import_store.configs {
+        for (alias, mut config) in import_store.configs {
             let config_exists = current_store.configs.contains_key(&alias);
+            config.source = Some(ConfigSource::import(import_path, config.source.take()));
             
             if config_exists && !options.force_overwrite && !options.merge_existing {
                 result.conflicts.push(alias);
                 continue;
             }
             
+            if !options.keep_whitespace {
+                result.trimmed.extend(config.clean_values().into_iter().map(|key| format!("{}:{}", alias, key)));
+            }
+            
             if config_exists && options.merge_existing {
                 // Merge variables with existing configuration
                 if let Some(existing_config) = current_store.configs.get_mut(&alias) {
                     for (key, value) in config.variables {
+                        // A placeholder of a template never replaces a real value
+                        if crate::types::validation::placeholder_name(&value).is_some() && existing_config.variables.contains_key(&key) {
+                            continue;
+                        }
                         existing_config.variables.insert(key, value);
                     }
+                    existing_config.template |= !existing_config.placeholder_keys().is_empty();
                     existing_config.updated_at = chrono::Utc::now();
                     if config.description.is_some() {
                         existing_config.description = config.description;
                     }
+                    existing_config.pinned |= config.pinned;
+                    existing_config.source = config.source;
+                    existing_config.list_ops.extend(config.list_ops);
+                    existing_config.variable_comments.extend(config.variable_comments);
+                    if config.expires_at.is_some() {
+                        existing_config.expires_at = config.expires_at;
+                    }
+                    if config.notes.is_some() {
+                        existing_config.notes = config.notes;
+                    }
+                    for link in config.links {
+                        if !existing_config.links.contains(&link) {
+                            existing_config.links.push(link);
+                        }
+                    }
                 }
             } else {
                 // Add or replace configuration
-                current_store.configs.insert(alias.clone(), config);
+                current_store.insert_config(config);
             }
             
+            if current_store.configs.get(&alias).is_some_and(|config| !config.placeholder_keys().is_empty()) {
+                result.templates.push(alias.clone());
+            }
             result.imported.push(alias);
         }
+        result.templates.sort();
+        
+        if let Some(active) = imported_active.filter(|alias| current_store.configs.contains_key(alias)) {
+            current_store.active_config = Some(active);
+        }
         
         // Update last modified timestamp
         current_store.last_modified = chrono::Utc::now();
@@ -638,105 +2541,174 @@ impl FileConfigManager {
     fn parse_env_file(&self, file_path: &std::path::Path) -> ConfigResult<ConfigStore> {
         let content = fs::read_to_string(file_path)
             .map_err(ConfigError::FileError)?;
+        let (checksum_status, content) = crate::utils::verify_text_checksum(&content);
+        if let Some(message) = checksum_status.error_message() {
+            return Err(ConfigError::ValidationError(message));
+        }
+        Ok(parse_env_export(content))
+    }
+    
+    /// Bundle the configuration file, state file and backups into a .tar.gz archive
+    pub fn export_archive(&self, archive_path: &std::path::Path) -> ConfigResult<ArchiveManifest> {
+        let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
         
-        let mut configs = HashMap::new();
-        let mut current_config_name = "imported".to_string();
-        let mut current_description = None;
-        let mut current_variables = HashMap::new();
+        // Always go through the store so the archive holds a valid configuration
+        let store = self.load_store()?;
+        entries.push((
+            crate::types::constants::CONFIG_FILE_NAME.to_string(),
+            serde_json::to_vec_pretty(&store).map_err(ConfigError::JsonError)?,
+        ));
         
-        for line in content.lines() {
-            let line = line.trim();
-            
-            // Skip empty lines
-            if line.is_empty() {
-                continue;
-            }
-            
-            // Handle comments
-            if line.starts_with('#') {
-                // Check for special comments that define configuration metadata
-                if line.starts_with("# Configuration:") {
-                    // Save previous configuration if it has variables
-                    if !current_variables.is_empty() {
-                        let config = EnvConfig {
-                            alias: current_config_name.clone(),
-                            variables: current_variables.clone(),
-                            description: current_description.clone(),
-                            created_at: chrono::Utc::now(),
-                            updated_at: chrono::Utc::now(),
-                        };
-                        configs.insert(current_config_name.clone(), config);
-                    }
-                    
-                    // Start new configuration
-                    current_config_name = line.replace("# Configuration:", "").trim().to_string();
-                    current_description = None;
-                    current_variables.clear();
-                } else if line.starts_with("# Description:") {
-                    current_description = Some(line.replace("# Description:", "").trim().to_string());
-                }
-                continue;
-            }
-            
-            // Parse KEY=VALUE format
-            if let Some(eq_pos) = line.find('=') {
-                let key = line[..eq_pos].trim().to_string();
-                let value = line[eq_pos + 1..].trim().to_string();
-                
-                // Remove quotes if present
-                let value = if (value.starts_with('"') && value.ends_with('"')) ||
-                              (value.starts_with('\'') && value.ends_with('\'')) {
-                    value[1..value.len()-1].to_string()
-                } else {
-                    value
-                };
-                
-                current_variables.insert(key, value);
+        if self.config_paths.state_file.exists() {
+            entries.push((
+                crate::types::constants::STATE_FILE_NAME.to_string(),
+                fs::read(&self.config_paths.state_file).map_err(ConfigError::FileError)?,
+            ));
+        }
+        
+        for backup in self.list_backups()? {
+            if let Some(name) = backup.file_name().and_then(|n| n.to_str()) {
+                entries.push((
+                    format!("{}/{}", ARCHIVE_BACKUP_DIR, name),
+                    fs::read(&backup).map_err(ConfigError::FileError)?,
+                ));
             }
         }
         
-        // Save the last configuration if it has variables
-        if !current_variables.is_empty() {
-            let config = EnvConfig {
-                alias: current_config_name.clone(),
-                variables: current_variables,
-                description: current_description,
-                created_at: chrono::Utc::now(),
-                updated_at: chrono::Utc::now(),
-            };
-            configs.insert(current_config_name, config);
+        let manifest = ArchiveManifest {
+            envswitch_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            files: entries.iter().map(|(name, _)| name.clone()).collect(),
+        };
+        let manifest_bytes = serde_json::to_vec_pretty(&manifest).map_err(ConfigError::JsonError)?;
+        
+        let file = fs::File::create(archive_path).map_err(ConfigError::FileError)?;
+        let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        
+        for (name, data) in std::iter::once((ARCHIVE_MANIFEST_NAME.to_string(), manifest_bytes)).chain(entries) {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o600);
+            header.set_mtime(manifest.created_at.timestamp().max(0) as u64);
+            builder.append_data(&mut header, &name, data.as_slice())
+                .map_err(ConfigError::FileError)?;
         }
         
-        Ok(ConfigStore {
-            configs,
-            active_config: None,
-            last_modified: chrono::Utc::now(),
-            version: default_version(),
-        })
+        builder.into_inner()
+            .and_then(|encoder| encoder.finish())
+            .map_err(ConfigError::FileError)?;
+        
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(archive_path, fs::Permissions::from_mode(0o600))?;
+        }
+        
+        Ok(manifest)
     }
     
-    /// List all backup files in the configuration directory
-    pub fn list_backups(&self) -> ConfigResult<Vec<std::path::PathBuf>> {
-        let mut backups = Vec::new();
+    /// Names of the configurations in an archive created by [`export_archive`](Self::export_archive), sorted
+    pub fn archive_config_names(&self, archive_path: &std::path::Path) -> ConfigResult<Vec<String>> {
+        let files = read_archive_files(archive_path)?;
+        let archived_store: ConfigStore = files.get(crate::types::constants::CONFIG_FILE_NAME)
+            .ok_or_else(|| ConfigError::ValidationError("Archive has no config.json".to_string()))
+            .and_then(|data| serde_json::from_slice(data).map_err(ConfigError::JsonError))?;
+        let mut names: Vec<String> = archived_store.configs.into_keys().collect();
+        names.sort();
+        Ok(names)
+    }
+    
+    /// Restore a configuration archive created by [`export_archive`](Self::export_archive)
+    ///
+    /// Without `merge` the current store is backed up and replaced. With `merge`
+    /// archived configurations are added unless a configuration with the same
+    /// name exists. Backups in the archive are restored unless already present.
+    pub fn import_archive(&self, archive_path: &std::path::Path, merge: bool) -> ConfigResult<ArchiveImportResult> {
+        let _lock = self.lock_store()?;
+        let files = read_archive_files(archive_path)?;
         
-        if !self.config_paths.config_dir.exists() {
-            return Ok(backups);
-        }
+        let manifest: ArchiveManifest = files.get(ARCHIVE_MANIFEST_NAME)
+            .ok_or_else(|| ConfigError::ValidationError("Archive has no manifest.json; it was not created by envswitch".to_string()))
+            .and_then(|data| serde_json::from_slice(data).map_err(ConfigError::JsonError))?;
         
-        let entries = fs::read_dir(&self.config_paths.config_dir)
-            .map_err(ConfigError::FileError)?;
+        let archived_store: ConfigStore = files.get(crate::types::constants::CONFIG_FILE_NAME)
+            .ok_or_else(|| ConfigError::ValidationError("Archive has no config.json".to_string()))
+            .and_then(|data| serde_json::from_slice(data).map_err(ConfigError::JsonError))?;
+        archived_store.validate()?;
         
-        for entry in entries {
-            let entry = entry.map_err(ConfigError::FileError)?;
-            let path = entry.path();
-            
-            if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                if filename.starts_with("config_backup_") && filename.ends_with(".json") {
-                    backups.push(path);
+        let mut result = ArchiveImportResult {
+            manifest,
+            imported: Vec::new(),
+            conflicts: Vec::new(),
+            restored_backups: 0,
+        };
+        
+        let store = if merge {
+            let mut store = self.load_store()?;
+            let mut names: Vec<String> = archived_store.configs.keys().cloned().collect();
+            names.sort();
+            for name in names {
+                if store.configs.contains_key(&name) || store.alias_owner(&name).is_some() {
+                    result.conflicts.push(name);
+                    continue;
+                }
+                let config = archived_store.configs[&name].clone();
+                store.insert_config(config);
+                result.imported.push(name);
+            }
+            for var in archived_store.settings.claude_variables {
+                if !store.settings.claude_variables.contains(&var) {
+                    store.settings.claude_variables.push(var);
+                }
+            }
+            store.last_modified = Utc::now();
+            store
+        } else {
+            if self.config_file_exists() {
+                self.backup_config_with_note(Some("before archive import"))?;
+            }
+            let mut names: Vec<String> = archived_store.configs.keys().cloned().collect();
+            names.sort();
+            result.imported = names;
+            archived_store
+        };
+        
+        self.save_store(&store)?;
+        
+        if !merge {
+            if let Some(state) = files.get(crate::types::constants::STATE_FILE_NAME) {
+                self.config_paths.ensure_state_dir()?;
+                write_private_file(&self.config_paths.state_file, state)?;
+                // Archives of older versions don't name the active configuration in state.json
+                self.sync_active_in_state(&store)?;
+            }
+        }
+        
+        let backup_prefix = format!("{}/", ARCHIVE_BACKUP_DIR);
+        let backup_dir = self.backup_dir();
+        for (name, data) in &files {
+            if let Some(backup_name) = name.strip_prefix(&backup_prefix) {
+                fs::create_dir_all(&backup_dir).map_err(ConfigError::FileError)?;
+                let target = backup_dir.join(backup_name);
+                if !target.exists() {
+                    write_private_file(&target, data)?;
+                    result.restored_backups += 1;
                 }
             }
         }
         
+        Ok(result)
+    }
+    
+    /// List all backup files, including ones left next to config.json by older versions
+    pub fn list_backups(&self) -> ConfigResult<Vec<std::path::PathBuf>> {
+        let mut backups = list_backup_files(&self.backup_dir())?;
+        backups.extend(list_backup_files(&self.config_paths.config_dir)?);
+        // Both locations are the same directory when backup-dir points at the config directory
+        backups.sort();
+        backups.dedup();
+        
         // Sort by modification time (newest first)
         backups.sort_by(|a, b| {
             let a_time = fs::metadata(a).and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
@@ -748,7 +2720,10 @@ impl FileConfigManager {
     }
     
     /// Clean up old backup files, keeping only the most recent N backups
+    ///
+    /// Backups that can't be removed are skipped and reported by [`FileConfigManager::take_warnings`].
     pub fn cleanup_backups(&self, keep_count: usize) -> ConfigResult<usize> {
+        let _timer = self.timings.step("cleanup_backups");
         let backups = self.list_backups()?;
         
         if backups.len() <= keep_count {
@@ -760,15 +2735,63 @@ impl FileConfigManager {
         
         for backup_path in to_remove {
             if let Err(e) = fs::remove_file(backup_path) {
-                eprintln!("Warning: Failed to remove backup file {:?}: {}", backup_path, e);
+                self.warn(format!("Failed to remove backup file {}: {}", backup_path.display(), e));
             } else {
                 removed_count += 1;
             }
         }
         
+        // Forget notes of backups that no longer exist
+        let mut index = self.load_backup_index();
+        let backup_dir = self.backup_dir();
+        let before = index.len();
+        index.retain(|name, _| backup_dir.join(name).exists());
+        if index.len() != before {
+            self.save_backup_index(&index)?;
+        }
+        
         Ok(removed_count)
     }
     
+    /// Gzip backups written uncompressed by older versions, keeping their names otherwise
+    ///
+    /// Modification times are kept so the backups stay in the same order.
+    pub fn compress_backups(&self) -> ConfigResult<BackupCompression> {
+        let mut summary = BackupCompression::default();
+        let mut index = self.load_backup_index();
+        
+        for path in self.list_backups()? {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()).map(str::to_string) else { continue };
+            if name.ends_with(crate::types::constants::COMPRESSED_BACKUP_EXTENSION) {
+                continue;
+            }
+            
+            let content = fs::read(&path).map_err(ConfigError::FileError)?;
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+            let compressed_name = format!("{}.gz", name);
+            let target = path.with_file_name(&compressed_name);
+            write_compressed(&target, &content)?;
+            if let Some(modified) = modified {
+                fs::File::options().write(true).open(&target)
+                    .and_then(|file| file.set_modified(modified))
+                    .map_err(ConfigError::FileError)?;
+            }
+            fs::remove_file(&path).map_err(ConfigError::FileError)?;
+            
+            if let Some(info) = index.remove(&name) {
+                index.insert(compressed_name, info);
+            }
+            summary.compressed += 1;
+            summary.original_bytes += content.len() as u64;
+            summary.compressed_bytes += fs::metadata(&target).map_err(ConfigError::FileError)?.len();
+        }
+        
+        if summary.compressed > 0 {
+            self.save_backup_index(&index)?;
+        }
+        Ok(summary)
+    }
+    
     /// Get configuration statistics
     pub fn get_stats(&self) -> ConfigResult<ConfigStats> {
         let store = self.load_store()?;
@@ -776,18 +2799,23 @@ impl FileConfigManager {
         
         let mut total_variables = 0;
         let mut claude_configs = 0;
+        let mut archived_configs = 0;
         
         for config in store.configs.values() {
             total_variables += config.variables.len();
-            if config.is_claude_config() {
+            if config.is_claude_config(&store.settings.claude_variables) {
                 claude_configs += 1;
             }
+            if config.archived {
+                archived_configs += 1;
+            }
         }
         
         Ok(ConfigStats {
             total_configs: store.configs.len(),
             total_variables,
             claude_configs,
+            archived_configs,
             active_config: store.active_config.clone(),
             backup_count: backups.len(),
             last_modified: store.last_modified,
@@ -799,287 +2827,2106 @@ impl FileConfigManager {
         })
     }
     
-    /// Ensure configuration directory exists
-    fn ensure_config_dir(&self) -> ConfigResult<()> {
-        self.config_paths.ensure_config_dir()
+    /// Resolve a configuration name or alias to the canonical configuration name
+    pub fn resolve_config_name(&self, name: &str) -> ConfigResult<Option<String>> {
+        let store = self.load_store()?;
+        Ok(store.resolve_name(name))
     }
     
-    /// Load configuration store from file, creating default if not exists
-    fn load_store(&self) -> ConfigResult<ConfigStore> {
-        if !self.config_paths.config_file.exists() {
-            return Ok(ConfigStore::default());
+    /// Names of the configurations `filter` includes, sorted
+    pub fn list_configs_matching(&self, filter: ArchiveFilter) -> ConfigResult<Vec<String>> {
+        if self.uses_split_layout() {
+            return self.split.list_configs_matching(filter);
         }
-        
-        let content = fs::read_to_string(&self.config_paths.config_file)
-            .map_err(ConfigError::FileError)?;
-        
-        let store: ConfigStore = serde_json::from_str(&content)
-            .map_err(ConfigError::JsonError)?;
-        
-        // Validate the loaded store
-        store.validate()?;
-        
-        Ok(store)
+        let store = self.load_store()?;
+        Ok(store.list_aliases().into_iter()
+            .filter(|name| store.configs.get(name).is_some_and(|config| filter.includes(config)))
+            .collect())
     }
     
-    /// Save configuration store to file
-    fn save_store(&self, store: &ConfigStore) -> ConfigResult<()> {
-        self.ensure_config_dir()?;
-        
-        // Validate before saving
-        store.validate()?;
-        
-        let content = serde_json::to_string_pretty(store)
-            .map_err(ConfigError::JsonError)?;
-        
-        fs::write(&self.config_paths.config_file, content)
-            .map_err(ConfigError::FileError)?;
-        
-        // Set restrictive permissions (Unix only)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&self.config_paths.config_file)?.permissions();
-            perms.set_mode(0o600); // rw-------
-            fs::set_permissions(&self.config_paths.config_file, perms)?;
-        }
-        
-        Ok(())
+    /// List configuration names followed by all aliases, for suggestions
+    pub fn list_names_and_aliases(&self) -> ConfigResult<Vec<String>> {
+        let store = self.load_store()?;
+        let mut names = store.list_aliases();
+        names.extend(store.list_config_aliases().into_iter().map(|(alias, _)| alias));
+        Ok(names)
     }
-}
-
-impl Default for FileConfigManager {
-    fn default() -> Self {
-        Self::new().expect("Failed to create FileConfigManager")
+    
+    /// Replace the prepend/append modes of a configuration's list variables
+    pub fn set_config_list_ops(&self, alias: &str, list_ops: HashMap<String, ListOp>) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.set_list_ops(alias, list_ops)?;
+        self.save_store(&store)
     }
-}
-
-impl ConfigManager for FileConfigManager {
-    fn load_configs(&self) -> ConfigResult<ConfigStore> {
-        self.load_store()
+    
+    /// Replace the per-variable comments of a configuration
+    pub fn set_config_variable_comments(&self, alias: &str, comments: HashMap<String, String>) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.set_variable_comments(alias, comments)?;
+        self.save_store(&store)
     }
     
-    fn save_configs(&self, store: &ConfigStore) -> ConfigResult<()> {
-        self.save_store(store)
+    /// Set or clear the expiration date of a configuration
+    pub fn set_config_expiry(&self, alias: &str, expires_at: Option<DateTime<Utc>>) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.set_expiry(alias, expires_at)?;
+        self.save_store(&store)
     }
     
-    fn create_config(&self, alias: String, variables: HashMap<String, String>, description: Option<String>) -> ConfigResult<()> {
+    /// Replace the notes and links of a configuration
+    pub fn set_config_details(&self, alias: &str, notes: Option<String>, links: Vec<String>) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
         let mut store = self.load_store()?;
-        let config = EnvConfig::new(alias, variables, description)?;
-        store.add_config(config)?;
+        store.set_details(alias, notes, links)?;
         self.save_store(&store)
     }
     
-    fn update_config(&self, alias: String, variables: HashMap<String, String>, description: Option<String>) -> ConfigResult<()> {
+    /// Pin or unpin a configuration
+    pub fn set_config_pinned(&self, alias: &str, pinned: bool) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
         let mut store = self.load_store()?;
-        store.update_config(&alias, variables, description)?;
+        store.set_pinned(alias, pinned)?;
         self.save_store(&store)
     }
     
-    fn delete_config(&self, alias: String) -> ConfigResult<()> {
+    /// Trim whitespace and control characters from every stored value, saving if anything changed
+    pub fn clean_values(&self) -> ConfigResult<Vec<(String, String)>> {
+        let _lock = self.lock_store()?;
         let mut store = self.load_store()?;
-        store.remove_config(&alias)?;
+        let cleaned = store.clean_values();
+        if !cleaned.is_empty() {
+            self.save_store(&store)?;
+        }
+        Ok(cleaned)
+    }
+    
+    /// Archive or unarchive a configuration
+    pub fn set_config_archived(&self, alias: &str, archived: bool) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.set_archived(alias, archived)?;
         self.save_store(&store)
     }
     
-    fn get_config(&self, alias: &str) -> ConfigResult<Option<EnvConfig>> {
-        let store = self.load_store()?;
-        Ok(store.get_config(alias).cloned())
+    /// Record where a configuration came from, e.g. a preset
+    pub fn set_config_source(&self, alias: &str, source: ConfigSource) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.set_source(alias, source)?;
+        self.save_store(&store)
     }
     
-    fn list_configs(&self) -> ConfigResult<Vec<String>> {
-        let store = self.load_store()?;
-        Ok(store.list_aliases())
+    /// Mark or unmark a configuration as protected
+    pub fn set_config_protected(&self, alias: &str, protected: bool) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.set_protected(alias, protected)?;
+        self.save_store(&store)
     }
     
-    fn set_active_config(&self, alias: String) -> ConfigResult<()> {
+    /// Set one variable of a configuration, saving only if its value changed
+    pub fn set_config_variable(&self, alias: &str, key: &str, value: &str) -> ConfigResult<bool> {
+        let _lock = self.lock_store()?;
         let mut store = self.load_store()?;
-        store.set_active(alias)?;
+        let changed = store.set_variable(alias, key, value)?;
+        if changed {
+            self.save_store(&store)?;
+        }
+        Ok(changed)
+    }
+    
+    /// Remove one variable from a configuration, saving only if it was set
+    pub fn unset_config_variable(&self, alias: &str, key: &str) -> ConfigResult<bool> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        let removed = store.unset_variable(alias, key)?;
+        if removed {
+            self.save_store(&store)?;
+        }
+        Ok(removed)
+    }
+    
+    /// Add an alias to a configuration
+    pub fn add_config_alias(&self, config_name: &str, alias: &str) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.add_config_alias(config_name, alias)?;
         self.save_store(&store)
     }
     
-    fn get_active_config(&self) -> ConfigResult<Option<String>> {
-        let store = self.load_store()?;
-        Ok(store.active_config)
+    /// Create a configuration, optionally allowing a name that differs only by case from an existing one
+    pub fn create_config_with_case_check(&self, alias: String, variables: HashMap<String, String>, description: Option<String>, allow_case_collision: bool) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        let config = EnvConfig::new(alias, variables, description)?;
+        if allow_case_collision {
+            store.add_config_allowing_case_collision(config)?;
+        } else {
+            store.add_config(config)?;
+        }
+        self.save_store(&store)
     }
     
-    fn clear_active_config(&self) -> ConfigResult<()> {
+    /// Create or replace a whole configuration in a single store write
+    ///
+    /// A new name is checked like [`Self::create_config_with_case_check`] does.
+    pub fn save_config(&self, config: EnvConfig, allow_case_collision: bool) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
         let mut store = self.load_store()?;
-        store.clear_active();
+        if store.configs.contains_key(&config.alias) {
+            store.insert_config(config);
+            store.last_modified = Utc::now();
+        } else if allow_case_collision {
+            store.add_config_allowing_case_collision(config)?;
+        } else {
+            store.add_config(config)?;
+        }
         self.save_store(&store)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashMap;
-    use tempfile::TempDir;
-
 
-    fn create_test_variables() -> HashMap<String, String> {
-        let mut vars = HashMap::new();
-        vars.insert("ANTHROPIC_BASE_URL".to_string(), "https://api.deepseek.com".to_string());
-        vars.insert("ANTHROPIC_MODEL".to_string(), "deepseek-chat".to_string());
-        vars
+    /// Configuration names and aliases that differ only by case
+    pub fn case_collisions(&self) -> ConfigResult<Vec<Vec<String>>> {
+        Ok(self.load_store()?.case_collisions())
     }
-
-    fn create_test_config_paths() -> ConfigPaths {
-        let temp_dir = TempDir::new().unwrap();
-        let config_dir = temp_dir.path().to_path_buf();
-        let config_file = config_dir.join("config.json");
-        let state_file = config_dir.join("state.json");
+    
+    /// Remove an alias, returning the name of the configuration it pointed to
+    pub fn remove_config_alias(&self, alias: &str) -> ConfigResult<String> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        let owner = store.remove_config_alias(alias)?;
+        self.save_store(&store)?;
+        Ok(owner)
+    }
+    
+    /// Configurations stored under another key than their alias, as (key, alias)
+    ///
+    /// Reads the store without validating it, since such a store fails validation.
+    pub fn alias_mismatches(&self) -> ConfigResult<Vec<(String, String)>> {
+        Ok(self.read_store()?.alias_mismatches())
+    }
+    
+    /// Repair mismatches from [`Self::alias_mismatches`], given as (key, keep the key), returning the names kept
+    pub fn repair_alias_mismatches(&self, repairs: &[(String, bool)]) -> ConfigResult<Vec<String>> {
+        let _lock = self.lock_store()?;
+        let mut store = self.read_store()?;
+        let names = repairs.iter()
+            .map(|(key, keep_key)| store.repair_alias_mismatch(key, *keep_key))
+            .collect::<ConfigResult<Vec<String>>>()?;
+        self.save_store(&store)?;
+        Ok(names)
+    }
+    
+    /// Load the user preferences
+    pub fn get_settings(&self) -> ConfigResult<Settings> {
+        Ok(self.load_store()?.settings)
+    }
+    
+    /// Add a variable name to the Claude watchlist, returning false if it was already watched
+    pub fn watch_claude_variable(&self, name: &str) -> ConfigResult<bool> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        let added = store.watch_claude_variable(name)?;
+        if added {
+            self.save_store(&store)?;
+        }
+        Ok(added)
+    }
+    
+    /// Remove a user-added variable name from the Claude watchlist
+    pub fn unwatch_claude_variable(&self, name: &str) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.unwatch_claude_variable(name)?;
+        self.save_store(&store)
+    }
+    
+    /// The git repository in the state directory that tracks config.json
+    fn history_repo(&self) -> crate::history::Repo {
+        crate::history::Repo {
+            git_dir: self.config_paths.history_dir(),
+            work_tree: self.config_paths.config_dir.clone(),
+        }
+    }
+    
+    /// Recent configuration history as "<hash> <date> <message>" lines
+    pub fn history(&self, limit: usize) -> ConfigResult<Vec<String>> {
+        crate::history::log(&self.history_repo(), limit)
+    }
+    
+    /// How a variable changed across the configuration history, oldest first
+    ///
+    /// Empty when the history is disabled. Versions that can't be read are skipped.
+    pub fn variable_history(&self, alias: &str, key: &str) -> ConfigResult<Vec<crate::diff::ValueChange>> {
+        let repo = &self.history_repo();
+        let file_name = crate::types::constants::CONFIG_FILE_NAME;
+        let versions: Vec<crate::diff::VariableVersion> = crate::history::versions(repo, file_name)?
+            .into_iter()
+            .filter_map(|(commit, timestamp)| {
+                let content = crate::history::show_file(repo, &commit, file_name).ok()?;
+                let store: ConfigStore = serde_json::from_str(&content).ok()?;
+                let value = store.configs.get(alias).and_then(|config| config.variables.get(key)).cloned();
+                Some(crate::diff::VariableVersion { commit, timestamp, value })
+            })
+            .collect();
+        Ok(crate::diff::value_changes(&versions))
+    }
+    
+    /// Restore the store as it was at a commit of the configuration history
+    pub fn revert_to_commit(&self, commit: &str) -> ConfigResult<ConfigStore> {
+        let _lock = self.lock_store()?;
+        let content = crate::history::show_file(&self.history_repo(), commit, crate::types::constants::CONFIG_FILE_NAME)?;
+        let mut store: ConfigStore = serde_json::from_str(&content)
+            .map_err(ConfigError::JsonError)?;
+        store.validate()?;
         
-        // Keep temp_dir alive by leaking it (for test purposes only)
-        std::mem::forget(temp_dir);
+        // Stay tracked even when reverting to a commit from before tracking was enabled
+        store.settings.git_track = self.load_store()?.settings.git_track;
+        store.last_modified = Utc::now();
         
-        ConfigPaths {
-            config_dir,
-            config_file,
-            state_file,
+        self.write_store(&store, Some(format!("revert to {}", commit)))?;
+        Ok(store)
+    }
+    
+    /// Set or clear the configuration applied to new shells, returning its canonical name
+    pub fn set_default_config(&self, name: Option<&str>) -> ConfigResult<Option<String>> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        let resolved = match name {
+            Some(name) => Some(store.resolve_name(name)
+                .ok_or_else(|| ConfigError::not_found(name.to_string()))?),
+            None => None,
+        };
+        store.settings.default_config = resolved.clone();
+        store.last_modified = Utc::now();
+        self.save_store(&store)?;
+        Ok(resolved)
+    }
+    
+    /// Apply a settings document, e.g. from an export; with `dry_run` only report the changes
+    pub fn apply_settings_document(&self, document: &SettingsDocument, dry_run: bool) -> ConfigResult<Vec<SettingChange>> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        let changes = store.apply_settings_document(document)?;
+        if !dry_run && !changes.is_empty() {
+            self.save_store(&store)?;
         }
+        Ok(changes)
     }
-
-    #[test]
-    fn test_env_config_creation() {
-        let variables = create_test_variables();
-        let config = EnvConfig::new(
-            "test".to_string(),
-            variables.clone(),
-            Some("Test configuration".to_string())
-        ).unwrap();
-
-        assert_eq!(config.alias, "test");
-        assert_eq!(config.variables, variables);
-        assert_eq!(config.description, Some("Test configuration".to_string()));
-        assert!(config.created_at <= Utc::now());
-        assert!(config.updated_at <= Utc::now());
+    
+    /// Change a setting by name
+    pub fn set_setting(&self, key: &str, value: &str) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.settings.set(key, value)?;
+        store.last_modified = Utc::now();
+        self.save_store(&store)
     }
-
-    #[test]
-    fn test_env_config_invalid_alias() {
-        let variables = create_test_variables();
-        let result = EnvConfig::new(
-            "invalid-name-with-spaces and-symbols!".to_string(),
-            variables,
-            None
-        );
-        assert!(result.is_err());
+    
+    /// State of the last activation from state.json, None before the first `use`
+    pub fn activation_state(&self) -> Option<ActivationState> {
+        self.load_state()
     }
-
-    #[test]
-    fn test_env_config_invalid_variables() {
-        let mut variables = HashMap::new();
-        variables.insert("123INVALID".to_string(), "value".to_string());
+    
+    /// Record what `use` left in the shell for `drift`, right after the activation
+    pub fn record_applied_values(&self, applied: HashMap<String, Option<String>>) -> ConfigResult<()> {
+        let _lock = self.lock_store()?;
+        let mut state = self.load_state().unwrap_or_default();
+        state.applied = applied;
+        write_activation_state(&self.config_paths.state_file, &state)
+    }
+    
+    /// Ensure configuration directory exists
+    fn ensure_config_dir(&self) -> ConfigResult<()> {
+        self.config_paths.ensure_config_dir()
+    }
+    
+    /// Load configuration store from file, creating default if not exists
+    fn load_store(&self) -> ConfigResult<ConfigStore> {
+        let _timer = self.timings.step("load_store");
+        let mut store = self.read_store()?;
         
-        let result = EnvConfig::new("test".to_string(), variables, None);
-        assert!(result.is_err());
+        // Validate the loaded store
+        {
+            let _timer = self.timings.step("validate");
+            store.validate()?;
+        }
+        
+        // The activation time only matters while a configuration is active
+        if store.active_config.is_some() {
+            if let Some(state) = self.load_state() {
+                store.activated_at = state.activated_at;
+            }
+        }
+        
+        Ok(store)
     }
-
-    #[test]
-    fn test_env_config_update() {
-        let variables = create_test_variables();
-        let mut config = EnvConfig::new("test".to_string(), variables, None).unwrap();
+    
+    /// Read the store as it is on disk, without validating it
+    fn read_store(&self) -> ConfigResult<ConfigStore> {
+        if self.uses_split_layout() {
+            self.split.load_store()
+        } else if !self.config_paths.config_file.exists() {
+            Ok(ConfigStore::default())
+        } else {
+            let content = fs::read_to_string(&self.config_paths.config_file)
+                .map_err(ConfigError::FileError)?;
+            serde_json::from_str::<ConfigStore>(&content).map_err(ConfigError::JsonError)
+        }
+    }
+    
+    /// Load state.json; None when it doesn't exist yet or can't be read
+    fn load_state(&self) -> Option<ActivationState> {
+        read_activation_state(&self.config_paths.state_file)
+    }
+    
+    /// Record the active configuration and its activation time in state.json
+    fn save_activation_state(&self, store: &ConfigStore) -> ConfigResult<()> {
+        update_activation_state(&self.config_paths.state_file, store)
+    }
+    
+    /// Point state.json at the store's active configuration, keeping the rest of the state
+    fn sync_active_in_state(&self, store: &ConfigStore) -> ConfigResult<()> {
+        let mut state = self.load_state().unwrap_or_default();
+        state.active_config = store.active_config.clone();
+        write_activation_state(&self.config_paths.state_file, &state)
+    }
+    
+    /// Lock the store against concurrent modification until the guard is dropped
+    ///
+    /// Operations that read, modify and write the store hold the lock for the
+    /// whole sequence so concurrent commands can't overwrite each other's changes.
+    fn lock_store(&self) -> ConfigResult<crate::lock::StoreLock> {
+        self.ensure_config_dir()?;
+        crate::lock::StoreLock::acquire(&self.config_paths.config_dir)
+    }
+    
+    /// Where config.json is written: the target of a symlink unless `follow-symlinks` is off
+    ///
+    /// Writing through the link keeps setups like a config.json linked into a dotfiles repository.
+    fn config_file_target(&self, settings: &Settings) -> ConfigResult<std::path::PathBuf> {
+        let config_file = &self.config_paths.config_file;
+        if settings.replace_symlinks {
+            return Ok(config_file.clone());
+        }
+        resolve_symlink(config_file)
+    }
+    
+    /// Save configuration store to file
+    fn save_store(&self, store: &ConfigStore) -> ConfigResult<()> {
+        self.write_store(store, None)
+    }
+    
+    /// Save configuration store, committing it when git tracking is enabled
+    ///
+    /// Without a message the commit message is derived from the previous store.
+    fn write_store(&self, store: &ConfigStore, message: Option<String>) -> ConfigResult<()> {
+        let _timer = self.timings.step("save_store");
+        self.ensure_config_dir()?;
         
-        let original_updated_at = config.updated_at;
+        // Every code path inserts through insert_config, so only a bug can get here
+        debug_assert!(store.alias_mismatches().is_empty(), "saving a store with mismatched aliases: {:?}", store.alias_mismatches());
         
-        // Wait a bit to ensure timestamp difference
-        std::thread::sleep(std::time::Duration::from_millis(1));
+        // Validate before saving
+        {
+            let _timer = self.timings.step("validate");
+            store.validate()?;
+        }
         
-        let mut new_variables = HashMap::new();
-        new_variables.insert("NEW_VAR".to_string(), "new_value".to_string());
+        let message = if store.settings.git_track {
+            let previous = self.load_store().unwrap_or_default();
+            Some(message.unwrap_or_else(|| describe_store_change(&previous, store)))
+        } else {
+            None
+        };
         
-        config.update(new_variables.clone(), Some("Updated description".to_string())).unwrap();
+        let _lock = self.lock_store()?;
+        if store.settings.storage_layout.is_single() {
+            let content = serde_json::to_string_pretty(store)
+                .map_err(ConfigError::JsonError)?;
+            
+            // Readers never see a partial store, not even after a crash
+            write_private_file(&self.config_file_target(&store.settings)?, content.as_bytes())?;
+            
+            // Left over from the split layout, or from an interrupted migration
+            self.split.remove()?;
+        } else {
+            // config.json is only removed once the split files are complete
+            self.split.write_store(store)?;
+            if self.config_paths.config_file.exists() {
+                fs::remove_file(&self.config_paths.config_file).map_err(ConfigError::FileError)?;
+            }
+        }
         
-        assert_eq!(config.variables, new_variables);
-        assert_eq!(config.description, Some("Updated description".to_string()));
-        assert!(config.updated_at > original_updated_at);
+        self.save_activation_state(store)?;
+        
+        // History is best effort; the store itself was saved successfully
+        if let Some(message) = message {
+            if let Err(e) = crate::history::commit(&self.history_repo(), crate::types::constants::CONFIG_FILE_NAME, &message) {
+                self.warn(format!("Could not record configuration history: {}", e));
+            }
+        }
+        
+        Ok(())
     }
+}
 
-    #[test]
-    fn test_env_config_summary() {
-        let variables = create_test_variables();
-        let config = EnvConfig::new(
-            "test".to_string(),
-            variables,
-            Some("Test config".to_string())
-        ).unwrap();
+/// Read the activation state file; None when it doesn't exist yet or can't be read
+pub(crate) fn read_activation_state(state_file: &std::path::Path) -> Option<ActivationState> {
+    let content = fs::read_to_string(state_file).ok()?;
+    serde_json::from_str(&content).ok()
+}
 
-        let summary = config.summary();
+/// Record the active configuration and its activation time, keeping the snapshot taken by
+/// `use` for that same activation
+pub(crate) fn update_activation_state(state_file: &std::path::Path, store: &ConfigStore) -> ConfigResult<()> {
+    let mut state = read_activation_state(state_file).unwrap_or_default();
+    if state.activated_at != store.activated_at {
+        // Another activation, or none at all: the snapshot no longer applies
+        state = ActivationState { activated_at: store.activated_at, ..Default::default() };
+    }
+    state.active_config = store.active_config.clone();
+    write_activation_state(state_file, &state)
+}
+
+/// Write the activation state file when it differs from what is on disk
+pub(crate) fn write_activation_state(state_file: &std::path::Path, state: &ActivationState) -> ConfigResult<()> {
+    if read_activation_state(state_file).unwrap_or_default() == *state {
+        return Ok(());
+    }
+    
+    let content = serde_json::to_string_pretty(state)
+        .map_err(ConfigError::JsonError)?;
+    if let Some(state_dir) = state_file.parent() {
+        crate::types::ensure_private_dir(state_dir)?;
+    }
+    write_private_file(state_file, content.as_bytes())
+}
+
+/// The file a symlink at `path` finally points to, or `path` itself when it is no symlink
+///
+/// A link to a file that doesn't exist yet resolves to where that file will be.
+pub(crate) fn resolve_symlink(path: &std::path::Path) -> ConfigResult<std::path::PathBuf> {
+    let mut path = path.to_path_buf();
+    // Guards against link cycles, like the kernel's limit on nested links
+    for _ in 0..40 {
+        if !fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Ok(path);
+        }
+        let target = fs::read_link(&path).map_err(ConfigError::FileError)?;
+        path = match path.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+    }
+    Err(ConfigError::FileError(std::io::Error::other(format!("Too many levels of symbolic links at {}", path.display()))))
+}
+
+/// Write a file readable only by the user so that a crash leaves either the old or the complete new file
+///
+/// The content goes to a temporary file next to `path`, which is synced and then renamed
+/// over it; the directory is synced too so the rename itself survives a power loss.
+pub(crate) fn write_private_file(path: &std::path::Path, content: &[u8]) -> ConfigResult<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_file = path.with_file_name(temp_name);
+    
+    let mut file = fs::File::create(&temp_file).map_err(ConfigError::FileError)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    std::io::Write::write_all(&mut file, content)
+        .and_then(|_| file.sync_all())
+        .map_err(ConfigError::FileError)?;
+    drop(file);
+    
+    fs::rename(&temp_file, path).map_err(ConfigError::FileError)?;
+    
+    // Directories can't be opened for syncing everywhere, and the data itself is safe by now
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        let _ = fs::File::open(dir).and_then(|dir| dir.sync_all());
+    }
+    Ok(())
+}
+
+/// A JSON or YAML export, serialized without building the whole document in memory
+///
+/// Keys come out sorted, as in the canonical form [`crate::utils::add_json_checksum`] hashes,
+/// and only one configuration at a time is converted to a JSON value to sort its keys.
+struct ExportDocument<'a> {
+    /// Everything but the configurations, with an empty `configs` object in their place
+    head: &'a serde_json::Map<String, serde_json::Value>,
+    configs: &'a HashMap<String, EnvConfig>,
+    /// Written last, after everything it covers
+    checksum: Option<&'a str>,
+}
+
+impl Serialize for ExportDocument<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(None)?;
+        for (key, value) in self.head {
+            if key == "configs" {
+                map.serialize_entry(key, &SortedConfigs(self.configs))?;
+            } else {
+                map.serialize_entry(key, value)?;
+            }
+        }
+        if let Some(checksum) = self.checksum {
+            map.serialize_entry(crate::utils::JSON_CHECKSUM_FIELD, checksum)?;
+        }
+        map.end()
+    }
+}
+
+/// Configurations by name, in name order and with their fields sorted
+struct SortedConfigs<'a>(&'a HashMap<String, EnvConfig>);
+
+impl Serialize for SortedConfigs<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeMap};
+        let mut names: Vec<&String> = self.0.keys().collect();
+        names.sort();
+        let mut map = serializer.serialize_map(Some(names.len()))?;
+        for name in names {
+            let config = serde_json::to_value(&self.0[name]).map_err(S::Error::custom)?;
+            map.serialize_entry(name, &config)?;
+        }
+        map.end()
+    }
+}
+
+fn write_json<W: std::io::Write, T: Serialize>(writer: W, value: &T, pretty: bool) -> ConfigResult<()> {
+    if pretty {
+        serde_json::to_writer_pretty(writer, value)
+    } else {
+        serde_json::to_writer(writer, value)
+    }.map_err(ConfigError::JsonError)
+}
+
+/// Why an imported configuration can't be saved, naming the offending variable; None if it is valid
+fn invalid_config_reason(alias: &str, config: &EnvConfig) -> Option<String> {
+    if let Err(e) = crate::error::validate_config_name(alias) {
+        return Some(e.to_string());
+    }
+    if alias != config.alias {
+        return ConfigStore::validate_config(alias, config).err().map(|e| e.to_string());
+    }
+    let mut keys: Vec<&String> = config.variables.keys().collect();
+    keys.sort();
+    keys.into_iter().find_map(|key| {
+        crate::types::validation::validate_env_var(key, &config.variables[key])
+            .err()
+            .map(|e| format!("{}: {}", key, e))
+    })
+}
+
+/// Quote a word for a POSIX shell: single quotes, with embedded single quotes spliced in
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\"'\"'"))
+}
+
+/// Write a shell script of `envswitch set` commands recreating the configurations of `store`
+///
+/// Descriptions, notes, links, expiry dates and list variables become flags of `set`;
+/// aliases, pins and protection follow as their own commands. With `redact_secrets`,
+/// sensitive values are taken from same-named environment variables when the script runs.
+fn write_shell_export(
+    out: &mut dyn std::io::Write,
+    store: &ConfigStore,
+    settings: Option<&SettingsDocument>,
+    redact_secrets: bool,
+) -> std::io::Result<()> {
+    writeln!(out, "#!/bin/sh")?;
+    writeln!(out, "# Recreates {} envswitch configuration(s); run it where envswitch is installed", store.configs.len())?;
+    writeln!(out, "# Exported by envswitch {} on {}", env!("CARGO_PKG_VERSION"), Utc::now().format("%Y-%m-%d %H:%M:%S UTC"))?;
+    if redact_secrets {
+        writeln!(out, "# Secrets were left out: export the variables named below before running it")?;
+    }
+    writeln!(out, "set -e")?;
+    
+    let mut names: Vec<&String> = store.configs.keys().collect();
+    names.sort();
+    for name in names {
+        let config = &store.configs[name];
+        writeln!(out)?;
+        if config.template {
+            writeln!(out, "# '{}' was a template; its {{{{KEY}}}} placeholders are set as plain values", name)?;
+        }
+        
+        let mut command = format!("envswitch set {}", shell_quote(name));
+        let mut keys: Vec<&String> = config.variables.keys().collect();
+        keys.sort();
+        for key in keys {
+            let value = &config.variables[key];
+            let flag = match config.list_ops.get(key) {
+                Some(ListOp::Prepend) => "--prepend",
+                Some(ListOp::Append) => "--append",
+                None => "-e",
+            };
+            if redact_secrets && crate::utils::is_sensitive_key(key) {
+                command.push_str(&format!(" \\\n    {} \"{}=${{{}:?export {} before running this script}}\"", flag, key, key, key));
+            } else {
+                command.push_str(&format!(" \\\n    {} {}", flag, shell_quote(&format!("{}={}", key, value))));
+            }
+        }
+        if let Some(description) = &config.description {
+            command.push_str(&format!(" \\\n    -d {}", shell_quote(description)));
+        }
+        if let Some(notes) = &config.notes {
+            command.push_str(&format!(" \\\n    --note {}", shell_quote(notes)));
+        }
+        for link in &config.links {
+            command.push_str(&format!(" \\\n    --link {}", shell_quote(link)));
+        }
+        if let Some(expires_at) = config.expires_at {
+            command.push_str(&format!(" \\\n    --expires {}", shell_quote(&expires_at.to_rfc3339())));
+        }
+        // Values are stored as they are, so set must not trim or reject any of them
+        if config.variables.values().any(String::is_empty) {
+            command.push_str(" \\\n    --allow-empty-value");
+        }
+        if config.variables.values().any(|value| crate::types::validation::clean_value(value).is_some()) {
+            command.push_str(" \\\n    --keep-whitespace");
+        }
+        if store.configs.keys().any(|other| other != name && other.eq_ignore_ascii_case(name)) {
+            command.push_str(" \\\n    --allow-case-collision");
+        }
+        writeln!(out, "{}", command)?;
+        
+        for alias in &config.aliases {
+            writeln!(out, "envswitch alias add {} {}", shell_quote(name), shell_quote(alias))?;
+        }
+        if config.pinned {
+            writeln!(out, "envswitch pin {}", shell_quote(name))?;
+        }
+        if config.protected {
+            writeln!(out, "envswitch protect {}", shell_quote(name))?;
+        }
+        if config.archived {
+            writeln!(out, "envswitch archive {}", shell_quote(name))?;
+        }
+    }
+    
+    if let Some(settings) = settings.filter(|settings| !settings.is_empty()) {
+        writeln!(out)?;
+        for (key, value) in settings {
+            writeln!(out, "envswitch settings set {} {}", key, shell_quote(value))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write configurations as KEY=VALUE lines, one block per configuration
+fn write_env_export(out: &mut dyn std::io::Write, store: &ConfigStore, include_metadata: bool) -> std::io::Result<()> {
+    if include_metadata {
+        writeln!(out, "# Exported from envswitch on {}", Utc::now().format("%Y-%m-%d %H:%M:%S UTC"))?;
+        writeln!(out, "# Total configurations: {}", store.configs.len())?;
+        if let Some(active) = &store.active_config {
+            writeln!(out, "# Active configuration: {}", active)?;
+        }
+        writeln!(out)?;
+    }
+    
+    for (alias, config) in &store.configs {
+        if include_metadata {
+            writeln!(out, "# Configuration: {}", alias)?;
+            if let Some(desc) = &config.description {
+                writeln!(out, "# Description: {}", escape_env_comment(desc))?;
+            }
+            if config.pinned {
+                writeln!(out, "# Pinned: true")?;
+            }
+            if config.template {
+                writeln!(out, "# Template: true")?;
+            }
+            if config.archived {
+                writeln!(out, "# Archived: true")?;
+            }
+            if let Some(notes) = &config.notes {
+                writeln!(out, "# Notes: {}", escape_env_comment(notes))?;
+            }
+            for link in &config.links {
+                writeln!(out, "# Link: {}", escape_env_comment(link))?;
+            }
+            if let Some(expires_at) = config.expires_at {
+                writeln!(out, "# Expires: {}", expires_at.to_rfc3339())?;
+            }
+            writeln!(out, "# Created: {}", config.created_at.to_rfc3339())?;
+            writeln!(out, "# Updated: {}", config.updated_at.to_rfc3339())?;
+        }
+        
+        for (key, value) in &config.variables {
+            if let Some(comment) = config.variable_comments.get(key) {
+                writeln!(out, "# {}", escape_env_comment(comment))?;
+            }
+            writeln!(out, "{}", crate::utils::format_env_line(key, value))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Read the layout written by [`write_env_export`], or a plain .env file as one configuration named "imported"
+///
+/// Metadata comments apply to the configuration of the last `# Configuration:` header;
+/// configurations without variables are left out. Other comments directly above a variable
+/// become its comment.
+fn parse_env_export(content: &str) -> ConfigStore {
+    let now = Utc::now();
+    let blank = |alias: &str| EnvConfig {
+        alias: alias.to_string(),
+        variables: HashMap::new(),
+        description: None,
+        created_at: now,
+        updated_at: now,
+        aliases: Vec::new(),
+        pinned: false,
+        protected: false,
+        notes: None,
+        links: Vec::new(),
+        source: None,
+        expires_at: None,
+        list_ops: HashMap::new(),
+        template: false,
+        archived: false,
+        variable_comments: HashMap::new(),
+    };
+    
+    let mut store = ConfigStore { last_modified: now, ..ConfigStore::default() };
+    let mut active_config = None;
+    let mut current = blank("imported");
+    let mut pending_comment: Option<String> = None;
+    // Between a `# Configuration:` header and its variables or `# Updated:` line
+    let mut in_header = false;
+    for raw_line in content.lines() {
+        let line = raw_line.trim();
+        if line.is_empty() {
+            pending_comment = None;
+            continue;
+        }
+        
+        if line.starts_with('#') {
+            let text = &raw_line.trim_start()[1..];
+            // Metadata fields only count in a header, so variable comments may contain colons.
+            // Values keep their own whitespace; only the space after the colon belongs to the layout
+            let metadata = text.split_once(':')
+                .map(|(field, value)| (field.trim(), value.strip_prefix(' ').unwrap_or(value)))
+                .filter(|(field, _)| match *field {
+                    "Configuration" => true,
+                    "Active configuration" => store.configs.is_empty() && current.variables.is_empty(),
+                    _ => in_header,
+                });
+            match metadata {
+                Some(("Configuration", value)) => {
+                    in_header = true;
+                    let finished = std::mem::replace(&mut current, blank(value.trim()));
+                    if !finished.variables.is_empty() {
+                        store.insert_config(finished);
+                    }
+                }
+                Some(("Active configuration", value)) => active_config = Some(value.trim().to_string()),
+                Some(("Description", value)) => current.description = Some(unescape_env_comment(value)),
+                Some(("Pinned", value)) => current.pinned = value.trim() == "true",
+                Some(("Template", value)) => current.template = value.trim() == "true",
+                Some(("Archived", value)) => current.archived = value.trim() == "true",
+                Some(("Notes", value)) => current.notes = Some(unescape_env_comment(value)),
+                Some(("Link", value)) => current.links.push(unescape_env_comment(value)),
+                Some(("Expires", value)) => current.expires_at = parse_export_timestamp(value),
+                Some(("Created", value)) => current.created_at = parse_export_timestamp(value).unwrap_or(now),
+                Some(("Updated", value)) => {
+                    in_header = false;
+                    current.updated_at = parse_export_timestamp(value).unwrap_or(now);
+                }
+                _ => {
+                    // Anything else describes the variable below it
+                    let comment = unescape_env_comment(text.strip_prefix(' ').unwrap_or(text));
+                    pending_comment = Some(match pending_comment.take() {
+                        Some(previous) => format!("{}\n{}", previous, comment),
+                        None => comment,
+                    });
+                    continue;
+                }
+            }
+            pending_comment = None;
+            continue;
+        }
+        
+        in_header = false;
+        let comment = pending_comment.take();
+        if let Some((key, value)) = crate::utils::parse_env_line(line) {
+            if let Some(comment) = comment {
+                current.variable_comments.insert(key.clone(), comment);
+            }
+            current.variables.insert(key, value);
+        }
+    }
+    if !current.variables.is_empty() {
+        store.insert_config(current);
+    }
+    
+    store.active_config = active_config.filter(|alias| store.configs.contains_key(alias));
+    store
+}
+
+/// A JSON file of nested variables as a store holding one configuration named "imported",
+/// like a plain .env file without export metadata
+fn read_flattened_json(path: &std::path::Path, format: ImportFormat, flatten: &crate::utils::file_utils::FlattenOptions) -> ConfigResult<ConfigStore> {
+    if !matches!(format, ImportFormat::Json) {
+        return Err(ConfigError::ValidationError("Only JSON files can be flattened".to_string()));
+    }
+    let content = fs::read_to_string(path).map_err(ConfigError::FileError)?;
+    let value: serde_json::Value = serde_json::from_str(&content).map_err(ConfigError::JsonError)?;
+    let variables = crate::utils::file_utils::flatten_json(&value, flatten)
+        .map_err(|e| ConfigError::ValidationError(format!("Cannot flatten {}: {}", path.display(), e)))?;
+    let mut store = ConfigStore::new();
+    store.insert_config(EnvConfig::new("imported".to_string(), variables, None)?);
+    Ok(store)
+}
+
+/// Keep a metadata value on its comment line: backslashes, newlines and carriage returns are escaped
+fn escape_env_comment(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+/// Undo [`escape_env_comment`]; other backslashes are kept as they are
+fn unescape_env_comment(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('\\', Some('\\')) => '\\',
+            ('\\', Some('n')) => '\n',
+            ('\\', Some('r')) => '\r',
+            _ => {
+                unescaped.push(c);
+                continue;
+            }
+        };
+        chars.next();
+        unescaped.push(escaped);
+    }
+    unescaped
+}
+
+/// Timestamp of an ENV export comment: RFC 3339, or `2024-06-01 12:00:00 UTC` as written by older versions
+fn parse_export_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .ok()
+        .or_else(|| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S UTC").ok().map(|naive| naive.and_utc()))
+}
+
+/// Comment opening every YAML export, describing the layout for the tools reading it
+pub const YAML_EXPORT_HEADER: &str = "\
+# envswitch YAML export: the document of a JSON export, which `envswitch import` reads back
+# Layout (optional keys are left out when unset):
+#   configs:                      one entry per configuration name
+#     <name>:
+#       alias: string
+#       variables:                variable name to string value
+#       description: string       or null
+#       created_at, updated_at:   RFC 3339 strings
+#       aliases: [string]         optional
+#       pinned, protected, template, archived: bool  optional
+#       notes: string             optional
+#       links: [string]           optional
+#       expires_at: RFC 3339 string  optional
+#       variable_comments:        variable name to comment, optional
+#   active_config: string         or null
+#   last_modified: RFC 3339 string
+#   version: string
+#   partial_export:               baseline and time of `export --since`, optional
+#   metadata:                     settings of `--include-settings` and the template flag, optional
+#   checksum: string              with `--checksum`, over the rest of the document
+";
+
+/// Write `content` gzip-compressed and atomically, readable only by the user
+fn write_compressed(path: &std::path::Path, content: &[u8]) -> ConfigResult<()> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let compressed = std::io::Write::write_all(&mut encoder, content)
+        .and_then(|_| encoder.finish())
+        .map_err(ConfigError::FileError)?;
+    write_private_file(path, &compressed)
+}
+
+/// Content of a backup file, decompressing gzipped ones
+fn read_backup_file(path: &std::path::Path) -> ConfigResult<String> {
+    let data = fs::read(path).map_err(ConfigError::FileError)?;
+    // Gzip files start with these two bytes, whatever their name
+    if !data.starts_with(&[0x1f, 0x8b]) {
+        return String::from_utf8(data)
+            .map_err(|e| ConfigError::FileError(std::io::Error::new(std::io::ErrorKind::InvalidData, e)));
+    }
+    
+    let mut content = String::new();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(data.as_slice()), &mut content)
+        .map_err(ConfigError::FileError)?;
+    Ok(content)
+}
+
+/// Backup files directly inside `dir`; a missing directory has none
+fn list_backup_files(dir: &std::path::Path) -> ConfigResult<Vec<std::path::PathBuf>> {
+    let mut backups = Vec::new();
+    
+    if !dir.exists() {
+        return Ok(backups);
+    }
+    
+    let entries = fs::read_dir(dir).map_err(ConfigError::FileError)?;
+    for entry in entries {
+        let path = entry.map_err(ConfigError::FileError)?.path();
+        
+        if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
+            if filename.starts_with(crate::types::constants::BACKUP_FILE_PREFIX)
+                && (filename.ends_with(".json") || filename.ends_with(crate::types::constants::COMPRESSED_BACKUP_EXTENSION)) {
+                backups.push(path);
+            }
+        }
+    }
+    
+    Ok(backups)
+}
+
+/// Error message for a name that differs only by case from an existing one
+fn case_collision_message(name: &str, existing: &str) -> String {
+    format!(
+        "'{}' differs only by case from existing '{}', which is easy to confuse and collides on case-insensitive file systems",
+        name, existing
+    )
+}
+
+/// Parse a JSON export or backup, verifying its checksum when it has one
+///
+/// Truncated files and checksum mismatches get their own errors instead of a
+/// generic parse error.
+fn parse_json_export(content: &str) -> ConfigResult<ConfigStore> {
+    parse_export_value(parse_json_document(content)?)
+}
+
+/// A JSON document, telling a truncated file apart from other syntax errors
+fn parse_json_document(content: &str) -> ConfigResult<serde_json::Value> {
+    serde_json::from_str(content).map_err(|e| {
+        if e.is_eof() {
+            ConfigError::ValidationError(format!("File appears to be truncated ({})", e))
+        } else {
+            ConfigError::JsonError(e)
+        }
+    })
+}
+
+/// A backup as the configuration file it was made from, without the checksum added to the backup
+fn without_json_checksum(content: &str) -> ConfigResult<std::borrow::Cow<'_, str>> {
+    let mut value = parse_json_document(content)?;
+    match value.as_object_mut().and_then(|object| object.remove(crate::utils::JSON_CHECKSUM_FIELD)) {
+        Some(_) => serde_json::to_string_pretty(&value).map(std::borrow::Cow::Owned).map_err(ConfigError::JsonError),
+        None => Ok(std::borrow::Cow::Borrowed(content)),
+    }
+}
+
+/// Parse a YAML export, the same document as a JSON export
+fn parse_yaml_export(content: &str) -> ConfigResult<ConfigStore> {
+    let value: serde_json::Value = serde_yaml::from_str(content)
+        .map_err(|e| ConfigError::ValidationError(format!("Invalid YAML export: {}", e)))?;
+    parse_export_value(value)
+}
+
+/// The store of a parsed JSON or YAML export, verifying its checksum when it has one
+fn parse_export_value(mut value: serde_json::Value) -> ConfigResult<ConfigStore> {
+    if let Some(message) = crate::utils::verify_json_checksum(&mut value).error_message() {
+        return Err(ConfigError::ValidationError(message));
+    }
+    
+    serde_json::from_value(value).map_err(|e| {
+        ConfigError::ValidationError(format!("Unsupported export format: {}", e))
+    })
+}
+
+/// Read every file of a configuration archive into memory, rejecting unsafe paths
+///
+/// Only `manifest.json`, `config.json`, `state.json` and `backups/<name>.json`
+/// are accepted; anything else, including absolute paths or `..`, fails the import.
+fn read_archive_files(archive_path: &std::path::Path) -> ConfigResult<HashMap<String, Vec<u8>>> {
+    use std::io::Read;
+    use std::path::Component;
+    
+    let file = fs::File::open(archive_path).map_err(ConfigError::FileError)?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut files = HashMap::new();
+    
+    for entry in archive.entries().map_err(ConfigError::FileError)? {
+        let mut entry = entry.map_err(ConfigError::FileError)?;
+        let path = entry.path().map_err(ConfigError::FileError)?.into_owned();
+        let display = path.display().to_string();
+        
+        let components: Vec<&str> = path.components()
+            .map(|component| match component {
+                Component::Normal(part) => part.to_str().ok_or(()),
+                _ => Err(()),
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|_| ConfigError::ValidationError(
+                format!("Archive entry '{}' escapes the configuration directory", display)
+            ))?;
+        
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+        if !entry.header().entry_type().is_file() {
+            return Err(ConfigError::ValidationError(
+                format!("Archive entry '{}' is not a regular file", display)
+            ));
+        }
+        
+        let name = match components.as_slice() {
+            [name] if *name == ARCHIVE_MANIFEST_NAME
+                || *name == crate::types::constants::CONFIG_FILE_NAME
+                || *name == crate::types::constants::STATE_FILE_NAME => name.to_string(),
+            [dir, name] if *dir == ARCHIVE_BACKUP_DIR
+                && (name.ends_with(".json") || name.ends_with(crate::types::constants::COMPRESSED_BACKUP_EXTENSION)) => format!("{}/{}", dir, name),
+            _ => return Err(ConfigError::ValidationError(
+                format!("Unexpected archive entry '{}'", display)
+            )),
+        };
+        
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(ConfigError::FileError)?;
+        files.insert(name, data);
+    }
+    
+    Ok(files)
+}
+
+impl Default for FileConfigManager {
+    fn default() -> Self {
+        Self::new().expect("Failed to create FileConfigManager")
+    }
+}
+
+impl ConfigManager for FileConfigManager {
+    fn load_configs(&self) -> ConfigResult<ConfigStore> {
+        self.load_store()
+    }
+    
+    fn save_configs(&self, store: &ConfigStore) -> ConfigResult<()> {
+        self.save_store(store)
+    }
+    
+    fn create_config(&self, alias: String, variables: HashMap<String, String>, description: Option<String>) -> ConfigResult<()> {
+        self.create_config_with_case_check(alias, variables, description, false)
+    }
+    
+    fn update_config(&self, alias: String, variables: HashMap<String, String>, description: Option<String>) -> ConfigResult<()> {
+        if self.uses_split_layout() {
+            return self.split.update_config(alias, variables, description);
+        }
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.update_config(&alias, variables, description)?;
+        self.save_store(&store)
+    }
+    
+    fn delete_config(&self, alias: String) -> ConfigResult<()> {
+        if self.uses_split_layout() {
+            return self.split.delete_config(alias);
+        }
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.remove_config(&alias)?;
+        self.save_store(&store)
+    }
+    
+    fn get_config(&self, alias: &str) -> ConfigResult<Option<EnvConfig>> {
+        if self.uses_split_layout() {
+            return self.split.get_config(alias);
+        }
+        let store = self.load_store()?;
+        Ok(store.get_config(alias).cloned())
+    }
+    
+    fn list_configs(&self) -> ConfigResult<Vec<String>> {
+        if self.uses_split_layout() {
+            return self.split.list_configs();
+        }
+        let store = self.load_store()?;
+        Ok(store.list_aliases())
+    }
+    
+    fn set_active_config(&self, alias: String) -> ConfigResult<()> {
+        if self.uses_split_layout() {
+            return self.split.set_active_config(alias);
+        }
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.set_active(alias)?;
+        self.save_store(&store)
+    }
+    
+    fn get_active_config(&self) -> ConfigResult<Option<String>> {
+        if self.uses_split_layout() {
+            return self.split.get_active_config();
+        }
+        let store = self.load_store()?;
+        Ok(store.active_config)
+    }
+    
+    fn clear_active_config(&self) -> ConfigResult<()> {
+        if self.uses_split_layout() {
+            return self.split.clear_active_config();
+        }
+        let _lock = self.lock_store()?;
+        let mut store = self.load_store()?;
+        store.clear_active();
+        self.save_store(&store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+
+    fn create_test_variables() -> HashMap<String, String> {
+        let mut vars = HashMap::new();
+        vars.insert("ANTHROPIC_BASE_URL".to_string(), "https://api.deepseek.com".to_string());
+        vars.insert("ANTHROPIC_MODEL".to_string(), "deepseek-chat".to_string());
+        vars
+    }
+
+    fn create_test_config_paths() -> ConfigPaths {
+        let temp_dir = TempDir::new().unwrap();
+        let config_paths = ConfigPaths::in_dir(temp_dir.path());
+        
+        // Keep temp_dir alive by leaking it (for test purposes only)
+        std::mem::forget(temp_dir);
+        
+        config_paths
+    }
+
+    #[test]
+    fn test_env_config_creation() {
+        let variables = create_test_variables();
+        let config = EnvConfig::new(
+            "test".to_string(),
+            variables.clone(),
+            Some("Test configuration".to_string())
+        ).unwrap();
+
+        assert_eq!(config.alias, "test");
+        assert_eq!(config.variables, variables);
+        assert_eq!(config.description, Some("Test configuration".to_string()));
+        assert!(config.created_at <= Utc::now());
+        assert!(config.updated_at <= Utc::now());
+    }
+
+    #[test]
+    fn test_env_config_invalid_alias() {
+        let variables = create_test_variables();
+        let result = EnvConfig::new(
+            "invalid-name-with-spaces and-symbols!".to_string(),
+            variables,
+            None
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_env_config_invalid_variables() {
+        let mut variables = HashMap::new();
+        variables.insert("123INVALID".to_string(), "value".to_string());
+        
+        let result = EnvConfig::new("test".to_string(), variables, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_env_config_update() {
+        let variables = create_test_variables();
+        let mut config = EnvConfig::new("test".to_string(), variables, None).unwrap();
+        
+        let original_updated_at = config.updated_at;
+        
+        // Wait a bit to ensure timestamp difference
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        
+        let mut new_variables = HashMap::new();
+        new_variables.insert("NEW_VAR".to_string(), "new_value".to_string());
+        
+        config.update(new_variables.clone(), Some("Updated description".to_string())).unwrap();
+        
+        assert_eq!(config.variables, new_variables);
+        assert_eq!(config.description, Some("Updated description".to_string()));
+        assert!(config.updated_at > original_updated_at);
+    }
+
+    #[test]
+    fn test_env_config_summary() {
+        let variables = create_test_variables();
+        let config = EnvConfig::new(
+            "test".to_string(),
+            variables,
+            Some("Test config".to_string())
+        ).unwrap();
+
+        let summary = config.summary();
         assert!(summary.contains("test"));
         assert!(summary.contains("2 variables"));
         assert!(summary.contains("Test config"));
     }
 
     #[test]
-    fn test_env_config_claude_detection() {
+    fn test_env_config_claude_detection() {
+        let variables = create_test_variables();
+        let config = EnvConfig::new("test".to_string(), variables, None).unwrap();
+        
+        assert!(config.is_claude_config(&[]));
+        
+        let claude_vars = config.claude_variables(&[]);
+        assert_eq!(claude_vars.len(), 2);
+        assert!(claude_vars.contains_key("ANTHROPIC_BASE_URL"));
+        assert!(claude_vars.contains_key("ANTHROPIC_MODEL"));
+    }
+
+    #[test]
+    fn test_claude_watchlist_settings() {
+        let mut store = ConfigStore::default();
+        
+        assert!(store.watch_claude_variable("OPENROUTER_API_KEY").unwrap());
+        assert!(!store.watch_claude_variable("OPENROUTER_API_KEY").unwrap());
+        assert!(!store.watch_claude_variable("ANTHROPIC_MODEL").unwrap());
+        assert!(store.watch_claude_variable("BAD-NAME").is_err());
+        assert_eq!(store.settings.claude_variables, vec!["OPENROUTER_API_KEY".to_string()]);
+        
+        let mut variables = HashMap::new();
+        variables.insert("OPENROUTER_API_KEY".to_string(), "sk-or".to_string());
+        let config = EnvConfig::new("openrouter".to_string(), variables, None).unwrap();
+        assert!(!config.is_claude_config(&[]));
+        assert!(config.is_claude_config(&store.settings.claude_variables));
+        
+        assert!(store.unwatch_claude_variable("ANTHROPIC_MODEL").is_err());
+        store.unwatch_claude_variable("OPENROUTER_API_KEY").unwrap();
+        assert!(store.settings.is_empty());
+        assert!(store.unwatch_claude_variable("OPENROUTER_API_KEY").is_err());
+    }
+
+    #[test]
+    fn test_describe_store_change() {
+        let old = ConfigStore::default();
+        let mut new = ConfigStore::default();
+        new.add_config(EnvConfig::new("deepseek".to_string(), create_test_variables(), None).unwrap()).unwrap();
+        assert_eq!(describe_store_change(&old, &new), "set deepseek: +2 vars");
+        
+        let mut changed = new.clone();
+        let mut variables = create_test_variables();
+        variables.insert("ANTHROPIC_MODEL".to_string(), "other".to_string());
+        variables.insert("EXTRA".to_string(), "1".to_string());
+        changed.update_config("deepseek", variables, None).unwrap();
+        changed.set_active("deepseek".to_string()).unwrap();
+        assert_eq!(describe_store_change(&new, &changed), "set deepseek: +1 ~1 vars; use deepseek");
+        
+        assert_eq!(describe_store_change(&new, &old), "delete deepseek");
+        assert_eq!(describe_store_change(&old, &old), "update configuration");
+    }
+
+    #[test]
+    fn test_git_tracked_history_and_revert() {
+        if !crate::history::is_git_available() {
+            return;
+        }
+        
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths);
+        
+        manager.set_setting("git-track", "true").unwrap();
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        manager.delete_config("deepseek".to_string()).unwrap();
+        
+        let history = manager.history(10).unwrap();
+        assert_eq!(history.len(), 3);
+        assert!(history[0].ends_with("delete deepseek"));
+        assert!(history[1].ends_with("set deepseek: +2 vars"));
+        
+        let commit = history[1].split_whitespace().next().unwrap();
+        manager.revert_to_commit(commit).unwrap();
+        assert!(manager.get_config("deepseek").unwrap().is_some());
+        
+        let history = manager.history(10).unwrap();
+        assert!(history[0].ends_with(&format!("revert to {}", commit)));
+        
+        // Created, deleted, then restored by the revert
+        let changes = manager.variable_history("deepseek", "ANTHROPIC_MODEL").unwrap();
+        let values: Vec<(Option<&str>, Option<&str>)> = changes.iter()
+            .map(|change| (change.old.as_deref(), change.new.as_deref()))
+            .collect();
+        let model = Some("deepseek-chat");
+        assert_eq!(values, vec![(None, model), (model, None), (None, model)]);
+        assert!(manager.variable_history("deepseek", "MISSING").unwrap().is_empty());
+        assert!(manager.revert_to_commit("0000000").is_err());
+    }
+
+    #[test]
+    fn test_history_failures_are_returned_as_warnings() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        
+        // No repository can be created where a file is in the way
+        fs::create_dir_all(&config_paths.config_dir).unwrap();
+        fs::write(config_paths.history_dir(), "not a repository").unwrap();
+        manager.set_setting("git-track", "true").unwrap();
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        
+        let warnings = manager.take_warnings();
+        assert!(!warnings.is_empty());
+        assert!(warnings.iter().all(|warning| warning.starts_with("Could not record configuration history")), "{:?}", warnings);
+        assert!(manager.take_warnings().is_empty());
+        assert!(manager.get_config("deepseek").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_check_integrity_fixes_store() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        assert!(manager.check_integrity().unwrap().is_clean());
+        
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        manager.set_active_config("deepseek".to_string()).unwrap();
+        assert!(manager.check_integrity().unwrap().is_clean());
+        
+        // An active pointer to a removed configuration is cleared
+        let mut store = manager.load_store().unwrap();
+        store.configs.clear();
+        fs::write(&config_paths.config_file, serde_json::to_string(&store).unwrap()).unwrap();
+        
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&config_paths.config_file, fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        
+        let report = manager.check_integrity().unwrap();
+        assert_eq!(report.cleared_active.as_deref(), Some("deepseek"));
+        assert_eq!(report.fixed_permissions, cfg!(unix));
+        assert_eq!(manager.get_active_config().unwrap(), None);
+        assert!(manager.check_integrity().unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_default_config_follows_deletion() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths);
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        manager.add_config_alias("deepseek", "ds").unwrap();
+        
+        assert!(manager.set_default_config(Some("missing")).is_err());
+        assert_eq!(manager.set_default_config(Some("ds")).unwrap().as_deref(), Some("deepseek"));
+        assert_eq!(manager.get_settings().unwrap().default_config.as_deref(), Some("deepseek"));
+        
+        manager.delete_config("deepseek".to_string()).unwrap();
+        assert_eq!(manager.get_settings().unwrap().default_config, None);
+        
+        manager.create_config("kimi".to_string(), create_test_variables(), None).unwrap();
+        manager.set_default_config(Some("kimi")).unwrap();
+        assert_eq!(manager.set_default_config(None).unwrap(), None);
+        assert!(manager.get_settings().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recover_corrupt_store_from_backup() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        manager.backup_config().unwrap();
+        
+        fs::write(&config_paths.config_file, "{\"configs\": {").unwrap();
+        let report = manager.check_integrity().unwrap();
+        assert!(report.parse_error.is_some());
+        
+        let backup = manager.newest_valid_backup().unwrap();
+        let stash = manager.recover_from_backup(&backup).unwrap();
+        
+        assert!(stash.file_name().unwrap().to_str().unwrap().starts_with("config.json.corrupt-"));
+        assert_eq!(fs::read_to_string(stash).unwrap(), "{\"configs\": {");
+        assert!(manager.get_config("deepseek").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_backups_live_in_subdirectory() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        
+        let backup = manager.backup_config().unwrap();
+        assert_eq!(backup.parent().unwrap(), config_paths.config_dir.join("backups"));
+        
+        // Backups written by older versions are still found, then migrated
+        let legacy = config_paths.config_dir.join("config_backup_20240101_000000.000.json");
+        fs::copy(&config_paths.config_file, &legacy).unwrap();
+        assert_eq!(manager.list_backups().unwrap().len(), 2);
+        
+        assert_eq!(manager.migrate_legacy_backups().unwrap(), 1);
+        assert_eq!(manager.migrate_legacy_backups().unwrap(), 0);
+        assert!(!legacy.exists());
+        assert!(config_paths.config_dir.join("backups/config_backup_20240101_000000.000.json").exists());
+        assert_eq!(manager.list_backups().unwrap().len(), 2);
+        
+        // Bare names are looked up in the backup directory
+        manager.restore_from_backup(std::path::Path::new("config_backup_20240101_000000.000.json")).unwrap();
+        
+        assert_eq!(manager.cleanup_backups(1).unwrap(), 1);
+        assert_eq!(manager.list_backups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_backup_cleanup_failures_are_returned_as_warnings() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths);
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        manager.backup_config().unwrap();
+        
+        // Named like a backup, but not a file that can be removed
+        let stuck = manager.backup_dir().join("config_backup_20240101_000000.000.json");
+        fs::create_dir(&stuck).unwrap();
+        
+        assert_eq!(manager.cleanup_backups(0).unwrap(), 1);
+        let warnings = manager.take_warnings();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].starts_with(&format!("Failed to remove backup file {}", stuck.display())));
+        assert_eq!(manager.list_backups().unwrap(), vec![stuck]);
+    }
+
+    #[test]
+    fn test_backups_are_compressed() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        
+        let backup = manager.backup_config().unwrap();
+        assert!(backup.to_string_lossy().ends_with(".json.gz"));
+        assert!(fs::read(&backup).unwrap().starts_with(&[0x1f, 0x8b]));
+        assert!(manager.load_backup(&backup).unwrap().configs.contains_key("deepseek"));
+        
+        // Plain backups of older versions are listed, then compressed in place with their note
+        let legacy = config_paths.config_dir.join("backups/config_backup_20240101_000000.000.json");
+        fs::copy(&config_paths.config_file, &legacy).unwrap();
+        let mut index = manager.load_backup_index();
+        index.insert("config_backup_20240101_000000.000.json".to_string(), BackupInfo {
+            note: Some("legacy".to_string()),
+            created_at: Utc::now(),
+            configs: 1,
+            variables: 2,
+        });
+        manager.save_backup_index(&index).unwrap();
+        fs::File::options().write(true).open(&legacy).unwrap()
+            .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)).unwrap();
+        assert_eq!(manager.list_backups().unwrap(), vec![backup.clone(), legacy.clone()]);
+        
+        let summary = manager.compress_backups().unwrap();
+        assert_eq!(summary.compressed, 1);
+        assert_eq!(summary.original_bytes, fs::metadata(&config_paths.config_file).unwrap().len());
+        assert!(summary.saved_bytes() > 0);
+        assert!(!legacy.exists());
+        let compressed = legacy.with_file_name("config_backup_20240101_000000.000.json.gz");
+        assert_eq!(manager.list_backups().unwrap(), vec![backup, compressed.clone()]);
+        assert_eq!(manager.find_backup("legacy").unwrap(), compressed);
+        assert_eq!(manager.compress_backups().unwrap(), BackupCompression::default());
+        
+        manager.delete_config("deepseek".to_string()).unwrap();
+        manager.restore_from_backup(&compressed).unwrap();
+        assert!(manager.get_config("deepseek").unwrap().is_some());
+        assert!(fs::read_to_string(&config_paths.config_file).unwrap().contains("deepseek"));
+    }
+
+    #[test]
+    fn test_verify_backup_checksums() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        
+        let backup = manager.backup_config().unwrap();
+        assert_eq!(manager.verify_backup(&backup).unwrap(), crate::utils::ChecksumStatus::Verified);
+        
+        // Restoring gives back the configuration file, not the checksummed backup
+        manager.restore_from_backup(&backup).unwrap();
+        assert!(!fs::read_to_string(&config_paths.config_file).unwrap().contains(crate::utils::JSON_CHECKSUM_FIELD));
+        
+        // A value changed after the backup was made
+        let tampered = read_backup_file(&backup).unwrap().replace("deepseek-chat", "tampered");
+        write_compressed(&backup, tampered.as_bytes()).unwrap();
+        assert!(matches!(manager.verify_backup(&backup).unwrap(), crate::utils::ChecksumStatus::Mismatch { .. }));
+        
+        // Backups of older versions carry no checksum but still parse
+        let legacy = manager.backup_dir().join("config_backup_20240101_000000.000.json");
+        fs::copy(&config_paths.config_file, &legacy).unwrap();
+        assert_eq!(manager.verify_backup(&legacy).unwrap(), crate::utils::ChecksumStatus::Missing);
+        
+        let content = fs::read_to_string(&legacy).unwrap();
+        fs::write(&legacy, &content[..content.len() / 2]).unwrap();
+        assert!(manager.verify_backup(&legacy).unwrap_err().to_string().contains("truncated"));
+    }
+
+    #[test]
+    fn test_backup_refuses_a_damaged_store() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        let good = manager.backup_config().unwrap();
+        
+        // As if another process had been interrupted halfway through writing
+        let content = fs::read_to_string(&config_paths.config_file).unwrap();
+        fs::write(&config_paths.config_file, &content[..content.len() / 2]).unwrap();
+        
+        let error = manager.backup_config().unwrap_err().to_string();
+        assert!(error.contains("Refusing to back up"), "{}", error);
+        assert_eq!(manager.list_backups().unwrap(), vec![good.clone()]);
+        assert!(fs::read_dir(manager.backup_dir()).unwrap()
+            .all(|entry| !entry.unwrap().file_name().to_string_lossy().ends_with(".tmp")));
+        assert!(manager.load_backup(&good).unwrap().configs.contains_key("deepseek"));
+    }
+    
+    #[test]
+    fn test_backup_notes_and_lookup() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths);
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        
+        let refactor = manager.backup_config_with_note(Some("Before big refactor")).unwrap();
+        let hourly = manager.backup_config().unwrap();
+        let import = manager.backup_config_with_note(Some("before import of team.json")).unwrap();
+        
+        let backups = manager.list_backups_with_info().unwrap();
+        assert_eq!(backups.len(), 3);
+        let info = backups.iter().find(|(path, _)| *path == refactor).unwrap().1.clone().unwrap();
+        assert_eq!(info.note.as_deref(), Some("Before big refactor"));
+        assert_eq!((info.configs, info.variables), (1, 2));
+        assert_eq!(backups.iter().find(|(path, _)| *path == hourly).unwrap().1.as_ref().unwrap().note, None);
+        
+        assert_eq!(manager.find_backup("REFACTOR").unwrap(), refactor);
+        assert_eq!(manager.find_backup(import.to_str().unwrap()).unwrap(), import);
+        assert!(manager.find_backup("before").is_err());
+        assert!(manager.find_backup("nothing like this").is_err());
+        
+        // Cleanup drops the notes of removed backups
+        manager.cleanup_backups(0).unwrap();
+        assert!(manager.load_backup_index().is_empty());
+    }
+
+    #[test]
+    fn test_backup_dir_setting() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        let elsewhere = TempDir::new().unwrap();
+        
+        manager.set_setting("backup-dir", elsewhere.path().to_str().unwrap()).unwrap();
+        let backup = manager.backup_config().unwrap();
+        assert_eq!(backup.parent().unwrap(), elsewhere.path());
+        assert_eq!(manager.list_backups().unwrap(), vec![backup]);
+        
+        manager.set_setting("backup-dir", "").unwrap();
+        assert_eq!(manager.backup_dir(), config_paths.config_dir.join("backups"));
+    }
+
+    #[test]
+    fn test_settings_set() {
+        let mut settings = Settings::default();
+        
+        settings.set("skip-verification", "yes").unwrap();
+        assert!(settings.skip_verification);
+        assert!(!settings.is_empty());
+        
+        settings.set("skip-verification", "false").unwrap();
+        assert!(settings.is_empty());
+        
+        settings.set("lazy-secrets", "on").unwrap();
+        assert!(settings.lazy_secrets);
+        settings.set("lazy-secrets", "off").unwrap();
+        
+        assert!(settings.set("skip-verification", "maybe").is_err());
+        assert!(settings.set("unknown", "true").is_err());
+        
+        settings.set("list-columns", "name, vars,active").unwrap();
+        assert_eq!(settings.list_columns.as_deref(), Some("name, vars,active"));
+        let error = settings.set("list-columns", "name,tags").unwrap_err().to_string();
+        assert!(error.contains("Unknown column 'tags'") && error.contains("expires"));
+        settings.set("list-columns", "").unwrap();
+        assert!(settings.is_empty());
+    }
+
+    #[test]
+    fn test_config_store_operations() {
+        let mut store = ConfigStore::new();
+        assert!(store.is_empty());
+        assert_eq!(store.len(), 0);
+
+        // Add configuration
         let variables = create_test_variables();
         let config = EnvConfig::new("test".to_string(), variables, None).unwrap();
+        store.add_config(config).unwrap();
+
+        assert!(!store.is_empty());
+        assert_eq!(store.len(), 1);
+        assert!(store.get_config("test").is_some());
+
+        // Set active
+        store.set_active("test".to_string()).unwrap();
+        assert_eq!(store.active_config, Some("test".to_string()));
+        assert!(store.get_active_config().is_some());
+
+        // List aliases
+        let aliases = store.list_aliases();
+        assert_eq!(aliases, vec!["test"]);
+
+        // Remove configuration
+        let removed = store.remove_config("test").unwrap();
+        assert_eq!(removed.alias, "test");
+        assert!(store.is_empty());
+        assert!(store.active_config.is_none());
+    }
+
+    #[test]
+    fn test_config_store_duplicate_alias() {
+        let mut store = ConfigStore::new();
+        let variables = create_test_variables();
         
-        assert!(config.is_claude_config());
+        let config1 = EnvConfig::new("test".to_string(), variables.clone(), None).unwrap();
+        let config2 = EnvConfig::new("test".to_string(), variables, None).unwrap();
         
-        let claude_vars = config.claude_variables();
-        assert_eq!(claude_vars.len(), 2);
-        assert!(claude_vars.contains_key("ANTHROPIC_BASE_URL"));
-        assert!(claude_vars.contains_key("ANTHROPIC_MODEL"));
+        store.add_config(config1).unwrap();
+        let result = store.add_config(config2);
+        
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), ConfigError::ConfigExists(_)));
+    }
+
+    #[test]
+    fn test_config_store_nonexistent_config() {
+        let store = ConfigStore::new();
+        
+        assert!(store.get_config("nonexistent").is_none());
+        
+        let mut store = store;
+        let result = store.set_active("nonexistent".to_string());
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), ConfigError::ConfigNotFound { .. }));
+    }
+
+    #[test]
+    fn test_config_store_aliases() {
+        let mut store = ConfigStore::new();
+        let variables = create_test_variables();
+        store.add_config(EnvConfig::new("deepseek".to_string(), variables.clone(), None).unwrap()).unwrap();
+        store.add_config(EnvConfig::new("kimi".to_string(), variables.clone(), None).unwrap()).unwrap();
+
+        store.add_config_alias("deepseek", "ds").unwrap();
+        assert_eq!(store.resolve_name("ds"), Some("deepseek".to_string()));
+        assert_eq!(store.resolve_name("deepseek"), Some("deepseek".to_string()));
+        assert_eq!(store.resolve_name("missing"), None);
+        assert!(store.validate().is_ok());
+
+        // Collisions with config names and existing aliases are rejected
+        assert!(store.add_config_alias("kimi", "deepseek").is_err());
+        assert!(store.add_config_alias("kimi", "ds").is_err());
+        let result = store.add_config(EnvConfig::new("ds".to_string(), variables, None).unwrap());
+        assert!(result.is_err());
+
+        assert_eq!(store.list_config_aliases(), vec![("ds".to_string(), "deepseek".to_string())]);
+
+        let owner = store.remove_config_alias("ds").unwrap();
+        assert_eq!(owner, "deepseek");
+        assert_eq!(store.resolve_name("ds"), None);
+        assert!(store.remove_config_alias("ds").is_err());
+    }
+
+    #[test]
+    fn test_config_store_case_collisions() {
+        let mut store = ConfigStore::new();
+        let variables = create_test_variables();
+        store.add_config(EnvConfig::new("deepseek".to_string(), variables.clone(), None).unwrap()).unwrap();
+        store.add_config_alias("deepseek", "ds").unwrap();
+
+        let err = store.add_config(EnvConfig::new("DeepSeek".to_string(), variables.clone(), None).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("differs only by case from existing 'deepseek'"), "{}", err);
+        assert!(store.add_config(EnvConfig::new("DS".to_string(), variables.clone(), None).unwrap()).is_err());
+        assert!(store.add_config_alias("deepseek", "DeepSeek").is_err());
+        assert!(store.case_collisions().is_empty());
+
+        store.add_config_allowing_case_collision(EnvConfig::new("DeepSeek".to_string(), variables, None).unwrap()).unwrap();
+        assert!(store.case_collision("DEEPSEEK").is_some());
+        assert_eq!(store.case_collision("deepseek"), Some("DeepSeek"));
+        assert_eq!(store.case_collisions(), vec![vec!["DeepSeek".to_string(), "deepseek".to_string()]]);
+        assert_eq!(store.suggest_distinct_name("deepseek"), "deepseek-2");
+    }
+
+    #[test]
+    fn test_config_store_pinned_ordering() {
+        let mut store = ConfigStore::new();
+        for name in ["alpha", "beta", "gamma"] {
+            let config = EnvConfig::new(name.to_string(), create_test_variables(), None).unwrap();
+            store.add_config(config).unwrap();
+        }
+        assert_eq!(store.list_for_display("name", ArchiveFilter::All), vec!["alpha", "beta", "gamma"]);
+
+        store.set_pinned("gamma", true).unwrap();
+        assert_eq!(store.list_for_display("name", ArchiveFilter::All), vec!["gamma", "alpha", "beta"]);
+
+        store.get_config_mut("alpha").unwrap().variables.insert("EXTRA".to_string(), "1".to_string());
+        assert_eq!(store.list_for_display("variables", ArchiveFilter::All), vec!["gamma", "alpha", "beta"]);
+
+        assert!(store.set_pinned("missing", true).is_err());
+
+        // Pinned state survives serialization
+        let json = serde_json::to_string(&store).unwrap();
+        let restored: ConfigStore = serde_json::from_str(&json).unwrap();
+        assert!(restored.get_config("gamma").unwrap().pinned);
+    }
+
+    #[test]
+    fn test_config_store_archived_configs_are_filtered() {
+        let mut store = ConfigStore::new();
+        for name in ["alpha", "beta", "gamma"] {
+            let config = EnvConfig::new(name.to_string(), create_test_variables(), None).unwrap();
+            store.add_config(config).unwrap();
+        }
+        store.set_archived("beta", true).unwrap();
+        assert!(store.set_archived("missing", true).is_err());
+
+        assert_eq!(store.list_for_display("name", ArchiveFilter::default()), vec!["alpha", "gamma"]);
+        assert_eq!(store.list_for_display("name", ArchiveFilter::Archived), vec!["beta"]);
+        assert_eq!(store.list_for_display("name", ArchiveFilter::from_flags(true, true)), vec!["alpha", "beta", "gamma"]);
+
+        // Only archived configurations carry the field
+        let json = serde_json::to_string(&store).unwrap();
+        assert_eq!(json.matches("\"archived\"").count(), 1);
+        let restored: ConfigStore = serde_json::from_str(&json).unwrap();
+        assert!(restored.get_config("beta").unwrap().archived);
+    }
+
+    #[test]
+    fn test_config_store_details() {
+        let mut store = ConfigStore::new();
+        let config = EnvConfig::new("test".to_string(), create_test_variables(), None).unwrap();
+        store.add_config(config).unwrap();
+
+        store.set_details(
+            "test",
+            Some("token expires 2025-03-01".to_string()),
+            vec!["https://console.example.com".to_string()],
+        ).unwrap();
+
+        let config = store.get_config("test").unwrap();
+        assert_eq!(config.notes.as_deref(), Some("token expires 2025-03-01"));
+        assert_eq!(config.links, vec!["https://console.example.com"]);
+
+        // Blank notes are cleared and blank links rejected
+        store.set_details("test", Some("  ".to_string()), Vec::new()).unwrap();
+        assert!(store.get_config("test").unwrap().notes.is_none());
+        assert!(store.set_details("test", None, vec![" ".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_config_source_tracking() {
+        let config = EnvConfig::new("test".to_string(), create_test_variables(), None).unwrap();
+        assert_eq!(config.source, Some(ConfigSource::Manual));
+
+        // Configs saved before provenance tracking deserialize without a source
+        let legacy = r#"{"alias":"old","variables":{},"description":null,
+            "created_at":"2024-01-01T00:00:00Z","updated_at":"2024-01-01T00:00:00Z"}"#;
+        let legacy: EnvConfig = serde_json::from_str(legacy).unwrap();
+        assert!(legacy.source.is_none());
+
+        let source = ConfigSource::import(std::path::Path::new("team.json"), Some(ConfigSource::Manual));
+        assert!(source.describe(&OutputContext::default()).starts_with("import:"));
+        assert!(source.describe(&OutputContext::default()).ends_with("<- manual"));
+
+        let json = serde_json::to_string(&source).unwrap();
+        let restored: ConfigSource = serde_json::from_str(&json).unwrap();
+        assert_eq!(source, restored);
+    }
+
+    #[test]
+    fn test_active_config_staleness() {
+        let mut store = ConfigStore::new();
+        let config = EnvConfig::new("test".to_string(), create_test_variables(), None).unwrap();
+        store.add_config(config).unwrap();
+        assert!(store.activated_at.is_none());
+
+        store.set_active("test".to_string()).unwrap();
+        assert!(store.activated_at.is_some());
+        assert!(!store.is_active_stale());
+
+        let mut variables = create_test_variables();
+        variables.insert("NEW_VAR".to_string(), "value".to_string());
+        store.update_config("test", variables, None).unwrap();
+        assert!(store.is_active_stale());
+
+        store.clear_active();
+        assert!(store.activated_at.is_none());
+        assert!(!store.is_active_stale());
+    }
+
+    #[test]
+    fn test_state_moves_out_of_an_old_layout_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_dir = temp_dir.path().join("config");
+        let old = FileConfigManager::with_paths(ConfigPaths::in_dir(&config_dir));
+        old.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        old.set_active_config("deepseek".to_string()).unwrap();
+        fs::write(config_dir.join(".welcome_shown"), "").unwrap();
+        // Older versions kept the history repository in the configuration directory itself
+        let git = crate::history::is_git_available();
+        if git {
+            let legacy = crate::history::Repo { git_dir: config_dir.join(".git"), work_tree: config_dir.clone() };
+            crate::history::commit(&legacy, "config.json", "set deepseek").unwrap();
+        }
+        let activated_at = old.load_configs().unwrap().activated_at;
+        assert!(activated_at.is_some());
+        
+        let config_paths = ConfigPaths {
+            state_dir: temp_dir.path().join("state"),
+            state_file: temp_dir.path().join("state/state.json"),
+            cache_dir: temp_dir.path().join("cache"),
+            ..ConfigPaths::in_dir(&config_dir)
+        };
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        let moved = manager.migrate_to_state_dir().unwrap();
+        let mut expected = vec!["state.json", ".welcome_shown"];
+        if git {
+            expected.push("history");
+        }
+        assert_eq!(moved, expected);
+        assert!(manager.migrate_to_state_dir().unwrap().is_empty());
+        
+        // Only the configuration is left in the directory users may sync
+        assert!(!config_dir.join("state.json").exists());
+        assert!(!config_dir.join(".welcome_shown").exists());
+        assert!(!config_dir.join(".git").exists());
+        assert!(config_paths.state_dir.join(".welcome_shown").exists());
+        assert_eq!(manager.load_configs().unwrap().activated_at, activated_at);
+        if git {
+            assert_eq!(manager.history(10).unwrap().len(), 1);
+            manager.set_setting("git-track", "true").unwrap();
+            manager.delete_config("deepseek".to_string()).unwrap();
+            let history = manager.history(10).unwrap();
+            assert_eq!(history.len(), 3);
+            assert!(history[0].contains("delete deepseek"));
+            assert!(!config_dir.join(".git").exists());
+        }
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_store_keeps_its_link() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let dotfiles = temp_dir.path().join("dotfiles");
+        fs::create_dir(&dotfiles).unwrap();
+        let target = dotfiles.join("envswitch.json");
+        let config_paths = ConfigPaths::in_dir(&temp_dir.path().join("config"));
+        fs::create_dir(&config_paths.config_dir).unwrap();
+        std::os::unix::fs::symlink("../dotfiles/envswitch.json", &config_paths.config_file).unwrap();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        let is_link = || fs::symlink_metadata(&config_paths.config_file).unwrap().file_type().is_symlink();
+        
+        // The first save creates the target of the dangling link
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        assert!(is_link());
+        assert!(fs::read_to_string(&target).unwrap().contains("deepseek"));
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(fs::read_dir(&dotfiles).unwrap().count() == 1, "no temporary file is left behind");
+        
+        let backup = manager.backup_config().unwrap();
+        manager.delete_config("deepseek".to_string()).unwrap();
+        assert!(is_link());
+        manager.restore_from_backup(&backup).unwrap();
+        assert!(is_link());
+        assert!(manager.get_config("deepseek").unwrap().is_some());
+        
+        // Opting out replaces the link with a regular file and leaves the target alone
+        manager.set_setting("follow-symlinks", "false").unwrap();
+        assert!(!is_link());
+        assert!(!fs::read_to_string(&target).unwrap().contains("replace_symlinks"));
+        assert_eq!(manager.get_settings().unwrap().get("follow-symlinks").as_deref(), Some("false"));
+    }
+    
+    #[test]
+    fn test_activation_time_kept_in_state_file() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        manager.set_active_config("deepseek".to_string()).unwrap();
+        
+        let activated_at = manager.load_configs().unwrap().activated_at.unwrap();
+        assert!(!fs::read_to_string(&config_paths.config_file).unwrap().contains("activated_at"));
+        let state: ActivationState = serde_json::from_str(&fs::read_to_string(&config_paths.state_file).unwrap()).unwrap();
+        assert_eq!(state.activated_at, Some(activated_at));
+        assert_eq!(state.active_config.as_deref(), Some("deepseek"));
+        
+        // Stores written before the state file existed keep their activation time
+        fs::remove_file(&config_paths.state_file).unwrap();
+        let mut legacy: serde_json::Value = serde_json::from_str(&fs::read_to_string(&config_paths.config_file).unwrap()).unwrap();
+        legacy["activated_at"] = serde_json::json!(activated_at);
+        fs::write(&config_paths.config_file, legacy.to_string()).unwrap();
+        assert_eq!(manager.load_configs().unwrap().activated_at, Some(activated_at));
+        
+        manager.clear_active_config().unwrap();
+        assert_eq!(manager.load_configs().unwrap().activated_at, None);
+    }
+
+    #[test]
+    fn test_applied_values_last_until_the_next_activation() {
+        let manager = FileConfigManager::with_paths(create_test_config_paths());
+        manager.create_config("deepseek".to_string(), create_test_variables(), None).unwrap();
+        manager.set_active_config("deepseek".to_string()).unwrap();
+        let applied = HashMap::from([
+            ("ANTHROPIC_MODEL".to_string(), Some("deepseek-chat".to_string())),
+            ("HTTPS_PROXY".to_string(), None),
+        ]);
+        manager.record_applied_values(applied.clone()).unwrap();
+        
+        // Editing the configuration keeps what the shell was given
+        manager.update_config("deepseek".to_string(), HashMap::from([("OTHER".to_string(), "1".to_string())]), None).unwrap();
+        assert_eq!(manager.activation_state().unwrap().applied, applied);
+        
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        manager.set_active_config("deepseek".to_string()).unwrap();
+        assert!(manager.activation_state().unwrap().applied.is_empty());
+        manager.record_applied_values(applied).unwrap();
+        manager.clear_active_config().unwrap();
+        assert_eq!(manager.activation_state().unwrap(), ActivationState::default());
     }
 
     #[test]
-    fn test_config_store_operations() {
+    fn test_insert_config_keys_by_alias() {
         let mut store = ConfigStore::new();
-        assert!(store.is_empty());
-        assert_eq!(store.len(), 0);
+        assert!(store.insert_config(EnvConfig::new("work".to_string(), create_test_variables(), None).unwrap()).is_none());
+        let replaced = store.insert_config(EnvConfig::new("work".to_string(), HashMap::new(), None).unwrap());
+        assert_eq!(replaced.unwrap().variables, create_test_variables());
+        assert!(store.alias_mismatches().is_empty());
 
-        // Add configuration
-        let variables = create_test_variables();
-        let config = EnvConfig::new("test".to_string(), variables, None).unwrap();
-        store.add_config(config).unwrap();
+        // A store read from disk keeps whatever key the file used
+        let json = serde_json::to_string(&store).unwrap().replace("{\"work\":", "{\"job\":");
+        let store: ConfigStore = serde_json::from_str(&json).unwrap();
+        assert_eq!(store.alias_mismatches(), vec![("job".to_string(), "work".to_string())]);
+        assert!(store.validate().is_err());
+    }
 
-        assert!(!store.is_empty());
-        assert_eq!(store.len(), 1);
-        assert!(store.get_config("test").is_some());
+    #[test]
+    fn test_hand_edited_alias_mismatch_is_repairable() {
+        let paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(paths.clone());
+        for name in ["deepseek", "kimi"] {
+            manager.create_config(name.to_string(), create_test_variables(), None).unwrap();
+        }
+        manager.set_active_config("deepseek".to_string()).unwrap();
+        let content = fs::read_to_string(&paths.config_file).unwrap();
+        let corrupted = content
+            .replace("\"alias\": \"deepseek\"", "\"alias\": \"ds\"")
+            .replace("\"alias\": \"kimi\"", "\"alias\": \"moonshot\"");
+        fs::write(&paths.config_file, corrupted).unwrap();
 
-        // Set active
-        store.set_active("test".to_string()).unwrap();
-        assert_eq!(store.active_config, Some("test".to_string()));
-        assert!(store.get_active_config().is_some());
+        assert!(manager.load_configs().is_err());
+        let mismatches = manager.alias_mismatches().unwrap();
+        assert_eq!(mismatches, vec![
+            ("deepseek".to_string(), "ds".to_string()),
+            ("kimi".to_string(), "moonshot".to_string()),
+        ]);
 
-        // List aliases
-        let aliases = store.list_aliases();
-        assert_eq!(aliases, vec!["test"]);
+        let repairs = [("deepseek".to_string(), false), ("kimi".to_string(), true)];
+        assert_eq!(manager.repair_alias_mismatches(&repairs).unwrap(), vec!["ds", "kimi"]);
+        let store = manager.load_configs().unwrap();
+        assert_eq!(store.list_aliases(), vec!["ds", "kimi"]);
+        assert_eq!(store.active_config.as_deref(), Some("ds"));
+        assert!(manager.alias_mismatches().unwrap().is_empty());
 
-        // Remove configuration
-        let removed = store.remove_config("test").unwrap();
-        assert_eq!(removed.alias, "test");
-        assert!(store.is_empty());
-        assert!(store.active_config.is_none());
+        // Moving onto a name that is taken leaves the store as it was
+        let mut store = store;
+        store.configs.get_mut("kimi").unwrap().alias = "ds".to_string();
+        assert!(store.repair_alias_mismatch("kimi", false).is_err());
+        assert_eq!(store.alias_mismatches(), vec![("kimi".to_string(), "ds".to_string())]);
     }
 
     #[test]
-    fn test_config_store_duplicate_alias() {
+    fn test_configs_defining_value() {
         let mut store = ConfigStore::new();
-        let variables = create_test_variables();
-        
-        let config1 = EnvConfig::new("test".to_string(), variables.clone(), None).unwrap();
-        let config2 = EnvConfig::new("test".to_string(), variables, None).unwrap();
-        
-        store.add_config(config1).unwrap();
-        let result = store.add_config(config2);
+        for (name, model) in [("deepseek", "shared-model"), ("kimi", "shared-model"), ("other", "other-model")] {
+            let variables = [("ANTHROPIC_MODEL".to_string(), model.to_string())].into_iter().collect();
+            store.add_config(EnvConfig::new(name.to_string(), variables, None).unwrap()).unwrap();
+        }
         
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), ConfigError::ConfigExists(_)));
+        assert_eq!(store.configs_defining("ANTHROPIC_MODEL", "shared-model"), vec!["deepseek".to_string(), "kimi".to_string()]);
+        assert!(store.configs_defining("ANTHROPIC_MODEL", "missing").is_empty());
+        assert!(store.configs_defining("OTHER_KEY", "shared-model").is_empty());
     }
 
     #[test]
-    fn test_config_store_nonexistent_config() {
-        let store = ConfigStore::new();
+    fn test_set_and_unset_single_variable() {
+        let mut store = ConfigStore::new();
+        store.add_config(EnvConfig::new("test".to_string(), create_test_variables(), None).unwrap()).unwrap();
+        let updated_at = store.get_config("test").unwrap().updated_at;
         
-        assert!(store.get_config("nonexistent").is_none());
+        assert!(!store.set_variable("test", "ANTHROPIC_MODEL", "deepseek-chat").unwrap());
+        assert!(!store.unset_variable("test", "MISSING").unwrap());
+        assert_eq!(store.get_config("test").unwrap().updated_at, updated_at);
         
-        let mut store = store;
-        let result = store.set_active("nonexistent".to_string());
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), ConfigError::ConfigNotFound(_)));
+        assert!(store.set_variable("test", "ANTHROPIC_MODEL", "deepseek-coder").unwrap());
+        assert!(store.get_config("test").unwrap().updated_at > updated_at);
+        assert!(store.set_variable("test", "1INVALID", "value").is_err());
+        assert!(store.set_variable("missing", "KEY", "value").is_err());
+        
+        assert!(store.unset_variable("test", "ANTHROPIC_MODEL").unwrap());
+        assert!(!store.get_config("test").unwrap().variables.contains_key("ANTHROPIC_MODEL"));
+    }
+
+    #[test]
+    fn test_config_list_ops() {
+        let mut store = ConfigStore::new();
+        let mut variables = create_test_variables();
+        variables.insert("PATH".to_string(), "/opt/tool/bin".to_string());
+        let config = EnvConfig::new("tools".to_string(), variables.clone(), None).unwrap();
+        store.add_config(config).unwrap();
+
+        let mut list_ops = HashMap::new();
+        list_ops.insert("PATH".to_string(), ListOp::Prepend);
+        store.set_list_ops("tools", list_ops.clone()).unwrap();
+        assert_eq!(store.get_config("tools").unwrap().list_ops, list_ops);
+
+        // Unknown variables are rejected
+        let mut unknown = HashMap::new();
+        unknown.insert("MISSING".to_string(), ListOp::Append);
+        assert!(store.set_list_ops("tools", unknown).is_err());
+
+        // Removing the variable drops its list mode
+        variables.remove("PATH");
+        store.update_config("tools", variables, None).unwrap();
+        assert!(store.get_config("tools").unwrap().list_ops.is_empty());
+    }
+
+    #[test]
+    fn test_config_expiry() {
+        let mut store = ConfigStore::new();
+        for name in ["fresh", "stale"] {
+            let config = EnvConfig::new(name.to_string(), create_test_variables(), None).unwrap();
+            store.add_config(config).unwrap();
+        }
+        assert!(store.expired_configs().is_empty());
+
+        store.set_expiry("fresh", Some(Utc::now() + chrono::Duration::days(1))).unwrap();
+        store.set_expiry("stale", Some(Utc::now() - chrono::Duration::days(1))).unwrap();
+
+        assert!(!store.get_config("fresh").unwrap().is_expired());
+        assert!(store.get_config("stale").unwrap().is_expired());
+        assert_eq!(store.expired_configs(), vec!["stale"]);
+
+        store.set_expiry("stale", None).unwrap();
+        assert!(store.expired_configs().is_empty());
     }
 
     #[test]
@@ -1111,8 +4958,10 @@ mod tests {
         assert!(json.contains("test"));
         assert!(json.contains("ANTHROPIC_BASE_URL"));
 
-        // Deserialize from JSON
+        // Deserialize from JSON; the activation time lives in the state file
         let deserialized: ConfigStore = serde_json::from_str(&json).unwrap();
+        assert!(!json.contains("activated_at"));
+        store.activated_at = None;
         assert_eq!(store, deserialized);
     }
 
@@ -1275,6 +5124,859 @@ mod tests {
         assert_eq!(config2.description, Some("Config 2".to_string()));
     }
 
+    #[test]
+    fn test_env_export_round_trips_metadata() {
+        let timestamp = |rfc3339: &str| DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc);
+        let descriptions = [
+            "Prod: EU # primary region",
+            "line one\nline two\r\nline three",
+            "  padded  ",
+            "C:\\new\\dir and a literal \\n",
+            "ends with a backslash\\",
+            "# Configuration: not a header",
+        ];
+        let mut store = ConfigStore::new();
+        for (i, description) in descriptions.iter().enumerate() {
+            let variables = [(format!("VAR_{}", i), format!("value {}", i))].into_iter().collect();
+            let mut config = EnvConfig::new(format!("config-{}", i), variables, Some(description.to_string())).unwrap();
+            config.created_at = timestamp("2024-06-01T12:00:00.123456789Z");
+            config.updated_at = timestamp("2024-06-02T03:14:15Z");
+            config.notes = Some(format!("notes for\n{}", description));
+            config.pinned = i == 1;
+            store.add_config(config).unwrap();
+        }
+        store.active_config = Some("config-2".to_string());
+        
+        let mut exported = Vec::new();
+        write_env_export(&mut exported, &store, true).unwrap();
+        let parsed = parse_env_export(&String::from_utf8(exported).unwrap());
+        
+        assert_eq!(parsed.active_config.as_deref(), Some("config-2"));
+        assert_eq!(parsed.configs.len(), store.configs.len());
+        for (alias, expected) in &store.configs {
+            let actual = &parsed.configs[alias];
+            assert_eq!(actual.description, expected.description, "{}", alias);
+            assert_eq!(actual.notes, expected.notes, "{}", alias);
+            assert_eq!(actual.variables, expected.variables, "{}", alias);
+            assert_eq!(actual.created_at, expected.created_at, "{}", alias);
+            assert_eq!(actual.updated_at, expected.updated_at, "{}", alias);
+            assert_eq!(actual.pinned, expected.pinned, "{}", alias);
+        }
+    }
+    
+    #[test]
+    fn test_env_export_reads_older_metadata() {
+        let parsed = parse_env_export(concat!(
+            "# Active configuration: work\n",
+            "# Configuration: work\n",
+            "# Description: Work: main account\n",
+            "# Created: 2024-06-01 12:00:00 UTC\n",
+            "API_URL=https://example.com\n",
+        ));
+        let config = &parsed.configs["work"];
+        assert_eq!(config.description.as_deref(), Some("Work: main account"));
+        assert_eq!(config.created_at, DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z").unwrap());
+        assert_eq!(parsed.active_config.as_deref(), Some("work"));
+        
+        // Variables before any header form one configuration
+        let plain = parse_env_export("# just a comment: nothing more\nA=1\n");
+        assert_eq!(plain.configs["imported"].variables["A"], "1");
+        assert_eq!(plain.active_config, None);
+    }
+    
+    #[test]
+    fn test_env_export_round_trips_variable_comments() {
+        let variables = [("API_URL", "https://v1.example.com"), ("MODEL", "large"), ("TIMEOUT", "30")]
+            .into_iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        let mut config = EnvConfig::new("work".to_string(), variables, None).unwrap();
+        config.variable_comments.insert("API_URL".to_string(), "pinned until v2: see ticket #12".to_string());
+        config.variable_comments.insert("MODEL".to_string(), "Notes: cheaper\nbut slower".to_string());
+        let mut store = ConfigStore::new();
+        store.add_config(config.clone()).unwrap();
+        
+        // The first variable's comment comes right after the header
+        let mut solo = EnvConfig::new("solo".to_string(), [("A".to_string(), "1".to_string())].into_iter().collect(), None).unwrap();
+        solo.variable_comments.insert("A".to_string(), "Description: not metadata".to_string());
+        let mut solo_store = ConfigStore::new();
+        solo_store.add_config(solo.clone()).unwrap();
+        let mut exported = Vec::new();
+        write_env_export(&mut exported, &solo_store, true).unwrap();
+        let parsed = parse_env_export(&String::from_utf8(exported).unwrap());
+        assert_eq!(parsed.configs["solo"].variable_comments, solo.variable_comments);
+        assert_eq!(parsed.configs["solo"].description, None);
+        
+        for include_metadata in [true, false] {
+            let mut exported = Vec::new();
+            write_env_export(&mut exported, &store, include_metadata).unwrap();
+            let exported = String::from_utf8(exported).unwrap();
+            assert!(exported.contains("# pinned until v2: see ticket #12\nAPI_URL="), "{}", exported);
+            
+            let parsed = parse_env_export(&exported);
+            let alias = if include_metadata { "work" } else { "imported" };
+            assert_eq!(parsed.configs[alias].variable_comments, config.variable_comments, "{}", exported);
+            assert_eq!(parsed.configs[alias].notes, None);
+        }
+        
+        // Comments belong to the variable right below them
+        let parsed = parse_env_export("# section\n\nA=1\n# why B\nB=2\n");
+        let comments = &parsed.configs["imported"].variable_comments;
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments["B"], "why B");
+    }
+    
+    #[test]
+    fn test_key_map_file_and_collisions() {
+        let pairs = parse_key_map_file(r#"{"DEEPSEEK_API_KEY": "ANTHROPIC_AUTH_TOKEN", "DEEPSEEK_URL": "ANTHROPIC_BASE_URL"}"#).unwrap();
+        let map = build_key_map(pairs).unwrap();
+        assert_eq!(map["DEEPSEEK_API_KEY"], "ANTHROPIC_AUTH_TOKEN");
+        assert_eq!(map.len(), 2);
+        
+        assert!(parse_key_map_file(r#"{"A": 1}"#).unwrap_err().to_string().contains("must be a variable name"));
+        assert!(parse_key_map_file("[\"A\"]").is_err());
+        assert!(build_key_map(vec![("A".to_string(), "1B".to_string())]).is_err());
+        
+        // Two old names may not end up as the same new one; repeating a rename is fine
+        let pair = |old: &str, new: &str| (old.to_string(), new.to_string());
+        let err = build_key_map(vec![pair("A", "C"), pair("B", "C")]).unwrap_err();
+        assert!(err.to_string().contains("both A and B map to C"), "{}", err);
+        assert!(build_key_map(vec![pair("A", "C"), pair("A", "C")]).is_ok());
+    }
+    
+    #[test]
+    fn test_import_renames_mapped_keys() {
+        let config_paths = create_test_config_paths();
+        let import_path = config_paths.config_dir.join("vendor.env");
+        let manager = FileConfigManager::with_paths(config_paths);
+        fs::create_dir_all(import_path.parent().unwrap()).unwrap();
+        fs::write(&import_path, "# Configuration: vendor\nDEEPSEEK_API_KEY=sk-1\nDEEPSEEK_MODEL=chat\nOTHER=1\n").unwrap();
+        
+        let pair = |old: &str, new: &str| (old.to_string(), new.to_string());
+        let mut options = ImportOptions {
+            format: ImportFormat::Env,
+            force_overwrite: false,
+            merge_existing: false,
+            replace_store: false,
+            skip_validation: false,
+            dry_run: true,
+            keep_whitespace: false,
+            strict: false,
+            key_map: build_key_map(vec![pair("DEEPSEEK_API_KEY", "ANTHROPIC_AUTH_TOKEN"), pair("MISSING", "UNUSED")]).unwrap(),
+            exclude_keys: Vec::new(),
+            rewrite_home: false,
+            configs: None,
+            flatten: None,
+        };
+        let preview = manager.import_from_file_with_options(&import_path, &options).unwrap();
+        assert_eq!(preview.renamed, vec![("vendor".to_string(), "DEEPSEEK_API_KEY".to_string(), "ANTHROPIC_AUTH_TOKEN".to_string())]);
+        
+        options.dry_run = false;
+        manager.import_from_file_with_options(&import_path, &options).unwrap();
+        let variables = manager.get_config("vendor").unwrap().unwrap().variables;
+        let mut keys: Vec<&String> = variables.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["ANTHROPIC_AUTH_TOKEN", "DEEPSEEK_MODEL", "OTHER"]);
+        assert_eq!(variables["ANTHROPIC_AUTH_TOKEN"], "sk-1");
+        
+        // A renamed variable may not replace one the configuration keeps
+        options.key_map = build_key_map(vec![pair("DEEPSEEK_MODEL", "OTHER")]).unwrap();
+        options.force_overwrite = true;
+        let err = manager.import_from_file_with_options(&import_path, &options).unwrap_err();
+        assert!(err.to_string().contains("DEEPSEEK_MODEL would be renamed to OTHER"), "{}", err);
+        
+        // ... unless that one is renamed as well
+        options.key_map = build_key_map(vec![pair("DEEPSEEK_MODEL", "OTHER"), pair("OTHER", "DEEPSEEK_MODEL")]).unwrap();
+        manager.import_from_file_with_options(&import_path, &options).unwrap();
+        let variables = manager.get_config("vendor").unwrap().unwrap().variables;
+        assert_eq!((variables["OTHER"].as_str(), variables["DEEPSEEK_MODEL"].as_str()), ("chat", "1"));
+    }
+    
+    #[test]
+    fn test_exclude_keys_on_export_and_import() {
+        let config_paths = create_test_config_paths();
+        let export_path = config_paths.config_dir.join("export.env");
+        let manager = FileConfigManager::with_paths(config_paths);
+        let variables: HashMap<String, String> = [("API_URL", "https://api.example.com"), ("AWS_KEY", "a"), ("AWS_SECRET", "b"), ("DB_TOKEN", "c")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        manager.create_config("work".to_string(), variables, None).unwrap();
+        
+        let export_options = ExportOptions {
+            format: ExportFormat::Env,
+            include_metadata: true,
+            pretty_print: false,
+            configs: None,
+            resolve_files: false,
+            checksum: false,
+            baseline: None,
+            include_settings: false,
+            exclude_keys: vec!["AWS_*".to_string()],
+            template: false,
+        };
+        let exported = manager.export_to_file_with_options(&export_path, &export_options).unwrap();
+        assert_eq!(exported.excluded, vec!["work:AWS_KEY".to_string(), "work:AWS_SECRET".to_string()]);
+        let content = fs::read_to_string(&export_path).unwrap();
+        assert!(content.contains("API_URL=") && content.contains("DB_TOKEN="));
+        assert!(!content.contains("AWS_"));
+        
+        manager.delete_config("work".to_string()).unwrap();
+        let options = ImportOptions {
+            format: ImportFormat::Env,
+            force_overwrite: false,
+            merge_existing: false,
+            replace_store: false,
+            skip_validation: false,
+            dry_run: false,
+            keep_whitespace: false,
+            strict: false,
+            key_map: HashMap::new(),
+            exclude_keys: vec!["*_TOKEN".to_string()],
+            rewrite_home: false,
+            configs: None,
+            flatten: None,
+        };
+        let result = manager.import_from_file_with_options(&export_path, &options).unwrap();
+        assert_eq!(result.excluded, vec!["work:DB_TOKEN".to_string()]);
+        let keys: Vec<String> = manager.get_config("work").unwrap().unwrap().variables.into_keys().collect();
+        assert_eq!(keys, vec!["API_URL".to_string()]);
+    }
+    
+    #[test]
+    fn test_template_export_keeps_secrets_out_and_merges_without_them() {
+        let config_paths = create_test_config_paths();
+        let export_path = config_paths.config_dir.join("template.json");
+        let manager = FileConfigManager::with_paths(config_paths);
+        let variables: HashMap<String, String> = [("API_URL", "https://api.example.com"), ("API_KEY", "sk-real-secret")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        manager.create_config("team".to_string(), variables, None).unwrap();
+        
+        let export_options = ExportOptions {
+            format: ExportFormat::Json,
+            include_metadata: true,
+            pretty_print: false,
+            configs: None,
+            resolve_files: false,
+            checksum: true,
+            baseline: None,
+            include_settings: false,
+            exclude_keys: Vec::new(),
+            template: true,
+        };
+        let exported = manager.export_to_file_with_options(&export_path, &export_options).unwrap();
+        assert_eq!(exported.placeholders, vec!["team:API_KEY".to_string()]);
+        let content = fs::read_to_string(&export_path).unwrap();
+        assert!(!content.contains("sk-real-secret"));
+        assert!(content.contains("{{API_KEY}}") && content.contains("https://api.example.com"));
+        
+        // Merging into the real configuration keeps its secret
+        let mut options = ImportOptions {
+            format: ImportFormat::Json,
+            force_overwrite: false,
+            merge_existing: true,
+            replace_store: false,
+            skip_validation: false,
+            dry_run: false,
+            keep_whitespace: false,
+            strict: false,
+            key_map: HashMap::new(),
+            exclude_keys: Vec::new(),
+            rewrite_home: false,
+            configs: None,
+            flatten: None,
+        };
+        let result = manager.import_from_file_with_options(&export_path, &options).unwrap();
+        assert!(result.templates.is_empty());
+        let config = manager.get_config("team").unwrap().unwrap();
+        assert_eq!(config.variables["API_KEY"], "sk-real-secret");
+        assert!(!config.template);
+        
+        manager.delete_config("team".to_string()).unwrap();
+        options.merge_existing = false;
+        let result = manager.import_from_file_with_options(&export_path, &options).unwrap();
+        assert_eq!(result.templates, vec!["team".to_string()]);
+        let config = manager.get_config("team").unwrap().unwrap();
+        assert!(config.template);
+        assert_eq!(config.placeholder_keys(), vec!["API_KEY".to_string()]);
+    }
+    
+    #[test]
+    fn test_import_flags_and_rewrites_foreign_home() {
+        let config_paths = create_test_config_paths();
+        let import_path = config_paths.config_dir.join("teammate.env");
+        let manager = FileConfigManager::with_paths(config_paths);
+        fs::create_dir_all(import_path.parent().unwrap()).unwrap();
+        fs::write(&import_path, "# Configuration: shared\nMODEL_DIR=/Users/envswitch-teammate/models\nPROXY=http://10.1.2.3:8080\n").unwrap();
+        
+        let mut options = ImportOptions {
+            format: ImportFormat::Env,
+            force_overwrite: true,
+            merge_existing: false,
+            replace_store: false,
+            skip_validation: false,
+            dry_run: false,
+            keep_whitespace: false,
+            strict: false,
+            key_map: HashMap::new(),
+            exclude_keys: Vec::new(),
+            rewrite_home: false,
+            configs: None,
+            flatten: None,
+        };
+        let result = manager.import_from_file_with_options(&import_path, &options).unwrap();
+        assert_eq!(result.host_warnings.len(), 2, "{:?}", result.host_warnings);
+        assert!(result.host_warnings[0].starts_with("shared:MODEL_DIR points into another user's home directory"));
+        assert!(result.host_warnings[1].starts_with("shared:PROXY refers to 10.1.2.3"));
+        assert!(result.rewritten_home.is_empty());
+        
+        // Only the home directory is rewritten; the private address stays a warning
+        options.rewrite_home = true;
+        let result = manager.import_from_file_with_options(&import_path, &options).unwrap();
+        assert_eq!(result.rewritten_home, vec!["shared:MODEL_DIR".to_string()]);
+        assert_eq!(result.host_warnings.len(), 1);
+        let home = dirs::home_dir().unwrap();
+        let variables = manager.get_config("shared").unwrap().unwrap().variables;
+        assert_eq!(variables["MODEL_DIR"], format!("{}/models", home.to_string_lossy().trim_end_matches('/')));
+    }
+    
+    #[test]
+    fn test_import_skips_invalid_configs() {
+        let config_paths = create_test_config_paths();
+        let import_path = config_paths.config_dir.join("mixed.json");
+        let manager = FileConfigManager::with_paths(config_paths);
+        let config = |alias: &str, variables: serde_json::Value| serde_json::json!({
+            "alias": alias,
+            "variables": variables,
+            "description": null,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        });
+        let mixed = serde_json::json!({
+            "configs": {
+                "good": config("good", serde_json::json!({"API_URL": "https://api.example.com"})),
+                "bad-key": config("bad-key", serde_json::json!({"OK": "1", "1BAD": "2"})),
+                "bad name": config("bad name", serde_json::json!({"OK": "1"})),
+            },
+            "active_config": "bad-key",
+        });
+        fs::create_dir_all(import_path.parent().unwrap()).unwrap();
+        fs::write(&import_path, mixed.to_string()).unwrap();
+        
+        let mut options = ImportOptions {
+            format: ImportFormat::Json,
+            force_overwrite: false,
+            merge_existing: false,
+            replace_store: false,
+            skip_validation: false,
+            dry_run: true,
+            keep_whitespace: false,
+            strict: false,
+            key_map: HashMap::new(),
+            exclude_keys: Vec::new(),
+            rewrite_home: false,
+            configs: None,
+            flatten: None,
+        };
+        let expected_errors = |result: &ImportResult| {
+            assert_eq!(result.errors.len(), 2);
+            assert!(result.errors[0].starts_with("bad name: "));
+            assert!(result.errors[1].starts_with("bad-key: 1BAD: "), "{}", result.errors[1]);
+        };
+        
+        // The dry run gives the same verdicts as the import
+        let preview = manager.import_from_file_with_options(&import_path, &options).unwrap();
+        assert_eq!(preview.imported, vec!["good".to_string()]);
+        expected_errors(&preview);
+        assert!(manager.list_configs().unwrap().is_empty());
+        
+        options.dry_run = false;
+        options.strict = true;
+        let error = manager.import_from_file_with_options(&import_path, &options).unwrap_err();
+        assert!(error.to_string().contains("2 invalid configuration(s)"));
+        assert!(manager.list_configs().unwrap().is_empty());
+        
+        options.strict = false;
+        let result = manager.import_from_file_with_options(&import_path, &options).unwrap();
+        assert_eq!(result.imported, vec!["good".to_string()]);
+        expected_errors(&result);
+        assert_eq!(manager.list_configs().unwrap(), vec!["good".to_string()]);
+        
+        // Skipping validation still never saves an invalid configuration
+        options.skip_validation = true;
+        options.force_overwrite = true;
+        let result = manager.import_from_file_with_options(&import_path, &options).unwrap();
+        expected_errors(&result);
+        assert!(manager.load_configs().is_ok());
+    }
+
+    #[test]
+    fn test_export_checksum_detects_corruption() {
+        let config_paths1 = create_test_config_paths();
+        let config_paths2 = create_test_config_paths();
+        let export_path = config_paths1.config_dir.join("export.json");
+        let manager1 = FileConfigManager::with_paths(config_paths1);
+        let manager2 = FileConfigManager::with_paths(config_paths2);
+        manager1.create_config("config1".to_string(), create_test_variables(), None).unwrap();
+        
+        let options = ExportOptions {
+            format: ExportFormat::Json,
+            include_metadata: false,
+            pretty_print: true,
+            configs: None,
+            resolve_files: false,
+            checksum: true,
+            baseline: None,
+            include_settings: false,
+            exclude_keys: Vec::new(),
+            template: false,
+        };
+        manager1.export_to_file_with_options(&export_path, &options).unwrap();
+        let content = fs::read_to_string(&export_path).unwrap();
+        assert!(content.contains("\"checksum\": \"sha256:"));
+        
+        // Intact files import normally
+        assert_eq!(manager2.import_from_file(&export_path, false).unwrap(), vec!["config1".to_string()]);
+        
+        fs::write(&export_path, content.replace("deepseek-chat", "deepseek-coder")).unwrap();
+        let err = manager2.import_from_file(&export_path, false).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"), "{}", err);
+        
+        fs::write(&export_path, &content[..content.len() / 2]).unwrap();
+        let err = manager2.import_from_file(&export_path, false).unwrap_err();
+        assert!(err.to_string().contains("truncated"), "{}", err);
+        
+        // Text formats carry the checksum in a header line
+        let env_path = export_path.with_extension("env");
+        manager1.export_to_file_with_options(&env_path, &ExportOptions { format: ExportFormat::Env, ..options }).unwrap();
+        let content = fs::read_to_string(&env_path).unwrap();
+        assert!(content.starts_with("# Checksum: sha256:"));
+        fs::write(&env_path, &content[..content.len() - 3]).unwrap();
+        assert!(manager2.parse_env_file(&env_path).unwrap_err().to_string().contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn test_partial_export_since_baseline() {
+        let config_paths = create_test_config_paths();
+        let export_path = config_paths.config_dir.join("delta.json");
+        let manager = FileConfigManager::with_paths(config_paths);
+        manager.create_config("old".to_string(), create_test_variables(), None).unwrap();
+        manager.create_config("kept".to_string(), create_test_variables(), None).unwrap();
+        let backup = manager.backup_config().unwrap();
+        
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let cutoff = Utc::now();
+        manager.create_config("new".to_string(), create_test_variables(), None).unwrap();
+        let mut variables = create_test_variables();
+        variables.insert("EXTRA".to_string(), "1".to_string());
+        manager.update_config("old".to_string(), variables, None).unwrap();
+        
+        let options = ExportOptions {
+            format: ExportFormat::Json,
+            include_metadata: false,
+            pretty_print: false,
+            configs: None,
+            resolve_files: false,
+            checksum: true,
+            baseline: Some(ExportBaseline::Since(cutoff)),
+            include_settings: false,
+            exclude_keys: Vec::new(),
+            template: false,
+        };
+        manager.export_to_file_with_options(&export_path, &options).unwrap();
+        let content = fs::read_to_string(&export_path).unwrap();
+        let exported = parse_json_export(&content).unwrap();
+        let mut names: Vec<_> = exported.configs.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["new".to_string(), "old".to_string()]);
+        assert!(partial_export_baseline(&content).unwrap().starts_with("changed since "));
+        
+        // A backup baseline picks the same delta, and text formats carry a marker comment
+        let env_path = export_path.with_extension("env");
+        let options = ExportOptions {
+            format: ExportFormat::Env,
+            include_metadata: true,
+            baseline: Some(ExportBaseline::Backup(backup.clone())),
+            ..options
+        };
+        manager.export_to_file_with_options(&env_path, &options).unwrap();
+        let content = fs::read_to_string(&env_path).unwrap();
+        assert!(content.contains("# Configuration: new") && content.contains("# Configuration: old"));
+        assert!(!content.contains("# Configuration: kept"));
+        let name = backup.file_name().unwrap().to_string_lossy().into_owned();
+        assert_eq!(partial_export_baseline(&content), Some(format!("changed since backup {}", name)));
+        
+        assert_eq!(partial_export_baseline("{\"configs\": {}}"), None);
+        assert_eq!(partial_export_baseline("API_KEY=abc\n"), None);
+    }
+
+    #[test]
+    fn test_streamed_export_matches_in_memory_output() {
+        let config_paths = create_test_config_paths();
+        let dir = config_paths.config_dir.clone();
+        let manager = FileConfigManager::with_paths(config_paths);
+        let mut variables = HashMap::new();
+        variables.insert("API_URL".to_string(), "https://api.example.com".to_string());
+        manager.create_config("small".to_string(), variables, Some("Small".to_string())).unwrap();
+        manager.set_active_config("small".to_string()).unwrap();
+        let store = manager.load_store().unwrap();
+        
+        let options = ExportOptions {
+            format: ExportFormat::Json,
+            include_metadata: false,
+            pretty_print: true,
+            configs: None,
+            resolve_files: false,
+            checksum: false,
+            baseline: None,
+            include_settings: false,
+            exclude_keys: Vec::new(),
+            template: false,
+        };
+        let json_path = dir.join("small.json");
+        manager.export_to_file_with_options(&json_path, &options).unwrap();
+        let value = serde_json::to_value(&store).unwrap();
+        assert_eq!(fs::read_to_string(&json_path).unwrap(), serde_json::to_string_pretty(&value).unwrap());
+        
+        // The checksum computed while writing is the one of the whole document
+        manager.export_to_file_with_options(&json_path, &ExportOptions { checksum: true, ..options.clone() }).unwrap();
+        let mut checksummed = value.clone();
+        crate::utils::add_json_checksum(&mut checksummed);
+        let exported: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(exported, checksummed);
+        
+        let env_path = dir.join("small.env");
+        manager.export_to_file_with_options(&env_path, &ExportOptions { format: ExportFormat::Env, ..options.clone() }).unwrap();
+        assert_eq!(fs::read_to_string(&env_path).unwrap(), "API_URL=https://api.example.com\n\n");
+        manager.export_to_file_with_options(&env_path, &ExportOptions { format: ExportFormat::Env, checksum: true, ..options.clone() }).unwrap();
+        assert_eq!(fs::read_to_string(&env_path).unwrap(), crate::utils::add_text_checksum("API_URL=https://api.example.com\n\n"));
+        
+        let yaml_path = dir.join("small.yaml");
+        manager.export_to_file_with_options(&yaml_path, &ExportOptions { format: ExportFormat::Yaml, ..options }).unwrap();
+        let yaml = fs::read_to_string(&yaml_path).unwrap();
+        let document: serde_json::Value = serde_yaml::from_str(yaml.strip_prefix(YAML_EXPORT_HEADER).unwrap()).unwrap();
+        assert_eq!(document, serde_json::to_value(&store).unwrap());
+    }
+
+    #[test]
+    fn test_yaml_export_imports_like_json() {
+        let config_paths = create_test_config_paths();
+        let dir = config_paths.config_dir.clone();
+        let manager = FileConfigManager::with_paths(config_paths);
+        let mut variables = HashMap::new();
+        variables.insert("QUOTED".to_string(), "say \"hi\" \\ bye".to_string());
+        variables.insert("FLAG".to_string(), "true".to_string());
+        variables.insert("PORT".to_string(), "0x10".to_string());
+        manager.create_config("work".to_string(), variables, Some("Work: main # account".to_string())).unwrap();
+        let mut store = manager.load_store().unwrap();
+        let config = store.configs.get_mut("work").unwrap();
+        config.archived = true;
+        config.links.push("https://console.example.com".to_string());
+        config.variable_comments.insert("FLAG".to_string(), "# not a comment".to_string());
+        manager.save_store(&store).unwrap();
+        
+        for checksum in [false, true] {
+            let yaml_path = dir.join("work.yaml");
+            let options = ExportOptions {
+                format: ExportFormat::Yaml,
+                include_metadata: true,
+                pretty_print: false,
+                configs: None,
+                resolve_files: false,
+                checksum,
+                baseline: None,
+                include_settings: false,
+                exclude_keys: Vec::new(),
+                template: false,
+            };
+            manager.export_to_file_with_options(&yaml_path, &options).unwrap();
+            assert!(fs::read_to_string(&yaml_path).unwrap().starts_with(YAML_EXPORT_HEADER));
+            
+            let imported = manager.read_import_file(&yaml_path, ImportFormat::Yaml).unwrap();
+            assert_eq!(imported.configs, manager.load_configs().unwrap().configs);
+        }
+        
+        // A changed value no longer matches the checksum
+        let yaml_path = dir.join("work.yaml");
+        let content = fs::read_to_string(&yaml_path).unwrap();
+        fs::write(&yaml_path, content.replace("0x10", "0x11")).unwrap();
+        assert!(manager.read_import_file(&yaml_path, ImportFormat::Yaml).is_err());
+    }
+
+    #[test]
+    fn test_export_large_store() {
+        let config_paths = create_test_config_paths();
+        let export_path = config_paths.config_dir.join("large.json");
+        let manager = FileConfigManager::with_paths(config_paths);
+        let mut store = ConfigStore::default();
+        for i in 0..2000 {
+            let variables = (0..20)
+                .map(|j| (format!("VAR_{}", j), format!("value-{}-{}-{}", i, j, "x".repeat(64))))
+                .collect();
+            store.add_config(EnvConfig::new(format!("config{}", i), variables, None).unwrap()).unwrap();
+        }
+        manager.save_store(&store).unwrap();
+        
+        let options = ExportOptions {
+            format: ExportFormat::Json,
+            include_metadata: false,
+            pretty_print: true,
+            configs: Some((0..1000).map(|i| format!("config{}", i * 2)).collect()),
+            resolve_files: false,
+            checksum: true,
+            baseline: None,
+            include_settings: false,
+            exclude_keys: Vec::new(),
+            template: false,
+        };
+        let start = std::time::Instant::now();
+        manager.export_to_file_with_options(&export_path, &options).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(10), "export took {:?}", start.elapsed());
+        
+        // Parsing verifies the checksum computed while writing
+        let content = fs::read_to_string(&export_path).unwrap();
+        assert!(content.contains("\"checksum\": \"sha256:"));
+        let exported = parse_json_export(&content).unwrap();
+        assert_eq!(exported.configs.len(), 1000);
+        assert!(exported.configs.contains_key("config1998") && !exported.configs.contains_key("config1"));
+        
+        let yaml_path = export_path.with_extension("yaml");
+        manager.export_to_file_with_options(&yaml_path, &ExportOptions { format: ExportFormat::Yaml, ..options.clone() }).unwrap();
+        assert_eq!(parse_yaml_export(&fs::read_to_string(&yaml_path).unwrap()).unwrap().configs.len(), 1000);
+        
+        let env_path = export_path.with_extension("env");
+        manager.export_to_file_with_options(&env_path, &ExportOptions { format: ExportFormat::Env, configs: None, ..options }).unwrap();
+        let content = fs::read_to_string(&env_path).unwrap();
+        assert_eq!(crate::utils::verify_text_checksum(&content).0, crate::utils::ChecksumStatus::Verified);
+        let lines = content.lines().filter(|line| !line.is_empty() && !line.starts_with('#')).count();
+        assert_eq!(lines, 2000 * 20);
+    }
+
+    #[test]
+    fn test_storage_layout_migration_round_trip() {
+        let config_paths = create_test_config_paths();
+        let manager = FileConfigManager::with_paths(config_paths.clone());
+        manager.create_config("work".to_string(), create_test_variables(), Some("Work".to_string())).unwrap();
+        manager.create_config("home".to_string(), create_test_variables(), None).unwrap();
+        manager.add_config_alias("work", "w").unwrap();
+        manager.set_active_config("work".to_string()).unwrap();
+        let before = manager.load_configs().unwrap();
+        let backup = manager.backup_config().unwrap();
+        
+        manager.set_setting("storage-layout", "split").unwrap();
+        assert!(manager.uses_split_layout());
+        assert!(!config_paths.config_file.exists());
+        assert!(manager.config_file_path().ends_with(crate::types::constants::SPLIT_INDEX_FILE_NAME));
+        let split = manager.load_configs().unwrap();
+        assert_eq!(split.configs, before.configs);
+        assert_eq!(split.active_config, before.active_config);
+        assert_eq!(split.activated_at, before.activated_at);
+        assert_eq!(manager.resolve_config_name("w").unwrap().as_deref(), Some("work"));
+        assert!(manager.set_setting("git-track", "true").is_err());
+        assert!(manager.check_integrity().unwrap().is_clean());
+        
+        // Restoring a backup made before the migration goes back to config.json
+        manager.restore_from_backup(&backup).unwrap();
+        assert!(!manager.uses_split_layout());
+        assert!(config_paths.config_file.exists());
+        assert!(!manager.split_manager().configs_dir().exists());
+        assert_eq!(manager.load_configs().unwrap().configs, before.configs);
+    }
+
+    #[test]
+    fn test_settings_export_round_trip() {
+        let source = FileConfigManager::with_paths(create_test_config_paths());
+        source.create_config("work".to_string(), create_test_variables(), None).unwrap();
+        source.set_default_config(Some("work")).unwrap();
+        source.set_setting("no-emoji", "true").unwrap();
+        source.set_setting("language", "zh-CN").unwrap();
+        
+        let export_dir = tempfile::tempdir().unwrap();
+        for (format, file) in [(ExportFormat::Json, "settings.json"), (ExportFormat::Env, "settings.env"), (ExportFormat::Yaml, "settings.yaml")] {
+            let export_path = export_dir.path().join(file);
+            let options = ExportOptions {
+                format,
+                include_metadata: false,
+                pretty_print: true,
+                configs: None,
+                resolve_files: false,
+                checksum: false,
+                baseline: None,
+                include_settings: true,
+                exclude_keys: Vec::new(),
+                template: false,
+            };
+            source.export_to_file_with_options(&export_path, &options).unwrap();
+            let document = exported_settings(&fs::read_to_string(&export_path).unwrap()).unwrap();
+            assert_eq!(document["default"], "work");
+            assert_eq!(document["language"], "zh-CN");
+            
+            let target = FileConfigManager::with_paths(create_test_config_paths());
+            target.create_config("work".to_string(), create_test_variables(), None).unwrap();
+            let preview = target.apply_settings_document(&document, true).unwrap();
+            assert!(preview.iter().any(|change| change.key == "no-emoji" && change.new == "true"));
+            assert!(!target.load_configs().unwrap().settings.no_emoji);
+            
+            target.apply_settings_document(&document, false).unwrap();
+            let settings = target.load_configs().unwrap().settings;
+            assert_eq!(settings.default_config.as_deref(), Some("work"));
+            assert!(settings.no_emoji);
+            assert!(target.apply_settings_document(&document, false).unwrap().is_empty());
+        }
+        
+        // Without --include-settings nothing about the settings is exported
+        let plain_path = export_dir.path().join("plain.json");
+        source.export_to_file(&plain_path).unwrap();
+        assert_eq!(exported_settings(&fs::read_to_string(&plain_path).unwrap()), None);
+    }
+
+    #[test]
+    fn test_settings_document_round_trips_every_field() {
+        // Every field is listed, so a new setting fails to compile here until it is added,
+        // and fails the test until the settings document carries it
+        let tracked = Settings {
+            claude_variables: vec!["OPENROUTER_API_KEY".to_string()],
+            skip_verification: true,
+            git_track: true,
+            lazy_secrets: true,
+            skip_integrity_check: true,
+            backup_dir: Some("/tmp/envswitch-backups".to_string()),
+            skip_duplicate_check: true,
+            default_config: Some("work".to_string()),
+            no_emoji: true,
+            language: Some("zh-CN".to_string()),
+            storage_layout: StorageLayout::Single,
+            list_columns: Some("name,vars".to_string()),
+            mask_length_hints: true,
+            utc_timestamps: true,
+            exclude_keys: vec!["AWS_*".to_string()],
+            drift_watch: vec!["PATH".to_string()],
+            replace_symlinks: true,
+            strict: true,
+            replace_guard: Some(5),
+        };
+        // git-track needs the single layout, so the split layout is checked on its own
+        let split = Settings { git_track: false, storage_layout: StorageLayout::Split, ..tracked.clone() };
+        
+        for settings in [tracked, split] {
+            let mut store = ConfigStore::new();
+            store.insert_config(EnvConfig::new("work".to_string(), create_test_variables(), None).unwrap());
+            store.apply_settings_document(&settings.to_document()).unwrap();
+            assert_eq!(store.settings, settings);
+        }
+    }
+
+    #[test]
+    fn test_settings_document_rejects_invalid_values() {
+        let manager = FileConfigManager::with_paths(create_test_config_paths());
+        manager.create_config("work".to_string(), create_test_variables(), None).unwrap();
+        
+        let invalid = SettingsDocument::from([
+            ("no-emoji".to_string(), "true".to_string()),
+            ("language".to_string(), "klingon".to_string()),
+        ]);
+        assert!(manager.apply_settings_document(&invalid, false).is_err());
+        let missing_default = SettingsDocument::from([("default".to_string(), "gone".to_string())]);
+        assert!(manager.apply_settings_document(&missing_default, false).is_err());
+        assert!(manager.load_configs().unwrap().settings.is_empty());
+        
+        // git-track sorts before storage-layout, so switching both needs the second pass
+        manager.set_setting("storage-layout", "split").unwrap();
+        let single_tracked = SettingsDocument::from([
+            ("git-track".to_string(), "true".to_string()),
+            ("storage-layout".to_string(), "single".to_string()),
+        ]);
+        let changes = manager.apply_settings_document(&single_tracked, false).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(manager.load_configs().unwrap().settings.git_track);
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let config_paths1 = create_test_config_paths();
+        let config_paths2 = create_test_config_paths();
+        let archive_path = config_paths1.config_dir.join("backup.tar.gz");
+        let manager1 = FileConfigManager::with_paths(config_paths1);
+        let manager2 = FileConfigManager::with_paths(config_paths2);
+
+        manager1.create_config("config1".to_string(), create_test_variables(), Some("Config 1".to_string())).unwrap();
+        manager1.set_active_config("config1".to_string()).unwrap();
+        manager1.watch_claude_variable("OPENROUTER_API_KEY").unwrap();
+        manager1.backup_config().unwrap();
+
+        let manifest = manager1.export_archive(&archive_path).unwrap();
+        assert_eq!(manifest.envswitch_version, env!("CARGO_PKG_VERSION"));
+        assert!(manifest.files.contains(&"config.json".to_string()));
+        assert_eq!(manifest.files.iter().filter(|f| f.starts_with("backups/")).count(), 1);
+
+        // Replacing restores configs, settings, active config and backups
+        manager2.create_config("local".to_string(), HashMap::new(), None).unwrap();
+        let result = manager2.import_archive(&archive_path, false).unwrap();
+        assert_eq!(result.manifest, manifest);
+        assert_eq!(result.imported, vec!["config1".to_string()]);
+        assert_eq!(result.restored_backups, 1);
+        assert!(manager2.get_config("local").unwrap().is_none());
+        assert_eq!(manager2.get_config("config1").unwrap().unwrap().variables, create_test_variables());
+        assert_eq!(manager2.get_active_config().unwrap(), Some("config1".to_string()));
+        assert_eq!(manager2.get_settings().unwrap().claude_variables, vec!["OPENROUTER_API_KEY".to_string()]);
+        // The replaced store was backed up first
+        assert_eq!(manager2.list_backups().unwrap().len(), 2);
+
+        // Merging keeps local configs and reports conflicts
+        manager2.create_config("local".to_string(), HashMap::new(), None).unwrap();
+        let result = manager2.import_archive(&archive_path, true).unwrap();
+        assert!(result.imported.is_empty());
+        assert_eq!(result.conflicts, vec!["config1".to_string()]);
+        assert_eq!(result.restored_backups, 0);
+        assert!(manager2.get_config("local").unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archive_restores_backups_privately() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let config_paths1 = create_test_config_paths();
+        let config_paths2 = create_test_config_paths();
+        let archive_path = config_paths1.config_dir.join("backup.tar.gz");
+        let manager1 = FileConfigManager::with_paths(config_paths1);
+        let manager2 = FileConfigManager::with_paths(config_paths2);
+        
+        manager1.create_config("config1".to_string(), create_test_variables(), None).unwrap();
+        manager1.backup_config().unwrap();
+        manager1.export_archive(&archive_path).unwrap();
+        
+        let result = manager2.import_archive(&archive_path, true).unwrap();
+        assert_eq!(result.restored_backups, 1);
+        let backups = manager2.list_backups().unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::metadata(&backups[0]).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_archive_rejects_escaping_paths() {
+        let config_paths = create_test_config_paths();
+        let archive_path = config_paths.config_dir.join("evil.tar.gz");
+        let manager = FileConfigManager::with_paths(config_paths);
+
+        let file = fs::File::create(&archive_path).unwrap();
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(file, flate2::Compression::default()));
+        let data = b"{}";
+        let mut header = tar::Header::new_gnu();
+        // Builder::append_data refuses "..", so write the name into the header directly
+        let name = b"../escaped.json";
+        header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o600);
+        header.set_cksum();
+        builder.append(&header, &data[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let err = manager.import_archive(&archive_path, false).unwrap_err();
+        assert!(err.to_string().contains("escapes"), "{}", err);
+        assert!(!manager.config_file_exists());
+    }
+
     #[test]
     fn test_file_config_manager_backup_management() {
         let config_paths = create_test_config_paths();
@@ -1328,6 +6030,7 @@ mod tests {
         manager.create_config("claude".to_string(), claude_vars, Some("Claude config".to_string())).unwrap();
         manager.create_config("other".to_string(), other_vars, Some("Other config".to_string())).unwrap();
         manager.set_active_config("claude".to_string()).unwrap();
+        manager.set_config_archived("other", true).unwrap();
 
         // Create a backup
         manager.backup_config().unwrap();
@@ -1337,6 +6040,7 @@ mod tests {
         assert_eq!(stats.total_configs, 2);
         assert_eq!(stats.total_variables, 3); // 2 Claude vars + 1 other var
         assert_eq!(stats.claude_configs, 1);
+        assert_eq!(stats.archived_configs, 1);
         assert_eq!(stats.active_config, Some("claude".to_string()));
         assert_eq!(stats.backup_count, 1);
         assert!(stats.config_file_size.is_some());
@@ -1363,4 +6067,4 @@ mod tests {
         let path = manager.config_file_path();
         assert!(path.ends_with("config.json"));
     }
-}
\ No newline at end of file
+}
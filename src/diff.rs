@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use crate::config::ConfigStore;
+
+/// How one variable differs between two versions of a configuration
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableChange {
+    Added { key: String, value: String },
+    Removed { key: String, value: String },
+    Changed { key: String, old: String, new: String },
+}
+
+impl VariableChange {
+    /// Name of the variable that changed
+    pub fn key(&self) -> &str {
+        match self {
+            VariableChange::Added { key, .. }
+            | VariableChange::Removed { key, .. }
+            | VariableChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// Variable changes of a configuration present on both sides
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiff {
+    pub name: String,
+    pub changes: Vec<VariableChange>,
+}
+
+impl ConfigDiff {
+    /// Counts of added, changed and removed variables
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let mut counts = (0, 0, 0);
+        for change in &self.changes {
+            match change {
+                VariableChange::Added { .. } => counts.0 += 1,
+                VariableChange::Changed { .. } => counts.1 += 1,
+                VariableChange::Removed { .. } => counts.2 += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// Difference between two configuration stores, going from `old` to `new`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreDiff {
+    /// Configurations only in the new store
+    pub added: Vec<String>,
+    /// Configurations only in the old store
+    pub removed: Vec<String>,
+    /// Configurations in both stores whose variables differ
+    pub changed: Vec<ConfigDiff>,
+}
+
+impl StoreDiff {
+    /// Check if the stores hold the same configurations and variables
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two sets of variables, sorted by key
+pub fn diff_variables(old: &HashMap<String, String>, new: &HashMap<String, String>) -> Vec<VariableChange> {
+    let mut changes = Vec::new();
+
+    for (key, new_value) in new {
+        match old.get(key) {
+            None => changes.push(VariableChange::Added { key: key.clone(), value: new_value.clone() }),
+            Some(old_value) if old_value != new_value => changes.push(VariableChange::Changed {
+                key: key.clone(),
+                old: old_value.clone(),
+                new: new_value.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, old_value) in old {
+        if !new.contains_key(key) {
+            changes.push(VariableChange::Removed { key: key.clone(), value: old_value.clone() });
+        }
+    }
+
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+    changes
+}
+
+/// The value of one variable in a stored version of the configurations
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableVersion {
+    pub commit: String,
+    pub timestamp: DateTime<Utc>,
+    /// None when the configuration or the variable did not exist in this version
+    pub value: Option<String>,
+}
+
+/// A version in which a variable got a different value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    pub commit: String,
+    pub timestamp: DateTime<Utc>,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// The versions, oldest first, in which the value differs from the version before
+///
+/// The variable counts as unset before the first version.
+pub fn value_changes(versions: &[VariableVersion]) -> Vec<ValueChange> {
+    let mut previous: Option<&String> = None;
+    let mut changes = Vec::new();
+    for version in versions {
+        if version.value.as_ref() != previous {
+            changes.push(ValueChange {
+                commit: version.commit.clone(),
+                timestamp: version.timestamp,
+                old: previous.cloned(),
+                new: version.value.clone(),
+            });
+        }
+        previous = version.value.as_ref();
+    }
+    changes
+}
+
+/// Compare two stores configuration by configuration
+pub fn diff_stores(old: &ConfigStore, new: &ConfigStore) -> StoreDiff {
+    let mut diff = StoreDiff::default();
+
+    for (name, new_config) in &new.configs {
+        match old.configs.get(name) {
+            None => diff.added.push(name.clone()),
+            Some(old_config) => {
+                let changes = diff_variables(&old_config.variables, &new_config.variables);
+                if !changes.is_empty() {
+                    diff.changed.push(ConfigDiff { name: name.clone(), changes });
+                }
+            }
+        }
+    }
+    diff.removed = old.configs.keys().filter(|name| !new.configs.contains_key(*name)).cloned().collect();
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort_by(|a, b| a.name.cmp(&b.name));
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnvConfig;
+
+    fn store_with(configs: &[(&str, &[(&str, &str)])]) -> ConfigStore {
+        let mut store = ConfigStore::default();
+        for (name, variables) in configs {
+            let variables = variables.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            store.add_config(EnvConfig::new(name.to_string(), variables, None).unwrap()).unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn test_diff_variables() {
+        let old: HashMap<String, String> = [("A", "1"), ("B", "2"), ("C", "3")]
+            .iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let new: HashMap<String, String> = [("A", "1"), ("B", "20"), ("D", "4")]
+            .iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        assert_eq!(diff_variables(&old, &new), vec![
+            VariableChange::Changed { key: "B".to_string(), old: "2".to_string(), new: "20".to_string() },
+            VariableChange::Removed { key: "C".to_string(), value: "3".to_string() },
+            VariableChange::Added { key: "D".to_string(), value: "4".to_string() },
+        ]);
+        assert!(diff_variables(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_value_changes() {
+        let versions: Vec<VariableVersion> = [None, Some("a"), Some("a"), Some("b"), None, None, Some("b")]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| VariableVersion {
+                commit: format!("c{}", i),
+                timestamp: DateTime::from_timestamp(i as i64 * 60, 0).unwrap(),
+                value: value.map(str::to_string),
+            })
+            .collect();
+
+        let changes = value_changes(&versions);
+        let summary: Vec<(&str, Option<&str>, Option<&str>)> = changes.iter()
+            .map(|change| (change.commit.as_str(), change.old.as_deref(), change.new.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("c1", None, Some("a")),
+            ("c3", Some("a"), Some("b")),
+            ("c4", Some("b"), None),
+            ("c6", None, Some("b")),
+        ]);
+        assert_eq!(changes[1].timestamp, versions[3].timestamp);
+
+        assert!(value_changes(&[]).is_empty());
+        assert!(value_changes(&versions[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_diff_stores_extra_config_and_changed_value() {
+        let current = store_with(&[
+            ("deepseek", &[("ANTHROPIC_MODEL", "deepseek-chat")]),
+            ("local", &[("API_URL", "http://localhost")]),
+        ]);
+        let backup = store_with(&[
+            ("deepseek", &[("ANTHROPIC_MODEL", "deepseek-coder")]),
+            ("kimi", &[("ANTHROPIC_MODEL", "kimi-k2")]),
+            ("local", &[("API_URL", "http://localhost")]),
+        ]);
+
+        let diff = diff_stores(&current, &backup);
+        assert_eq!(diff.added, vec!["kimi".to_string()]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].name, "deepseek");
+        assert_eq!(diff.changed[0].counts(), (0, 1, 0));
+
+        let reverse = diff_stores(&backup, &current);
+        assert_eq!(reverse.removed, vec!["kimi".to_string()]);
+        assert!(diff_stores(&current, &current).is_empty());
+    }
+}
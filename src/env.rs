@@ -1,7 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
 use crate::error::{EnvError, EnvResult};
-use crate::shell::{ShellType, ShellDetector};
+use crate::types::validation::claude_watchlist;
+use crate::shell::{ShellType, ShellDetector, VariableScope, ListOp, LIST_SEPARATOR};
 
 /// Environment variable status information
 #[derive(Debug, Clone, PartialEq)]
@@ -31,6 +34,185 @@ impl EnvVarStatus {
     }
 }
 
+/// Prefix marking a variable value as a reference to a file holding the real value
+pub const FILE_REFERENCE_PREFIX: &str = "@file:";
+
+/// Check whether a stored value is a `@file:` reference
+pub fn is_file_reference(value: &str) -> bool {
+    value.starts_with(FILE_REFERENCE_PREFIX)
+}
+
+/// Path referenced by a `@file:` value, with a leading `~` expanded to the home directory
+pub fn file_reference_path(value: &str) -> Option<PathBuf> {
+    let path = value.strip_prefix(FILE_REFERENCE_PREFIX)?.trim();
+    
+    if path == "~" {
+        return dirs::home_dir();
+    }
+    if let Some(rest) = path.strip_prefix("~/") {
+        return dirs::home_dir().map(|home| home.join(rest));
+    }
+    
+    Some(PathBuf::from(path))
+}
+
+/// Prefix marking a value as a 1Password reference read with `op read` when activated
+pub const OP_REFERENCE_PREFIX: &str = "@op:";
+
+/// Prefix marking a value as a shell command whose output is the value when activated
+pub const COMMAND_REFERENCE_PREFIX: &str = "@cmd:";
+
+/// Check whether a stored value is resolved by running a command (`@op:` or `@cmd:`)
+pub fn is_lazy_reference(value: &str) -> bool {
+    value.starts_with(OP_REFERENCE_PREFIX) || value.starts_with(COMMAND_REFERENCE_PREFIX)
+}
+
+/// Read a secret from 1Password with `op read <reference>`
+pub fn read_op_secret(key: &str, reference: &str) -> EnvResult<String> {
+    let mut command = std::process::Command::new("op");
+    command.arg("read").arg(reference);
+    run_secret_command(key, &format!("op read {}", reference), command)
+}
+
+/// Read a secret from the output of a shell command
+pub fn read_command_secret(key: &str, shell_command: &str) -> EnvResult<String> {
+    let command = if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.arg("/C").arg(shell_command);
+        command
+    } else {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(shell_command);
+        command
+    };
+    run_secret_command(key, shell_command, command)
+}
+
+/// Run a secret command and return its stdout without the trailing newline
+///
+/// The output is never included in errors; only the command's stderr is.
+fn run_secret_command(key: &str, description: &str, mut command: std::process::Command) -> EnvResult<String> {
+    let failed = |stderr: String| EnvError::ValueCommandFailed {
+        key: key.to_string(),
+        command: description.to_string(),
+        stderr,
+    };
+    
+    let output = command
+        .stdin(std::process::Stdio::inherit())
+        .output()
+        .map_err(|e| failed(e.to_string()))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(failed(if stderr.is_empty() { output.status.to_string() } else { stderr }));
+    }
+    
+    let stdout = String::from_utf8(output.stdout).map_err(|_| failed("output is not valid UTF-8".to_string()))?;
+    let value = stdout.strip_suffix('\n').unwrap_or(&stdout);
+    let value = value.strip_suffix('\r').unwrap_or(value);
+    Ok(value.to_string())
+}
+
+/// Resolve `@op:` and `@cmd:` references by running their commands
+///
+/// Fails with [`EnvError::LazyReferencesDisabled`] when a reference is found but not allowed.
+pub fn resolve_lazy_references(variables: &HashMap<String, String>, allowed: bool) -> EnvResult<HashMap<String, String>> {
+    let mut resolved = HashMap::with_capacity(variables.len());
+    for (key, value) in variables {
+        if is_lazy_reference(value) && !allowed {
+            return Err(EnvError::LazyReferencesDisabled(key.clone()));
+        }
+        resolved.insert(key.clone(), resolve_lazy_reference(key, value)?);
+    }
+    Ok(resolved)
+}
+
+/// Resolve a single `@op:` or `@cmd:` value; other values are returned unchanged
+pub fn resolve_lazy_reference(key: &str, value: &str) -> EnvResult<String> {
+    if let Some(reference) = value.strip_prefix(OP_REFERENCE_PREFIX) {
+        read_op_secret(key, reference.trim())
+    } else if let Some(shell_command) = value.strip_prefix(COMMAND_REFERENCE_PREFIX) {
+        read_command_secret(key, shell_command.trim())
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// Resolve a single variable value, reading `@file:` references from disk
+pub fn resolve_variable_value(key: &str, value: &str) -> EnvResult<String> {
+    let Some(path) = file_reference_path(value) else {
+        return Ok(value.to_string());
+    };
+    
+    let content = std::fs::read_to_string(&path).map_err(|e| EnvError::ValueFileUnreadable {
+        key: key.to_string(),
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })?;
+    
+    // Files written by editors usually end with a newline that isn't part of the value
+    let content = content.strip_suffix('\n').unwrap_or(&content);
+    let content = content.strip_suffix('\r').unwrap_or(content);
+    Ok(content.to_string())
+}
+
+/// Resolve all `@file:` references in a set of variables
+pub fn resolve_variables(variables: &HashMap<String, String>) -> EnvResult<HashMap<String, String>> {
+    let mut resolved = HashMap::with_capacity(variables.len());
+    for (key, value) in variables {
+        resolved.insert(key.clone(), resolve_variable_value(key, value)?);
+    }
+    Ok(resolved)
+}
+
+/// Check whether a variable's current value satisfies the configured value
+///
+/// PATH-style list variables only need to contain the configured entry.
+pub fn value_matches_expected(current: Option<&str>, expected: &str, list_op: Option<ListOp>) -> bool {
+    match (current, list_op) {
+        (Some(current), Some(_)) => {
+            let padded = format!("{sep}{}{sep}", current, sep = LIST_SEPARATOR);
+            padded.contains(&format!("{sep}{}{sep}", expected, sep = LIST_SEPARATOR))
+        }
+        (Some(current), None) => current == expected,
+        (None, _) => false,
+    }
+}
+
+/// How activating a configuration affects the variables currently exported
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SwitchBreakdown {
+    /// Variables that are not set yet
+    pub new: Vec<String>,
+    /// Variables that already have the configured value
+    pub matching: Vec<String>,
+    /// Variables whose current value came from the previously active configuration
+    pub replacing: Vec<String>,
+    /// Variables set to a different value by something other than envswitch
+    pub foreign: Vec<String>,
+    /// Variables of the previous configuration that the new one doesn't set
+    pub unset: Vec<String>,
+}
+
+/// Structured description of a switch, printed by `use --format json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwitchPayload {
+    /// Configuration being activated
+    pub alias: String,
+    /// Shell the commands would have been generated for
+    pub shell: String,
+    /// Variables to export, with `@file:` references resolved
+    pub set: BTreeMap<String, String>,
+    /// Variables to remove
+    pub unset: Vec<String>,
+    /// Variables in `set` that are prepended or appended to instead of assigned
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub list_ops: BTreeMap<String, ListOp>,
+    /// How the switch relates to the current environment
+    pub changes: SwitchBreakdown,
+}
+
 pub trait EnvironmentManager {
     fn set_variables(&self, variables: &HashMap<String, String>) -> EnvResult<()>;
     fn unset_variables(&self, keys: &[String]) -> EnvResult<()>;
@@ -44,17 +226,105 @@ pub trait EnvironmentManager {
 
 pub struct ShellEnvironmentManager {
     shell_type: ShellType,
+    scope: VariableScope,
 }
 
 impl ShellEnvironmentManager {
     pub fn new() -> Self {
         Self {
             shell_type: ShellDetector::detect_shell(),
+            scope: VariableScope::default(),
         }
     }
     
     pub fn with_shell_type(shell_type: ShellType) -> Self {
-        Self { shell_type }
+        Self { shell_type, scope: VariableScope::default() }
+    }
+    
+    /// Use the given variable scope for generated commands (fish only)
+    pub fn with_scope(mut self, scope: VariableScope) -> Self {
+        self.scope = scope;
+        self
+    }
+    
+    /// Generate commands for a configuration whose list variables are prepended or appended
+    pub fn generate_config_commands(
+        &self,
+        variables: &HashMap<String, String>,
+        list_ops: &HashMap<String, ListOp>,
+    ) -> EnvResult<String> {
+        if variables.is_empty() {
+            return Ok(String::new());
+        }
+        
+        // Values stored as file references are read at activation time
+        let variables = resolve_variables(variables)?;
+        
+        // Validate all variable names before generating commands
+        for (key, value) in &variables {
+            crate::types::validation::validate_env_var(key, value)?;
+        }
+        
+        ShellDetector::generate_env_commands_with_list_ops(&self.shell_type, &variables, list_ops, self.scope)
+    }
+    
+    /// Generate commands removing the entries a configuration added to list variables
+    pub fn generate_list_removal_commands(
+        &self,
+        variables: &HashMap<String, String>,
+        list_ops: &HashMap<String, ListOp>,
+    ) -> EnvResult<String> {
+        let mut keys: Vec<&String> = list_ops.keys().collect();
+        keys.sort();
+        
+        let mut commands = Vec::new();
+        for key in keys {
+            if let Some(value) = variables.get(key) {
+                let value = resolve_variable_value(key, value)?;
+                commands.push(ShellDetector::generate_list_removal_commands(&self.shell_type, key, &value, self.scope)?);
+            }
+        }
+        
+        Ok(commands.join("\n"))
+    }
+    
+    /// Compare the current environment against a configuration about to be activated
+    ///
+    /// `previous` holds the variables of the previously active configuration;
+    /// values that still match it are considered managed by envswitch.
+    pub fn plan_switch(
+        &self,
+        target: &HashMap<String, String>,
+        list_ops: &HashMap<String, ListOp>,
+        previous: &HashMap<String, String>,
+        previous_list_ops: &HashMap<String, ListOp>,
+    ) -> SwitchBreakdown {
+        let mut breakdown = SwitchBreakdown::default();
+        let keys: Vec<String> = target.keys().cloned().collect();
+        
+        for status in self.get_variable_status(&keys) {
+            let expected = &target[&status.key];
+            let list_op = list_ops.get(&status.key).copied();
+            
+            match status.value.as_deref() {
+                None => breakdown.new.push(status.key),
+                Some(current) if value_matches_expected(Some(current), expected, list_op) => breakdown.matching.push(status.key),
+                // List variables are extended rather than overwritten
+                Some(_) if list_op.is_some() => breakdown.new.push(status.key),
+                Some(current) if previous.get(&status.key).map(String::as_str) == Some(current) => breakdown.replacing.push(status.key),
+                Some(_) => breakdown.foreign.push(status.key),
+            }
+        }
+        
+        breakdown.unset = previous.keys()
+            .filter(|key| !target.contains_key(*key) && !previous_list_ops.contains_key(*key))
+            .cloned()
+            .collect();
+        
+        for keys in [&mut breakdown.new, &mut breakdown.matching, &mut breakdown.replacing, &mut breakdown.foreign, &mut breakdown.unset] {
+            keys.sort();
+        }
+        breakdown
     }
     
     /// Generate commands to switch to a configuration
@@ -72,16 +342,9 @@ impl ShellEnvironmentManager {
         self.get_variable(key).is_some()
     }
     
-    /// Get Claude-specific environment variables status
-    pub fn get_claude_variables_status(&self) -> Vec<EnvVarStatus> {
-        let claude_vars = [
-            "ANTHROPIC_BASE_URL",
-            "ANTHROPIC_MODEL", 
-            "ANTHROPIC_AUTH_TOKEN",
-            "ANTHROPIC_SMALL_FAST_MODEL",
-        ];
-        
-        self.get_variable_status(&claude_vars.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    /// Get Claude-specific environment variables status, including user-added names
+    pub fn get_claude_variables_status(&self, extra: &[String]) -> Vec<EnvVarStatus> {
+        self.get_variable_status(&claude_watchlist(extra))
     }
     
     /// Generate shell integration instructions
@@ -130,16 +393,7 @@ impl EnvironmentManager for ShellEnvironmentManager {
     }
     
     fn generate_shell_commands(&self, variables: &HashMap<String, String>) -> EnvResult<String> {
-        if variables.is_empty() {
-            return Ok(String::new());
-        }
-        
-        // Validate all variable names before generating commands
-        for (key, value) in variables {
-            crate::types::validation::validate_env_var(key, value)?;
-        }
-        
-        crate::shell::ShellDetector::generate_env_commands(&self.shell_type, variables)
+        self.generate_config_commands(variables, &HashMap::new())
     }
     
     fn generate_unset_commands(&self, keys: &[String]) -> EnvResult<String> {
@@ -152,7 +406,7 @@ impl EnvironmentManager for ShellEnvironmentManager {
             crate::error::validate_env_var_name(key)?;
         }
         
-        crate::shell::ShellDetector::generate_unset_commands(&self.shell_type, keys)
+        crate::shell::ShellDetector::generate_unset_commands_with_scope(&self.shell_type, keys, self.scope)
     }
     
     fn get_shell_type(&self) -> &ShellType {
@@ -289,9 +543,9 @@ mod tests {
         
         let commands = manager.generate_shell_commands(&variables).unwrap();
         
-        assert!(commands.contains("set -x ANTHROPIC_BASE_URL"));
-        assert!(commands.contains("set -x ANTHROPIC_MODEL"));
-        assert!(commands.contains("set -x TEST_VAR"));
+        assert!(commands.contains("set -gx ANTHROPIC_BASE_URL"));
+        assert!(commands.contains("set -gx ANTHROPIC_MODEL"));
+        assert!(commands.contains("set -gx TEST_VAR"));
         assert!(commands.contains("https://api.deepseek.com"));
         assert!(commands.contains("deepseek-chat"));
         assert!(commands.contains("test_value"));
@@ -331,6 +585,142 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_resolve_file_reference() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let secret_path = temp_dir.path().join("service-account.json");
+        std::fs::write(&secret_path, "{\"type\": \"service_account\"}\n").unwrap();
+        
+        let mut variables = HashMap::new();
+        variables.insert("PLAIN".to_string(), "value".to_string());
+        variables.insert("CREDENTIALS".to_string(), format!("@file:{}", secret_path.display()));
+        
+        let resolved = resolve_variables(&variables).unwrap();
+        assert_eq!(resolved.get("PLAIN").unwrap(), "value");
+        assert_eq!(resolved.get("CREDENTIALS").unwrap(), "{\"type\": \"service_account\"}");
+        
+        let manager = ShellEnvironmentManager::with_shell_type(ShellType::Bash);
+        let commands = manager.generate_shell_commands(&variables).unwrap();
+        assert!(commands.contains("service_account"));
+        assert!(!commands.contains("@file:"));
+    }
+
+    #[test]
+    fn test_resolve_missing_file_reference() {
+        let mut variables = HashMap::new();
+        variables.insert("CREDENTIALS".to_string(), "@file:/nonexistent/envswitch/secret".to_string());
+        
+        let error = resolve_variables(&variables).unwrap_err();
+        assert!(matches!(error, EnvError::ValueFileUnreadable { ref key, .. } if key == "CREDENTIALS"));
+        
+        let manager = ShellEnvironmentManager::new();
+        assert!(manager.generate_shell_commands(&variables).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_lazy_references() {
+        let mut variables = HashMap::new();
+        variables.insert("PLAIN".to_string(), "value".to_string());
+        variables.insert("TOKEN".to_string(), "@cmd:printf 'sk-secret\\n'".to_string());
+        
+        let resolved = resolve_lazy_references(&variables, true).unwrap();
+        assert_eq!(resolved.get("PLAIN").unwrap(), "value");
+        assert_eq!(resolved.get("TOKEN").unwrap(), "sk-secret");
+        
+        let error = resolve_lazy_references(&variables, false).unwrap_err();
+        assert!(matches!(error, EnvError::LazyReferencesDisabled(ref key) if key == "TOKEN"));
+        
+        // Only stderr is reported, never stdout
+        variables.insert("TOKEN".to_string(), "@cmd:printf 'partial-%s' secret; echo 'vault locked' >&2; exit 3".to_string());
+        let error = resolve_lazy_references(&variables, true).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("vault locked"));
+        assert!(!message.contains("partial-secret"));
+    }
+
+    #[test]
+    fn test_plan_switch_categories() {
+        env::set_var("ENVSWITCH_PLAN_MATCHING", "same");
+        env::set_var("ENVSWITCH_PLAN_REPLACING", "from-previous");
+        env::set_var("ENVSWITCH_PLAN_FOREIGN", "from-elsewhere");
+        env::remove_var("ENVSWITCH_PLAN_NEW");
+        
+        let mut target = HashMap::new();
+        for key in ["MATCHING", "REPLACING", "FOREIGN", "NEW"] {
+            target.insert(format!("ENVSWITCH_PLAN_{}", key), "value".to_string());
+        }
+        target.insert("ENVSWITCH_PLAN_MATCHING".to_string(), "same".to_string());
+        
+        let mut previous = HashMap::new();
+        previous.insert("ENVSWITCH_PLAN_REPLACING".to_string(), "from-previous".to_string());
+        previous.insert("ENVSWITCH_PLAN_OLD_ONLY".to_string(), "x".to_string());
+        
+        let manager = ShellEnvironmentManager::with_shell_type(ShellType::Bash);
+        let breakdown = manager.plan_switch(&target, &HashMap::new(), &previous, &HashMap::new());
+        
+        assert_eq!(breakdown.new, vec!["ENVSWITCH_PLAN_NEW"]);
+        assert_eq!(breakdown.matching, vec!["ENVSWITCH_PLAN_MATCHING"]);
+        assert_eq!(breakdown.replacing, vec!["ENVSWITCH_PLAN_REPLACING"]);
+        assert_eq!(breakdown.foreign, vec!["ENVSWITCH_PLAN_FOREIGN"]);
+        assert_eq!(breakdown.unset, vec!["ENVSWITCH_PLAN_OLD_ONLY"]);
+        
+        env::remove_var("ENVSWITCH_PLAN_MATCHING");
+        env::remove_var("ENVSWITCH_PLAN_REPLACING");
+        env::remove_var("ENVSWITCH_PLAN_FOREIGN");
+    }
+
+    #[test]
+    fn test_switch_payload_round_trip() {
+        let mut set = BTreeMap::new();
+        set.insert("ANTHROPIC_MODEL".to_string(), "deepseek-chat".to_string());
+        set.insert("PATH".to_string(), "/opt/tool/bin".to_string());
+        let mut list_ops = BTreeMap::new();
+        list_ops.insert("PATH".to_string(), ListOp::Prepend);
+        
+        let payload = SwitchPayload {
+            alias: "deepseek".to_string(),
+            shell: "zsh".to_string(),
+            set,
+            unset: vec!["OLD_VAR".to_string()],
+            list_ops,
+            changes: SwitchBreakdown {
+                new: vec!["ANTHROPIC_MODEL".to_string()],
+                unset: vec!["OLD_VAR".to_string()],
+                ..Default::default()
+            },
+        };
+        
+        let json = serde_json::to_string(&payload).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["set"]["ANTHROPIC_MODEL"], "deepseek-chat");
+        assert_eq!(value["unset"][0], "OLD_VAR");
+        assert_eq!(value["list_ops"]["PATH"], "prepend");
+        assert_eq!(value["shell"], "zsh");
+        
+        let restored: SwitchPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, payload);
+    }
+
+    #[test]
+    fn test_value_matches_expected_list() {
+        assert!(value_matches_expected(Some("/opt/bin:/usr/bin"), "/opt/bin", Some(ListOp::Prepend)));
+        assert!(value_matches_expected(Some("/usr/bin:/opt/bin"), "/opt/bin", Some(ListOp::Append)));
+        assert!(!value_matches_expected(Some("/opt/bin2:/usr/bin"), "/opt/bin", Some(ListOp::Prepend)));
+        assert!(!value_matches_expected(Some("/opt/bin:/usr/bin"), "/opt/bin", None));
+        assert!(!value_matches_expected(None, "/opt/bin", Some(ListOp::Prepend)));
+    }
+
+    #[test]
+    fn test_file_reference_path_expands_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(file_reference_path("@file:~/secret.json"), Some(home.join("secret.json")));
+        assert_eq!(file_reference_path("@file:/etc/secret"), Some(PathBuf::from("/etc/secret")));
+        assert_eq!(file_reference_path("plain"), None);
+        assert!(is_file_reference("@file:x"));
+        assert!(!is_file_reference("x@file:"));
+    }
+
     #[test]
     fn test_generate_unset_commands_zsh() {
         let manager = ShellEnvironmentManager::with_shell_type(ShellType::Zsh);
@@ -428,15 +818,35 @@ mod tests {
     fn test_get_claude_variables_status() {
         let manager = ShellEnvironmentManager::new();
         
-        let statuses = manager.get_claude_variables_status();
-        assert_eq!(statuses.len(), 4);
+        let statuses = manager.get_claude_variables_status(&[]);
+        assert_eq!(statuses.len(), crate::types::constants::CLAUDE_ENV_VARS.len());
         
-        let expected_vars = ["ANTHROPIC_BASE_URL", "ANTHROPIC_MODEL", "ANTHROPIC_AUTH_TOKEN", "ANTHROPIC_SMALL_FAST_MODEL"];
-        for expected_var in &expected_vars {
+        for expected_var in crate::types::constants::CLAUDE_ENV_VARS {
             assert!(statuses.iter().any(|s| s.key == *expected_var));
         }
     }
 
+    #[test]
+    fn test_claude_views_agree() {
+        let manager = ShellEnvironmentManager::new();
+        let extra = vec!["OPENROUTER_API_KEY".to_string()];
+        
+        // Every variable reported by status must also mark a configuration as Claude, and vice versa
+        let statuses = manager.get_claude_variables_status(&extra);
+        let status_keys: Vec<String> = statuses.iter().map(|s| s.key.clone()).collect();
+        assert_eq!(status_keys, claude_watchlist(&extra));
+        
+        for key in &status_keys {
+            let mut variables = HashMap::new();
+            variables.insert(key.clone(), "value".to_string());
+            assert!(crate::utils::is_claude_configuration(&variables, &extra), "{} missing from detection", key);
+        }
+        
+        let mut variables = HashMap::new();
+        variables.insert("NOT_WATCHED".to_string(), "value".to_string());
+        assert!(!crate::utils::is_claude_configuration(&variables, &extra));
+    }
+
     #[test]
     fn test_get_integration_instructions() {
         let zsh_manager = ShellEnvironmentManager::with_shell_type(ShellType::Zsh);
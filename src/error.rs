@@ -3,8 +3,8 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum ConfigError {
-    #[error("Configuration '{0}' not found")]
-    ConfigNotFound(String),
+    #[error("Configuration '{name}' not found")]
+    ConfigNotFound { name: String, suggestions: Vec<String> },
     
     #[error("Configuration file error: {0}")]
     FileError(#[from] std::io::Error),
@@ -27,6 +27,21 @@ pub enum ConfigError {
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
     
+    #[error("Cannot lock the configuration store at {path}: {reason}")]
+    LockFailed { path: String, reason: String },
+    
+    #[error("Configuration directory is read-only: {0}")]
+    ReadOnly(String),
+    
+    #[error("Migration to the {layout} storage layout failed: {reason}")]
+    MigrationFailed { layout: String, reason: String },
+    
+    #[error("{0} warning(s) treated as errors in strict mode")]
+    StrictWarnings(usize),
+    
+    #[error("Refusing to remove {removed} variables from '{alias}' without confirmation")]
+    ReplaceGuard { alias: String, removed: usize },
+    
     #[error("Environment variable error: {0}")]
     EnvError(#[from] EnvError),
 }
@@ -50,6 +65,15 @@ pub enum EnvError {
     
     #[error("Command generation failed: {0}")]
     CommandGenerationFailed(String),
+    
+    #[error("Cannot read value file for '{key}': {path}: {reason}")]
+    ValueFileUnreadable { key: String, path: String, reason: String },
+    
+    #[error("Secret command for '{key}' failed: {command}: {stderr}")]
+    ValueCommandFailed { key: String, command: String, stderr: String },
+    
+    #[error("Lazy secret reference for '{0}' is disabled")]
+    LazyReferencesDisabled(String),
 }
 
 #[derive(Debug, Error)]
@@ -73,10 +97,15 @@ pub type EnvResult<T> = Result<T, EnvError>;
 pub type AppResult<T> = Result<T, AppError>;
 
 impl ConfigError {
+    /// A missing configuration, without "did you mean" suggestions
+    pub fn not_found(name: impl Into<String>) -> Self {
+        ConfigError::ConfigNotFound { name: name.into(), suggestions: Vec::new() }
+    }
+
     /// Provides user-friendly error messages with suggestions
     pub fn user_message(&self) -> String {
         match self {
-            ConfigError::ConfigNotFound(name) => {
+            ConfigError::ConfigNotFound { name, .. } => {
                 format!("Configuration '{}' not found. Use 'envswitch list' to see available configurations.", name)
             }
             ConfigError::FileError(err) => {
@@ -100,6 +129,21 @@ impl ConfigError {
             ConfigError::PermissionDenied(path) => {
                 format!("Permission denied accessing '{}'. Check file permissions.", path)
             }
+            ConfigError::LockFailed { path, reason } => {
+                format!("Cannot lock the configuration store ({}): {}", path, reason)
+            }
+            ConfigError::ReadOnly(dir) => {
+                format!("Cannot save changes: the configuration directory '{}' is read-only.", dir)
+            }
+            ConfigError::MigrationFailed { layout, reason } => {
+                format!("Moving the store to the {} storage layout failed: {}", layout, reason)
+            }
+            ConfigError::StrictWarnings(count) => {
+                format!("Stopped because of {} warning(s): warnings are errors with --strict or the strict setting.", count)
+            }
+            ConfigError::ReplaceGuard { alias, removed } => {
+                format!("Nothing changed: --replace would remove {} variables from '{}' and was not confirmed.", removed, alias)
+            }
             ConfigError::EnvError(env_err) => {
                 format!("Environment variable error: {}", env_err.user_message())
             }
@@ -129,6 +173,15 @@ impl EnvError {
             EnvError::CommandGenerationFailed(msg) => {
                 format!("Failed to generate shell commands: {}", msg)
             }
+            EnvError::ValueFileUnreadable { key, path, reason } => {
+                format!("Cannot read the value of '{}' from '{}': {}. Check that the file exists and is readable.", key, path, reason)
+            }
+            EnvError::ValueCommandFailed { key, command, stderr } => {
+                format!("Cannot read the value of '{}': '{}' failed: {}", key, command, stderr)
+            }
+            EnvError::LazyReferencesDisabled(key) => {
+                format!("'{}' is resolved by a command at activation time, but lazy secrets are disabled. Enable them with: envswitch settings set lazy-secrets true", key)
+            }
         }
     }
 }
@@ -226,7 +279,7 @@ mod tests {
 
     #[test]
     fn test_error_user_messages() {
-        let config_error = ConfigError::ConfigNotFound("test".to_string());
+        let config_error = ConfigError::not_found("test".to_string());
         let message = config_error.user_message();
         assert!(message.contains("test"));
         assert!(message.contains("envswitch list"));
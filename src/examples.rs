@@ -0,0 +1,317 @@
+//! Example invocations shared by `--help`, `envswitch examples`, the tutorial and hints
+//!
+//! Commands are written for bash and zsh; [`for_shell`] adapts the `eval "$(...)"`
+//! snippets for fish.
+
+use crate::shell::{ShellDetector, ShellType};
+
+/// One example: what it does and the command line
+pub struct Example {
+    pub description: &'static str,
+    pub command: &'static str,
+}
+
+const fn ex(description: &'static str, command: &'static str) -> Example {
+    Example { description, command }
+}
+
+/// A task-oriented recipe printed by `envswitch examples <topic>`
+pub struct Recipe {
+    pub topic: &'static str,
+    pub title: &'static str,
+    pub steps: &'static [Example],
+}
+
+pub const RECIPES: &[Recipe] = &[
+    Recipe {
+        topic: "switching",
+        title: "Create configurations and switch between them",
+        steps: &[
+            ex("Create a configuration", "envswitch set deepseek -e ANTHROPIC_BASE_URL=https://api.deepseek.com -e ANTHROPIC_MODEL=deepseek-chat"),
+            ex("Preview the commands without applying them", "envswitch use deepseek --dry-run"),
+            ex("Switch to it in the current shell", "eval \"$(envswitch use deepseek)\""),
+            ex("Check what is active and what differs", "envswitch status"),
+            ex("Unset its variables again", "eval \"$(envswitch clear)\""),
+        ],
+    },
+    Recipe {
+        topic: "sharing",
+        title: "Share configurations with a team",
+        steps: &[
+            ex("Export without secrets; sensitive values become {{KEY}} placeholders", "envswitch export --template --output team.json"),
+            ex("Preview what an import would change", "envswitch import team.json --dry-run"),
+            ex("Import, merging into configurations you already have", "envswitch import team.json --merge --backup"),
+            ex("Compare with the shared file later and pick updates", "envswitch sync team.json"),
+            ex("Switch; placeholders are asked for", "eval \"$(envswitch use team)\""),
+        ],
+    },
+    Recipe {
+        topic: "ci",
+        title: "Use configurations in CI and scripts",
+        steps: &[
+            ex("Bootstrap the store from a shared export without prompts", "envswitch sync team.json --apply-all-missing"),
+            ex("Export a configuration in a CI step", "eval \"$(envswitch use ci)\""),
+            ex("Write plain KEY=VALUE lines for other tools", "envswitch env ci > ci.env"),
+            ex("Fail the build when a change did nothing", "envswitch set ci -e DEPLOY_ENV=staging --fail-on-noop"),
+            ex("Print the active configuration, or exit 7 when none is", "envswitch current"),
+        ],
+    },
+    Recipe {
+        topic: "direnv",
+        title: "Load a configuration per project with direnv",
+        steps: &[
+            ex("Load the configuration whenever you enter the project (in .envrc)", "eval \"$(envswitch use project)\""),
+            ex("Or write a dotenv file that direnv reads with dotenv", "envswitch env project > .env"),
+            ex("Keep the dotenv file current while you edit the configuration", "envswitch watch --dotenv .env"),
+            ex("Allow the updated .envrc", "direnv allow"),
+        ],
+    },
+];
+
+/// The recipe of a topic, by name or unambiguous prefix
+pub fn recipe(topic: &str) -> Option<&'static Recipe> {
+    let topic = topic.to_lowercase();
+    RECIPES.iter().find(|recipe| recipe.topic == topic).or_else(|| {
+        let mut matches = RECIPES.iter().filter(|recipe| recipe.topic.starts_with(&topic));
+        match (matches.next(), matches.next()) {
+            (Some(recipe), None) if !topic.is_empty() => Some(recipe),
+            _ => None,
+        }
+    })
+}
+
+/// Examples of each subcommand, by the name typed on the command line
+pub const COMMAND_EXAMPLES: &[(&str, &[Example])] = &[
+    ("set", &[
+        ex("Create or update a configuration", "envswitch set deepseek -e ANTHROPIC_BASE_URL=https://api.deepseek.com -e ANTHROPIC_MODEL=deepseek-chat"),
+        ex("Capture a variable already exported in this shell", "envswitch set work -e ANTHROPIC_AUTH_TOKEN"),
+        ex("Read KEY=VALUE lines from a file or stdin", "pbpaste | envswitch set work --stdin"),
+        ex("Prepend to PATH instead of replacing it", "envswitch set tools --prepend PATH=/opt/tool/bin"),
+        ex("Note why a variable is set", "envswitch set work -e API_URL=https://v1.example.com --comment API_URL='pinned until v2'"),
+        ex("Preview a replacement without saving", "envswitch set work -e API_KEY=new-key --replace --dry-run"),
+    ]),
+    ("use", &[
+        ex("Switch to a configuration in the current shell", "eval \"$(envswitch use deepseek)\""),
+        ex("Preview the commands", "envswitch use deepseek --dry-run"),
+        ex("Check the token and BASE_URL first, aborting on failure", "eval \"$(envswitch use deepseek --verify strict)\""),
+        ex("Describe the switch as JSON", "envswitch use deepseek --format json --force"),
+        ex("Switch without writing anything, e.g. with a read-only store", "eval \"$(envswitch use deepseek --no-persist)\""),
+    ]),
+    ("clear", &[
+        ex("Unset the active configuration's variables", "eval \"$(envswitch clear)\""),
+        ex("Unset the variables of every configuration", "eval \"$(envswitch clear --all-known)\""),
+        ex("Show the commands only", "envswitch clear --dry-run"),
+    ]),
+    ("list", &[
+        ex("List configurations", "envswitch list"),
+        ex("Choose the table columns", "envswitch list --columns name,vars,updated"),
+        ex("Just the names, e.g. for fzf", "envswitch list --names --with-description"),
+        ex("Include archived configurations", "envswitch list --all"),
+    ]),
+    ("status", &[
+        ex("Show the active configuration and its variables", "envswitch status"),
+        ex("Only variables that differ from the configuration", "envswitch status --mismatched"),
+        ex("Machine-readable status", "envswitch status --json"),
+        ex("Just whether each variable matches, e.g. in CI", "envswitch status --json --fields key,matches"),
+    ]),
+    ("drift", &[
+        ex("Show variables changed since activation", "envswitch drift"),
+        ex("Restore them", "eval \"$(envswitch drift --fix)\""),
+        ex("Also watch variables outside the configuration", "envswitch settings set drift-watch PATH,HTTPS_PROXY"),
+    ]),
+    ("edit", &[
+        ex("Edit variables interactively", "envswitch edit my-config"),
+    ]),
+    ("delete", &[
+        ex("Delete after confirmation", "envswitch delete old-config"),
+        ex("Delete without asking", "envswitch delete temp-config --force"),
+    ]),
+    ("export", &[
+        ex("Export everything", "envswitch export --output my-configs.json"),
+        ex("Export some configurations as .env", "envswitch export --configs dev,prod --format env --output configs.env"),
+        ex("Include metadata, pretty-printed", "envswitch export --metadata --pretty --output detailed-configs.json"),
+        ex("Leave variables out by pattern", "envswitch export --exclude-keys 'AWS_*' --output shareable.json"),
+        ex("Replace secrets with placeholders", "envswitch export --template --output team-template.json"),
+        ex("Leave archived configurations out", "envswitch export --exclude-archived --output current.json"),
+        ex("A script of set commands, secrets read from the environment", "envswitch export --format shell --redact-secrets --output restore.sh"),
+        ex("Bundle the whole configuration directory", "envswitch export --archive backup.tar.gz"),
+    ]),
+    ("import", &[
+        ex("Import new configurations", "envswitch import configs.json"),
+        ex("Preview first", "envswitch import --dry-run --verbose new-configs.yaml"),
+        ex("Merge into existing configurations after a backup", "envswitch import --backup --merge team-configs.json"),
+        ex("Replace the whole store", "envswitch import --replace-store --backup laptop.json"),
+        ex("Take over the settings too", "envswitch import --include-settings laptop.json"),
+        ex("Rename variables while importing", "envswitch import --map DEEPSEEK_API_KEY=ANTHROPIC_AUTH_TOKEN vendor.env"),
+        ex("Point another user's home paths at yours", "envswitch import --rewrite-home teammate.json"),
+        ex("Restore an archive, keeping existing configurations", "envswitch import --archive backup.tar.gz"),
+    ]),
+    ("sync", &[
+        ex("Compare with a shared export and choose what to apply", "envswitch sync team-repo/envswitch.json"),
+        ex("Import everything missing without asking, e.g. in CI", "envswitch sync team-repo/envswitch.json --apply-all-missing"),
+    ]),
+    ("watch", &[
+        ex("Re-apply the active configuration when it changes", "while read -r cmd; do eval \"$cmd\"; done < <(envswitch watch)"),
+        ex("Keep a dotenv file up to date", "envswitch watch --dotenv ~/project/.env"),
+    ]),
+    ("convert", &[
+        ex("Convert by extension", "envswitch convert vars.env vars.yaml"),
+        ex("Give the formats explicitly", "envswitch convert --from json --to toml exported.txt vars.toml"),
+    ]),
+    ("set-var", &[
+        ex("Change one value", "envswitch set-var deepseek ANTHROPIC_MODEL deepseek-chat"),
+        ex("Be prompted for the value, hidden for secrets", "envswitch set-var deepseek ANTHROPIC_AUTH_TOKEN"),
+    ]),
+    ("unset-var", &[
+        ex("Remove one variable", "envswitch unset-var deepseek HTTPS_PROXY"),
+    ]),
+    ("protect", &[
+        ex("Require --force to change or delete a configuration", "envswitch protect prod"),
+    ]),
+    ("unprotect", &[
+        ex("Allow changes again", "envswitch unprotect prod"),
+    ]),
+    ("archive", &[
+        ex("Retire an old project without deleting it", "envswitch archive old-project"),
+        ex("See what was archived", "envswitch list --archived"),
+    ]),
+    ("unarchive", &[
+        ex("Show it in listings again", "envswitch unarchive old-project"),
+    ]),
+    ("pin", &[
+        ex("List a configuration first", "envswitch pin deepseek"),
+    ]),
+    ("unpin", &[
+        ex("Sort it with the others again", "envswitch unpin deepseek"),
+    ]),
+    ("prune", &[
+        ex("Delete expired configurations after confirmation", "envswitch prune --expired"),
+    ]),
+    ("alias", &[
+        ex("Add a short name", "envswitch alias add deepseek ds"),
+        ex("Remove it", "envswitch alias remove ds"),
+        ex("List all aliases", "envswitch alias list"),
+    ]),
+    ("log", &[
+        ex("Show the last changes (needs the git-track setting)", "envswitch log -n 10"),
+    ]),
+    ("history-var", &[
+        ex("Show when a value changed", "envswitch history-var deepseek ANTHROPIC_BASE_URL"),
+        ex("Show secret values too, after confirmation", "envswitch history-var deepseek ANTHROPIC_AUTH_TOKEN --reveal"),
+    ]),
+    ("revert", &[
+        ex("Restore the configurations of a commit from 'envswitch log'", "envswitch revert 3f2a9c1"),
+    ]),
+    ("settings", &[
+        ex("Show the settings", "envswitch settings show"),
+        ex("Watch another variable as Claude-related", "envswitch settings watch-claude OPENROUTER_API_KEY"),
+    ]),
+    ("env", &[
+        ex("Pass a configuration to a container", "docker run --env-file <(envswitch env prod) image"),
+        ex("The active configuration as JSON", "envswitch env --active --json"),
+    ]),
+    ("render", &[
+        ex("Write the active configuration to a script", "envswitch render --output ./activate.sh"),
+        ex("A bash script and its counterpart for a systemd unit", "envswitch render --output ./activate.sh --unset-script ./deactivate.sh --shell bash --alias deepseek"),
+    ]),
+    ("paths", &[
+        ex("Show where everything is stored", "envswitch paths"),
+        ex("Use another configuration directory", "ENVSWITCH_CONFIG_DIR=/tmp/envswitch envswitch paths"),
+    ]),
+    ("current", &[
+        ex("Print the active configuration", "envswitch current"),
+        ex("Include the activation time", "envswitch current --json"),
+        ex("Forget the active configuration", "envswitch current --clear"),
+    ]),
+    ("prompt", &[
+        ex("Show the active configuration in your prompt", "PS1='$(envswitch prompt) '\"$PS1\""),
+    ]),
+    ("audit", &[
+        ex("Look for shared and placeholder secrets", "envswitch audit"),
+        ex("Trim stray whitespace from stored values", "envswitch audit --repair"),
+    ]),
+    ("doctor", &[
+        ex("Check the store for problems", "envswitch doctor"),
+        ex("Repair names after a hand edit", "envswitch doctor --repair --keep alias"),
+    ]),
+    ("migrate-storage", &[
+        ex("One file per configuration, for large stores", "envswitch migrate-storage split"),
+        ex("Back to a single config.json", "envswitch migrate-storage single"),
+    ]),
+    ("default", &[
+        ex("Apply a configuration to every new shell", "envswitch default set deepseek"),
+        ex("Show the default", "envswitch default show"),
+    ]),
+    ("init", &[
+        ex("Set up envswitch step by step", "envswitch init"),
+        ex("Script it from a dotfiles repo", "envswitch init --non-interactive --install --create claude --preset claude --set-default"),
+        ex("Start from the variables already exported", "envswitch init --create work --from-env ANTHROPIC_"),
+    ]),
+    ("shellenv", &[
+        ex("Add to your shell rc file to apply the default", "eval \"$(envswitch shellenv --activate)\""),
+    ]),
+    ("backup", &[
+        ex("Back up with a note", "envswitch backup create --note \"before big refactor\""),
+        ex("List backups", "envswitch backup list"),
+        ex("Restore by part of its note", "envswitch backup restore refactor"),
+        ex("Check every backup for damage", "envswitch backup verify"),
+    ]),
+    ("examples", &[
+        ex("List the topics", "envswitch examples"),
+        ex("Show a recipe", "envswitch examples sharing"),
+    ]),
+];
+
+/// Examples of a subcommand; empty for commands without any
+pub fn command_examples(command: &str) -> &'static [Example] {
+    COMMAND_EXAMPLES.iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, examples)| *examples)
+        .unwrap_or(&[])
+}
+
+/// Adapt a bash/zsh example to `shell`: fish evaluates `(cmd)` instead of `"$(cmd)"`
+pub fn for_shell(command: &str, shell: &ShellType) -> String {
+    if *shell != ShellType::Fish {
+        return command.to_string();
+    }
+    match command.strip_prefix("eval \"$(").and_then(|rest| rest.strip_suffix(")\"")) {
+        Some(inner) => format!("eval ({})", inner),
+        None => command.to_string(),
+    }
+}
+
+/// Command applying a configuration in the detected shell, for hints
+pub fn activation_command(config: &str) -> String {
+    for_shell(&format!("eval \"$(envswitch use {})\"", config), &ShellDetector::detect_shell())
+}
+
+/// The `Examples:` section appended to the help of a subcommand
+pub fn after_help(command: &str) -> String {
+    let mut help = String::from("Examples:");
+    for example in command_examples(command) {
+        help.push_str(&format!("\n  # {}\n  {}", example.description, example.command));
+    }
+    help
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fish_gets_its_own_eval() {
+        let command = "eval \"$(envswitch use work)\"";
+        assert_eq!(for_shell(command, &ShellType::Bash), command);
+        assert_eq!(for_shell(command, &ShellType::Fish), "eval (envswitch use work)");
+        assert_eq!(for_shell("envswitch list", &ShellType::Fish), "envswitch list");
+    }
+
+    #[test]
+    fn test_recipes_by_topic_or_prefix() {
+        assert_eq!(recipe("ci").unwrap().topic, "ci");
+        assert_eq!(recipe("Shar").unwrap().topic, "sharing");
+        assert!(recipe("").is_none());
+        assert!(recipe("nope").is_none());
+    }
+}
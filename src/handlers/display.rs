@@ -1,174 +1,457 @@
 use std::collections::HashMap;
-use crate::config::{FileConfigManager, ConfigManager};
-use crate::env::{ShellEnvironmentManager, EnvVarStatus, EnvironmentManager};
-use crate::utils::{is_sensitive_key, mask_sensitive_value};
+use serde::Serialize;
+use crate::config::{FileConfigManager, ConfigManager, ConfigStore, EnvConfig};
+use crate::env::{ShellEnvironmentManager, EnvVarStatus, EnvironmentManager, is_file_reference, resolve_variable_value, value_matches_expected};
+use crate::shell::ListOp;
+use crate::types::validation::claude_watchlist;
+use crate::verify::{CheckStatus, VerificationReport};
+use crate::output::OutputContext;
+use crate::utils::{format_relative_time, format_timestamp, is_sensitive_key, mask_sensitive_value};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Display configurations in list format
+///
+/// With `verbose`, large configurations list their variables grouped by prefix unless `flat` is set.
 pub fn display_configs_list(
+    output: &OutputContext,
     configs: &[String],
     config_manager: &FileConfigManager,
     verbose: bool,
+    flat: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let active_config = config_manager.get_active_config()?;
+    let store = config_manager.load_configs()?;
+    let active_config = store.active_config.clone();
+    let stale = store.is_active_stale();
     
-    println!("Available configurations:");
+    outln!(output, "{}", msg!(output, "list.available"));
     
     for config_alias in configs {
         let is_active = active_config.as_ref() == Some(config_alias);
-        let marker = if is_active { " (active)" } else { "" };
+        let marker = match (is_active, stale) {
+            (true, true) => " (active, modified since activation)",
+            (true, false) => " (active)",
+            _ => "",
+        };
         
         if let Ok(Some(config)) = config_manager.get_config(config_alias) {
             let var_count = config.variables.len();
             let desc = config.description.as_deref().unwrap_or("No description");
+            let name = display_config_name(&config, output);
             
             if verbose {
-                println!("  {} - {} ({} variables){}", config_alias, desc, var_count, marker);
-                println!("    Created: {}", config.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
-                println!("    Updated: {}", config.updated_at.format("%Y-%m-%d %H:%M:%S UTC"));
+                outln!(output, "  {} - {} ({} variables){}", name, desc, var_count, marker);
+                outln!(output, "    Created: {}", format_timestamp(output, config.created_at));
+                outln!(output, "    Updated: {}", format_timestamp(output, config.updated_at));
+                if let Some(expires_at) = config.expires_at {
+                    outln!(output, "    Expires: {}", format_timestamp(output, expires_at));
+                }
+                if let Some(source) = &config.source {
+                    outln!(output, "    Source: {}", source.describe(output));
+                }
+                if let Some(notes) = &config.notes {
+                    outln!(output, "    Notes: {}", notes);
+                }
+                for link in &config.links {
+                    outln!(output, "    Link: {}", link);
+                }
                 
                 if !config.variables.is_empty() {
-                    println!("    Variables:");
-                    let mut sorted_vars: Vec<_> = config.variables.iter().collect();
-                    sorted_vars.sort_by_key(|(k, _)| *k);
-                    
-                    for (key, value) in sorted_vars {
+                    outln!(output, "    Variables:");
+                    print_grouped(output, config.variables.keys().map(String::as_str), flat, "      ", |key, indent| {
+                        let value = &config.variables[key];
                         let display_value = if is_sensitive_key(key) {
-                            mask_sensitive_value(value)
-                        } else if value.len() > 50 {
-                            format!("{}...", &value[..47])
+                            mask_sensitive_value(output, value)
                         } else {
-                            value.to_string()
+                            truncate_to_width(value, 50)
                         };
-                        println!("      {} = {}", key, display_value);
-                    }
+                        match config.variable_comments.get(key) {
+                            Some(comment) => outln!(output, "{}{} = {}  # {}", indent, key, display_value, comment.replace('\n', " ")),
+                            None => outln!(output, "{}{} = {}", indent, key, display_value),
+                        }
+                    });
                 }
-                println!();
+                outln!(output);
             } else {
-                println!("  {} - {} ({} variables){}", config_alias, desc, var_count, marker);
+                outln!(output, "  {} - {} ({} variables){}", name, desc, var_count, marker);
             }
         } else {
-            println!("  {}{}", config_alias, marker);
+            outln!(output, "  {}{}", config_alias, marker);
         }
     }
+    print_stale_hint(output, active_config.as_deref().filter(|_| stale));
     
     Ok(())
 }
 
+/// Fewest variables a listing needs before it is grouped by prefix
+const GROUPING_THRESHOLD: usize = 10;
+
+/// Fewest keys sharing a prefix that make a group of their own
+const MIN_GROUP_SIZE: usize = 3;
+
+/// Variable names sharing the part before their first `_`, e.g. `ANTHROPIC`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyGroup<'a> {
+    /// None for the keys whose prefix too few others share
+    pub prefix: Option<&'a str>,
+    pub keys: Vec<&'a str>,
+}
+
+/// Group variable names by the part before their first `_`
+///
+/// Prefixes shared by fewer than three names don't get a group; those names are
+/// gathered in a last group without prefix. Groups and the names within them are
+/// sorted alphabetically.
+pub fn group_by_prefix<'a>(keys: impl IntoIterator<Item = &'a str>) -> Vec<KeyGroup<'a>> {
+    let mut by_prefix: std::collections::BTreeMap<&str, Vec<&str>> = std::collections::BTreeMap::new();
+    let mut rest = Vec::new();
+    for key in keys {
+        match key.split_once('_').map(|(prefix, _)| prefix).filter(|prefix| !prefix.is_empty()) {
+            Some(prefix) => by_prefix.entry(prefix).or_default().push(key),
+            None => rest.push(key),
+        }
+    }
+    
+    let mut groups = Vec::new();
+    for (prefix, mut keys) in by_prefix {
+        if keys.len() >= MIN_GROUP_SIZE {
+            keys.sort_unstable();
+            groups.push(KeyGroup { prefix: Some(prefix), keys });
+        } else {
+            rest.extend(keys);
+        }
+    }
+    if !rest.is_empty() {
+        rest.sort_unstable();
+        groups.push(KeyGroup { prefix: None, keys: rest });
+    }
+    groups
+}
+
+/// Groups to list `keys` in: by prefix for large sets, otherwise one sorted group
+fn variable_groups<'a>(keys: impl IntoIterator<Item = &'a str>, flat: bool) -> Vec<KeyGroup<'a>> {
+    let mut keys: Vec<&str> = keys.into_iter().collect();
+    if !flat && keys.len() >= GROUPING_THRESHOLD {
+        return group_by_prefix(keys);
+    }
+    keys.sort_unstable();
+    vec![KeyGroup { prefix: None, keys }]
+}
+
+/// Print a heading with a count for each prefix group and call `print` with each key and its indentation
+fn print_grouped<'a>(output: &OutputContext, keys: impl IntoIterator<Item = &'a str>, flat: bool, indent: &str, mut print: impl FnMut(&'a str, &str)) {
+    let groups = variable_groups(keys, flat);
+    let grouped = groups.iter().any(|group| group.prefix.is_some());
+    let key_indent = if grouped { format!("{}  ", indent) } else { indent.to_string() };
+    for group in groups {
+        if grouped {
+            match group.prefix {
+                Some(prefix) => outln!(output, "{}{}_* ({})", indent, prefix, group.keys.len()),
+                None => outln!(output, "{}Other ({})", indent, group.keys.len()),
+            }
+        }
+        for key in group.keys {
+            print(key, &key_indent);
+        }
+    }
+}
+
+/// Tell the user how to refresh a shell whose active configuration changed after activation
+pub fn print_stale_hint(output: &OutputContext, stale_alias: Option<&str>) {
+    if let Some(alias) = stale_alias {
+        outln!(output, "⚠️  '{}' was modified after activation; your shell may be out of date.", alias);
+        outln!(output, "💡 Re-run: {}", crate::examples::activation_command(alias));
+    }
+}
+
+/// Format a configuration name followed by its aliases in parentheses
+fn display_name_with_aliases(name: &str, aliases: &[String]) -> String {
+    if aliases.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} ({})", name, aliases.join(", "))
+    }
+}
+
+/// Format a configuration's display name with its pin and expiry markers and aliases
+fn display_config_name(config: &crate::config::EnvConfig, output: &OutputContext) -> String {
+    let mut name = display_name_with_aliases(&config.alias, &config.aliases);
+    if config.pinned {
+        name = format!("{} {}", output.pin(), name);
+    }
+    if config.is_expired() {
+        name.push_str(" [expired]");
+    }
+    if config.protected {
+        name.push_str(" [protected]");
+    }
+    if config.archived {
+        name.push_str(" [archived]");
+    }
+    name
+}
+
+/// A column of `list --table`
+///
+/// Each column knows its header and how to fill a cell from a configuration, so
+/// the table is laid out the same way whatever columns are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListColumn {
+    Name,
+    Description,
+    Vars,
+    Active,
+    Updated,
+    Created,
+    Expires,
+    Source,
+}
+
+impl ListColumn {
+    /// Names accepted by [`ListColumn::parse`]
+    pub const NAMES: &'static [&'static str] = &["name", "description", "vars", "active", "updated", "created", "expires", "source"];
+    
+    /// Columns shown when neither `--columns` nor the list-columns setting chooses any
+    pub const DEFAULT: &'static [ListColumn] = &[
+        ListColumn::Name, ListColumn::Description, ListColumn::Vars, ListColumn::Active, ListColumn::Updated,
+    ];
+    
+    /// Parse a column name, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "name" => Some(ListColumn::Name),
+            "description" => Some(ListColumn::Description),
+            "vars" => Some(ListColumn::Vars),
+            "active" => Some(ListColumn::Active),
+            "updated" => Some(ListColumn::Updated),
+            "created" => Some(ListColumn::Created),
+            "expires" => Some(ListColumn::Expires),
+            "source" => Some(ListColumn::Source),
+            _ => None,
+        }
+    }
+    
+    /// Parse a comma-separated column list such as "name,vars,active"
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        let columns = list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| Self::parse(name).ok_or_else(|| format!(
+                "Unknown column '{}'. Available columns: {}", name, Self::NAMES.join(", ")
+            )))
+            .collect::<Result<Vec<_>, _>>()?;
+        if columns.is_empty() {
+            return Err(format!("No columns given. Available columns: {}", Self::NAMES.join(", ")));
+        }
+        Ok(columns)
+    }
+    
+    fn header(&self) -> &'static str {
+        match self {
+            ListColumn::Name => "Name",
+            ListColumn::Description => "Description",
+            ListColumn::Vars => "Variables",
+            ListColumn::Active => "Active",
+            ListColumn::Updated => "Updated",
+            ListColumn::Created => "Created",
+            ListColumn::Expires => "Expires",
+            ListColumn::Source => "Source",
+        }
+    }
+    
+    /// Cell for a configuration; `active_marker` is the shared active/stale mark of the row
+    ///
+    /// Updated shows how long ago the configuration changed unless `exact_times` is set.
+    fn cell(&self, config: &EnvConfig, active_marker: &str, output: &OutputContext, exact_times: bool) -> String {
+        match self {
+            ListColumn::Name => display_config_name(config, output),
+            ListColumn::Description => truncate_to_width(config.description.as_deref().unwrap_or("No description"), 50),
+            ListColumn::Vars => config.variables.len().to_string(),
+            ListColumn::Active => active_marker.to_string(),
+            ListColumn::Updated if exact_times => format_timestamp(output, config.updated_at),
+            ListColumn::Updated => format_relative_time(config.updated_at),
+            ListColumn::Created => format_timestamp(output, config.created_at),
+            ListColumn::Expires => config.expires_at
+                .map(|expires_at| format_timestamp(output, expires_at))
+                .unwrap_or_default(),
+            ListColumn::Source => config.source.as_ref().map(|source| source.describe(output)).unwrap_or_default(),
+        }
+    }
+    
+    /// Cell for a configuration that could not be loaded
+    fn error_cell(&self, alias: &str, active_marker: &str) -> String {
+        match self {
+            ListColumn::Name => alias.to_string(),
+            ListColumn::Description => "Error loading config".to_string(),
+            ListColumn::Vars => "?".to_string(),
+            ListColumn::Active => active_marker.to_string(),
+            ListColumn::Updated | ListColumn::Created => "Unknown".to_string(),
+            ListColumn::Expires | ListColumn::Source => String::new(),
+        }
+    }
+}
+
+/// Width of text in terminal columns; emoji and CJK characters take two
+fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Pad text with spaces to a width in terminal columns
+fn pad_to_width(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(display_width(text))))
+}
+
+/// Shorten text to at most `max_width` terminal columns, ending in "..." when cut
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        width += UnicodeWidthChar::width(c).unwrap_or(0);
+        if width > max_width.saturating_sub(3) {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.push_str("...");
+    truncated
+}
+
+/// Join cells padded to their column widths; the last column is not padded
+fn table_row(cells: &[String], widths: &[usize]) -> String {
+    let last = cells.len().saturating_sub(1);
+    cells.iter().zip(widths).enumerate()
+        .map(|(i, (cell, width))| if i == last { cell.clone() } else { pad_to_width(cell, *width) })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end()
+        .to_string()
+}
+
 /// Display configurations in table format
 pub fn display_configs_table(
+    output: &OutputContext,
     configs: &[String],
     config_manager: &FileConfigManager,
+    columns: &[ListColumn],
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let active_config = config_manager.get_active_config()?;
-    
-    // Calculate column widths
-    let mut max_name_width = 4; // "Name"
-    let mut max_desc_width = 11; // "Description"
-    let mut max_vars_width = 9; // "Variables"
+    let store = config_manager.load_configs()?;
+    let stale_alias = store.active_config.as_deref().filter(|_| store.is_active_stale());
+    let rows: Vec<(String, Option<EnvConfig>)> = configs.iter()
+        .map(|config_alias| (config_alias.clone(), config_manager.get_config(config_alias).ok().flatten()))
+        .collect();
     
-    for config_alias in configs {
-        max_name_width = max_name_width.max(config_alias.len());
-        
-        if let Ok(Some(config)) = config_manager.get_config(config_alias) {
-            let desc = config.description.as_deref().unwrap_or("No description");
-            max_desc_width = max_desc_width.max(desc.len().min(50));
-            max_vars_width = max_vars_width.max(config.variables.len().to_string().len());
-        }
+    let lines = configs_table_lines(&rows, &store, columns, output, verbose);
+    for line in lines {
+        outln!(output, "{}", line);
     }
+    print_stale_hint(output, stale_alias);
     
-    // Add padding
-    max_name_width += 2;
-    max_desc_width += 2;
-    max_vars_width += 2;
+    Ok(())
+}
+
+/// Lines of the `list --table` output; configurations that could not be loaded are None
+fn configs_table_lines(
+    rows: &[(String, Option<EnvConfig>)],
+    store: &ConfigStore,
+    columns: &[ListColumn],
+    output: &OutputContext,
+    verbose: bool,
+) -> Vec<String> {
+    let stale = store.is_active_stale();
+    let cells: Vec<Vec<String>> = rows.iter()
+        .map(|(config_alias, config)| {
+            let is_active = store.active_config.as_ref() == Some(config_alias);
+            let active_marker = match (is_active, stale) {
+                (true, true) => format!("{} stale", output.mark(true)),
+                (true, false) => output.mark(true).to_string(),
+                _ => String::new(),
+            };
+            match config {
+                Some(config) => columns.iter().map(|column| column.cell(config, &active_marker, output, verbose)).collect(),
+                None => columns.iter().map(|column| column.error_cell(config_alias, &active_marker)).collect(),
+            }
+        })
+        .collect();
     
-    // Print header
-    println!("{:<width_name$} {:<width_desc$} {:<width_vars$} {:<8} {:<19}",
-        "Name", "Description", "Variables", "Active", "Updated",
-        width_name = max_name_width,
-        width_desc = max_desc_width,
-        width_vars = max_vars_width
-    );
+    // Every column but the last gets two spaces of padding
+    let last = columns.len().saturating_sub(1);
+    let widths: Vec<usize> = columns.iter().enumerate()
+        .map(|(i, column)| {
+            let width = cells.iter()
+                .map(|row| display_width(&row[i]))
+                .fold(column.header().len(), usize::max);
+            if i == last { width } else { width + 2 }
+        })
+        .collect();
     
-    println!("{} {} {} {} {}",
-        "-".repeat(max_name_width),
-        "-".repeat(max_desc_width),
-        "-".repeat(max_vars_width),
-        "-".repeat(8),
-        "-".repeat(19)
-    );
+    let headers: Vec<String> = columns.iter().map(|column| column.header().to_string()).collect();
+    let mut lines = vec![
+        table_row(&headers, &widths),
+        widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>().join(" "),
+    ];
     
-    // Print configurations
-    for config_alias in configs {
-        let is_active = active_config.as_ref() == Some(config_alias);
-        let active_marker = if is_active { "✓" } else { "" };
+    for ((_, config), row) in rows.iter().zip(&cells) {
+        lines.push(table_row(row, &widths));
         
-        if let Ok(Some(config)) = config_manager.get_config(config_alias) {
-            let desc = config.description.as_deref().unwrap_or("No description");
-            let truncated_desc = if desc.len() > 50 {
-                format!("{}...", &desc[..47])
-            } else {
-                desc.to_string()
-            };
+        if let Some(config) = config.as_ref().filter(|config| verbose && !config.variables.is_empty()) {
+            lines.push("  Variables:".to_string());
+            let mut sorted_vars: Vec<_> = config.variables.iter().collect();
+            sorted_vars.sort_by_key(|(k, _)| *k);
             
-            println!("{:<width_name$} {:<width_desc$} {:<width_vars$} {:<8} {}",
-                config_alias,
-                truncated_desc,
-                config.variables.len(),
-                active_marker,
-                config.updated_at.format("%Y-%m-%d %H:%M:%S"),
-                width_name = max_name_width,
-                width_desc = max_desc_width,
-                width_vars = max_vars_width
-            );
-            
-            if verbose && !config.variables.is_empty() {
-                println!("  Variables:");
-                let mut sorted_vars: Vec<_> = config.variables.iter().collect();
-                sorted_vars.sort_by_key(|(k, _)| *k);
-                
-                for (key, value) in sorted_vars {
-                    let display_value = if is_sensitive_key(key) {
-                        mask_sensitive_value(value)
-                    } else if value.len() > 40 {
-                        format!("{}...", &value[..37])
-                    } else {
-                        value.clone()
-                    };
-                    println!("    {} = {}", key, display_value);
-                }
-                println!();
+            for (key, value) in sorted_vars {
+                let display_value = if is_sensitive_key(key) {
+                    mask_sensitive_value(output, value)
+                } else {
+                    truncate_to_width(value, 40)
+                };
+                lines.push(format!("    {} = {}", key, display_value));
             }
-        } else {
-            println!("{:<width_name$} {:<width_desc$} {:<width_vars$} {:<8} {}",
-                config_alias,
-                "Error loading config",
-                "?",
-                active_marker,
-                "Unknown",
-                width_name = max_name_width,
-                width_desc = max_desc_width,
-                width_vars = max_vars_width
-            );
+            lines.push(String::new());
         }
     }
-    
-    Ok(())
+    lines
 }
 
 /// Display Claude-specific status
+///
+/// When a configuration is active, its Claude variables are shown as the expected values.
 pub fn display_claude_status(
+    output: &OutputContext,
     env_manager: &ShellEnvironmentManager,
+    extra_claude_vars: &[String],
+    active_config: Option<(&str, &EnvConfig)>,
+    store: &ConfigStore,
     table: bool,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let claude_vars = [
-        "ANTHROPIC_BASE_URL",
-        "ANTHROPIC_MODEL",
-        "ANTHROPIC_AUTH_TOKEN",
-        "ANTHROPIC_SMALL_FAST_MODEL",
-        "ANTHROPIC_API_KEY",
-    ];
+    let claude_vars = claude_watchlist(extra_claude_vars);
+    
+    if let Some((config_name, config)) = active_config {
+        let expected_variables = config.claude_variables(extra_claude_vars);
+        let keys: Vec<String> = claude_vars.iter()
+            .filter(|var| expected_variables.contains_key(*var) || env_manager.is_variable_set(var))
+            .cloned()
+            .collect();
+        
+        if keys.is_empty() {
+            outln!(output, "No Claude environment variables found in the environment or in '{}'", config_name);
+            return Ok(());
+        }
+        
+        outln!(output, "Claude Environment Variables (compared with '{}'):", config_name);
+        let statuses = env_manager.get_variable_status(&keys);
+        let provenance = VariableProvenance::new(config_name, store);
+        if table {
+            display_status_table(output, &statuses, &expected_variables, &config.list_ops, &provenance, verbose)?;
+        } else {
+            display_status_list(output, &statuses, &expected_variables, &config.list_ops, &provenance, verbose, true)?;
+        }
+        return Ok(());
+    }
     
     let mut claude_env_vars = HashMap::new();
     for var in &claude_vars {
@@ -178,30 +461,30 @@ pub fn display_claude_status(
     }
     
     if claude_env_vars.is_empty() {
-        println!("No Claude environment variables found");
-        println!("Common Claude variables:");
+        outln!(output, "No Claude environment variables found");
+        outln!(output, "Common Claude variables:");
         for var in &claude_vars {
-            println!("  {}", var);
+            outln!(output, "  {}", var);
         }
         return Ok(());
     }
     
-    println!("Claude Environment Variables:");
+    outln!(output, "Claude Environment Variables:");
     
     let keys: Vec<String> = claude_env_vars.keys().cloned().collect();
     let statuses = env_manager.get_variable_status(&keys);
     
     if table {
-        display_claude_status_table(&statuses, verbose)?;
+        display_claude_status_table(output, &statuses, verbose)?;
     } else {
         for status in &statuses {
             let value_display = if is_sensitive_key(&status.key) {
-                mask_sensitive_value(&status.value.as_deref().unwrap_or(""))
+                mask_sensitive_value(output, status.value.as_deref().unwrap_or(""))
             } else {
                 status.value.as_deref().unwrap_or("(not set)").to_string()
             };
             
-            println!("  {} = {}", status.key, value_display);
+            outln!(output, "  {} = {}", status.key, value_display);
         }
     }
     
@@ -210,6 +493,7 @@ pub fn display_claude_status(
 
 /// Display Claude status in table format
 pub fn display_claude_status_table(
+    output: &OutputContext,
     statuses: &[EnvVarStatus],
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -227,13 +511,13 @@ pub fn display_claude_status_table(
     let max_value_width = 30;
     
     // Print header
-    println!("{:<width_key$} {:<width_value$} {:<6}",
+    outln!(output, "{:<width_key$} {:<width_value$} {:<6}",
         "Variable", "Value", "Status",
         width_key = max_key_width,
         width_value = max_value_width
     );
     
-    println!("{} {} {}",
+    outln!(output, "{} {} {}",
         "-".repeat(max_key_width),
         "-".repeat(max_value_width),
         "-".repeat(6)
@@ -243,77 +527,263 @@ pub fn display_claude_status_table(
     for status in statuses {
         let value_display = if let Some(ref value) = status.value {
             if is_sensitive_key(&status.key) {
-                mask_sensitive_value(value)
-            } else if value.len() > max_value_width - 2 {
-                format!("{}...", &value[..max_value_width - 5])
+                mask_sensitive_value(output, value)
             } else {
-                value.clone()
+                truncate_to_width(value, max_value_width - 2)
             }
         } else {
             "(not set)".to_string()
         };
         
-        let status_symbol = if status.value.is_some() { "✓" } else { "✗" };
+        let status_symbol = output.mark(status.value.is_some());
         
-        println!("{:<width_key$} {:<width_value$} {:<6}",
+        outln!(output, "{:<width_key$} {} {}",
             status.key,
-            value_display,
+            pad_to_width(&value_display, max_value_width),
             status_symbol,
-            width_key = max_key_width,
-            width_value = max_value_width
+            width_key = max_key_width
         );
     }
     
     Ok(())
 }
 
+/// Expected value of a variable with `@file:` references resolved, and whether it must be masked
+///
+/// Values read from files are always masked. Unreadable files fall back to the reference itself.
+fn expected_status_value(key: &str, expected_variables: &HashMap<String, String>) -> (Option<String>, bool) {
+    match expected_variables.get(key) {
+        Some(raw) if is_file_reference(raw) => {
+            (Some(resolve_variable_value(key, raw).unwrap_or_else(|_| raw.clone())), true)
+        }
+        Some(raw) => (Some(raw.clone()), is_sensitive_key(key)),
+        None => (None, is_sensitive_key(key)),
+    }
+}
+
+/// One variable of `status --json`; sensitive values are masked as in the text output
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct VariableStatusReport {
+    pub key: String,
+    pub current: Option<String>,
+    pub matches: bool,
+    /// Configuration that provided the current value, when it matches
+    pub source: Option<String>,
+    /// Other configurations defining the current value, when it doesn't match
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub current_matches: Vec<String>,
+}
+
+/// Where the current values of variables came from, for status output
+#[derive(Debug, Clone, Copy)]
+pub struct VariableProvenance<'a> {
+    /// Configuration the expected values come from
+    pub source: &'a str,
+    pub store: &'a ConfigStore,
+}
+
+impl<'a> VariableProvenance<'a> {
+    pub fn new(source: &'a str, store: &'a ConfigStore) -> Self {
+        Self { source, store }
+    }
+    
+    /// Other configurations that define the current value of a variable
+    pub fn other_configs(&self, status: &EnvVarStatus) -> Vec<String> {
+        let Some(current) = &status.value else {
+            return Vec::new();
+        };
+        let mut names = self.store.configs_defining(&status.key, current);
+        names.retain(|name| name != self.source);
+        names
+    }
+}
+
+/// Describe other configurations defining a value, e.g. "current value matches 'kimi'"
+fn other_configs_note(names: &[String]) -> String {
+    let quoted: Vec<String> = names.iter().map(|name| format!("'{}'", name)).collect();
+    format!("current value matches {}", quoted.join(", "))
+}
+
+/// Output of `status --json`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StatusReport {
+    pub active_config: Option<String>,
+    /// The configuration was applied by `shellenv --activate` rather than `use`
+    pub via_default: bool,
+    pub activated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The configuration was modified after it was activated
+    pub stale: bool,
+    pub variables: Vec<VariableStatusReport>,
+}
+
+/// One configuration in the output of `list --json`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ConfigSummary {
+    pub name: String,
+    pub description: Option<String>,
+    /// Number of variables; their values are never listed
+    pub variables: usize,
+    pub active: bool,
+    pub pinned: bool,
+    pub archived: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub source: Option<String>,
+}
+
+impl ConfigSummary {
+    pub fn new(output: &OutputContext, config: &EnvConfig, store: &ConfigStore) -> Self {
+        Self {
+            name: config.alias.clone(),
+            description: config.description.clone(),
+            variables: config.variables.len(),
+            active: store.active_config.as_deref() == Some(config.alias.as_str()),
+            pinned: config.pinned,
+            archived: config.archived,
+            created_at: config.created_at,
+            updated_at: config.updated_at,
+            expires_at: config.expires_at,
+            source: config.source.as_ref().map(|source| source.describe(output)),
+        }
+    }
+}
+
+/// Fields of a [`VariableStatusReport`] that `status --json --fields` selects from
+pub const STATUS_FIELDS: &[&str] = &["key", "current", "matches", "source", "current_matches"];
+
+/// Fields of a [`ConfigSummary`] that `list --json --fields` selects from
+pub const LIST_FIELDS: &[&str] = &["name", "description", "variables", "active", "pinned", "archived", "created_at", "updated_at", "expires_at", "source"];
+
+/// Parse a comma-separated `--fields` list, rejecting names that aren't in `valid`
+pub fn parse_fields(list: &str, valid: &[&str]) -> Result<Vec<String>, String> {
+    let fields: Vec<String> = list.split(',')
+        .map(|field| field.trim().to_lowercase())
+        .filter(|field| !field.is_empty())
+        .collect();
+    if fields.is_empty() {
+        return Err(format!("No fields given. Available fields: {}", valid.join(", ")));
+    }
+    if let Some(unknown) = fields.iter().find(|field| !valid.contains(&field.as_str())) {
+        return Err(format!("Unknown field '{}'. Available fields: {}", unknown, valid.join(", ")));
+    }
+    Ok(fields)
+}
+
+/// Keep only `fields` in each object of a JSON array, leaving anything else as it is
+///
+/// The reports are serialized in full first, so the schema stays the same with or without `--fields`.
+pub fn select_fields(items: &mut serde_json::Value, fields: &[String]) {
+    let Some(items) = items.as_array_mut() else { return };
+    for item in items.iter_mut().filter_map(serde_json::Value::as_object_mut) {
+        item.retain(|key, _| fields.contains(key));
+    }
+}
+
+/// Output of `current --json`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CurrentReport {
+    pub active_config: Option<String>,
+    pub activated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The configuration was modified after it was activated
+    pub stale: bool,
+    /// The shell envswitch detects, which `use` generates commands for
+    pub shell: String,
+}
+
+/// Compare each variable's current value with the configuration for `status --json`
+pub fn variable_status_reports(
+    output: &OutputContext,
+    statuses: &[EnvVarStatus],
+    expected_variables: &HashMap<String, String>,
+    list_ops: &HashMap<String, ListOp>,
+    provenance: &VariableProvenance,
+) -> Vec<VariableStatusReport> {
+    let mut reports: Vec<_> = statuses.iter().map(|status| {
+        let (expected_value, sensitive) = expected_status_value(&status.key, expected_variables);
+        let matches = expected_value.as_ref().is_some_and(|expected| {
+            value_matches_expected(status.value.as_deref(), expected, list_ops.get(&status.key).copied())
+        });
+        let current = status.value.as_ref()
+            .map(|value| if sensitive { mask_sensitive_value(output, value) } else { value.clone() });
+        let source = matches.then(|| provenance.source.to_string());
+        let current_matches = if matches { Vec::new() } else { provenance.other_configs(status) };
+        VariableStatusReport { key: status.key.clone(), current, matches, source, current_matches }
+    }).collect();
+    reports.sort_by(|a, b| a.key.cmp(&b.key));
+    reports
+}
+
 /// Display status in list format
+///
+/// Large sets of variables are grouped by prefix unless `flat` is set.
 pub fn display_status_list(
+    output: &OutputContext,
     statuses: &[EnvVarStatus],
     expected_variables: &HashMap<String, String>,
+    list_ops: &HashMap<String, ListOp>,
+    provenance: &VariableProvenance,
     verbose: bool,
+    flat: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Environment Variable Status:");
+    outln!(output, "Environment Variable Status:");
     
-    for status in statuses {
-        let expected_value = expected_variables.get(&status.key);
-        let matches_expected = expected_value.map_or(false, |expected| {
-            status.value.as_deref() == Some(expected)
+    let by_key: HashMap<&str, &EnvVarStatus> = statuses.iter().map(|status| (status.key.as_str(), status)).collect();
+    print_grouped(output, by_key.keys().copied(), flat, "  ", |key, indent| {
+        let status = by_key[key];
+        let (expected_value, sensitive) = expected_status_value(&status.key, expected_variables);
+        let expected_value = expected_value.as_ref();
+        let matches_expected = expected_value.is_some_and(|expected| {
+            value_matches_expected(status.value.as_deref(), expected, list_ops.get(&status.key).copied())
         });
-        let status_symbol = if matches_expected { "✓" } else { "✗" };
+        let status_symbol = output.mark(matches_expected);
         
-        println!("  {} {}", status_symbol, status.key);
+        if matches_expected {
+            outln!(output, "{}{} {} (from '{}')", indent, status_symbol, status.key, provenance.source);
+        } else {
+            outln!(output, "{}{} {}", indent, status_symbol, status.key);
+        }
         
         if verbose || !matches_expected {
             if let Some(current) = &status.value {
-                let display_current = if is_sensitive_key(&status.key) {
-                    mask_sensitive_value(current)
+                let display_current = if sensitive {
+                    mask_sensitive_value(output, current)
                 } else {
                     current.to_string()
                 };
-                println!("    Current: {}", display_current);
+                outln!(output, "{}  Current: {}", indent, display_current);
             } else {
-                println!("    Current: (not set)");
+                outln!(output, "{}  Current: (not set)", indent);
             }
             
             if let Some(expected) = expected_value {
-                let display_expected = if is_sensitive_key(&status.key) {
-                    mask_sensitive_value(expected)
+                let display_expected = if sensitive {
+                    mask_sensitive_value(output, expected)
                 } else {
                     expected.clone()
                 };
-                println!("    Expected: {}", display_expected);
+                outln!(output, "{}  Expected: {}", indent, display_expected);
             }
         }
-    }
+        
+        if !matches_expected {
+            let others = provenance.other_configs(status);
+            if !others.is_empty() {
+                outln!(output, "{}  ({})", indent, other_configs_note(&others));
+            }
+        }
+    });
     
     Ok(())
 }
 
 /// Display status in table format
 pub fn display_status_table(
+    output: &OutputContext,
     statuses: &[EnvVarStatus],
     expected_variables: &HashMap<String, String>,
+    list_ops: &HashMap<String, ListOp>,
+    provenance: &VariableProvenance,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if statuses.is_empty() {
@@ -330,60 +800,205 @@ pub fn display_status_table(
     let max_value_width = 25;
     
     // Print header
-    println!("{:<width_key$} {:<width_value$} {:<width_value$} {:<6}",
+    outln!(output, "{:<width_key$} {:<width_value$} {:<width_value$} {:<6} Source",
         "Variable", "Current", "Expected", "Match",
         width_key = max_key_width,
         width_value = max_value_width
     );
     
-    println!("{} {} {} {}",
+    outln!(output, "{} {} {} {} {}",
         "-".repeat(max_key_width),
         "-".repeat(max_value_width),
         "-".repeat(max_value_width),
-        "-".repeat(6)
+        "-".repeat(6),
+        "-".repeat(12)
     );
     
     // Print variables
     for status in statuses {
+        let (expected_value, sensitive) = expected_status_value(&status.key, expected_variables);
+        
         let current_display = if let Some(ref current) = status.value {
-            if is_sensitive_key(&status.key) {
-                mask_sensitive_value(current)
-            } else if current.len() > max_value_width - 2 {
-                format!("{}...", &current[..max_value_width - 5])
+            if sensitive {
+                mask_sensitive_value(output, current)
             } else {
-                current.to_string()
+                truncate_to_width(current, max_value_width - 2)
             }
         } else {
             "(not set)".to_string()
         };
         
-        let expected_display = if let Some(expected) = expected_variables.get(&status.key) {
-            if is_sensitive_key(&status.key) {
-                mask_sensitive_value(expected)
-            } else if expected.len() > max_value_width - 2 {
-                format!("{}...", &expected[..max_value_width - 5])
+        let expected_display = if let Some(expected) = &expected_value {
+            if sensitive {
+                mask_sensitive_value(output, expected)
             } else {
-                expected.clone()
+                truncate_to_width(expected, max_value_width - 2)
             }
         } else {
             "(none)".to_string()
         };
         
-        let expected_value = expected_variables.get(&status.key);
-        let matches_expected = expected_value.map_or(false, |expected| {
-            status.value.as_deref() == Some(expected)
+        let matches_expected = expected_value.as_ref().is_some_and(|expected| {
+            value_matches_expected(status.value.as_deref(), expected, list_ops.get(&status.key).copied())
         });
-        let match_symbol = if matches_expected { "✓" } else { "✗" };
+        let match_symbol = output.mark(matches_expected);
+        let source = if matches_expected {
+            provenance.source.to_string()
+        } else {
+            let others = provenance.other_configs(status);
+            if others.is_empty() { "-".to_string() } else { others.join(", ") }
+        };
         
-        println!("{:<width_key$} {:<width_value$} {:<width_value$} {:<6}",
+        outln!(output, "{:<width_key$} {} {} {} {}",
             status.key,
-            current_display,
-            expected_display,
-            match_symbol,
-            width_key = max_key_width,
-            width_value = max_value_width
+            pad_to_width(&current_display, max_value_width),
+            pad_to_width(&expected_display, max_value_width),
+            pad_to_width(match_symbol, 6),
+            source,
+            width_key = max_key_width
         );
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Display a verification report on stderr so it stays out of eval'd output
+pub fn display_verification_report(output: &OutputContext, alias: &str, report: &VerificationReport) {
+    if report.results.is_empty() {
+        eoutln!(output, "ℹ️  Nothing to verify in '{}' (no tokens or URLs)", alias);
+        return;
+    }
+    
+    eoutln!(output, "🔍 Verification of '{}':", alias);
+    for result in &report.results {
+        let symbol = match result.status {
+            CheckStatus::Passed => output.mark(true),
+            CheckStatus::Failed => output.mark(false),
+            CheckStatus::Skipped => "-",
+        };
+        eoutln!(output, "  {} {} {}: {}", symbol, result.key, result.check, result.detail);
+    }
+    
+    if report.has_failures() {
+        eoutln!(output, "⚠️  {} check(s) failed", report.failure_count());
+    } else {
+        eoutln!(output, "✅ All checks passed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn snapshot_rows() -> (Vec<(String, Option<EnvConfig>)>, ConfigStore) {
+        let created = chrono::Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let updated = chrono::Utc::now() - chrono::Duration::hours(3);
+        let config = |alias: &str, description: Option<&str>, pinned: bool| {
+            let variables = [("API_URL".to_string(), "https://example.com".to_string())].into_iter().collect();
+            let mut config = EnvConfig::new(alias.to_string(), variables, description.map(str::to_string)).unwrap();
+            config.created_at = created;
+            config.updated_at = updated;
+            config.pinned = pinned;
+            config
+        };
+        let rows = vec![
+            ("work".to_string(), Some(config("work", Some("工作环境 🚀"), true))),
+            ("home".to_string(), Some(config("home", None, false))),
+            ("broken".to_string(), None),
+        ];
+        let store = ConfigStore { active_config: Some("work".to_string()), ..ConfigStore::default() };
+        (rows, store)
+    }
+
+    #[test]
+    fn test_configs_table_unicode() {
+        let (rows, store) = snapshot_rows();
+        let lines = configs_table_lines(&rows, &store, ListColumn::DEFAULT, &OutputContext::default(), false);
+        assert_eq!(lines.join("\n"), "\
+Name     Description            Variables   Active   Updated
+-------- ---------------------- ----------- -------- -----------
+★ work   工作环境 🚀            1           ✓        3 hours ago
+home     No description         1                    3 hours ago
+broken   Error loading config   ?                    Unknown");
+    }
+
+    #[test]
+    fn test_configs_table_exact_times() {
+        let (rows, store) = snapshot_rows();
+        let output = OutputContext::default().with_utc(true);
+        let columns = ListColumn::parse_list("name,created").unwrap();
+        let lines = configs_table_lines(&rows[1..2], &store, &columns, &output, false);
+        assert_eq!(lines.join("\n"), "\
+Name   Created
+------ -----------------------
+home   2024-06-01 12:00:00 UTC");
+    }
+
+    #[test]
+    fn test_configs_table_ascii() {
+        let (rows, store) = snapshot_rows();
+        let output = OutputContext::default().with_ascii(true);
+        let columns = ListColumn::parse_list("name,active,description").unwrap();
+        let lines = configs_table_lines(&rows, &store, &columns, &output, false);
+        assert_eq!(lines.join("\n"), "\
+Name     Active   Description
+-------- -------- --------------------
+* work   *        工作环境 🚀
+home              No description
+broken            Error loading config");
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("short", 10), "short");
+        assert_eq!(truncate_to_width("abcdefghijkl", 10), "abcdefg...");
+        assert_eq!(truncate_to_width("环境环境环境环境", 10), "环境环...");
+        assert_eq!(display_width(&truncate_to_width("环境环境环境环境", 10)), 9);
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(ListColumn::parse_list("Name, vars").unwrap(), vec![ListColumn::Name, ListColumn::Vars]);
+        assert!(ListColumn::parse_list("name,tags").unwrap_err().contains("Available columns"));
+        assert!(ListColumn::parse_list(" , ").is_err());
+    }
+
+    #[test]
+    fn test_group_by_prefix() {
+        let groups = group_by_prefix([
+            "AWS_REGION", "ANTHROPIC_MODEL", "PATH", "AWS_ACCESS_KEY_ID", "APP_ENV", "ANTHROPIC_BASE_URL",
+            "AWS_SECRET_ACCESS_KEY", "ANTHROPIC_AUTH_TOKEN", "APP_DEBUG", "_PRIVATE", "AWS_PROFILE",
+        ]);
+        assert_eq!(groups, vec![
+            KeyGroup { prefix: Some("ANTHROPIC"), keys: vec!["ANTHROPIC_AUTH_TOKEN", "ANTHROPIC_BASE_URL", "ANTHROPIC_MODEL"] },
+            KeyGroup { prefix: Some("AWS"), keys: vec!["AWS_ACCESS_KEY_ID", "AWS_PROFILE", "AWS_REGION", "AWS_SECRET_ACCESS_KEY"] },
+            // Two APP_ keys are too few for a group
+            KeyGroup { prefix: None, keys: vec!["APP_DEBUG", "APP_ENV", "PATH", "_PRIVATE"] },
+        ]);
+        
+        // Prefixes are compared exactly, so A and AB stay apart
+        let groups = group_by_prefix(["A_1", "AB_1", "A_2", "A_3"]);
+        assert_eq!(groups, vec![
+            KeyGroup { prefix: Some("A"), keys: vec!["A_1", "A_2", "A_3"] },
+            KeyGroup { prefix: None, keys: vec!["AB_1"] },
+        ]);
+        assert!(group_by_prefix([]).is_empty());
+    }
+    
+    #[test]
+    fn test_small_or_flat_listings_are_not_grouped() {
+        let keys = ["DB_HOST", "DB_PORT", "DB_USER", "API_URL"];
+        assert_eq!(variable_groups(keys, false), vec![
+            KeyGroup { prefix: None, keys: vec!["API_URL", "DB_HOST", "DB_PORT", "DB_USER"] },
+        ]);
+        
+        let many: Vec<String> = (0..12).map(|i| format!("SVC_{:02}", i)).collect();
+        let groups = variable_groups(many.iter().map(String::as_str), false);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].prefix, Some("SVC"));
+        let groups = variable_groups(many.iter().map(String::as_str), true);
+        assert_eq!(groups[0].prefix, None);
+        assert_eq!(groups[0].keys.len(), 12);
+    }
+}
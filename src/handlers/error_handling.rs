@@ -1,70 +1,30 @@
 use std::error::Error;
+use std::io::ErrorKind;
+use crate::error::{AppError, ConfigError, EnvError};
+use crate::types::ConfigPaths;
+use crate::types::constants::{EXIT_CODE_DEFAULT_REPLACE_GUARD, EXIT_CODE_INVALID_DATA, EXIT_CODE_NOT_FOUND, EXIT_CODE_PERMISSION_DENIED};
+use crate::output::OutputContext;
 
-/// Enhanced error handling with user-friendly messages and suggestions
-pub fn handle_error(error: &Box<dyn Error>, verbose: bool) {
-    // Check if this is a known error type that we can provide better messages for
-    if let Some(config_error) = error.downcast_ref::<crate::error::ConfigError>() {
-        eprintln!("❌ {}", config_error.user_message());
-        
-        // Provide additional context based on error type
-        match config_error {
-            crate::error::ConfigError::ConfigNotFound(_) => {
-                eprintln!("💡 Tip: Use 'envswitch list' to see all available configurations");
-                eprintln!("   Or use 'envswitch set <name>' to create a new configuration");
-            }
-            crate::error::ConfigError::FileError(_) => {
-                eprintln!("💡 Tip: Check that you have write permissions to ~/.config/envswitch/");
-                eprintln!("   You can also try running: mkdir -p ~/.config/envswitch");
-            }
-            crate::error::ConfigError::JsonError(_) => {
-                eprintln!("💡 Tip: Your configuration file may be corrupted");
-                eprintln!("   You can backup and recreate it, or restore from a backup");
-                eprintln!("   Use 'envswitch export' to backup current configurations");
-            }
-            crate::error::ConfigError::InvalidConfigName(_) => {
-                eprintln!("💡 Tip: Configuration names should contain only letters, numbers, hyphens, and underscores");
-                eprintln!("   Examples: 'my-config', 'dev_env', 'production123'");
-            }
-            _ => {}
-        }
-    } else if let Some(env_error) = error.downcast_ref::<crate::error::EnvError>() {
-        eprintln!("❌ {}", env_error.user_message());
-        
-        match env_error {
-            crate::error::EnvError::ShellDetectionFailed => {
-                eprintln!("💡 Tip: Try setting your SHELL environment variable:");
-                eprintln!("   export SHELL=/bin/zsh  # or /bin/bash, /usr/bin/fish");
-            }
-            crate::error::EnvError::UnsupportedShell(_) => {
-                eprintln!("💡 Tip: envswitch works best with zsh, bash, or fish");
-                eprintln!("   Generic export commands will be used for your shell");
-            }
-            _ => {}
-        }
-    } else {
-        // Generic error handling
-        eprintln!("❌ Error: {}", error);
-        
-        // Check for common error patterns and provide suggestions
-        let error_msg = error.to_string().to_lowercase();
-        if error_msg.contains("permission denied") {
-            eprintln!("💡 Tip: Check file permissions and try running with appropriate privileges");
-        } else if error_msg.contains("not found") {
-            eprintln!("💡 Tip: Make sure the file or configuration exists");
-            eprintln!("   Use 'envswitch list' to see available configurations");
-        } else if error_msg.contains("already exists") {
-            eprintln!("💡 Tip: Use a different name or use 'envswitch edit' to modify existing configuration");
+/// Print an error with suggestions for its type and return the exit status for it
+pub fn handle_error(output: &OutputContext, error: &(dyn Error + 'static), verbose: bool) -> i32 {
+    eoutln!(output, "❌ {}", error_message(error));
+
+    let suggestions = error_suggestions(error);
+    if let Some((first, rest)) = suggestions.split_first() {
+        eoutln!(output, "💡 Tip: {}", first);
+        for suggestion in rest {
+            eoutln!(output, "   {}", suggestion);
         }
     }
-    
+
     if verbose {
-        eprintln!("\n🔍 Debug information:");
-        eprintln!("Error type: {}", std::any::type_name_of_val(&**error));
-        eprintln!("Full error chain:");
-        let mut current_error: &dyn Error = &**error;
+        eoutln!(output, "\n🔍 Debug information:");
+        eoutln!(output, "Error type: {}", std::any::type_name_of_val(error));
+        eoutln!(output, "Full error chain:");
+        let mut current_error: &dyn Error = error;
         let mut level = 0;
         loop {
-            eprintln!("  {}: {}", level, current_error);
+            eoutln!(output, "  {}: {}", level, current_error);
             match current_error.source() {
                 Some(source) => {
                     current_error = source;
@@ -74,6 +34,289 @@ pub fn handle_error(error: &Box<dyn Error>, verbose: bool) {
             }
         }
     }
-    
-    eprintln!("\n📚 For more help, use 'envswitch --help' or 'envswitch <command> --help'");
-}
\ No newline at end of file
+
+    eoutln!(output, "\n📚 For more help, use 'envswitch --help' or 'envswitch <command> --help'");
+    exit_code(error)
+}
+
+/// The error as shown to the user, with the friendlier wording of typed errors
+fn error_message(error: &(dyn Error + 'static)) -> String {
+    if let Some(config_error) = error.downcast_ref::<ConfigError>() {
+        config_error.user_message()
+    } else if let Some(env_error) = error.downcast_ref::<EnvError>() {
+        env_error.user_message()
+    } else {
+        format!("Error: {}", error)
+    }
+}
+
+/// Exit status for an error: not found, permission and invalid data errors get their own
+pub fn exit_code(error: &(dyn Error + 'static)) -> i32 {
+    if let Some(config_error) = error.downcast_ref::<ConfigError>() {
+        match config_error {
+            ConfigError::ConfigNotFound { .. } => EXIT_CODE_NOT_FOUND,
+            ConfigError::PermissionDenied(_) | ConfigError::InvalidConfigDir | ConfigError::ReadOnly(_) => EXIT_CODE_PERMISSION_DENIED,
+            ConfigError::FileError(io_error) => io_exit_code(io_error),
+            ConfigError::JsonError(_) | ConfigError::ValidationError(_) | ConfigError::InvalidConfigName(_)
+            | ConfigError::StrictWarnings(_) => EXIT_CODE_INVALID_DATA,
+            ConfigError::ReplaceGuard { .. } => EXIT_CODE_DEFAULT_REPLACE_GUARD,
+            _ => 1,
+        }
+    } else if let Some(app_error) = error.downcast_ref::<AppError>() {
+        match app_error {
+            AppError::Config(config_error) => exit_code(config_error),
+            _ => 1,
+        }
+    } else if let Some(io_error) = error.downcast_ref::<std::io::Error>() {
+        io_exit_code(io_error)
+    } else {
+        1
+    }
+}
+
+fn io_exit_code(error: &std::io::Error) -> i32 {
+    match error.kind() {
+        ErrorKind::PermissionDenied => EXIT_CODE_PERMISSION_DENIED,
+        _ => 1,
+    }
+}
+
+/// Hints for fixing an error, chosen by its type and the path, alias or command it carries
+///
+/// Errors that are only a message get none; their message is expected to say what to do.
+pub fn error_suggestions(error: &(dyn Error + 'static)) -> Vec<String> {
+    if let Some(config_error) = error.downcast_ref::<ConfigError>() {
+        config_suggestions(config_error)
+    } else if let Some(env_error) = error.downcast_ref::<EnvError>() {
+        env_suggestions(env_error)
+    } else if let Some(app_error) = error.downcast_ref::<AppError>() {
+        match app_error {
+            AppError::Config(config_error) => config_suggestions(config_error),
+            AppError::Environment(env_error) => env_suggestions(env_error),
+            AppError::CliError(_) => vec!["Run 'envswitch <command> --help' to see the accepted arguments".to_string()],
+            AppError::General(_) => Vec::new(),
+        }
+    } else if let Some(io_error) = error.downcast_ref::<std::io::Error>() {
+        io_suggestions(io_error)
+    } else {
+        Vec::new()
+    }
+}
+
+fn config_suggestions(error: &ConfigError) -> Vec<String> {
+    match error {
+        ConfigError::ConfigNotFound { name, suggestions } => {
+            let mut hints = Vec::new();
+            if !suggestions.is_empty() {
+                hints.push(format!("Did you mean: {}?", suggestions.join(", ")));
+            }
+            hints.push("Use 'envswitch list' to see all available configurations".to_string());
+            hints.push(format!("Or use 'envswitch set {} -e KEY=value' to create it", name));
+            hints
+        }
+        ConfigError::FileError(io_error) => io_suggestions(io_error),
+        ConfigError::JsonError(_) => vec![
+            "Your configuration file may be corrupted".to_string(),
+            "Run 'envswitch doctor' to check it, or 'envswitch backup list' to find a backup".to_string(),
+            "Restore one with: envswitch backup restore <backup>".to_string(),
+        ],
+        ConfigError::ConfigExists(alias) => vec![
+            format!("Use a different name, or 'envswitch edit {}' to modify the existing configuration", alias),
+        ],
+        ConfigError::InvalidConfigName(_) => vec![
+            "Configuration names should contain only letters, numbers, hyphens, and underscores".to_string(),
+            "Examples: 'my-config', 'dev_env', 'production123'".to_string(),
+        ],
+        ConfigError::InvalidConfigDir => {
+            let mut suggestions = permission_suggestions(&config_dir_display());
+            suggestions.push("Or point envswitch at another directory with ENVSWITCH_CONFIG_DIR".to_string());
+            suggestions
+        }
+        ConfigError::PermissionDenied(path) => permission_suggestions(path),
+        ConfigError::LockFailed { path, .. } => vec![
+            "Another envswitch process may be holding the lock; wait for it to finish".to_string(),
+            format!("If none is running, check that you can write to {}", path),
+        ],
+        ConfigError::ReadOnly(_) => vec![
+            "Commands that only read, such as list, show, status, env and export, still work".to_string(),
+            "To make changes, point envswitch at a writable directory with --config-dir <DIR> or ENVSWITCH_CONFIG_DIR".to_string(),
+        ],
+        ConfigError::MigrationFailed { layout, .. } => vec![
+            "Your configurations were backed up before the migration".to_string(),
+            "Find the backup with 'envswitch backup list' and restore it with: envswitch backup restore <backup>".to_string(),
+            format!("Then retry with: envswitch migrate-storage {}", layout),
+        ],
+        ConfigError::ReplaceGuard { alias, .. } => vec![
+            format!("Leave out --replace to keep them, or confirm with: envswitch set {} ... --replace --yes", alias),
+            "Raise the limit or turn it off (0) with: envswitch settings set replace-guard <keys>".to_string(),
+        ],
+        ConfigError::EnvError(env_error) => env_suggestions(env_error),
+        ConfigError::ValidationError(_) => Vec::new(),
+        ConfigError::StrictWarnings(_) => vec![
+            "Fix the warnings above, or run without --strict to accept them".to_string(),
+            "If the strict setting is on, turn it off with: envswitch settings set strict false".to_string(),
+        ],
+    }
+}
+
+fn env_suggestions(error: &EnvError) -> Vec<String> {
+    match error {
+        EnvError::ShellDetectionFailed => vec![
+            "Try setting your SHELL environment variable:".to_string(),
+            "export SHELL=/bin/zsh  # or /bin/bash, /usr/bin/fish".to_string(),
+        ],
+        EnvError::UnsupportedShell(_) => vec![
+            "envswitch works best with zsh, bash, or fish".to_string(),
+            "Generic export commands will be used for your shell".to_string(),
+        ],
+        EnvError::ValueFileUnreadable { path, .. } => vec![
+            format!("Check that the file exists and is readable: ls -l {}", path),
+        ],
+        EnvError::ValueCommandFailed { command, .. } => vec![
+            format!("Run the command yourself to see what is wrong: {}", command),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+fn io_suggestions(error: &std::io::Error) -> Vec<String> {
+    match error.kind() {
+        ErrorKind::PermissionDenied => permission_suggestions(&config_dir_display()),
+        ErrorKind::NotFound => vec![
+            "Check that the path is correct and the directory exists".to_string(),
+            "Use absolute paths if relative paths aren't working".to_string(),
+        ],
+        ErrorKind::StorageFull => vec![
+            "Check available disk space: df -h".to_string(),
+            "Old backups can be removed from the backups directory (see 'envswitch paths')".to_string(),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+fn permission_suggestions(path: &str) -> Vec<String> {
+    vec![
+        format!("Check that you have write permissions to {}", path),
+        format!("Inspect them with: ls -la {}", path),
+    ]
+}
+
+fn config_dir_display() -> String {
+    ConfigPaths::resolve_config_dir()
+        .map(|(dir, _)| dir.display().to_string())
+        .unwrap_or_else(|_| "~/.config/envswitch".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hints(error: impl Error + 'static) -> String {
+        error_suggestions(&error).join("\n")
+    }
+
+    #[test]
+    fn test_not_found_suggestions_use_alias() {
+        let error = ConfigError::not_found("parse".to_string());
+        let hints = hints(error);
+        assert!(hints.contains("envswitch list"));
+        assert!(hints.contains("envswitch set parse"));
+        // A configuration named like a parse error must not get JSON advice
+        assert!(!hints.contains("corrupted"));
+        assert_eq!(exit_code(&ConfigError::not_found("x".to_string())), EXIT_CODE_NOT_FOUND);
+        assert!(!hints.contains("Did you mean"));
+    }
+
+    #[test]
+    fn test_not_found_suggests_similar_names() {
+        let error = ConfigError::ConfigNotFound { name: "prd".to_string(), suggestions: vec!["prod".to_string()] };
+        assert_eq!(hints(error).lines().next(), Some("Did you mean: prod?"));
+    }
+
+    #[test]
+    fn test_json_error_suggestions() {
+        let json_error = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let error = ConfigError::JsonError(json_error);
+        assert_eq!(exit_code(&error), EXIT_CODE_INVALID_DATA);
+        let hints = hints(error);
+        assert!(hints.contains("corrupted"));
+        assert!(hints.contains("envswitch backup restore"));
+    }
+
+    #[test]
+    fn test_permission_suggestions() {
+        let error = ConfigError::PermissionDenied("/etc/envswitch".to_string());
+        assert_eq!(exit_code(&error), EXIT_CODE_PERMISSION_DENIED);
+        assert!(hints(error).contains("ls -la /etc/envswitch"));
+
+        let io_error = ConfigError::FileError(std::io::Error::from(ErrorKind::PermissionDenied));
+        assert_eq!(exit_code(&io_error), EXIT_CODE_PERMISSION_DENIED);
+        assert!(hints(io_error).contains("write permissions"));
+        assert!(hints(ConfigError::InvalidConfigDir).contains("ENVSWITCH_CONFIG_DIR"));
+    }
+
+    #[test]
+    fn test_io_suggestions() {
+        assert!(hints(std::io::Error::from(ErrorKind::NotFound)).contains("path is correct"));
+        assert!(hints(std::io::Error::from(ErrorKind::StorageFull)).contains("df -h"));
+        assert_eq!(exit_code(&std::io::Error::from(ErrorKind::NotFound)), 1);
+    }
+
+    #[test]
+    fn test_lock_suggestions() {
+        let error = ConfigError::LockFailed { path: "/tmp/cfg/.lock".to_string(), reason: "busy".to_string() };
+        let hints = hints(error);
+        assert!(hints.contains("Another envswitch process"));
+        assert!(hints.contains("/tmp/cfg/.lock"));
+    }
+
+    #[test]
+    fn test_read_only_suggestions() {
+        let error = ConfigError::ReadOnly("/etc/envswitch".to_string());
+        assert!(error.user_message().contains("'/etc/envswitch' is read-only"));
+        assert_eq!(exit_code(&error), EXIT_CODE_PERMISSION_DENIED);
+        assert!(hints(error).contains("--config-dir"));
+    }
+
+    #[test]
+    fn test_replace_guard_suggestions() {
+        let error = ConfigError::ReplaceGuard { alias: "work".to_string(), removed: 12 };
+        assert_eq!(exit_code(&error), EXIT_CODE_DEFAULT_REPLACE_GUARD);
+        assert!(hints(error).contains("--replace --yes"));
+    }
+
+    #[test]
+    fn test_migration_suggestions() {
+        let error = ConfigError::MigrationFailed { layout: "split".to_string(), reason: "disk full".to_string() };
+        let hints = hints(error);
+        assert!(hints.contains("envswitch backup restore"));
+        assert!(hints.contains("envswitch migrate-storage split"));
+    }
+
+    #[test]
+    fn test_env_suggestions() {
+        assert!(hints(EnvError::ShellDetectionFailed).contains("export SHELL="));
+        let error = EnvError::ValueCommandFailed {
+            key: "TOKEN".to_string(),
+            command: "op read op://vault/token".to_string(),
+            stderr: "not signed in".to_string(),
+        };
+        assert!(hints(error).contains("op read op://vault/token"));
+        let nested = ConfigError::EnvError(EnvError::ValueFileUnreadable {
+            key: "KEY".to_string(),
+            path: "/run/secret".to_string(),
+            reason: "missing".to_string(),
+        });
+        assert!(hints(nested).contains("ls -l /run/secret"));
+    }
+
+    #[test]
+    fn test_plain_messages_get_no_suggestions() {
+        let error: Box<dyn Error> = "Configuration 'json' could not be parsed".into();
+        assert!(error_suggestions(&*error).is_empty());
+        assert_eq!(exit_code(&*error), 1);
+        let wrapped = AppError::Config(ConfigError::not_found("x".to_string()));
+        assert_eq!(exit_code(&wrapped), EXIT_CODE_NOT_FOUND);
+        assert!(hints(wrapped).contains("envswitch set x"));
+    }
+}
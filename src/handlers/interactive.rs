@@ -1,184 +1,846 @@
+use crate::output::OutputContext;
 use std::collections::HashMap;
-use std::io::{self, Write};
+use std::io;
 
-/// Interactive mode to collect environment variables
-pub fn interactive_env_input(verbose: bool) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
-    let mut variables = HashMap::new();
+/// Read a line from stdin without echoing it when stdin is a terminal
+pub fn read_hidden_line(output: &OutputContext) -> io::Result<String> {
+    output.execution.check_prompt("a hidden value")?;
+    #[cfg(unix)]
+    if io::IsTerminal::is_terminal(&io::stdin()) {
+        let mut original = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: tcgetattr fills in the struct when it returns 0
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, original.as_mut_ptr()) } == 0 {
+            let original = unsafe { original.assume_init() };
+            let mut hidden = original;
+            hidden.c_lflag &= !libc::ECHO;
+            hidden.c_lflag |= libc::ECHONL;
+            
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &hidden) };
+            let mut line = String::new();
+            let result = io::stdin().read_line(&mut line);
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &original) };
+            
+            result?;
+            return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+        }
+    }
     
-    println!("Interactive mode: Enter environment variables (press Enter with empty name to finish)");
-    println!("Format: KEY=VALUE or just KEY (you'll be prompted for the value)");
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Ask for the values of a template's placeholders, on stderr so stdout stays eval-safe
+///
+/// Sensitive values are read without echo. The answers only apply to this activation.
+pub fn prompt_template_values(output: &OutputContext, alias: &str, keys: &[String]) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    output.execution.check_prompt(&format!("the placeholders of '{}'", alias))?;
+    eoutln!(output, "🧩 '{}' is a template; enter the values for this activation (they are not saved)", alias);
+    let mut values = HashMap::new();
+    for key in keys {
+        output.prompt(&format!("{}: ", key))?;
+        let value = if crate::utils::is_sensitive_key(key) {
+            read_hidden_line(output)?
+        } else {
+            let mut line = String::new();
+            io::stdin().read_line(&mut line)?;
+            line
+        };
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            return Err(format!(
+                "No value entered for {}; '{}' was not activated.\n💡 Save the values instead with: envswitch set {} -e {}=value",
+                key, alias, alias, key
+            ).into());
+        }
+        crate::types::validation::validate_env_var(key, &value)?;
+        values.insert(key.clone(), value);
+    }
+    Ok(values)
+}
+
+/// Where interactive flows get their answers from, so they can be driven by a script in tests
+pub trait Prompter {
+    /// Show `prompt` and read one line without its line break; None at the end of input
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>>;
     
-    loop {
-        print!("Variable (or press Enter to finish): ");
-        io::stdout().flush()?;
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim();
+    /// Like [`Prompter::read_line`], for values that shouldn't be echoed
+    fn read_secret(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        self.read_line(prompt)
+    }
+    
+    /// Ask a yes/no question; an empty answer or the end of input gives the default
+    fn confirm(&mut self, output: &OutputContext, message: &str, default_yes: bool) -> io::Result<bool> {
+        let choices = if default_yes { "[Y/n]" } else { "[y/N]" };
+        loop {
+            let answer = match self.read_line(&format!("{} {}: ", message, choices))? {
+                Some(answer) => answer.trim().to_lowercase(),
+                None => return Ok(default_yes),
+            };
+            match answer.as_str() {
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                "" => return Ok(default_yes),
+                _ => outln!(output, "Please enter 'y' for yes or 'n' for no."),
+            }
+        }
+    }
+}
+
+/// Prompts on stderr through the output context, answers from stdin
+pub struct TerminalPrompter<'a> {
+    output: &'a OutputContext,
+}
+
+impl<'a> TerminalPrompter<'a> {
+    /// Refuses to prompt when `output` says nobody can answer
+    pub fn new(output: &'a OutputContext) -> Self {
+        Self { output }
+    }
+}
+
+impl Prompter for TerminalPrompter<'_> {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        self.output.execution.check_prompt(prompt)?;
+        self.output.prompt(prompt)?;
         
-        if input.is_empty() {
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+    
+    fn read_secret(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        self.output.execution.check_prompt(prompt)?;
+        self.output.prompt(prompt)?;
+        read_hidden_line(self.output).map(Some)
+    }
+}
+
+/// Answers from a fixed list, ending the input when it runs out; records every prompt shown
+#[derive(Debug, Default)]
+pub struct ScriptedPrompter {
+    answers: std::collections::VecDeque<String>,
+    pub prompts: Vec<String>,
+    /// The prompts that asked for hidden input
+    pub secret_prompts: Vec<String>,
+}
+
+impl ScriptedPrompter {
+    pub fn new(answers: &[&str]) -> Self {
+        Self {
+            answers: answers.iter().map(|answer| answer.to_string()).collect(),
+            prompts: Vec::new(),
+            secret_prompts: Vec::new(),
+        }
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        self.prompts.push(prompt.to_string());
+        Ok(self.answers.pop_front())
+    }
+    
+    fn read_secret(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        self.secret_prompts.push(prompt.to_string());
+        self.read_line(prompt)
+    }
+}
+
+/// Value as shown in previews: masked for sensitive keys
+fn preview_value(output: &OutputContext, key: &str, value: &str) -> String {
+    if crate::utils::is_sensitive_key(key) {
+        crate::utils::mask_sensitive_value(output, value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Paste KEY=VALUE lines into `variables`, ending with an empty line or the end of input
+///
+/// The lines are parsed like a .env file. Invalid lines are listed and skipped; the
+/// additions and overwrites are previewed with sensitive values masked and only applied
+/// after confirmation. Returns whether anything changed.
+pub fn paste_variables(
+    output: &OutputContext,
+    prompter: &mut dyn Prompter,
+    variables: &mut HashMap<String, String>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    outln!(output, "Paste KEY=VALUE lines, then an empty line to finish:");
+    let mut content = String::new();
+    while let Some(line) = prompter.read_line("")? {
+        if line.trim().is_empty() {
             break;
         }
+        content.push_str(&line);
+        content.push('\n');
+    }
+    
+    let parsed = crate::utils::parse_env_content(&content);
+    for (line_number, reason) in &parsed.skipped {
+        outln!(output, "⚠️  Skipped line {}: {}", line_number, reason);
+    }
+    
+    let mut changes: Vec<(String, String)> = parsed.variables.into_iter()
+        .filter(|(key, value)| variables.get(key) != Some(value))
+        .collect();
+    if changes.is_empty() {
+        outln!(output, "⏭️  Nothing to change");
+        return Ok(false);
+    }
+    changes.sort();
+    
+    outln!(output, "📋 Pasted variables:");
+    for (key, value) in &changes {
+        match variables.get(key) {
+            Some(old) => outln!(output, "   ~ {} = {} (was {})", key, preview_value(output, key, value), preview_value(output, key, old)),
+            None => outln!(output, "   + {} = {}", key, preview_value(output, key, value)),
+        }
+    }
+    
+    if !prompter.confirm(output, &format!("Apply {} changes?", changes.len()), true)? {
+        outln!(output, "❌ Paste discarded");
+        return Ok(false);
+    }
+    
+    let count = changes.len();
+    variables.extend(changes);
+    outln!(output, "✅ Applied {} pasted variables", count);
+    Ok(true)
+}
+
+/// The parts of a configuration the interactive editor works on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EditableConfig {
+    pub variables: HashMap<String, String>,
+    pub description: Option<String>,
+    pub notes: Option<String>,
+    pub links: Vec<String>,
+    /// Why a variable is set, by variable name
+    pub comments: HashMap<String, String>,
+}
+
+/// Menu-driven editor behind `edit` and `set -i`
+///
+/// Lists the variables with sensitive values masked, reads sensitive values without echo and
+/// asks before dropping unsaved changes. Notes and links are only offered after
+/// [`VariableEditor::with_details`].
+pub struct VariableEditor {
+    config: EditableConfig,
+    original: EditableConfig,
+    details: bool,
+}
+
+/// What the editor does after an action
+enum EditorStep {
+    Continue,
+    Save,
+    Quit,
+}
+
+impl VariableEditor {
+    pub fn new(config: EditableConfig) -> Self {
+        Self {
+            original: config.clone(),
+            config,
+            details: false,
+        }
+    }
+    
+    /// Also offer editing the notes and links
+    pub fn with_details(mut self) -> Self {
+        self.details = true;
+        self
+    }
+    
+    /// Run the menu until the user saves or quits
+    ///
+    /// Returns the edited configuration on save, None when the user quits or the input ends.
+    pub fn run(mut self, output: &OutputContext, prompter: &mut dyn Prompter) -> Result<Option<EditableConfig>, Box<dyn std::error::Error>> {
+        loop {
+            self.show(output);
+            let Some(action) = prompter.read_line("> ")? else {
+                return Ok(Self::cancelled(output));
+            };
+            let step = match action.trim().to_lowercase().as_str() {
+                "a" | "add" => self.add(output, prompter)?,
+                "e" | "edit" => self.edit(output, prompter)?,
+                "d" | "delete" => self.delete(output, prompter)?,
+                "c" | "comment" => self.comment(output, prompter)?,
+                "p" | "paste" => {
+                    outln!(output);
+                    paste_variables(output, prompter, &mut self.config.variables)?;
+                    EditorStep::Continue
+                }
+                "desc" | "description" => {
+                    outln!(output);
+                    match Self::read_text(output, prompter, "description", "Description", self.config.description.as_deref())? {
+                        Some(description) => {
+                            self.config.description = description;
+                            EditorStep::Continue
+                        }
+                        None => EditorStep::Quit,
+                    }
+                }
+                "n" | "notes" if self.details => {
+                    outln!(output);
+                    match Self::read_text(output, prompter, "notes", "Notes", self.config.notes.as_deref())? {
+                        Some(notes) => {
+                            self.config.notes = notes;
+                            EditorStep::Continue
+                        }
+                        None => EditorStep::Quit,
+                    }
+                }
+                "l" | "links" if self.details => self.edit_links(output, prompter)?,
+                "s" | "save" => {
+                    if self.config.variables.is_empty()
+                        && !prompter.confirm(output, "⚠️  Configuration has no variables. Save anyway?", false)?
+                    {
+                        outln!(output, "❌ Save cancelled. Add some variables first.");
+                        EditorStep::Continue
+                    } else {
+                        EditorStep::Save
+                    }
+                }
+                "q" | "quit" => {
+                    if self.config != self.original
+                        && !prompter.confirm(output, "You have unsaved changes. Quit anyway?", false)?
+                    {
+                        EditorStep::Continue
+                    } else {
+                        EditorStep::Quit
+                    }
+                }
+                _ => {
+                    outln!(output, "❌ Invalid option. Please choose from the available actions.");
+                    EditorStep::Continue
+                }
+            };
+            
+            match step {
+                EditorStep::Continue => outln!(output),
+                EditorStep::Save => return Ok(Some(self.config)),
+                EditorStep::Quit => return Ok(Self::cancelled(output)),
+            }
+        }
+    }
+    
+    fn show(&self, output: &OutputContext) {
+        if self.config.variables.is_empty() {
+            outln!(output, "📋 Current variables: (none)");
+        } else {
+            outln!(output, "📋 Current variables:");
+            for (i, key) in self.sorted_keys().iter().enumerate() {
+                let comment = self.config.comments.get(*key).map(|comment| format!("  # {}", comment.replace('\n', " "))).unwrap_or_default();
+                outln!(output, "   {}. {} = {}{}", i + 1, key, preview_value(output, key, &self.config.variables[*key]), comment);
+            }
+        }
         
-        let (key, value) = if let Some(eq_pos) = input.find('=') {
-            // KEY=VALUE format
-            let key = input[..eq_pos].trim().to_string();
-            let value = input[eq_pos + 1..].trim().to_string();
-            (key, value)
+        outln!(output);
+        outln!(output, "Actions:");
+        outln!(output, "   [a]dd     - Add a new variable");
+        outln!(output, "   [e]dit    - Edit an existing variable");
+        outln!(output, "   [d]elete  - Delete a variable");
+        outln!(output, "   [c]omment - Note why a variable is set");
+        outln!(output, "   [p]aste   - Paste KEY=VALUE lines");
+        outln!(output, "   [desc]    - Edit description");
+        if self.details {
+            outln!(output, "   [n]otes   - Edit notes");
+            outln!(output, "   [l]inks   - Edit links");
+        }
+        outln!(output, "   [s]ave    - Save changes and exit");
+        outln!(output, "   [q]uit    - Quit without saving");
+        outln!(output);
+    }
+    
+    fn sorted_keys(&self) -> Vec<&String> {
+        let mut keys: Vec<&String> = self.config.variables.keys().collect();
+        keys.sort();
+        keys
+    }
+    
+    /// Read a value, without echo for sensitive keys
+    fn read_value(prompter: &mut dyn Prompter, key: &str, prompt: &str) -> io::Result<Option<String>> {
+        if crate::utils::is_sensitive_key(key) {
+            prompter.read_secret(prompt)
         } else {
-            // Just KEY, prompt for value
-            let key = input.to_string();
-            print!("Value for '{}': ", key);
-            io::stdout().flush()?;
-            
-            let mut value = String::new();
-            io::stdin().read_line(&mut value)?;
-            let value = value.trim().to_string();
-            (key, value)
+            prompter.read_line(prompt)
+        }
+    }
+    
+    /// Ask for an existing variable by name or by its number in the list
+    fn select(&self, output: &OutputContext, prompter: &mut dyn Prompter, action: &str) -> io::Result<Option<Option<String>>> {
+        let Some(answer) = prompter.read_line(&format!("Variable to {} (name or number): ", action))? else {
+            return Ok(None);
+        };
+        let answer = answer.trim();
+        let keys = self.sorted_keys();
+        let key = match answer.parse::<usize>() {
+            Ok(number) if (1..=keys.len()).contains(&number) => Some(keys[number - 1].clone()),
+            _ => self.config.variables.contains_key(answer).then(|| answer.to_string()),
+        };
+        if key.is_none() {
+            outln!(output, "❌ Variable '{}' not found.", answer);
+        }
+        Ok(Some(key))
+    }
+    
+    fn add(&mut self, output: &OutputContext, prompter: &mut dyn Prompter) -> Result<EditorStep, Box<dyn std::error::Error>> {
+        outln!(output);
+        let Some(answer) = prompter.read_line("Variable name (or KEY=VALUE): ")? else {
+            return Ok(EditorStep::Quit);
+        };
+        let (key, value) = match answer.split_once('=') {
+            Some((key, value)) => (key.trim().to_string(), Some(value.to_string())),
+            None => (answer.trim().to_string(), None),
         };
         
-        if key.is_empty() {
-            println!("Variable name cannot be empty. Try again.");
-            continue;
+        if let Err(e) = crate::error::validate_env_var_name(&key) {
+            outln!(output, "❌ {}", e);
+            return Ok(EditorStep::Continue);
+        }
+        if self.config.variables.contains_key(&key) {
+            outln!(output, "⚠️  Variable '{}' already exists. Use 'edit' to modify it.", key);
+            return Ok(EditorStep::Continue);
         }
         
-        // Validate variable name
-        if !key.chars().next().unwrap_or('0').is_ascii_alphabetic() && !key.starts_with('_') {
-            println!("Variable name '{}' must start with a letter or underscore. Try again.", key);
-            continue;
+        let value = match value {
+            Some(value) => value,
+            None => match Self::read_value(prompter, &key, &format!("Value for '{}': ", key))? {
+                Some(value) => value,
+                None => return Ok(EditorStep::Quit),
+            },
+        };
+        self.config.variables.insert(key.clone(), value);
+        outln!(output, "✅ Added variable '{}'", key);
+        Ok(EditorStep::Continue)
+    }
+    
+    fn edit(&mut self, output: &OutputContext, prompter: &mut dyn Prompter) -> Result<EditorStep, Box<dyn std::error::Error>> {
+        if self.config.variables.is_empty() {
+            outln!(output, "❌ No variables to edit. Use 'add' to create variables first.");
+            return Ok(EditorStep::Continue);
         }
         
-        if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
-            println!("Variable name '{}' can only contain letters, numbers, and underscores. Try again.", key);
-            continue;
+        outln!(output);
+        let key = match self.select(output, prompter, "edit")? {
+            Some(Some(key)) => key,
+            Some(None) => return Ok(EditorStep::Continue),
+            None => return Ok(EditorStep::Quit),
+        };
+        outln!(output, "Current value: {}", preview_value(output, &key, &self.config.variables[&key]));
+        let Some(value) = Self::read_value(prompter, &key, "New value (or press Enter to keep current): ")? else {
+            return Ok(EditorStep::Quit);
+        };
+        
+        if value.is_empty() {
+            outln!(output, "⏭️  Variable '{}' unchanged", key);
+        } else {
+            self.config.variables.insert(key.clone(), value);
+            outln!(output, "✅ Updated variable '{}'", key);
+        }
+        Ok(EditorStep::Continue)
+    }
+    
+    fn delete(&mut self, output: &OutputContext, prompter: &mut dyn Prompter) -> Result<EditorStep, Box<dyn std::error::Error>> {
+        if self.config.variables.is_empty() {
+            outln!(output, "❌ No variables to delete.");
+            return Ok(EditorStep::Continue);
         }
         
-        if variables.contains_key(&key) {
-            println!("Variable '{}' already exists. Overwriting.", key);
+        outln!(output);
+        match self.select(output, prompter, "delete")? {
+            Some(Some(key)) => {
+                self.config.variables.remove(&key);
+                self.config.comments.remove(&key);
+                outln!(output, "✅ Deleted variable '{}'", key);
+                Ok(EditorStep::Continue)
+            }
+            Some(None) => Ok(EditorStep::Continue),
+            None => Ok(EditorStep::Quit),
+        }
+    }
+    
+    fn comment(&mut self, output: &OutputContext, prompter: &mut dyn Prompter) -> Result<EditorStep, Box<dyn std::error::Error>> {
+        if self.config.variables.is_empty() {
+            outln!(output, "❌ No variables to comment on.");
+            return Ok(EditorStep::Continue);
         }
         
-        variables.insert(key.clone(), value.clone());
+        outln!(output);
+        let key = match self.select(output, prompter, "comment on")? {
+            Some(Some(key)) => key,
+            Some(None) => return Ok(EditorStep::Continue),
+            None => return Ok(EditorStep::Quit),
+        };
+        if let Some(current) = self.config.comments.get(&key) {
+            outln!(output, "Current comment: {}", current);
+        }
+        let Some(comment) = prompter.read_line(&format!("Comment for '{}' (or press Enter to remove): ", key))? else {
+            return Ok(EditorStep::Quit);
+        };
+        let comment = comment.trim();
+        if comment.is_empty() {
+            self.config.comments.remove(&key);
+            outln!(output, "✅ Comment on '{}' removed", key);
+        } else {
+            self.config.comments.insert(key.clone(), comment.to_string());
+            outln!(output, "✅ Comment on '{}' updated", key);
+        }
+        Ok(EditorStep::Continue)
+    }
+    
+    fn edit_links(&mut self, output: &OutputContext, prompter: &mut dyn Prompter) -> Result<EditorStep, Box<dyn std::error::Error>> {
+        outln!(output);
+        if !self.config.links.is_empty() {
+            outln!(output, "Current links: {}", self.config.links.join(" "));
+        }
+        let Some(links) = prompter.read_line("Enter links separated by spaces (or press Enter to clear): ")? else {
+            return Ok(EditorStep::Quit);
+        };
+        self.config.links = links.split_whitespace().map(|link| link.to_string()).collect();
         
-        if verbose {
-            let display_value = if value.len() > 30 {
-                format!("{}...", &value[..27])
-            } else {
-                value
-            };
-            println!("Added: {} = {}", key, display_value);
+        if self.config.links.is_empty() {
+            outln!(output, "✅ Links cleared");
         } else {
-            println!("Added: {}", key);
+            outln!(output, "✅ Links updated ({})", self.config.links.len());
         }
+        Ok(EditorStep::Continue)
     }
     
-    if variables.is_empty() {
-        println!("No variables entered.");
-    } else {
-        println!("Collected {} variables.", variables.len());
+    /// Replace a free-text field; an empty answer clears it. The outer None is the end of input.
+    fn read_text(output: &OutputContext, prompter: &mut dyn Prompter, field: &str, label: &str, current: Option<&str>) -> io::Result<Option<Option<String>>> {
+        if let Some(current) = current {
+            outln!(output, "Current {}: {}", field, current);
+        }
+        let Some(text) = prompter.read_line(&format!("Enter new {} (or press Enter to clear): ", field))? else {
+            return Ok(None);
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            outln!(output, "✅ {} cleared", label);
+            Ok(Some(None))
+        } else {
+            outln!(output, "✅ {} updated", label);
+            Ok(Some(Some(text.to_string())))
+        }
     }
     
-    Ok(variables)
+    fn cancelled(output: &OutputContext) -> Option<EditableConfig> {
+        outln!(output, "❌ Edit cancelled. No changes saved.");
+        None
+    }
+}
+
+/// What the `set` wizard collected, once the user confirmed it
+#[derive(Debug)]
+pub struct SetWizardAnswers {
+    pub description: Option<String>,
+    pub variables: HashMap<String, String>,
 }
 
-/// Prompt user for a new variable (key=value)
-pub fn prompt_for_variable() -> Result<Option<(String, String)>, Box<dyn std::error::Error>> {
-    print!("Enter variable name: ");
-    io::stdout().flush()?;
+/// Guide the creation of `alias` when `set` got no variables
+///
+/// Asks for a description, offers the Claude preset (suggested when the name or description
+/// mentions Claude or Anthropic) with a prompt per variable, hidden for sensitive ones, then
+/// takes further KEY=VALUE lines. `current` supplies defaults when the configuration exists.
+/// Returns None when the user cancels, adds nothing or declines the summary.
+pub fn set_wizard(
+    output: &OutputContext,
+    prompter: &mut dyn Prompter,
+    alias: &str,
+    current: &HashMap<String, String>,
+) -> io::Result<Option<SetWizardAnswers>> {
+    let preset = &crate::types::presets::CLAUDE;
+    outln!(output, "🧩 Setting up '{}' (Ctrl-D cancels)", alias);
     
-    let mut key = String::new();
-    io::stdin().read_line(&mut key)?;
-    let key = key.trim();
+    let description = match prompter.read_line("Description (optional): ")? {
+        Some(description) => Some(description.trim().to_string()).filter(|d| !d.is_empty()),
+        None => return Ok(cancelled(output)),
+    };
     
-    if key.is_empty() {
-        return Ok(None);
+    let mentions_claude = [alias, description.as_deref().unwrap_or("")].iter()
+        .any(|text| ["claude", "anthropic"].iter().any(|word| text.to_lowercase().contains(word)));
+    let mut variables = HashMap::new();
+    if prompter.confirm(output, &format!("Use the {} preset ({})?", preset.name, preset.description), mentions_claude)? {
+        for variable in preset.variables {
+            let default = current.get(variable.key).map(String::as_str).or(variable.default);
+            let sensitive = crate::utils::is_sensitive_key(variable.key);
+            let prompt = match (default, variable.required) {
+                (Some(default), _) => format!("{} - {} [{}]: ", variable.key, variable.prompt, preview_value(output, variable.key, default)),
+                (None, true) => format!("{} - {}: ", variable.key, variable.prompt),
+                (None, false) => format!("{} - {} (optional): ", variable.key, variable.prompt),
+            };
+            loop {
+                let answer = if sensitive { prompter.read_secret(&prompt)? } else { prompter.read_line(&prompt)? };
+                let answer = match answer {
+                    Some(answer) => answer.trim().to_string(),
+                    None => return Ok(cancelled(output)),
+                };
+                match (answer.is_empty(), default) {
+                    (false, _) => {
+                        variables.insert(variable.key.to_string(), answer);
+                    }
+                    (true, Some(default)) => {
+                        variables.insert(variable.key.to_string(), default.to_string());
+                    }
+                    (true, None) if variable.required => {
+                        outln!(output, "⚠️  {} is required", variable.key);
+                        continue;
+                    }
+                    (true, None) => {}
+                }
+                break;
+            }
+        }
     }
     
-    // Validate variable name
-    if !key.chars().next().unwrap_or('0').is_ascii_alphabetic() && !key.starts_with('_') {
-        return Err("Variable name must start with a letter or underscore".into());
+    outln!(output, "Add other variables as KEY=VALUE, then an empty line to finish:");
+    while let Some(line) = prompter.read_line("> ")? {
+        if line.trim().is_empty() {
+            break;
+        }
+        let parsed = crate::utils::parse_env_content(&line);
+        for (_, reason) in &parsed.skipped {
+            outln!(output, "⚠️  Skipped: {}", reason);
+        }
+        variables.extend(parsed.variables);
     }
     
-    if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
-        return Err("Variable name can only contain letters, numbers, and underscores".into());
+    if variables.is_empty() {
+        outln!(output, "⏭️  No variables entered; nothing saved");
+        return Ok(None);
     }
     
-    print!("Enter value for '{}': ", key);
-    io::stdout().flush()?;
-    
-    let mut value = String::new();
-    io::stdin().read_line(&mut value)?;
-    let value = value.trim().to_string();
+    outln!(output, "📋 Configuration '{}':", alias);
+    if let Some(description) = &description {
+        outln!(output, "   Description: {}", description);
+    }
+    let mut keys: Vec<&String> = variables.keys().collect();
+    keys.sort();
+    for key in keys {
+        outln!(output, "   {} = {}", key, preview_value(output, key, &variables[key]));
+    }
+    if !prompter.confirm(output, &format!("Save '{}'?", alias), true)? {
+        return Ok(cancelled(output));
+    }
     
-    Ok(Some((key.to_string(), value)))
+    Ok(Some(SetWizardAnswers { description, variables }))
 }
 
-/// Prompt user for a variable with a default value
-pub fn prompt_for_variable_with_default(
-    key: &str, 
-    default_value: Option<&String>
-) -> Result<String, Box<dyn std::error::Error>> {
-    if let Some(default) = default_value {
-        print!("Value for '{}' [{}]: ", key, default);
-    } else {
-        print!("Value for '{}': ", key);
+fn cancelled(output: &OutputContext) -> Option<SetWizardAnswers> {
+    outln!(output, "❌ Cancelled; nothing saved");
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
     }
-    io::stdout().flush()?;
-    
-    let mut value = String::new();
-    io::stdin().read_line(&mut value)?;
-    let value = value.trim();
-    
-    if value.is_empty() {
-        if let Some(default) = default_value {
-            Ok(default.clone())
-        } else {
-            Err("Value cannot be empty".into())
-        }
-    } else {
-        Ok(value.to_string())
+
+    #[test]
+    fn test_paste_adds_and_overwrites_after_confirmation() {
+        let mut current = variables(&[("API_URL", "https://old.example.com"), ("MODEL", "a")]);
+        let mut prompter = ScriptedPrompter::new(&[
+            "export API_URL=https://new.example.com",
+            "# comment",
+            "MODEL=a",
+            "API_TOKEN='sk-1234567890'",
+            "",
+            "y",
+        ]);
+        
+        assert!(paste_variables(&OutputContext::default(), &mut prompter, &mut current).unwrap());
+        assert_eq!(current, variables(&[
+            ("API_URL", "https://new.example.com"),
+            ("MODEL", "a"),
+            ("API_TOKEN", "sk-1234567890"),
+        ]));
+        assert_eq!(prompter.prompts.last().unwrap(), "Apply 2 changes? [Y/n]: ");
     }
-}
 
-/// Prompt user to select a variable from the list
-pub fn prompt_for_variable_selection(
-    variables: &HashMap<String, String>,
-    action: &str
-) -> Result<Option<String>, Box<dyn std::error::Error>> {
-    if variables.is_empty() {
-        println!("No variables available to {}.", action);
-        return Ok(None);
+    #[test]
+    fn test_paste_skips_invalid_lines_and_stops_at_end_of_input() {
+        let mut current = HashMap::new();
+        let mut prompter = ScriptedPrompter::new(&["not a variable", "1BAD=x", "GOOD=yes"]);
+        
+        // The input ends before the confirmation, which then takes its default
+        assert!(paste_variables(&OutputContext::default(), &mut prompter, &mut current).unwrap());
+        assert_eq!(current, variables(&[("GOOD", "yes")]));
     }
-    
-    println!("Select a variable to {}:", action);
-    let mut vars: Vec<_> = variables.keys().collect();
-    vars.sort();
-    
-    for (i, key) in vars.iter().enumerate() {
-        println!("  {}. {}", i + 1, key);
+
+    #[test]
+    fn test_paste_declined_or_unchanged_keeps_variables() {
+        let original = variables(&[("KEY", "value")]);
+        
+        let mut current = original.clone();
+        let mut prompter = ScriptedPrompter::new(&["KEY=other", "NEW=1", "", "n"]);
+        assert!(!paste_variables(&OutputContext::default(), &mut prompter, &mut current).unwrap());
+        assert_eq!(current, original);
+        
+        let mut prompter = ScriptedPrompter::new(&["KEY=value", ""]);
+        assert!(!paste_variables(&OutputContext::default(), &mut prompter, &mut current).unwrap());
+        assert_eq!(prompter.prompts.len(), 2, "no confirmation without changes");
     }
-    
-    print!("Enter number (1-{}) or variable name: ", vars.len());
-    io::stdout().flush()?;
-    
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    let input = input.trim();
-    
-    if input.is_empty() {
-        return Ok(None);
+
+    #[test]
+    fn test_confirm_repeats_until_answered() {
+        let mut prompter = ScriptedPrompter::new(&["maybe", "no"]);
+        assert!(!prompter.confirm(&OutputContext::default(), "Apply?", true).unwrap());
+        assert_eq!(prompter.prompts.len(), 2);
+        assert!(ScriptedPrompter::new(&[]).confirm(&OutputContext::default(), "Apply?", true).unwrap());
     }
-    
-    // Try to parse as number first
-    if let Ok(num) = input.parse::<usize>() {
-        if num >= 1 && num <= vars.len() {
-            return Ok(Some(vars[num - 1].clone()));
-        }
+
+    fn editable(pairs: &[(&str, &str)]) -> EditableConfig {
+        EditableConfig { variables: variables(pairs), ..Default::default() }
     }
-    
-    // Try as variable name
-    if variables.contains_key(input) {
-        Ok(Some(input.to_string()))
-    } else {
-        Err(format!("Invalid selection: '{}'", input).into())
+
+    #[test]
+    fn test_editor_adds_edits_and_deletes_by_name_or_number() {
+        let mut prompter = ScriptedPrompter::new(&[
+            "a", "B=2",
+            "e", "1", "10",
+            "e", "B", "",
+            "d", "3",
+            "desc", "Work account",
+            "s",
+        ]);
+        let config = editable(&[("A", "1"), ("SECRET_TOKEN", "sk-1234567890")]);
+        
+        let edited = VariableEditor::new(config).run(&OutputContext::default(), &mut prompter).unwrap().unwrap();
+        assert_eq!(edited.variables, variables(&[("A", "10"), ("B", "2")]));
+        assert_eq!(edited.description.as_deref(), Some("Work account"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_editor_validates_names_and_hides_sensitive_values() {
+        let mut prompter = ScriptedPrompter::new(&["a", "1BAD", "a", "API_TOKEN", "sk-1234567890", "a", "API_TOKEN", "s"]);
+        
+        let edited = VariableEditor::new(EditableConfig::default()).run(&OutputContext::default(), &mut prompter).unwrap().unwrap();
+        assert_eq!(edited.variables, variables(&[("API_TOKEN", "sk-1234567890")]));
+        assert_eq!(prompter.secret_prompts, vec!["Value for 'API_TOKEN': "]);
+        // A second add of the same name is refused without asking for a value
+        assert_eq!(prompter.prompts.iter().filter(|prompt| prompt.starts_with("Value for")).count(), 1);
+    }
+
+    #[test]
+    fn test_editor_asks_before_dropping_unsaved_changes() {
+        let mut prompter = ScriptedPrompter::new(&["a", "X=1", "q", "n", "q", "y"]);
+        assert!(VariableEditor::new(editable(&[])).run(&OutputContext::default(), &mut prompter).unwrap().is_none());
+        let asked = prompter.prompts.iter().filter(|prompt| *prompt == "You have unsaved changes. Quit anyway? [y/N]: ").count();
+        assert_eq!(asked, 2);
+        
+        // Declining keeps the changes, which can still be saved
+        let mut prompter = ScriptedPrompter::new(&["a", "X=1", "q", "n", "s"]);
+        let edited = VariableEditor::new(editable(&[])).run(&OutputContext::default(), &mut prompter).unwrap().unwrap();
+        assert_eq!(edited.variables, variables(&[("X", "1")]));
+        
+        // Without changes, or when the input ends, it quits without asking
+        let mut prompter = ScriptedPrompter::new(&["q"]);
+        assert!(VariableEditor::new(editable(&[("A", "1")])).run(&OutputContext::default(), &mut prompter).unwrap().is_none());
+        assert_eq!(prompter.prompts.len(), 1);
+        let mut prompter = ScriptedPrompter::new(&["a", "X=1"]);
+        assert!(VariableEditor::new(editable(&[])).run(&OutputContext::default(), &mut prompter).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_editor_comments_on_variables() {
+        let mut prompter = ScriptedPrompter::new(&[
+            "c", "API_URL", "pinned until v2",
+            "c", "2", "cheaper",
+            "c", "2", "",
+            "d", "API_URL",
+            "c", "MISSING",
+            "s",
+        ]);
+        let config = editable(&[("API_URL", "https://v1.example.com"), ("MODEL", "large"), ("OTHER", "x")]);
+        
+        let edited = VariableEditor::new(config).run(&OutputContext::default(), &mut prompter).unwrap().unwrap();
+        // An empty answer removes the comment, deleting the variable drops it
+        assert!(edited.comments.is_empty());
+        assert_eq!(prompter.prompts.iter().filter(|prompt| prompt.starts_with("Comment for")).count(), 3);
+        
+        let mut prompter = ScriptedPrompter::new(&["c", "1", "pinned until v2", "s"]);
+        let edited = VariableEditor::new(editable(&[("API_URL", "x")])).run(&OutputContext::default(), &mut prompter).unwrap().unwrap();
+        assert_eq!(edited.comments["API_URL"], "pinned until v2");
+    }
+
+    #[test]
+    fn test_editor_offers_notes_and_links_only_with_details() {
+        let script = ["n", "Rotate monthly", "l", "https://a.example https://b.example", "y", "s"];
+        let config = editable(&[("A", "1")]);
+        
+        let edited = VariableEditor::new(config.clone()).with_details()
+            .run(&OutputContext::default(), &mut ScriptedPrompter::new(&script)).unwrap().unwrap();
+        assert_eq!(edited.notes.as_deref(), Some("Rotate monthly"));
+        assert_eq!(edited.links, vec!["https://a.example", "https://b.example"]);
+        
+        // Without details every answer is an unknown action until "s"
+        let edited = VariableEditor::new(config.clone()).run(&OutputContext::default(), &mut ScriptedPrompter::new(&script)).unwrap().unwrap();
+        assert_eq!(edited, config);
+    }
+
+    #[test]
+    fn test_editor_confirms_saving_without_variables() {
+        let mut prompter = ScriptedPrompter::new(&["d", "1", "s", "n", "s", "y"]);
+        let edited = VariableEditor::new(editable(&[("A", "1")])).run(&OutputContext::default(), &mut prompter).unwrap().unwrap();
+        assert!(edited.variables.is_empty());
+        assert_eq!(prompter.prompts.iter().filter(|prompt| prompt.contains("no variables")).count(), 2);
+    }
+
+    #[test]
+    fn test_set_wizard_fills_the_claude_preset() {
+        let mut prompter = ScriptedPrompter::new(&[
+            "My Claude proxy",
+            "",
+            "",
+            "sk-ant-1234567890",
+            "claude-sonnet",
+            "",
+            "",
+            "EXTRA=1",
+            "",
+            "y",
+        ]);
+        
+        let answers = set_wizard(&OutputContext::default(), &mut prompter, "work", &HashMap::new()).unwrap().unwrap();
+        assert_eq!(answers.description.as_deref(), Some("My Claude proxy"));
+        assert_eq!(answers.variables, variables(&[
+            ("ANTHROPIC_BASE_URL", "https://api.anthropic.com"),
+            ("ANTHROPIC_AUTH_TOKEN", "sk-ant-1234567890"),
+            ("ANTHROPIC_MODEL", "claude-sonnet"),
+            ("EXTRA", "1"),
+        ]));
+        // The description mentions Claude, so the preset is the default
+        assert!(prompter.prompts[1].ends_with("[Y/n]: "));
+        assert!(prompter.secret_prompts.iter().any(|prompt| prompt.starts_with("ANTHROPIC_AUTH_TOKEN")));
+        assert!(!prompter.secret_prompts.iter().any(|prompt| prompt.starts_with("ANTHROPIC_MODEL")));
+    }
+
+    #[test]
+    fn test_set_wizard_repeats_required_values_and_can_be_declined() {
+        let mut prompter = ScriptedPrompter::new(&["", "y", "", "", "sk-ant-1234567890", "", "", "", "", "n"]);
+        assert!(set_wizard(&OutputContext::default(), &mut prompter, "work", &HashMap::new()).unwrap().is_none());
+        assert_eq!(prompter.secret_prompts.len(), 3);
+        
+        // Without the preset and without variables nothing is returned
+        let mut prompter = ScriptedPrompter::new(&["", "", ""]);
+        assert!(set_wizard(&OutputContext::default(), &mut prompter, "plain", &HashMap::new()).unwrap().is_none());
+        assert!(prompter.prompts[1].ends_with("[y/N]: "));
+    }
+
+    #[test]
+    fn test_set_wizard_defaults_to_current_values() {
+        let current = variables(&[("ANTHROPIC_BASE_URL", "https://proxy.example.com"), ("ANTHROPIC_AUTH_TOKEN", "sk-ant-old-token")]);
+        let mut prompter = ScriptedPrompter::new(&["", "y", "", "", "", "", "", "", ""]);
+        let answers = set_wizard(&OutputContext::default(), &mut prompter, "claude", &current).unwrap().unwrap();
+        assert_eq!(answers.variables, current);
+        assert!(!prompter.prompts.iter().any(|prompt| prompt.contains("sk-ant-old-token")));
+    }
+}
@@ -1,46 +1,152 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use crate::split_store::SplitFileConfigManager;
+use crate::types::{constants, ConfigPaths};
+use crate::output::OutputContext;
 
 /// Check if this is the first time using envswitch
 pub fn should_show_welcome() -> bool {
-    let config_dir = dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("envswitch");
-    
-    let config_file = config_dir.join("config.json");
-    let welcome_file = config_dir.join(".welcome_shown");
+    let config_dir = ConfigPaths::resolve_config_dir()
+        .map(|(dir, _)| dir)
+        .unwrap_or_else(|_| PathBuf::from(".").join("envswitch"));
+    let state_dir = ConfigPaths::resolve_state_dir().unwrap_or_else(|_| config_dir.clone());
+    is_first_run(&config_dir, &state_dir)
+}
+
+/// No store exists in either layout and the welcome message hasn't been shown
+fn is_first_run(config_dir: &Path, state_dir: &Path) -> bool {
+    let paths = ConfigPaths::in_dir(config_dir);
+    let store_exists = paths.config_file.exists() || SplitFileConfigManager::with_paths(paths).exists();
+    // Older versions left the marker in the configuration directory
+    let welcome_shown = [state_dir, config_dir].iter().any(|dir| dir.join(constants::WELCOME_MARKER_NAME).exists());
     
-    // Show welcome if config doesn't exist and welcome hasn't been shown
-    !config_file.exists() && !welcome_file.exists()
+    !store_exists && !welcome_shown
 }
 
 /// Show welcome message for first-time users
-pub fn show_welcome_message() {
-    println!("🎉 Welcome to EnvSwitch!");
-    println!("========================");
-    println!();
-    println!("EnvSwitch helps you manage and switch between different sets of environment variables.");
-    println!("Perfect for managing API keys, database connections, and development environments!");
-    println!();
-    println!("🚀 Quick Start:");
-    println!("  1. Create your first configuration:");
-    println!("     envswitch set my-config -e API_KEY=your-key -e API_URL=https://api.example.com");
-    println!();
-    println!("  2. Switch to it:");
-    println!("     eval \"$(envswitch use my-config)\"");
-    println!();
-    println!("  3. Check status:");
-    println!("     envswitch status");
-    println!();
-    println!("📚 For a complete tutorial, run: envswitch tutorial");
-    println!("❓ For help with any command, use: envswitch <command> --help");
-    println!();
+pub fn show_welcome_message(output: &OutputContext) {
+    outln!(output, "🎉 Welcome to EnvSwitch!");
+    outln!(output, "========================");
+    outln!(output);
+    outln!(output, "EnvSwitch helps you manage and switch between different sets of environment variables.");
+    outln!(output, "Perfect for managing API keys, database connections, and development environments!");
+    outln!(output);
+    outln!(output, "🚀 Quick Start:");
+    outln!(output, "  1. Create your first configuration:");
+    outln!(output, "     envswitch set my-config -e API_KEY=your-key -e API_URL=https://api.example.com");
+    outln!(output);
+    outln!(output, "  2. Switch to it:");
+    outln!(output, "     {}", crate::examples::activation_command("my-config"));
+    outln!(output);
+    outln!(output, "  3. Check status:");
+    outln!(output, "     envswitch status");
+    outln!(output);
+    outln!(output, "📚 For a complete tutorial, run: envswitch tutorial");
+    outln!(output, "❓ For help with any command, use: envswitch <command> --help");
+    outln!(output);
     
-    // Create welcome marker file
-    if let Some(config_dir) = dirs::config_dir() {
-        let envswitch_dir = config_dir.join("envswitch");
-        if let Ok(()) = std::fs::create_dir_all(&envswitch_dir) {
-            let welcome_file = envswitch_dir.join(".welcome_shown");
-            let _ = std::fs::write(welcome_file, "");
+    mark_welcome_shown();
+}
+
+/// Create the marker that stops the welcome message, e.g. once `init` has run
+pub fn mark_welcome_shown() {
+    if let Ok(state_dir) = ConfigPaths::resolve_state_dir() {
+        if let Ok(()) = crate::types::ensure_private_dir(&state_dir) {
+            let _ = std::fs::write(state_dir.join(constants::WELCOME_MARKER_NAME), "");
         }
     }
-}
\ No newline at end of file
+}
+
+/// Move files left by older versions: backups next to config.json go to the backup
+/// directory, session state and history to the state directory
+///
+/// Failures are reported on stderr and don't stop the command.
+pub fn run_migrations(output: &OutputContext, config_manager: &crate::config::FileConfigManager) {
+    match config_manager.migrate_to_state_dir() {
+        Ok(moved) if moved.is_empty() => {}
+        Ok(moved) => eoutln!(output, "📦 Moved {} to {}", moved.join(", "), config_manager.config_paths().state_dir.display()),
+        Err(e) => eoutln!(output, "⚠️  Could not move the session state to {}: {}", config_manager.config_paths().state_dir.display(), e),
+    }
+    match config_manager.migrate_legacy_backups() {
+        Ok(0) => {}
+        Ok(moved) => eoutln!(output, "📦 Moved {} backup(s) to {}", moved, config_manager.backup_dir().display()),
+        Err(e) => eoutln!(output, "⚠️  Could not move backups to {}: {}", config_manager.backup_dir().display(), e),
+    }
+}
+
+/// Check the configuration store before a command changes it, fixing what it can
+///
+/// Prints nothing when the store is healthy. Notices go to stderr so they never
+/// end up in an eval'd script. A corrupt config.json is replaced by the newest
+/// valid backup when `auto_recover` is set or the user agrees.
+pub fn run_integrity_check(
+    output: &OutputContext,
+    config_manager: &crate::config::FileConfigManager,
+    auto_recover: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::IsTerminal;
+
+    let report = config_manager.check_integrity()?;
+    if report.is_clean() {
+        return Ok(());
+    }
+
+    if report.fixed_permissions {
+        eoutln!(output, "🔒 Reset permissions of {} to 0600", config_manager.config_file_path().display());
+    }
+    if let Some(active) = &report.cleared_active {
+        eoutln!(output, "⚠️  Active configuration '{}' no longer exists and was cleared", active);
+    }
+    if let Some(default) = &report.cleared_default {
+        eoutln!(output, "⚠️  Default configuration '{}' no longer exists and was cleared", default);
+    }
+    if let Some(error) = &report.backups_error {
+        eoutln!(output, "⚠️  Backups cannot be read: {}", error);
+    }
+
+    let Some(parse_error) = report.parse_error else {
+        return Ok(());
+    };
+
+    eoutln!(output, "❌ {} is corrupt: {}", config_manager.config_file_path().display(), parse_error);
+    let Some(backup) = config_manager.newest_valid_backup() else {
+        eoutln!(output, "💡 No valid backup found. Fix the file by hand or restore an export with 'envswitch import'");
+        return Ok(());
+    };
+
+    let recover = if auto_recover {
+        true
+    } else if !output.execution.eval && std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+        crate::utils::confirm_on_stderr(output, &format!("Restore the newest valid backup ({})?", backup.display()))?
+    } else {
+        eoutln!(output, "💡 Run 'envswitch --auto-recover doctor' to restore {}", backup.display());
+        false
+    };
+
+    if recover {
+        let stash = config_manager.recover_from_backup(&backup)?;
+        eoutln!(output, "✅ Restored {}", backup.display());
+        eoutln!(output, "   The corrupt file was kept as {}", stash.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_first_run_detects_either_layout() {
+        let dir = TempDir::new().unwrap();
+        assert!(is_first_run(dir.path(), dir.path()));
+
+        fs::write(dir.path().join(constants::SPLIT_INDEX_FILE_NAME), "{}").unwrap();
+        assert!(!is_first_run(dir.path(), dir.path()));
+
+        fs::remove_file(dir.path().join(constants::SPLIT_INDEX_FILE_NAME)).unwrap();
+        fs::write(dir.path().join(constants::CONFIG_FILE_NAME), "{}").unwrap();
+        assert!(!is_first_run(dir.path(), dir.path()));
+    }
+}
@@ -1,18 +1,72 @@
-/// Check if a key contains sensitive information that should be masked
-pub fn is_sensitive_key(key: &str) -> bool {
-    let sensitive_patterns = [
-        "TOKEN", "KEY", "SECRET", "PASSWORD", "AUTH", "CREDENTIAL", "API_KEY"
-    ];
+pub use crate::utils::{is_sensitive_key, mask_sensitive_value};
+use crate::error::ConfigError;
+use crate::output::OutputContext;
+
+/// Fail when warnings were shown and `envswitch --strict` or the strict setting is on
+///
+/// Commands call it before saving so a strict run changes nothing; the router calls it
+/// once more after every command for warnings shown at the end.
+pub fn check_strict(output: &OutputContext) -> Result<(), ConfigError> {
+    let count = output.execution.warnings_shown();
+    if count == 0 || !output.execution.strict {
+        return Ok(());
+    }
+    Err(ConfigError::StrictWarnings(count))
+}
+
+/// Warn on stderr about credential values that look mis-pasted or fake
+///
+/// Returns the number of warnings printed; the values are saved either way.
+pub fn warn_suspicious_tokens(output: &OutputContext, config: &str, variables: &std::collections::HashMap<String, String>) -> usize {
+    let mut keys: Vec<&String> = variables.keys().collect();
+    keys.sort();
     
-    let upper_key = key.to_uppercase();
-    sensitive_patterns.iter().any(|pattern| upper_key.contains(pattern))
+    let mut count = 0;
+    for key in keys {
+        for warning in crate::types::validation::token_value_warnings(key, &variables[key]) {
+            eoutln!(output, "⚠️  {}:{} {}", config, key, warning);
+            count += 1;
+        }
+    }
+    output.execution.record_warnings(count);
+    count
 }
 
-/// Mask sensitive values for display
-pub fn mask_sensitive_value(value: &str) -> String {
-    if value.len() <= 8 {
-        "*".repeat(value.len())
-    } else {
-        format!("{}***{}", &value[..4], &value[value.len()-4..])
+/// Collect the warnings of [`warn_suspicious_tokens`] for a summary instead of printing each
+pub fn collect_suspicious_tokens(
+    config: &str,
+    variables: &std::collections::HashMap<String, String>,
+    warnings: &mut crate::utils::WarningAggregator,
+) {
+    let mut keys: Vec<&String> = variables.keys().collect();
+    keys.sort();
+    
+    for key in keys {
+        for warning in crate::types::validation::token_value_warnings(key, &variables[key]) {
+            warnings.add(&format!("{{count}} variable(s) whose value {}", warning), format!("{}:{}", config, key));
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Trim whitespace and control characters from values, noting each change on stderr
+///
+/// Returns the number of values changed.
+pub fn trim_values_with_notice(output: &OutputContext, config: &str, variables: &mut std::collections::HashMap<String, String>) -> usize {
+    let mut keys: Vec<String> = variables.keys().cloned().collect();
+    keys.sort();
+    
+    let mut count = 0;
+    for key in keys {
+        if let Some(clean) = crate::types::validation::clean_value(&variables[&key]) {
+            print_trimmed_notice(output, &format!("{}:{}", config, key));
+            variables.insert(key, clean);
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Tell the user a value was trimmed; `location` is "config:KEY"
+pub fn print_trimmed_notice(output: &OutputContext, location: &str) {
+    eoutln!(output, "✂️  {} had surrounding whitespace or control characters; trimmed (keep them with --keep-whitespace)", location);
+}
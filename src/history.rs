@@ -0,0 +1,263 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use chrono::{DateTime, Utc};
+use crate::error::{ConfigError, ConfigResult};
+
+/// The `.gitignore` older versions wrote into the configuration directory
+const LEGACY_GITIGNORE: &str = "*\n!.gitignore\n!config.json\n";
+
+/// Identity used for commits when the user has no git identity configured
+const FALLBACK_NAME: &str = "envswitch";
+const FALLBACK_EMAIL: &str = "envswitch@localhost";
+
+/// Check if a git executable is available
+pub fn is_git_available() -> bool {
+    Command::new("git")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// A repository kept apart from the directory it tracks
+///
+/// The history belongs to one machine, so it lives in the state directory while
+/// the configuration directory it tracks may be synced elsewhere.
+#[derive(Debug, Clone)]
+pub struct Repo {
+    pub git_dir: PathBuf,
+    pub work_tree: PathBuf,
+}
+
+impl Repo {
+    /// Check if the repository has been created
+    pub fn exists(&self) -> bool {
+        self.git_dir.join("HEAD").exists()
+    }
+    
+    /// A git command running on this repository
+    fn command(&self) -> Command {
+        let mut command = Command::new("git");
+        command.arg("--git-dir").arg(&self.git_dir).arg("--work-tree").arg(&self.work_tree);
+        command
+    }
+}
+
+/// Run git on `repo` and return its output, failing on a non-zero exit status
+fn git(repo: &Repo, args: &[&str]) -> ConfigResult<Output> {
+    let output = repo.command()
+        .args(args)
+        .output()
+        .map_err(|e| ConfigError::ValidationError(format!("Could not run git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(ConfigError::ValidationError(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or(""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output)
+}
+
+/// Initialize the repository; only files added by [`commit`] are ever tracked
+pub fn ensure_repo(repo: &Repo) -> ConfigResult<()> {
+    if repo.exists() {
+        return Ok(());
+    }
+
+    if !is_git_available() {
+        return Err(ConfigError::ValidationError(
+            "git-track is enabled but git was not found in PATH".to_string()
+        ));
+    }
+
+    if let Some(parent) = repo.git_dir.parent() {
+        fs::create_dir_all(parent).map_err(ConfigError::FileError)?;
+    }
+    git(repo, &["init", "--quiet"])?;
+    Ok(())
+}
+
+/// Move a repository older versions kept as `.git` in the tracked directory to `repo.git_dir`
+///
+/// Returns whether one was moved. Nothing happens when `repo` already exists.
+pub fn migrate_legacy_repo(repo: &Repo) -> ConfigResult<bool> {
+    let legacy = repo.work_tree.join(".git");
+    if !legacy.is_dir() || repo.exists() {
+        return Ok(false);
+    }
+    
+    if let Some(parent) = repo.git_dir.parent() {
+        fs::create_dir_all(parent).map_err(ConfigError::FileError)?;
+    }
+    fs::rename(&legacy, &repo.git_dir).map_err(ConfigError::FileError)?;
+    
+    // Only remove the .gitignore if it is still the one envswitch wrote
+    let gitignore = repo.work_tree.join(".gitignore");
+    if fs::read_to_string(&gitignore).is_ok_and(|content| content == LEGACY_GITIGNORE) {
+        fs::remove_file(&gitignore).map_err(ConfigError::FileError)?;
+    }
+    Ok(true)
+}
+
+/// Commit the current config.json with the given message
+///
+/// Does nothing when the file is unchanged since the last commit.
+pub fn commit(repo: &Repo, file_name: &str, message: &str) -> ConfigResult<()> {
+    ensure_repo(repo)?;
+    git(repo, &["add", file_name])?;
+
+    // `diff --cached --quiet` exits with 1 when something is staged
+    let staged = repo.command()
+        .args(["diff", "--cached", "--quiet"])
+        .status()
+        .map_err(|e| ConfigError::ValidationError(format!("Could not run git: {}", e)))?;
+    if staged.success() {
+        return Ok(());
+    }
+
+    let has_identity = git(repo, &["config", "user.email"]).is_ok();
+    let name_config = format!("user.name={}", FALLBACK_NAME);
+    let email_config = format!("user.email={}", FALLBACK_EMAIL);
+    // Automated commits must never wait for a signing passphrase
+    let mut args = vec!["-c", "commit.gpgsign=false"];
+    if !has_identity {
+        args.extend(["-c", name_config.as_str(), "-c", email_config.as_str()]);
+    }
+    args.extend(["commit", "--quiet", "--no-verify", "-m", message]);
+
+    git(repo, &args)?;
+    Ok(())
+}
+
+/// Recent history of config.json as "<hash> <date> <message>" lines, newest first
+pub fn log(repo: &Repo, limit: usize) -> ConfigResult<Vec<String>> {
+    if !repo.exists() {
+        return Ok(Vec::new());
+    }
+
+    let limit = format!("-{}", limit);
+    let output = git(repo, &["log", &limit, "--date=format:%Y-%m-%d %H:%M:%S", "--format=%h %ad %s"]);
+    match output {
+        Ok(output) => Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect()),
+        // A fresh repository without commits has no history yet
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+/// Every commit that changed `file_name` as (abbreviated hash, commit time), oldest first
+pub fn versions(repo: &Repo, file_name: &str) -> ConfigResult<Vec<(String, DateTime<Utc>)>> {
+    if !repo.exists() {
+        return Ok(Vec::new());
+    }
+
+    let output = match git(repo, &["log", "--reverse", "--format=%h %cI", "--", file_name]) {
+        Ok(output) => output,
+        Err(_) => return Ok(Vec::new()),
+    };
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (hash, date) = line.split_once(' ')?;
+            let date = DateTime::parse_from_rfc3339(date).ok()?.with_timezone(&Utc);
+            Some((hash.to_string(), date))
+        })
+        .collect())
+}
+
+/// Read a file as it was at the given commit
+pub fn show_file(repo: &Repo, commit: &str, file_name: &str) -> ConfigResult<String> {
+    if !repo.exists() {
+        return Err(ConfigError::ValidationError(
+            "No history found. Enable it with: envswitch settings set git-track true".to_string()
+        ));
+    }
+
+    if commit.starts_with('-') {
+        return Err(ConfigError::ValidationError(format!("Invalid commit '{}'", commit)));
+    }
+
+    let spec = format!("{}:{}", commit, file_name);
+    let output = git(repo, &["show", &spec])
+        .map_err(|_| ConfigError::ValidationError(format!("Commit '{}' not found in the configuration history", commit)))?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_commit_log_and_show() {
+        if !is_git_available() {
+            return;
+        }
+
+        let dir = TempDir::new().unwrap();
+        let repo = &Repo { git_dir: dir.path().join("state/history"), work_tree: dir.path().to_path_buf() };
+        fs::write(dir.path().join("config.json"), "{\"v\": 1}").unwrap();
+        fs::write(dir.path().join("config_backup_1.json"), "{}").unwrap();
+        commit(repo, "config.json", "first").unwrap();
+        assert!(!dir.path().join(".git").exists());
+
+        fs::write(dir.path().join("config.json"), "{\"v\": 2}").unwrap();
+        commit(repo, "config.json", "second").unwrap();
+        // Unchanged file doesn't create an empty commit
+        commit(repo, "config.json", "third").unwrap();
+
+        let entries = log(repo, 10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].ends_with("second"));
+        assert!(entries[1].ends_with("first"));
+
+        let first_hash = entries[1].split_whitespace().next().unwrap();
+        assert_eq!(show_file(repo, first_hash, "config.json").unwrap(), "{\"v\": 1}");
+
+        let versions = versions(repo, "config.json").unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].0, first_hash);
+        assert!(versions[0].1 <= versions[1].1);
+        assert!(show_file(repo, "deadbeef", "config.json").is_err());
+        assert!(show_file(repo, "--output=x", "config.json").is_err());
+    }
+
+    #[test]
+    fn test_log_without_repo() {
+        let dir = TempDir::new().unwrap();
+        let repo = &Repo { git_dir: dir.path().join("history"), work_tree: dir.path().to_path_buf() };
+        assert!(log(repo, 10).unwrap().is_empty());
+        assert!(versions(repo, "config.json").unwrap().is_empty());
+        assert!(show_file(repo, "HEAD", "config.json").is_err());
+    }
+
+    #[test]
+    fn test_legacy_repo_is_moved_out_of_the_tracked_directory() {
+        if !is_git_available() {
+            return;
+        }
+
+        let dir = TempDir::new().unwrap();
+        let config_dir = dir.path().join("config");
+        fs::create_dir(&config_dir).unwrap();
+        fs::write(config_dir.join("config.json"), "{\"v\": 1}").unwrap();
+        // The layout of older versions: .git and .gitignore next to config.json
+        let legacy = &Repo { git_dir: config_dir.join(".git"), work_tree: config_dir.clone() };
+        commit(legacy, "config.json", "before").unwrap();
+        fs::write(config_dir.join(".gitignore"), LEGACY_GITIGNORE).unwrap();
+
+        let repo = &Repo { git_dir: dir.path().join("state/history"), work_tree: config_dir.clone() };
+        assert!(migrate_legacy_repo(repo).unwrap());
+        assert!(!migrate_legacy_repo(repo).unwrap());
+        assert!(!config_dir.join(".git").exists());
+        assert!(!config_dir.join(".gitignore").exists());
+
+        fs::write(config_dir.join("config.json"), "{\"v\": 2}").unwrap();
+        commit(repo, "config.json", "after").unwrap();
+        let entries = log(repo, 10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[1].ends_with("before"));
+    }
+}
@@ -1,9 +1,22 @@
+#[macro_use]
+pub mod output;
+#[macro_use]
+pub mod messages;
 pub mod cli;
 pub mod config;
+pub mod split_store;
 pub mod env;
 pub mod shell;
 pub mod error;
 pub mod types;
 pub mod commands;
 pub mod handlers;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod verify;
+pub mod history;
+pub mod lock;
+pub mod diff;
+pub mod examples;
+pub mod audit;
+pub mod timing;
+pub mod bench_support;
@@ -0,0 +1,138 @@
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, ThreadId};
+use crate::error::{ConfigError, ConfigResult};
+
+/// Name of the lock file in the configuration directory
+pub const LOCK_FILE_NAME: &str = ".lock";
+
+/// Which thread holds a store lock and how many times it re-entered it
+struct LockState {
+    owner: ThreadId,
+    depth: usize,
+    /// Open while locked; closing it releases the flock
+    _file: File,
+}
+
+/// Held locks by lock file path, so stores in different directories don't wait for each other
+static HELD: Mutex<BTreeMap<PathBuf, LockState>> = Mutex::new(BTreeMap::new());
+static RELEASED: Condvar = Condvar::new();
+
+/// Exclusive lock on the configuration store, released when dropped
+///
+/// Threads of one process are serialized in memory, and processes through an
+/// advisory `flock` on the lock file (Unix only). The lock is re-entrant so a
+/// locked operation can call other locked operations.
+pub struct StoreLock {
+    path: PathBuf,
+}
+
+impl StoreLock {
+    /// Block until the store in `config_dir` can be modified exclusively
+    pub fn acquire(config_dir: &Path) -> ConfigResult<StoreLock> {
+        let me = thread::current().id();
+        let path = config_dir.join(LOCK_FILE_NAME);
+        let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+        while held.get(&path).is_some_and(|state| state.owner != me) {
+            held = RELEASED.wait(held).unwrap_or_else(|e| e.into_inner());
+        }
+
+        if let Some(state) = held.get_mut(&path) {
+            state.depth += 1;
+        } else {
+            // Only a writer takes the lock, so failing to open it means nothing can be saved
+            let lock_failed = |e: std::io::Error| match e.kind() {
+                ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => {
+                    ConfigError::ReadOnly(config_dir.display().to_string())
+                }
+                _ => ConfigError::LockFailed {
+                    path: path.display().to_string(),
+                    reason: e.to_string(),
+                },
+            };
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)
+                .map_err(lock_failed)?;
+            lock_file(&file).map_err(lock_failed)?;
+            held.insert(path.clone(), LockState { owner: me, depth: 1, _file: file });
+        }
+
+        Ok(StoreLock { path })
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(state) = held.get_mut(&self.path) else {
+            return;
+        };
+        state.depth -= 1;
+        if state.depth == 0 {
+            // Dropping the state closes the file, which releases the flock
+            held.remove(&self.path);
+            RELEASED.notify_all();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lock_file(file: &File) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the descriptor is owned by `file` and stays open while locked
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn lock_file(_file: &File) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_is_reentrant_and_exclusive() {
+        let dir = TempDir::new().unwrap();
+        let outer = StoreLock::acquire(dir.path()).unwrap();
+        let inner = StoreLock::acquire(dir.path()).unwrap();
+        drop(inner);
+
+        let path = dir.path().to_path_buf();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let waiter = thread::spawn(move || {
+            let _lock = StoreLock::acquire(&path).unwrap();
+            tx.send(()).unwrap();
+        });
+
+        // The other thread waits until the outer lock is released
+        assert!(rx.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+        drop(outer);
+        rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+        waiter.join().unwrap();
+        assert!(dir.path().join(LOCK_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_locks_of_different_directories_are_independent() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        let _held = StoreLock::acquire(first.path()).unwrap();
+
+        let path = second.path().to_path_buf();
+        let other = thread::spawn(move || StoreLock::acquire(&path).map(drop).is_ok());
+        assert!(other.join().unwrap());
+    }
+}
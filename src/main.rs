@@ -1,28 +1,24 @@
-mod cli;
-mod commands;
-mod env;
-mod error;
-mod handlers;
-pub mod shell;
-mod types;
-mod utils;
+use envswitch::{commands, handlers};
 
-use envswitch::config;
-
-use clap::Parser;
-use cli::Cli;
+use envswitch::cli::Cli;
 use std::process;
 
 fn main() {
-    let cli = Cli::parse();
+    let cli = Cli::parse_checked();
+    // Everything that locates the store reads the variable, so the flag simply sets it
+    if let Some(dir) = &cli.config_dir {
+        std::env::set_var(envswitch::types::constants::CONFIG_DIR_ENV_VAR, dir);
+    }
+    let output = commands::router::init_output(&cli);
 
-    // Check for first-time usage and show welcome message
-    if handlers::startup::should_show_welcome() {
-        handlers::startup::show_welcome_message();
+    // The welcome check touches the filesystem, so it only runs for commands that may show it
+    if !cli.quiet && !output.execution.eval && cli.command.shows_welcome() && handlers::startup::should_show_welcome() {
+        handlers::startup::show_welcome_message(&output);
     }
 
-    if let Err(e) = commands::router::run_command(cli.command, cli.verbose) {
-        handlers::error_handling::handle_error(&e, cli.verbose);
-        process::exit(1);
+    let result = commands::router::run_command(cli.command, &output, cli.verbose, cli.auto_recover);
+    output.execution.timings.print_summary(&output);
+    if let Err(e) = result {
+        process::exit(handlers::error_handling::handle_error(&output, e.as_ref(), cli.verbose));
     }
 }
@@ -0,0 +1,242 @@
+use std::fmt::Display;
+
+/// Environment variable choosing the message language, e.g. `ENVSWITCH_LANG=zh-CN`
+pub const LANG_ENV_VAR: &str = "ENVSWITCH_LANG";
+
+/// Text of a message in the locale of the [`OutputContext`](crate::output::OutputContext) given first;
+/// named `{placeholders}` are filled from the arguments
+///
+/// Emoji stay at the call site so `--no-emoji` and `--quiet` work the same in every language.
+#[macro_export]
+macro_rules! msg {
+    ($output:expr, $key:literal) => {
+        $output.locale.text($key).to_string()
+    };
+    ($output:expr, $key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::messages::format($output.locale, $key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+])
+    };
+}
+
+/// English messages, the fallback for every other locale
+const EN: &[(&str, &str)] = &[
+    ("common.nothing_saved", "Nothing was saved."),
+    ("common.resolution", "Resolution options:"),
+    ("common.force_option", "Overwrite existing configurations"),
+    ("common.merge_option", "Merge with existing configurations"),
+    ("set.no_variables", "No environment variables provided."),
+    ("set.examples", "Examples:"),
+    ("set.dry_run_details", "Dry run: would update '{alias}'"),
+    ("set.dry_run_create", "Dry run: would create configuration '{alias}'"),
+    ("set.dry_run_update", "Dry run: would update configuration '{alias}'"),
+    ("set.details_updated", "Notes and links for '{alias}' updated successfully!"),
+    ("set.saved_to", "Configuration saved to: {path}"),
+    ("use.expired", "WARNING: configuration '{alias}' EXPIRED on {date}. Its credentials may no longer work."),
+    ("use.switching", "Switching to configuration: {alias}"),
+    ("use.overwrites_external", "'{alias}' will overwrite variables that were set outside envswitch:"),
+    ("use.copied", "Activation commands for '{alias}' copied to clipboard"),
+    ("use.copy_failed", "Could not copy to clipboard: {error}"),
+    ("use.copy_dry_run", "Dry run: the commands below would be copied to the clipboard"),
+    ("use.verification_skipped", "Verification skipped (skip-verification is enabled in settings)"),
+    ("use.activated", "Configuration '{alias}' activated"),
+    ("use.variables_set", "{count} environment variables set"),
+    ("list.empty", "No configurations found"),
+    ("list.get_started", "Get started by creating your first configuration:"),
+    ("list.tutorial", "Or try the tutorial:"),
+    ("list.available", "Available configurations:"),
+    ("list.active", "Active configuration: {alias}"),
+    ("list.no_active", "No active configuration"),
+    ("delete.details", "Configuration details:"),
+    ("delete.dry_run", "Dry run: would delete configuration '{alias}'"),
+    ("delete.nothing_changed", "Nothing was changed."),
+    ("delete.confirm", "Delete configuration '{alias}'? This cannot be undone."),
+    ("delete.is_active", "This is your currently active configuration!"),
+    ("delete.clears_active", "Deleting it will clear your active configuration."),
+    ("delete.is_default", "This is the default configuration for new shells!"),
+    ("delete.cancelled", "Deletion cancelled."),
+    ("delete.deleted", "Configuration '{alias}' deleted successfully!"),
+    ("delete.active_cleared", "Active configuration cleared."),
+    ("delete.use_another", "Use 'envswitch use <config>' to activate another configuration."),
+    ("delete.none_remaining", "No configurations remaining."),
+    ("delete.create_new", "Create a new configuration with: envswitch set <name> -e KEY=value"),
+    ("delete.remaining", "Remaining configurations: {names}"),
+    ("delete.completed", "Delete operation completed successfully."),
+    ("import.dry_run_results", "Dry run results:"),
+    ("import.would_import", "Would import {count} configurations:"),
+    ("import.conflicts", "{count} conflicts found:"),
+    ("import.conflicts_skipped", "{count} conflicts found (skipped):"),
+    ("import.already_exists", "{name} (already exists)"),
+    ("import.errors_found", "{count} errors found:"),
+    ("import.errors_occurred", "{count} errors occurred:"),
+    ("import.run_without_dry_run", "To perform the actual import, run the same command without --dry-run"),
+    ("import.imported", "Successfully imported {count} configurations:"),
+    ("import.overwrote", "Overwrote {count} existing configurations:"),
+    ("import.merged", "Merged with {count} existing configurations:"),
+    ("import.resolve", "To resolve conflicts:"),
+    ("import.none", "No configurations were imported"),
+    ("export.specific", "Exporting {count} specific configurations: {names}"),
+    ("export.done", "Exported {count} configurations"),
+    ("export.hint_import", "Import on another machine"),
+    ("export.hint_view", "View exported content"),
+];
+
+/// Simplified Chinese messages; missing keys fall back to [`EN`]
+const ZH_CN: &[(&str, &str)] = &[
+    ("common.nothing_saved", "未保存任何内容。"),
+    ("common.resolution", "解决方法："),
+    ("common.force_option", "覆盖已有的配置"),
+    ("common.merge_option", "与已有的配置合并"),
+    ("set.no_variables", "未提供任何环境变量。"),
+    ("set.examples", "示例："),
+    ("set.dry_run_details", "试运行：将更新 '{alias}'"),
+    ("set.dry_run_create", "试运行：将创建配置 '{alias}'"),
+    ("set.dry_run_update", "试运行：将更新配置 '{alias}'"),
+    ("set.details_updated", "'{alias}' 的备注和链接已更新！"),
+    ("set.saved_to", "配置已保存到：{path}"),
+    ("use.expired", "警告：配置 '{alias}' 已于 {date} 过期，其中的凭据可能已失效。"),
+    ("use.switching", "正在切换到配置：{alias}"),
+    ("use.overwrites_external", "'{alias}' 将覆盖在 envswitch 之外设置的变量："),
+    ("use.copied", "'{alias}' 的激活命令已复制到剪贴板"),
+    ("use.copy_failed", "无法复制到剪贴板：{error}"),
+    ("use.copy_dry_run", "试运行：以下命令将被复制到剪贴板"),
+    ("use.verification_skipped", "已跳过验证（设置中启用了 skip-verification）"),
+    ("use.activated", "配置 '{alias}' 已激活"),
+    ("use.variables_set", "已设置 {count} 个环境变量"),
+    ("list.empty", "没有找到任何配置"),
+    ("list.get_started", "从创建第一个配置开始："),
+    ("list.tutorial", "或者试试教程："),
+    ("list.available", "可用的配置："),
+    ("list.active", "当前配置：{alias}"),
+    ("list.no_active", "没有激活的配置"),
+    ("delete.details", "配置详情："),
+    ("delete.dry_run", "试运行：将删除配置 '{alias}'"),
+    ("delete.nothing_changed", "未做任何更改。"),
+    ("delete.confirm", "删除配置 '{alias}'？此操作无法撤销。"),
+    ("delete.is_active", "这是当前激活的配置！"),
+    ("delete.clears_active", "删除后将清除当前激活的配置。"),
+    ("delete.is_default", "这是新 shell 的默认配置！"),
+    ("delete.cancelled", "已取消删除。"),
+    ("delete.deleted", "配置 '{alias}' 已删除！"),
+    ("delete.active_cleared", "已清除当前激活的配置。"),
+    ("delete.use_another", "使用 'envswitch use <config>' 激活其他配置。"),
+    ("delete.none_remaining", "已没有任何配置。"),
+    ("delete.create_new", "创建新配置：envswitch set <name> -e KEY=value"),
+    ("delete.remaining", "剩余的配置：{names}"),
+    ("delete.completed", "删除操作已完成。"),
+    ("import.dry_run_results", "试运行结果："),
+    ("import.would_import", "将导入 {count} 个配置："),
+    ("import.conflicts", "发现 {count} 个冲突："),
+    ("import.conflicts_skipped", "发现 {count} 个冲突（已跳过）："),
+    ("import.already_exists", "{name}（已存在）"),
+    ("import.errors_found", "发现 {count} 个错误："),
+    ("import.errors_occurred", "出现 {count} 个错误："),
+    ("import.run_without_dry_run", "去掉 --dry-run 重新运行即可真正导入"),
+    ("import.imported", "成功导入 {count} 个配置："),
+    ("import.overwrote", "覆盖了 {count} 个已有配置："),
+    ("import.merged", "与 {count} 个已有配置合并："),
+    ("import.resolve", "解决冲突："),
+    ("import.none", "没有导入任何配置"),
+    ("export.specific", "正在导出 {count} 个指定的配置：{names}"),
+    ("export.done", "已导出 {count} 个配置"),
+    ("export.hint_import", "在另一台机器上导入"),
+    ("export.hint_view", "查看导出的内容"),
+];
+
+/// Language of user-facing messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    ZhCn,
+}
+
+impl Locale {
+    /// Names accepted by [`Locale::parse`], for error messages
+    pub const NAMES: &'static [&'static str] = &["en", "zh-CN"];
+
+    /// Parse a language tag such as "en", "zh-CN" or "zh_CN.UTF-8"
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.split('.').next().unwrap_or_default().replace('_', "-").to_lowercase();
+        match tag.as_str() {
+            "en" | "en-us" | "en-gb" | "c" | "posix" => Some(Locale::En),
+            "zh" | "zh-cn" | "zh-hans" | "zh-sg" => Some(Locale::ZhCn),
+            _ => None,
+        }
+    }
+
+    /// Locale from `$ENVSWITCH_LANG`, then the `language` setting; unknown names mean English
+    pub fn detect(setting: Option<&str>) -> Self {
+        std::env::var(LANG_ENV_VAR).ok()
+            .and_then(|tag| Self::parse(&tag))
+            .or_else(|| setting.and_then(Self::parse))
+            .unwrap_or_default()
+    }
+
+    fn messages(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => EN,
+            Locale::ZhCn => ZH_CN,
+        }
+    }
+
+    /// Text of a message in this locale, falling back to English and then to the key itself
+    pub fn text(self, key: &'static str) -> &'static str {
+        let lookup = |messages: &[(&'static str, &'static str)]| {
+            messages.iter().find(|(k, _)| *k == key).map(|(_, text)| *text)
+        };
+        lookup(self.messages()).or_else(|| lookup(EN)).unwrap_or(key)
+    }
+}
+
+/// Text of a message in `locale` with `{name}` placeholders replaced
+pub fn format(locale: Locale, key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    let mut message = locale.text(key).to_string();
+    for (name, value) in args {
+        message = message.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text.split('{').skip(1).filter_map(|rest| rest.split('}').next()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_locale_parse() {
+        assert_eq!(Locale::parse("zh-CN"), Some(Locale::ZhCn));
+        assert_eq!(Locale::parse("zh_CN.UTF-8"), Some(Locale::ZhCn));
+        assert_eq!(Locale::parse("en"), Some(Locale::En));
+        assert_eq!(Locale::parse("fr"), None);
+    }
+
+    #[test]
+    fn test_translations_match_english() {
+        for (key, text) in ZH_CN {
+            let english = EN.iter().find(|(k, _)| k == key)
+                .unwrap_or_else(|| panic!("'{}' has no English text", key));
+            assert_eq!(placeholders(text), placeholders(english.1), "placeholders of '{}' differ", key);
+        }
+    }
+
+    #[test]
+    fn test_missing_keys_fall_back() {
+        assert_eq!(Locale::ZhCn.text("set.dry_run_create"), "试运行：将创建配置 '{alias}'");
+        assert_eq!(Locale::ZhCn.text("test.only_english"), "test.only_english");
+        assert_eq!(Locale::En.text("list.empty"), "No configurations found");
+        assert_eq!(format(Locale::En, "set.dry_run_create", &[("alias", &"deepseek")]), "Dry run: would create configuration 'deepseek'");
+    }
+
+    #[test]
+    fn test_msg_follows_the_context_locale() {
+        let english = crate::output::OutputContext::default();
+        let chinese = english.clone().with_locale(Locale::ZhCn);
+        assert_eq!(msg!(&english, "delete.cancelled"), "Deletion cancelled.");
+        assert_eq!(msg!(&chinese, "delete.cancelled"), "已取消删除。");
+        assert_eq!(msg!(&chinese, "set.dry_run_create", alias = "dev"), "试运行：将创建配置 'dev'");
+    }
+}
@@ -0,0 +1,389 @@
+use crate::messages::Locale;
+use crate::timing::Timings;
+use std::cell::Cell;
+use std::io::Write;
+
+/// Print a message to stdout, decorated according to the [`OutputContext`] given first
+///
+/// Shell code meant for `eval` is printed with `println!` instead, so values are never rewritten.
+#[macro_export]
+macro_rules! outln {
+    ($output:expr) => {
+        $crate::output::emit($output, "", false)
+    };
+    ($output:expr, $($arg:tt)*) => {
+        $crate::output::emit($output, &format!($($arg)*), false)
+    };
+}
+
+/// Print a message to stderr, decorated according to the [`OutputContext`] given first
+#[macro_export]
+macro_rules! eoutln {
+    ($output:expr) => {
+        $crate::output::emit($output, "", true)
+    };
+    ($output:expr, $($arg:tt)*) => {
+        $crate::output::emit($output, &format!($($arg)*), true)
+    };
+}
+
+/// Leading symbols of messages: the `--no-emoji` replacement and whether `--quiet` drops the line
+///
+/// Symbols with a variation selector come before their plain form.
+const SYMBOLS: &[(&str, &str, bool)] = &[
+    ("✅", "[OK]", false),
+    ("❌", "[ERROR]", false),
+    ("⚠️", "[WARN]", false),
+    ("⚠", "[WARN]", false),
+    ("💡", "[HINT]", true),
+    ("ℹ️", "[INFO]", true),
+    ("ℹ", "[INFO]", true),
+    ("🚀", "[NEXT]", true),
+    ("📚", "[DOCS]", true),
+    ("❓", "[HELP]", true),
+    ("🎉", "[DONE]", true),
+    ("📊", "[SUMMARY]", true),
+    ("📝", "[NOTE]", true),
+    ("⏱️", "[TIME]", true),
+    ("💾", "[SAVE]", true),
+    ("📥", "[IMPORT]", true),
+    ("👀", "[WATCH]", true),
+    ("👋", "[BYE]", true),
+    ("🔍", "[CHECK]", false),
+    ("🔄", "[UPDATE]", false),
+    ("📋", "[LIST]", false),
+    ("📭", "[EMPTY]", false),
+    ("📦", "[ARCHIVE]", false),
+    ("📄", "[FILE]", false),
+    ("📁", "[DIR]", false),
+    ("🧩", "[PARTIAL]", false),
+    ("⏳", "[EXPIRY]", false),
+    ("🗑️", "[DELETE]", false),
+    ("✂️", "[TRIM]", false),
+    ("🔒", "[LOCK]", false),
+    ("⏭️", "[SKIP]", false),
+    ("🔗", "[MERGE]", false),
+    ("⭐", "[ACTIVE]", false),
+    ("★", "[PIN]", false),
+    ("✓", "[OK]", false),
+    ("✗", "[X]", false),
+    ("•", "-", false),
+];
+
+/// Variable the shell wrappers set when the output of envswitch is eval'd
+pub const EVAL_ENV_VAR: &str = "ENVSWITCH_EVAL";
+
+/// Whether stdout feeds an `eval`, decided once per run from `--eval` and [`EVAL_ENV_VAR`]
+///
+/// In eval mode the welcome message is skipped, prompts fail instead of waiting for an
+/// answer, and [`outln!`] writes to stderr like [`eoutln!`], so stdout only ever carries
+/// shell code. envswitch prints no colours, so there is nothing to turn off for those.
+///
+/// With `--report json` stdout is likewise kept for the JSON operation report, see
+/// [`crate::utils::display_operation_report`]; prompts and messages go to stderr.
+///
+/// With `--strict` the warnings counted by [`ExecutionContext::record_warnings`] fail the command.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionContext {
+    pub eval: bool,
+    pub json_report: bool,
+    pub strict: bool,
+    /// Step timings for `--timings` and `--verbose`, shared with the managers the router creates
+    pub timings: Timings,
+    /// Warnings shown so far in this run
+    warnings: Cell<usize>,
+}
+
+impl ExecutionContext {
+    /// Eval mode if `eval` is set or [`EVAL_ENV_VAR`] has a value other than `0`
+    pub fn detect(eval: bool) -> Self {
+        let from_env = std::env::var(EVAL_ENV_VAR).is_ok_and(|value| !value.is_empty() && value != "0");
+        Self { eval: eval || from_env, ..Self::default() }
+    }
+
+    /// Print the operation report as JSON on stdout, moving everything else to stderr
+    pub fn with_json_report(self, json_report: bool) -> Self {
+        Self { json_report, ..self }
+    }
+
+    /// Treat warnings as errors
+    pub fn with_strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
+    }
+
+    pub fn with_timings(self, timings: Timings) -> Self {
+        Self { timings, ..self }
+    }
+
+    /// Count `count` warnings that were shown, see [`ExecutionContext::warnings_shown`]
+    pub fn record_warnings(&self, count: usize) {
+        self.warnings.set(self.warnings.get() + count);
+    }
+
+    /// Number of warnings shown so far, by [`crate::utils::display_warning`],
+    /// [`crate::utils::WarningAggregator::display`] or anything else that recorded them
+    pub fn warnings_shown(&self) -> usize {
+        self.warnings.get()
+    }
+
+    /// Whether stdout only carries machine output, shell code or a JSON report
+    pub fn stdout_reserved(&self) -> bool {
+        self.eval || self.json_report
+    }
+
+    /// Fail fast instead of asking `prompt` when nobody can answer it
+    pub fn check_prompt(&self, prompt: &str) -> std::io::Result<()> {
+        if self.eval {
+            return Err(std::io::Error::other(format!(
+                "Cannot ask \"{}\" while the output is eval'd ({}=1 or --eval); run the command without them to answer it",
+                prompt.trim().trim_end_matches(':').trim(), EVAL_ENV_VAR
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How messages are decorated and where they go, created once per run by the router from the
+/// global flags and settings and passed to every handler that prints
+#[derive(Debug, Clone, Default)]
+pub struct OutputContext {
+    /// Leave out hints, summaries and progress messages
+    pub quiet: bool,
+    /// Replace leading emoji with ASCII tags
+    pub no_emoji: bool,
+    /// Only print ASCII: emoji become tags like with `no_emoji`, table markers become `*` and `x`
+    pub ascii: bool,
+    /// Add the length to masked secrets and flag empty ones, see [`crate::utils::mask_value`]
+    pub mask_length_hints: bool,
+    /// Show timestamps in UTC instead of the local time zone, see [`crate::utils::format_timestamp`]
+    pub utc: bool,
+    /// Language of messages looked up with [`msg!`]
+    pub locale: Locale,
+    /// Whether stdout is eval'd or kept for a JSON report, and whether warnings are errors
+    pub execution: ExecutionContext,
+    /// Whether the last line printed to stdout, or to stderr, belonged to a hint that `quiet` drops
+    stdout_in_hint: Cell<bool>,
+    stderr_in_hint: Cell<bool>,
+}
+
+impl OutputContext {
+    pub fn new(quiet: bool, no_emoji: bool) -> Self {
+        Self { quiet, no_emoji, ..Self::default() }
+    }
+    
+    pub fn with_ascii(self, ascii: bool) -> Self {
+        Self { ascii, ..self }
+    }
+    
+    pub fn with_mask_length_hints(self, mask_length_hints: bool) -> Self {
+        Self { mask_length_hints, ..self }
+    }
+    
+    pub fn with_utc(self, utc: bool) -> Self {
+        Self { utc, ..self }
+    }
+    
+    pub fn with_locale(self, locale: Locale) -> Self {
+        Self { locale, ..self }
+    }
+    
+    pub fn with_execution(self, execution: ExecutionContext) -> Self {
+        Self { execution, ..self }
+    }
+    
+    /// Whether the terminal is unlikely to show Unicode glyphs: `TERM=dumb` or a non-UTF-8 locale
+    ///
+    /// The locale is taken from `LC_ALL`, `LC_CTYPE` and `LANG`, in that order; if none is set
+    /// the terminal is assumed to cope.
+    pub fn terminal_lacks_unicode() -> bool {
+        let var = |name| std::env::var(name).ok().filter(|value: &String| !value.is_empty());
+        if var("TERM").as_deref() == Some("dumb") {
+            return true;
+        }
+        ["LC_ALL", "LC_CTYPE", "LANG"].into_iter()
+            .find_map(var)
+            .is_some_and(|locale| {
+                let locale = locale.to_lowercase();
+                !locale.contains("utf-8") && !locale.contains("utf8")
+            })
+    }
+
+    /// Symbol for a check that passed or failed, e.g. in status tables
+    pub fn mark(&self, ok: bool) -> &'static str {
+        if self.ascii {
+            return if ok { "*" } else { "x" };
+        }
+        match (ok, self.no_emoji) {
+            (true, false) => "✓",
+            (false, false) => "✗",
+            (true, true) => "[OK]",
+            (false, true) => "[X]",
+        }
+    }
+
+    /// Show `prompt` on stderr without a line break, so an answer can follow on the same line
+    ///
+    /// Prompts never go to stdout, which may be eval'd or carry a JSON report, and `quiet`
+    /// doesn't drop them.
+    pub fn prompt(&self, prompt: &str) -> std::io::Result<()> {
+        let decoration = OutputContext { quiet: false, ..self.clone() };
+        let rendered = decoration.render(prompt, &mut false).unwrap_or_default();
+        let mut stderr = std::io::stderr();
+        write!(stderr, "{}", rendered)?;
+        stderr.flush()
+    }
+
+    /// Marker in front of pinned configurations
+    pub fn pin(&self) -> &'static str {
+        if self.ascii { "*" } else { "★" }
+    }
+    
+    /// Decorate a message line by line
+    ///
+    /// Only the symbol at the start of a line is replaced, so values printed inside
+    /// messages and shell code are left alone. In quiet mode a dropped hint takes the
+    /// indented and blank lines after it along. Returns None if nothing is left to print.
+    pub fn render(&self, text: &str, in_hint: &mut bool) -> Option<String> {
+        let mut lines = Vec::new();
+        for line in text.split('\n') {
+            let trimmed = line.trim_start();
+            let indent = &line[..line.len() - trimmed.len()];
+            let symbol = SYMBOLS.iter().find(|(symbol, _, _)| trimmed.starts_with(symbol));
+
+            if self.quiet {
+                if symbol.is_some_and(|(_, _, hint)| *hint) {
+                    *in_hint = true;
+                    continue;
+                }
+                if *in_hint && (trimmed.is_empty() || !indent.is_empty()) {
+                    continue;
+                }
+                *in_hint = false;
+            }
+
+            match symbol {
+                Some((symbol, tag, _)) if self.no_emoji || self.ascii => {
+                    lines.push(format!("{}{} {}", indent, tag, trimmed[symbol.len()..].trim_start()));
+                }
+                _ => lines.push(line.to_string()),
+            }
+        }
+
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+/// Print a rendered message; used by [`outln!`] and [`eoutln!`]
+pub fn emit(output: &OutputContext, text: &str, to_stderr: bool) {
+    let to_stderr = to_stderr || output.execution.stdout_reserved();
+    let in_hint = if to_stderr { &output.stderr_in_hint } else { &output.stdout_in_hint };
+    let mut state = in_hint.get();
+    let rendered = output.render(text, &mut state);
+    in_hint.set(state);
+
+    if let Some(rendered) = rendered {
+        // Like println!, but a closed pipe (e.g. `| head`) is not worth a panic
+        let _ = if to_stderr {
+            writeln!(std::io::stderr(), "{}", rendered)
+        } else {
+            writeln!(std::io::stdout(), "{}", rendered)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_all(context: OutputContext, messages: &[&str]) -> String {
+        let mut in_hint = false;
+        messages.iter()
+            .filter_map(|message| context.render(message, &mut in_hint))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    const SET_OUTPUT: &[&str] = &[
+        "✂️  a:K had surrounding whitespace or control characters; trimmed",
+        "✅ Configuration 'a' created successfully!",
+        "📝 1 environment variables configured",
+        "",
+        "🚀 Next steps:",
+        "   envswitch use a           # Activate this configuration",
+        "   envswitch list             # See all configurations",
+        "⚠️  a:API_KEY looks too short",
+        "export API_KEY='✅ value'",
+    ];
+
+    #[test]
+    fn test_render_default_keeps_messages() {
+        assert_eq!(render_all(OutputContext::default(), SET_OUTPUT), SET_OUTPUT.join("\n"));
+    }
+
+    #[test]
+    fn test_render_no_emoji() {
+        let expected = "\
+[TRIM] a:K had surrounding whitespace or control characters; trimmed
+[OK] Configuration 'a' created successfully!
+[NOTE] 1 environment variables configured
+
+[NEXT] Next steps:
+   envswitch use a           # Activate this configuration
+   envswitch list             # See all configurations
+[WARN] a:API_KEY looks too short
+export API_KEY='✅ value'";
+        assert_eq!(render_all(OutputContext::new(false, true), SET_OUTPUT), expected);
+    }
+
+    #[test]
+    fn test_render_quiet() {
+        let expected = "\
+✂️  a:K had surrounding whitespace or control characters; trimmed
+✅ Configuration 'a' created successfully!
+⚠️  a:API_KEY looks too short
+export API_KEY='✅ value'";
+        assert_eq!(render_all(OutputContext::new(true, false), SET_OUTPUT), expected);
+        assert_eq!(OutputContext::new(true, false).render("💡 Hint\n   more", &mut false), None);
+    }
+
+    #[test]
+    fn test_render_quiet_no_emoji() {
+        let expected = "\
+[TRIM] a:K had surrounding whitespace or control characters; trimmed
+[OK] Configuration 'a' created successfully!
+[WARN] a:API_KEY looks too short
+export API_KEY='✅ value'";
+        assert_eq!(render_all(OutputContext::new(true, true), SET_OUTPUT), expected);
+    }
+
+    #[test]
+    fn test_eval_context_refuses_prompts() {
+        assert!(ExecutionContext::default().check_prompt("Continue?").is_ok());
+        let error = ExecutionContext { eval: true, ..Default::default() }.check_prompt("Value for 'KEY': ").unwrap_err();
+        assert_eq!(error.to_string(), "Cannot ask \"Value for 'KEY'\" while the output is eval'd (ENVSWITCH_EVAL=1 or --eval); run the command without them to answer it");
+    }
+
+    #[test]
+    fn test_warnings_are_counted_per_context() {
+        let execution = ExecutionContext::default();
+        execution.record_warnings(2);
+        execution.record_warnings(1);
+        assert_eq!(execution.warnings_shown(), 3);
+        assert_eq!(ExecutionContext::default().warnings_shown(), 0);
+    }
+
+    #[test]
+    fn test_mark() {
+        assert_eq!(OutputContext::default().mark(true), "✓");
+        assert_eq!(OutputContext::new(false, true).mark(false), "[X]");
+        assert_eq!(OutputContext::default().with_ascii(true).mark(true), "*");
+        assert_eq!(OutputContext::new(false, true).with_ascii(true).mark(false), "x");
+        assert_eq!(OutputContext::default().with_ascii(true).pin(), "*");
+    }
+
+    #[test]
+    fn test_render_ascii() {
+        let rendered = render_all(OutputContext::default().with_ascii(true), &SET_OUTPUT[..2]);
+        assert_eq!(rendered, "[TRIM] a:K had surrounding whitespace or control characters; trimmed\n[OK] Configuration 'a' created successfully!");
+    }
+}
@@ -1,5 +1,7 @@
 use std::env;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
 use crate::error::{EnvError, EnvResult};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -13,7 +15,70 @@ pub enum ShellType {
 #[derive(Debug, Clone)]
 pub enum ShellCommandFormat {
     Export, // export KEY=VALUE
-    Set,    // set -x KEY VALUE
+    Set,    // set -gx KEY VALUE
+}
+
+/// Lifetime of variables set by generated commands
+///
+/// Only fish distinguishes scopes; other shells always use session scope.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum VariableScope {
+    /// Global to the current shell session (`set -gx`)
+    #[default]
+    Session,
+    /// Fish universal variables shared by all sessions and persisted (`set -Ux`)
+    Universal,
+}
+
+/// How a PATH-style list variable is combined with its existing value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListOp {
+    /// Put the value in front of the existing entries
+    Prepend,
+    /// Put the value after the existing entries
+    Append,
+}
+
+/// Separator between entries of PATH-style variables
+pub const LIST_SEPARATOR: char = ':';
+
+impl std::str::FromStr for VariableScope {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "session" => Ok(VariableScope::Session),
+            "universal" => Ok(VariableScope::Universal),
+            _ => Err(format!("Invalid scope '{}'. Expected session or universal", s)),
+        }
+    }
+}
+
+/// How the current shell was determined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetectionMethod {
+    /// The $ENVSWITCH_SHELL override
+    Override,
+    /// The $SHELL environment variable
+    ShellVariable,
+    /// The $_ variable holding the invoking program
+    ProcessName,
+    /// The name of the parent process
+    ParentProcess,
+    /// A parent process lookup cached earlier in this terminal session
+    Cached,
+    /// A shell-specific version variable such as $ZSH_VERSION
+    VersionVariable,
+    /// Nothing matched
+    Fallback,
+}
+
+/// Result of shell detection
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellDetection {
+    pub shell: ShellType,
+    pub method: DetectionMethod,
 }
 
 pub struct ShellDetector;
@@ -21,41 +86,118 @@ pub struct ShellDetector;
 impl ShellDetector {
     /// Detect the current shell type using multiple methods
     pub fn detect_shell() -> ShellType {
+        Self::detect().shell
+    }
+    
+    /// Detect the current shell type along with the method that identified it
+    pub fn detect() -> ShellDetection {
+        let found = |shell, method| ShellDetection { shell, method };
+        
+        // An explicit override always wins
+        if let Ok(value) = env::var("ENVSWITCH_SHELL") {
+            if !value.trim().is_empty() {
+                return found(Self::shell_from_name(&value), DetectionMethod::Override);
+            }
+        }
+        
         // Method 1: Check $SHELL environment variable
         if let Ok(shell_path) = env::var("SHELL") {
             if let Some(shell_type) = Self::parse_shell_from_path(&shell_path) {
-                return shell_type;
+                return found(shell_type, DetectionMethod::ShellVariable);
             }
         }
         
         // Method 2: Check $0 (current process name)
         if let Ok(args) = env::var("_") {
             if let Some(shell_type) = Self::parse_shell_from_path(&args) {
-                return shell_type;
+                return found(shell_type, DetectionMethod::ProcessName);
             }
         }
         
         // Method 3: Check parent process (Unix only)
         #[cfg(unix)]
         {
-            if let Some(shell_type) = Self::detect_parent_shell() {
-                return shell_type;
+            if let Some((shell_type, method)) = Self::detect_parent_shell() {
+                return found(shell_type, method);
             }
         }
         
         // Method 4: Check common shell-specific environment variables
         if env::var("ZSH_VERSION").is_ok() {
-            return ShellType::Zsh;
+            return found(ShellType::Zsh, DetectionMethod::VersionVariable);
         }
         if env::var("FISH_VERSION").is_ok() {
-            return ShellType::Fish;
+            return found(ShellType::Fish, DetectionMethod::VersionVariable);
         }
         if env::var("BASH_VERSION").is_ok() {
-            return ShellType::Bash;
+            return found(ShellType::Bash, DetectionMethod::VersionVariable);
         }
         
         // Default to unknown
-        ShellType::Unknown("unknown".to_string())
+        found(ShellType::Unknown("unknown".to_string()), DetectionMethod::Fallback)
+    }
+    
+    /// Startup file where the shell integration is installed
+    pub fn shell_config_file(shell_type: &ShellType) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        match shell_type {
+            ShellType::Zsh => Some(home.join(".zshrc")),
+            ShellType::Bash => Some(home.join(".bashrc")),
+            ShellType::Fish => Some(home.join(".config").join("fish").join("config.fish")),
+            ShellType::Unknown(_) => None,
+        }
+    }
+    
+    /// Check whether the eval wrapper from install-shell-integration.sh appears in the shell startup file
+    ///
+    /// Returns None when the shell is unknown or its startup file doesn't exist.
+    pub fn is_integration_installed(shell_type: &ShellType) -> Option<bool> {
+        let config_file = Self::shell_config_file(shell_type)?;
+        let content = std::fs::read_to_string(config_file).ok()?;
+        Some(content.contains("EnvSwitch Shell Integration") || content.contains("EnvSwitch Fish Shell Integration"))
+    }
+    
+    /// Block `init` adds to the startup file: the `envswitch-use` function and the default configuration
+    ///
+    /// Uses the markers of install-shell-integration.sh, so either installer sees the other's block.
+    pub fn integration_block(shell_type: &ShellType) -> Option<&'static str> {
+        match shell_type {
+            ShellType::Bash | ShellType::Zsh => Some(r#"
+# EnvSwitch Shell Integration - START
+# This section was automatically added by envswitch init
+envswitch-use() { eval "$(ENVSWITCH_EVAL=1 envswitch use "$@")"; }
+eval "$(envswitch shellenv --activate)"
+# EnvSwitch Shell Integration - END
+"#),
+            ShellType::Fish => Some(r#"
+# EnvSwitch Fish Shell Integration - START
+# This section was automatically added by envswitch init
+function envswitch-use
+    eval (ENVSWITCH_EVAL=1 envswitch use $argv)
+end
+envswitch shellenv --activate --shell fish | source
+# EnvSwitch Fish Shell Integration - END
+"#),
+            ShellType::Unknown(_) => None,
+        }
+    }
+    
+    /// Append [`ShellDetector::integration_block`] to the startup file unless an integration is already there
+    ///
+    /// Returns the startup file and whether it changed, or None for shells without a known startup file.
+    pub fn install_integration(shell_type: &ShellType) -> std::io::Result<Option<(PathBuf, bool)>> {
+        let (Some(config_file), Some(block)) = (Self::shell_config_file(shell_type), Self::integration_block(shell_type)) else {
+            return Ok(None);
+        };
+        if Self::is_integration_installed(shell_type) == Some(true) {
+            return Ok(Some((config_file, false)));
+        }
+        if let Some(parent) = config_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&config_file)?;
+        std::io::Write::write_all(&mut file, block.as_bytes())?;
+        Ok(Some((config_file, true)))
     }
     
     /// Parse shell type from a path string
@@ -73,28 +215,68 @@ impl ShellDetector {
         }
     }
     
+    /// Shell type from a name or path, e.g. $ENVSWITCH_SHELL or `--shell`; unrecognized names are kept as unknown shells
+    pub fn shell_from_name(value: &str) -> ShellType {
+        Self::parse_shell_from_path(value.trim())
+            .unwrap_or_else(|| ShellType::Unknown(value.trim().to_string()))
+    }
+    
     /// Detect shell from parent process (Unix only)
+    ///
+    /// The result is cached per parent process and terminal so repeated
+    /// invocations from the same shell don't spawn `ps` again.
     #[cfg(unix)]
-    fn detect_parent_shell() -> Option<ShellType> {
+    fn detect_parent_shell() -> Option<(ShellType, DetectionMethod)> {
         use std::process::Command;
         
         // Get parent process ID
         let ppid = unsafe { libc::getppid() };
+        let cache_file = Self::session_cache_file(ppid);
+        
+        if let Some(cached) = cache_file.as_ref().and_then(|path| std::fs::read_to_string(path).ok()) {
+            if let Some(shell_type) = Self::parse_shell_from_path(cached.trim()) {
+                return Some((shell_type, DetectionMethod::Cached));
+            }
+        }
         
         // Try to get process name using ps
         if let Ok(output) = Command::new("ps")
-            .args(&["-p", &ppid.to_string(), "-o", "comm="])
+            .args(["-p", &ppid.to_string(), "-o", "comm="])
             .output()
         {
             if let Ok(comm) = String::from_utf8(output.stdout) {
-                let comm = comm.trim();
-                return Self::parse_shell_from_path(comm);
+                let shell_type = Self::parse_shell_from_path(comm.trim())?;
+                
+                // Caching is best effort; detection works without it
+                if let Some(path) = cache_file {
+                    if let Some(parent) = path.parent() {
+                        let _ = std::fs::create_dir_all(parent);
+                    }
+                    let _ = std::fs::write(path, shell_type.to_string());
+                }
+                
+                return Some((shell_type, DetectionMethod::ParentProcess));
             }
         }
         
         None
     }
     
+    /// Cache file for the shell detected for a parent process in the current terminal
+    #[cfg(unix)]
+    fn session_cache_file(ppid: libc::pid_t) -> Option<PathBuf> {
+        let tty = unsafe {
+            let name = libc::ttyname(0);
+            if name.is_null() {
+                "notty".to_string()
+            } else {
+                std::ffi::CStr::from_ptr(name).to_string_lossy().trim_start_matches('/').replace('/', "_")
+            }
+        };
+        
+        crate::types::ConfigPaths::resolve_cache_dir().ok().map(|dir| dir.join(format!("shell-{}-{}", ppid, tty)))
+    }
+    
     /// Get the appropriate command format for a shell type
     pub fn get_shell_command_format(shell_type: &ShellType) -> ShellCommandFormat {
         match shell_type {
@@ -108,6 +290,26 @@ impl ShellDetector {
     pub fn generate_env_commands(
         shell_type: &ShellType,
         variables: &HashMap<String, String>,
+    ) -> EnvResult<String> {
+        Self::generate_env_commands_with_scope(shell_type, variables, VariableScope::Session)
+    }
+    
+    /// Generate shell commands to set environment variables in the given scope
+    pub fn generate_env_commands_with_scope(
+        shell_type: &ShellType,
+        variables: &HashMap<String, String>,
+        scope: VariableScope,
+    ) -> EnvResult<String> {
+        Self::generate_env_commands_with_list_ops(shell_type, variables, &HashMap::new(), scope)
+    }
+    
+    /// Generate shell commands to set environment variables, prepending or
+    /// appending to PATH-style variables listed in `list_ops`
+    pub fn generate_env_commands_with_list_ops(
+        shell_type: &ShellType,
+        variables: &HashMap<String, String>,
+        list_ops: &HashMap<String, ListOp>,
+        scope: VariableScope,
     ) -> EnvResult<String> {
         if variables.is_empty() {
             return Ok(String::new());
@@ -116,18 +318,34 @@ impl ShellDetector {
         let format = Self::get_shell_command_format(shell_type);
         let mut commands = Vec::new();
         
-        for (key, value) in variables {
+        // Sorted so the same configuration always produces the same script
+        let mut sorted: Vec<_> = variables.iter().collect();
+        sorted.sort();
+        for (key, value) in sorted {
             // Validate environment variable name
             crate::error::validate_env_var_name(key)?;
             
-            let command = match format {
-                ShellCommandFormat::Export => {
+            let command = match (format.clone(), list_ops.get(key)) {
+                (ShellCommandFormat::Export, None) => {
                     // For bash/zsh: export KEY='value'
                     format!("export {}='{}'", key, Self::escape_value_for_export(value))
                 }
-                ShellCommandFormat::Set => {
-                    // For fish: set -x KEY 'value'
-                    format!("set -x {} '{}'", key, Self::escape_value_for_fish(value))
+                (ShellCommandFormat::Export, Some(ListOp::Prepend)) => {
+                    // export KEY='value'"${KEY:+:$KEY}" avoids a trailing separator when KEY is empty
+                    format!("export {key}='{}'\"${{{key}:+:${key}}}\"", Self::escape_value_for_export(value), key = key)
+                }
+                (ShellCommandFormat::Export, Some(ListOp::Append)) => {
+                    format!("export {key}=\"${{{key}:+${key}:}}\"'{}'", Self::escape_value_for_export(value), key = key)
+                }
+                (ShellCommandFormat::Set, op) => {
+                    // For fish: set -gx KEY 'value' (or -Ux for universal variables)
+                    let flags = Self::fish_scope_flags(scope);
+                    let position = match op {
+                        None => "",
+                        Some(ListOp::Prepend) => " --prepend",
+                        Some(ListOp::Append) => " --append",
+                    };
+                    format!("set {}{} {} '{}'", flags, position, key, Self::escape_value_for_fish(value))
                 }
             };
             commands.push(command);
@@ -136,10 +354,56 @@ impl ShellDetector {
         Ok(commands.join("\n"))
     }
     
+    /// Generate shell commands that remove exactly one entry from a PATH-style variable
+    pub fn generate_list_removal_commands(
+        shell_type: &ShellType,
+        key: &str,
+        value: &str,
+        scope: VariableScope,
+    ) -> EnvResult<String> {
+        crate::error::validate_env_var_name(key)?;
+        
+        let command = match Self::get_shell_command_format(shell_type) {
+            ShellCommandFormat::Export => {
+                // Pad with separators so the first and last entries match too
+                format!(
+                    "{key}=\":${key}:\"; {key}=\"${{{key}//:'{value}':/:}}\"; {key}=\"${{{key}#:}}\"; {key}=\"${{{key}%:}}\"; export {key}",
+                    key = key,
+                    value = Self::escape_value_for_export(value)
+                )
+            }
+            ShellCommandFormat::Set => {
+                format!(
+                    "set {} {} (string match -v -- '{}' ${})",
+                    Self::fish_scope_flags(scope), key, Self::escape_value_for_fish(value), key
+                )
+            }
+        };
+        
+        Ok(command)
+    }
+    
+    /// Flags for fish `set` in the given scope
+    fn fish_scope_flags(scope: VariableScope) -> &'static str {
+        match scope {
+            VariableScope::Session => "-gx",
+            VariableScope::Universal => "-Ux",
+        }
+    }
+    
     /// Generate shell commands to unset environment variables
     pub fn generate_unset_commands(
         shell_type: &ShellType,
         variable_names: &[String],
+    ) -> EnvResult<String> {
+        Self::generate_unset_commands_with_scope(shell_type, variable_names, VariableScope::Session)
+    }
+    
+    /// Generate shell commands to unset environment variables in the given scope
+    pub fn generate_unset_commands_with_scope(
+        shell_type: &ShellType,
+        variable_names: &[String],
+        scope: VariableScope,
     ) -> EnvResult<String> {
         if variable_names.is_empty() {
             return Ok(String::new());
@@ -150,8 +414,9 @@ impl ShellDetector {
         for name in variable_names {
             crate::error::validate_env_var_name(name)?;
             
-            let command = match shell_type {
-                ShellType::Fish => format!("set -e {}", name),
+            let command = match (shell_type, scope) {
+                (ShellType::Fish, VariableScope::Session) => format!("set -e {}", name),
+                (ShellType::Fish, VariableScope::Universal) => format!("set -eU {}", name),
                 _ => format!("unset {}", name),
             };
             commands.push(command);
@@ -168,8 +433,9 @@ impl ShellDetector {
     
     /// Escape value for fish set command
     fn escape_value_for_fish(value: &str) -> String {
-        // Fish uses the same escaping as bash for single quotes
-        value.replace('\'', "'\"'\"'")
+        // Unlike bash, fish reads \\ and \' as escapes inside single quotes, so backslashes are doubled;
+        // single quotes are escaped the same way as for bash
+        value.replace('\\', "\\\\").replace('\'', "'\"'\"'")
     }
     
     /// Get shell-specific configuration instructions
@@ -177,7 +443,10 @@ impl ShellDetector {
         match shell_type {
             ShellType::Zsh => {
                 r#"# Add to your ~/.zshrc:
-alias envswitch-use='eval "$(envswitch use $1)"'
+envswitch-use() { eval "$(ENVSWITCH_EVAL=1 envswitch use "$@")"; }
+
+# Follow changes made on other machines (synced config directory):
+# while read -r cmd; do eval "$cmd"; done < <(envswitch watch)
 
 # Usage:
 # envswitch-use deepseek
@@ -186,16 +455,27 @@ alias envswitch-use='eval "$(envswitch use $1)"'
             ShellType::Fish => {
                 r#"# Add to your ~/.config/fish/config.fish:
 function envswitch-use
-    eval (envswitch use $argv[1])
+    eval (ENVSWITCH_EVAL=1 envswitch use $argv)
 end
 
+# Follow changes made on other machines (synced config directory):
+# envswitch watch | while read -l cmd; eval $cmd; end
+
 # Usage:
 # envswitch-use deepseek
-# envswitch-use kimi"#.to_string()
+# envswitch-use kimi
+
+# Variables are set with 'set -gx' and last for the current session.
+# 'envswitch use <config> --scope universal' uses 'set -Ux' instead:
+# the values persist across restarts and apply to every running fish
+# session, and stay until erased with 'set -eU'."#.to_string()
             }
             ShellType::Bash => {
                 r#"# Add to your ~/.bashrc:
-alias envswitch-use='eval "$(envswitch use $1)"'
+envswitch-use() { eval "$(ENVSWITCH_EVAL=1 envswitch use "$@")"; }
+
+# Follow changes made on other machines (synced config directory):
+# while read -r cmd; do eval "$cmd"; done < <(envswitch watch)
 
 # Usage:
 # envswitch-use deepseek
@@ -204,15 +484,29 @@ alias envswitch-use='eval "$(envswitch use $1)"'
             ShellType::Unknown(name) => {
                 format!(r#"# Shell '{}' is not fully supported.
 # Try using the generic approach:
-eval "$(envswitch use <config-name>)"
+eval "$(ENVSWITCH_EVAL=1 envswitch use <config-name>)"
 
 # Or add to your shell's configuration file:
-alias envswitch-use='eval "$(envswitch use $1)"'"#, name)
+envswitch-use() {{ eval "$(ENVSWITCH_EVAL=1 envswitch use "$@")"; }}"#, name)
             }
         }
     }
 }
 
+impl std::fmt::Display for DetectionMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DetectionMethod::Override => write!(f, "$ENVSWITCH_SHELL"),
+            DetectionMethod::ShellVariable => write!(f, "$SHELL"),
+            DetectionMethod::ProcessName => write!(f, "process name"),
+            DetectionMethod::ParentProcess => write!(f, "parent process"),
+            DetectionMethod::Cached => write!(f, "parent process (cached)"),
+            DetectionMethod::VersionVariable => write!(f, "shell version variable"),
+            DetectionMethod::Fallback => write!(f, "fallback"),
+        }
+    }
+}
+
 impl std::fmt::Display for ShellType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -287,7 +581,62 @@ mod tests {
 
         let commands = ShellDetector::generate_env_commands(&ShellType::Fish, &vars).unwrap();
         
-        assert_eq!(commands, "set -x TEST_VAR 'test_value'");
+        assert_eq!(commands, "set -gx TEST_VAR 'test_value'");
+    }
+
+    #[test]
+    fn test_generate_env_commands_fish_scopes() {
+        let mut vars = HashMap::new();
+        vars.insert("TEST_VAR".to_string(), "test_value".to_string());
+        
+        let session = ShellDetector::generate_env_commands_with_scope(&ShellType::Fish, &vars, VariableScope::Session).unwrap();
+        assert_eq!(session, "set -gx TEST_VAR 'test_value'");
+        
+        let universal = ShellDetector::generate_env_commands_with_scope(&ShellType::Fish, &vars, VariableScope::Universal).unwrap();
+        assert_eq!(universal, "set -Ux TEST_VAR 'test_value'");
+        
+        // Scope only matters for fish
+        let bash = ShellDetector::generate_env_commands_with_scope(&ShellType::Bash, &vars, VariableScope::Universal).unwrap();
+        assert_eq!(bash, "export TEST_VAR='test_value'");
+        
+        let names = vec!["TEST_VAR".to_string()];
+        let unset = ShellDetector::generate_unset_commands_with_scope(&ShellType::Fish, &names, VariableScope::Universal).unwrap();
+        assert_eq!(unset, "set -eU TEST_VAR");
+    }
+
+    #[test]
+    fn test_generate_env_commands_list_ops() {
+        let mut vars = HashMap::new();
+        vars.insert("PATH".to_string(), "/opt/tool/bin".to_string());
+        let mut list_ops = HashMap::new();
+        list_ops.insert("PATH".to_string(), ListOp::Prepend);
+        
+        let bash = ShellDetector::generate_env_commands_with_list_ops(&ShellType::Bash, &vars, &list_ops, VariableScope::Session).unwrap();
+        assert_eq!(bash, "export PATH='/opt/tool/bin'\"${PATH:+:$PATH}\"");
+        
+        let fish = ShellDetector::generate_env_commands_with_list_ops(&ShellType::Fish, &vars, &list_ops, VariableScope::Session).unwrap();
+        assert_eq!(fish, "set -gx --prepend PATH '/opt/tool/bin'");
+        
+        list_ops.insert("PATH".to_string(), ListOp::Append);
+        let bash = ShellDetector::generate_env_commands_with_list_ops(&ShellType::Bash, &vars, &list_ops, VariableScope::Session).unwrap();
+        assert_eq!(bash, "export PATH=\"${PATH:+$PATH:}\"'/opt/tool/bin'");
+    }
+
+    #[test]
+    fn test_generate_list_removal_commands() {
+        let bash = ShellDetector::generate_list_removal_commands(&ShellType::Bash, "PATH", "/opt/tool/bin", VariableScope::Session).unwrap();
+        assert!(bash.contains("${PATH//:'/opt/tool/bin':/:}"));
+        assert!(bash.ends_with("export PATH"));
+        
+        let fish = ShellDetector::generate_list_removal_commands(&ShellType::Fish, "PATH", "/opt/tool/bin", VariableScope::Session).unwrap();
+        assert_eq!(fish, "set -gx PATH (string match -v -- '/opt/tool/bin' $PATH)");
+    }
+
+    #[test]
+    fn test_variable_scope_from_str() {
+        assert_eq!("session".parse::<VariableScope>().unwrap(), VariableScope::Session);
+        assert_eq!("universal".parse::<VariableScope>().unwrap(), VariableScope::Universal);
+        assert!("global".parse::<VariableScope>().is_err());
     }
 
     #[test]
@@ -351,6 +700,10 @@ mod tests {
             ShellDetector::escape_value_for_fish("with'quote"),
             "with'\"'\"'quote"
         );
+        assert_eq!(
+            ShellDetector::escape_value_for_fish("dir\\"),
+            "dir\\\\"
+        );
     }
 
     #[test]
@@ -380,6 +733,33 @@ mod tests {
         assert!(unknown_instructions.contains("not fully supported"));
     }
 
+    #[test]
+    fn test_detect_reports_method() {
+        let detection = ShellDetector::detect();
+        assert_eq!(detection.shell, ShellDetector::detect_shell());
+        if detection.method == DetectionMethod::Fallback {
+            assert!(matches!(detection.shell, ShellType::Unknown(_)));
+        }
+    }
+
+    #[test]
+    fn test_shell_from_name() {
+        assert_eq!(ShellDetector::shell_from_name("fish"), ShellType::Fish);
+        assert_eq!(ShellDetector::shell_from_name("/usr/bin/zsh"), ShellType::Zsh);
+        assert_eq!(ShellDetector::shell_from_name(" nu "), ShellType::Unknown("nu".to_string()));
+    }
+
+    #[test]
+    fn test_shell_config_file() {
+        if let Some(path) = ShellDetector::shell_config_file(&ShellType::Zsh) {
+            assert!(path.ends_with(".zshrc"));
+        }
+        if let Some(path) = ShellDetector::shell_config_file(&ShellType::Fish) {
+            assert!(path.ends_with("fish/config.fish"));
+        }
+        assert!(ShellDetector::shell_config_file(&ShellType::Unknown("sh".to_string())).is_none());
+    }
+
     #[test]
     fn test_detect_shell_with_env_vars() {
         // This test sets environment variables to simulate different shells
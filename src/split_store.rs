@@ -0,0 +1,423 @@
+//! Split storage layout: one file per configuration plus a small index
+//!
+//! Reading a single configuration, listing names or checking the active
+//! configuration only touches the index and the files involved, so large stores
+//! don't have to be parsed as a whole for every command.
+//!
+//! ```text
+//! <config_dir>/store-index.json   names, aliases, active configuration, settings
+//! <config_dir>/configs/<name>.json
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::config::{read_activation_state, ArchiveFilter, update_activation_state, write_private_file, ConfigManager, ConfigStore, EnvConfig, Settings};
+use crate::error::{ConfigError, ConfigResult};
+use crate::lock::StoreLock;
+use crate::types::constants::{SPLIT_CONFIGS_DIR_NAME, SPLIT_INDEX_FILE_NAME};
+use crate::types::ConfigPaths;
+
+/// Everything of a store except the configurations themselves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoreIndex {
+    configs: BTreeMap<String, IndexEntry>,
+    active_config: Option<String>,
+    #[serde(default = "Utc::now")]
+    last_modified: DateTime<Utc>,
+    version: String,
+    #[serde(default, skip_serializing_if = "Settings::is_empty")]
+    settings: Settings,
+}
+
+/// Where a configuration is stored and which aliases resolve to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexEntry {
+    /// File name inside the `configs` directory
+    file: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+    /// Copied from the configuration, so listings can leave it out without reading it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    archived: bool,
+}
+
+impl StoreIndex {
+    fn empty() -> Self {
+        let store = ConfigStore::default();
+        Self {
+            configs: BTreeMap::new(),
+            active_config: None,
+            last_modified: store.last_modified,
+            version: store.version,
+            settings: store.settings,
+        }
+    }
+
+    /// Take over everything but the configurations from `store`
+    fn update_from(&mut self, store: &ConfigStore) {
+        self.active_config = store.active_config.clone();
+        self.last_modified = store.last_modified;
+        self.version = store.version.clone();
+        self.settings = store.settings.clone();
+    }
+
+    /// Add or update the entry of a configuration, keeping the file it already has
+    fn insert(&mut self, config: &EnvConfig) -> &IndexEntry {
+        if !self.configs.contains_key(&config.alias) {
+            let file = self.unused_file_name(&config.alias);
+            self.configs.insert(config.alias.clone(), IndexEntry { file, aliases: Vec::new(), archived: false });
+        }
+        let entry = self.configs.get_mut(&config.alias).expect("entry was just inserted");
+        entry.aliases = config.aliases.clone();
+        entry.archived = config.archived;
+        entry
+    }
+
+    /// `<name>.json`, with a numeric suffix when a file differing only by case is taken,
+    /// since names like "Work" and "work" would share a file on case-insensitive file systems
+    fn unused_file_name(&self, name: &str) -> String {
+        let taken: HashSet<String> = self.configs.values().map(|entry| entry.file.to_lowercase()).collect();
+        let mut file = format!("{}.json", name);
+        let mut suffix = 2;
+        while taken.contains(&file.to_lowercase()) {
+            file = format!("{}-{}.json", name, suffix);
+            suffix += 1;
+        }
+        file
+    }
+
+    fn into_store(self, configs: HashMap<String, EnvConfig>) -> ConfigStore {
+        ConfigStore {
+            configs,
+            active_config: self.active_config,
+            activated_at: None,
+            last_modified: self.last_modified,
+            version: self.version,
+            settings: self.settings,
+        }
+    }
+}
+
+/// Configuration manager for the split storage layout
+///
+/// `FileConfigManager` hands its work to this manager while the
+/// storage-layout setting is "split"; the store is validated and locked there.
+pub struct SplitFileConfigManager {
+    config_paths: ConfigPaths,
+    index_file: PathBuf,
+    configs_dir: PathBuf,
+}
+
+impl SplitFileConfigManager {
+    /// Create a split-layout manager for the default configuration directory
+    pub fn new() -> ConfigResult<Self> {
+        Ok(Self::with_paths(ConfigPaths::new()?))
+    }
+
+    /// Create with custom paths (mainly for testing)
+    pub fn with_paths(config_paths: ConfigPaths) -> Self {
+        let index_file = config_paths.config_dir.join(SPLIT_INDEX_FILE_NAME);
+        let configs_dir = config_paths.config_dir.join(SPLIT_CONFIGS_DIR_NAME);
+        Self { config_paths, index_file, configs_dir }
+    }
+
+    /// Path of the index file
+    pub fn index_path(&self) -> &Path {
+        &self.index_file
+    }
+
+    /// Directory holding one file per configuration
+    pub fn configs_dir(&self) -> &Path {
+        &self.configs_dir
+    }
+
+    /// Check if the store is kept in the split layout
+    pub fn exists(&self) -> bool {
+        self.index_file.exists()
+    }
+
+    /// Load every configuration; the store is not validated
+    pub fn load_store(&self) -> ConfigResult<ConfigStore> {
+        let index = self.load_index()?;
+        let mut configs = HashMap::with_capacity(index.configs.len());
+        for (name, entry) in &index.configs {
+            configs.insert(name.clone(), self.read_config(name, entry)?);
+        }
+        Ok(index.into_store(configs))
+    }
+
+    /// Names of the configurations `filter` includes, sorted, read from the index alone
+    pub fn list_configs_matching(&self, filter: ArchiveFilter) -> ConfigResult<Vec<String>> {
+        Ok(self.load_index()?.configs.into_iter()
+            .filter(|(_, entry)| match filter {
+                ArchiveFilter::Unarchived => !entry.archived,
+                ArchiveFilter::Archived => entry.archived,
+                ArchiveFilter::All => true,
+            })
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Write the whole store, rewriting only configuration files whose content changed
+    ///
+    /// The index is written last, and files of removed configurations are deleted after it.
+    pub fn write_store(&self, store: &ConfigStore) -> ConfigResult<()> {
+        self.ensure_configs_dir()?;
+        let mut index = self.load_index().unwrap_or_else(|_| StoreIndex::empty());
+        index.configs.retain(|name, _| store.configs.contains_key(name));
+        index.update_from(store);
+
+        let mut names: Vec<&String> = store.configs.keys().collect();
+        names.sort();
+        for name in names {
+            let config = &store.configs[name];
+            let entry = index.insert(config).clone();
+            self.write_config(&entry, config)?;
+        }
+        self.write_index(&index)?;
+
+        let files: HashSet<&str> = index.configs.values().map(|entry| entry.file.as_str()).collect();
+        for entry in fs::read_dir(&self.configs_dir).map_err(ConfigError::FileError)? {
+            let path = entry.map_err(ConfigError::FileError)?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if name.ends_with(".json") && !files.contains(name) {
+                fs::remove_file(&path).map_err(ConfigError::FileError)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete the index and the configuration files, e.g. after going back to config.json
+    pub fn remove(&self) -> ConfigResult<()> {
+        if self.index_file.exists() {
+            fs::remove_file(&self.index_file).map_err(ConfigError::FileError)?;
+        }
+        if self.configs_dir.exists() {
+            fs::remove_dir_all(&self.configs_dir).map_err(ConfigError::FileError)?;
+        }
+        Ok(())
+    }
+
+    /// The index; an empty one when the store has not been written yet
+    fn load_index(&self) -> ConfigResult<StoreIndex> {
+        if !self.index_file.exists() {
+            return Ok(StoreIndex::empty());
+        }
+        let content = fs::read_to_string(&self.index_file).map_err(ConfigError::FileError)?;
+        serde_json::from_str(&content).map_err(ConfigError::JsonError)
+    }
+
+    fn read_config(&self, name: &str, entry: &IndexEntry) -> ConfigResult<EnvConfig> {
+        let content = fs::read_to_string(self.configs_dir.join(&entry.file)).map_err(|e| {
+            ConfigError::FileError(std::io::Error::new(e.kind(), format!("configuration '{}' ({}): {}", name, entry.file, e)))
+        })?;
+        serde_json::from_str(&content).map_err(ConfigError::JsonError)
+    }
+
+    fn write_config(&self, entry: &IndexEntry, config: &EnvConfig) -> ConfigResult<()> {
+        let content = serde_json::to_string_pretty(config).map_err(ConfigError::JsonError)?;
+        let path = self.configs_dir.join(&entry.file);
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == content) {
+            return Ok(());
+        }
+        write_private_file(&path, content.as_bytes())
+    }
+
+    fn write_index(&self, index: &StoreIndex) -> ConfigResult<()> {
+        let content = serde_json::to_string_pretty(index).map_err(ConfigError::JsonError)?;
+        write_private_file(&self.index_file, content.as_bytes())
+    }
+
+    fn ensure_configs_dir(&self) -> ConfigResult<()> {
+        self.config_paths.ensure_config_dir()?;
+        if !self.configs_dir.exists() {
+            fs::create_dir_all(&self.configs_dir).map_err(ConfigError::FileError)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&self.configs_dir, fs::Permissions::from_mode(0o700))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> ConfigResult<StoreLock> {
+        self.ensure_configs_dir()?;
+        StoreLock::acquire(&self.config_paths.config_dir)
+    }
+
+    /// Index plus a store holding only the named configurations that exist
+    fn load_partial(&self, names: &[&str]) -> ConfigResult<(StoreIndex, ConfigStore)> {
+        let index = self.load_index()?;
+        let mut configs = HashMap::new();
+        for name in names {
+            if let Some(entry) = index.configs.get(*name) {
+                configs.insert(name.to_string(), self.read_config(name, entry)?);
+            }
+        }
+
+        let mut store = index.clone().into_store(configs);
+        if store.active_config.is_some() {
+            store.activated_at = read_activation_state(&self.config_paths.state_file).and_then(|state| state.activated_at);
+        }
+        Ok((index, store))
+    }
+
+    /// Write back the named configurations of a store from [`load_partial`](Self::load_partial)
+    ///
+    /// Named configurations missing from the store are deleted.
+    fn write_partial(&self, mut index: StoreIndex, store: &ConfigStore, names: &[&str]) -> ConfigResult<()> {
+        index.update_from(store);
+
+        let mut removed = Vec::new();
+        for name in names {
+            match store.configs.get(*name) {
+                Some(config) => {
+                    let entry = index.insert(config).clone();
+                    self.write_config(&entry, config)?;
+                }
+                None => removed.extend(index.configs.remove(*name)),
+            }
+        }
+        self.write_index(&index)?;
+
+        for entry in removed {
+            fs::remove_file(self.configs_dir.join(&entry.file)).map_err(ConfigError::FileError)?;
+        }
+        update_activation_state(&self.config_paths.state_file, store)
+    }
+}
+
+impl ConfigManager for SplitFileConfigManager {
+    fn load_configs(&self) -> ConfigResult<ConfigStore> {
+        let mut store = self.load_store()?;
+        store.validate()?;
+        if store.active_config.is_some() {
+            store.activated_at = read_activation_state(&self.config_paths.state_file).and_then(|state| state.activated_at);
+        }
+        Ok(store)
+    }
+
+    fn save_configs(&self, store: &ConfigStore) -> ConfigResult<()> {
+        store.validate()?;
+        let _lock = self.lock()?;
+        self.write_store(store)?;
+        update_activation_state(&self.config_paths.state_file, store)
+    }
+
+    fn create_config(&self, alias: String, variables: HashMap<String, String>, description: Option<String>) -> ConfigResult<()> {
+        // Name and case collisions are checked against every configuration
+        let _lock = self.lock()?;
+        let mut store = self.load_configs()?;
+        store.add_config(EnvConfig::new(alias, variables, description)?)?;
+        self.save_configs(&store)
+    }
+
+    fn update_config(&self, alias: String, variables: HashMap<String, String>, description: Option<String>) -> ConfigResult<()> {
+        let _lock = self.lock()?;
+        let (index, mut store) = self.load_partial(&[&alias])?;
+        store.update_config(&alias, variables, description)?;
+        self.write_partial(index, &store, &[&alias])
+    }
+
+    fn delete_config(&self, alias: String) -> ConfigResult<()> {
+        let _lock = self.lock()?;
+        // The file itself is not read, so a broken configuration can still be deleted
+        let (index, mut store) = self.load_partial(&[])?;
+        if index.configs.contains_key(&alias) {
+            store.insert_config(EnvConfig::new(alias.clone(), HashMap::new(), None)?);
+        }
+        store.remove_config(&alias)?;
+        self.write_partial(index, &store, &[&alias])
+    }
+
+    fn get_config(&self, alias: &str) -> ConfigResult<Option<EnvConfig>> {
+        let index = self.load_index()?;
+        index.configs.get(alias)
+            .map(|entry| self.read_config(alias, entry))
+            .transpose()
+    }
+
+    fn list_configs(&self) -> ConfigResult<Vec<String>> {
+        self.list_configs_matching(ArchiveFilter::All)
+    }
+
+    fn set_active_config(&self, alias: String) -> ConfigResult<()> {
+        let _lock = self.lock()?;
+        let (index, mut store) = self.load_partial(&[&alias])?;
+        store.set_active(alias)?;
+        self.write_partial(index, &store, &[])
+    }
+
+    fn get_active_config(&self) -> ConfigResult<Option<String>> {
+        Ok(self.load_index()?.active_config)
+    }
+
+    fn clear_active_config(&self) -> ConfigResult<()> {
+        let _lock = self.lock()?;
+        let (index, mut store) = self.load_partial(&[])?;
+        store.clear_active();
+        self.write_partial(index, &store, &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_manager() -> (TempDir, SplitFileConfigManager) {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = SplitFileConfigManager::with_paths(ConfigPaths::in_dir(temp_dir.path()));
+        (temp_dir, manager)
+    }
+
+    fn variables(value: &str) -> HashMap<String, String> {
+        [("API_URL".to_string(), value.to_string())].into_iter().collect()
+    }
+
+    #[test]
+    fn test_configs_are_stored_in_separate_files() {
+        let (_temp_dir, manager) = create_manager();
+        manager.create_config("work".to_string(), variables("https://work"), None).unwrap();
+        manager.create_config("home".to_string(), variables("https://home"), None).unwrap();
+        manager.set_active_config("work".to_string()).unwrap();
+
+        assert!(manager.configs_dir().join("work.json").exists());
+        assert!(manager.configs_dir().join("home.json").exists());
+        assert_eq!(manager.list_configs().unwrap(), vec!["home".to_string(), "work".to_string()]);
+        assert_eq!(manager.get_active_config().unwrap().as_deref(), Some("work"));
+        assert_eq!(manager.get_config("home").unwrap().unwrap().variables, variables("https://home"));
+        assert!(manager.load_configs().unwrap().activated_at.is_some());
+
+        // A broken file only matters to commands that read that configuration
+        fs::write(manager.configs_dir().join("home.json"), "{").unwrap();
+        manager.update_config("work".to_string(), variables("https://work/v2"), None).unwrap();
+        assert_eq!(manager.get_config("work").unwrap().unwrap().variables, variables("https://work/v2"));
+        assert!(manager.get_config("home").is_err());
+
+        manager.delete_config("home".to_string()).unwrap();
+        assert!(!manager.configs_dir().join("home.json").exists());
+        assert_eq!(manager.list_configs().unwrap(), vec!["work".to_string()]);
+    }
+
+    #[test]
+    fn test_write_store_removes_stale_files_and_separates_case_variants() {
+        let (_temp_dir, manager) = create_manager();
+        let mut store = ConfigStore::default();
+        store.add_config(EnvConfig::new("Work".to_string(), variables("a"), None).unwrap()).unwrap();
+        store.add_config_allowing_case_collision(EnvConfig::new("work".to_string(), variables("b"), None).unwrap()).unwrap();
+        manager.save_configs(&store).unwrap();
+        assert!(manager.configs_dir().join("Work.json").exists());
+        assert!(manager.configs_dir().join("work-2.json").exists());
+        assert_eq!(manager.get_config("work").unwrap().unwrap().variables, variables("b"));
+
+        store.remove_config("Work").unwrap();
+        manager.save_configs(&store).unwrap();
+        assert!(!manager.configs_dir().join("Work.json").exists());
+        assert_eq!(manager.load_configs().unwrap().configs, store.configs);
+    }
+}
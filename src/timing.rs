@@ -0,0 +1,139 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::output::OutputContext;
+
+/// Step timings of one run, kept on the [`ExecutionContext`](crate::output::ExecutionContext)
+///
+/// Clones share the recorded steps, so the managers created by the router record into the
+/// same table the router prints. The default records nothing and costs nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    state: Option<Arc<TimingState>>,
+}
+
+#[derive(Debug)]
+struct TimingState {
+    verbose: bool,
+    summary: bool,
+    started: Instant,
+    steps: Mutex<Vec<(&'static str, Duration)>>,
+}
+
+impl Timings {
+    /// Turn on step timings: `verbose` prints each step as it finishes, `summary` collects them for [`Timings::print_summary`]
+    pub fn new(verbose: bool, summary: bool) -> Self {
+        if !verbose && !summary {
+            return Self::default();
+        }
+        let state = TimingState { verbose, summary, started: Instant::now(), steps: Mutex::new(Vec::new()) };
+        Self { state: Some(Arc::new(state)) }
+    }
+
+    /// Time a step until the returned guard is dropped
+    pub fn step(&self, name: &'static str) -> StepTimer<'_> {
+        StepTimer { timings: self, name, started: self.state.as_ref().map(|_| Instant::now()) }
+    }
+
+    /// Per step, in order of first appearance: how often it ran and how long it took in total
+    pub fn step_totals(&self) -> Vec<(&'static str, usize, Duration)> {
+        self.state.as_ref()
+            .and_then(|state| state.steps.lock().ok().map(|steps| totals(&steps)))
+            .unwrap_or_default()
+    }
+
+    /// Print the per-step table on stderr when `--timings` was given
+    ///
+    /// Steps can be nested, e.g. `validate` inside `load_store`, so the totals may add up
+    /// to more than the duration of the whole command.
+    pub fn print_summary(&self, output: &OutputContext) {
+        let Some(state) = self.state.as_ref().filter(|state| state.summary) else {
+            return;
+        };
+        let totals = self.step_totals();
+        let width = totals.iter().map(|(name, _, _)| name.len()).max().unwrap_or(0);
+        let report = totals.iter().fold(
+            crate::utils::OperationReport::new("Timing", totals.len(), state.started.elapsed()),
+            |report, (name, count, total)| {
+                report.with_detail(&format!("{:<width$}", name, width = width), format!("{:>3}x  {:>9.1}ms", count, millis(*total)))
+            },
+        );
+
+        for line in crate::utils::render_operation_report(&report, crate::utils::ReportMode::Plain) {
+            eoutln!(output, "{}", line);
+        }
+    }
+}
+
+/// Guard returned by [`Timings::step`]; records the step when dropped
+pub struct StepTimer<'a> {
+    timings: &'a Timings,
+    name: &'static str,
+    started: Option<Instant>,
+}
+
+impl Drop for StepTimer<'_> {
+    fn drop(&mut self) {
+        let (Some(state), Some(started)) = (&self.timings.state, self.started) else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        if state.verbose {
+            // Written directly: steps are timed in the managers, below the handlers that print
+            let _ = writeln!(std::io::stderr(), "step {} took {:.1}ms", self.name, millis(elapsed));
+        }
+        if state.summary {
+            if let Ok(mut steps) = state.steps.lock() {
+                steps.push((self.name, elapsed));
+            }
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn totals(steps: &[(&'static str, Duration)]) -> Vec<(&'static str, usize, Duration)> {
+    let mut totals: Vec<(&'static str, usize, Duration)> = Vec::new();
+    for &(name, elapsed) in steps {
+        match totals.iter_mut().find(|(total_name, _, _)| *total_name == name) {
+            Some((_, count, total)) => {
+                *count += 1;
+                *total += elapsed;
+            }
+            None => totals.push((name, 1, elapsed)),
+        }
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totals_keep_first_appearance_order() {
+        let ms = Duration::from_millis;
+        let steps = [("load_store", ms(3)), ("validate", ms(1)), ("load_store", ms(2)), ("detect_shell", ms(5))];
+        assert_eq!(totals(&steps), vec![
+            ("load_store", 2, ms(5)),
+            ("validate", 1, ms(1)),
+            ("detect_shell", 1, ms(5)),
+        ]);
+        assert!(totals(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_clones_share_steps() {
+        let timings = Timings::new(false, true);
+        drop(timings.clone().step("load_store"));
+        drop(timings.step("load_store"));
+        assert_eq!(timings.step_totals().len(), 1);
+        assert_eq!(timings.step_totals()[0].1, 2);
+
+        let disabled = Timings::default();
+        drop(disabled.step("load_store"));
+        assert!(disabled.step_totals().is_empty());
+    }
+}
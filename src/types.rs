@@ -18,6 +18,69 @@ pub mod constants {
     /// State file name
     pub const STATE_FILE_NAME: &str = "state.json";
     
+    /// Marker file in the state directory recording that the welcome message was shown
+    pub const WELCOME_MARKER_NAME: &str = ".welcome_shown";
+    
+    /// Git repository of the configuration history inside the state directory
+    pub const HISTORY_DIR_NAME: &str = "history";
+    
+    /// Cache directory inside the configuration directory when `$ENVSWITCH_CONFIG_DIR` is set
+    pub const CACHE_DIR_NAME: &str = "cache";
+    
+    /// Default backup directory inside the configuration directory
+    pub const BACKUP_DIR_NAME: &str = "backups";
+    
+    /// Prefix of backup file names
+    pub const BACKUP_FILE_PREFIX: &str = "config_backup_";
+    
+    /// Extension of gzip-compressed backups; older versions wrote plain `.json` backups
+    pub const COMPRESSED_BACKUP_EXTENSION: &str = ".json.gz";
+    
+    /// Sidecar file in the backup directory holding backup notes
+    pub const BACKUP_INDEX_NAME: &str = "index.json";
+    
+    /// Index file of the split storage layout
+    pub const SPLIT_INDEX_FILE_NAME: &str = "store-index.json";
+    
+    /// Directory of the split storage layout holding one file per configuration
+    pub const SPLIT_CONFIGS_DIR_NAME: &str = "configs";
+    
+    /// Environment variable overriding the configuration directory
+    pub const CONFIG_DIR_ENV_VAR: &str = "ENVSWITCH_CONFIG_DIR";
+    
+    /// Environment variable overriding the backup directory
+    pub const BACKUP_DIR_ENV_VAR: &str = "ENVSWITCH_BACKUP_DIR";
+    
+    /// Variable set by `shellenv --activate` to the default configuration it applied
+    pub const DEFAULT_MARKER_VAR: &str = "ENVSWITCH_DEFAULT";
+    
+    /// Credentials shorter than this are probably truncated or fake
+    pub const MIN_TOKEN_LENGTH: usize = 16;
+    
+    /// Credentials with fewer bits of entropy per character are probably not random
+    pub const MIN_TOKEN_ENTROPY_BITS: f64 = 3.0;
+    
+    /// Exit status when the requested configuration doesn't exist
+    pub const EXIT_CODE_NOT_FOUND: i32 = 3;
+    
+    /// Exit status when a file or directory can't be read or written for lack of permissions
+    pub const EXIT_CODE_PERMISSION_DENIED: i32 = 4;
+    
+    /// Exit status when the store or an imported file is malformed or fails validation
+    pub const EXIT_CODE_INVALID_DATA: i32 = 5;
+    
+    /// Exit status of `set --fail-on-noop` when nothing changed
+    pub const EXIT_CODE_NOOP: i32 = 6;
+    
+    /// Exit status of `current` when no configuration is active; 4 is taken by permission errors
+    pub const EXIT_CODE_NO_ACTIVE: i32 = 7;
+    
+    /// Exit status of `set --replace` when removing many variables was declined or couldn't be asked
+    pub const EXIT_CODE_DEFAULT_REPLACE_GUARD: i32 = 8;
+    
+    /// Variables `set --replace` may remove without asking, unless that is over half of them
+    pub const DEFAULT_REPLACE_GUARD: usize = 10;
+    
     /// Maximum number of configurations
     pub const MAX_CONFIGS: usize = 100;
     
@@ -30,56 +93,185 @@ pub mod constants {
     /// Maximum length for environment variable values
     pub const MAX_ENV_VAR_VALUE_LENGTH: usize = 1000;
     
-    /// Supported environment variable prefixes for Claude Code
+    /// Environment variables used by Claude Code
     pub const CLAUDE_ENV_VARS: &[&str] = &[
         "ANTHROPIC_BASE_URL",
         "ANTHROPIC_MODEL", 
         "ANTHROPIC_AUTH_TOKEN",
         "ANTHROPIC_SMALL_FAST_MODEL",
+        "ANTHROPIC_API_KEY",
     ];
 }
 
+/// Ready-made sets of variables offered when creating a configuration
+pub mod presets {
+    /// One variable of a preset and how to ask for it
+    #[derive(Debug, Clone, Copy)]
+    pub struct PresetVariable {
+        pub key: &'static str,
+        pub prompt: &'static str,
+        pub default: Option<&'static str>,
+        pub required: bool,
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct Preset {
+        pub name: &'static str,
+        pub description: &'static str,
+        pub variables: &'static [PresetVariable],
+    }
+
+    /// Claude Code against the Anthropic API or a compatible endpoint
+    pub const CLAUDE: Preset = Preset {
+        name: "claude",
+        description: "Claude Code with an Anthropic-compatible API",
+        variables: &[
+            PresetVariable { key: "ANTHROPIC_BASE_URL", prompt: "API endpoint", default: Some("https://api.anthropic.com"), required: true },
+            PresetVariable { key: "ANTHROPIC_AUTH_TOKEN", prompt: "Auth token", default: None, required: true },
+            PresetVariable { key: "ANTHROPIC_MODEL", prompt: "Model", default: None, required: false },
+            PresetVariable { key: "ANTHROPIC_SMALL_FAST_MODEL", prompt: "Small, fast model", default: None, required: false },
+            PresetVariable { key: "ANTHROPIC_API_KEY", prompt: "API key, if your endpoint wants one instead of the token", default: None, required: false },
+        ],
+    };
+
+    pub const PRESETS: &[Preset] = &[CLAUDE];
+
+    pub fn find(name: &str) -> Option<&'static Preset> {
+        PRESETS.iter().find(|preset| preset.name.eq_ignore_ascii_case(name))
+    }
+}
+
 /// Application configuration paths
+///
+/// The configuration may be synced between machines, so per-machine state and caches
+/// live in their own directories (`$XDG_STATE_HOME` and `$XDG_CACHE_HOME` on Linux).
 #[derive(Debug, Clone)]
 pub struct ConfigPaths {
     pub config_dir: std::path::PathBuf,
     pub config_file: std::path::PathBuf,
+    /// Session state and the configuration history
+    pub state_dir: std::path::PathBuf,
     pub state_file: std::path::PathBuf,
+    /// Data that can be rebuilt at any time, e.g. the detected shell of a terminal
+    pub cache_dir: std::path::PathBuf,
+}
+
+/// What determined the location of the configuration directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigDirSource {
+    /// `$ENVSWITCH_CONFIG_DIR`
+    EnvVar,
+    /// `envswitch` inside the platform configuration directory
+    Default,
+}
+
+impl std::fmt::Display for ConfigDirSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigDirSource::EnvVar => write!(f, "${}", constants::CONFIG_DIR_ENV_VAR),
+            ConfigDirSource::Default => write!(f, "default"),
+        }
+    }
 }
 
 impl ConfigPaths {
-    /// Create new ConfigPaths with default locations
-    pub fn new() -> Result<Self, crate::error::ConfigError> {
+    /// Configuration directory and what determined it
+    ///
+    /// A non-empty `$ENVSWITCH_CONFIG_DIR` is used as-is; otherwise the
+    /// `envswitch` directory inside the platform configuration directory.
+    pub fn resolve_config_dir() -> Result<(std::path::PathBuf, ConfigDirSource), crate::error::ConfigError> {
+        if let Some(dir) = std::env::var_os(constants::CONFIG_DIR_ENV_VAR).filter(|dir| !dir.is_empty()) {
+            return Ok((std::path::PathBuf::from(dir), ConfigDirSource::EnvVar));
+        }
+        
         let config_dir = dirs::config_dir()
             .ok_or(crate::error::ConfigError::InvalidConfigDir)?
             .join(constants::CONFIG_DIR_NAME);
-            
-        let config_file = config_dir.join(constants::CONFIG_FILE_NAME);
-        let state_file = config_dir.join(constants::STATE_FILE_NAME);
+        Ok((config_dir, ConfigDirSource::Default))
+    }
+    
+    /// State directory: `envswitch` inside the platform state directory
+    ///
+    /// That is `$XDG_STATE_HOME` on Linux and the local application data directory
+    /// elsewhere. With `$ENVSWITCH_CONFIG_DIR` set, the state stays in that directory.
+    pub fn resolve_state_dir() -> Result<std::path::PathBuf, crate::error::ConfigError> {
+        match Self::resolve_config_dir()? {
+            (config_dir, ConfigDirSource::EnvVar) => Ok(config_dir),
+            (_, ConfigDirSource::Default) => dirs::state_dir()
+                .or_else(dirs::data_local_dir)
+                .map(|dir| dir.join(constants::CONFIG_DIR_NAME))
+                .ok_or(crate::error::ConfigError::InvalidConfigDir),
+        }
+    }
+    
+    /// Cache directory: `envswitch` inside the platform cache directory
+    ///
+    /// With `$ENVSWITCH_CONFIG_DIR` set, the `cache` directory inside it.
+    pub fn resolve_cache_dir() -> Result<std::path::PathBuf, crate::error::ConfigError> {
+        match Self::resolve_config_dir()? {
+            (config_dir, ConfigDirSource::EnvVar) => Ok(config_dir.join(constants::CACHE_DIR_NAME)),
+            (_, ConfigDirSource::Default) => dirs::cache_dir()
+                .map(|dir| dir.join(constants::CONFIG_DIR_NAME))
+                .ok_or(crate::error::ConfigError::InvalidConfigDir),
+        }
+    }
+    
+    /// Create new ConfigPaths with default locations
+    pub fn new() -> Result<Self, crate::error::ConfigError> {
+        let (config_dir, _) = Self::resolve_config_dir()?;
+        let state_dir = Self::resolve_state_dir()?;
         
         Ok(Self {
+            config_file: config_dir.join(constants::CONFIG_FILE_NAME),
+            state_file: state_dir.join(constants::STATE_FILE_NAME),
+            cache_dir: Self::resolve_cache_dir()?,
             config_dir,
-            config_file,
-            state_file,
+            state_dir,
         })
     }
     
+    /// Everything inside one directory, laid out as with `$ENVSWITCH_CONFIG_DIR`
+    pub fn in_dir(dir: &std::path::Path) -> Self {
+        Self {
+            config_dir: dir.to_path_buf(),
+            config_file: dir.join(constants::CONFIG_FILE_NAME),
+            state_dir: dir.to_path_buf(),
+            state_file: dir.join(constants::STATE_FILE_NAME),
+            cache_dir: dir.join(constants::CACHE_DIR_NAME),
+        }
+    }
+    
+    /// Git repository of the configuration history
+    pub fn history_dir(&self) -> std::path::PathBuf {
+        self.state_dir.join(constants::HISTORY_DIR_NAME)
+    }
+    
     /// Ensure configuration directory exists
     pub fn ensure_config_dir(&self) -> Result<(), crate::error::ConfigError> {
-        if !self.config_dir.exists() {
-            std::fs::create_dir_all(&self.config_dir)?;
-            
-            // Set restrictive permissions (Unix only)
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                let mut perms = std::fs::metadata(&self.config_dir)?.permissions();
-                perms.set_mode(0o700); // rwx------
-                std::fs::set_permissions(&self.config_dir, perms)?;
-            }
+        ensure_private_dir(&self.config_dir)
+    }
+    
+    /// Ensure the state directory exists
+    pub fn ensure_state_dir(&self) -> Result<(), crate::error::ConfigError> {
+        ensure_private_dir(&self.state_dir)
+    }
+}
+
+/// Create a directory only the user can enter, leaving existing ones alone
+pub(crate) fn ensure_private_dir(dir: &std::path::Path) -> Result<(), crate::error::ConfigError> {
+    if !dir.exists() {
+        std::fs::create_dir_all(dir)?;
+        
+        // Set restrictive permissions (Unix only)
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(dir)?.permissions();
+            perms.set_mode(0o700); // rwx------
+            std::fs::set_permissions(dir, perms)?;
         }
-        Ok(())
     }
+    Ok(())
 }
 
 impl Default for ConfigPaths {
@@ -126,6 +318,70 @@ pub mod validation {
         Ok(())
     }
     
+    /// Value without leading/trailing whitespace and control characters, or None if it has none
+    ///
+    /// Line breaks and tabs inside the value are kept, e.g. for PEM certificates.
+    pub fn clean_value(value: &str) -> Option<String> {
+        let cleaned: String = value.trim()
+            .chars()
+            .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+            .collect();
+        (cleaned != value).then_some(cleaned)
+    }
+    
+    /// Check if a variable is expected to hold a credential, e.g. ANTHROPIC_AUTH_TOKEN or OPENAI_API_KEY
+    pub fn is_token_key(name: &str) -> bool {
+        let upper = name.to_uppercase();
+        upper == "API_KEY" || ["_TOKEN", "_API_KEY", "_SECRET", "_SECRET_KEY"].iter().any(|suffix| upper.ends_with(suffix))
+    }
+    
+    /// Shannon entropy of a value in bits per character
+    pub fn entropy_bits_per_char(value: &str) -> f64 {
+        let mut counts = std::collections::HashMap::new();
+        for c in value.chars() {
+            *counts.entry(c).or_insert(0usize) += 1;
+        }
+        let total = value.chars().count() as f64;
+        counts.values()
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+    
+    /// Reasons a credential value looks mis-pasted or fake; empty when it looks fine
+    ///
+    /// These are heuristics for warnings, never for rejecting a value. Only
+    /// token-style keys are checked, and references resolved on activation
+    /// (`@file:`, `@op:`, `@cmd:`) are skipped.
+    pub fn token_value_warnings(name: &str, value: &str) -> Vec<String> {
+        if !is_token_key(name) || crate::env::is_file_reference(value) || crate::env::is_lazy_reference(value) {
+            return Vec::new();
+        }
+        
+        let mut warnings = Vec::new();
+        if value.contains(['\n', '\r']) {
+            warnings.push("contains a line break, often a pasted trailing newline; it breaks exported shell lines".to_string());
+        } else if value.trim() != value || value.contains(char::is_whitespace) {
+            warnings.push("contains whitespace".to_string());
+        }
+        
+        let trimmed = value.trim();
+        if trimmed.len() >= 2 && ['"', '\''].iter().any(|&q| trimmed.starts_with(q) && trimmed.ends_with(q)) {
+            warnings.push("is wrapped in quotes, probably copied from JSON or a shell script".to_string());
+        }
+        
+        let length = trimmed.chars().count();
+        if length < MIN_TOKEN_LENGTH {
+            warnings.push(format!("is only {} characters long, shorter than most credentials", length));
+        } else if entropy_bits_per_char(trimmed) < MIN_TOKEN_ENTROPY_BITS {
+            warnings.push("has very little variety in its characters; it doesn't look like a real credential".to_string());
+        }
+        
+        warnings
+    }
+    
     /// Validate configuration alias
     pub fn validate_config_alias(alias: &str) -> Result<(), ConfigError> {
         crate::error::validate_config_name(alias)
@@ -133,7 +389,133 @@ pub mod validation {
     
     /// Check if environment variable is commonly used with Claude Code
     pub fn is_claude_env_var(name: &str) -> bool {
-        CLAUDE_ENV_VARS.iter().any(|&var| name == var)
+        CLAUDE_ENV_VARS.contains(&name)
+    }
+    
+    /// Claude variable watchlist: the built-in names followed by user-added ones
+    pub fn claude_watchlist(extra: &[String]) -> Vec<String> {
+        let mut watchlist: Vec<String> = CLAUDE_ENV_VARS.iter().map(|var| var.to_string()).collect();
+        for name in extra {
+            if !watchlist.contains(name) {
+                watchlist.push(name.clone());
+            }
+        }
+        watchlist
+    }
+    
+    /// Check if a variable is on the Claude watchlist extended with user-added names
+    pub fn is_watched_claude_var(name: &str, extra: &[String]) -> bool {
+        is_claude_env_var(name) || extra.iter().any(|var| var == name)
+    }
+    
+    /// Directories whose children are home directories, e.g. `/Users/alice`
+    const HOME_PARENTS: &[&str] = &["/home/", "/Users/"];
+    
+    /// Host name suffixes that only resolve inside a private network
+    const PRIVATE_HOST_SUFFIXES: &[&str] = &[".local", ".lan", ".internal", ".home.arpa"];
+    
+    /// Byte ranges of the home directories a value points into, e.g. `/Users/alice`, in order
+    ///
+    /// Only paths starting the value or following a separator such as `:` or `=` count,
+    /// so the path of a URL like `https://example.com/home/alice` is left alone.
+    fn home_dir_spans(value: &str) -> Vec<(usize, usize)> {
+        let is_separator = |c: char| c.is_whitespace() || ":;,='\"()".contains(c);
+        let mut spans = Vec::new();
+        for parent in HOME_PARENTS {
+            for (start, _) in value.match_indices(parent) {
+                if value[..start].chars().next_back().is_some_and(|c| !is_separator(c)) {
+                    continue;
+                }
+                let name_start = start + parent.len();
+                let end = value[name_start..]
+                    .find(|c: char| c == '/' || is_separator(c))
+                    .map_or(value.len(), |end| name_start + end);
+                if end > name_start {
+                    spans.push((start, end));
+                }
+            }
+        }
+        spans.sort();
+        spans
+    }
+    
+    /// Home directories of other users that a value points into, e.g. `/Users/alice`, in order of appearance
+    pub fn foreign_home_dirs(value: &str, local_home: &str) -> Vec<String> {
+        let local_home = local_home.trim_end_matches('/');
+        let mut homes: Vec<String> = Vec::new();
+        for (start, end) in home_dir_spans(value) {
+            let home = &value[start..end];
+            if home != local_home && !homes.iter().any(|known| known == home) {
+                homes.push(home.to_string());
+            }
+        }
+        homes
+    }
+    
+    /// IP addresses in private ranges and host names of private networks that a value refers to
+    ///
+    /// Loopback addresses and `localhost` are left out: they mean the same thing on every machine.
+    pub fn private_hosts(value: &str) -> Vec<String> {
+        let mut hosts: Vec<String> = Vec::new();
+        for token in value.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-')) {
+            let token = token.trim_matches('.');
+            let private = match token.parse::<std::net::Ipv4Addr>() {
+                Ok(ip) => ip.is_private() || ip.is_link_local(),
+                Err(_) => token.contains('.') && PRIVATE_HOST_SUFFIXES.iter()
+                    .any(|suffix| token.to_lowercase().ends_with(suffix)),
+            };
+            if private && !hosts.iter().any(|known| known == token) {
+                hosts.push(token.to_string());
+            }
+        }
+        hosts
+    }
+    
+    /// Reasons a value may only work on the machine it came from; empty when it looks portable
+    ///
+    /// Like [`token_value_warnings`], these are heuristics for warnings only.
+    pub fn host_specific_warnings(value: &str, local_home: Option<&str>) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some(local_home) = local_home {
+            for home in foreign_home_dirs(value, local_home) {
+                warnings.push(format!("points into another user's home directory {}", home));
+            }
+        }
+        for host in private_hosts(value) {
+            warnings.push(format!("refers to {}, which is only reachable on a private network", host));
+        }
+        warnings
+    }
+    
+    /// The value with the home directory of another user replaced by `local_home`
+    ///
+    /// None when the value has no such path, or paths into more than one other home,
+    /// where it is unclear which one to replace.
+    pub fn rewrite_foreign_home(value: &str, local_home: &str) -> Option<String> {
+        let [home] = foreign_home_dirs(value, local_home).try_into().ok()?;
+        let local_home = local_home.trim_end_matches('/');
+        let mut rewritten = String::with_capacity(value.len());
+        let mut copied = 0;
+        for (start, end) in home_dir_spans(value) {
+            if value[start..end] == home {
+                rewritten.push_str(&value[copied..start]);
+                rewritten.push_str(local_home);
+                copied = end;
+            }
+        }
+        rewritten.push_str(&value[copied..]);
+        Some(rewritten)
+    }
+    
+    /// Placeholder that `export --template` writes instead of a secret, e.g. `{{API_KEY}}`
+    pub fn template_placeholder(key: &str) -> String {
+        format!("{{{{{}}}}}", key)
+    }
+    
+    /// Name inside a `{{NAME}}` placeholder when the whole value is one
+    pub fn placeholder_name(value: &str) -> Option<&str> {
+        let name = value.strip_prefix("{{")?.strip_suffix("}}")?.trim();
+        crate::error::validate_env_var_name(name).ok().map(|_| name)
     }
 }#[
 cfg(test)]
@@ -141,6 +523,16 @@ mod tests {
     use super::*;
     use super::validation::*;
 
+    #[test]
+    fn test_claude_preset_covers_claude_variables() {
+        let mut keys: Vec<&str> = presets::CLAUDE.variables.iter().map(|variable| variable.key).collect();
+        let mut expected = constants::CLAUDE_ENV_VARS.to_vec();
+        keys.sort();
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert!(presets::find("Claude").is_some());
+    }
+
     #[test]
     fn test_validate_env_var_valid() {
         assert!(validate_env_var("VALID_NAME", "valid_value").is_ok());
@@ -154,16 +546,100 @@ mod tests {
         assert!(validate_env_var("VALID_NAME", &"x".repeat(1001)).is_err()); // Too long value
     }
 
+    #[test]
+    fn test_clean_value() {
+        assert_eq!(clean_value("sk-123\n"), Some("sk-123".to_string()));
+        assert_eq!(clean_value("  value \r\n"), Some("value".to_string()));
+        assert_eq!(clean_value("a\u{1b}b"), Some("ab".to_string()));
+        assert_eq!(clean_value("line1\nline2"), None);
+        assert_eq!(clean_value("plain"), None);
+    }
+
+    #[test]
+    fn test_token_value_warnings() {
+        let token = "sk-ant-REDACTED";
+        assert!(token_value_warnings("ANTHROPIC_AUTH_TOKEN", token).is_empty());
+        
+        // A trailing newline from a paste would otherwise end up in the exported line
+        let warnings = token_value_warnings("ANTHROPIC_AUTH_TOKEN", &format!("{}\n", token));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("line break"));
+        
+        assert!(token_value_warnings("OPENAI_API_KEY", &format!("\"{}\"", token))[0].contains("quotes"));
+        assert!(token_value_warnings("OPENAI_API_KEY", "sk-123")[0].contains("6 characters"));
+        assert!(token_value_warnings("OPENAI_API_KEY", &"ab".repeat(10))[0].contains("variety"));
+        assert!(token_value_warnings("OPENAI_API_KEY", "my token value with spaces")[0].contains("whitespace"));
+        
+        // Other variables and references are not checked
+        assert!(token_value_warnings("ANTHROPIC_MODEL", "x y").is_empty());
+        assert!(token_value_warnings("GITHUB_TOKEN", "@file:~/.gh-token").is_empty());
+    }
+
+    #[test]
+    fn test_host_specific_warnings() {
+        let home = "/home/me";
+        assert_eq!(foreign_home_dirs("/Users/alice/.cache/models", home), vec!["/Users/alice"]);
+        assert_eq!(foreign_home_dirs("/home/bob/bin:/home/me/bin:/home/bob/.local/bin", home), vec!["/home/bob"]);
+        assert!(foreign_home_dirs("/home/me/.cache", home).is_empty());
+        assert!(foreign_home_dirs("https://example.com/home/alice", home).is_empty());
+        assert_eq!(foreign_home_dirs("/home/me2/x", home), vec!["/home/me2"]);
+        
+        assert_eq!(private_hosts("http://192.168.1.20:8080/v1"), vec!["192.168.1.20"]);
+        assert_eq!(private_hosts("https://proxy.corp.internal/api,10.0.0.1"), vec!["proxy.corp.internal", "10.0.0.1"]);
+        assert!(private_hosts("http://127.0.0.1:8080 localhost https://api.anthropic.com 8.8.8.8").is_empty());
+        
+        let warnings = host_specific_warnings("/Users/alice/models http://printer.local", Some(home));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("/Users/alice") && warnings[1].contains("printer.local"));
+        assert!(host_specific_warnings("/Users/alice/models", None).is_empty());
+    }
+
+    #[test]
+    fn test_template_placeholders() {
+        assert_eq!(template_placeholder("API_KEY"), "{{API_KEY}}");
+        assert_eq!(placeholder_name("{{API_KEY}}"), Some("API_KEY"));
+        assert_eq!(placeholder_name("{{ API_KEY }}"), Some("API_KEY"));
+        assert_eq!(placeholder_name("Bearer {{API_KEY}}"), None);
+        assert_eq!(placeholder_name("{{not a name}}"), None);
+        assert_eq!(placeholder_name("{{}}"), None);
+    }
+
+    #[test]
+    fn test_rewrite_foreign_home() {
+        let home = "/home/me/";
+        assert_eq!(rewrite_foreign_home("/Users/alice/.cache", home), Some("/home/me/.cache".to_string()));
+        assert_eq!(
+            rewrite_foreign_home("/Users/alice/bin:/Users/alice2x/bin:/Users/alice", "/home/me"),
+            None,
+            "two other homes are ambiguous"
+        );
+        assert_eq!(rewrite_foreign_home("/Users/alice/a:/Users/alice/b", home), Some("/home/me/a:/home/me/b".to_string()));
+        assert_eq!(rewrite_foreign_home("/home/me/.cache", home), None);
+    }
+
     #[test]
     fn test_is_claude_env_var() {
         assert!(is_claude_env_var("ANTHROPIC_BASE_URL"));
         assert!(is_claude_env_var("ANTHROPIC_MODEL"));
         assert!(is_claude_env_var("ANTHROPIC_AUTH_TOKEN"));
         assert!(is_claude_env_var("ANTHROPIC_SMALL_FAST_MODEL"));
+        assert!(is_claude_env_var("ANTHROPIC_API_KEY"));
         assert!(!is_claude_env_var("OTHER_VAR"));
         assert!(!is_claude_env_var("ANTHROPIC_OTHER"));
     }
 
+    #[test]
+    fn test_claude_watchlist_extension() {
+        let extra = vec!["OPENROUTER_API_KEY".to_string(), "ANTHROPIC_MODEL".to_string()];
+        let watchlist = claude_watchlist(&extra);
+        
+        assert_eq!(watchlist.len(), super::constants::CLAUDE_ENV_VARS.len() + 1);
+        assert_eq!(watchlist.last().map(String::as_str), Some("OPENROUTER_API_KEY"));
+        assert!(is_watched_claude_var("OPENROUTER_API_KEY", &extra));
+        assert!(!is_watched_claude_var("OPENROUTER_API_KEY", &[]));
+        assert!(is_watched_claude_var("ANTHROPIC_API_KEY", &[]));
+    }
+
     #[test]
     fn test_runtime_state_creation() {
         let state = RuntimeState::new(Some("test".to_string()), "zsh".to_string());
@@ -179,6 +655,8 @@ mod tests {
             assert!(paths.config_dir.ends_with("envswitch"));
             assert!(paths.config_file.ends_with("config.json"));
             assert!(paths.state_file.ends_with("state.json"));
+            assert!(paths.state_file.starts_with(&paths.state_dir));
+            assert!(paths.history_dir().starts_with(&paths.state_dir));
         }
     }
 }
\ No newline at end of file
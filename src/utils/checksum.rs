@@ -0,0 +1,196 @@
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+/// Prefix identifying the hash algorithm of a checksum
+pub const CHECKSUM_PREFIX: &str = "sha256:";
+
+/// Field holding the checksum in JSON exports
+pub const JSON_CHECKSUM_FIELD: &str = "checksum";
+
+/// First line of text exports carrying the checksum of the rest of the file
+pub const TEXT_CHECKSUM_HEADER: &str = "# Checksum: ";
+
+/// Result of checking an exported file against its embedded checksum
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChecksumStatus {
+    /// The content matches the embedded checksum
+    Verified,
+    /// The file carries no checksum
+    Missing,
+    /// The content was changed or truncated after export
+    Mismatch { expected: String, actual: String },
+}
+
+impl ChecksumStatus {
+    /// User-facing error for a mismatch, or None if the file can be trusted as far as we know
+    pub fn error_message(&self) -> Option<String> {
+        match self {
+            ChecksumStatus::Mismatch { expected, actual } => Some(format!(
+                "File corrupted or truncated (checksum mismatch: expected {}, got {})",
+                expected, actual
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Compute the checksum of some bytes as "sha256:<hex>"
+pub fn compute_checksum(data: &[u8]) -> String {
+    format_digest(&Sha256::digest(data))
+}
+
+fn format_digest(digest: &[u8]) -> String {
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}{}", CHECKSUM_PREFIX, hex)
+}
+
+/// Writer passing everything on to `inner` while computing the checksum of it
+///
+/// Lets large exports be checksummed as they are written instead of in a buffer.
+pub struct ChecksumWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, hasher: Sha256::new() }
+    }
+
+    /// Checksum of everything written so far, as [`compute_checksum`] gives it
+    pub fn checksum(&self) -> String {
+        format_digest(&self.hasher.clone().finalize())
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Canonical form of a JSON document: compact, with object keys sorted
+fn canonical_json(value: &serde_json::Value) -> String {
+    // serde_json::Map is ordered by key, so the compact form is canonical
+    value.to_string()
+}
+
+/// Add a checksum field to a JSON export computed over the rest of the document
+pub fn add_json_checksum(value: &mut serde_json::Value) {
+    if let Some(object) = value.as_object_mut() {
+        object.remove(JSON_CHECKSUM_FIELD);
+    }
+    let checksum = compute_checksum(canonical_json(value).as_bytes());
+    if let Some(object) = value.as_object_mut() {
+        object.insert(JSON_CHECKSUM_FIELD.to_string(), serde_json::Value::String(checksum));
+    }
+}
+
+/// Remove the checksum field from a JSON export and check it against the document
+pub fn verify_json_checksum(value: &mut serde_json::Value) -> ChecksumStatus {
+    let expected = match value.as_object_mut().and_then(|object| object.remove(JSON_CHECKSUM_FIELD)) {
+        Some(serde_json::Value::String(expected)) => expected,
+        Some(other) => other.to_string(),
+        None => return ChecksumStatus::Missing,
+    };
+
+    let actual = compute_checksum(canonical_json(value).as_bytes());
+    if actual == expected {
+        ChecksumStatus::Verified
+    } else {
+        ChecksumStatus::Mismatch { expected, actual }
+    }
+}
+
+/// Prefix a text export with a checksum header covering everything after it
+pub fn add_text_checksum(content: &str) -> String {
+    format!("{}{}", text_checksum_header(&compute_checksum(content.as_bytes())), content)
+}
+
+/// First line of a text export with this checksum; every checksum gives a line of the same length
+pub fn text_checksum_header(checksum: &str) -> String {
+    format!("{}{}\n", TEXT_CHECKSUM_HEADER, checksum)
+}
+
+/// Check the checksum header of a text export, returning the status and the content after it
+pub fn verify_text_checksum(content: &str) -> (ChecksumStatus, &str) {
+    let Some(rest) = content.strip_prefix(TEXT_CHECKSUM_HEADER) else {
+        return (ChecksumStatus::Missing, content);
+    };
+
+    let (expected, body) = rest.split_once('\n').unwrap_or((rest, ""));
+    let expected = expected.trim().to_string();
+    let actual = compute_checksum(body.as_bytes());
+
+    if actual == expected {
+        (ChecksumStatus::Verified, body)
+    } else {
+        (ChecksumStatus::Mismatch { expected, actual }, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_checksum() {
+        assert_eq!(
+            compute_checksum(b"abc"),
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_checksum_writer_matches_compute_checksum() {
+        let mut writer = ChecksumWriter::new(Vec::new());
+        writer.write_all(b"a").unwrap();
+        writer.write_all(b"bc").unwrap();
+        assert_eq!(writer.checksum(), compute_checksum(b"abc"));
+        assert_eq!(text_checksum_header(&writer.checksum()).len(), text_checksum_header(&compute_checksum(b"")).len());
+    }
+
+    #[test]
+    fn test_json_checksum_round_trip() {
+        let mut value = serde_json::json!({"configs": {"a": {"variables": {"K": "v"}}}, "version": "1.0"});
+        add_json_checksum(&mut value);
+        assert!(value[JSON_CHECKSUM_FIELD].as_str().unwrap().starts_with(CHECKSUM_PREFIX));
+
+        // Formatting doesn't matter, only content
+        let pretty = serde_json::to_string_pretty(&value).unwrap();
+        let mut parsed: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(verify_json_checksum(&mut parsed), ChecksumStatus::Verified);
+        assert!(parsed.get(JSON_CHECKSUM_FIELD).is_none());
+
+        let mut tampered: serde_json::Value = serde_json::from_str(&pretty.replace("\"v\"", "\"w\"")).unwrap();
+        let status = verify_json_checksum(&mut tampered);
+        assert!(matches!(status, ChecksumStatus::Mismatch { .. }));
+        assert!(status.error_message().unwrap().contains("checksum mismatch"));
+
+        let mut plain = serde_json::json!({"configs": {}});
+        assert_eq!(verify_json_checksum(&mut plain), ChecksumStatus::Missing);
+    }
+
+    #[test]
+    fn test_text_checksum_round_trip() {
+        let content = "# Configuration: dev\nKEY=value\n\n";
+        let exported = add_text_checksum(content);
+        assert!(exported.starts_with(TEXT_CHECKSUM_HEADER));
+
+        let (status, body) = verify_text_checksum(&exported);
+        assert_eq!(status, ChecksumStatus::Verified);
+        assert_eq!(body, content);
+
+        // Truncation is detected
+        let truncated = &exported[..exported.len() - 5];
+        assert!(matches!(verify_text_checksum(truncated).0, ChecksumStatus::Mismatch { .. }));
+
+        assert_eq!(verify_text_checksum(content), (ChecksumStatus::Missing, content));
+    }
+}
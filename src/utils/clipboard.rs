@@ -0,0 +1,80 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Copy text to the system clipboard
+///
+/// Uses the `arboard` crate when built with the `clipboard` feature, and
+/// falls back to the platform clipboard tools (pbcopy, wl-copy, xclip,
+/// xsel, clip.exe) otherwise.
+pub fn copy_to_clipboard(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "clipboard")]
+    {
+        if let Ok(mut clipboard) = arboard::Clipboard::new() {
+            if clipboard.set_text(text.to_string()).is_ok() {
+                return Ok(());
+            }
+        }
+    }
+
+    for (program, args) in clipboard_commands() {
+        if copy_with_command(program, args, text).is_ok() {
+            return Ok(());
+        }
+    }
+
+    Err("No clipboard tool available (tried pbcopy, wl-copy, xclip, xsel, clip.exe)".into())
+}
+
+/// Candidate clipboard commands for the current platform, in order of preference
+fn clipboard_commands() -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", &[])]
+    } else if cfg!(windows) {
+        vec![("clip.exe", &[])]
+    } else {
+        vec![
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+            // WSL exposes the Windows clipboard through clip.exe
+            ("clip.exe", &[]),
+        ]
+    }
+}
+
+/// Pipe text into a clipboard command
+fn copy_with_command(program: &str, args: &[&str], text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", program, status).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipboard_commands_not_empty() {
+        assert!(!clipboard_commands().is_empty());
+    }
+
+    #[test]
+    fn test_copy_with_missing_command() {
+        let result = copy_with_command("envswitch-nonexistent-clipboard-tool", &[], "text");
+        assert!(result.is_err());
+    }
+}
@@ -1,4 +1,7 @@
+use crate::output::OutputContext;
+use serde::Serialize;
 use std::io::{self, Write};
+use std::path::Path;
 use std::time::{Duration, Instant};
 use std::thread;
 use std::collections::HashMap;
@@ -37,28 +40,28 @@ impl ProgressIndicator {
     }
     
     /// Finish the progress indicator with success
-    pub fn finish_success(&mut self, result_message: &str) {
+    pub fn finish_success(&mut self, output: &OutputContext, result_message: &str) {
         if self.is_running {
             let elapsed = self.start_time.elapsed();
-            println!(" ✅ {} ({:.1}s)", result_message, elapsed.as_secs_f64());
+            outln!(output, " ✅ {} ({:.1}s)", result_message, elapsed.as_secs_f64());
             self.is_running = false;
         }
     }
     
     /// Finish the progress indicator with error
-    pub fn finish_error(&mut self, error_message: &str) {
+    pub fn finish_error(&mut self, output: &OutputContext, error_message: &str) {
         if self.is_running {
             let elapsed = self.start_time.elapsed();
-            println!(" ❌ {} ({:.1}s)", error_message, elapsed.as_secs_f64());
+            outln!(output, " ❌ {} ({:.1}s)", error_message, elapsed.as_secs_f64());
             self.is_running = false;
         }
     }
     
     /// Finish the progress indicator with warning
-    pub fn finish_warning(&mut self, warning_message: &str) {
+    pub fn finish_warning(&mut self, output: &OutputContext, warning_message: &str) {
         if self.is_running {
             let elapsed = self.start_time.elapsed();
-            println!(" ⚠️  {} ({:.1}s)", warning_message, elapsed.as_secs_f64());
+            outln!(output, " ⚠️  {} ({:.1}s)", warning_message, elapsed.as_secs_f64());
             self.is_running = false;
         }
     }
@@ -90,96 +93,156 @@ where
     result
 }
 
-/// Display user-friendly error messages with suggestions
-pub fn display_error_with_suggestions(error: &dyn std::error::Error, verbose: bool) {
-    println!("❌ Error: {}", error);
-    
-    if verbose {
-        // Show error chain
-        let mut source = error.source();
-        let mut level = 1;
-        while let Some(err) = source {
-            println!("   {}: {}", level, err);
-            source = err.source();
-            level += 1;
-        }
-    }
-    
-    // Provide context-specific suggestions
-    let error_str = error.to_string().to_lowercase();
-    
-    if error_str.contains("permission denied") {
-        println!();
-        println!("💡 Suggestions:");
-        println!("   • Check file permissions: ls -la ~/.config/envswitch/");
-        println!("   • Ensure you have write access to the configuration directory");
-        println!("   • Try running with appropriate permissions");
-    } else if error_str.contains("no such file or directory") {
-        println!();
-        println!("💡 Suggestions:");
-        println!("   • Check if the file path is correct");
-        println!("   • Ensure the directory exists");
-        println!("   • Use absolute paths if relative paths aren't working");
-    } else if error_str.contains("not found") {
-        println!();
-        println!("💡 Suggestions:");
-        println!("   • Use 'envswitch list' to see available configurations");
-        println!("   • Check spelling of configuration names");
-        println!("   • Create the configuration first with 'envswitch set'");
-    } else if error_str.contains("json") || error_str.contains("parse") {
-        println!();
-        println!("💡 Suggestions:");
-        println!("   • Check file format - ensure it's valid JSON/ENV/YAML");
-        println!("   • Use 'envswitch import --dry-run' to preview import");
-        println!("   • Validate file content with external tools");
-    } else if error_str.contains("disk") || error_str.contains("space") {
-        println!();
-        println!("💡 Suggestions:");
-        println!("   • Check available disk space: df -h");
-        println!("   • Clean up old backup files");
-        println!("   • Use a different output location");
-    }
-}
-
 /// Display success messages with next steps
-pub fn display_success_with_next_steps(message: &str, next_steps: &[&str]) {
-    println!("✅ {}", message);
+pub fn display_success_with_next_steps(output: &OutputContext, message: &str, next_steps: &[&str]) {
+    outln!(output, "✅ {}", message);
     
     if !next_steps.is_empty() {
-        println!();
-        println!("🚀 Next steps:");
+        outln!(output);
+        outln!(output, "🚀 Next steps:");
         for step in next_steps {
-            println!("   {}", step);
+            outln!(output, "   {}", step);
         }
     }
 }
 
 /// Display warning messages
-pub fn display_warning(message: &str, details: Option<&[&str]>) {
-    println!("⚠️  {}", message);
+pub fn display_warning(output: &OutputContext, message: &str, details: Option<&[&str]>) {
+    output.execution.record_warnings(1);
+    outln!(output, "⚠️  {}", message);
     
     if let Some(details) = details {
         for detail in details {
-            println!("   • {}", detail);
+            outln!(output, "   • {}", detail);
+        }
+    }
+}
+
+/// Examples a [`WarningAggregator`] shows per warning unless the full list is asked for
+pub const WARNING_EXAMPLES: usize = 3;
+
+/// Warnings of one kind collected by a [`WarningAggregator`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarningGroup {
+    /// Leading symbol of the summary line
+    pub symbol: &'static str,
+    /// Summary with a `{count}` placeholder, e.g. "{count} variable(s) with non-standard names"
+    pub template: String,
+    /// Where the warning applied, e.g. "config:KEY", in the order they were added
+    pub examples: Vec<String>,
+}
+
+impl WarningGroup {
+    pub fn count(&self) -> usize {
+        self.examples.len()
+    }
+}
+
+/// Collects repeated warnings during a batch operation and prints one line per kind of warning
+///
+/// A file with the same problem in 500 places then gives "500 variable(s) with ..., e.g. a, b, c"
+/// instead of 500 lines that bury everything else. The verbose form lists every occurrence.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WarningAggregator {
+    groups: Vec<WarningGroup>,
+}
+
+impl WarningAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a warning shown with ⚠️
+    pub fn add(&mut self, template: &str, example: impl Into<String>) {
+        self.add_with_symbol("⚠️", template, example);
+    }
+
+    /// Record a warning under another symbol, e.g. ℹ️ for findings that may well be fine
+    pub fn add_with_symbol(&mut self, symbol: &'static str, template: &str, example: impl Into<String>) {
+        match self.groups.iter_mut().find(|group| group.template == template && group.symbol == symbol) {
+            Some(group) => group.examples.push(example.into()),
+            None => self.groups.push(WarningGroup {
+                symbol,
+                template: template.to_string(),
+                examples: vec![example.into()],
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Number of warnings added, over all groups
+    pub fn len(&self) -> usize {
+        self.groups.iter().map(WarningGroup::count).sum()
+    }
+
+    pub fn groups(&self) -> &[WarningGroup] {
+        &self.groups
+    }
+
+    /// The summary, one line per group with the first [`WARNING_EXAMPLES`] examples,
+    /// or with `verbose` a heading per group followed by every example
+    pub fn lines(&self, verbose: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        for group in &self.groups {
+            let summary = group.template.replace("{count}", &group.count().to_string());
+            if verbose {
+                lines.push(format!("{}  {}:", group.symbol, summary));
+                lines.extend(group.examples.iter().map(|example| format!("   • {}", example)));
+                continue;
+            }
+            let shown = group.examples[..group.count().min(WARNING_EXAMPLES)].join(", ");
+            let hidden = group.count().saturating_sub(WARNING_EXAMPLES);
+            lines.push(if hidden == 0 {
+                format!("{}  {}: {}", group.symbol, summary, shown)
+            } else {
+                format!("{}  {}, e.g. {} (and {} more; --verbose lists all)", group.symbol, summary, shown, hidden)
+            });
+        }
+        lines
+    }
+
+    /// Number of warnings shown with ⚠️; other symbols mark notices, not problems
+    pub fn warning_count(&self) -> usize {
+        self.groups.iter().filter(|group| group.symbol == "⚠️").map(WarningGroup::count).sum()
+    }
+
+    /// Print [`WarningAggregator::lines`], counting the warnings for `--strict`
+    pub fn display(&self, output: &OutputContext, verbose: bool) {
+        output.execution.record_warnings(self.warning_count());
+        for line in self.lines(verbose) {
+            outln!(output, "{}", line);
         }
     }
 }
 
 /// Display informational messages with icons
-pub fn display_info(message: &str, icon: &str) {
-    println!("{} {}", icon, message);
+pub fn display_info(output: &OutputContext, message: &str, icon: &str) {
+    outln!(output, "{} {}", icon, message);
+}
+
+/// Ask a yes/no question on stderr so the prompt never ends up in an eval'd script
+pub fn confirm_on_stderr(output: &OutputContext, message: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    output.execution.check_prompt(message)?;
+    output.prompt(&format!("{} [y/N]: ", message))?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
 /// Prompt user for confirmation with custom message
-pub fn prompt_confirmation(message: &str, default_yes: bool) -> Result<bool, Box<dyn std::error::Error>> {
+pub fn prompt_confirmation(output: &OutputContext, message: &str, default_yes: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    output.execution.check_prompt(message)?;
     let prompt = if default_yes {
         format!("{} [Y/n]: ", message)
     } else {
         format!("{} [y/N]: ", message)
     };
     
-    print!("{}", prompt);
-    io::stdout().flush()?;
+    output.prompt(&prompt)?;
     
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
@@ -190,48 +253,177 @@ pub fn prompt_confirmation(message: &str, default_yes: bool) -> Result<bool, Box
         "n" | "no" => false,
         "" => default_yes, // Use default for empty input
         _ => {
-            println!("Please enter 'y' for yes or 'n' for no.");
-            return prompt_confirmation(message, default_yes);
+            outln!(output, "Please enter 'y' for yes or 'n' for no.");
+            return prompt_confirmation(output, message, default_yes);
         }
     };
     
     Ok(result)
 }
 
-/// Display operation summary with statistics
-pub fn display_operation_summary(
-    operation: &str,
-    success_count: usize,
-    warning_count: usize,
-    error_count: usize,
-    duration: Duration,
-    details: Option<&[&str]>,
-) {
-    println!();
-    println!("📊 {} Summary:", operation);
-    println!("   ✅ Successful: {}", success_count);
+/// Ask for a destructive action to be confirmed by typing `expected`; anything else declines
+pub fn prompt_typed_confirmation(output: &OutputContext, message: &str, expected: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    output.execution.check_prompt(message)?;
+    output.prompt(&format!("{}\nType '{}' to confirm: ", message, expected))?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim() == expected)
+}
+
+/// Outcome of a command that changed something, printed by [`display_operation_report`]
+///
+/// Built with [`OperationReport::new`] when the work is done, so every mutating command ends
+/// with the same summary: as text, as one line with `--quiet`, or as JSON with `--report json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OperationReport {
+    pub operation: String,
+    pub succeeded: usize,
+    pub warnings: usize,
+    pub errors: usize,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    pub details: Vec<ReportDetail>,
+    pub artifacts: Vec<ReportArtifact>,
+}
+
+/// A labelled value in the details of an [`OperationReport`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportDetail {
+    pub label: String,
+    pub value: String,
+}
+
+/// A file written by the operation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportArtifact {
+    pub path: String,
+    pub size: Option<u64>,
+}
+
+fn serialize_millis<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+impl OperationReport {
+    pub fn new(operation: &str, succeeded: usize, duration: Duration) -> Self {
+        Self {
+            operation: operation.to_string(),
+            succeeded,
+            warnings: 0,
+            errors: 0,
+            duration,
+            details: Vec::new(),
+            artifacts: Vec::new(),
+        }
+    }
     
-    if warning_count > 0 {
-        println!("   ⚠️  Warnings: {}", warning_count);
+    pub fn with_warnings(mut self, warnings: usize) -> Self {
+        self.warnings = warnings;
+        self
     }
     
-    if error_count > 0 {
-        println!("   ❌ Errors: {}", error_count);
+    pub fn with_errors(mut self, errors: usize) -> Self {
+        self.errors = errors;
+        self
     }
     
-    println!("   ⏱️  Duration: {:.2}s", duration.as_secs_f64());
+    pub fn with_detail(mut self, label: &str, value: impl ToString) -> Self {
+        self.details.push(ReportDetail { label: label.to_string(), value: value.to_string() });
+        self
+    }
     
-    if let Some(details) = details {
-        println!();
-        println!("📋 Details:");
-        for detail in details {
-            println!("   • {}", detail);
+    /// Record a written file, with its size if it can be read
+    pub fn with_artifact(mut self, path: &Path) -> Self {
+        self.artifacts.push(ReportArtifact {
+            path: path.display().to_string(),
+            size: std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+        });
+        self
+    }
+}
+
+/// How an [`OperationReport`] is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportMode {
+    Plain,
+    Quiet,
+    Json,
+}
+
+impl ReportMode {
+    /// The mode chosen by `--report json` and `--quiet` in `output`
+    pub fn for_output(output: &OutputContext) -> Self {
+        if output.execution.json_report {
+            ReportMode::Json
+        } else if output.quiet {
+            ReportMode::Quiet
+        } else {
+            ReportMode::Plain
+        }
+    }
+}
+
+/// The lines of `report` in `mode`; JSON is a single pretty-printed entry
+pub fn render_operation_report(report: &OperationReport, mode: ReportMode) -> Vec<String> {
+    let seconds = report.duration.as_secs_f64();
+    match mode {
+        ReportMode::Json => vec![serde_json::to_string_pretty(report).unwrap_or_default()],
+        ReportMode::Quiet => vec![format!(
+            "{}: {} succeeded, {} warnings, {} errors in {:.2}s",
+            report.operation, report.succeeded, report.warnings, report.errors, seconds
+        )],
+        ReportMode::Plain => {
+            let mut lines = vec![
+                String::new(),
+                format!("📊 {} Summary:", report.operation),
+                format!("   ✅ Successful: {}", report.succeeded),
+            ];
+            if report.warnings > 0 {
+                lines.push(format!("   ⚠️  Warnings: {}", report.warnings));
+            }
+            if report.errors > 0 {
+                lines.push(format!("   ❌ Errors: {}", report.errors));
+            }
+            lines.push(format!("   ⏱️  Duration: {:.2}s", seconds));
+            
+            if !report.details.is_empty() {
+                lines.push(String::new());
+                lines.push("📋 Details:".to_string());
+                for detail in &report.details {
+                    lines.push(format!("   • {}: {}", detail.label, detail.value));
+                }
+            }
+            if !report.artifacts.is_empty() {
+                lines.push(String::new());
+                lines.push("📁 Files:".to_string());
+                for artifact in &report.artifacts {
+                    match artifact.size {
+                        Some(size) => lines.push(format!("   • {} ({})", artifact.path, format_file_size(size))),
+                        None => lines.push(format!("   • {}", artifact.path)),
+                    }
+                }
+            }
+            lines
+        }
+    }
+}
+
+/// Print `report` in the [`ReportMode::for_output`] mode; JSON always goes to stdout
+pub fn display_operation_report(output: &OutputContext, report: &OperationReport) {
+    let mode = ReportMode::for_output(output);
+    for line in render_operation_report(report, mode) {
+        if mode == ReportMode::Json {
+            println!("{}", line);
+        } else {
+            outln!(output, "{}", line);
         }
     }
 }
 
 /// Display file operation results
 pub fn display_file_operation_result(
+    output: &OutputContext,
     operation: &str,
     file_path: &str,
     file_size: Option<u64>,
@@ -240,14 +432,10 @@ pub fn display_file_operation_result(
     let icon = if success { "✅" } else { "❌" };
     let status = if success { "completed" } else { "failed" };
     
-    print!("{} {} {} for: {}", icon, operation, status, file_path);
-    
-    if let Some(size) = file_size {
-        let size_str = format_file_size(size);
-        print!(" ({})", size_str);
+    match file_size {
+        Some(size) => outln!(output, "{} {} {} for: {} ({})", icon, operation, status, file_path, format_file_size(size)),
+        None => outln!(output, "{} {} {} for: {}", icon, operation, status, file_path),
     }
-    
-    println!();
 }
 
 /// Format file size in human-readable format
@@ -269,31 +457,31 @@ pub fn format_file_size(bytes: u64) -> String {
 }
 
 /// Display verbose operation details
-pub fn display_verbose_info(message: &str, details: &[(&str, &str)]) {
-    println!("🔍 {}", message);
+pub fn display_verbose_info(output: &OutputContext, message: &str, details: &[(&str, &str)]) {
+    outln!(output, "🔍 {}", message);
     for (key, value) in details {
-        println!("   {}: {}", key, value);
+        outln!(output, "   {}: {}", key, value);
     }
 }
 
 /// Display conflict resolution options
-pub fn display_conflict_resolution_options(conflicts: &[String]) -> Result<String, Box<dyn std::error::Error>> {
-    println!("⚠️  {} conflicts found:", conflicts.len());
+pub fn display_conflict_resolution_options(output: &OutputContext, conflicts: &[String]) -> Result<String, Box<dyn std::error::Error>> {
+    output.execution.check_prompt("Choose resolution")?;
+    outln!(output, "⚠️  {} conflicts found:", conflicts.len());
     for (i, conflict) in conflicts.iter().enumerate() {
-        println!("   {}. {}", i + 1, conflict);
+        outln!(output, "   {}. {}", i + 1, conflict);
     }
     
-    println!();
-    println!("Resolution options:");
-    println!("   [f]orce    - Overwrite existing configurations");
-    println!("   [m]erge    - Merge with existing configurations");
-    println!("   [s]kip     - Skip conflicting configurations");
-    println!("   [c]ancel   - Cancel the operation");
-    println!();
+    outln!(output);
+    outln!(output, "Resolution options:");
+    outln!(output, "   [f]orce    - Overwrite existing configurations");
+    outln!(output, "   [m]erge    - Merge with existing configurations");
+    outln!(output, "   [s]kip     - Skip conflicting configurations");
+    outln!(output, "   [c]ancel   - Cancel the operation");
+    outln!(output);
     
     loop {
-        print!("Choose resolution [f/m/s/c]: ");
-        io::stdout().flush()?;
+        output.prompt("Choose resolution [f/m/s/c]: ")?;
         
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
@@ -305,7 +493,7 @@ pub fn display_conflict_resolution_options(conflicts: &[String]) -> Result<Strin
             "s" | "skip" => return Ok("skip".to_string()),
             "c" | "cancel" => return Ok("cancel".to_string()),
             _ => {
-                println!("Invalid option. Please choose f, m, s, or c.");
+                outln!(output, "Invalid option. Please choose f, m, s, or c.");
                 continue;
             }
         }
@@ -313,15 +501,15 @@ pub fn display_conflict_resolution_options(conflicts: &[String]) -> Result<Strin
 }
 
 /// Prompt for text input with optional default value
-pub fn prompt_for_input(prompt: &str, default: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+pub fn prompt_for_input(output: &OutputContext, prompt: &str, default: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    output.execution.check_prompt(prompt)?;
     let display_prompt = if let Some(default_val) = default {
         format!("{} [{}]: ", prompt, default_val)
     } else {
         format!("{}: ", prompt)
     };
     
-    print!("{}", display_prompt);
-    io::stdout().flush()?;
+    output.prompt(&display_prompt)?;
     
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
@@ -338,59 +526,6 @@ pub fn prompt_for_input(prompt: &str, default: Option<&str>) -> Result<String, B
     }
 }
 
-/// Prompt for non-empty text input with validation
-pub fn prompt_for_required_input(prompt: &str, validator: Option<fn(&str) -> Result<(), String>>) -> Result<String, Box<dyn std::error::Error>> {
-    loop {
-        let input = prompt_for_input(prompt, None)?;
-        
-        if input.is_empty() {
-            println!("❌ Input cannot be empty. Please try again.");
-            continue;
-        }
-        
-        if let Some(validate) = validator {
-            match validate(&input) {
-                Ok(()) => return Ok(input),
-                Err(error) => {
-                    println!("❌ {}", error);
-                    continue;
-                }
-            }
-        } else {
-            return Ok(input);
-        }
-    }
-}
-
-/// Display interactive menu and get user selection
-pub fn display_interactive_menu(title: &str, options: &[(&str, &str)]) -> Result<String, Box<dyn std::error::Error>> {
-    println!("{}", title);
-    println!();
-    
-    for (key, description) in options {
-        println!("   [{}] - {}", key, description);
-    }
-    
-    println!();
-    
-    let valid_keys: Vec<&str> = options.iter().map(|(key, _)| *key).collect();
-    
-    loop {
-        print!("> ");
-        io::stdout().flush()?;
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
-        
-        if valid_keys.contains(&input.as_str()) {
-            return Ok(input);
-        } else {
-            println!("❌ Invalid option. Please choose from: {}", valid_keys.join(", "));
-        }
-    }
-}
-
 /// Create a reusable confirmation dialog
 pub struct ConfirmationDialog {
     message: String,
@@ -417,178 +552,94 @@ impl ConfirmationDialog {
         self
     }
     
-    pub fn show(self) -> Result<bool, Box<dyn std::error::Error>> {
-        println!("⚠️  {}", self.message);
+    pub fn show(self, output: &OutputContext) -> Result<bool, Box<dyn std::error::Error>> {
+        outln!(output, "⚠️  {}", self.message);
         
         for detail in &self.details {
-            println!("   {}", detail);
+            outln!(output, "   {}", detail);
         }
         
         if !self.details.is_empty() {
-            println!();
+            outln!(output);
         }
         
-        prompt_confirmation("Continue?", self.default_yes)
+        prompt_confirmation(output, "Continue?", self.default_yes)
     }
 }
 
-/// Interactive variable editor for configuration editing
-pub struct VariableEditor {
-    variables: std::collections::HashMap<String, String>,
-    changed: bool,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl VariableEditor {
-    pub fn new(variables: std::collections::HashMap<String, String>) -> Self {
-        Self {
-            variables,
-            changed: false,
-        }
-    }
-    
-    pub fn edit_interactively(mut self) -> Result<(std::collections::HashMap<String, String>, bool), Box<dyn std::error::Error>> {
-        loop {
-            self.display_current_variables();
-            
-            let action = display_interactive_menu(
-                "📝 Variable Editor",
-                &[
-                    ("a", "Add a new variable"),
-                    ("e", "Edit an existing variable"),
-                    ("d", "Delete a variable"),
-                    ("s", "Save changes and exit"),
-                    ("q", "Quit without saving"),
-                ]
-            )?;
-            
-            match action.as_str() {
-                "a" => {
-                    if let Some((key, value)) = self.prompt_for_new_variable()? {
-                        self.variables.insert(key.clone(), value);
-                        self.changed = true;
-                        println!("✅ Added variable '{}'", key);
-                    }
-                }
-                "e" => {
-                    if self.variables.is_empty() {
-                        println!("❌ No variables to edit. Use 'add' to create variables first.");
-                        continue;
-                    }
-                    self.edit_existing_variable()?;
-                }
-                "d" => {
-                    if self.variables.is_empty() {
-                        println!("❌ No variables to delete.");
-                        continue;
-                    }
-                    self.delete_variable()?;
-                }
-                "s" => {
-                    return Ok((self.variables, self.changed));
-                }
-                "q" => {
-                    if self.changed {
-                        let confirm = prompt_confirmation("You have unsaved changes. Quit anyway?", false)?;
-                        if !confirm {
-                            continue;
-                        }
-                    }
-                    return Ok((self.variables, false));
-                }
-                _ => unreachable!(),
-            }
-            
-            println!();
-        }
-    }
-    
-    fn display_current_variables(&self) {
-        if self.variables.is_empty() {
-            println!("📋 Current variables: (none)");
-        } else {
-            println!("📋 Current variables:");
-            let mut sorted_vars: Vec<_> = self.variables.iter().collect();
-            sorted_vars.sort_by_key(|(k, _)| *k);
-            
-            for (i, (key, value)) in sorted_vars.iter().enumerate() {
-                // Basic masking for sensitive-looking keys
-                let display_value = if key.to_uppercase().contains("TOKEN") || 
-                                      key.to_uppercase().contains("KEY") || 
-                                      key.to_uppercase().contains("SECRET") {
-                    format!("{}***", &value.chars().take(4).collect::<String>())
-                } else {
-                    value.to_string()
-                };
-                println!("   {}. {} = {}", i + 1, key, display_value);
-            }
-        }
-        println!();
-    }
-    
-    fn prompt_for_new_variable(&self) -> Result<Option<(String, String)>, Box<dyn std::error::Error>> {
-        let key = prompt_for_required_input("Enter variable name", Some(|name| {
-            if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                Ok(())
-            } else {
-                Err("Variable name must contain only letters, numbers, and underscores".to_string())
-            }
-        }))?;
-        
-        if self.variables.contains_key(&key) {
-            println!("⚠️  Variable '{}' already exists. Use 'edit' to modify it.", key);
-            return Ok(None);
+    fn sample_report() -> OperationReport {
+        OperationReport {
+            artifacts: vec![ReportArtifact { path: "/tmp/backup.json.gz".to_string(), size: Some(1536) }],
+            ..OperationReport::new("Import", 3, Duration::from_millis(1250))
+                .with_warnings(1)
+                .with_detail("Format", "json")
+                .with_detail("Skipped", 1)
         }
-        
-        let value = prompt_for_input("Enter variable value", None)?;
-        Ok(Some((key, value)))
     }
-    
-    fn edit_existing_variable(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let key = prompt_for_input("Enter variable name to edit", None)?;
-        
-        if let Some(current_value) = self.variables.get(&key) {
-            let display_value = if key.to_uppercase().contains("TOKEN") || 
-                                  key.to_uppercase().contains("KEY") || 
-                                  key.to_uppercase().contains("SECRET") {
-                format!("{}***", &current_value.chars().take(4).collect::<String>())
-            } else {
-                current_value.clone()
-            };
-            
-            println!("Current value: {}", display_value);
-            let new_value = prompt_for_input("Enter new value (or press Enter to keep current)", None)?;
-            
-            if !new_value.is_empty() {
-                self.variables.insert(key.clone(), new_value);
-                self.changed = true;
-                println!("✅ Updated variable '{}'", key);
-            } else {
-                println!("⏭️  Variable '{}' unchanged", key);
-            }
-        } else {
-            println!("❌ Variable '{}' not found.", key);
-        }
+
+    #[test]
+    fn test_operation_report_plain() {
+        let expected = "
+📊 Import Summary:
+   ✅ Successful: 3
+   ⚠️  Warnings: 1
+   ⏱️  Duration: 1.25s
+
+📋 Details:
+   • Format: json
+   • Skipped: 1
+
+📁 Files:
+   • /tmp/backup.json.gz (1.5 KB)";
+        assert_eq!(render_operation_report(&sample_report(), ReportMode::Plain).join("\n"), expected);
         
-        Ok(())
+        let empty = OperationReport::new("Prune", 0, Duration::ZERO).with_errors(2);
+        assert_eq!(render_operation_report(&empty, ReportMode::Plain).join("\n"), "
+📊 Prune Summary:
+   ✅ Successful: 0
+   ❌ Errors: 2
+   ⏱️  Duration: 0.00s");
     }
-    
-    fn delete_variable(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let key = prompt_for_input("Enter variable name to delete", None)?;
-        
-        if self.variables.remove(&key).is_some() {
-            self.changed = true;
-            println!("✅ Deleted variable '{}'", key);
-        } else {
-            println!("❌ Variable '{}' not found.", key);
-        }
-        
-        Ok(())
+
+    #[test]
+    fn test_operation_report_quiet() {
+        assert_eq!(
+            render_operation_report(&sample_report(), ReportMode::Quiet),
+            vec!["Import: 3 succeeded, 1 warnings, 0 errors in 1.25s"]
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_operation_report_json() {
+        let expected = r#"{
+  "operation": "Import",
+  "succeeded": 3,
+  "warnings": 1,
+  "errors": 0,
+  "duration_ms": 1250,
+  "details": [
+    {
+      "label": "Format",
+      "value": "json"
+    },
+    {
+      "label": "Skipped",
+      "value": "1"
+    }
+  ],
+  "artifacts": [
+    {
+      "path": "/tmp/backup.json.gz",
+      "size": 1536
+    }
+  ]
+}"#;
+        assert_eq!(render_operation_report(&sample_report(), ReportMode::Json), vec![expected]);
+    }
 
     #[test]
     fn test_format_file_size() {
@@ -600,6 +651,43 @@ mod tests {
         assert_eq!(format_file_size(1073741824), "1.0 GB");
     }
 
+    #[test]
+    fn test_warning_aggregator_groups_by_template() {
+        let mut warnings = WarningAggregator::new();
+        assert!(warnings.is_empty());
+        for config in ["a", "b", "c", "d", "e"] {
+            warnings.add("{count} variable name(s) with non-standard characters", format!("{}:my-key", config));
+        }
+        warnings.add_with_symbol("ℹ️", "{count} value(s) only valid on this machine", "a:PROXY");
+        warnings.add("{count} variable name(s) with non-standard characters", "f:my-key");
+
+        assert_eq!(warnings.len(), 7);
+        // Only ⚠️ counts for --strict
+        assert_eq!(warnings.warning_count(), 6);
+        assert_eq!(warnings.groups().len(), 2);
+        assert_eq!(warnings.groups()[0].count(), 6);
+        assert_eq!(warnings.lines(false), vec![
+            "⚠️  6 variable name(s) with non-standard characters, e.g. a:my-key, b:my-key, c:my-key (and 3 more; --verbose lists all)".to_string(),
+            "ℹ️  1 value(s) only valid on this machine: a:PROXY".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_warning_aggregator_truncates_unless_verbose() {
+        let mut warnings = WarningAggregator::new();
+        for index in 0..WARNING_EXAMPLES {
+            warnings.add("{count} empty secret(s)", format!("c{}:TOKEN", index));
+        }
+        assert_eq!(warnings.lines(false), vec!["⚠️  3 empty secret(s): c0:TOKEN, c1:TOKEN, c2:TOKEN".to_string()]);
+
+        warnings.add("{count} empty secret(s)", "c3:TOKEN");
+        assert!(warnings.lines(false)[0].ends_with("(and 1 more; --verbose lists all)"));
+        let verbose = warnings.lines(true);
+        assert_eq!(verbose.len(), 5);
+        assert_eq!(verbose[0], "⚠️  4 empty secret(s):");
+        assert_eq!(verbose[4], "   • c3:TOKEN");
+    }
+
     #[test]
     fn test_progress_indicator() {
         let mut progress = ProgressIndicator::new("Testing");
@@ -608,7 +696,7 @@ mod tests {
         progress.start();
         assert!(progress.is_running);
         
-        progress.finish_success("Done");
+        progress.finish_success(&OutputContext::default(), "Done");
         assert!(!progress.is_running);
     }
 }
\ No newline at end of file
@@ -8,6 +8,36 @@ pub enum FileFormat {
     Json,
     Env,
     Yaml,
+    Toml,
+}
+
+impl FileFormat {
+    /// Parse a format name as accepted on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(FileFormat::Json),
+            "env" => Some(FileFormat::Env),
+            "yaml" | "yml" => Some(FileFormat::Yaml),
+            "toml" => Some(FileFormat::Toml),
+            _ => None,
+        }
+    }
+    
+    /// Guess the format from a file extension
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_name)
+    }
+}
+
+/// Outcome of converting a file between formats
+#[derive(Debug, Clone, Default)]
+pub struct ConversionSummary {
+    /// Number of variables written to the output
+    pub variables: usize,
+    /// Information that could not be carried over, e.g. dropped comments
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -15,7 +45,85 @@ pub struct FormatValidationResult {
     pub is_valid: bool,
     pub format: Option<FileFormat>,
     pub errors: Vec<String>,
-    pub warnings: Vec<String>,
+    pub warnings: crate::utils::feedback::WarningAggregator,
+    /// Set when the file is in the right format but was corrupted or truncated
+    pub integrity_error: Option<String>,
+}
+
+/// Nesting deeper than this is rejected when flattening, so pathological files fail early
+pub const MAX_FLATTEN_DEPTH: usize = 8;
+
+/// How nested JSON objects become variables, e.g. `{"anthropic": {"model": ...}}` → `ANTHROPIC_MODEL`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlattenOptions {
+    /// Joins the keys of nested objects
+    pub separator: String,
+    /// Flatten arrays into `KEY_0`, `KEY_1`, ... instead of rejecting them
+    pub index_arrays: bool,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        Self { separator: "_".to_string(), index_arrays: false }
+    }
+}
+
+/// Flatten a JSON object into uppercased variables
+///
+/// Fails on arrays unless `index_arrays` is set, on nesting deeper than
+/// [`MAX_FLATTEN_DEPTH`], and when two paths end up with the same name.
+pub fn flatten_json(value: &serde_json::Value, options: &FlattenOptions) -> Result<HashMap<String, String>, String> {
+    let serde_json::Value::Object(object) = value else {
+        return Err("JSON file must contain an object at root level".to_string());
+    };
+    let mut flat: HashMap<String, (String, String)> = HashMap::new();
+    for (key, value) in object {
+        flatten_value(&key.to_uppercase(), key, value, 1, options, &mut flat)?;
+    }
+    Ok(flat.into_iter().map(|(name, (_, value))| (name, value)).collect())
+}
+
+/// Add `value` under `name`, remembering the JSON `path` it came from for collision errors
+fn flatten_value(
+    name: &str,
+    path: &str,
+    value: &serde_json::Value,
+    depth: usize,
+    options: &FlattenOptions,
+    flat: &mut HashMap<String, (String, String)>,
+) -> Result<(), String> {
+    let mut nested = |key: &str, child_path: String, child: &serde_json::Value| {
+        if depth >= MAX_FLATTEN_DEPTH {
+            return Err(format!("'{}' is nested more than {} levels deep", child_path, MAX_FLATTEN_DEPTH));
+        }
+        let child_name = format!("{}{}{}", name, options.separator, key.to_uppercase());
+        flatten_value(&child_name, &child_path, child, depth + 1, options, flat)
+    };
+    let leaf = match value {
+        serde_json::Value::Object(object) => {
+            for (key, child) in object {
+                nested(key, format!("{}.{}", path, key), child)?;
+            }
+            return Ok(());
+        }
+        serde_json::Value::Array(items) => {
+            if !options.index_arrays {
+                return Err(format!("'{}' is an array; flatten arrays with --flatten-arrays index", path));
+            }
+            for (index, child) in items.iter().enumerate() {
+                nested(&index.to_string(), format!("{}[{}]", path, index), child)?;
+            }
+            return Ok(());
+        }
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    if let Some((other_path, _)) = flat.get(name) {
+        return Err(format!("'{}' and '{}' both flatten to {}", other_path, path, name));
+    }
+    flat.insert(name.to_string(), (path.to_string(), leaf));
+    Ok(())
 }
 
 /// Read environment variables from a file
@@ -24,11 +132,23 @@ pub struct FormatValidationResult {
 /// - .env format
 /// - JSON format
 pub fn read_env_file(file_path: &str) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    read_env_file_with(file_path, None)
+}
+
+/// Read environment variables from a file, flattening nested JSON objects when `flatten` is given
+pub fn read_env_file_with(file_path: &str, flatten: Option<&FlattenOptions>) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
     let content = fs::read_to_string(file_path)
         .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?;
     
     let mut variables = HashMap::new();
     
+    if let Some(flatten) = flatten.filter(|_| file_path.ends_with(".json")) {
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse JSON file '{}': {}", file_path, e))?;
+        return flatten_json(&value, flatten)
+            .map_err(|e| format!("Cannot flatten '{}': {}", file_path, e).into());
+    }
+    
     // Try to parse as JSON first
     if file_path.ends_with(".json") {
         let json_vars: HashMap<String, serde_json::Value> = serde_json::from_str(&content)
@@ -54,23 +174,12 @@ pub fn read_env_file(file_path: &str) -> Result<HashMap<String, String>, Box<dyn
             }
             
             // Parse KEY=VALUE format
-            if let Some(eq_pos) = line.find('=') {
-                let key = line[..eq_pos].trim().to_string();
-                let value = line[eq_pos + 1..].trim();
-                
-                // Remove quotes if present
-                let value = if (value.starts_with('"') && value.ends_with('"')) ||
-                              (value.starts_with('\'') && value.ends_with('\'')) {
-                    &value[1..value.len()-1]
-                } else {
-                    value
-                };
-                
+            if let Some((key, value)) = parse_env_line(line) {
                 if key.is_empty() {
                     return Err(format!("Empty variable name on line {} in file '{}'", line_num + 1, file_path).into());
                 }
                 
-                variables.insert(key, value.to_string());
+                variables.insert(key, value);
             } else {
                 return Err(format!("Invalid format on line {} in file '{}': expected KEY=VALUE", line_num + 1, file_path).into());
             }
@@ -83,17 +192,7 @@ pub fn read_env_file(file_path: &str) -> Result<HashMap<String, String>, Box<dyn
 /// Detect file format based on extension and content analysis
 pub fn detect_file_format(path: &Path) -> Result<FileFormat, Box<dyn std::error::Error>> {
     // First try extension-based detection
-    let extension = path.extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-    
-    let format_from_extension = match extension.as_str() {
-        "json" => Some(FileFormat::Json),
-        "env" => Some(FileFormat::Env),
-        "yaml" | "yml" => Some(FileFormat::Yaml),
-        _ => None,
-    };
+    let format_from_extension = FileFormat::from_extension(path);
     
     // If extension is clear, validate content matches
     if let Some(expected_format) = format_from_extension {
@@ -101,6 +200,10 @@ pub fn detect_file_format(path: &Path) -> Result<FileFormat, Box<dyn std::error:
         if validation.is_valid {
             return Ok(expected_format);
         }
+        // A damaged file must not be misdetected as another format
+        if let Some(integrity_error) = validation.integrity_error {
+            return Err(integrity_error.into());
+        }
     }
     
     // Try content-based detection
@@ -132,16 +235,20 @@ pub fn detect_file_format(path: &Path) -> Result<FileFormat, Box<dyn std::error:
         }
     }
     
-    // YAML detection - look for key: value patterns
+    // YAML detection - key: value patterns that also parse as a YAML mapping, since
+    // prose with a colon in it would otherwise pass for YAML
     let has_yaml_pattern = trimmed.lines()
         .filter(|line| !line.trim().is_empty() && !line.trim().starts_with('#'))
         .any(|line| line.contains(':') && !line.contains('='));
     
     if has_yaml_pattern {
-        return Ok(FileFormat::Yaml);
+        let validation = validate_file_format(path, &FileFormat::Yaml)?;
+        if validation.is_valid {
+            return Ok(FileFormat::Yaml);
+        }
     }
     
-    Err("Unable to detect file format. Supported formats: JSON (.json), ENV (.env), YAML (.yaml/.yml)".into())
+    Err("Unable to detect file format. Supported formats: JSON (.json), ENV (.env), YAML (.yaml/.yml), TOML (.toml)".into())
 }
 
 /// Validate that a file matches the expected format
@@ -151,15 +258,26 @@ pub fn validate_file_format(path: &Path, expected_format: &FileFormat) -> Result
         is_valid: false,
         format: None,
         errors: Vec::new(),
-        warnings: Vec::new(),
+        warnings: Default::default(),
+        integrity_error: None,
     };
     
     match expected_format {
         FileFormat::Json => {
             match serde_json::from_str::<serde_json::Value>(&content) {
-                Ok(_) => {
-                    result.is_valid = true;
-                    result.format = Some(FileFormat::Json);
+                Ok(mut value) => {
+                    if let Some(message) = crate::utils::verify_json_checksum(&mut value).error_message() {
+                        result.errors.push(message.clone());
+                        result.integrity_error = Some(message);
+                    } else {
+                        result.is_valid = true;
+                        result.format = Some(FileFormat::Json);
+                    }
+                }
+                Err(e) if e.is_eof() => {
+                    let message = format!("File appears to be truncated: {}", e);
+                    result.errors.push(message.clone());
+                    result.integrity_error = Some(message);
                 }
                 Err(e) => {
                     result.errors.push(format!("Invalid JSON format: {}", e));
@@ -168,6 +286,12 @@ pub fn validate_file_format(path: &Path, expected_format: &FileFormat) -> Result
         }
         
         FileFormat::Env => {
+            if let Some(message) = crate::utils::verify_text_checksum(&content).0.error_message() {
+                result.errors.push(message.clone());
+                result.integrity_error = Some(message);
+                return Ok(result);
+            }
+            
             let mut line_num = 0;
             let mut has_valid_entries = false;
             
@@ -188,13 +312,13 @@ pub fn validate_file_format(path: &Path, expected_format: &FileFormat) -> Result
                     if key.is_empty() {
                         result.errors.push(format!("Line {}: Empty variable name", line_num));
                     } else if !key.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                        result.warnings.push(format!("Line {}: Variable name '{}' contains non-standard characters", line_num, key));
+                        result.warnings.add("{count} variable name(s) with non-standard characters", format!("line {} ({})", line_num, key));
                     }
                     
                     // Check for unquoted values with spaces
                     if value.contains(' ') && !((value.starts_with('"') && value.ends_with('"')) ||
                                                (value.starts_with('\'') && value.ends_with('\''))) {
-                        result.warnings.push(format!("Line {}: Value contains spaces but is not quoted", line_num));
+                        result.warnings.add("{count} unquoted value(s) containing spaces", format!("line {}", line_num));
                     }
                     
                     has_valid_entries = true;
@@ -212,31 +336,22 @@ pub fn validate_file_format(path: &Path, expected_format: &FileFormat) -> Result
         }
         
         FileFormat::Yaml => {
-            // Basic YAML validation - check for key: value patterns
-            let mut has_valid_entries = false;
-            let mut line_num = 0;
-            
-            for line in content.lines() {
-                line_num += 1;
-                let line = line.trim();
-                
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
-                }
-                
-                if line.contains(':') {
-                    has_valid_entries = true;
-                } else if !line.starts_with('-') && !line.starts_with(' ') {
-                    result.warnings.push(format!("Line {}: May not be valid YAML format", line_num));
+            match serde_yaml::from_str::<serde_yaml::Mapping>(&content) {
+                Ok(_) => {
+                    result.is_valid = true;
+                    result.format = Some(FileFormat::Yaml);
                 }
+                Err(e) => result.errors.push(format!("Invalid YAML format: {}", e)),
             }
-            
-            if has_valid_entries {
-                result.is_valid = true;
-                result.format = Some(FileFormat::Yaml);
-                result.warnings.push("YAML format detected but full validation requires YAML parser".to_string());
-            } else {
-                result.errors.push("No valid YAML entries found".to_string());
+        }
+        
+        FileFormat::Toml => {
+            match content.parse::<toml::Table>() {
+                Ok(_) => {
+                    result.is_valid = true;
+                    result.format = Some(FileFormat::Toml);
+                }
+                Err(e) => result.errors.push(format!("Invalid TOML format: {}", e.message())),
             }
         }
     }
@@ -244,40 +359,53 @@ pub fn validate_file_format(path: &Path, expected_format: &FileFormat) -> Result
     Ok(result)
 }
 
-/// Convert between different configuration formats
+/// Convert a flat variable map between different configuration formats
 pub fn convert_format(
     input_path: &Path,
     output_path: &Path,
     source_format: &FileFormat,
     target_format: &FileFormat,
-) -> Result<(), Box<dyn std::error::Error>> {
-    if source_format == target_format {
-        // Just copy the file
-        fs::copy(input_path, output_path)?;
-        return Ok(());
-    }
+) -> Result<ConversionSummary, Box<dyn std::error::Error>> {
+    let mut summary = ConversionSummary::default();
     
     // Read and parse source format
     let variables = match source_format {
-        FileFormat::Json => parse_json_file(input_path)?,
-        FileFormat::Env => read_env_file(input_path.to_str().unwrap())?,
-        FileFormat::Yaml => {
-            return Err("YAML parsing not yet implemented for format conversion".into());
-        }
+        FileFormat::Json => parse_json_file(input_path, &mut summary.warnings)?,
+        FileFormat::Env => read_env_file(input_path.to_str().ok_or("Input path is not valid UTF-8")?)?,
+        FileFormat::Yaml => parse_yaml_file(input_path, &mut summary.warnings)?,
+        FileFormat::Toml => parse_toml_file(input_path, &mut summary.warnings)?,
     };
+    summary.variables = variables.len();
+    
+    if source_format == target_format {
+        // Just copy the file so nothing is lost
+        fs::copy(input_path, output_path)?;
+        return Ok(summary);
+    }
+    
+    let comment_lines = fs::read_to_string(input_path)?
+        .lines()
+        .map(str::trim_start)
+        // The header written by our own writers is regenerated, so it isn't lost
+        .filter(|line| line.starts_with('#') && *line != "# Environment variables" && !line.starts_with("# Generated on "))
+        .count();
+    if comment_lines > 0 && *source_format != FileFormat::Json {
+        summary.warnings.push(format!("{} comment line(s) dropped", comment_lines));
+    }
     
     // Write in target format
     match target_format {
         FileFormat::Json => write_json_file(output_path, &variables, true)?,
         FileFormat::Env => write_env_file(output_path, &variables)?,
         FileFormat::Yaml => write_yaml_file(output_path, &variables)?,
+        FileFormat::Toml => write_toml_file(output_path, &variables)?,
     }
     
-    Ok(())
+    Ok(summary)
 }
 
 /// Parse JSON file into environment variables
-fn parse_json_file(path: &Path) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+fn parse_json_file(path: &Path, warnings: &mut Vec<String>) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
     let content = fs::read_to_string(path)?;
     let json_value: serde_json::Value = serde_json::from_str(&content)?;
     
@@ -291,7 +419,10 @@ fn parse_json_file(path: &Path) -> Result<HashMap<String, String>, Box<dyn std::
                     serde_json::Value::Number(n) => n.to_string(),
                     serde_json::Value::Bool(b) => b.to_string(),
                     serde_json::Value::Null => String::new(),
-                    _ => serde_json::to_string(&value)?.trim_matches('"').to_string(),
+                    _ => {
+                        warnings.push(format!("'{}' is nested and was flattened to a JSON string", key));
+                        serde_json::to_string(&value)?
+                    }
                 };
                 variables.insert(key, string_value);
             }
@@ -302,6 +433,56 @@ fn parse_json_file(path: &Path) -> Result<HashMap<String, String>, Box<dyn std::
     Ok(variables)
 }
 
+/// Parse a flat YAML mapping into environment variables
+fn parse_yaml_file(path: &Path, warnings: &mut Vec<String>) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    // An empty file, or one with only comments, is an empty document
+    let mapping: Option<HashMap<String, serde_yaml::Value>> = serde_yaml::from_str(&content)
+        .map_err(|e| format!("Invalid YAML: {}", e))?;
+    
+    let mut variables = HashMap::new();
+    for (key, value) in mapping.unwrap_or_default() {
+        let string_value = match value {
+            serde_yaml::Value::String(s) => s,
+            serde_yaml::Value::Number(n) => n.to_string(),
+            serde_yaml::Value::Bool(b) => b.to_string(),
+            serde_yaml::Value::Null => String::new(),
+            _ => {
+                warnings.push(format!("'{}' is nested and was skipped", key));
+                continue;
+            }
+        };
+        variables.insert(key, string_value);
+    }
+    
+    Ok(variables)
+}
+
+/// Parse a flat TOML table into environment variables
+fn parse_toml_file(path: &Path, warnings: &mut Vec<String>) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let table: toml::Table = content.parse()
+        .map_err(|e: toml::de::Error| format!("Invalid TOML: {}", e.message()))?;
+    
+    let mut variables = HashMap::new();
+    for (key, value) in table {
+        let string_value = match value {
+            toml::Value::String(s) => s,
+            toml::Value::Integer(i) => i.to_string(),
+            toml::Value::Float(f) => f.to_string(),
+            toml::Value::Boolean(b) => b.to_string(),
+            toml::Value::Datetime(d) => d.to_string(),
+            toml::Value::Array(_) | toml::Value::Table(_) => {
+                warnings.push(format!("'{}' is nested and was skipped", key));
+                continue;
+            }
+        };
+        variables.insert(key, string_value);
+    }
+    
+    Ok(variables)
+}
+
 /// Write variables to JSON file
 fn write_json_file(
     path: &Path,
@@ -319,7 +500,7 @@ fn write_json_file(
 }
 
 /// Write variables to ENV file
-fn write_env_file(
+pub fn write_env_file(
     path: &Path,
     variables: &HashMap<String, String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -331,44 +512,212 @@ fn write_env_file(
     sorted_vars.sort_by_key(|(k, _)| *k);
     
     for (key, value) in sorted_vars {
-        // Quote values that contain spaces or special characters
-        let quoted_value = if value.contains(' ') || value.contains('\t') || value.contains('\n') {
-            format!("\"{}\"", value.replace('"', "\\\""))
-        } else {
-            value.clone()
-        };
-        
-        content.push_str(&format!("{}={}\n", key, quoted_value));
+        content.push_str(&format_env_line(key, value));
+        content.push('\n');
     }
     
     fs::write(path, content)?;
     Ok(())
 }
 
-/// Write variables to YAML file (basic implementation)
+/// Format one `KEY=VALUE` line, quoting the value only when it needs it
+///
+/// Plain values are written as is. Single-line values with spaces or special
+/// characters are single-quoted; values with single quotes or line breaks are
+/// double-quoted with backslash escapes. [`parse_env_line`] reads all of them back unchanged.
+pub fn format_env_line(key: &str, value: &str) -> String {
+    let plain = value.chars().all(|c| c.is_ascii_alphanumeric() || "_-./:,@%+=~^".contains(c));
+    if plain {
+        format!("{}={}", key, value)
+    } else if !value.contains(['\n', '\r', '\'']) {
+        format!("{}='{}'", key, value)
+    } else {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "\\$")
+            .replace('`', "\\`")
+            .replace('\r', "\\r")
+            .replace('\n', "\\n");
+        format!("{}=\"{}\"", key, escaped)
+    }
+}
+
+/// Split a `KEY=VALUE` line and unquote the value, the reverse of [`format_env_line`]
+///
+/// Double-quoted values understand `\\`, `\"`, `\$`, `` \` ``, `\n`, `\r` and `\t`;
+/// single-quoted values are taken literally. A quoted value may be followed by a
+/// `# comment`. Unquoted values are used as they are, so `#` in URLs survives.
+/// Returns None for lines without `=`.
+pub fn parse_env_line(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.split_once('=')?;
+    let key = key.trim();
+    let key = key.strip_prefix("export ").map(str::trim_start).unwrap_or(key);
+    Some((key.to_string(), parse_env_value(value.trim())))
+}
+
+/// Variables parsed from KEY=VALUE text, with the lines that were skipped
+#[derive(Debug, Default)]
+pub struct ParsedEnv {
+    pub variables: HashMap<String, String>,
+    /// Line number and reason of every line that was not a valid assignment
+    pub skipped: Vec<(usize, String)>,
+}
+
+/// Parse KEY=VALUE text with the rules of [`read_env_file`], skipping invalid lines instead of failing
+///
+/// Used for text that is pasted or piped in, where one bad line shouldn't lose the rest.
+pub fn parse_env_content(content: &str) -> ParsedEnv {
+    let mut parsed = ParsedEnv::default();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        
+        let reason = match parse_env_line(line) {
+            None => "expected KEY=VALUE".to_string(),
+            Some((key, value)) => match crate::error::validate_env_var_name(&key) {
+                Ok(()) => {
+                    parsed.variables.insert(key, value);
+                    continue;
+                }
+                Err(e) => e.to_string(),
+            },
+        };
+        parsed.skipped.push((index + 1, reason));
+    }
+    parsed
+}
+
+fn parse_env_value(raw: &str) -> String {
+    let quoted = match raw.chars().next() {
+        Some('"') => unquote_double(&raw[1..]),
+        Some('\'') => raw[1..].split_once('\'').map(|(value, rest)| (value.to_string(), rest)),
+        _ => None,
+    };
+    match quoted {
+        Some((value, rest)) if rest.trim().is_empty() || rest.trim_start().starts_with('#') => value,
+        // Older versions wrote 'it's' without escaping, so fall back to dropping the outer quotes
+        _ if raw.len() >= 2 && (raw.starts_with('"') && raw.ends_with('"') || raw.starts_with('\'') && raw.ends_with('\'')) => {
+            raw[1..raw.len() - 1].to_string()
+        }
+        _ => raw.to_string(),
+    }
+}
+
+/// Read a double-quoted value up to its closing quote, returning it and the rest of the line
+fn unquote_double(raw: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = raw.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &raw[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                escaped @ ('\\' | '"' | '$' | '`') => value.push(escaped),
+                other => {
+                    value.push('\\');
+                    value.push(other);
+                }
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Write variables to YAML file, quoting values that YAML would read as another type
 fn write_yaml_file(
     path: &Path,
     variables: &HashMap<String, String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let sorted_vars: std::collections::BTreeMap<&String, &String> = variables.iter().collect();
+    
     let mut content = String::new();
     content.push_str("# Environment variables\n");
     content.push_str(&format!("# Generated on {}\n\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
+    content.push_str(&serde_yaml::to_string(&sorted_vars)?);
     
-    let mut sorted_vars: Vec<_> = variables.iter().collect();
-    sorted_vars.sort_by_key(|(k, _)| *k);
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Write variables to TOML file
+fn write_toml_file(
+    path: &Path,
+    variables: &HashMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sorted_vars: std::collections::BTreeMap<&String, &String> = variables.iter().collect();
     
-    for (key, value) in sorted_vars {
-        // Basic YAML formatting - quote values that need it
-        let yaml_value = if value.contains(':') || value.contains('#') || value.contains('\n') ||
-                           value.starts_with(' ') || value.ends_with(' ') {
-            format!("\"{}\"", value.replace('"', "\\\""))
-        } else {
-            value.clone()
-        };
-        
-        content.push_str(&format!("{}: {}\n", key, yaml_value));
-    }
+    let mut content = String::new();
+    content.push_str("# Environment variables\n");
+    content.push_str(&format!("# Generated on {}\n\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
+    content.push_str(&toml::to_string(&sorted_vars)?);
     
     fs::write(path, content)?;
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn flatten(value: serde_json::Value, options: &FlattenOptions) -> Result<Vec<(String, String)>, String> {
+        let mut flat: Vec<(String, String)> = flatten_json(&value, options)?.into_iter().collect();
+        flat.sort();
+        Ok(flat)
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_flatten_nested_objects() {
+        let value = json!({
+            "anthropic": {"base_url": "https://api.example.com", "model": "m1", "limits": {"tokens": 4096}},
+            "debug": true,
+            "proxy": null,
+        });
+        assert_eq!(flatten(value.clone(), &FlattenOptions::default()).unwrap(), pairs(&[
+            ("ANTHROPIC_BASE_URL", "https://api.example.com"),
+            ("ANTHROPIC_LIMITS_TOKENS", "4096"),
+            ("ANTHROPIC_MODEL", "m1"),
+            ("DEBUG", "true"),
+            ("PROXY", ""),
+        ]));
+
+        let options = FlattenOptions { separator: "__".to_string(), ..FlattenOptions::default() };
+        assert!(flatten(value, &options).unwrap().contains(&("ANTHROPIC__MODEL".to_string(), "m1".to_string())));
+        assert!(flatten(json!(["not", "an", "object"]), &FlattenOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_flatten_arrays_are_rejected_or_indexed() {
+        let value = json!({"hosts": ["a", {"name": "b"}]});
+        let error = flatten(value.clone(), &FlattenOptions::default()).unwrap_err();
+        assert!(error.contains("'hosts' is an array"), "{}", error);
+
+        let options = FlattenOptions { index_arrays: true, ..FlattenOptions::default() };
+        assert_eq!(flatten(value, &options).unwrap(), pairs(&[("HOSTS_0", "a"), ("HOSTS_1_NAME", "b")]));
+    }
+
+    #[test]
+    fn test_flatten_reports_collisions_and_deep_nesting() {
+        let error = flatten(json!({"a_b": "1", "a": {"b": "2"}}), &FlattenOptions::default()).unwrap_err();
+        assert!(error.contains("both flatten to A_B"), "{}", error);
+        assert!(error.contains("'a.b'") && error.contains("'a_b'"), "{}", error);
+        let error = flatten(json!({"key": "1", "KEY": "2"}), &FlattenOptions::default()).unwrap_err();
+        assert!(error.contains("both flatten to KEY"), "{}", error);
+
+        let mut deep = json!("leaf");
+        for _ in 0..=MAX_FLATTEN_DEPTH {
+            deep = json!({"x": deep});
+        }
+        let error = flatten(deep, &FlattenOptions::default()).unwrap_err();
+        assert!(error.contains(&format!("more than {} levels", MAX_FLATTEN_DEPTH)), "{}", error);
+    }
+}
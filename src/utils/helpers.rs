@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use crate::error::ConfigError;
+use crate::output::OutputContext;
 
 /// Check if a key contains sensitive information that should be masked
 pub fn is_sensitive_key(key: &str) -> bool {
@@ -10,66 +12,447 @@ pub fn is_sensitive_key(key: &str) -> bool {
     sensitive_patterns.iter().any(|pattern| upper_key.contains(pattern))
 }
 
-/// Mask sensitive values for display
-pub fn mask_sensitive_value(value: &str) -> String {
-    if value.len() <= 8 {
-        "*".repeat(value.len())
+/// Mask sensitive values for display, with length hints when the mask-length-hints setting is on
+pub fn mask_sensitive_value(output: &OutputContext, value: &str) -> String {
+    mask_value(value, output.mask_length_hints, output.ascii)
+}
+
+/// Mask a value: `sk-a***wxyz` for long values, one `*` per character for short ones
+///
+/// With `length_hints` only the first four characters of long values are kept and
+/// the length is added, e.g. `sk-a****(51 chars)`, so an empty or truncated token
+/// stands out; empty values become `⚠ empty` (`! empty` in ASCII mode).
+pub fn mask_value(value: &str, length_hints: bool, ascii: bool) -> String {
+    let length = value.chars().count();
+    if length_hints {
+        return match length {
+            0 if ascii => "! empty".to_string(),
+            0 => "⚠ empty".to_string(),
+            1..=8 => format!("****({} chars)", length),
+            _ => format!("{}****({} chars)", value.chars().take(4).collect::<String>(), length),
+        };
+    }
+    if length <= 8 {
+        "*".repeat(length)
     } else {
-        format!("{}***{}", &value[..4], &value[value.len()-4..])
+        let start: String = value.chars().take(4).collect();
+        let end: String = value.chars().skip(length - 4).collect();
+        format!("{}***{}", start, end)
     }
 }
 
 /// Check if the configuration appears to be for Claude
-pub fn is_claude_configuration(variables: &HashMap<String, String>) -> bool {
-    let claude_indicators = [
-        "ANTHROPIC_BASE_URL",
-        "ANTHROPIC_MODEL", 
-        "ANTHROPIC_AUTH_TOKEN",
-        "ANTHROPIC_SMALL_FAST_MODEL"
-    ];
-    
-    claude_indicators.iter().any(|key| variables.contains_key(*key))
+pub fn is_claude_configuration(variables: &HashMap<String, String>, extra: &[String]) -> bool {
+    variables.keys().any(|key| crate::types::validation::is_watched_claude_var(key, extra))
 }
 
-/// Find configurations with similar names (simple string distance)
+/// Find configurations with names close to `target`, best match first
+///
+/// Names starting with or containing the target rank first, then names within
+/// an edit distance that grows with the target's length (transpositions count
+/// as one edit). Comparison ignores case. At most 3 names are returned.
 pub fn find_similar_configs(target: &str, available: &[String]) -> Vec<String> {
-    let mut suggestions = Vec::new();
     let target_lower = target.to_lowercase();
+    let target_len = target_lower.chars().count();
+    if target_len == 0 {
+        return Vec::new();
+    }
+    // One typo per three characters, and at least one
+    let max_distance = (target_len / 3).max(1);
     
-    for config in available {
-        let config_lower = config.to_lowercase();
-        
-        // Check for substring matches
-        if config_lower.contains(&target_lower) || target_lower.contains(&config_lower) {
-            suggestions.push(config.clone());
-            continue;
+    let mut ranked: Vec<(u8, usize, &String)> = available.iter()
+        .filter_map(|config| {
+            let config_lower = config.to_lowercase();
+            let distance = edit_distance(&target_lower, &config_lower);
+            let rank = if target_len >= 2 && config_lower.starts_with(&target_lower) {
+                0
+            } else if target_len >= 3 && config_lower.contains(&target_lower) {
+                1
+            } else if distance <= max_distance {
+                2
+            } else {
+                return None;
+            };
+            Some((rank, distance, config))
+        })
+        .collect();
+    
+    ranked.sort();
+    ranked.into_iter().take(3).map(|(_, _, config)| config.clone()).collect()
+}
+
+/// Number of insertions, deletions, substitutions and adjacent swaps turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
         }
-        
-        // Check for similar length and character overlap
-        if (config.len() as i32 - target.len() as i32).abs() <= 2 {
-            let similarity = calculate_similarity(&target_lower, &config_lower);
-            if similarity > 0.6 {
-                suggestions.push(config.clone());
+    }
+    
+    rows[a.len()][b.len()]
+}
+
+/// Error message for a configuration name that doesn't exist, with suggestions
+///
+/// `configs` lists the configuration names; `candidates` also includes aliases.
+pub fn config_not_found_message(name: &str, configs: &[String], candidates: &[String]) -> String {
+    if configs.is_empty() {
+        return format!(
+            "Configuration '{}' not found. No configurations exist yet.\n💡 Create your first configuration with: envswitch set {} -e KEY=value",
+            name, name
+        );
+    }
+    
+    let suggestions = find_similar_configs(name, candidates);
+    if suggestions.is_empty() {
+        format!(
+            "Configuration '{}' not found.\nAvailable configurations: {}\n💡 Use 'envswitch list' to see all configurations",
+            name, configs.join(", ")
+        )
+    } else {
+        format!(
+            "Configuration '{}' not found.\nDid you mean: {}?\nAvailable configurations: {}",
+            name, suggestions.join(", "), configs.join(", ")
+        )
+    }
+}
+
+/// Typed error for a configuration name that doesn't exist, with names close to it
+///
+/// `candidates` lists configuration names and aliases.
+pub fn config_not_found(name: &str, candidates: &[String]) -> ConfigError {
+    ConfigError::ConfigNotFound {
+        name: name.to_string(),
+        suggestions: find_similar_configs(name, candidates),
+    }
+}
+
+/// Best-effort name of the current machine
+pub fn current_hostname() -> Option<String> {
+    for var in ["HOSTNAME", "COMPUTERNAME"] {
+        if let Ok(name) = std::env::var(var) {
+            if !name.trim().is_empty() {
+                return Some(name.trim().to_string());
+            }
+        }
+    }
+    
+    #[cfg(unix)]
+    {
+        let mut buf = [0u8; 256];
+        let result = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+        if result == 0 {
+            let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+            let name = String::from_utf8_lossy(&buf[..len]).trim().to_string();
+            if !name.is_empty() {
+                return Some(name);
             }
         }
     }
     
-    // Limit to top 3 suggestions
-    suggestions.truncate(3);
-    suggestions
+    None
+}
+
+/// Parse a relative duration such as "30m", "12h", "7d" or "2w"
+pub fn parse_duration(input: &str) -> Result<chrono::Duration, String> {
+    let input = input.trim();
+    let split = input.find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("Invalid duration '{}'. Add a unit: m, h, d or w (e.g. 7d)", input))?;
+    let (amount, unit) = input.split_at(split);
+    
+    let amount: i64 = amount.parse()
+        .map_err(|_| format!("Invalid duration '{}'. Expected a number followed by m, h, d or w", input))?;
+    
+    match unit {
+        "m" | "min" => Ok(chrono::Duration::minutes(amount)),
+        "h" => Ok(chrono::Duration::hours(amount)),
+        "d" => Ok(chrono::Duration::days(amount)),
+        "w" => Ok(chrono::Duration::weeks(amount)),
+        _ => Err(format!("Invalid duration unit '{}'. Use m, h, d or w", unit)),
+    }
 }
 
-/// Calculate simple similarity score between two strings
-fn calculate_similarity(s1: &str, s2: &str) -> f64 {
-    let chars1: std::collections::HashSet<char> = s1.chars().collect();
-    let chars2: std::collections::HashSet<char> = s2.chars().collect();
+/// Parse an absolute date ("2025-01-31", taken as midnight UTC) or an RFC 3339 timestamp
+pub fn parse_date(input: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    let input = input.trim();
+    
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(input) {
+        return Ok(timestamp.with_timezone(&chrono::Utc));
+    }
     
-    let intersection = chars1.intersection(&chars2).count();
-    let union = chars1.union(&chars2).count();
+    chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| format!("Invalid date '{}'. Expected YYYY-MM-DD or an RFC 3339 timestamp", input))
+}
+
+/// A timestamp for people to read, in the local time zone unless `--utc` or the utc-timestamps setting is on
+///
+/// Machine-readable output (JSON, export metadata) uses RFC 3339 in UTC instead.
+pub fn format_timestamp(output: &OutputContext, timestamp: chrono::DateTime<chrono::Utc>) -> String {
+    if output.utc {
+        format_timestamp_in(timestamp, &chrono::Utc)
+    } else {
+        format_timestamp_in(timestamp, &chrono::Local)
+    }
+}
+
+/// [`format_timestamp`] in the given time zone: `2024-06-02 03:14:00 UTC` or `2024-06-02 05:14:00 +02:00`
+pub fn format_timestamp_in<Tz: chrono::TimeZone>(timestamp: chrono::DateTime<chrono::Utc>, zone: &Tz) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    timestamp.with_timezone(zone).format("%Y-%m-%d %H:%M:%S %Z").to_string()
+}
+
+/// How long ago a timestamp was, e.g. "3 hours ago"; see [`format_elapsed`]
+pub fn format_relative_time(timestamp: chrono::DateTime<chrono::Utc>) -> String {
+    format_elapsed(chrono::Utc::now().signed_duration_since(timestamp))
+}
+
+/// A duration for people to glance at: "just now" under a minute, then whole minutes, hours, days or weeks
+///
+/// Durations are rounded down, and negative ones (clock skew) count as "just now".
+pub fn format_elapsed(elapsed: chrono::Duration) -> String {
+    let plural = |n: i64, unit: &str| format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" });
     
-    if union == 0 {
-        0.0
+    if elapsed.num_minutes() < 1 {
+        "just now".to_string()
+    } else if elapsed.num_hours() < 1 {
+        plural(elapsed.num_minutes(), "minute")
+    } else if elapsed.num_days() < 1 {
+        plural(elapsed.num_hours(), "hour")
+    } else if elapsed.num_weeks() < 1 {
+        plural(elapsed.num_days(), "day")
     } else {
-        intersection as f64 / union as f64
+        plural(elapsed.num_weeks(), "week")
+    }
+}
+
+/// The exact timestamp with `exact`, e.g. for `--verbose`, otherwise how long ago it was
+pub fn format_recency(output: &OutputContext, timestamp: chrono::DateTime<chrono::Utc>, exact: bool) -> String {
+    if exact {
+        format_timestamp(output, timestamp)
+    } else {
+        format_relative_time(timestamp)
+    }
+}
+
+/// Match `text` against a glob pattern where `*` stands for any run of characters and `?` for one
+///
+/// Matching is case-sensitive, like variable names. Used by `--exclude-keys`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+    
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, t));
+            p += 1;
+        } else if let Some((after_star, tried)) = star {
+            // Let the last `*` swallow one more character
+            p = after_star;
+            t = tried + 1;
+            star = Some((after_star, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether `key` matches any of the glob `patterns`, see [`glob_match`]
+pub fn matches_any_glob(patterns: &[String], key: &str) -> bool {
+    patterns.iter().any(|pattern| glob_match(pattern, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_value() {
+        assert_eq!(mask_value("sk-1234567890abcd", false, false), "sk-1***abcd");
+        assert_eq!(mask_value("short", false, false), "*****");
+        assert_eq!(mask_value("", false, false), "");
+        assert_eq!(mask_value("ключ-секрет-значение", false, false), "ключ***ение");
+
+        assert_eq!(mask_value("sk-1234567890abcd", true, false), "sk-1****(17 chars)");
+        assert_eq!(mask_value("short", true, false), "****(5 chars)");
+        assert_eq!(mask_value("", true, false), "⚠ empty");
+        assert_eq!(mask_value("", true, true), "! empty");
+    }
+
+    #[test]
+    fn test_find_similar_configs() {
+        let available: Vec<String> = ["deepseek", "deepseek-coder", "kimi", "openrouter", "prod", "staging"]
+            .iter().map(|s| s.to_string()).collect();
+        let cases: &[(&str, &[&str])] = &[
+            // Missing, extra and swapped characters
+            ("deepsek", &["deepseek"]),
+            ("deepseeek", &["deepseek"]),
+            ("depeseek", &["deepseek"]),
+            ("kmii", &["kimi"]),
+            ("stagign", &["staging"]),
+            // Prefixes and substrings rank before typos
+            ("deep", &["deepseek", "deepseek-coder"]),
+            ("router", &["openrouter"]),
+            ("PROD", &["prod"]),
+            // Unrelated names suggest nothing
+            ("xyz", &[]),
+            ("production-eu", &[]),
+            ("", &[]),
+        ];
+
+        for (target, expected) in cases {
+            assert_eq!(find_similar_configs(target, &available), *expected, "suggestions for '{}'", target);
+        }
+    }
+
+    #[test]
+    fn test_find_similar_configs_caps_at_three() {
+        let available: Vec<String> = (1..=5).map(|n| format!("dev{}", n)).collect();
+        assert_eq!(find_similar_configs("dev", &available), vec!["dev1", "dev2", "dev3"]);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("deepseek", "deepseek"), 0);
+        assert_eq!(edit_distance("deepsek", "deepseek"), 1);
+        assert_eq!(edit_distance("kmii", "kimi"), 1);
+        assert_eq!(edit_distance("abc", ""), 3);
     }
-}
\ No newline at end of file
+    use chrono::{Datelike, Timelike};
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("30m").unwrap(), chrono::Duration::minutes(30));
+        assert_eq!(parse_duration("12h").unwrap(), chrono::Duration::hours(12));
+        assert_eq!(parse_duration("7d").unwrap(), chrono::Duration::days(7));
+        assert_eq!(parse_duration("2w").unwrap(), chrono::Duration::weeks(2));
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("7y").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn test_parse_date_absolute() {
+        let date = parse_date("2025-01-31").unwrap();
+        assert_eq!((date.year(), date.month(), date.day()), (2025, 1, 31));
+        assert_eq!(date.hour(), 0);
+
+        let timestamp = parse_date("2025-01-31T12:30:00+02:00").unwrap();
+        assert_eq!(timestamp.hour(), 10);
+    }
+
+    #[test]
+    fn test_format_timestamp_in() {
+        let timestamp = parse_date("2024-06-02T03:14:00Z").unwrap();
+        assert_eq!(format_timestamp_in(timestamp, &chrono::Utc), "2024-06-02 03:14:00 UTC");
+
+        let berlin = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(format_timestamp_in(timestamp, &berlin), "2024-06-02 05:14:00 +02:00");
+        let los_angeles = chrono::FixedOffset::west_opt(7 * 3600).unwrap();
+        assert_eq!(format_timestamp_in(timestamp, &los_angeles), "2024-06-01 20:14:00 -07:00");
+    }
+
+    #[test]
+    fn test_parse_date_invalid() {
+        assert!(parse_date("31/01/2025").is_err());
+        assert!(parse_date("2025-13-01").is_err());
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        let now = chrono::Utc::now();
+        assert_eq!(format_relative_time(now), "just now");
+        assert_eq!(format_relative_time(now - chrono::Duration::minutes(1)), "1 minute ago");
+        assert_eq!(format_relative_time(now - chrono::Duration::minutes(5)), "5 minutes ago");
+        assert_eq!(format_relative_time(now - chrono::Duration::hours(3)), "3 hours ago");
+        assert_eq!(format_relative_time(now - chrono::Duration::days(2)), "2 days ago");
+    }
+
+    #[test]
+    fn test_format_elapsed_boundaries() {
+        use chrono::Duration;
+        let cases = [
+            (Duration::seconds(-30), "just now"),
+            (Duration::zero(), "just now"),
+            (Duration::seconds(59), "just now"),
+            (Duration::seconds(60), "1 minute ago"),
+            (Duration::seconds(119), "1 minute ago"),
+            (Duration::minutes(59) + Duration::seconds(59), "59 minutes ago"),
+            (Duration::minutes(60), "1 hour ago"),
+            (Duration::hours(23) + Duration::minutes(59), "23 hours ago"),
+            (Duration::hours(24), "1 day ago"),
+            (Duration::days(6) + Duration::hours(23), "6 days ago"),
+            (Duration::days(7), "1 week ago"),
+            (Duration::days(13), "1 week ago"),
+            (Duration::days(14), "2 weeks ago"),
+            (Duration::days(400), "57 weeks ago"),
+        ];
+        for (elapsed, expected) in cases {
+            assert_eq!(format_elapsed(elapsed), expected, "{:?}", elapsed);
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("AWS_*", "AWS_SECRET_ACCESS_KEY"));
+        assert!(glob_match("AWS_*", "AWS_"));
+        assert!(!glob_match("AWS_*", "MY_AWS_KEY"));
+        assert!(glob_match("*_TOKEN", "ANTHROPIC_AUTH_TOKEN"));
+        assert!(glob_match("*AUTH*", "ANTHROPIC_AUTH_TOKEN"));
+        assert!(glob_match("KEY_?", "KEY_1"));
+        assert!(!glob_match("KEY_?", "KEY_12"));
+        assert!(glob_match("A*B*C", "AxxBxxBxxC"));
+        assert!(!glob_match("A*B*C", "AxxBxx"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("EXACT", "EXACT"));
+        assert!(!glob_match("EXACT", "EXACTLY"));
+        assert!(!glob_match("aws_*", "AWS_KEY"), "matching is case-sensitive");
+        assert!(matches_any_glob(&["FOO".to_string(), "*_SECRET".to_string()], "DB_SECRET"));
+        assert!(!matches_any_glob(&[], "DB_SECRET"));
+    }
+
+    #[test]
+    fn test_claude_configuration_uses_shared_list() {
+        for var in crate::types::constants::CLAUDE_ENV_VARS {
+            let mut variables = HashMap::new();
+            variables.insert(var.to_string(), "value".to_string());
+            assert!(is_claude_configuration(&variables, &[]), "{} should mark a Claude configuration", var);
+        }
+        
+        let mut variables = HashMap::new();
+        variables.insert("OTHER_VAR".to_string(), "value".to_string());
+        assert!(!is_claude_configuration(&variables, &[]));
+    }
+}
@@ -2,8 +2,12 @@ pub mod file_utils;
 pub mod shell_integration;
 pub mod helpers;
 pub mod feedback;
+pub mod clipboard;
+pub mod checksum;
 
 pub use file_utils::*;
 pub use shell_integration::*;
 pub use helpers::*;
-pub use feedback::*;
\ No newline at end of file
+pub use feedback::*;
+pub use clipboard::*;
+pub use checksum::*;
\ No newline at end of file
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use crate::utils::is_sensitive_key;
+
+/// How long to wait for a BASE_URL host to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Outcome of a single verification check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Result of running one check against one variable
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub key: String,
+    pub check: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// All check results for a configuration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerificationReport {
+    pub results: Vec<CheckResult>,
+}
+
+impl VerificationReport {
+    /// Check if any check failed
+    pub fn has_failures(&self) -> bool {
+        self.results.iter().any(|result| result.status == CheckStatus::Failed)
+    }
+
+    /// Number of failed checks
+    pub fn failure_count(&self) -> usize {
+        self.results.iter().filter(|result| result.status == CheckStatus::Failed).count()
+    }
+}
+
+/// Options controlling which checks run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerifyOptions {
+    /// Skip checks that need the network
+    pub offline: bool,
+}
+
+/// Run the verification rules against resolved variable values
+///
+/// Sensitive variables must not be empty, and URL variables must be valid
+/// http(s) URLs whose host accepts TCP connections.
+pub fn verify_variables(variables: &HashMap<String, String>, options: VerifyOptions) -> VerificationReport {
+    let mut keys: Vec<&String> = variables.keys().collect();
+    keys.sort();
+
+    let mut report = VerificationReport::default();
+    for key in keys {
+        let value = &variables[key];
+
+        if is_sensitive_key(key) {
+            report.results.push(check_non_empty(key, value));
+        }
+
+        if key.to_uppercase().ends_with("URL") {
+            match parse_http_url(value) {
+                Ok((host, port)) => {
+                    report.results.push(CheckResult {
+                        key: key.clone(),
+                        check: "valid URL",
+                        status: CheckStatus::Passed,
+                        detail: format!("{}:{}", host, port),
+                    });
+                    report.results.push(if options.offline {
+                        CheckResult {
+                            key: key.clone(),
+                            check: "reachable",
+                            status: CheckStatus::Skipped,
+                            detail: "offline".to_string(),
+                        }
+                    } else {
+                        check_reachable(key, &host, port)
+                    });
+                }
+                Err(reason) => report.results.push(CheckResult {
+                    key: key.clone(),
+                    check: "valid URL",
+                    status: CheckStatus::Failed,
+                    detail: reason,
+                }),
+            }
+        }
+    }
+
+    report
+}
+
+/// Check that a secret has a value
+fn check_non_empty(key: &str, value: &str) -> CheckResult {
+    let empty = value.trim().is_empty();
+    CheckResult {
+        key: key.to_string(),
+        check: "non-empty",
+        status: if empty { CheckStatus::Failed } else { CheckStatus::Passed },
+        detail: if empty { "value is empty".to_string() } else { format!("{} characters", value.len()) },
+    }
+}
+
+/// Check that a host accepts TCP connections on the given port
+fn check_reachable(key: &str, host: &str, port: u16) -> CheckResult {
+    let result = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("cannot resolve {}: {}", host, e))
+        .and_then(|addrs| {
+            let mut last_error = format!("no addresses found for {}", host);
+            for addr in addrs {
+                match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                    Ok(_) => return Ok(()),
+                    Err(e) => last_error = format!("cannot connect to {}:{}: {}", host, port, e),
+                }
+            }
+            Err(last_error)
+        });
+
+    CheckResult {
+        key: key.to_string(),
+        check: "reachable",
+        status: if result.is_ok() { CheckStatus::Passed } else { CheckStatus::Failed },
+        detail: result.err().unwrap_or_else(|| format!("{}:{}", host, port)),
+    }
+}
+
+/// Split an http(s) URL into host and port, using the scheme's default port
+pub fn parse_http_url(value: &str) -> Result<(String, u16), String> {
+    let (scheme, rest) = value.trim().split_once("://")
+        .ok_or_else(|| format!("'{}' has no scheme", value))?;
+
+    let default_port = match scheme.to_lowercase().as_str() {
+        "http" => 80,
+        "https" => 443,
+        other => return Err(format!("unsupported scheme '{}'", other)),
+    };
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+
+    let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+        // IPv6 literal such as [::1]:8080
+        let (host, after) = bracketed.split_once(']')
+            .ok_or_else(|| format!("'{}' has an unterminated IPv6 address", value))?;
+        (host, after.strip_prefix(':'))
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+
+    if host.is_empty() {
+        return Err(format!("'{}' has no host", value));
+    }
+
+    let port = match port {
+        Some(port) => port.parse::<u16>().map_err(|_| format!("invalid port '{}'", port))?,
+        None => default_port,
+    };
+
+    Ok((host.to_string(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse_http_url() {
+        assert_eq!(parse_http_url("https://api.deepseek.com/anthropic").unwrap(), ("api.deepseek.com".to_string(), 443));
+        assert_eq!(parse_http_url("http://localhost:8080").unwrap(), ("localhost".to_string(), 8080));
+        assert_eq!(parse_http_url("http://user:pw@host/path").unwrap(), ("host".to_string(), 80));
+        assert_eq!(parse_http_url("http://[::1]:9000/").unwrap(), ("::1".to_string(), 9000));
+        assert!(parse_http_url("api.example.com").is_err());
+        assert!(parse_http_url("ftp://example.com").is_err());
+        assert!(parse_http_url("https://").is_err());
+        assert!(parse_http_url("http://host:notaport").is_err());
+    }
+
+    #[test]
+    fn test_verify_empty_token_fails() {
+        let mut variables = HashMap::new();
+        variables.insert("ANTHROPIC_AUTH_TOKEN".to_string(), "  ".to_string());
+        variables.insert("ANTHROPIC_MODEL".to_string(), "".to_string());
+
+        let report = verify_variables(&variables, VerifyOptions { offline: true });
+        assert_eq!(report.results.len(), 1);
+        assert!(report.has_failures());
+        assert_eq!(report.results[0].check, "non-empty");
+    }
+
+    #[test]
+    fn test_verify_url_offline_skips_reachability() {
+        let mut variables = HashMap::new();
+        variables.insert("ANTHROPIC_BASE_URL".to_string(), "https://api.example.com".to_string());
+
+        let report = verify_variables(&variables, VerifyOptions { offline: true });
+        assert!(!report.has_failures());
+        assert_eq!(report.results[1].status, CheckStatus::Skipped);
+
+        variables.insert("ANTHROPIC_BASE_URL".to_string(), "not a url".to_string());
+        let report = verify_variables(&variables, VerifyOptions { offline: true });
+        assert_eq!(report.failure_count(), 1);
+    }
+
+    #[test]
+    fn test_verify_url_reachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let mut variables = HashMap::new();
+        variables.insert("ANTHROPIC_BASE_URL".to_string(), format!("http://127.0.0.1:{}", port));
+
+        let report = verify_variables(&variables, VerifyOptions::default());
+        assert!(!report.has_failures(), "{:?}", report);
+
+        drop(listener);
+        let report = verify_variables(&variables, VerifyOptions::default());
+        assert!(report.has_failures());
+    }
+}
@@ -0,0 +1,1159 @@
+//! End-to-end tests running the compiled binary, covering argument parsing,
+//! command dispatch, exit codes and what goes to stdout versus stderr
+
+use assert_cmd::assert::Assert;
+use assert_cmd::Command;
+use envswitch::types::constants::EXIT_CODE_DEFAULT_REPLACE_GUARD;
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// The envswitch binary with a scratch home and configuration directory
+struct Envswitch {
+    home: TempDir,
+}
+
+impl Envswitch {
+    fn new() -> Self {
+        Self { home: TempDir::new().expect("Failed to create temp directory") }
+    }
+
+    fn config_dir(&self) -> PathBuf {
+        self.home.path().join("config")
+    }
+
+    /// A command with a predictable environment: bash, UTF-8 and English messages
+    fn cmd(&self, args: &[&str]) -> Command {
+        let mut command = Command::cargo_bin("envswitch").expect("Failed to find the envswitch binary");
+        command.args(args)
+            .env("HOME", self.home.path())
+            .env("ENVSWITCH_CONFIG_DIR", self.config_dir())
+            .env("SHELL", "/bin/bash")
+            .env("LC_ALL", "C.UTF-8")
+            .env("TERM", "xterm")
+            .env_remove("ENVSWITCH_LANG");
+        command
+    }
+
+    /// Run a command with `input` piped to its stdin
+    fn cmd_with_stdin(&self, args: &[&str], input: &str) -> Command {
+        let mut command = self.cmd(args);
+        command.write_stdin(input);
+        command
+    }
+
+    /// Run a command that must succeed, returning its stdout
+    fn ok(&self, args: &[&str]) -> String {
+        stdout(&self.cmd(args).assert().success())
+    }
+
+    fn path(&self, name: &str) -> String {
+        self.home.path().join(name).display().to_string()
+    }
+}
+
+fn stdout(assert: &Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).into_owned()
+}
+
+fn stderr(assert: &Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stderr).into_owned()
+}
+
+fn assert_shell_code(stdout: &str) {
+    for line in stdout.lines().filter(|line| !line.is_empty()) {
+        assert!(
+            line.starts_with("export ") || line.starts_with("unset ") || line.starts_with('#'),
+            "unexpected line on stdout: {:?}", line
+        );
+    }
+}
+
+#[test]
+fn test_set_and_list() {
+    let envswitch = Envswitch::new();
+    envswitch.cmd(&["set", "work", "-e", "API_URL=https://api.example.com", "-d", "Work account"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work"));
+    assert!(Path::new(&envswitch.config_dir()).join("config.json").exists());
+
+    envswitch.cmd(&["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work - Work account (1 variables)"));
+
+    let stdout = envswitch.ok(&["list", "--columns", "name,vars"]);
+    assert!(stdout.lines().any(|line| line.starts_with("work") && line.trim_end().ends_with('1')));
+}
+
+#[test]
+fn test_set_from_stdin() {
+    let envswitch = Envswitch::new();
+    let input = "# from the clipboard\nexport API_URL=https://api.example.com\nNAME=\"from stdin\"\noops\n";
+    envswitch.cmd_with_stdin(&["set", "work", "--stdin", "-e", "NAME=from flag"], input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Parsed 2 variables from stdin"))
+        .stderr(predicate::str::contains("Skipped line 4 of stdin: expected KEY=VALUE"));
+
+    envswitch.cmd(&["use", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("export API_URL='https://api.example.com'"))
+        .stdout(predicate::str::contains("export NAME='from flag'"));
+    envswitch.cmd(&["set", "work", "--stdin", "--interactive"]).assert().code(2);
+}
+
+#[test]
+fn test_set_and_import_flatten_nested_json() {
+    let envswitch = Envswitch::new();
+    let file = envswitch.path("settings.json");
+    std::fs::write(&file, r#"{"anthropic": {"base_url": "https://api.example.com", "model": "m1"}, "hosts": ["a", "b"]}"#).unwrap();
+
+    envswitch.cmd(&["set", "work", "--file", &file, "--flatten"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("'hosts' is an array"));
+
+    envswitch.ok(&["set", "work", "--file", &file, "--flatten", "--flatten-arrays", "index"]);
+    envswitch.cmd(&["use", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("export ANTHROPIC_BASE_URL='https://api.example.com'"))
+        .stdout(predicate::str::contains("export HOSTS_1='b'"));
+
+    std::fs::write(&file, r#"{"anthropic": {"model": "m2"}}"#).unwrap();
+    envswitch.ok(&["import", &file, "--flatten", "--flatten-separator", "__"]);
+    envswitch.cmd(&["use", "imported"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("export ANTHROPIC__MODEL='m2'"));
+}
+
+#[test]
+fn test_use_prints_only_shell_code() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "API_URL=https://api.example.com", "-e", "NAME=it's"]);
+
+    for args in [&["use", "work"][..], &["--verbose", "use", "work"]] {
+        let assert = envswitch.cmd(args)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("export API_URL='https://api.example.com'"))
+            .stdout(predicate::str::contains(r#"export NAME='it'"'"'s'"#));
+        assert_shell_code(&stdout(&assert));
+    }
+
+    envswitch.cmd(&["list", "--active"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work"));
+}
+
+#[test]
+fn test_use_dry_run_leaves_clipboard_alone() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "API_URL=https://api.example.com"]);
+
+    envswitch.cmd(&["use", "work", "--dry-run", "--copy"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("export API_URL='https://api.example.com'"))
+        .stderr(predicate::str::contains("would be copied to the clipboard"))
+        .stderr(predicate::str::contains("clipboard:").not());
+}
+
+#[test]
+fn test_list_names_prints_only_names() {
+    let envswitch = Envswitch::new();
+
+    // A fresh home gets neither the welcome message nor a configuration directory
+    envswitch.cmd(&["list", "--names"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::is_empty());
+    assert!(!envswitch.config_dir().exists());
+
+    envswitch.ok(&["set", "zeta", "-e", "KEY=value"]);
+    envswitch.ok(&["set", "alpha", "-e", "KEY=value", "-d", "Work\taccount"]);
+    envswitch.ok(&["use", "alpha"]);
+
+    for args in [&["list", "--names"][..], &["--no-emoji", "--ascii", "list", "--names"]] {
+        envswitch.cmd(args)
+            .assert()
+            .success()
+            .stdout("alpha\nzeta\n")
+            .stderr(predicate::str::is_empty());
+    }
+
+    envswitch.cmd(&["list", "--names", "--with-description"])
+        .assert()
+        .success()
+        .stdout("alpha\tWork account\nzeta\t\n");
+    envswitch.cmd(&["list", "--names", "--table"]).assert().code(2);
+}
+
+#[test]
+fn test_current_prints_only_the_active_name() {
+    let envswitch = Envswitch::new();
+
+    envswitch.cmd(&["current"])
+        .assert()
+        .code(7)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::is_empty());
+    assert!(!envswitch.config_dir().exists());
+
+    envswitch.ok(&["set", "work", "-e", "KEY=value"]);
+    envswitch.ok(&["use", "work"]);
+    for args in [&["current"][..], &["--quiet", "current"], &["cur"]] {
+        envswitch.cmd(args)
+            .assert()
+            .success()
+            .stdout("work\n")
+            .stderr(predicate::str::is_empty());
+    }
+
+    let report: serde_json::Value = serde_json::from_str(&envswitch.ok(&["current", "--json"])).unwrap();
+    assert_eq!(report["active_config"], "work");
+    assert_eq!(report["stale"], false);
+    assert!(report["activated_at"].is_string());
+    assert!(report["shell"].is_string());
+
+    // --verbose reads the store for details, on stderr
+    envswitch.cmd(&["--verbose", "current"])
+        .assert()
+        .success()
+        .stdout("work\n")
+        .stderr(predicate::str::contains("Activated: "));
+
+    // Only the state file is read for the name, so a broken store doesn't break prompts
+    let config_file = envswitch.config_dir().join("config.json");
+    let store = std::fs::read_to_string(&config_file).unwrap();
+    std::fs::write(&config_file, "{ broken").unwrap();
+    envswitch.cmd(&["current"]).assert().success().stdout("work\n");
+    envswitch.cmd(&["current", "--json"]).assert().failure();
+    std::fs::write(&config_file, store).unwrap();
+
+    envswitch.ok(&["current", "--clear"]);
+    envswitch.cmd(&["current"]).assert().code(7);
+    let assert = envswitch.cmd(&["current", "--json"]).assert();
+    let report: serde_json::Value = serde_json::from_str(&stdout(&assert)).unwrap();
+    assert!(report["active_config"].is_null());
+    envswitch.cmd(&["list", "--names"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work"));
+}
+
+#[test]
+fn test_clear_unsets_variables_and_forgets_the_active_config() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "WORK_KEY=1", "-e", "SHARED=a", "--prepend", "PATH=/opt/work/bin"]);
+    envswitch.ok(&["set", "home", "-e", "HOME_KEY=2", "-e", "SHARED=b"]);
+
+    // Nothing active: nothing to eval
+    envswitch.cmd(&["clear"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("No configuration is active"));
+
+    envswitch.ok(&["use", "work"]);
+    envswitch.cmd(&["clear", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("unset SHARED\nunset WORK_KEY"))
+        // PATH only loses the entry envswitch added
+        .stdout(predicate::str::contains("/opt/work/bin"))
+        .stdout(predicate::str::contains("unset PATH").not())
+        .stdout(predicate::str::contains("HOME_KEY").not())
+        .stderr(predicate::str::contains("Clearing 3 variable(s) of 'work'"))
+        .stderr(predicate::str::contains("Removing added entries from PATH"))
+        .stderr(predicate::str::contains("Dry run"));
+    envswitch.cmd(&["current"]).assert().success().stdout("work\n");
+
+    envswitch.cmd(&["clear"])
+        .env("ENVSWITCH_SHELL", "fish")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("set -e SHARED\nset -e WORK_KEY"))
+        .stdout(predicate::str::contains("unset ").not());
+    envswitch.cmd(&["current"]).assert().code(7);
+
+    // --all-known covers every configuration even with nothing active
+    let stdout = envswitch.ok(&["clear", "--all-known"]);
+    for key in ["HOME_KEY", "SHARED", "WORK_KEY"] {
+        assert_eq!(stdout.matches(&format!("unset {}\n", key)).count(), 1, "{}", stdout);
+    }
+}
+
+#[test]
+fn test_drift_compares_with_the_values_set_at_activation() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "WORK_URL=https://one.example.com", "-e", "WORK_MODE=fast"]);
+    envswitch.ok(&["settings", "set", "drift-watch", "HTTPS_PROXY"]);
+    envswitch.ok(&["use", "work"]);
+
+    let drift_in_sync = || envswitch.cmd(&["drift"])
+        .env("WORK_URL", "https://one.example.com")
+        .env("WORK_MODE", "fast")
+        .env_remove("HTTPS_PROXY")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No drift"));
+    drift_in_sync();
+
+    // Editing the configuration is not drift; the shell still has what `use` set
+    envswitch.ok(&["set", "work", "-e", "WORK_MODE=slow"]);
+    drift_in_sync();
+
+    let assert = envswitch.cmd(&["drift", "--fix"])
+        .env("WORK_URL", "https://other.example.com")
+        .env_remove("WORK_MODE")
+        .env("HTTPS_PROXY", "http://proxy:8080")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("export WORK_MODE='fast'"))
+        .stdout(predicate::str::contains("export WORK_URL='https://one.example.com'"))
+        .stdout(predicate::str::contains("unset HTTPS_PROXY"))
+        .stderr(predicate::str::contains("3 variable(s) changed"))
+        .stderr(predicate::str::contains("WORK_MODE: unset"));
+    assert_shell_code(&stdout(&assert));
+
+    envswitch.ok(&["clear"]);
+    envswitch.cmd(&["drift"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No configuration is active"));
+}
+
+#[test]
+fn test_template_round_trip_asks_for_placeholders() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "team", "-e", "API_URL=https://api.example.com", "-e", "API_KEY=sk-team-secret-0123456789"]);
+    let template = envswitch.path("team.env");
+    envswitch.ok(&["export", "--template", "--format", "env", "--metadata", "--output", &template]);
+    let content = std::fs::read_to_string(&template).unwrap();
+    assert!(!content.contains("sk-team-secret"), "{}", content);
+    assert!(content.contains("API_KEY='{{API_KEY}}'"), "{}", content);
+
+    // A teammate imports the file into their own, empty store
+    envswitch.ok(&["delete", "team", "--force"]);
+    envswitch.cmd(&["import", &template])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 template configuration(s)"));
+
+    let assert = envswitch.cmd_with_stdin(&["use", "team"], "sk-typed-by-teammate-42\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("export API_KEY='sk-typed-by-teammate-42'"))
+        .stdout(predicate::str::contains("export API_URL='https://api.example.com'"))
+        .stderr(predicate::str::contains("API_KEY: "));
+    assert_shell_code(&stdout(&assert));
+
+    // The answer is not saved, and nothing is activated without one
+    assert!(!std::fs::read_to_string(envswitch.config_dir().join("config.json")).unwrap().contains("sk-typed"));
+    envswitch.cmd_with_stdin(&["use", "team"], "\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("No value entered for API_KEY"));
+}
+
+#[test]
+fn test_sync_imports_missing_configs_only_when_asked() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "shared", "-e", "API_KEY=sk-team-secret-0123456789", "-e", "URL=https://team.example.com"]);
+    envswitch.ok(&["set", "ci", "-e", "CI=true"]);
+    let team = envswitch.path("team.json");
+    envswitch.ok(&["export", "--output", &team]);
+
+    // Locally, 'ci' is missing, 'shared' has another key and 'mine' is not shared
+    envswitch.ok(&["delete", "ci", "--force"]);
+    envswitch.ok(&["set", "shared", "-e", "API_KEY=sk-local-secret-9876543210"]);
+    envswitch.ok(&["set", "mine", "-e", "LOCAL=1"]);
+    let config_file = envswitch.config_dir().join("config.json");
+    let before = std::fs::read_to_string(&config_file).unwrap();
+
+    envswitch.cmd_with_stdin(&["sync", &team], "")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  + ci"))
+        .stdout(predicate::str::contains("  • mine"))
+        .stdout(predicate::str::contains("~ shared (0 new, 1 changed, 0 only yours)"))
+        .stdout(predicate::str::contains("--apply-all-missing"))
+        .stdout(predicate::str::contains("sk-team-secret").not())
+        .stdout(predicate::str::contains("sk-local-secret").not());
+    assert_eq!(std::fs::read_to_string(&config_file).unwrap(), before);
+
+    envswitch.cmd(&["sync", &team, "--apply-all-missing"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 'ci'"))
+        .stdout(predicate::str::contains("Sync Summary"));
+    envswitch.cmd(&["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ci").and(predicate::str::contains("mine")));
+    let store = std::fs::read_to_string(&config_file).unwrap();
+    assert!(store.contains("sk-local-secret") && !store.contains("sk-team-secret"), "differing configurations are left alone");
+}
+
+#[test]
+fn test_doctor_repairs_a_hand_edited_name() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "KEY=value"]);
+    let config_file = envswitch.config_dir().join("config.json");
+    let content = std::fs::read_to_string(&config_file).unwrap();
+    std::fs::write(&config_file, content.replace("\"alias\": \"work\"", "\"alias\": \"job\"")).unwrap();
+    envswitch.cmd(&["list"]).assert().failure();
+
+    envswitch.cmd(&["doctor"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Configuration 'job' is stored under the name 'work'"));
+    envswitch.cmd_with_stdin(&["doctor", "--repair"], "")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--keep key or --keep alias"));
+
+    envswitch.cmd(&["doctor", "--repair", "--keep", "alias"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Repaired 'job'"));
+    envswitch.cmd(&["list", "--names"]).assert().success().stdout("job\n");
+    envswitch.cmd(&["doctor"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No problems found"));
+}
+
+#[test]
+fn test_examples_follow_the_detected_shell() {
+    let envswitch = Envswitch::new();
+    envswitch.cmd(&["use", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Examples:\n  # Switch to a configuration in the current shell\n  eval \"$(envswitch use deepseek)\"",
+        ));
+
+    envswitch.cmd(&["examples"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("• direnv - "));
+    envswitch.cmd(&["examples", "ci"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("   eval \"$(envswitch use ci)\""));
+    envswitch.cmd(&["examples", "ci"])
+        .env("ENVSWITCH_SHELL", "fish")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("   eval (envswitch use ci)"));
+
+    envswitch.cmd(&["examples", "kubernetes"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Topics: switching, sharing, ci, direnv"));
+}
+
+#[test]
+fn test_render_writes_scripts_and_keeps_foreign_files() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "deepseek", "-e", "API_URL=https://api.example.com", "-d", "DeepSeek"]);
+    let script = envswitch.path("activate.sh");
+    let unset = envswitch.path("deactivate.sh");
+
+    envswitch.ok(&["render", "--output", &script, "--unset-script", &unset, "--shell", "bash", "--alias", "deepseek"]);
+    let content = std::fs::read_to_string(&script).unwrap();
+    assert!(content.starts_with("#!/usr/bin/env bash\n# Generated by envswitch render"), "{}", content);
+    assert!(content.contains("export API_URL='https://api.example.com'"), "{}", content);
+    assert!(std::fs::read_to_string(&unset).unwrap().contains("unset API_URL"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(&script).unwrap().permissions().mode() & 0o777, 0o700);
+    }
+
+    // Generated scripts are regenerated, other files need --force
+    envswitch.ok(&["render", "--output", &script, "--alias", "deepseek"]);
+    std::fs::write(&script, "#!/bin/sh\necho mine\n").unwrap();
+    envswitch.cmd(&["render", "--output", &script, "--alias", "deepseek"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("wasn't generated by envswitch render"));
+    assert!(std::fs::read_to_string(&script).unwrap().contains("echo mine"));
+    envswitch.ok(&["render", "--output", &script, "--alias", "deepseek", "--force"]);
+
+    envswitch.cmd(&["render", "--output", &envswitch.path("none.sh")])
+        .assert()
+        .stderr(predicate::str::contains("No active configuration to render"));
+}
+
+#[test]
+fn test_repeated_import_warnings_are_summarised() {
+    let envswitch = Envswitch::new();
+    let file = envswitch.path("vendor.env");
+    let lines: Vec<String> = (1..=6).map(|index| format!("API_URL_{}=https://example.com/a b", index)).collect();
+    std::fs::write(&file, lines.join("\n")).unwrap();
+
+    let stdout = envswitch.ok(&["import", &file, "--dry-run"]);
+    let warnings: Vec<&str> = stdout.lines().filter(|line| line.contains("unquoted value(s)")).collect();
+    assert_eq!(warnings, ["⚠️  6 unquoted value(s) containing spaces, e.g. line 1, line 2, line 3 (and 3 more; --verbose lists all)"], "{}", stdout);
+
+    envswitch.cmd(&["import", &file, "--dry-run", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("⚠️  6 unquoted value(s) containing spaces:\n   • line 1\n"))
+        .stdout(predicate::str::contains("   • line 6\n"));
+}
+
+#[test]
+fn test_timings_go_to_stderr() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "KEY=value"]);
+
+    let assert = envswitch.cmd(&["--timings", "--verbose", "use", "work"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("step load_store took"))
+        .stderr(predicate::str::contains("Timing Summary"));
+    assert_shell_code(&stdout(&assert));
+    assert!(stderr(&assert).lines().any(|line| line.contains("detect_shell") && line.ends_with("ms")));
+}
+
+#[test]
+fn test_use_on_fresh_home_keeps_stdout_clean() {
+    let envswitch = Envswitch::new();
+    envswitch.cmd(&["use", "work"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("'work' not found"));
+}
+
+#[test]
+fn test_eval_mode_keeps_stdout_parseable() {
+    let envswitch = Envswitch::new();
+    let eval = |args: &[&str], input: &str| {
+        let mut command = envswitch.cmd_with_stdin(args, input);
+        command.env("ENVSWITCH_EVAL", "1");
+        command.assert()
+    };
+
+    // Neither the welcome message nor errors reach stdout
+    eval(&["use", "work"], "")
+        .code(3)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("'work' not found"));
+    eval(&["list"], "").stdout(predicate::str::is_empty());
+
+    envswitch.ok(&["set", "work", "-e", "API_URL=https://api.example.com"]);
+    for args in [&["use", "work"][..], &["--verbose", "use", "work"], &["use", "work", "--dry-run"]] {
+        let assert = eval(args, "")
+            .success()
+            .stdout(predicate::str::contains("export API_URL='https://api.example.com'"));
+        assert_shell_code(&stdout(&assert));
+    }
+
+    // Prompts fail instead of waiting for an answer
+    envswitch.ok(&["set", "team", "-e", "API_KEY=sk-team-secret-0123456789"]);
+    let template = envswitch.path("team.env");
+    envswitch.ok(&["export", "--template", "--format", "env", "--metadata", "--configs", "team", "--output", &template]);
+    envswitch.ok(&["import", &template, "--force"]);
+    eval(&["use", "team"], "sk-typed-0123456789\n")
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("while the output is eval'd"));
+    envswitch.cmd(&["--eval", "delete", "work"])
+        .assert()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("while the output is eval'd"));
+}
+
+#[test]
+fn test_watch_prints_only_shell_commands() {
+    let envswitch = Envswitch::new();
+
+    // Started before any configuration exists, when other commands show the welcome message
+    let output = std::thread::scope(|scope| {
+        scope.spawn(|| {
+            std::thread::sleep(std::time::Duration::from_millis(700));
+            envswitch.ok(&["set", "work", "-e", "API_URL=https://api.example.com"]);
+            envswitch.ok(&["use", "work"]);
+        });
+        envswitch.cmd(&["watch", "--once", "--debounce", "50"])
+            .timeout(std::time::Duration::from_secs(10))
+            .output()
+            .unwrap()
+    });
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("export API_URL='https://api.example.com'"), "stdout: {:?}", stdout);
+    assert_shell_code(&stdout);
+}
+
+#[test]
+fn test_git_track_names_the_history_repository() {
+    let envswitch = Envswitch::new();
+    let stdout = envswitch.ok(&["settings", "set", "git-track", "true"]);
+    let history = envswitch.config_dir().join("history");
+    assert!(stdout.contains(&format!("Repository: {}\n", history.display())), "stdout: {}", stdout);
+}
+
+#[test]
+fn test_not_found_suggestion() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "KEY=value"]);
+
+    envswitch.cmd(&["use", "wrok"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("Did you mean: work?"))
+        .stderr(predicate::str::contains("envswitch --help"));
+
+    for args in [
+        &["delete", "wrok", "--force"][..],
+        &["set-var", "wrok", "KEY", "other"],
+        &["alias", "add", "wrok", "w"],
+    ] {
+        envswitch.cmd(args)
+            .assert()
+            .code(3)
+            .stderr(predicate::str::contains("Configuration 'wrok' not found"))
+            .stderr(predicate::str::contains("Did you mean: work?"));
+    }
+
+    envswitch.cmd(&["env", "wrok"]).assert().code(3);
+}
+
+#[test]
+fn test_delete() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "KEY=value"]);
+    envswitch.ok(&["set", "home", "-e", "KEY=value"]);
+
+    envswitch.cmd(&["delete", "work", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("'work' deleted"));
+    envswitch.cmd(&["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work").not())
+        .stdout(predicate::str::contains("home"));
+
+    envswitch.cmd(&["delete", "work", "--force"]).assert().failure();
+}
+
+#[test]
+fn test_export_and_import() {
+    let source = Envswitch::new();
+    source.ok(&["set", "work", "-e", "API_URL=https://api.example.com"]);
+    source.ok(&["set", "home", "-e", "HOME_URL=http://localhost"]);
+    let export = source.path("export.json");
+    source.ok(&["export", "-o", &export]);
+
+    let target = Envswitch::new();
+    target.cmd(&["import", &export])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Successfully imported 2"));
+    target.cmd(&["use", "home"])
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("export HOME_URL='http://localhost'").trim());
+
+    target.cmd(&["import", &target.path("missing.json")])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("path is correct"));
+
+    // A YAML export is the same document, and imports the same way
+    let yaml = source.path("export.yaml");
+    source.ok(&["export", "-o", &yaml, "--format", "yaml", "--include-settings"]);
+    let target = Envswitch::new();
+    target.cmd(&["import", &yaml])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Successfully imported 2"));
+    target.cmd(&["use", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("export API_URL='https://api.example.com'"));
+}
+
+#[test]
+fn test_archive_import_keeps_existing_configs_unless_replacing_the_store() {
+    let source = Envswitch::new();
+    source.ok(&["set", "work", "-e", "API_URL=https://api.example.com"]);
+    let archive = source.path("envswitch.tar.gz");
+    source.ok(&["export", "--archive", &archive]);
+
+    let target = Envswitch::new();
+    target.ok(&["set", "local", "-e", "KEY=value"]);
+    let names = |expected: &'static str| target.cmd(&["list", "--names"]).assert().success().stdout(expected);
+
+    target.ok(&["import", "--archive", &archive]);
+    names("local\nwork\n");
+
+    // Replacing asks to type 'replace'; anything else keeps the store
+    target.cmd_with_stdin(&["import", "--archive", "--replace-store", &archive], "y\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("deletes 1 configuration(s)"));
+    names("local\nwork\n");
+
+    target.ok(&["import", "--archive", "--replace-store", "--yes", &archive]);
+    names("work\n");
+
+    // --force used to replace the store; it now has to be asked for
+    target.cmd(&["import", "--archive", "--force", &archive]).assert().code(2);
+}
+
+/// Configurations of a store without the timestamps and provenance that differ between runs
+fn comparable_configs(envswitch: &Envswitch) -> serde_json::Value {
+    let content = std::fs::read_to_string(envswitch.config_dir().join("config.json")).unwrap();
+    let mut store: serde_json::Value = serde_json::from_str(&content).unwrap();
+    let configs = store["configs"].as_object_mut().unwrap();
+    for config in configs.values_mut() {
+        let config = config.as_object_mut().unwrap();
+        for field in ["created_at", "updated_at", "source"] {
+            config.remove(field);
+        }
+    }
+    store["configs"].take()
+}
+
+#[test]
+fn test_shell_export_script_recreates_the_store() {
+    let source = Envswitch::new();
+    source.ok(&[
+        "set", "work", "-e", "API_URL=https://api.example.com/a b", "-e", "NAME=it's", "-e", "MULTI=line 1\nline 2",
+        "-e", "EMPTY=", "--allow-empty-value", "-e", "API_KEY=sk-work-secret-0123456789", "--prepend", "PATH=/opt/tool/bin",
+        "-d", "Work 'account'", "--note", "rotate monthly", "--link", "https://console.example.com", "--expires", "2030-01-01",
+    ]);
+    source.ok(&["set", "other", "-e", "KEY=value"]);
+    source.ok(&["alias", "add", "work", "w"]);
+    source.ok(&["pin", "work"]);
+    source.ok(&["protect", "work"]);
+    source.ok(&["archive", "other"]);
+    let script = source.path("restore.sh");
+    source.ok(&["export", "--format", "shell", "--output", &script]);
+
+    let binary_dir = Path::new(env!("CARGO_BIN_EXE_envswitch")).parent().unwrap();
+    let path = format!("{}:{}", binary_dir.display(), std::env::var("PATH").unwrap_or_default());
+    let run = |envswitch: &Envswitch, script: &str, extra: &[(&str, &str)]| {
+        let mut command = Command::new("sh");
+        command.arg(script)
+            .env("HOME", envswitch.path(""))
+            .env("ENVSWITCH_CONFIG_DIR", envswitch.config_dir())
+            .env("PATH", &path)
+            .envs(extra.iter().copied());
+        command.assert()
+    };
+    let target = Envswitch::new();
+    run(&target, &script, &[]).success();
+    assert_eq!(comparable_configs(&target), comparable_configs(&source));
+
+    // Redacted scripts hold no secrets and read them from the environment
+    let redacted = source.path("redacted.sh");
+    source.ok(&["export", "--format", "shell", "--redact-secrets", "--output", &redacted]);
+    assert!(!std::fs::read_to_string(&redacted).unwrap().contains("sk-work-secret"));
+    let target = Envswitch::new();
+    // Missing secrets stop the script
+    run(&target, &redacted, &[]).failure();
+    run(&target, &redacted, &[("API_KEY", "sk-work-secret-0123456789"), ("KEY", "value")]).success();
+    assert_eq!(comparable_configs(&target), comparable_configs(&source));
+}
+
+#[test]
+fn test_archived_configs_are_hidden_but_kept() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "current", "-e", "A=1"]);
+    envswitch.ok(&["set", "old-project", "-e", "B=2"]);
+    envswitch.ok(&["archive", "old-project"]);
+
+    envswitch.cmd(&["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old-project").not());
+    envswitch.cmd(&["list", "--names"]).assert().success().stdout("current\n");
+    envswitch.cmd(&["list", "--names", "--archived"]).assert().success().stdout("old-project\n");
+    envswitch.cmd(&["list", "--names", "--all"]).assert().success().stdout("current\nold-project\n");
+    envswitch.cmd(&["list", "--all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old-project [archived]"));
+
+    // Exports keep archived configurations unless told otherwise
+    let all = envswitch.path("all.json");
+    envswitch.ok(&["export", "--output", &all]);
+    assert!(std::fs::read_to_string(&all).unwrap().contains("old-project"));
+    let current = envswitch.path("current.json");
+    envswitch.ok(&["export", "--exclude-archived", "--output", &current]);
+    assert!(!std::fs::read_to_string(&current).unwrap().contains("old-project"));
+
+    envswitch.cmd(&["use", "old-project"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("is archived"));
+    envswitch.cmd(&["use", "old-project", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("B"));
+
+    envswitch.ok(&["unarchive", "old-project"]);
+    envswitch.cmd(&["list", "--names"]).assert().success().stdout("current\nold-project\n");
+}
+
+/// Set the mode of the configuration directory and everything directly inside it
+#[cfg(unix)]
+fn chmod_config_dir(envswitch: &Envswitch, dir_mode: u32, file_mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = envswitch.config_dir();
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_file() {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(file_mode)).unwrap();
+        }
+    }
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(dir_mode)).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_only_config_dir() {
+    // Permissions don't stop root, so there is nothing to test
+    if unsafe { libc::geteuid() } == 0 {
+        return;
+    }
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "A=1"]);
+    chmod_config_dir(&envswitch, 0o500, 0o400);
+
+    // Run everything before restoring the permissions, so a failed assertion can't leave them behind
+    let list = envswitch.cmd(&["list"]).assert();
+    let env = envswitch.cmd(&["env", "work"]).assert();
+    let export = envswitch.cmd(&["export", "--output", &envswitch.path("out.json")]).assert();
+    let used = envswitch.cmd(&["use", "work"]).assert();
+    let not_persisted = envswitch.cmd(&["use", "work", "--no-persist"]).assert();
+    let current = envswitch.cmd(&["current"]).assert();
+    let set = envswitch.cmd(&["set", "other", "-e", "B=2"]).assert();
+    let writable = Envswitch::new();
+    let elsewhere = envswitch.cmd(&["--config-dir", &writable.config_dir().display().to_string(), "set", "other", "-e", "B=2"])
+        .assert();
+    chmod_config_dir(&envswitch, 0o700, 0o600);
+
+    list.success().stdout(predicate::str::contains("work"));
+    env.success();
+    export.success();
+
+    used.success()
+        .stdout(predicate::str::contains("export A='1'"))
+        .stderr(predicate::str::contains("was not recorded as the active configuration"));
+    not_persisted.success().stderr(predicate::str::is_empty());
+    current.stdout(predicate::str::is_empty());
+
+    set.code(4)
+        .stderr(predicate::str::contains(format!("'{}' is read-only", envswitch.config_dir().display())))
+        .stderr(predicate::str::contains("--config-dir"));
+    elsewhere.success();
+    writable.cmd(&["list", "--names"]).assert().success().stdout("other\n");
+}
+
+#[test]
+fn test_mutating_commands_print_a_json_report() {
+    let envswitch = Envswitch::new();
+    let report = |args: &[&str]| -> serde_json::Value {
+        let args: Vec<&str> = ["--report", "json"].iter().chain(args).copied().collect();
+        let stdout = envswitch.ok(&args);
+        serde_json::from_str(&stdout).unwrap_or_else(|e| panic!("{:?} printed more than the report ({}):\n{}", args, e, stdout))
+    };
+
+    let set = report(&["set", "work", "-e", "A=1", "-e", "B=2"]);
+    assert_eq!(set["operation"], "Set");
+    assert_eq!(set["succeeded"], 1);
+    assert!(set["duration_ms"].is_u64());
+    assert!(set["details"].as_array().unwrap().iter().any(|detail| detail["label"] == "Total variables" && detail["value"] == "2"));
+
+    let backup = report(&["backup", "create", "--note", "before cleanup"]);
+    assert_eq!(backup["operation"], "Backup");
+    assert!(backup["artifacts"][0]["size"].as_u64().unwrap() > 0);
+
+    let export = envswitch.path("export.json");
+    assert_eq!(report(&["export", "-o", &export])["operation"], "Export");
+    assert_eq!(report(&["delete", "work", "--force"])["operation"], "Delete");
+    let import = report(&["import", &export]);
+    assert_eq!((&import["operation"], &import["succeeded"]), (&"Import".into(), &1.into()));
+    assert_eq!(report(&["prune", "--expired", "--force"])["succeeded"], 0);
+
+    // Without the flag the report is the usual text summary
+    envswitch.cmd(&["delete", "work", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("📊 Delete Summary:"));
+}
+
+#[test]
+fn test_json_fields_prune_status_and_list_output() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "APP_URL=https://example.com", "-e", "APP_MODE=dev", "-d", "Work"]);
+    envswitch.ok(&["set", "home", "-e", "APP_URL=http://localhost"]);
+    envswitch.ok(&["use", "work"]);
+
+    let assert = envswitch.cmd(&["status", "--json", "--fields", "key,matches"])
+        .env("APP_URL", "https://example.com")
+        .env_remove("APP_MODE")
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_str(&stdout(&assert)).unwrap();
+    assert_eq!(report["active_config"], "work");
+    assert_eq!(report["variables"], serde_json::json!([
+        {"key": "APP_MODE", "matches": false},
+        {"key": "APP_URL", "matches": true},
+    ]));
+
+    let list: serde_json::Value = serde_json::from_str(&envswitch.ok(&["list", "--json", "--fields", "name, active"])).unwrap();
+    assert_eq!(list, serde_json::json!([
+        {"name": "home", "active": false},
+        {"name": "work", "active": true},
+    ]));
+    // Without --fields the full schema is printed, but never the values
+    envswitch.cmd(&["list", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"description\": \"Work\""))
+        .stdout(predicate::str::contains("\"variables\": 2"))
+        .stdout(predicate::str::contains("example.com").not());
+
+    envswitch.cmd(&["status", "--json", "--fields", "key,value"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown field 'value'. Available fields: key, current, matches"));
+    envswitch.cmd(&["status", "--fields", "key"]).assert().failure();
+}
+
+#[test]
+fn test_init_is_scriptable_and_idempotent() {
+    let envswitch = Envswitch::new();
+    let args = ["init", "--shell", "bash", "--non-interactive", "--install", "--create", "claude", "--preset", "claude", "--set-default"];
+    let init = || envswitch.cmd(&args)
+        .env("ANTHROPIC_AUTH_TOKEN", "sk-ant-1234567890")
+        .assert()
+        .success();
+
+    init()
+        .stdout(predicate::str::contains("Added the shell integration"))
+        .stdout(predicate::str::contains("eval \"$(envswitch use claude)\""));
+    init()
+        .stdout(predicate::str::contains("Shell integration already in"))
+        .stdout(predicate::str::contains("'claude' already exists"))
+        .stdout(predicate::str::contains("'claude' is already the default"));
+
+    let bashrc = std::fs::read_to_string(envswitch.home.path().join(".bashrc")).unwrap();
+    assert_eq!(bashrc.matches("# EnvSwitch Shell Integration - START").count(), 1);
+    assert!(bashrc.contains("eval \"$(envswitch shellenv --activate)\""));
+    envswitch.cmd(&["default", "show"]).assert().success().stdout("Default configuration: claude\n");
+    envswitch.cmd(&["env", "claude"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ANTHROPIC_AUTH_TOKEN"));
+    assert!(envswitch.config_dir().join(".welcome_shown").exists());
+
+    // Without a source for the variables nothing is created
+    envswitch.cmd(&["init", "--non-interactive", "--create", "other"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--preset or --from-env"));
+}
+
+#[test]
+fn test_init_steps_can_be_skipped() {
+    let envswitch = Envswitch::new();
+    envswitch.cmd_with_stdin(&["init", "--shell", "zsh"], "n\nn\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Skipped the shell integration"))
+        .stdout(predicate::str::contains("Skipped creating a configuration"));
+    assert!(!envswitch.home.path().join(".zshrc").exists());
+    envswitch.cmd(&["list", "--names"]).assert().success().stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn test_usage_errors_exit_with_clap_status() {
+    let envswitch = Envswitch::new();
+    envswitch.cmd(&["use"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("Usage"));
+}
+
+#[test]
+fn test_variable_comments_are_kept_but_never_reach_the_shell() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "API_URL=https://v1.example.com", "--comment", "API_URL=pinned until v2"]);
+    envswitch.ok(&["set", "work", "-e", "MODEL=large"]);
+    // A comment alone updates an existing configuration
+    envswitch.ok(&["set", "work", "--comment", "MODEL=cheaper than xl"]);
+    envswitch.cmd(&["set", "work", "--comment", "MISSING=why"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not set: MISSING"));
+
+    envswitch.cmd(&["list", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("API_URL = https://v1.example.com  # pinned until v2"));
+    envswitch.cmd(&["use", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pinned").not())
+        .stdout(predicate::str::contains("cheaper").not());
+
+    let exported = envswitch.path("work.env");
+    envswitch.ok(&["export", "--format", "env", "--metadata", "--output", &exported]);
+    let content = std::fs::read_to_string(&exported).unwrap();
+    assert!(content.contains("# pinned until v2\nAPI_URL="), "{}", content);
+
+    envswitch.ok(&["delete", "work", "--force"]);
+    envswitch.ok(&["import", &exported]);
+    envswitch.cmd(&["list", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("MODEL = large  # cheaper than xl"));
+
+    // An empty reason removes the comment
+    envswitch.ok(&["set", "work", "--comment", "MODEL="]);
+    envswitch.cmd(&["list", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("cheaper").not());
+}
+
+#[test]
+fn test_strict_turns_warnings_into_errors() {
+    let envswitch = Envswitch::new();
+    let file = envswitch.path("team.env");
+    std::fs::write(&file, "GREETING=hello world\nMOTTO=carpe diem\n").unwrap();
+    let names = || envswitch.ok(&["list", "--names"]);
+
+    // The same import succeeds with warnings, and fails with --strict before importing anything
+    envswitch.cmd(&["--strict", "import", &file])
+        .assert()
+        .code(5)
+        .stdout(predicate::str::contains("2 unquoted value(s) containing spaces"))
+        .stderr(predicate::str::contains("Stopped because of 2 warning(s): warnings are errors with --strict"));
+    assert!(!names().contains("imported"));
+    // `import --strict` only refuses invalid configurations; warnings are fine
+    envswitch.cmd(&["import", &file, "--strict"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 unquoted value(s) containing spaces"));
+
+    // A credential that looks wrong is saved with a warning, or refused
+    envswitch.cmd(&["set", "work", "-e", "API_KEY=short"]).assert().success();
+    envswitch.cmd(&["--strict", "set", "home", "-e", "API_KEY=short"])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("is only 5 characters long"));
+    assert!(!names().contains("home"));
+
+    // The setting makes every run strict; clean input still passes
+    envswitch.ok(&["settings", "set", "strict", "true"]);
+    envswitch.cmd(&["set", "home", "-e", "API_KEY=short"]).assert().code(5);
+    envswitch.ok(&["set", "home", "-e", "APP_URL=https://example.com"]);
+}
+
+#[test]
+fn test_replace_asks_before_removing_many_variables() {
+    let envswitch = Envswitch::new();
+    let mut args = vec!["set", "work"];
+    let pairs: Vec<String> = (0..12).map(|i| format!("VAR_{:02}=value", i)).collect();
+    for pair in &pairs {
+        args.extend(["-e", pair.as_str()]);
+    }
+    envswitch.ok(&args);
+    let count = || envswitch.ok(&["list", "--verbose", "--flat"]).matches("VAR_").count();
+
+    // Declining, or no terminal to answer, changes nothing and gets its own exit status
+    for input in ["n\n", ""] {
+        envswitch.cmd_with_stdin(&["set", "work", "--replace", "-e", "KEY=v"], input)
+            .assert()
+            .code(EXIT_CODE_DEFAULT_REPLACE_GUARD)
+            .stderr(predicate::str::contains("--replace would remove 12 of 12 variables from 'work': VAR_00, VAR_01"))
+            .stderr(predicate::str::contains("Nothing changed"));
+        assert_eq!(count(), 12);
+    }
+    envswitch.cmd(&["backup", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("before set --replace").not());
+
+    // Confirming removes them, and the backup taken first undoes it
+    let undo = |stdout: &str| stdout.lines()
+        .find_map(|line| line.split("Undo with: envswitch backup restore ").nth(1))
+        .unwrap_or_else(|| panic!("no undo hint in: {}", stdout))
+        .to_string();
+    let assert = envswitch.cmd_with_stdin(&["set", "work", "--replace", "-e", "KEY=v"], "y\n")
+        .assert()
+        .success();
+    assert_eq!(count(), 0);
+    envswitch.cmd(&["backup", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("before set --replace of work"));
+    envswitch.ok(&["backup", "restore", &undo(&stdout(&assert)), "--force"]);
+    assert_eq!(count(), 12);
+
+    // Removing up to half is not asked about
+    envswitch.ok(&["set", "work", "--replace", "-e", "VAR_00=value", "-e", "VAR_01=value", "-e", "VAR_02=value",
+        "-e", "VAR_03=value", "-e", "VAR_04=value", "-e", "VAR_05=value"]);
+    assert_eq!(count(), 6);
+
+    // --yes removes them without asking, still after a backup
+    let stdout = envswitch.ok(&["set", "work", "--replace", "--yes", "-e", "KEY=v"]);
+    assert_eq!(count(), 0);
+    envswitch.ok(&["backup", "restore", &undo(&stdout), "--force"]);
+    assert_eq!(count(), 6);
+
+    // The guard can be turned off
+    envswitch.ok(&["settings", "set", "replace-guard", "off"]);
+    envswitch.ok(&["set", "work", "--replace", "-e", "KEY=v"]);
+    assert_eq!(count(), 0);
+}
+
+#[test]
+fn test_backup_verify_reports_damaged_backups() {
+    let envswitch = Envswitch::new();
+    envswitch.ok(&["set", "work", "-e", "KEY=value"]);
+    envswitch.ok(&["backup", "create", "--note", "good"]);
+    let damaged = envswitch.ok(&["backup", "create", "--note", "damaged"]);
+    let damaged = damaged.lines()
+        .find_map(|line| line.split("Backup created: ").nth(1))
+        .unwrap_or_else(|| panic!("no backup path in: {}", damaged))
+        .to_string();
+
+    let stdout = envswitch.ok(&["backup", "verify"]);
+    assert_eq!(stdout.matches("checksum verified").count(), 2, "{}", stdout);
+
+    // Cut short, as if the disk had filled up while it was written
+    let content = std::fs::read(&damaged).unwrap();
+    std::fs::write(&damaged, &content[..content.len() / 2]).unwrap();
+    envswitch.cmd(&["backup", "verify"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("❌"))
+        .stdout(predicate::str::contains("checksum verified"))
+        .stderr(predicate::str::contains("1 of 2 backups failed verification"));
+    envswitch.ok(&["backup", "verify", "good"]);
+}
@@ -3,23 +3,28 @@ use std::fs;
 use std::path::Path;
 use tempfile::TempDir;
 
-use envswitch::config::{ConfigManager, FileConfigManager};
-use envswitch::commands::import_export::{handle_export_command, handle_import_command};
+use envswitch::output::OutputContext;
+use envswitch::config::{ConfigManager, FileConfigManager, StorageLayout};
+use envswitch::commands::import_export::{handle_export_command, handle_import_command, ExportCommandOptions, ImportCommandOptions};
 use envswitch::commands::config_commands::{handle_delete_command};
+use envswitch::commands::backup_commands::handle_backup_diff_command;
+use envswitch::commands::env_commands::handle_env_command;
 use envswitch::types::ConfigPaths;
 
 /// Helper function to create a temporary config directory
 fn create_temp_config() -> (TempDir, ConfigPaths) {
     let temp_dir = TempDir::new().expect("Failed to create temp directory");
-    let config_dir = temp_dir.path().to_path_buf();
-    let config_paths = ConfigPaths {
-        config_dir: config_dir.clone(),
-        config_file: config_dir.join("config.json"),
-        state_file: config_dir.join("state.json"),
-    };
+    let config_paths = ConfigPaths::in_dir(temp_dir.path());
     (temp_dir, config_paths)
 }
 
+/// Helper function to create a manager keeping its store in the given layout
+fn create_manager(config_paths: ConfigPaths, layout: StorageLayout) -> FileConfigManager {
+    let config_manager = FileConfigManager::with_paths(config_paths);
+    config_manager.set_setting("storage-layout", &layout.to_string()).expect("Failed to choose the storage layout");
+    config_manager
+}
+
 /// Helper function to create test environment variables
 fn create_test_env_vars() -> HashMap<String, String> {
     let mut vars = HashMap::new();
@@ -65,8 +70,37 @@ mod end_to_end_workflow_tests {
 
     #[test]
     fn test_complete_configuration_lifecycle() {
+        complete_configuration_lifecycle(StorageLayout::Single);
+    }
+
+    #[test]
+    fn test_complete_configuration_lifecycle_split_layout() {
+        complete_configuration_lifecycle(StorageLayout::Split);
+    }
+
+    #[test]
+    fn test_export_import_with_conflicts() {
+        export_import_with_conflicts(StorageLayout::Single);
+    }
+
+    #[test]
+    fn test_export_import_with_conflicts_split_layout() {
+        export_import_with_conflicts(StorageLayout::Split);
+    }
+
+    #[test]
+    fn test_backup_and_restore_workflow() {
+        backup_and_restore_workflow(StorageLayout::Single);
+    }
+
+    #[test]
+    fn test_backup_and_restore_workflow_split_layout() {
+        backup_and_restore_workflow(StorageLayout::Split);
+    }
+
+    fn complete_configuration_lifecycle(layout: StorageLayout) {
         let (_temp_dir, config_paths) = create_temp_config();
-        let config_manager = FileConfigManager::with_paths(config_paths.clone());
+        let config_manager = create_manager(config_paths.clone(), layout);
         
         // Step 1: Create initial configurations
         let deepseek_vars = create_test_env_vars();
@@ -82,13 +116,16 @@ mod end_to_end_workflow_tests {
         // Step 2: Export configurations using command handler
         let export_path = config_paths.config_dir.join("lifecycle_export.json");
         let export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(export_path.to_string_lossy().to_string()),
-            vec![], // Export all
-            "json".to_string(),
-            true, // Include metadata
-            true, // Pretty print
-            false, // Not verbose
+            ExportCommandOptions {
+                output: Some(export_path.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                metadata: true,
+                pretty: true,
+                ..Default::default()
+            },
+            false,
         );
         assert!(export_result.is_ok(), "Export should succeed");
         assert!(export_path.exists(), "Export file should exist");
@@ -101,9 +138,11 @@ mod end_to_end_workflow_tests {
         
         // Step 4: Delete one configuration using command handler
         let delete_result = handle_delete_command(
+            &OutputContext::default(),
             &config_manager,
             "kimi".to_string(),
             true, // Force delete
+            false, // Not a dry run
             false, // Not verbose
         );
         assert!(delete_result.is_ok(), "Delete should succeed");
@@ -115,14 +154,14 @@ mod end_to_end_workflow_tests {
         
         // Step 5: Import configurations to restore deleted one
         let import_result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            export_path.to_string_lossy().to_string(),
-            false, // Not force
-            true,  // Merge existing
-            false, // Not dry run
-            false, // Don't skip validation
-            false, // No backup
-            false, // Not verbose
+            ImportCommandOptions {
+                file: export_path.to_string_lossy().to_string(),
+                merge: true,
+                ..Default::default()
+            },
+            false,
         );
         assert!(import_result.is_ok(), "Import should succeed");
         
@@ -137,10 +176,9 @@ mod end_to_end_workflow_tests {
         assert_eq!(restored_kimi.description, Some("Kimi AI config".to_string()), "Description should be restored");
     }
 
-    #[test]
-    fn test_export_import_with_conflicts() {
+    fn export_import_with_conflicts(layout: StorageLayout) {
         let (_temp_dir, config_paths) = create_temp_config();
-        let config_manager = FileConfigManager::with_paths(config_paths.clone());
+        let config_manager = create_manager(config_paths.clone(), layout);
         
         // Create initial configuration
         let mut original_vars = HashMap::new();
@@ -159,14 +197,14 @@ mod end_to_end_workflow_tests {
         
         // Test import with merge (should combine variables)
         let import_result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            export_path.to_string_lossy().to_string(),
-            false, // Not force
-            true,  // Merge existing
-            false, // Not dry run
-            false, // Don't skip validation
-            false, // No backup
-            false, // Not verbose
+            ImportCommandOptions {
+                file: export_path.to_string_lossy().to_string(),
+                merge: true,
+                ..Default::default()
+            },
+            false,
         );
         assert!(import_result.is_ok(), "Merge import should succeed");
         
@@ -177,10 +215,9 @@ mod end_to_end_workflow_tests {
         assert_eq!(merged_config.variables.get("VAR3"), Some(&"new_value3".to_string()), "VAR3 should be added");
     }
 
-    #[test]
-    fn test_backup_and_restore_workflow() {
+    fn backup_and_restore_workflow(layout: StorageLayout) {
         let (_temp_dir, config_paths) = create_temp_config();
-        let config_manager = FileConfigManager::with_paths(config_paths.clone());
+        let config_manager = create_manager(config_paths.clone(), layout);
         
         // Create initial configurations
         let test_vars = create_test_env_vars();
@@ -195,14 +232,15 @@ mod end_to_end_workflow_tests {
         
         // Import with backup enabled
         let import_result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            import_path.to_string_lossy().to_string(),
-            true,  // Force overwrite
-            false, // Don't merge
-            false, // Not dry run
-            false, // Don't skip validation
-            true,  // Create backup
-            false, // Not verbose
+            ImportCommandOptions {
+                file: import_path.to_string_lossy().to_string(),
+                force: true,
+                backup: true,
+                ..Default::default()
+            },
+            false,
         );
         assert!(import_result.is_ok(), "Import with backup should succeed");
         
@@ -239,22 +277,29 @@ mod cross_format_compatibility_tests {
         // Export as JSON
         let json_export = config_paths.config_dir.join("export.json");
         let json_export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(json_export.to_string_lossy().to_string()),
-            vec![],
-            "json".to_string(),
-            false, false, false,
+            ExportCommandOptions {
+                output: Some(json_export.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                ..Default::default()
+            },
+            false,
         );
         assert!(json_export_result.is_ok(), "JSON export should succeed");
         
         // Export as ENV with metadata to preserve config names
         let env_export = config_paths.config_dir.join("export.env");
         let env_export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(env_export.to_string_lossy().to_string()),
-            vec![],
-            "env".to_string(),
-            true, false, false, // Include metadata
+            ExportCommandOptions {
+                output: Some(env_export.to_string_lossy().to_string()),
+                format: "env".to_string(),
+                metadata: true,
+                ..Default::default()
+            },
+            false,
         );
         assert!(env_export_result.is_ok(), "ENV export should succeed");
         
@@ -275,9 +320,13 @@ mod cross_format_compatibility_tests {
         let config_manager2 = FileConfigManager::with_paths(config_paths2);
         
         let env_import_result = handle_import_command(
+            &OutputContext::default(),
             &config_manager2,
-            env_export.to_string_lossy().to_string(),
-            false, false, false, false, false, false,
+            ImportCommandOptions {
+                file: env_export.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            false,
         );
         assert!(env_import_result.is_ok(), "ENV import should succeed");
         
@@ -299,11 +348,14 @@ mod cross_format_compatibility_tests {
         // Export as YAML
         let yaml_export = config_paths.config_dir.join("export.yaml");
         let yaml_export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(yaml_export.to_string_lossy().to_string()),
-            vec![],
-            "yaml".to_string(),
-            false, false, false,
+            ExportCommandOptions {
+                output: Some(yaml_export.to_string_lossy().to_string()),
+                format: "yaml".to_string(),
+                ..Default::default()
+            },
+            false,
         );
         assert!(yaml_export_result.is_ok(), "YAML export should succeed");
         
@@ -339,12 +391,15 @@ mod performance_tests {
         let start_time = std::time::Instant::now();
         
         let export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(export_path.to_string_lossy().to_string()),
-            vec![],
-            "json".to_string(),
-            true, // Include metadata
-            true, // Pretty print
+            ExportCommandOptions {
+                output: Some(export_path.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                metadata: true,
+                pretty: true,
+                ..Default::default()
+            },
             false,
         );
         
@@ -363,9 +418,13 @@ mod performance_tests {
         
         let import_start = std::time::Instant::now();
         let import_result = handle_import_command(
+            &OutputContext::default(),
             &config_manager2,
-            export_path.to_string_lossy().to_string(),
-            false, false, false, false, false, false,
+            ImportCommandOptions {
+                file: export_path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            false,
         );
         let import_duration = import_start.elapsed();
         
@@ -405,11 +464,16 @@ mod performance_tests {
         let export_start = std::time::Instant::now();
         
         let export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(export_path.to_string_lossy().to_string()),
-            vec![], // Export all
-            "json".to_string(),
-            true, true, false,
+            ExportCommandOptions {
+                output: Some(export_path.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                metadata: true,
+                pretty: true,
+                ..Default::default()
+            },
+            false,
         );
         
         let export_duration = export_start.elapsed();
@@ -439,9 +503,13 @@ mod error_recovery_tests {
         
         // Test import with corrupted file
         let import_result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            corrupted_json.to_string_lossy().to_string(),
-            false, false, false, false, false, false,
+            ImportCommandOptions {
+                file: corrupted_json.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            false,
         );
         
         assert!(import_result.is_err(), "Import of corrupted file should fail");
@@ -465,11 +533,14 @@ mod error_recovery_tests {
             .expect("Failed to create test config");
         
         let export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(deep_path.to_string_lossy().to_string()),
-            vec![],
-            "json".to_string(),
-            false, false, false,
+            ExportCommandOptions {
+                output: Some(deep_path.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                ..Default::default()
+            },
+            false,
         );
         
         // Should succeed because we create directories
@@ -498,11 +569,14 @@ mod error_recovery_tests {
         
         // Test dry run import
         let dry_run_result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            import_path.to_string_lossy().to_string(),
-            false, false,
-            true,  // Dry run
-            false, false, false,
+            ImportCommandOptions {
+                file: import_path.to_string_lossy().to_string(),
+                dry_run: true,
+                ..Default::default()
+            },
+            false,
         );
         
         assert!(dry_run_result.is_ok(), "Dry run should succeed");
@@ -516,4 +590,89 @@ mod error_recovery_tests {
         assert_eq!(configs.len(), 1, "Only original config should exist after dry run");
         assert!(!configs.contains(&"dry_run_test".to_string()), "New config should not be created in dry run");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_backup_diff_reports_extra_config_and_changed_value() {
+        let (_temp_dir, config_paths) = create_temp_config();
+        let config_manager = FileConfigManager::with_paths(config_paths);
+        
+        config_manager.create_config("deepseek".to_string(), create_test_env_vars(), None).unwrap();
+        config_manager.create_config("kimi".to_string(), HashMap::from([
+            ("ANTHROPIC_MODEL".to_string(), "kimi-k2".to_string()),
+        ]), None).unwrap();
+        config_manager.backup_config_with_note(Some("before cleanup")).unwrap();
+        
+        // Nothing changed since the backup
+        assert!(!handle_backup_diff_command(&OutputContext::default(), &config_manager, None, true, true, false).unwrap());
+        
+        let mut vars = create_test_env_vars();
+        vars.insert("ANTHROPIC_MODEL".to_string(), "deepseek-coder".to_string());
+        config_manager.update_config("deepseek".to_string(), vars, None).unwrap();
+        config_manager.delete_config("kimi".to_string()).unwrap();
+        
+        let backup = config_manager.find_backup("cleanup").unwrap();
+        let diff = config_manager.diff_with_backup(&backup).unwrap();
+        assert_eq!(diff.added, vec!["kimi".to_string()]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].counts(), (0, 1, 0));
+        
+        assert!(handle_backup_diff_command(&OutputContext::default(), &config_manager, Some("cleanup".to_string()), false, true, false).unwrap());
+    }
+
+    #[test]
+    fn test_partial_export_imports_in_merge_mode() {
+        let (_temp_dir, config_paths) = create_temp_config();
+        let config_manager = FileConfigManager::with_paths(config_paths.clone());
+        config_manager.create_config("shared".to_string(), create_test_env_vars(), None).unwrap();
+        
+        let export_path = config_paths.config_dir.join("delta.json");
+        handle_export_command(
+            &OutputContext::default(),
+            &config_manager,
+            ExportCommandOptions {
+                output: Some(export_path.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                since: Some("2000-01-01".to_string()),
+                ..Default::default()
+            },
+            false,
+        ).unwrap();
+        
+        // The receiving side has a configuration the delta doesn't mention
+        let (_other_dir, other_paths) = create_temp_config();
+        let receiver = FileConfigManager::with_paths(other_paths);
+        receiver.create_config("local_only".to_string(), create_test_env_vars(), None).unwrap();
+        
+        handle_import_command(
+            &OutputContext::default(),
+            &receiver,
+            ImportCommandOptions {
+                file: export_path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            false,
+        ).unwrap();
+        
+        let mut configs = receiver.list_configs().unwrap();
+        configs.sort();
+        assert_eq!(configs, vec!["local_only".to_string(), "shared".to_string()]);
+    }
+
+    #[test]
+    fn test_env_command_reports_missing_configurations() {
+        let (_temp_dir, config_paths) = create_temp_config();
+        let config_manager = FileConfigManager::with_paths(config_paths);
+        
+        // No active configuration yet
+        assert!(!handle_env_command(&OutputContext::default(), &config_manager, None, false, false).unwrap());
+        
+        config_manager.create_config("deepseek".to_string(), create_test_env_vars(), None).unwrap();
+        assert!(handle_env_command(&OutputContext::default(), &config_manager, Some("deepseek".to_string()), false, false).unwrap());
+        assert!(handle_env_command(&OutputContext::default(), &config_manager, Some("deepseek".to_string()), true, false).unwrap());
+        assert!(!handle_env_command(&OutputContext::default(), &config_manager, Some("deepsek".to_string()), false, true).unwrap());
+        
+        config_manager.set_active_config("deepseek".to_string()).unwrap();
+        assert!(handle_env_command(&OutputContext::default(), &config_manager, None, false, true).unwrap());
+    }
+}
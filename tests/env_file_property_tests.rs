@@ -0,0 +1,168 @@
+//! Property-based tests: whatever envswitch writes as a .env file it must read back unchanged
+//!
+//! Values are arbitrary strings, including quotes, `#`, backslashes, newlines and
+//! surrounding whitespace. Cases that broke before are kept as regression tests below.
+
+use std::collections::HashMap;
+use std::fs;
+
+use proptest::prelude::*;
+use tempfile::TempDir;
+
+use envswitch::config::{ConfigManager, ExportFormat, ExportOptions, FileConfigManager, ImportFormat, ImportOptions};
+use envswitch::types::ConfigPaths;
+use envswitch::utils::file_utils::{format_env_line, parse_env_content, parse_env_line, write_env_file};
+use envswitch::utils::read_env_file;
+
+fn key() -> impl Strategy<Value = String> {
+    "[A-Za-z_][A-Za-z0-9_]{0,15}"
+}
+
+fn variables() -> impl Strategy<Value = HashMap<String, String>> {
+    prop::collection::hash_map(key(), "(?s).{0,32}", 0..8)
+}
+
+fn write_and_read(variables: &HashMap<String, String>) -> HashMap<String, String> {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("vars.env");
+    write_env_file(&path, variables).unwrap();
+    read_env_file(path.to_str().unwrap()).unwrap()
+}
+
+fn manager(dir: &TempDir, name: &str) -> FileConfigManager {
+    FileConfigManager::with_paths(ConfigPaths::in_dir(&dir.path().join(name)))
+}
+
+/// Export one configuration as .env with metadata and import it into a fresh store
+fn export_and_import(variables: &HashMap<String, String>) -> HashMap<String, String> {
+    let dir = TempDir::new().unwrap();
+    let source = manager(&dir, "source");
+    source.create_config("work".to_string(), variables.clone(), None).unwrap();
+
+    let path = dir.path().join("export.env");
+    let options = ExportOptions {
+        format: ExportFormat::Env,
+        include_metadata: true,
+        pretty_print: false,
+        configs: None,
+        resolve_files: false,
+        checksum: true,
+        baseline: None,
+        include_settings: false,
+        exclude_keys: Vec::new(),
+        template: false,
+    };
+    source.export_to_file_with_options(&path, &options).unwrap();
+
+    let target = manager(&dir, "target");
+    let options = ImportOptions {
+        format: ImportFormat::Env,
+        force_overwrite: true,
+        merge_existing: false,
+        replace_store: false,
+        skip_validation: true,
+        dry_run: false,
+        keep_whitespace: true,
+        strict: true,
+        key_map: HashMap::new(),
+        exclude_keys: Vec::new(),
+        rewrite_home: false,
+        configs: None,
+        flatten: None,
+    };
+    target.import_from_file_with_options(&path, &options).unwrap();
+    target.get_config("work").unwrap().map(|config| config.variables).unwrap_or_default()
+}
+
+proptest! {
+    #[test]
+    fn prop_env_line_round_trips(key in key(), value in "(?s).{0,64}") {
+        let line = format_env_line(&key, &value);
+        prop_assert_eq!(parse_env_line(&line), Some((key, value)), "line: {:?}", line);
+    }
+
+    #[test]
+    fn prop_env_file_round_trips(variables in variables()) {
+        prop_assert_eq!(write_and_read(&variables), variables);
+    }
+
+    #[test]
+    fn prop_env_export_round_trips(variables in variables().prop_filter("a configuration needs variables", |v| !v.is_empty())) {
+        prop_assert_eq!(export_and_import(&variables), variables);
+    }
+}
+
+/// Values that did not survive a write and read before
+fn regression_values() -> Vec<&'static str> {
+    vec![
+        "\"",
+        "'",
+        "\"\"",
+        "''",
+        "value # not a comment",
+        "https://example.com/#anchor",
+        "  leading and trailing  ",
+        "say \"hi\"",
+        "it's",
+        "'quoted'",
+        "\"quoted\"",
+        "line one\nline two",
+        "carriage\rreturn",
+        "trailing backslash\\",
+        "\\n is not a newline",
+        "$HOME and `cmd`",
+        "tab\there",
+    ]
+}
+
+#[test]
+fn test_regression_values_round_trip() {
+    let variables: HashMap<String, String> = regression_values().into_iter()
+        .enumerate()
+        .map(|(i, value)| (format!("VAR{}", i), value.to_string()))
+        .collect();
+    assert_eq!(write_and_read(&variables), variables);
+    assert_eq!(export_and_import(&variables), variables);
+}
+
+#[test]
+fn test_hand_written_env_files_still_parse() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("handwritten.env");
+    fs::write(&path, concat!(
+        "# comment\n",
+        "PLAIN=value\n",
+        "SPACED = value with spaces \n",
+        "DOUBLE=\"double quoted\"\n",
+        "SINGLE='single quoted'\n",
+        "COMMENTED=\"value\" # trailing comment\n",
+        "LEGACY='it's'\n",
+        "LONE=\"\n",
+        "URL=https://example.com/#anchor\n",
+        "WINDOWS=\"C:\\Users\\me\"\n",
+        "export EXPORTED='from a shell script'\n",
+    )).unwrap();
+
+    let variables = read_env_file(path.to_str().unwrap()).unwrap();
+    assert_eq!(variables["PLAIN"], "value");
+    assert_eq!(variables["SPACED"], "value with spaces");
+    assert_eq!(variables["DOUBLE"], "double quoted");
+    assert_eq!(variables["SINGLE"], "single quoted");
+    assert_eq!(variables["COMMENTED"], "value");
+    assert_eq!(variables["LEGACY"], "it's");
+    assert_eq!(variables["LONE"], "\"");
+    assert_eq!(variables["URL"], "https://example.com/#anchor");
+    assert_eq!(variables["WINDOWS"], "C:\\Users\\me");
+    assert_eq!(variables["EXPORTED"], "from a shell script");
+}
+
+#[test]
+fn test_parse_env_content_skips_invalid_lines() {
+    let parsed = parse_env_content("# pasted\nexport A=1\nnot an assignment\n\nB='two words'\n1X=3\n=empty\n");
+    assert_eq!(parsed.variables.len(), 2);
+    assert_eq!(parsed.variables["A"], "1");
+    assert_eq!(parsed.variables["B"], "two words");
+    let lines: Vec<usize> = parsed.skipped.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![3, 6, 7]);
+    assert_eq!(parsed.skipped[0].1, "expected KEY=VALUE");
+}
@@ -9,12 +9,7 @@ use envswitch::types::ConfigPaths;
 /// Helper function to create a temporary config directory
 fn create_temp_config() -> (TempDir, ConfigPaths) {
     let temp_dir = TempDir::new().expect("Failed to create temp directory");
-    let config_dir = temp_dir.path().to_path_buf();
-    let config_paths = ConfigPaths {
-        config_dir: config_dir.clone(),
-        config_file: config_dir.join("config.json"),
-        state_file: config_dir.join("state.json"),
-    };
+    let config_paths = ConfigPaths::in_dir(temp_dir.path());
     (temp_dir, config_paths)
 }
 
@@ -38,7 +33,7 @@ fn test_config_not_found_error() {
     // Test deleting non-existent configuration
     let result = config_manager.delete_config("nonexistent".to_string());
     assert!(result.is_err());
-    if let Err(ConfigError::ConfigNotFound(name)) = result {
+    if let Err(ConfigError::ConfigNotFound { name, .. }) = result {
         assert_eq!(name, "nonexistent");
     } else {
         panic!("Expected ConfigNotFound error");
@@ -48,7 +43,7 @@ fn test_config_not_found_error() {
     let test_vars = create_test_env_vars();
     let result = config_manager.update_config("nonexistent".to_string(), test_vars, None);
     assert!(result.is_err());
-    if let Err(ConfigError::ConfigNotFound(name)) = result {
+    if let Err(ConfigError::ConfigNotFound { name, .. }) = result {
         assert_eq!(name, "nonexistent");
     } else {
         panic!("Expected ConfigNotFound error");
@@ -116,7 +111,7 @@ fn test_active_config_errors() {
     // Try to set non-existent configuration as active
     let result = config_manager.set_active_config("nonexistent".to_string());
     assert!(result.is_err());
-    if let Err(ConfigError::ConfigNotFound(name)) = result {
+    if let Err(ConfigError::ConfigNotFound { name, .. }) = result {
         assert_eq!(name, "nonexistent");
     } else {
         panic!("Expected ConfigNotFound error");
@@ -144,7 +139,7 @@ fn test_environment_variable_errors() {
 #[test]
 fn test_error_message_formatting() {
     // Test ConfigError display
-    let config_not_found = ConfigError::ConfigNotFound("test".to_string());
+    let config_not_found = ConfigError::not_found("test".to_string());
     assert_eq!(format!("{}", config_not_found), "Configuration 'test' not found");
     
     let config_exists = ConfigError::ConfigExists("test".to_string());
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use envswitch::utils::file_utils::{convert_format, format_env_line, FileFormat};
+use envswitch::utils::read_env_file;
+
+const FORMATS: [(FileFormat, &str); 4] = [
+    (FileFormat::Env, "env"),
+    (FileFormat::Json, "json"),
+    (FileFormat::Yaml, "yaml"),
+    (FileFormat::Toml, "toml"),
+];
+
+/// Variables that exercise quoting in every format
+fn sample_variables() -> HashMap<String, String> {
+    let mut variables = HashMap::new();
+    variables.insert("ANTHROPIC_BASE_URL".to_string(), "https://api.example.com/v1?region=us".to_string());
+    variables.insert("ANTHROPIC_MODEL".to_string(), "claude-3-5-sonnet".to_string());
+    variables.insert("GREETING".to_string(), "hello world".to_string());
+    variables.insert("PORT".to_string(), "8080".to_string());
+    variables.insert("DEBUG".to_string(), "true".to_string());
+    variables
+}
+
+/// Write the sample variables as a JSON file, the only format with a trivial writer here
+fn write_sample(dir: &TempDir) -> PathBuf {
+    let path = dir.path().join("source.json");
+    fs::write(&path, serde_json::to_string_pretty(&sample_variables()).unwrap()).unwrap();
+    path
+}
+
+/// Read a file back through JSON so every format is compared the same way
+fn read_back(dir: &TempDir, path: &Path, format: &FileFormat) -> HashMap<String, String> {
+    if *format == FileFormat::Env {
+        return read_env_file(path.to_str().unwrap()).unwrap();
+    }
+    let json_path = dir.path().join("read_back.json");
+    convert_format(path, &json_path, format, &FileFormat::Json).unwrap();
+    serde_json::from_str(&fs::read_to_string(json_path).unwrap()).unwrap()
+}
+
+#[test]
+fn test_round_trip_between_every_format_pair() {
+    for (source_format, source_ext) in &FORMATS {
+        for (target_format, target_ext) in &FORMATS {
+            let dir = TempDir::new().unwrap();
+            let sample = write_sample(&dir);
+
+            let source = dir.path().join(format!("source.{}", source_ext));
+            if *source_format != FileFormat::Json {
+                convert_format(&sample, &source, &FileFormat::Json, source_format).unwrap();
+            }
+
+            let target = dir.path().join(format!("target.{}", target_ext));
+            let summary = convert_format(&source, &target, source_format, target_format).unwrap();
+            assert_eq!(summary.variables, 5, "{} -> {}", source_ext, target_ext);
+
+            let back = dir.path().join(format!("back.{}", source_ext));
+            convert_format(&target, &back, target_format, source_format).unwrap();
+
+            assert_eq!(
+                read_back(&dir, &back, source_format),
+                sample_variables(),
+                "{} -> {} -> {}",
+                source_ext, target_ext, source_ext
+            );
+        }
+    }
+}
+
+#[test]
+fn test_yaml_values_stay_strings() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("vars.json");
+    fs::write(&input, r#"{"HEX": "0x10", "ALIAS": "*ref", "QUOTE": "say \"hi\"", "PATH_LIKE": "C:\\tmp"}"#).unwrap();
+
+    let yaml = dir.path().join("vars.yaml");
+    convert_format(&input, &yaml, &FileFormat::Json, &FileFormat::Yaml).unwrap();
+
+    let output = dir.path().join("out.json");
+    convert_format(&yaml, &output, &FileFormat::Yaml, &FileFormat::Json).unwrap();
+
+    let result: HashMap<String, String> = serde_json::from_str(&fs::read_to_string(output).unwrap()).unwrap();
+    assert_eq!(result["HEX"], "0x10");
+    assert_eq!(result["ALIAS"], "*ref");
+    assert_eq!(result["QUOTE"], "say \"hi\"");
+    assert_eq!(result["PATH_LIKE"], "C:\\tmp");
+}
+
+#[test]
+fn test_hand_written_yaml_scalars_become_strings() {
+    let dir = TempDir::new().unwrap();
+    let yaml = dir.path().join("vars.yaml");
+    fs::write(&yaml, "# written by hand\nPORT: 8080\nDEBUG: true\nEMPTY:\nNAME: 'x'\n").unwrap();
+
+    let output = dir.path().join("out.json");
+    convert_format(&yaml, &output, &FileFormat::Yaml, &FileFormat::Json).unwrap();
+    let result: HashMap<String, String> = serde_json::from_str(&fs::read_to_string(output).unwrap()).unwrap();
+    assert_eq!(result["PORT"], "8080");
+    assert_eq!(result["DEBUG"], "true");
+    assert_eq!(result["EMPTY"], "");
+    assert_eq!(result["NAME"], "x");
+
+    // A file with nothing but comments is an empty document
+    fs::write(&yaml, "# nothing yet\n").unwrap();
+    let summary = convert_format(&yaml, &dir.path().join("empty.env"), &FileFormat::Yaml, &FileFormat::Env).unwrap();
+    assert_eq!(summary.variables, 0);
+}
+
+#[test]
+fn test_lossy_conversion_warnings() {
+    let dir = TempDir::new().unwrap();
+
+    let env = dir.path().join("vars.env");
+    fs::write(&env, "# API settings\nAPI_KEY=abc\n").unwrap();
+    let summary = convert_format(&env, &dir.path().join("vars.toml"), &FileFormat::Env, &FileFormat::Toml).unwrap();
+    assert_eq!(summary.variables, 1);
+    assert!(summary.warnings.iter().any(|w| w.contains("comment")));
+
+    let toml = dir.path().join("nested.toml");
+    fs::write(&toml, "NAME = \"x\"\nRETRIES = 3\n\n[server]\nhost = \"localhost\"\n").unwrap();
+    let summary = convert_format(&toml, &dir.path().join("nested.env"), &FileFormat::Toml, &FileFormat::Env).unwrap();
+    assert_eq!(summary.variables, 2);
+    assert!(summary.warnings.iter().any(|w| w.contains("'server'")));
+
+    let yaml = dir.path().join("list.yaml");
+    fs::write(&yaml, "NAME: x\nHOSTS:\n  - a\n  - b\n").unwrap();
+    let summary = convert_format(&yaml, &dir.path().join("list.json"), &FileFormat::Yaml, &FileFormat::Json).unwrap();
+    assert_eq!(summary.variables, 1);
+    assert!(summary.warnings.iter().any(|w| w.contains("'HOSTS'")));
+}
+
+#[test]
+fn test_format_names_and_extensions() {
+    assert_eq!(FileFormat::from_name("YML"), Some(FileFormat::Yaml));
+    assert_eq!(FileFormat::from_name("toml"), Some(FileFormat::Toml));
+    assert_eq!(FileFormat::from_name("ini"), None);
+    assert_eq!(FileFormat::from_extension(Path::new("a/b.toml")), Some(FileFormat::Toml));
+    assert_eq!(FileFormat::from_extension(Path::new("Makefile")), None);
+}
+
+#[test]
+fn test_env_lines_quote_only_when_needed_and_round_trip() {
+    assert_eq!(format_env_line("URL", "https://api.example.com/v1"), "URL=https://api.example.com/v1");
+    assert_eq!(format_env_line("EMPTY", ""), "EMPTY=");
+    assert_eq!(format_env_line("GREETING", "hello world"), "GREETING='hello world'");
+    assert_eq!(format_env_line("MULTI", "a\nb"), "MULTI=\"a\\nb\"");
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("vars.env");
+    let values = ["plain", "hello world", "say \"hi\"", "it's", "$HOME/#hash", "  padded  "];
+    let lines: Vec<String> = values.iter().enumerate()
+        .map(|(i, value)| format_env_line(&format!("VAR{}", i), value))
+        .collect();
+    fs::write(&path, lines.join("\n")).unwrap();
+
+    let read = read_env_file(path.to_str().unwrap()).unwrap();
+    for (i, value) in values.iter().enumerate() {
+        assert_eq!(read[&format!("VAR{}", i)], *value);
+    }
+}
@@ -10,12 +10,7 @@ use envswitch::types::ConfigPaths;
 /// Helper function to create a temporary config directory
 fn create_temp_config() -> (TempDir, ConfigPaths) {
     let temp_dir = TempDir::new().expect("Failed to create temp directory");
-    let config_dir = temp_dir.path().to_path_buf();
-    let config_paths = ConfigPaths {
-        config_dir: config_dir.clone(),
-        config_file: config_dir.join("config.json"),
-        state_file: config_dir.join("state.json"),
-    };
+    let config_paths = ConfigPaths::in_dir(temp_dir.path());
     (temp_dir, config_paths)
 }
 
@@ -248,4 +243,89 @@ fn test_large_configuration_handling() {
     assert!(commands.contains("VAR_0"));
     assert!(commands.contains("VAR_50"));
     assert!(commands.contains("VAR_99"));
-}
\ No newline at end of file
+}
+#[test]
+fn test_concurrent_set_and_use_keep_every_config() {
+    let (_temp_dir, config_paths) = create_temp_config();
+    let config_manager = std::sync::Arc::new(FileConfigManager::with_paths(config_paths));
+    config_manager.create_config("base".to_string(), create_test_env_vars(), None)
+        .expect("Failed to create config");
+    
+    let threads: Vec<_> = (0..16).map(|i| {
+        let config_manager = config_manager.clone();
+        std::thread::spawn(move || {
+            for j in 0..5 {
+                let alias = format!("config-{}-{}", i, j);
+                config_manager.create_config(alias.clone(), create_test_env_vars(), None)
+                    .expect("Failed to create config");
+                config_manager.set_active_config(if j % 2 == 0 { alias } else { "base".to_string() })
+                    .expect("Failed to set active config");
+            }
+        })
+    }).collect();
+    
+    for thread in threads {
+        thread.join().expect("Worker thread panicked");
+    }
+    
+    let configs = config_manager.list_configs().unwrap();
+    assert_eq!(configs.len(), 1 + 16 * 5);
+    for i in 0..16 {
+        for j in 0..5 {
+            assert!(configs.contains(&format!("config-{}-{}", i, j)));
+        }
+    }
+    
+    let active = config_manager.get_active_config().unwrap().expect("No active config");
+    assert!(configs.contains(&active));
+}
+
+/// Run the envswitch binary with `ps` and `git` replaced by shims that log each call
+#[cfg(unix)]
+fn run_with_process_shims(temp_dir: &TempDir, args: &[&str]) -> (std::process::Output, String) {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let shim_dir = temp_dir.path().join("shims");
+    let log = temp_dir.path().join("spawned.log");
+    fs::create_dir_all(&shim_dir).unwrap();
+    for name in ["ps", "git"] {
+        let shim = shim_dir.join(name);
+        fs::write(&shim, format!("#!/bin/sh\necho {} >> '{}'\n", name, log.display())).unwrap();
+        fs::set_permissions(&shim, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_envswitch"))
+        .args(args)
+        .env_clear()
+        .env("PATH", &shim_dir)
+        .env("HOME", temp_dir.path())
+        .env("ENVSWITCH_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to run envswitch");
+    (output, fs::read_to_string(&log).unwrap_or_default())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_prompt_skips_shell_detection_and_startup_checks() {
+    let (temp_dir, config_paths) = create_temp_config();
+    let config_manager = FileConfigManager::with_paths(config_paths);
+    config_manager.create_config("deepseek".to_string(), create_test_env_vars(), None).unwrap();
+    config_manager.set_active_config("deepseek".to_string()).unwrap();
+    // Moved by the integrity check that other commands run first
+    let legacy_backup = temp_dir.path().join("config_backup_20240101_000000.json");
+    fs::write(&legacy_backup, "{}").unwrap();
+    
+    for args in [&["prompt"][..], &["list", "--active"][..]] {
+        let (output, spawned) = run_with_process_shims(&temp_dir, args);
+        assert!(output.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+        assert!(String::from_utf8_lossy(&output.stdout).contains("deepseek"));
+        assert_eq!(spawned, "", "{:?} started a subprocess", args);
+    }
+    assert!(legacy_backup.exists(), "prompt commands must not run the integrity check");
+    
+    // The shims do record shell detection when a command needs it
+    let (_, spawned) = run_with_process_shims(&temp_dir, &["use", "deepseek"]);
+    assert!(spawned.contains("ps"));
+    assert!(!legacy_backup.exists());
+}
@@ -4,8 +4,10 @@ use std::path::Path;
 use tempfile::TempDir;
 
 use envswitch::config::{ConfigManager, FileConfigManager};
-use envswitch::commands::import_export::{handle_export_command, handle_import_command};
-use envswitch::commands::config_commands::handle_delete_command;
+use envswitch::commands::import_export::{handle_export_command, handle_import_command, ExportCommandOptions, ImportCommandOptions};
+use envswitch::commands::config_commands::{handle_delete_command, handle_set_command, SetOptions, SetSummary};
+use envswitch::env::ShellEnvironmentManager;
+use envswitch::output::OutputContext;
 use envswitch::utils::file_utils::{detect_file_format, validate_file_format, FileFormat};
 use envswitch::utils::feedback::{format_file_size, ProgressIndicator};
 use envswitch::types::ConfigPaths;
@@ -13,12 +15,7 @@ use envswitch::types::ConfigPaths;
 /// Helper function to create a temporary config directory
 fn create_temp_config() -> (TempDir, ConfigPaths) {
     let temp_dir = TempDir::new().expect("Failed to create temp directory");
-    let config_dir = temp_dir.path().to_path_buf();
-    let config_paths = ConfigPaths {
-        config_dir: config_dir.clone(),
-        config_file: config_dir.join("config.json"),
-        state_file: config_dir.join("state.json"),
-    };
+    let config_paths = ConfigPaths::in_dir(temp_dir.path());
     (temp_dir, config_paths)
 }
 
@@ -31,6 +28,12 @@ fn create_test_env_vars() -> HashMap<String, String> {
     vars
 }
 
+/// Contents and modification time of the store file
+fn store_snapshot(config_manager: &FileConfigManager) -> (String, std::time::SystemTime) {
+    let path = config_manager.config_file_path();
+    (fs::read_to_string(path).unwrap(), fs::metadata(path).unwrap().modified().unwrap())
+}
+
 /// Helper function to create test JSON file
 fn create_test_json_file(path: &Path, vars: &HashMap<String, String>) {
     use serde_json::json;
@@ -77,13 +80,13 @@ mod export_command_tests {
         
         // Test export with default output file
         let result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            None, // Default output
-            vec![], // All configs
-            "json".to_string(),
-            false, // No metadata
-            false, // No pretty print
-            false, // Not verbose
+            ExportCommandOptions {
+                format: "json".to_string(),
+                ..Default::default()
+            },
+            false,
         );
         
         assert!(result.is_ok());
@@ -113,13 +116,17 @@ mod export_command_tests {
         // Test export with specific configs
         let export_path = config_paths.config_dir.join("specific_export.json");
         let result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(export_path.to_string_lossy().to_string()),
-            vec!["config1".to_string()], // Only config1
-            "json".to_string(),
-            true, // Include metadata
-            true, // Pretty print
-            false, // Not verbose
+            ExportCommandOptions {
+                output: Some(export_path.to_string_lossy().to_string()),
+                configs: vec!["config1".to_string()],
+                format: "json".to_string(),
+                metadata: true,
+                pretty: true,
+                ..Default::default()
+            },
+            false,
         );
         
         assert!(result.is_ok());
@@ -138,12 +145,13 @@ mod export_command_tests {
         
         // Test export with invalid format
         let result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some("test.txt".to_string()),
-            vec![],
-            "invalid_format".to_string(),
-            false,
-            false,
+            ExportCommandOptions {
+                output: Some("test.txt".to_string()),
+                format: "invalid_format".to_string(),
+                ..Default::default()
+            },
             false,
         );
         
@@ -158,12 +166,14 @@ mod export_command_tests {
         
         // Test export with non-existent config
         let result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some("test.json".to_string()),
-            vec!["nonexistent".to_string()],
-            "json".to_string(),
-            false,
-            false,
+            ExportCommandOptions {
+                output: Some("test.json".to_string()),
+                configs: vec!["nonexistent".to_string()],
+                format: "json".to_string(),
+                ..Default::default()
+            },
             false,
         );
         
@@ -184,12 +194,13 @@ mod export_command_tests {
         // Test JSON export
         let json_path = config_paths.config_dir.join("export.json");
         let result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(json_path.to_string_lossy().to_string()),
-            vec![],
-            "json".to_string(),
-            false,
-            false,
+            ExportCommandOptions {
+                output: Some(json_path.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                ..Default::default()
+            },
             false,
         );
         assert!(result.is_ok());
@@ -198,12 +209,13 @@ mod export_command_tests {
         // Test ENV export
         let env_path = config_paths.config_dir.join("export.env");
         let result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(env_path.to_string_lossy().to_string()),
-            vec![],
-            "env".to_string(),
-            false,
-            false,
+            ExportCommandOptions {
+                output: Some(env_path.to_string_lossy().to_string()),
+                format: "env".to_string(),
+                ..Default::default()
+            },
             false,
         );
         assert!(result.is_ok());
@@ -212,12 +224,13 @@ mod export_command_tests {
         // Test YAML export
         let yaml_path = config_paths.config_dir.join("export.yaml");
         let result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(yaml_path.to_string_lossy().to_string()),
-            vec![],
-            "yaml".to_string(),
-            false,
-            false,
+            ExportCommandOptions {
+                output: Some(yaml_path.to_string_lossy().to_string()),
+                format: "yaml".to_string(),
+                ..Default::default()
+            },
             false,
         );
         assert!(result.is_ok());
@@ -241,19 +254,47 @@ mod import_command_tests {
         
         // Test import
         let result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            import_path.to_string_lossy().to_string(),
-            false, // Not force
-            false, // Not merge
-            false, // Not dry run
-            false, // Don't skip validation
-            false, // No backup
-            false, // Not verbose
+            ImportCommandOptions {
+                file: import_path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            false,
         );
         
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_import_keeps_existing_configs_unless_replacing_the_store() {
+        let (_temp_dir, config_paths) = create_temp_config();
+        let config_manager = FileConfigManager::with_paths(config_paths.clone());
+        config_manager.create_config("existing".to_string(), create_test_env_vars(), None).unwrap();
+        
+        let import_path = config_paths.config_dir.join("import.json");
+        create_test_json_file(&import_path, &create_test_env_vars());
+        let import = |replace_store: bool| handle_import_command(
+            &OutputContext::default(),
+            &config_manager,
+            ImportCommandOptions {
+                file: import_path.to_string_lossy().to_string(),
+                replace_store,
+                yes: true,
+                ..Default::default()
+            },
+            false,
+        );
+        
+        import(false).unwrap();
+        let mut configs = config_manager.list_configs().unwrap();
+        configs.sort();
+        assert_eq!(configs, vec!["existing".to_string(), "test_config".to_string()]);
+        
+        import(true).unwrap();
+        assert_eq!(config_manager.list_configs().unwrap(), vec!["test_config".to_string()]);
+    }
+
     #[test]
     fn test_import_command_env_file() {
         let (_temp_dir, config_paths) = create_temp_config();
@@ -266,13 +307,12 @@ mod import_command_tests {
         
         // Test import
         let result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            import_path.to_string_lossy().to_string(),
-            false,
-            false,
-            false,
-            false,
-            false,
+            ImportCommandOptions {
+                file: import_path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
             false,
         );
         
@@ -286,13 +326,12 @@ mod import_command_tests {
         
         // Test import with non-existent file
         let result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            "nonexistent.json".to_string(),
-            false,
-            false,
-            false,
-            false,
-            false,
+            ImportCommandOptions {
+                file: "nonexistent.json".to_string(),
+                ..Default::default()
+            },
             false,
         );
         
@@ -312,13 +351,13 @@ mod import_command_tests {
         
         // Test dry run import
         let result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            import_path.to_string_lossy().to_string(),
-            false,
-            false,
-            true, // Dry run
-            false,
-            false,
+            ImportCommandOptions {
+                file: import_path.to_string_lossy().to_string(),
+                dry_run: true,
+                ..Default::default()
+            },
             false,
         );
         
@@ -348,13 +387,13 @@ mod import_command_tests {
         
         // Test import with backup
         let result = handle_import_command(
+            &OutputContext::default(),
             &config_manager,
-            import_path.to_string_lossy().to_string(),
-            false,
-            false,
-            false,
-            false,
-            true, // Create backup
+            ImportCommandOptions {
+                file: import_path.to_string_lossy().to_string(),
+                backup: true,
+                ..Default::default()
+            },
             false,
         );
         
@@ -378,9 +417,11 @@ mod delete_command_tests {
         
         // Test delete with force flag
         let result = handle_delete_command(
+            &OutputContext::default(),
             &config_manager,
             "test_config".to_string(),
             true, // Force
+            false, // Not a dry run
             false, // Not verbose
         );
         
@@ -398,10 +439,12 @@ mod delete_command_tests {
         
         // Test delete non-existent configuration
         let result = handle_delete_command(
+            &OutputContext::default(),
             &config_manager,
             "nonexistent".to_string(),
             true,
             false,
+            false,
         );
         
         assert!(result.is_err());
@@ -423,10 +466,12 @@ mod delete_command_tests {
         
         // Test delete active configuration
         let result = handle_delete_command(
+            &OutputContext::default(),
             &config_manager,
             "active_config".to_string(),
             true,
             false,
+            false,
         );
         
         assert!(result.is_ok());
@@ -435,6 +480,118 @@ mod delete_command_tests {
         let active = config_manager.get_active_config().unwrap();
         assert!(active.is_none());
     }
+
+    #[test]
+    fn test_delete_dry_run_changes_nothing() {
+        let (_temp_dir, config_paths) = create_temp_config();
+        let config_manager = FileConfigManager::with_paths(config_paths);
+        config_manager.create_config("active_config".to_string(), create_test_env_vars(), None).unwrap();
+        config_manager.set_active_config("active_config".to_string()).unwrap();
+        let before = store_snapshot(&config_manager);
+        
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        handle_delete_command(&OutputContext::default(), &config_manager, "active_config".to_string(), false, true, false).unwrap();
+        
+        assert_eq!(store_snapshot(&config_manager), before);
+        assert_eq!(config_manager.get_active_config().unwrap(), Some("active_config".to_string()));
+        assert!(config_manager.list_backups().unwrap().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod set_command_tests {
+    use super::*;
+
+    #[test]
+    fn test_set_dry_run_changes_nothing() {
+        let (_temp_dir, config_paths) = create_temp_config();
+        let config_manager = FileConfigManager::with_paths(config_paths);
+        let env_manager = ShellEnvironmentManager::new();
+        config_manager.create_config("existing".to_string(), create_test_env_vars(), None).unwrap();
+        let before = store_snapshot(&config_manager);
+        
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        for (alias, replace) in [("existing", false), ("existing", true), ("brand_new", false)] {
+            let options = SetOptions {
+                alias: alias.to_string(),
+                env_vars: vec![("ANTHROPIC_MODEL".to_string(), Some("deepseek-coder".to_string()))],
+                description: Some("changed".to_string()),
+                replace,
+                note: Some("note".to_string()),
+                expires_in: Some("7d".to_string()),
+                from_cmd: vec![("API_KEY".to_string(), "touch should-not-run".to_string())],
+                dry_run: true,
+                output: "text".to_string(),
+                ..Default::default()
+            };
+            handle_set_command(&OutputContext::default(), &config_manager, &env_manager, options, false).unwrap();
+        }
+        
+        assert_eq!(store_snapshot(&config_manager), before);
+        assert_eq!(config_manager.list_configs().unwrap(), vec!["existing".to_string()]);
+        assert!(!Path::new("should-not-run").exists());
+    }
+
+    #[test]
+    fn test_set_trims_values_unless_keep_whitespace() {
+        let (_temp_dir, config_paths) = create_temp_config();
+        let config_manager = FileConfigManager::with_paths(config_paths);
+        let env_manager = ShellEnvironmentManager::new();
+        let set = |alias: &str, keep_whitespace: bool| {
+            let options = SetOptions {
+                alias: alias.to_string(),
+                env_vars: vec![("API_KEY".to_string(), Some("sk-1234567890abcdef\r\n".to_string()))],
+                output: "text".to_string(),
+                keep_whitespace,
+                ..Default::default()
+            };
+            handle_set_command(&OutputContext::default(), &config_manager, &env_manager, options, false).unwrap();
+            config_manager.get_config(alias).unwrap().unwrap().variables["API_KEY"].clone()
+        };
+        
+        assert_eq!(set("trimmed", false), "sk-1234567890abcdef");
+        assert_eq!(set("verbatim", true), "sk-1234567890abcdef\r\n");
+        
+        let report = envswitch::audit::audit_store(&OutputContext::default(), &config_manager.load_configs().unwrap(), true, None);
+        assert_eq!(report.untrimmed.len(), 1);
+        assert_eq!(config_manager.clean_values().unwrap(), vec![("verbatim".to_string(), "API_KEY".to_string())]);
+        assert!(config_manager.clean_values().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_reports_whether_anything_changed() {
+        let (_temp_dir, config_paths) = create_temp_config();
+        let config_manager = FileConfigManager::with_paths(config_paths);
+        let env_manager = ShellEnvironmentManager::new();
+        let set = |pairs: &[(&str, &str)], replace: bool| {
+            let options = SetOptions {
+                alias: "provisioned".to_string(),
+                env_vars: pairs.iter().map(|(k, v)| (k.to_string(), Some(v.to_string()))).collect(),
+                replace,
+                output: "json".to_string(),
+                ..Default::default()
+            };
+            handle_set_command(&OutputContext::default(), &config_manager, &env_manager, options, false).unwrap()
+        };
+        
+        assert!(set(&[("A", "1"), ("B", "2")], false));
+        assert!(!set(&[("A", "1")], false), "same value again is a no-op");
+        assert!(set(&[("A", "10")], false));
+        assert!(set(&[("A", "10")], true), "replace removes B");
+        
+        let existing = config_manager.get_config("provisioned").unwrap().unwrap();
+        let mut variables = existing.variables.clone();
+        variables.insert("C".to_string(), "3".to_string());
+        variables.remove("A");
+        let summary = SetSummary::new("provisioned", Some(&existing), &variables);
+        assert_eq!(summary.added, vec!["C".to_string()]);
+        assert_eq!(summary.removed, vec!["A".to_string()]);
+        assert!(!summary.created && summary.has_changes());
+        
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["alias"], "provisioned");
+        assert_eq!(json["total_variables"], 1);
+    }
 }
 
 #[cfg(test)]
@@ -478,6 +635,24 @@ mod format_detection_tests {
         assert_eq!(detected_format, FileFormat::Yaml);
     }
 
+    #[test]
+    fn test_text_with_colons_is_not_yaml() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_path = temp_dir.path().join("notes.txt");
+        
+        // Prose with colons, but not a YAML mapping
+        fs::write(&notes_path, "Meeting notes: Tuesday\nAttendees: Ann, Bob\nwe agreed to ship it\n- and: then: some\n").unwrap();
+        assert!(detect_file_format(&notes_path).is_err());
+        
+        // A .yaml extension doesn't make it valid either
+        let yaml_path = temp_dir.path().join("notes.yaml");
+        fs::write(&yaml_path, "Meeting notes: Tuesday\nwe agreed to ship it\n").unwrap();
+        let validation = validate_file_format(&yaml_path, &FileFormat::Yaml).unwrap();
+        assert!(!validation.is_valid);
+        assert!(validation.errors[0].contains("Invalid YAML"), "{:?}", validation.errors);
+        assert!(detect_file_format(&yaml_path).is_err());
+    }
+
     #[test]
     fn test_validate_json_format() {
         let temp_dir = TempDir::new().unwrap();
@@ -579,19 +754,19 @@ mod feedback_utilities_tests {
         assert!(progress.is_running);
         
         // Test finish success
-        progress.finish_success("Completed successfully");
+        progress.finish_success(&OutputContext::default(), "Completed successfully");
         assert!(!progress.is_running);
         
         // Test restart and finish with error
         let mut progress2 = ProgressIndicator::new("Testing error");
         progress2.start();
-        progress2.finish_error("Failed with error");
+        progress2.finish_error(&OutputContext::default(), "Failed with error");
         assert!(!progress2.is_running);
         
         // Test restart and finish with warning
         let mut progress3 = ProgressIndicator::new("Testing warning");
         progress3.start();
-        progress3.finish_warning("Completed with warnings");
+        progress3.finish_warning(&OutputContext::default(), "Completed with warnings");
         assert!(!progress3.is_running);
     }
 }
@@ -602,7 +777,7 @@ mod error_handling_tests {
 
     #[test]
     fn test_config_error_user_messages() {
-        let error = ConfigError::ConfigNotFound("test".to_string());
+        let error = ConfigError::not_found("test".to_string());
         let message = error.user_message();
         assert!(message.contains("test"));
         assert!(message.contains("envswitch list"));
@@ -665,12 +840,15 @@ mod integration_workflow_tests {
         // Export configurations
         let export_path = config_paths1.config_dir.join("full_export.json");
         let export_result = handle_export_command(
+            &OutputContext::default(),
             &source_manager,
-            Some(export_path.to_string_lossy().to_string()),
-            vec![], // Export all
-            "json".to_string(),
-            true, // Include metadata
-            true, // Pretty print
+            ExportCommandOptions {
+                output: Some(export_path.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                metadata: true,
+                pretty: true,
+                ..Default::default()
+            },
             false,
         );
         assert!(export_result.is_ok());
@@ -682,13 +860,12 @@ mod integration_workflow_tests {
         
         // Import configurations
         let import_result = handle_import_command(
+            &OutputContext::default(),
             &dest_manager,
-            export_path.to_string_lossy().to_string(),
-            false,
-            false,
-            false,
-            false,
-            false,
+            ImportCommandOptions {
+                file: export_path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
             false,
         );
         assert!(import_result.is_ok());
@@ -717,12 +894,13 @@ mod integration_workflow_tests {
         // Export as JSON
         let json_export = config_paths.config_dir.join("export.json");
         let export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(json_export.to_string_lossy().to_string()),
-            vec![],
-            "json".to_string(),
-            false,
-            false,
+            ExportCommandOptions {
+                output: Some(json_export.to_string_lossy().to_string()),
+                format: "json".to_string(),
+                ..Default::default()
+            },
             false,
         );
         assert!(export_result.is_ok());
@@ -730,12 +908,13 @@ mod integration_workflow_tests {
         // Export as ENV
         let env_export = config_paths.config_dir.join("export.env");
         let export_result = handle_export_command(
+            &OutputContext::default(),
             &config_manager,
-            Some(env_export.to_string_lossy().to_string()),
-            vec![],
-            "env".to_string(),
-            false,
-            false,
+            ExportCommandOptions {
+                output: Some(env_export.to_string_lossy().to_string()),
+                format: "env".to_string(),
+                ..Default::default()
+            },
             false,
         );
         assert!(export_result.is_ok());
@@ -0,0 +1,48 @@
+//! Coarse timing checks that catch accidental quadratic behaviour, not small slowdowns
+//!
+//! The ceilings are far above the real timings (see `cargo bench`) so slow CI machines
+//! and debug builds pass; an O(n²) scan over 1,000 configurations does not.
+
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+use envswitch::bench_support::{manager_with_store, synthetic_store};
+use envswitch::config::ConfigManager;
+
+const CEILING: Duration = Duration::from_secs(2);
+
+#[test]
+fn test_loading_1000_configs_stays_fast() {
+    let dir = TempDir::new().unwrap();
+    let manager = manager_with_store(dir.path(), &synthetic_store(1_000, 10));
+
+    let started = Instant::now();
+    let store = manager.load_configs().unwrap();
+    let elapsed = started.elapsed();
+
+    assert_eq!(store.configs.len(), 1_000);
+    assert!(elapsed < CEILING, "loading 1,000 configurations took {:?}", elapsed);
+}
+
+#[test]
+fn test_saving_1000_configs_stays_fast() {
+    let dir = TempDir::new().unwrap();
+    let store = synthetic_store(1_000, 10);
+    let manager = manager_with_store(dir.path(), &store);
+
+    let started = Instant::now();
+    manager.save_configs(&store).unwrap();
+    let elapsed = started.elapsed();
+
+    assert!(elapsed < CEILING, "saving 1,000 configurations took {:?}", elapsed);
+}
+
+#[test]
+fn test_synthetic_stores_are_deterministic() {
+    let first = serde_json::to_string(&synthetic_store(20, 5)).unwrap();
+    let second = serde_json::to_string(&synthetic_store(20, 5)).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&first).unwrap(),
+        serde_json::from_str::<serde_json::Value>(&second).unwrap()
+    );
+}
@@ -81,7 +81,7 @@ fn test_fish_command_generation() {
     // Verify fish set format (check for key components)
     assert!(commands.contains("TEST_VAR_1"));
     assert!(commands.contains("simple_value"));
-    assert!(commands.contains("set -x"));
+    assert!(commands.contains("set -gx"));
     
     // Test unset commands
     let keys: Vec<String> = test_vars.keys().cloned().collect();
@@ -146,13 +146,13 @@ fn test_integration_instructions() {
     
     // Verify shell-specific instructions
     assert!(zsh_instructions.contains("~/.zshrc"));
-    assert!(zsh_instructions.contains("eval \"$(envswitch use"));
+    assert!(zsh_instructions.contains("eval \"$(ENVSWITCH_EVAL=1 envswitch use"));
     
     assert!(fish_instructions.contains("~/.config/fish/config.fish"));
-    assert!(fish_instructions.contains("eval (envswitch use"));
+    assert!(fish_instructions.contains("eval (ENVSWITCH_EVAL=1 envswitch use"));
     
     assert!(bash_instructions.contains("~/.bashrc"));
-    assert!(bash_instructions.contains("eval \"$(envswitch use"));
+    assert!(bash_instructions.contains("eval \"$(ENVSWITCH_EVAL=1 envswitch use"));
 }
 
 #[test]
@@ -160,7 +160,7 @@ fn test_claude_variables_detection() {
     let manager = ShellEnvironmentManager::new();
     
     // Test getting Claude variables status
-    let status = manager.get_claude_variables_status();
+    let status = manager.get_claude_variables_status(&[]);
     
     // Should return a vector of EnvVarStatus
     assert!(status.is_empty() || !status.is_empty()); // Just verify it returns something
@@ -0,0 +1,148 @@
+//! Golden-file tests for the shell commands generated by `use` and `unset`
+//!
+//! The expected scripts live in tests/golden/shell. After an intended change of the
+//! generated commands, regenerate them with:
+//!   ENVSWITCH_UPDATE_GOLDEN=1 cargo test --test shell_golden_tests
+//!
+//! With ENVSWITCH_SHELL_TESTS=1 the scripts are also run by every shell installed,
+//! checking that the shell ends up with exactly the input values.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use envswitch::shell::{ShellDetector, ShellType};
+
+/// Values that are easy to break when quoting
+fn tricky_variables() -> HashMap<String, String> {
+    [
+        ("SIMPLE", "value"),
+        ("SPACES", "value with  spaces"),
+        ("SINGLE_QUOTE", "it's"),
+        ("DOUBLE_QUOTE", "say \"hi\""),
+        ("MIXED_QUOTES", "'\"'\"'"),
+        ("NEWLINE", "line one\nline two\n"),
+        ("UNICODE", "héllo 世界 🚀"),
+        ("DOLLAR", "$HOME ${PATH} $(whoami)"),
+        ("BACKTICK", "`whoami`"),
+        ("BACKSLASH", "C:\\path\\to\\dir \\n"),
+        ("TRAILING_BACKSLASH", "dir\\"),
+        ("DOUBLE_BACKSLASH", "a\\\\b"),
+        ("GLOB", "* ? [a-z]"),
+        ("SEMICOLON", "a; echo pwned"),
+        ("EMPTY", ""),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect()
+}
+
+/// Every shell type with its golden file name
+///
+/// The match makes adding a ShellType variant fail to compile here until it gets
+/// golden files; add the new shell to [`all_shells`] and [`interpreter`] too.
+fn golden_name(shell: &ShellType) -> &'static str {
+    match shell {
+        ShellType::Zsh => "zsh",
+        ShellType::Fish => "fish",
+        ShellType::Bash => "bash",
+        ShellType::Unknown(_) => "unknown",
+    }
+}
+
+fn all_shells() -> Vec<ShellType> {
+    vec![ShellType::Zsh, ShellType::Fish, ShellType::Bash, ShellType::Unknown("sh".to_string())]
+}
+
+/// Program that runs the scripts of a shell type in [`test_generated_scripts_run_in_real_shells`]
+fn interpreter(shell: &ShellType) -> &'static str {
+    match shell {
+        ShellType::Zsh => "zsh",
+        ShellType::Fish => "fish",
+        ShellType::Bash => "bash",
+        ShellType::Unknown(_) => "sh",
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/shell").join(name)
+}
+
+/// Compare with the golden file, or rewrite it when ENVSWITCH_UPDATE_GOLDEN is set
+fn assert_golden(name: &str, actual: &str) {
+    let path = golden_path(name);
+    if std::env::var_os("ENVSWITCH_UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| panic!(
+        "Missing golden file {}; create it with ENVSWITCH_UPDATE_GOLDEN=1", path.display()
+    ));
+    assert_eq!(actual, expected, "{} differs from the generated commands", name);
+}
+
+fn sorted_names(variables: &HashMap<String, String>) -> Vec<String> {
+    let mut names: Vec<String> = variables.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_set_commands_match_golden_files() {
+    let variables = tricky_variables();
+    for shell in all_shells() {
+        let commands = ShellDetector::generate_env_commands(&shell, &variables).unwrap();
+        assert_golden(&format!("{}_set.txt", golden_name(&shell)), &format!("{}\n", commands));
+    }
+}
+
+#[test]
+fn test_unset_commands_match_golden_files() {
+    let names = sorted_names(&tricky_variables());
+    for shell in all_shells() {
+        let commands = ShellDetector::generate_unset_commands(&shell, &names).unwrap();
+        assert_golden(&format!("{}_unset.txt", golden_name(&shell)), &format!("{}\n", commands));
+    }
+}
+
+#[test]
+fn test_generated_scripts_run_in_real_shells() {
+    if std::env::var_os("ENVSWITCH_SHELL_TESTS").is_none() || !cfg!(unix) {
+        return;
+    }
+
+    let variables = tricky_variables();
+    let names = sorted_names(&variables);
+    for shell in all_shells() {
+        let program = interpreter(&shell);
+        if Command::new(program).arg("-c").arg("true").output().is_err() {
+            eprintln!("skipping {}: not installed", program);
+            continue;
+        }
+
+        // Print every value NUL-terminated, then check that unset removes them again
+        let set = ShellDetector::generate_env_commands(&shell, &variables).unwrap();
+        let unset = ShellDetector::generate_unset_commands(&shell, &names).unwrap();
+        let quoted: Vec<String> = names.iter().map(|name| format!("\"${}\"", name)).collect();
+        let script = format!(
+            "{}\nprintf '%s\\0' {}\n{}\nenv | grep -c '^SIMPLE=' || true\n",
+            set, quoted.join(" "), unset
+        );
+
+        let output = Command::new(program).arg("-c").arg(&script)
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{} failed: {}", program, String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let (values, after_unset) = stdout.rsplit_once('\0').unwrap();
+
+        let actual: HashMap<String, String> = names.iter().cloned()
+            .zip(values.split('\0').map(str::to_string))
+            .collect();
+        assert_eq!(actual, variables, "{} ended up with different values", program);
+        assert_eq!(after_unset.trim(), "0", "{} still has SIMPLE after unset", program);
+    }
+}